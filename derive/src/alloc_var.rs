@@ -0,0 +1,93 @@
+//! `#[derive(AllocVar)]`.
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Error, Fields, Index};
+
+pub fn expand(input: DeriveInput) -> Result<TokenStream, Error> {
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "AllocVar cannot be derived for generic structs",
+        ));
+    }
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "AllocVar can only be derived for structs",
+            ))
+        }
+    };
+    if fields.is_empty() {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "AllocVar cannot be derived for structs without fields",
+        ));
+    }
+
+    let name = &input.ident;
+    let var_name = format_ident!("{}Var", name);
+    let vis = &input.vis;
+    let alloc_var = quote!(::ark_bulletproofs::r1cs::gadgets::AllocVar);
+    let doc = format!("The allocated variables of a [`{}`].", name);
+
+    let types: Vec<_> = fields.iter().map(|field| &field.ty).collect();
+    let var_fields = fields.iter().map(|field| {
+        let docs = field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("doc"));
+        let (vis, ident, ty) = (&field.vis, &field.ident, &field.ty);
+        let colon = ident.as_ref().map(|_| quote!(:));
+        quote! {
+            #(#docs)*
+            #vis #ident #colon <#ty as #alloc_var<F>>::Var
+        }
+    });
+    // Allocate the fields in declaration order.
+    let allocations = fields.iter().enumerate().map(|(i, field)| {
+        let ty = &field.ty;
+        let member = match &field.ident {
+            Some(ident) => quote!(#ident),
+            None => {
+                let index = Index::from(i);
+                quote!(#index)
+            }
+        };
+        quote! {
+            #member: <#ty as #alloc_var<F>>::allocate(cs, value.map(|value| &value.#member))?
+        }
+    });
+    let bounds = quote!(where #(#types: #alloc_var<F>,)*);
+    let var_struct = match fields {
+        Fields::Named(_) => quote! {
+            #vis struct #var_name<F: ::ark_ff::PrimeField> #bounds {
+                #(#var_fields,)*
+            }
+        },
+        _ => quote! {
+            #vis struct #var_name<F: ::ark_ff::PrimeField>(#(#var_fields,)*) #bounds;
+        },
+    };
+
+    Ok(quote! {
+        #[doc = #doc]
+        #[derive(Clone, Debug)]
+        #var_struct
+
+        impl<F: ::ark_ff::PrimeField> #alloc_var<F> for #name #bounds {
+            type Var = #var_name<F>;
+
+            fn allocate<CS: ::ark_bulletproofs::r1cs::ConstraintSystem<F>>(
+                cs: &mut CS,
+                value: ::core::option::Option<&Self>,
+            ) -> ::core::result::Result<Self::Var, ::ark_bulletproofs::r1cs::R1CSError> {
+                ::core::result::Result::Ok(#var_name {
+                    #(#allocations,)*
+                })
+            }
+        }
+    })
+}
@@ -0,0 +1,89 @@
+//! `#[derive(Gadget)]`.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, Data, DeriveInput, Error, GenericParam, Index};
+
+pub fn expand(input: DeriveInput) -> Result<TokenStream, Error> {
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "Gadget can only be derived for structs",
+            ))
+        }
+    };
+    if fields.is_empty() {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "Gadget cannot be derived for structs without fields",
+        ));
+    }
+
+    let name = &input.ident;
+    let gadget = quote!(::ark_bulletproofs::r1cs::gadgets::Gadget);
+
+    // The field of the gadget is the type parameter `F` of the struct if
+    // it has one, and a new parameter otherwise.
+    let mut generics = input.generics.clone();
+    let has_field = generics.params.iter().any(|param| match param {
+        GenericParam::Type(param) => param.ident == "F",
+        _ => false,
+    });
+    if !has_field {
+        generics.params.push(parse_quote!(F));
+    }
+    let where_clause = generics.make_where_clause();
+    where_clause
+        .predicates
+        .push(parse_quote!(F: ::ark_ff::PrimeField));
+
+    // Every member gadget takes the outputs of the previous one.
+    let types: Vec<_> = fields.iter().map(|field| &field.ty).collect();
+    let (first, last) = (types[0], types[types.len() - 1]);
+    where_clause
+        .predicates
+        .push(parse_quote!(#first: #gadget<F>));
+    for pair in types.windows(2) {
+        let (previous, ty) = (pair[0], pair[1]);
+        where_clause.predicates.push(parse_quote!(
+            #ty: #gadget<F, Input = <#previous as #gadget<F>>::Output>
+        ));
+    }
+
+    let steps = fields.iter().enumerate().map(|(i, field)| {
+        let (member, namespace) = match &field.ident {
+            Some(ident) => (quote!(#ident), ident.to_string()),
+            None => {
+                let index = Index::from(i);
+                (quote!(#index), i.to_string())
+            }
+        };
+        quote! {
+            cs.push_namespace(#namespace);
+            let outputs = #gadget::<F>::synthesize(&self.#member, cs, outputs);
+            cs.pop_namespace();
+            let outputs = outputs?;
+        }
+    });
+
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #gadget<F> for #name #ty_generics #where_clause {
+            type Input = <#first as #gadget<F>>::Input;
+            type Output = <#last as #gadget<F>>::Output;
+
+            fn synthesize<CS: ::ark_bulletproofs::r1cs::ConstraintSystem<F>>(
+                &self,
+                cs: &mut CS,
+                inputs: Self::Input,
+            ) -> ::core::result::Result<Self::Output, ::ark_bulletproofs::r1cs::R1CSError> {
+                let outputs = inputs;
+                #(#steps)*
+                ::core::result::Result::Ok(outputs)
+            }
+        }
+    })
+}
@@ -0,0 +1,39 @@
+//! Derive macros for the constraint systems of `ark-bulletproofs`,
+//! re-exported by its `derive` feature.
+
+#![deny(missing_docs)]
+
+extern crate proc_macro;
+
+mod alloc_var;
+mod gadget;
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+/// Derives `AllocVar` for a struct whose fields all implement it, and
+/// defines the struct `<Name>Var<F>` of its allocated variables.
+///
+/// See the `AllocVar` trait of `ark_bulletproofs::r1cs::gadgets`.
+#[proc_macro_derive(AllocVar)]
+pub fn derive_alloc_var(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    alloc_var::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derives `Gadget` for a struct of gadgets, which synthesizes its
+/// fields in declaration order, each on the outputs of the previous one
+/// and in a namespace named after the field.
+///
+/// The field of the gadget is the type parameter `F` of the struct, if
+/// it has one, and any field otherwise.  See the `Gadget` trait of
+/// `ark_bulletproofs::r1cs::gadgets`.
+#[proc_macro_derive(Gadget)]
+pub fn derive_gadget(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    gadget::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
@@ -0,0 +1,93 @@
+/*
+ * C API of ark-bulletproofs, built with the `ffi` feature.
+ *
+ * Scalars are 32-byte little-endian encodings and commitments are
+ * 33-byte compressed secq256k1 points.  See the documentation of the
+ * `ffi` module for the ownership of handles and buffers.
+ */
+
+#ifndef BULLETPROOFS_H
+#define BULLETPROOFS_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef enum {
+    BP_OK = 0,
+    BP_NULL_POINTER = 1,
+    BP_INVALID_SCALAR = 2,
+    BP_INVALID_POINT = 3,
+    BP_INVALID_VARIABLE = 4,
+    BP_INVALID_GENERATORS_LENGTH = 5,
+    BP_FORMAT_ERROR = 6,
+    BP_VERIFICATION_ERROR = 7,
+    BP_UNSATISFIED_CONSTRAINT = 8,
+    BP_ERROR = 9,
+} BpStatus;
+
+/* The largest capacity of the generators of bp_prover_prove and
+ * bp_verifier_verify. */
+#define BP_MAX_GENS_CAPACITY ((size_t)1 << 20)
+
+/* Kinds of variables. */
+#define BP_VARIABLE_COMMITTED 0
+#define BP_VARIABLE_MULTIPLIER_LEFT 1
+#define BP_VARIABLE_MULTIPLIER_RIGHT 2
+#define BP_VARIABLE_MULTIPLIER_OUTPUT 3
+#define BP_VARIABLE_ONE 4
+
+typedef struct {
+    uint32_t kind;
+    uint64_t index;
+} BpVariable;
+
+typedef struct {
+    BpVariable variable;
+    uint8_t coefficient[32];
+} BpTerm;
+
+typedef struct {
+    uint8_t *data;
+    size_t len;
+} BpBuffer;
+
+typedef struct BpProver BpProver;
+typedef struct BpVerifier BpVerifier;
+
+BpProver *bp_prover_new(const uint8_t *label, size_t label_len);
+void bp_prover_free(BpProver *prover);
+BpStatus bp_prover_commit(BpProver *prover, const uint8_t value[32],
+                          const uint8_t blinding[32], uint8_t commitment[33],
+                          BpVariable *variable);
+BpStatus bp_prover_allocate_multiplier(BpProver *prover, const uint8_t left[32],
+                                       const uint8_t right[32],
+                                       BpVariable variables[3]);
+BpStatus bp_prover_multiply(BpProver *prover, const BpTerm *left, size_t left_len,
+                            const BpTerm *right, size_t right_len,
+                            BpVariable variables[3]);
+BpStatus bp_prover_constrain(BpProver *prover, const BpTerm *terms, size_t len);
+BpStatus bp_prover_prove(BpProver *prover, size_t gens_capacity, BpBuffer *proof);
+
+BpVerifier *bp_verifier_new(const uint8_t *label, size_t label_len);
+void bp_verifier_free(BpVerifier *verifier);
+BpStatus bp_verifier_commit(BpVerifier *verifier, const uint8_t commitment[33],
+                            BpVariable *variable);
+BpStatus bp_verifier_allocate_multiplier(BpVerifier *verifier, BpVariable variables[3]);
+BpStatus bp_verifier_multiply(BpVerifier *verifier, const BpTerm *left, size_t left_len,
+                              const BpTerm *right, size_t right_len,
+                              BpVariable variables[3]);
+BpStatus bp_verifier_constrain(BpVerifier *verifier, const BpTerm *terms, size_t len);
+BpStatus bp_verifier_verify(BpVerifier *verifier, size_t gens_capacity,
+                            const uint8_t *proof, size_t proof_len);
+
+void bp_buffer_free(BpBuffer buffer);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* BULLETPROOFS_H */
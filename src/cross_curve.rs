@@ -0,0 +1,426 @@
+#![allow(non_snake_case)]
+//! Definition of proofs that Pedersen commitments in two different groups
+//! commit to the same value.
+
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::{
+    io::Cursor,
+    rand::{CryptoRng, RngCore},
+    vec::Vec,
+    UniformRand, Zero,
+};
+use merlin::Transcript;
+#[cfg(feature = "ct")]
+use subtle::Choice;
+use zeroize::Zeroizing;
+
+use crate::errors::ProofError;
+use crate::generators::PedersenGens;
+use crate::msm::{secret_msm, AdaptiveMsm, MsmBackend};
+use crate::transcript::TranscriptProtocol;
+
+/// A proof that a Pedersen commitment \\(V_1\\) in the group `G1` and a
+/// Pedersen commitment \\(V_2\\) in the group `G2` commit to the same
+/// `n`-bit value, such as commitments used by the secq256k1 and the zorro
+/// constraint systems of this crate about the same statement.
+///
+/// The value is decomposed into bits, and each bit is committed in both
+/// groups, with blinding factors adding up to those of \\(V_1\\) and
+/// \\(V_2\\).  For every bit, a ring signature proves that both bit
+/// commitments open to 0, or that both open to 1.  The challenges are
+/// 128-bit integers, so that they are interpreted identically in the
+/// scalar fields of both groups.
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct CrossCurveEqualityProof<G1: AffineRepr, G2: AffineRepr> {
+    /// The proofs of the bits of the value, from the least significant.
+    bits: Vec<BitProof<G1, G2>>,
+    /// The challenge of the ring signatures.
+    e: [u8; 16],
+}
+
+/// The commitments to a bit in both groups, and the ring signature
+/// proving that they open to the same bit.
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+struct BitProof<G1: AffineRepr, G2: AffineRepr> {
+    /// Commitment to the bit in `G1`.
+    C1: G1,
+    /// Commitment to the bit in `G2`.
+    C2: G2,
+    /// The challenge of the branch for the bit 0.
+    e0: [u8; 16],
+    /// Responses in `G1` for the bits 0 and 1.
+    s1: [G1::ScalarField; 2],
+    /// Responses in `G2` for the bits 0 and 1.
+    s2: [G2::ScalarField; 2],
+}
+
+impl<G1: AffineRepr, G2: AffineRepr> CrossCurveEqualityProof<G1, G2> {
+    /// Creates a proof that `pc_gens1.commit(value, blinding1)` and
+    /// `pc_gens2.commit(value, blinding2)` commit to the same `n`-bit
+    /// `value`.
+    ///
+    /// The commitments are appended to the `transcript`, so the verifier
+    /// does not need to append them separately.
+    ///
+    /// Returns an error if `n` is not between 1 and 64, or if `value` does
+    /// not fit in `n` bits.
+    ///
+    /// With the `ct` feature, the nonces are multiplied in constant time,
+    /// and the real and simulated branches of the ring signatures are
+    /// ordered by the bits of `value` with masks instead of indices.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create<R: CryptoRng + RngCore>(
+        transcript: &mut Transcript,
+        pc_gens1: &PedersenGens<G1>,
+        pc_gens2: &PedersenGens<G2>,
+        value: u64,
+        blinding1: G1::ScalarField,
+        blinding2: G2::ScalarField,
+        n: usize,
+        rng: &mut R,
+    ) -> Result<CrossCurveEqualityProof<G1, G2>, ProofError> {
+        if n == 0 || n > 64 || (n < 64 && value >> n != 0) {
+            return Err(ProofError::InvalidBitsize);
+        }
+
+        let V1 = pc_gens1.commit(G1::ScalarField::from(value), blinding1);
+        let V2 = pc_gens2.commit(G2::ScalarField::from(value), blinding2);
+        append_statement(transcript, &V1, &V2, n);
+
+        let mut rng = {
+            let mut bytes = Zeroizing::new(Vec::with_capacity(
+                blinding1.uncompressed_size() + blinding2.uncompressed_size(),
+            ));
+            blinding1.serialize_uncompressed(&mut *bytes).unwrap();
+            blinding2.serialize_uncompressed(&mut *bytes).unwrap();
+            transcript
+                .build_rng()
+                .rekey_with_witness_bytes(b"v_blinding", &bytes)
+                .finalize(rng)
+        };
+
+        let r1 = bit_blindings(blinding1, n, &mut rng);
+        let r2 = bit_blindings(blinding2, n, &mut rng);
+
+        // The nonces of the real branches, and the challenges and
+        // responses of the simulated ones.  The branches are ordered by
+        // the secret bits without branching on them.
+        let msm1 =
+            |bases: &[G1], scalars: &[G1::ScalarField]| secret_msm(&AdaptiveMsm, bases, scalars);
+        let msm2 =
+            |bases: &[G2], scalars: &[G2::ScalarField]| secret_msm(&AdaptiveMsm, bases, scalars);
+        let mut secrets = Vec::with_capacity(n);
+        let mut bits = Vec::with_capacity(n);
+        let mut nonces = Vec::with_capacity(n);
+        for i in 0..n {
+            let b = (value >> i) & 1;
+            let C1 = pc_gens1.commit(G1::ScalarField::from(b), r1[i]);
+            let C2 = pc_gens2.commit(G2::ScalarField::from(b), r2[i]);
+
+            let k1 = G1::ScalarField::rand(&mut rng);
+            let k2 = G2::ScalarField::rand(&mut rng);
+            let mut e_sim = [0u8; 16];
+            rng.fill_bytes(&mut e_sim);
+            let s1_sim = G1::ScalarField::rand(&mut rng);
+            let s2_sim = G2::ScalarField::rand(&mut rng);
+
+            let K1 = order_points::<G1>(
+                b,
+                msm1(&[pc_gens1.B_blinding], &[k1]),
+                ring_nonce(pc_gens1, &C1, 1 - b, &e_sim, &s1_sim, msm1),
+            );
+            let K2 = order_points::<G2>(
+                b,
+                msm2(&[pc_gens2.B_blinding], &[k2]),
+                ring_nonce(pc_gens2, &C2, 1 - b, &e_sim, &s2_sim, msm2),
+            );
+
+            secrets.push((b, k1, k2, e_sim, s1_sim, s2_sim));
+            nonces.push((K1, K2));
+            bits.push(BitProof {
+                C1,
+                C2,
+                e0: [0u8; 16],
+                s1: [G1::ScalarField::zero(); 2],
+                s2: [G2::ScalarField::zero(); 2],
+            });
+        }
+
+        for bit in &bits {
+            transcript.append_point(b"C1", &bit.C1);
+            transcript.append_point(b"C2", &bit.C2);
+        }
+        append_nonces(transcript, &nonces);
+        let mut e = [0u8; 16];
+        transcript.challenge_bytes(b"e", &mut e);
+
+        for (i, (bit, (b, k1, k2, e_sim, s1_sim, s2_sim))) in
+            bits.iter_mut().zip(secrets).enumerate()
+        {
+            // The challenges of both branches XOR to `e`.
+            let e_b = xor(&e, &e_sim);
+            let s1 = k1 - challenge_scalar::<G1::ScalarField>(&e_b) * r1[i];
+            let s2 = k2 - challenge_scalar::<G2::ScalarField>(&e_b) * r2[i];
+            bit.s1 = order_scalars(b, s1, s1_sim);
+            bit.s2 = order_scalars(b, s2, s2_sim);
+            bit.e0 = order_bytes(b, e_b, e_sim)[0];
+        }
+
+        Ok(CrossCurveEqualityProof { bits, e })
+    }
+
+    /// Verifies that the commitments `V1` and `V2` commit to the same
+    /// `n`-bit value.
+    ///
+    /// The `transcript` must be in the same state as the one passed to
+    /// [`CrossCurveEqualityProof::create`].
+    pub fn verify(
+        &self,
+        transcript: &mut Transcript,
+        pc_gens1: &PedersenGens<G1>,
+        pc_gens2: &PedersenGens<G2>,
+        V1: &G1,
+        V2: &G2,
+        n: usize,
+    ) -> Result<(), ProofError> {
+        if self.bits.len() != n {
+            return Err(ProofError::VerificationError);
+        }
+        append_statement(transcript, V1, V2, n);
+
+        // The bit commitments add up to V1 and V2.
+        let C1: Vec<G1> = self.bits.iter().map(|bit| bit.C1).collect();
+        let C2: Vec<G2> = self.bits.iter().map(|bit| bit.C2).collect();
+        if AdaptiveMsm.msm(&C1, &powers_of_two(n)) != V1.into_group()
+            || AdaptiveMsm.msm(&C2, &powers_of_two(n)) != V2.into_group()
+        {
+            return Err(ProofError::VerificationError);
+        }
+
+        let msm1 = |bases: &[G1], scalars: &[G1::ScalarField]| AdaptiveMsm.msm(bases, scalars);
+        let msm2 = |bases: &[G2], scalars: &[G2::ScalarField]| AdaptiveMsm.msm(bases, scalars);
+        let nonces: Vec<_> = self
+            .bits
+            .iter()
+            .map(|bit| {
+                let e1 = xor(&self.e, &bit.e0);
+                let K1 = [
+                    ring_nonce(pc_gens1, &bit.C1, 0, &bit.e0, &bit.s1[0], msm1),
+                    ring_nonce(pc_gens1, &bit.C1, 1, &e1, &bit.s1[1], msm1),
+                ]
+                .map(|K| K.into_affine());
+                let K2 = [
+                    ring_nonce(pc_gens2, &bit.C2, 0, &bit.e0, &bit.s2[0], msm2),
+                    ring_nonce(pc_gens2, &bit.C2, 1, &e1, &bit.s2[1], msm2),
+                ]
+                .map(|K| K.into_affine());
+                (K1, K2)
+            })
+            .collect();
+
+        for bit in &self.bits {
+            transcript.append_point(b"C1", &bit.C1);
+            transcript.append_point(b"C2", &bit.C2);
+        }
+        append_nonces(transcript, &nonces);
+        let mut e = [0u8; 16];
+        transcript.challenge_bytes(b"e", &mut e);
+
+        if e != self.e {
+            return Err(ProofError::VerificationError);
+        }
+        Ok(())
+    }
+
+    /// Serializes the proof into a byte array.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ProofError> {
+        let mut cursor = Cursor::new(Vec::new());
+        self.serialize_compressed(&mut cursor)?;
+        Ok(cursor.into_inner())
+    }
+
+    /// Deserializes the proof from a byte slice.
+    ///
+    /// Returns an error if the byte slice cannot be parsed into a
+    /// `CrossCurveEqualityProof`.
+    pub fn from_bytes(slice: &[u8]) -> Result<CrossCurveEqualityProof<G1, G2>, ProofError> {
+        let mut cursor = Cursor::new(slice);
+        CrossCurveEqualityProof::deserialize_compressed(&mut cursor)
+            .map_err(|_| ProofError::FormatError)
+    }
+}
+
+/// Appends the domain separator and the commitments of the statement.
+fn append_statement<G1: AffineRepr, G2: AffineRepr>(
+    transcript: &mut Transcript,
+    V1: &G1,
+    V2: &G2,
+    n: usize,
+) {
+    <Transcript as TranscriptProtocol<G1>>::cross_curve_domain_sep(transcript, n as u64);
+    transcript.append_point(b"V1", V1);
+    transcript.append_point(b"V2", V2);
+}
+
+/// Appends the nonce commitments of the ring signatures of all the bits.
+#[allow(clippy::type_complexity)]
+fn append_nonces<G1: AffineRepr, G2: AffineRepr>(
+    transcript: &mut Transcript,
+    nonces: &[([G1; 2], [G2; 2])],
+) {
+    for (K1, K2) in nonces {
+        transcript.append_point(b"K1", &K1[0]);
+        transcript.append_point(b"K2", &K2[0]);
+        transcript.append_point(b"K1", &K1[1]);
+        transcript.append_point(b"K2", &K2[1]);
+    }
+}
+
+/// Returns the nonce commitment \\(s \tilde{B} + e (C - b B)\\) of the
+/// branch for the bit `b` of the ring signature of the bit commitment `C`,
+/// computed with `msm`.
+fn ring_nonce<G: AffineRepr, M: Fn(&[G], &[G::ScalarField]) -> G::Group>(
+    pc_gens: &PedersenGens<G>,
+    C: &G,
+    b: u64,
+    e: &[u8; 16],
+    s: &G::ScalarField,
+    msm: M,
+) -> G::Group {
+    let e = challenge_scalar::<G::ScalarField>(e);
+    let b = G::ScalarField::from(b);
+    msm(&[pc_gens.B_blinding, *C, pc_gens.B], &[*s, e, -(e * b)])
+}
+
+/// Returns `[real, simulated]` if the secret bit `b` is 0, and
+/// `[simulated, real]` otherwise, in constant time with the `ct` feature.
+fn order_points<G: AffineRepr>(b: u64, real: G::Group, simulated: G::Group) -> [G; 2] {
+    #[cfg(feature = "ct")]
+    let (first, second) = crate::ct::swap_points::<G>(real, simulated, Choice::from(b as u8));
+    #[cfg(not(feature = "ct"))]
+    let (first, second) = if b == 0 {
+        (real, simulated)
+    } else {
+        (simulated, real)
+    };
+    [first.into_affine(), second.into_affine()]
+}
+
+/// Same as [`order_points`], for scalars.
+fn order_scalars<F: PrimeField>(b: u64, real: F, simulated: F) -> [F; 2] {
+    #[cfg(feature = "ct")]
+    let (first, second) = crate::ct::swap_scalars(real, simulated, Choice::from(b as u8));
+    #[cfg(not(feature = "ct"))]
+    let (first, second) = if b == 0 {
+        (real, simulated)
+    } else {
+        (simulated, real)
+    };
+    [first, second]
+}
+
+/// Same as [`order_points`], for challenges.
+fn order_bytes(b: u64, real: [u8; 16], simulated: [u8; 16]) -> [[u8; 16]; 2] {
+    #[cfg(feature = "ct")]
+    let (first, second) = {
+        let choice = Choice::from(b as u8);
+        (
+            crate::ct::select_bytes(&real, &simulated, choice),
+            crate::ct::select_bytes(&simulated, &real, choice),
+        )
+    };
+    #[cfg(not(feature = "ct"))]
+    let (first, second) = if b == 0 {
+        (real, simulated)
+    } else {
+        (simulated, real)
+    };
+    [first, second]
+}
+
+/// Returns `n` blinding factors \\(r_i\\) such that
+/// \\(\sum_i 2^i r_i = r\\).
+fn bit_blindings<F: PrimeField, R: RngCore>(r: F, n: usize, rng: &mut R) -> Vec<F> {
+    let mut blindings: Vec<F> = (0..n - 1).map(|_| F::rand(rng)).collect();
+    let rest: F = blindings
+        .iter()
+        .zip(powers_of_two::<F>(n - 1))
+        .map(|(r_i, p)| *r_i * p)
+        .sum();
+    let last_power = F::from(2u64).pow([(n - 1) as u64]);
+    blindings.push((r - rest) * last_power.inverse().unwrap());
+    blindings
+}
+
+/// Returns the first `n` powers of two.
+fn powers_of_two<F: PrimeField>(n: usize) -> Vec<F> {
+    let mut powers = Vec::with_capacity(n);
+    let mut p = F::one();
+    for _ in 0..n {
+        powers.push(p);
+        p.double_in_place();
+    }
+    powers
+}
+
+/// Interprets a 128-bit challenge as a scalar.
+fn challenge_scalar<F: PrimeField>(e: &[u8; 16]) -> F {
+    F::from(u128::from_le_bytes(*e))
+}
+
+/// Returns the bitwise XOR of two challenges.
+fn xor(a: &[u8; 16], b: &[u8; 16]) -> [u8; 16] {
+    let mut out = [0u8; 16];
+    for (out, (a, b)) in out.iter_mut().zip(a.iter().zip(b.iter())) {
+        *out = a ^ b;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::curve::zorro::{Fr as ZorroFr, G1Affine as ZorroAffine};
+    use ark_secq256k1::{Affine as SecqAffine, Fr as SecqFr};
+
+    type Proof = CrossCurveEqualityProof<SecqAffine, ZorroAffine>;
+
+    fn proof_helper(value: u64, other: u64, n: usize) -> Result<(), ProofError> {
+        let pc_gens1 = PedersenGens::<SecqAffine>::default();
+        let pc_gens2 = PedersenGens::<ZorroAffine>::default();
+        let mut rng = rand::thread_rng();
+        let blinding1 = SecqFr::rand(&mut rng);
+        let blinding2 = ZorroFr::rand(&mut rng);
+
+        let mut transcript = Transcript::new(b"CrossCurveTest");
+        let proof = Proof::create(
+            &mut transcript,
+            &pc_gens1,
+            &pc_gens2,
+            value,
+            blinding1,
+            blinding2,
+            n,
+            &mut rng,
+        )?;
+        let proof = Proof::from_bytes(&proof.to_bytes()?)?;
+
+        let V1 = pc_gens1.commit(SecqFr::from(value), blinding1);
+        let V2 = pc_gens2.commit(ZorroFr::from(other), blinding2);
+        let mut transcript = Transcript::new(b"CrossCurveTest");
+        proof.verify(&mut transcript, &pc_gens1, &pc_gens2, &V1, &V2, n)
+    }
+
+    #[test]
+    fn cross_curve_equality() {
+        assert!(proof_helper(0, 0, 8).is_ok());
+        assert!(proof_helper(200, 200, 8).is_ok());
+        assert!(proof_helper(u64::MAX, u64::MAX, 64).is_ok());
+        assert!(proof_helper(200, 201, 8).is_err());
+        assert_eq!(proof_helper(256, 256, 8), Err(ProofError::InvalidBitsize));
+        assert_eq!(proof_helper(0, 0, 0), Err(ProofError::InvalidBitsize));
+    }
+}
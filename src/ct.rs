@@ -0,0 +1,222 @@
+//! The `ct` module contains the constant-time multiscalar multiplication
+//! used, with the `ct` feature, for the secret scalars of the provers:
+//! the witness, the blinding factors, and the vectors folded by the
+//! inner-product proof.  It also selects between values by secret
+//! choices, such as the real and the simulated branches of the ring
+//! signatures of the cross-curve equality proofs.
+//!
+//! The variable-time backends read their tables at indices given by the
+//! digits of the scalars, skip the zero digits, and add the identity or
+//! a point to itself through branches of the group law of arkworks.
+//! Here, every window of every scalar costs one addition of an entry
+//! read by masking the whole table, and the entries are offset by a
+//! point \\(D\\) unrelated to the bases, \\(T\_j = D + j \cdot P\\), so
+//! that neither the accumulator nor the added entries are the identity,
+//! and they are not equal but with negligible probability.
+//!
+//! The field arithmetic of arkworks ends its reductions with conditional
+//! subtractions, which are out of the reach of this module.
+
+#![allow(non_snake_case)]
+
+use ark_ec::{AffineRepr, CurveGroup, Group};
+use ark_ff::{Field, PrimeField, Zero};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::{rand::SeedableRng, vec::Vec, UniformRand};
+use digest::Digest;
+use rand_chacha::ChaChaRng;
+use sha3::Sha3_512;
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+use zeroize::Zeroize;
+
+/// The window of the digits of the scalars.
+const WINDOW: usize = 4;
+
+/// Returns \\(\sum\_i s\_i \cdot P\_i\\) for the `scalars` \\(s\_i\\)
+/// and the `bases` \\(P\_i\\), which have the same length, in time
+/// independent of the scalars.
+pub(crate) fn msm<G: AffineRepr>(bases: &[G], scalars: &[G::ScalarField]) -> G::Group {
+    assert_eq!(bases.len(), scalars.len());
+    if bases.is_empty() {
+        return G::Group::zero();
+    }
+    let D = offset::<G>();
+    let entries: Vec<G::Group> = bases
+        .iter()
+        .flat_map(|base| {
+            let mut entry = D;
+            (0..1 << WINDOW).map(move |_| {
+                let current = entry;
+                entry += base;
+                current
+            })
+        })
+        .collect();
+    // The tables are read as affine points, for mixed additions.
+    let entries = G::Group::normalize_batch(&entries);
+    let tables: Vec<Vec<u8>> = entries.chunks(1 << WINDOW).map(serialize).collect();
+    let mut scalars: Vec<_> = scalars.iter().map(|s| s.into_bigint()).collect();
+
+    let windows = (G::ScalarField::MODULUS_BIT_SIZE as usize).div_ceil(WINDOW);
+    let digit = |scalar: &<G::ScalarField as PrimeField>::BigInt, i: usize| {
+        let bit = i * WINDOW;
+        (scalar.as_ref()[bit / 64] >> (bit % 64)) & ((1 << WINDOW) - 1)
+    };
+    let read = |table: &[u8], index| select::<G>(table, 1 << WINDOW, index);
+    let mut result = read(&tables[0], digit(&scalars[0], windows - 1)).into_group();
+    for (table, scalar) in tables.iter().zip(&scalars).skip(1) {
+        result += read(table, digit(scalar, windows - 1));
+    }
+    for i in (0..windows - 1).rev() {
+        for _ in 0..WINDOW {
+            result.double_in_place();
+        }
+        for (table, scalar) in tables.iter().zip(&scalars) {
+            result += read(table, digit(scalar, i));
+        }
+    }
+    scalars.zeroize();
+
+    // Every window added D once per term, i.e. (2^(w * windows) - 1) /
+    // (2^w - 1) times D per term.
+    let sixteen = G::ScalarField::from(1u64 << WINDOW);
+    let times = (sixteen.pow([windows as u64]) - G::ScalarField::from(1u64))
+        * (sixteen - G::ScalarField::from(1u64)).inverse().unwrap()
+        * G::ScalarField::from(bases.len() as u64);
+    result - D * times
+}
+
+/// Returns `(a, b)` if `choice` is 0, and `(b, a)` if it is 1, reading
+/// the encodings of both points with masks.
+pub(crate) fn swap_points<G: AffineRepr>(
+    a: G::Group,
+    b: G::Group,
+    choice: Choice,
+) -> (G::Group, G::Group) {
+    let pair = serialize(&G::Group::normalize_batch(&[a, b]));
+    let index = u64::from(choice.unwrap_u8());
+    (
+        select::<G>(&pair, 2, index).into_group(),
+        select::<G>(&pair, 2, 1 ^ index).into_group(),
+    )
+}
+
+/// Returns `(a, b)` if `choice` is 0, and `(b, a)` if it is 1, selecting
+/// the limbs of their canonical integers with masks.
+pub(crate) fn swap_scalars<F: PrimeField>(a: F, b: F, choice: Choice) -> (F, F) {
+    let (mut x, mut y) = (a.into_bigint(), b.into_bigint());
+    for (x, y) in x.as_mut().iter_mut().zip(y.as_mut()) {
+        u64::conditional_swap(x, y, choice);
+    }
+    let result = (F::from_bigint(x).unwrap(), F::from_bigint(y).unwrap());
+    x.as_mut().zeroize();
+    y.as_mut().zeroize();
+    result
+}
+
+/// Returns `a` if `choice` is 0, and `b` if it is 1, selecting every
+/// byte with masks.
+pub(crate) fn select_bytes<const N: usize>(a: &[u8; N], b: &[u8; N], choice: Choice) -> [u8; N] {
+    let mut result = *a;
+    for (r, b) in result.iter_mut().zip(b) {
+        r.conditional_assign(b, choice);
+    }
+    result
+}
+
+/// Returns the concatenated uncompressed encodings of the `entries`.
+fn serialize<T: CanonicalSerialize>(entries: &[T]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for entry in entries {
+        entry.serialize_uncompressed(&mut bytes).unwrap();
+    }
+    bytes
+}
+
+/// Returns the entry `index` of the `table` of `len` encodings of the
+/// same size, as concatenated by [`serialize`], reading every byte of
+/// every entry with masks.
+fn select<T: CanonicalDeserialize>(table: &[u8], len: usize, index: u64) -> T {
+    let size = table.len() / len;
+    let mut bytes = Vec::from(&table[..size]);
+    for (j, entry) in table.chunks_exact(size).enumerate() {
+        let choice = (j as u64).ct_eq(&index);
+        for (byte, entry_byte) in bytes.iter_mut().zip(entry) {
+            byte.conditional_assign(entry_byte, choice);
+        }
+    }
+    // The bytes are those of one of the entries, which were valid.
+    T::deserialize_uncompressed_unchecked(&bytes[..]).unwrap()
+}
+
+/// Returns the offset \\(D\\) of the tables, hashed to the curve from the
+/// generator as the blinding base of the default Pedersen generators,
+/// but with a domain separator.
+fn offset<G: AffineRepr>() -> G::Group {
+    let mut bytes = b"ark-bulletproofs constant-time offset".to_vec();
+    G::generator().serialize_uncompressed(&mut bytes).unwrap();
+    let hash = Sha3_512::digest(&bytes);
+
+    let mut seed = [0u8; 32];
+    seed.copy_from_slice(&hash[..32]);
+    G::Group::rand(&mut ChaChaRng::from_seed(seed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ec::VariableBaseMSM;
+    use ark_std::One;
+
+    fn check<G: AffineRepr>() {
+        let mut rng = ark_std::test_rng();
+        for n in [0, 1, 2, 9] {
+            let bases: Vec<G> = (0..n).map(|_| G::rand(&mut rng)).collect();
+            let mut scalars: Vec<G::ScalarField> =
+                (0..n).map(|_| G::ScalarField::rand(&mut rng)).collect();
+            if n > 2 {
+                scalars[0] = G::ScalarField::zero();
+                scalars[1] = G::ScalarField::one();
+                scalars[2] = -G::ScalarField::one();
+            }
+            assert_eq!(
+                msm(&bases, &scalars),
+                G::Group::msm_unchecked(&bases, &scalars)
+            );
+        }
+    }
+
+    #[test]
+    fn msm_matches_arkworks() {
+        check::<ark_secq256k1::Affine>();
+        check::<ark_ed25519::EdwardsAffine>();
+    }
+
+    #[test]
+    fn swaps_by_choice() {
+        type G = ark_secq256k1::Affine;
+        type F = ark_secq256k1::Fr;
+
+        let mut rng = ark_std::test_rng();
+        let (P, Q) = (
+            <G as AffineRepr>::Group::rand(&mut rng),
+            <G as AffineRepr>::Group::rand(&mut rng),
+        );
+        let (x, y) = (F::rand(&mut rng), F::rand(&mut rng));
+        assert_eq!(swap_points::<G>(P, Q, Choice::from(0)), (P, Q));
+        assert_eq!(swap_points::<G>(P, Q, Choice::from(1)), (Q, P));
+        assert_eq!(swap_scalars(x, y, Choice::from(0)), (x, y));
+        assert_eq!(swap_scalars(x, y, Choice::from(1)), (y, x));
+        assert_eq!(select_bytes(&[1, 2], &[3, 4], Choice::from(0)), [1, 2]);
+        assert_eq!(select_bytes(&[1, 2], &[3, 4], Choice::from(1)), [3, 4]);
+    }
+
+    #[test]
+    fn select_reads_the_entry() {
+        let entries: Vec<[u64; 3]> = (0..16).map(|j| [j, 2 * j, 3 * j]).collect();
+        let table = serialize(&entries);
+        for j in 0..16 {
+            assert_eq!(select::<[u64; 3]>(&table, 16, j), entries[j as usize]);
+        }
+    }
+}
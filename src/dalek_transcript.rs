@@ -0,0 +1,146 @@
+//! The `dalek_transcript` module contains a Merlin transcript with the
+//! encodings of the dalek `bulletproofs` crate, for deployments that mix
+//! this crate with implementations of the dalek transcript in other
+//! languages.
+
+#![deny(missing_docs)]
+
+use ark_ec::AffineRepr;
+use ark_ff::PrimeField;
+use ark_std::rand::{CryptoRng, RngCore};
+use ark_std::vec::Vec;
+use merlin::{Transcript, TranscriptRng};
+
+use crate::transcript::{ByteTranscript, TranscriptProtocol};
+
+/// A Merlin transcript appending values in the encodings of the dalek
+/// `bulletproofs` crate instead of those of the [`TranscriptProtocol`]
+/// implementation of [`Transcript`].
+///
+/// The domain separators and labels are the same, but points are appended
+/// compressed, and challenge scalars are 64 challenge bytes reduced
+/// modulo the order of the group, as the `Scalar::from_bytes_mod_order_wide`
+/// of `curve25519-dalek`.  Scalars are appended in their 32-byte
+/// little-endian encodings, as in both.  Proofs created with this
+/// transcript differ from those created with a plain [`Transcript`], so
+/// the provers and verifiers of a deployment must agree on one of them.
+#[derive(Clone)]
+pub struct DalekCompatTranscript(pub Transcript);
+
+impl DalekCompatTranscript {
+    /// Creates a transcript with the domain separator `label`, as
+    /// [`Transcript::new`].
+    pub fn new(label: &'static [u8]) -> Self {
+        DalekCompatTranscript(Transcript::new(label))
+    }
+}
+
+impl From<Transcript> for DalekCompatTranscript {
+    fn from(transcript: Transcript) -> Self {
+        DalekCompatTranscript(transcript)
+    }
+}
+
+impl ByteTranscript for DalekCompatTranscript {
+    fn append_message(&mut self, label: &'static [u8], message: &[u8]) {
+        self.0.append_message(label, message);
+    }
+
+    fn append_u64(&mut self, label: &'static [u8], x: u64) {
+        self.0.append_u64(label, x);
+    }
+
+    fn challenge_bytes(&mut self, label: &'static [u8], dest: &mut [u8]) {
+        self.0.challenge_bytes(label, dest);
+    }
+}
+
+impl<G: AffineRepr> TranscriptProtocol<G> for DalekCompatTranscript {
+    type Rng = TranscriptRng;
+
+    fn append_scalar(&mut self, label: &'static [u8], scalar: &G::ScalarField) {
+        <Transcript as TranscriptProtocol<G>>::append_scalar(&mut self.0, label, scalar);
+    }
+
+    fn append_point(&mut self, label: &'static [u8], point: &G) {
+        let mut bytes = Vec::new();
+        point.serialize_compressed(&mut bytes).unwrap();
+        self.0.append_message(label, &bytes);
+    }
+
+    fn challenge_scalar(&mut self, label: &'static [u8]) -> G::ScalarField {
+        let mut buf = [0u8; 64];
+        self.0.challenge_bytes(label, &mut buf);
+
+        G::ScalarField::from_le_bytes_mod_order(&buf)
+    }
+
+    fn forked_challenge_scalar(&self, label: &'static [u8]) -> G::ScalarField {
+        <Self as TranscriptProtocol<G>>::challenge_scalar(&mut self.clone(), label)
+    }
+
+    fn witness_rng<R: RngCore + CryptoRng>(
+        &self,
+        witness: &[(&'static [u8], Vec<u8>)],
+        rng: &mut R,
+    ) -> TranscriptRng {
+        <Transcript as TranscriptProtocol<G>>::witness_rng(&self.0, witness, rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ark_serialize::CanonicalSerialize;
+
+    use crate::util::hex;
+
+    type G = ark_ed25519::EdwardsAffine;
+
+    fn transcript() -> DalekCompatTranscript {
+        let mut transcript = DalekCompatTranscript::new(b"test protocol");
+        transcript.append_message(b"some label", b"some data");
+        transcript
+    }
+
+    #[test]
+    fn dalek_transcript_known_answers() {
+        // The simple transcript test of the Go implementation of Merlin.
+        let mut challenge = [0u8; 32];
+        transcript().challenge_bytes(b"challenge", &mut challenge);
+        assert_eq!(
+            hex(&challenge),
+            "d5a21972d0d5fe320c0d263fac7fffb8145aa640af6e9bca177c03c7efcf0615"
+        );
+
+        // The challenge the dalek crate derives from the same transcript,
+        // with `Scalar::from_bytes_mod_order_wide` over 64 challenge bytes.
+        let x = <DalekCompatTranscript as TranscriptProtocol<G>>::challenge_scalar(
+            &mut transcript(),
+            b"challenge",
+        );
+        let mut bytes = Vec::new();
+        x.serialize_compressed(&mut bytes).unwrap();
+        assert_eq!(
+            hex(&bytes),
+            "e047e0ac03917bb8eb0344d4ba7b21cf28a572a09e14a2a893c1fe53c4aaa60b"
+        );
+    }
+
+    #[test]
+    fn dalek_transcript_compresses_points() {
+        let point = G::generator();
+        let mut compressed = Vec::new();
+        point.serialize_compressed(&mut compressed).unwrap();
+
+        let mut a = transcript();
+        <DalekCompatTranscript as TranscriptProtocol<G>>::append_point(&mut a, b"P", &point);
+        let mut b = transcript();
+        b.append_message(b"P", &compressed);
+        let (mut x, mut y) = ([0u8; 32], [0u8; 32]);
+        a.challenge_bytes(b"c", &mut x);
+        b.challenge_bytes(b"c", &mut y);
+        assert_eq!(x, y);
+    }
+}
@@ -0,0 +1,169 @@
+#![allow(non_snake_case)]
+//! Definition of discrete-log equality proofs.
+
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::{
+    io::Cursor,
+    rand::{CryptoRng, RngCore},
+    vec::Vec,
+    One, UniformRand, Zero,
+};
+use merlin::Transcript;
+use zeroize::Zeroizing;
+
+use crate::errors::ProofError;
+use crate::msm::{secret_msm, AdaptiveMsm, MsmBackend};
+use crate::transcript::TranscriptProtocol;
+
+/// A proof that \\(P = x G\\) and \\(Q = x H\\) for the same secret \\(x\\),
+/// with respect to two bases \\(G\\) and \\(H\\).
+///
+/// This is the usual companion statement of key images in linkable ring
+/// signatures: \\(P\\) is a public key, and \\(Q\\) the key image of the
+/// same secret key under a base \\(H\\) derived from \\(P\\).  The proof is
+/// created and verified over a Merlin transcript, so it can be bound to the
+/// same transcript as an [`R1CSProof`](::r1cs::R1CSProof).
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct DleqProof<G: AffineRepr> {
+    /// Commitment \\(k G\\) to the nonce.
+    R_G: G,
+    /// Commitment \\(k H\\) to the nonce.
+    R_H: G,
+    /// Response \\(k + e x\\).
+    s: G::ScalarField,
+}
+
+impl<G: AffineRepr> DleqProof<G> {
+    /// Creates a proof that `x * G` and `x * H` have the same discrete
+    /// logarithm `x` with respect to the bases `G` and `H`.
+    ///
+    /// The bases and the points are appended to the `transcript`, so the
+    /// verifier does not need to append them separately.  With the `ct`
+    /// feature, `x` and the nonce are multiplied in constant time.
+    pub fn create<R: CryptoRng + RngCore>(
+        transcript: &mut Transcript,
+        G: &G,
+        H: &G,
+        x: G::ScalarField,
+        rng: &mut R,
+    ) -> DleqProof<G> {
+        let P = secret_msm(&AdaptiveMsm, &[*G], &[x]).into_affine();
+        let Q = secret_msm(&AdaptiveMsm, &[*H], &[x]).into_affine();
+        append_statement(transcript, G, H, &P, &Q);
+
+        let mut rng = {
+            let mut bytes = Zeroizing::new(Vec::with_capacity(x.uncompressed_size()));
+            x.serialize_uncompressed(&mut *bytes).unwrap();
+            transcript
+                .build_rng()
+                .rekey_with_witness_bytes(b"x", &bytes)
+                .finalize(rng)
+        };
+        let k = G::ScalarField::rand(&mut rng);
+
+        let R_G = secret_msm(&AdaptiveMsm, &[*G], &[k]).into_affine();
+        let R_H = secret_msm(&AdaptiveMsm, &[*H], &[k]).into_affine();
+        transcript.append_point(b"R_G", &R_G);
+        transcript.append_point(b"R_H", &R_H);
+
+        let e = <Transcript as TranscriptProtocol<G>>::challenge_scalar(transcript, b"e");
+
+        DleqProof {
+            R_G,
+            R_H,
+            s: k + e * x,
+        }
+    }
+
+    /// Verifies that `P` and `Q` have the same discrete logarithm with
+    /// respect to the bases `G` and `H`.
+    ///
+    /// The `transcript` must be in the same state as the one passed to
+    /// [`DleqProof::create`].
+    pub fn verify(
+        &self,
+        transcript: &mut Transcript,
+        G: &G,
+        H: &G,
+        P: &G,
+        Q: &G,
+    ) -> Result<(), ProofError> {
+        append_statement(transcript, G, H, P, Q);
+        transcript.validate_and_append_point(b"R_G", &self.R_G)?;
+        transcript.validate_and_append_point(b"R_H", &self.R_H)?;
+
+        let e = <Transcript as TranscriptProtocol<G>>::challenge_scalar(transcript, b"e");
+
+        // Check s * G == R_G + e * P and s * H == R_H + e * Q
+        for (B, R, X) in [(G, &self.R_G, P), (H, &self.R_H, Q)] {
+            let check = AdaptiveMsm.msm(&[*B, *R, *X], &[self.s, -G::ScalarField::one(), -e]);
+            if !check.is_zero() {
+                return Err(ProofError::VerificationError);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Serializes the proof into a byte array.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ProofError> {
+        let mut cursor = Cursor::new(Vec::new());
+        self.serialize_compressed(&mut cursor)?;
+        Ok(cursor.into_inner())
+    }
+
+    /// Deserializes the proof from a byte slice.
+    ///
+    /// Returns an error if the byte slice cannot be parsed into a `DleqProof`.
+    pub fn from_bytes(slice: &[u8]) -> Result<DleqProof<G>, ProofError> {
+        let mut cursor = Cursor::new(slice);
+        DleqProof::deserialize_compressed(&mut cursor).map_err(|_| ProofError::FormatError)
+    }
+}
+
+/// Appends the domain separator, the bases and the points of the statement.
+fn append_statement<G: AffineRepr>(transcript: &mut Transcript, G: &G, H: &G, P: &G, Q: &G) {
+    <Transcript as TranscriptProtocol<G>>::dleq_domain_sep(transcript);
+    transcript.append_point(b"G", G);
+    transcript.append_point(b"H", H);
+    transcript.append_point(b"P", P);
+    transcript.append_point(b"Q", Q);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::generators::PedersenGens;
+    use ark_ff::PrimeField;
+
+    type G = ark_secq256k1::Affine;
+    type F = ark_secq256k1::Fr;
+
+    #[test]
+    fn dleq_proof_roundtrip() {
+        let pc_gens = PedersenGens::<G>::default();
+        let (G, H) = (pc_gens.B, pc_gens.B_blinding);
+        let mut rng = rand::thread_rng();
+        let x = F::rand(&mut rng);
+        let P = G.mul_bigint(x.into_bigint()).into_affine();
+        let Q = H.mul_bigint(x.into_bigint()).into_affine();
+
+        let mut transcript = Transcript::new(b"DleqProofTest");
+        let proof = DleqProof::create(&mut transcript, &G, &H, x, &mut rng);
+        let proof = DleqProof::<G>::from_bytes(&proof.to_bytes().unwrap()).unwrap();
+
+        let mut transcript = Transcript::new(b"DleqProofTest");
+        assert!(proof.verify(&mut transcript, &G, &H, &P, &Q).is_ok());
+
+        // A different transcript gives a different challenge.
+        let mut transcript = Transcript::new(b"OtherTest");
+        assert!(proof.verify(&mut transcript, &G, &H, &P, &Q).is_err());
+
+        // Points with different discrete logarithms are rejected.
+        let Q = H.mul_bigint((x + F::from(1u8)).into_bigint()).into_affine();
+        let mut transcript = Transcript::new(b"DleqProofTest");
+        assert!(proof.verify(&mut transcript, &G, &H, &P, &Q).is_err());
+    }
+}
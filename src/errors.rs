@@ -6,12 +6,20 @@ use ark_std::{
     string::{String, ToString},
     vec::Vec,
 };
+#[cfg(feature = "std")]
+use std::error::Error;
 
 /// Represents an error in proof creation, verification, or parsing.
 #[derive(Clone, Eq, PartialEq)]
 pub enum ProofError {
     /// This error occurs when a proof failed to verify.
     VerificationError,
+    /// This error occurs when a point of the proof, appended to the
+    /// transcript with `label`, is the identity.
+    InvalidPoint {
+        /// The transcript label of the point.
+        label: &'static [u8],
+    },
     /// This error occurs when the proof encoding is malformed.
     FormatError,
     /// This error occurs during proving if the number of blinding
@@ -40,6 +48,9 @@ impl fmt::Debug for ProofError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             ProofError::VerificationError => write!(f, "Proof verification failed."),
+            ProofError::InvalidPoint { label } => {
+                write!(f, "Invalid point {} in the proof.", label_str(label))
+            }
             ProofError::FormatError => write!(f, "Proof data could not be parsed."),
             ProofError::WrongNumBlindingFactors => {
                 write!(f, "Wrong number of blinding factors supplied.")
@@ -67,6 +78,16 @@ impl fmt::Display for ProofError {
     }
 }
 
+#[cfg(feature = "std")]
+impl Error for ProofError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ProofError::ProvingError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
 impl From<MPCError> for ProofError {
     fn from(e: MPCError) -> ProofError {
         match e {
@@ -107,6 +128,12 @@ pub enum MPCError {
     /// This error occurs when the dealer is given the wrong number of
     /// proof shares.
     WrongNumProofShares,
+    /// This error occurs when the dealer is given the wrong number of
+    /// witness commitments.
+    WrongNumWitnessCommitments,
+    /// This error occurs when a party's share of the witness does not
+    /// match the size of the circuit.
+    WrongWitnessLength,
     /// This error occurs when one or more parties submit malformed
     /// proof shares.
     MalformedProofShares {
@@ -129,6 +156,10 @@ impl fmt::Debug for MPCError {
             MPCError::WrongNumBitCommitments => write!(f, "Wrong number of value commitments"),
             MPCError::WrongNumPolyCommitments => write!(f, "Wrong number of value commitments"),
             MPCError::WrongNumProofShares => write!(f, "Wrong number of proof shares"),
+            MPCError::WrongNumWitnessCommitments => {
+                write!(f, "Wrong number of witness commitments")
+            }
+            MPCError::WrongWitnessLength => write!(f, "Witness share does not match the circuit"),
             MPCError::MalformedProofShares { bad_shares } => {
                 write!(f, "Malformed proof shares from parties {:?}", bad_shares)
             }
@@ -142,19 +173,63 @@ impl fmt::Display for MPCError {
     }
 }
 
+#[cfg(feature = "std")]
+impl Error for MPCError {}
+
 /// Represents an error during the proving or verifying of a constraint system.
 ///
 /// XXX: should this be separate from a `ProofError`?
 #[cfg(feature = "yoloproofs")]
 #[derive(Clone, Eq, PartialEq)]
 pub enum R1CSError {
-    /// Occurs when there are insufficient generators for the proof.
+    /// Occurs when there are insufficient generators for the proof, or
+    /// too few parties in them for an aggregated proof.
     InvalidGeneratorsLength,
+    /// Occurs when the generators have a smaller capacity than the
+    /// number of multipliers of the proof.
+    InsufficientGenerators {
+        /// The capacity of the generators.
+        capacity: usize,
+        /// The number of generators needed by the proof.
+        required: usize,
+    },
     /// This error occurs when the proof encoding is malformed.
     FormatError,
     /// Occurs when verification of an
     /// [`R1CSProof`](::r1cs::R1CSProof) fails.
     VerificationError,
+    /// Occurs when a point of the proof, appended to the transcript with
+    /// `label`, is the identity.
+    InvalidPoint {
+        /// The transcript label of the point.
+        label: &'static [u8],
+    },
+    /// Occurs when the vectors of an [`R1CSProof`](::r1cs::R1CSProof)
+    /// do not have the lengths expected from the circuit, e.g. the number
+    /// of its randomization phases.
+    InvalidProofShape {
+        /// The description of the mismatch.
+        description: String,
+    },
+    /// Occurs when the inner-product proof of an
+    /// [`R1CSProof`](::r1cs::R1CSProof) does not have one round per
+    /// halving of the padded number of multipliers.
+    InvalidInnerProductLength {
+        /// The number of rounds expected from the circuit.
+        expected: usize,
+        /// The number of rounds of the proof.
+        actual: usize,
+    },
+    /// Occurs when a proof or its circuit exceeds one of the
+    /// [`VerifierLimits`](::r1cs::VerifierLimits) of the verifier.
+    LimitExceeded {
+        /// The limited resource, e.g. `"multipliers"`.
+        resource: &'static str,
+        /// The limit of the verifier.
+        limit: usize,
+        /// The size of the proof or of the circuit.
+        actual: usize,
+    },
     /// Occurs when trying to use a missing variable assignment.
     /// Used by gadgets that build the constraint system to signal that
     /// a variable assignment is not provided when the prover needs it.
@@ -164,6 +239,26 @@ pub enum R1CSError {
         /// The description of the reasons for the error.
         description: String,
     },
+    /// Occurs when a prover in strict mode is given a witness that does
+    /// not satisfy one of its constraints.
+    UnsatisfiedConstraint {
+        /// The index of the first unsatisfied constraint, in the order
+        /// in which the constraints were added.
+        index: usize,
+    },
+    /// Occurs when a batch of proofs does not verify, and the invalid
+    /// proofs were identified.
+    BatchVerificationError {
+        /// The indices of the invalid proofs in the batch.
+        invalid_proofs: Vec<usize>,
+    },
+    /// Occurs when proving or verifying is aborted through the flag set
+    /// with [`Prover::set_cancellation_flag`](::r1cs::Prover::set_cancellation_flag)
+    /// or [`Verifier::set_cancellation_flag`](::r1cs::Verifier::set_cancellation_flag).
+    Cancelled,
+    /// Occurs when the multiparty computation of an aggregated proof
+    /// fails.
+    ProvingError(MPCError),
 }
 
 impl fmt::Debug for R1CSError {
@@ -172,10 +267,49 @@ impl fmt::Debug for R1CSError {
             R1CSError::InvalidGeneratorsLength => {
                 write!(f, "Invalid generators size, too few generators for proof")
             }
+            R1CSError::InsufficientGenerators { capacity, required } => write!(
+                f,
+                "Generators capacity {} is less than the {} required by the proof.",
+                capacity, required
+            ),
             R1CSError::FormatError => write!(f, "Proof data could not be parsed."),
             R1CSError::VerificationError => write!(f, "R1CSProof did not verify correctly."),
+            R1CSError::InvalidPoint { label } => {
+                write!(f, "Invalid point {} in the proof.", label_str(label))
+            }
+            R1CSError::InvalidProofShape { description } => {
+                write!(f, "Invalid proof shape: {}", description)
+            }
+            R1CSError::InvalidInnerProductLength { expected, actual } => write!(
+                f,
+                "Inner-product proof has {} rounds instead of {}.",
+                actual, expected
+            ),
+            R1CSError::LimitExceeded {
+                resource,
+                limit,
+                actual,
+            } => write!(
+                f,
+                "Proof has {} {}, more than the limit of {}.",
+                actual, resource, limit
+            ),
             R1CSError::MissingAssignment => write!(f, "Variable does not have a value assignment."),
             R1CSError::GadgetError { description } => write!(f, "Gadget error: {}", description),
+            R1CSError::UnsatisfiedConstraint { index } => {
+                write!(f, "Constraint {} is not satisfied by the witness.", index)
+            }
+            R1CSError::BatchVerificationError { invalid_proofs } => {
+                write!(
+                    f,
+                    "Proofs {:?} of the batch did not verify.",
+                    invalid_proofs
+                )
+            }
+            R1CSError::Cancelled => write!(f, "Proving or verifying was cancelled."),
+            R1CSError::ProvingError(e) => {
+                write!(f, "Error during aggregated proof creation: {:?}", e)
+            }
         }
     }
 }
@@ -186,14 +320,39 @@ impl fmt::Display for R1CSError {
     }
 }
 
+#[cfg(all(feature = "yoloproofs", feature = "std"))]
+impl Error for R1CSError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            R1CSError::ProvingError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
 #[cfg(feature = "yoloproofs")]
 impl From<ProofError> for R1CSError {
     fn from(e: ProofError) -> R1CSError {
         match e {
             ProofError::InvalidGeneratorsLength => R1CSError::InvalidGeneratorsLength,
-            ProofError::FormatError => R1CSError::FormatError,
+            ProofError::FormatError | ProofError::SerializationError(_) => R1CSError::FormatError,
             ProofError::VerificationError => R1CSError::VerificationError,
-            _ => panic!("unexpected error type in conversion"),
+            ProofError::InvalidPoint { label } => R1CSError::InvalidPoint { label },
+            ProofError::ProvingError(e) => R1CSError::ProvingError(e),
+            // The errors of range proofs do not occur in constraint systems.
+            _ => R1CSError::GadgetError {
+                description: e.to_string(),
+            },
+        }
+    }
+}
+
+#[cfg(feature = "yoloproofs")]
+impl From<MPCError> for R1CSError {
+    fn from(e: MPCError) -> R1CSError {
+        match e {
+            MPCError::InvalidGeneratorsLength => R1CSError::InvalidGeneratorsLength,
+            _ => R1CSError::ProvingError(e),
         }
     }
 }
@@ -209,3 +368,8 @@ impl From<SerializationError> for ProofError {
         ProofError::FormatError
     }
 }
+
+/// Returns a transcript label as text, for error messages.
+fn label_str(label: &[u8]) -> String {
+    String::from_utf8_lossy(label).into_owned()
+}
@@ -0,0 +1,712 @@
+#![allow(clippy::missing_safety_doc)]
+//! A C API, so that services written in other languages can create and
+//! verify proofs without a Rust dependency of their own.
+//!
+//! The API works over the secq256k1 curve with the default Pedersen
+//! generators.  Scalars are passed as 32-byte little-endian encodings,
+//! commitments as 33-byte compressed points, and linear combinations as
+//! arrays of [`BpTerm`].  Provers and verifiers are opaque handles,
+//! created by [`bp_prover_new`] and [`bp_verifier_new`], and consumed by
+//! [`bp_prover_prove`] and [`bp_verifier_verify`] or freed by
+//! [`bp_prover_free`] and [`bp_verifier_free`].  Proofs are returned in
+//! a [`BpBuffer`] owned by the caller, which must be released with
+//! [`bp_buffer_free`].
+//!
+//! Every function returns a [`BpStatus`], and only writes its outputs
+//! when it returns [`BpStatus::Ok`].  The matching C declarations are in
+//! `include/bulletproofs.h`.
+//!
+//! All pointers must be valid for the documented number of bytes or
+//! elements, and handles must not be used after they are consumed or
+//! freed, nor from several threads at once.
+//!
+//! Panics do not unwind into the caller: they are caught at the boundary
+//! and reported as [`BpStatus::Error`], or as a null handle.  A handle
+//! whose call panicked may be left inconsistent, and should be freed.
+
+use ark_secq256k1::{Affine, Fr};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::{boxed::Box, ptr, slice, vec::Vec};
+use merlin::Transcript;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::OnceLock;
+
+use crate::r1cs::{
+    ConstraintSystem, LinearCombination, Prover, R1CSError, R1CSProof, Variable, Verifier,
+};
+use crate::{BulletproofGens, PedersenGens};
+
+/// The length of an encoded scalar.
+const SCALAR_LEN: usize = 32;
+/// The length of an encoded commitment.
+const POINT_LEN: usize = 33;
+/// The largest capacity of the generators of [`bp_prover_prove`] and
+/// [`bp_verifier_verify`], which allocate them on every call.
+pub const BP_MAX_GENS_CAPACITY: usize = 1 << 20;
+
+/// The result of a call to the C API.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BpStatus {
+    /// The call succeeded.
+    Ok = 0,
+    /// A required pointer was null.
+    NullPointer = 1,
+    /// A scalar is not the canonical encoding of a field element.
+    InvalidScalar = 2,
+    /// A commitment is not the encoding of a curve point.
+    InvalidPoint = 3,
+    /// A variable has an unknown kind, or refers to a commitment or a
+    /// multiplier that does not exist.
+    InvalidVariable = 4,
+    /// See [`R1CSError::InvalidGeneratorsLength`].
+    InvalidGeneratorsLength = 5,
+    /// See [`R1CSError::FormatError`].
+    FormatError = 6,
+    /// See [`R1CSError::VerificationError`].
+    VerificationError = 7,
+    /// See [`R1CSError::UnsatisfiedConstraint`].
+    UnsatisfiedConstraint = 8,
+    /// Any other [`R1CSError`], or a panic.
+    Error = 9,
+}
+
+impl From<R1CSError> for BpStatus {
+    fn from(e: R1CSError) -> BpStatus {
+        match e {
+            R1CSError::InvalidGeneratorsLength | R1CSError::InsufficientGenerators { .. } => {
+                BpStatus::InvalidGeneratorsLength
+            }
+            R1CSError::FormatError => BpStatus::FormatError,
+            R1CSError::VerificationError
+            | R1CSError::InvalidPoint { .. }
+            | R1CSError::InvalidProofShape { .. }
+            | R1CSError::InvalidInnerProductLength { .. }
+            | R1CSError::LimitExceeded { .. } => BpStatus::VerificationError,
+            R1CSError::UnsatisfiedConstraint { .. } => BpStatus::UnsatisfiedConstraint,
+            _ => BpStatus::Error,
+        }
+    }
+}
+
+/// A variable of a constraint system.
+///
+/// The `kind` is 0 for a committed variable, 1, 2 and 3 for the left
+/// input, the right input and the output of a multiplier, and 4 for the
+/// constant 1, whose `index` is ignored.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BpVariable {
+    /// The kind of the variable.
+    pub kind: u32,
+    /// The index of the commitment or of the multiplier.
+    pub index: u64,
+}
+
+/// A term of a linear combination.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct BpTerm {
+    /// The variable of the term.
+    pub variable: BpVariable,
+    /// The coefficient of the variable, as a 32-byte scalar.
+    pub coefficient: [u8; SCALAR_LEN],
+}
+
+/// A byte buffer allocated by the library and owned by the caller.
+#[repr(C)]
+#[derive(Debug)]
+pub struct BpBuffer {
+    /// The bytes of the buffer.
+    pub data: *mut u8,
+    /// The number of bytes of the buffer.
+    pub len: usize,
+}
+
+impl BpBuffer {
+    fn new(bytes: Vec<u8>) -> BpBuffer {
+        let len = bytes.len();
+        let data = Box::into_raw(bytes.into_boxed_slice()) as *mut u8;
+        BpBuffer { data, len }
+    }
+}
+
+/// A prover building a constraint system, created by [`bp_prover_new`].
+pub struct BpProver {
+    prover: Prover<'static, Affine, Transcript>,
+    shape: Shape,
+}
+
+/// A verifier building a constraint system, created by [`bp_verifier_new`].
+pub struct BpVerifier {
+    verifier: Verifier<Affine, Transcript>,
+    shape: Shape,
+}
+
+/// The number of commitments and multipliers of a constraint system,
+/// against which the variables passed by the caller are checked.
+#[derive(Default)]
+struct Shape {
+    commitments: u64,
+    multipliers: u64,
+}
+
+impl Shape {
+    fn variable(&self, v: BpVariable) -> Result<Variable<Fr>, BpStatus> {
+        match v.kind {
+            0 if v.index < self.commitments => Ok(Variable::Committed(v.index as usize)),
+            1 if v.index < self.multipliers => Ok(Variable::MultiplierLeft(v.index as usize)),
+            2 if v.index < self.multipliers => Ok(Variable::MultiplierRight(v.index as usize)),
+            3 if v.index < self.multipliers => Ok(Variable::MultiplierOutput(v.index as usize)),
+            4 => Ok(Variable::One()),
+            _ => Err(BpStatus::InvalidVariable),
+        }
+    }
+
+    unsafe fn linear_combination(
+        &self,
+        terms: *const BpTerm,
+        len: usize,
+    ) -> Result<LinearCombination<Fr>, BpStatus> {
+        slice_from(terms, len)?
+            .iter()
+            .map(|term| Ok((self.variable(term.variable)?, scalar(&term.coefficient)?)))
+            .collect()
+    }
+
+    /// Records the multiplier whose inputs and output are `vars`, and
+    /// writes them to the 3 variables at `out`.
+    unsafe fn push_multiplier(
+        &mut self,
+        vars: (Variable<Fr>, Variable<Fr>, Variable<Fr>),
+        out: *mut BpVariable,
+    ) {
+        let index = match vars.0 {
+            Variable::MultiplierLeft(i) => i as u64,
+            _ => unreachable!("multipliers have a left input variable"),
+        };
+        self.multipliers = index + 1;
+        for (k, kind) in (1..=3).enumerate() {
+            *out.add(k) = BpVariable { kind, index };
+        }
+    }
+}
+
+fn pc_gens() -> &'static PedersenGens<Affine> {
+    static PC_GENS: OnceLock<PedersenGens<Affine>> = OnceLock::new();
+    PC_GENS.get_or_init(PedersenGens::default)
+}
+
+unsafe fn transcript(label: *const u8, label_len: usize) -> Result<Transcript, BpStatus> {
+    let mut transcript = Transcript::new(b"BulletproofsFFI");
+    transcript.append_message(b"label", slice_from(label, label_len)?);
+    Ok(transcript)
+}
+
+unsafe fn slice_from<'a, T>(data: *const T, len: usize) -> Result<&'a [T], BpStatus> {
+    if len == 0 {
+        Ok(&[])
+    } else if data.is_null() {
+        Err(BpStatus::NullPointer)
+    } else {
+        Ok(slice::from_raw_parts(data, len))
+    }
+}
+
+fn scalar(bytes: &[u8]) -> Result<Fr, BpStatus> {
+    Fr::deserialize_compressed(bytes).map_err(|_| BpStatus::InvalidScalar)
+}
+
+unsafe fn read_scalar(data: *const u8) -> Result<Fr, BpStatus> {
+    scalar(slice_from(data, SCALAR_LEN)?)
+}
+
+fn status(result: Result<(), BpStatus>) -> BpStatus {
+    result.err().unwrap_or(BpStatus::Ok)
+}
+
+/// Runs the body of an entry point, returning `default` if it panics.
+fn catch<T>(default: T, body: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or(default)
+}
+
+fn bp_gens(capacity: usize) -> Result<BulletproofGens<Affine>, BpStatus> {
+    if capacity > BP_MAX_GENS_CAPACITY {
+        return Err(BpStatus::InvalidGeneratorsLength);
+    }
+    Ok(BulletproofGens::new(capacity, 1))
+}
+
+macro_rules! handle {
+    ($ptr:expr) => {
+        match $ptr.as_mut() {
+            Some(handle) => handle,
+            None => return BpStatus::NullPointer,
+        }
+    };
+}
+
+/// Creates a prover whose transcript is bound to the `label_len` bytes
+/// at `label`.
+///
+/// Returns null if `label` is null and `label_len` is not zero.
+#[no_mangle]
+pub unsafe extern "C" fn bp_prover_new(label: *const u8, label_len: usize) -> *mut BpProver {
+    catch(ptr::null_mut(), || match transcript(label, label_len) {
+        Ok(transcript) => Box::into_raw(Box::new(BpProver {
+            prover: Prover::new(pc_gens(), transcript),
+            shape: Shape::default(),
+        })),
+        Err(_) => ptr::null_mut(),
+    })
+}
+
+/// Frees a prover that was not consumed by [`bp_prover_prove`].
+#[no_mangle]
+pub unsafe extern "C" fn bp_prover_free(prover: *mut BpProver) {
+    catch((), || {
+        if !prover.is_null() {
+            drop(Box::from_raw(prover));
+        }
+    })
+}
+
+/// Commits to the 32-byte scalar `value` with the 32-byte scalar
+/// `blinding`, writes the 33-byte commitment to `commitment` and the
+/// committed variable to `variable`.
+#[no_mangle]
+pub unsafe extern "C" fn bp_prover_commit(
+    prover: *mut BpProver,
+    value: *const u8,
+    blinding: *const u8,
+    commitment: *mut u8,
+    variable: *mut BpVariable,
+) -> BpStatus {
+    catch(BpStatus::Error, || {
+        let handle = handle!(prover);
+        if commitment.is_null() || variable.is_null() {
+            return BpStatus::NullPointer;
+        }
+        status((|| {
+            let (v, v_blinding) = (read_scalar(value)?, read_scalar(blinding)?);
+            let (point, _) = handle.prover.commit(v, v_blinding);
+            point
+                .serialize_compressed(slice::from_raw_parts_mut(commitment, POINT_LEN))
+                .map_err(|_| BpStatus::InvalidPoint)?;
+            *variable = BpVariable {
+                kind: 0,
+                index: handle.shape.commitments,
+            };
+            handle.shape.commitments += 1;
+            Ok(())
+        })())
+    })
+}
+
+/// Allocates a multiplier with the 32-byte scalars `left` and `right`
+/// as inputs, and writes its left input, right input and output to the
+/// 3 variables at `variables`.
+#[no_mangle]
+pub unsafe extern "C" fn bp_prover_allocate_multiplier(
+    prover: *mut BpProver,
+    left: *const u8,
+    right: *const u8,
+    variables: *mut BpVariable,
+) -> BpStatus {
+    catch(BpStatus::Error, || {
+        let handle = handle!(prover);
+        if variables.is_null() {
+            return BpStatus::NullPointer;
+        }
+        status((|| {
+            let assignments = (read_scalar(left)?, read_scalar(right)?);
+            let vars = handle.prover.allocate_multiplier(Some(assignments))?;
+            handle.shape.push_multiplier(vars, variables);
+            Ok(())
+        })())
+    })
+}
+
+/// Multiplies the linear combinations of `left_len` terms at `left`
+/// and `right_len` terms at `right`, and writes the left input, right
+/// input and output of the multiplier to the 3 variables at `variables`.
+#[no_mangle]
+pub unsafe extern "C" fn bp_prover_multiply(
+    prover: *mut BpProver,
+    left: *const BpTerm,
+    left_len: usize,
+    right: *const BpTerm,
+    right_len: usize,
+    variables: *mut BpVariable,
+) -> BpStatus {
+    catch(BpStatus::Error, || {
+        let handle = handle!(prover);
+        if variables.is_null() {
+            return BpStatus::NullPointer;
+        }
+        status((|| {
+            let left = handle.shape.linear_combination(left, left_len)?;
+            let right = handle.shape.linear_combination(right, right_len)?;
+            let vars = handle.prover.multiply(left, right);
+            handle.shape.push_multiplier(vars, variables);
+            Ok(())
+        })())
+    })
+}
+
+/// Constrains the linear combination of `len` terms at `terms` to be zero.
+#[no_mangle]
+pub unsafe extern "C" fn bp_prover_constrain(
+    prover: *mut BpProver,
+    terms: *const BpTerm,
+    len: usize,
+) -> BpStatus {
+    catch(BpStatus::Error, || {
+        let handle = handle!(prover);
+        status((|| {
+            let lc = handle.shape.linear_combination(terms, len)?;
+            handle.prover.constrain(lc);
+            Ok(())
+        })())
+    })
+}
+
+/// Consumes the prover to create a proof with generators of capacity
+/// `gens_capacity`, which must be at least the number of multipliers
+/// and at most [`BP_MAX_GENS_CAPACITY`], and writes the serialized proof
+/// to `proof`.
+///
+/// The prover is freed even if proving fails.
+#[no_mangle]
+pub unsafe extern "C" fn bp_prover_prove(
+    prover: *mut BpProver,
+    gens_capacity: usize,
+    proof: *mut BpBuffer,
+) -> BpStatus {
+    catch(BpStatus::Error, || {
+        if prover.is_null() {
+            return BpStatus::NullPointer;
+        }
+        let handle = Box::from_raw(prover);
+        if proof.is_null() {
+            return BpStatus::NullPointer;
+        }
+        status((|| {
+            let bp_gens = bp_gens(gens_capacity)?;
+            let bytes = handle
+                .prover
+                .prove(&mut rand::thread_rng(), &bp_gens)?
+                .to_bytes()
+                .map_err(|_| BpStatus::FormatError)?;
+            *proof = BpBuffer::new(bytes);
+            Ok(())
+        })())
+    })
+}
+
+/// Creates a verifier whose transcript is bound to the `label_len`
+/// bytes at `label`.
+///
+/// Returns null if `label` is null and `label_len` is not zero.
+#[no_mangle]
+pub unsafe extern "C" fn bp_verifier_new(label: *const u8, label_len: usize) -> *mut BpVerifier {
+    catch(ptr::null_mut(), || match transcript(label, label_len) {
+        Ok(transcript) => Box::into_raw(Box::new(BpVerifier {
+            verifier: Verifier::new(transcript),
+            shape: Shape::default(),
+        })),
+        Err(_) => ptr::null_mut(),
+    })
+}
+
+/// Frees a verifier that was not consumed by [`bp_verifier_verify`].
+#[no_mangle]
+pub unsafe extern "C" fn bp_verifier_free(verifier: *mut BpVerifier) {
+    catch((), || {
+        if !verifier.is_null() {
+            drop(Box::from_raw(verifier));
+        }
+    })
+}
+
+/// Adds the 33-byte `commitment` and writes its variable to `variable`.
+#[no_mangle]
+pub unsafe extern "C" fn bp_verifier_commit(
+    verifier: *mut BpVerifier,
+    commitment: *const u8,
+    variable: *mut BpVariable,
+) -> BpStatus {
+    catch(BpStatus::Error, || {
+        let handle = handle!(verifier);
+        if variable.is_null() {
+            return BpStatus::NullPointer;
+        }
+        status((|| {
+            let bytes = slice_from(commitment, POINT_LEN)?;
+            let point =
+                Affine::deserialize_compressed(bytes).map_err(|_| BpStatus::InvalidPoint)?;
+            handle.verifier.commit(point);
+            *variable = BpVariable {
+                kind: 0,
+                index: handle.shape.commitments,
+            };
+            handle.shape.commitments += 1;
+            Ok(())
+        })())
+    })
+}
+
+/// Allocates a multiplier, and writes its left input, right input and
+/// output to the 3 variables at `variables`.
+#[no_mangle]
+pub unsafe extern "C" fn bp_verifier_allocate_multiplier(
+    verifier: *mut BpVerifier,
+    variables: *mut BpVariable,
+) -> BpStatus {
+    catch(BpStatus::Error, || {
+        let handle = handle!(verifier);
+        if variables.is_null() {
+            return BpStatus::NullPointer;
+        }
+        status((|| {
+            let vars = handle.verifier.allocate_multiplier(None)?;
+            handle.shape.push_multiplier(vars, variables);
+            Ok(())
+        })())
+    })
+}
+
+/// Same as [`bp_prover_multiply`], for the verifier.
+#[no_mangle]
+pub unsafe extern "C" fn bp_verifier_multiply(
+    verifier: *mut BpVerifier,
+    left: *const BpTerm,
+    left_len: usize,
+    right: *const BpTerm,
+    right_len: usize,
+    variables: *mut BpVariable,
+) -> BpStatus {
+    catch(BpStatus::Error, || {
+        let handle = handle!(verifier);
+        if variables.is_null() {
+            return BpStatus::NullPointer;
+        }
+        status((|| {
+            let left = handle.shape.linear_combination(left, left_len)?;
+            let right = handle.shape.linear_combination(right, right_len)?;
+            let vars = handle.verifier.multiply(left, right);
+            handle.shape.push_multiplier(vars, variables);
+            Ok(())
+        })())
+    })
+}
+
+/// Same as [`bp_prover_constrain`], for the verifier.
+#[no_mangle]
+pub unsafe extern "C" fn bp_verifier_constrain(
+    verifier: *mut BpVerifier,
+    terms: *const BpTerm,
+    len: usize,
+) -> BpStatus {
+    catch(BpStatus::Error, || {
+        let handle = handle!(verifier);
+        status((|| {
+            let lc = handle.shape.linear_combination(terms, len)?;
+            handle.verifier.constrain(lc);
+            Ok(())
+        })())
+    })
+}
+
+/// Consumes the verifier to verify the serialized proof of `proof_len`
+/// bytes at `proof`, with generators of capacity `gens_capacity`, at
+/// most [`BP_MAX_GENS_CAPACITY`].
+///
+/// Returns [`BpStatus::Ok`] if the proof is valid.  The verifier is
+/// freed even if verification fails.
+#[no_mangle]
+pub unsafe extern "C" fn bp_verifier_verify(
+    verifier: *mut BpVerifier,
+    gens_capacity: usize,
+    proof: *const u8,
+    proof_len: usize,
+) -> BpStatus {
+    catch(BpStatus::Error, || {
+        if verifier.is_null() {
+            return BpStatus::NullPointer;
+        }
+        let handle = Box::from_raw(verifier);
+        status((|| {
+            let bp_gens = bp_gens(gens_capacity)?;
+            let proof = R1CSProof::from_bytes(slice_from(proof, proof_len)?)?;
+            handle.verifier.verify(&proof, pc_gens(), &bp_gens)?;
+            Ok(())
+        })())
+    })
+}
+
+/// Frees a buffer returned by the library.
+#[no_mangle]
+pub unsafe extern "C" fn bp_buffer_free(buffer: BpBuffer) {
+    catch((), || {
+        if !buffer.data.is_null() {
+            let data = ptr::slice_from_raw_parts_mut(buffer.data, buffer.len);
+            drop(Box::from_raw(data));
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode<S: Into<Fr>>(x: S) -> [u8; SCALAR_LEN] {
+        let mut bytes = [0u8; SCALAR_LEN];
+        x.into().serialize_compressed(&mut bytes[..]).unwrap();
+        bytes
+    }
+
+    fn term<S: Into<Fr>>(variable: BpVariable, coefficient: S) -> BpTerm {
+        BpTerm {
+            variable,
+            coefficient: encode(coefficient),
+        }
+    }
+
+    /// Proves that the committed `c` is the product of the committed `a`
+    /// and `b`, then verifies the proof against the commitments to
+    /// `(a, b, expected_c)`.
+    unsafe fn prove_and_verify(a: u64, b: u64, c: u64, expected_c: u64) -> BpStatus {
+        let label = b"FFITest";
+        let prover = bp_prover_new(label.as_ptr(), label.len());
+        let mut commitments = [[0u8; POINT_LEN]; 3];
+        let mut vars = [BpVariable { kind: 0, index: 0 }; 3];
+        for (i, value) in [a, b, c].iter().enumerate() {
+            let status = bp_prover_commit(
+                prover,
+                encode(*value).as_ptr(),
+                encode(i as u64 + 7).as_ptr(),
+                commitments[i].as_mut_ptr(),
+                &mut vars[i],
+            );
+            assert_eq!(status, BpStatus::Ok);
+        }
+        let mut mul = [BpVariable { kind: 0, index: 0 }; 3];
+        let (left, right) = ([term(vars[0], 1u64)], [term(vars[1], 1u64)]);
+        let status = bp_prover_multiply(
+            prover,
+            left.as_ptr(),
+            1,
+            right.as_ptr(),
+            1,
+            mul.as_mut_ptr(),
+        );
+        assert_eq!(status, BpStatus::Ok);
+        let lc = [term(mul[2], 1u64), term(vars[2], -Fr::from(1u64))];
+        assert_eq!(bp_prover_constrain(prover, lc.as_ptr(), 2), BpStatus::Ok);
+
+        let mut proof = BpBuffer {
+            data: ptr::null_mut(),
+            len: 0,
+        };
+        assert_eq!(bp_prover_prove(prover, 1, &mut proof), BpStatus::Ok);
+
+        let verifier = bp_verifier_new(label.as_ptr(), label.len());
+        let mut point = [0u8; POINT_LEN];
+        pc_gens()
+            .commit(Fr::from(expected_c), Fr::from(9u64))
+            .serialize_compressed(&mut point[..])
+            .unwrap();
+        commitments[2] = point;
+        for (i, commitment) in commitments.iter().enumerate() {
+            let status = bp_verifier_commit(verifier, commitment.as_ptr(), &mut vars[i]);
+            assert_eq!(status, BpStatus::Ok);
+        }
+        let status = bp_verifier_multiply(
+            verifier,
+            left.as_ptr(),
+            1,
+            right.as_ptr(),
+            1,
+            mul.as_mut_ptr(),
+        );
+        assert_eq!(status, BpStatus::Ok);
+        assert_eq!(
+            bp_verifier_constrain(verifier, lc.as_ptr(), 2),
+            BpStatus::Ok
+        );
+
+        let status = bp_verifier_verify(verifier, 1, proof.data, proof.len);
+        bp_buffer_free(proof);
+        status
+    }
+
+    #[test]
+    fn prove_and_verify_product() {
+        unsafe {
+            assert_eq!(prove_and_verify(3, 4, 12, 12), BpStatus::Ok);
+            assert_eq!(prove_and_verify(3, 4, 12, 13), BpStatus::VerificationError);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_inputs() {
+        unsafe {
+            let prover = bp_prover_new(ptr::null(), 0);
+            let lc = [term(BpVariable { kind: 0, index: 0 }, 1u64)];
+            assert_eq!(
+                bp_prover_constrain(prover, lc.as_ptr(), 1),
+                BpStatus::InvalidVariable
+            );
+            let mut point = [0u8; POINT_LEN];
+            let mut var = BpVariable { kind: 0, index: 0 };
+            let status = bp_prover_commit(
+                prover,
+                [0xffu8; SCALAR_LEN].as_ptr(),
+                encode(1u64).as_ptr(),
+                point.as_mut_ptr(),
+                &mut var,
+            );
+            assert_eq!(status, BpStatus::InvalidScalar);
+            assert_eq!(
+                bp_prover_constrain(prover, ptr::null(), 1),
+                BpStatus::NullPointer
+            );
+            bp_prover_free(prover);
+
+            let verifier = bp_verifier_new(ptr::null(), 0);
+            assert_eq!(
+                bp_verifier_commit(verifier, [0xffu8; POINT_LEN].as_ptr(), &mut var),
+                BpStatus::InvalidPoint
+            );
+            assert_eq!(
+                bp_verifier_verify(verifier, 1, [0u8; 4].as_ptr(), 4),
+                BpStatus::FormatError
+            );
+
+            let prover = bp_prover_new(ptr::null(), 0);
+            let mut proof = BpBuffer {
+                data: ptr::null_mut(),
+                len: 0,
+            };
+            assert_eq!(
+                bp_prover_prove(prover, usize::MAX, &mut proof),
+                BpStatus::InvalidGeneratorsLength
+            );
+            let verifier = bp_verifier_new(ptr::null(), 0);
+            assert_eq!(
+                bp_verifier_verify(verifier, BP_MAX_GENS_CAPACITY + 1, ptr::null(), 0),
+                BpStatus::InvalidGeneratorsLength
+            );
+        }
+    }
+
+    #[test]
+    fn catches_panics() {
+        assert_eq!(
+            catch(BpStatus::Error, || panic!("in the library")),
+            BpStatus::Error
+        );
+        assert_eq!(catch(BpStatus::Error, || BpStatus::Ok), BpStatus::Ok);
+    }
+}
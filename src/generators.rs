@@ -6,15 +6,21 @@
 
 extern crate alloc;
 
+use alloc::sync::Arc;
 use ark_ec::{AffineRepr, CurveGroup};
-use ark_ff::PrimeField;
-use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_ff::Zero;
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, SerializationError, Valid, Validate,
+};
+use ark_std::io::{Read, Write};
 use ark_std::marker::PhantomData;
-use ark_std::{ops::Add, rand::SeedableRng, vec::Vec};
+use ark_std::{rand::SeedableRng, vec, vec::Vec};
 use digest::Digest;
 use rand_chacha::ChaChaRng;
 use sha3::Sha3_512;
 
+use crate::msm::{AdaptiveMsm, FixedBaseTable, MsmBackend};
+
 /// Represents a pair of base points for Pedersen commitments.
 ///
 /// The Bulletproofs implementation and API is designed to support
@@ -36,11 +42,15 @@ pub struct PedersenGens<G: AffineRepr> {
 
 impl<G: AffineRepr> PedersenGens<G> {
     /// Creates a Pedersen commitment using the value scalar and a blinding factor.
+    ///
+    /// With the `ct` feature, the commitment is computed in time
+    /// independent of the value and of the blinding factor.
     pub fn commit(&self, value: G::ScalarField, blinding: G::ScalarField) -> G {
-        self.B
-            .mul_bigint(value.into_bigint())
-            .add(self.B_blinding.mul_bigint(blinding.into_bigint()))
-            .into_affine()
+        #[cfg(feature = "ct")]
+        let commitment = crate::ct::msm(&[self.B, self.B_blinding], &[value, blinding]);
+        #[cfg(not(feature = "ct"))]
+        let commitment = self.B * value + self.B_blinding * blinding;
+        commitment.into_affine()
     }
 }
 
@@ -146,7 +156,12 @@ impl<G: AffineRepr> Iterator for GeneratorsChain<G> {
 /// chain, and even forward-compatible to multiparty aggregation of
 /// constraint system proofs, since the generators are namespaced by
 /// their party index.
-#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+///
+/// The generators also carry the [`MsmBackend`] used by the proofs
+/// created and verified with them, and optionally fixed-base tables of
+/// the generators (see [`BulletproofGens::with_fixed_base_tables`]),
+/// neither of which is serialized.
+#[derive(Clone)]
 pub struct BulletproofGens<G: AffineRepr> {
     /// The maximum number of usable generators for each party.
     pub gens_capacity: usize,
@@ -156,6 +171,49 @@ pub struct BulletproofGens<G: AffineRepr> {
     G_vec: Vec<Vec<G>>,
     /// Precomputed \\(\mathbf H\\) generators for each party.
     H_vec: Vec<Vec<G>>,
+    /// Backend of the multiscalar multiplications of the proofs.
+    msm_backend: Arc<dyn MsmBackend<G>>,
+    /// Precomputed tables of the Pedersen bases and the generators.
+    tables: Option<Arc<FixedBaseTables<G>>>,
+}
+
+/// The fixed-base tables of the Pedersen bases `[B, B_blinding]` and of
+/// the \\(\mathbf G\\) and \\(\mathbf H\\) generators of each party.
+#[derive(Clone)]
+struct FixedBaseTables<G: AffineRepr> {
+    pedersen: (G, G),
+    pedersen_table: FixedBaseTable<G>,
+    G_tables: Vec<FixedBaseTable<G>>,
+    H_tables: Vec<FixedBaseTable<G>>,
+}
+
+/// The scalars of the terms of the fixed bases of a multiscalar
+/// multiplication: the Pedersen bases, and the generators
+/// `start..start + n` of each of `parties` parties, where `n` is
+/// `g.len() / parties`.  The scalars `h` of the \\(\mathbf H\\)
+/// generators are either empty or as many as `g`.
+pub(crate) struct FixedTerms<'a, F> {
+    pub(crate) b: F,
+    pub(crate) b_blinding: F,
+    pub(crate) g: &'a [F],
+    pub(crate) h: &'a [F],
+    pub(crate) start: usize,
+    pub(crate) parties: usize,
+}
+
+impl<'a, F: Zero> FixedTerms<'a, F> {
+    /// Returns the terms of a single-party commitment to `g` and `h`,
+    /// over the generators from `start`, blinded with `b_blinding`.
+    pub(crate) fn blinded(b_blinding: F, g: &'a [F], h: &'a [F], start: usize) -> Self {
+        FixedTerms {
+            b: F::zero(),
+            b_blinding,
+            g,
+            h,
+            start,
+            parties: 1,
+        }
+    }
 }
 
 impl<G: AffineRepr> BulletproofGens<G> {
@@ -177,11 +235,148 @@ impl<G: AffineRepr> BulletproofGens<G> {
             party_capacity,
             G_vec: (0..party_capacity).map(|_| Vec::new()).collect(),
             H_vec: (0..party_capacity).map(|_| Vec::new()).collect(),
+            msm_backend: Arc::new(AdaptiveMsm),
+            tables: None,
         };
         gens.increase_capacity(gens_capacity);
         gens
     }
 
+    /// Replaces the backend of the multiscalar multiplications of the
+    /// proofs created and verified with these generators, e.g. to offload
+    /// them to a GPU.  The default backend is [`AdaptiveMsm`].
+    pub fn with_msm_backend<B: MsmBackend<G> + 'static>(mut self, backend: B) -> Self {
+        self.msm_backend = Arc::new(backend);
+        self
+    }
+
+    /// Returns the backend of the multiscalar multiplications of the
+    /// proofs created and verified with these generators.
+    pub fn msm_backend(&self) -> &dyn MsmBackend<G> {
+        self.msm_backend.as_ref()
+    }
+
+    /// Precomputes fixed-base tables of the generators and of the
+    /// Pedersen bases of `pc_gens`, which the provers and verifiers using
+    /// these generators with `pc_gens` use for the terms of these bases,
+    /// instead of the [`MsmBackend`].
+    ///
+    /// The scalars are split in digits of `window` bits, between 2 and
+    /// 16, and each term costs one addition per digit.  The tables take
+    /// \\((\lfloor 256 / w \rfloor + 1) \cdot 2^{w - 1}\\) points per
+    /// generator for 256-bit scalars, e.g. 520 points for a window of 4
+    /// bits, so they are only worth their memory when many proofs are
+    /// created or verified with the same generators.
+    pub fn with_fixed_base_tables(mut self, pc_gens: &PedersenGens<G>, window: usize) -> Self {
+        let table = |bases: &[G]| FixedBaseTable::new(bases, window);
+        self.tables = Some(Arc::new(FixedBaseTables {
+            pedersen: (pc_gens.B, pc_gens.B_blinding),
+            pedersen_table: table(&[pc_gens.B, pc_gens.B_blinding]),
+            G_tables: self.G_vec.iter().map(|G_j| table(G_j)).collect(),
+            H_tables: self.H_vec.iter().map(|H_j| table(H_j)).collect(),
+        }));
+        self
+    }
+
+    /// Returns the multiscalar multiplication of the `fixed` terms and
+    /// of the `scalars` and `bases`, using the fixed-base tables for the
+    /// former if they were computed for `pc_gens`, and otherwise
+    /// borrowing the generators without copying them.
+    pub(crate) fn fixed_msm(
+        &self,
+        pc_gens: &PedersenGens<G>,
+        fixed: FixedTerms<'_, G::ScalarField>,
+        bases: &[G],
+        scalars: &[G::ScalarField],
+    ) -> G::Group {
+        trace_span!(
+            "msm",
+            terms = 2 + fixed.g.len() + fixed.h.len() + scalars.len()
+        );
+        let n = fixed.g.len() / fixed.parties;
+        let range = fixed.start..fixed.start + n;
+        let parties = 0..fixed.parties;
+
+        if let Some(tables) = self.fixed_base_tables(pc_gens) {
+            let mut result = tables
+                .pedersen_table
+                .msm([(0, fixed.b), (1, fixed.b_blinding)]);
+            if n > 0 {
+                for (j, g_j) in parties.clone().zip(fixed.g.chunks(n)) {
+                    result += tables.G_tables[j].msm(range.clone().zip(g_j.iter().copied()));
+                }
+                for (j, h_j) in parties.zip(fixed.h.chunks(n)) {
+                    result += tables.H_tables[j].msm(range.clone().zip(h_j.iter().copied()));
+                }
+            }
+            if !bases.is_empty() {
+                result += self.msm_backend.msm(bases, scalars);
+            }
+            return result;
+        }
+
+        // The generators of every party are borrowed in place.
+        let pedersen_bases = [pc_gens.B, pc_gens.B_blinding];
+        let pedersen_scalars = [fixed.b, fixed.b_blinding];
+        let mut terms = vec![
+            (&pedersen_bases[..], &pedersen_scalars[..]),
+            (bases, scalars),
+        ];
+        if n > 0 {
+            for (j, g_j) in parties.clone().zip(fixed.g.chunks(n)) {
+                terms.push((&self.G_vec[j][range.clone()], g_j));
+            }
+            for (j, h_j) in parties.zip(fixed.h.chunks(n)) {
+                terms.push((&self.H_vec[j][range.clone()], h_j));
+            }
+        }
+        self.msm_backend.msm_slices(&terms)
+    }
+
+    /// Returns the multiscalar multiplication of the `fixed` terms, whose
+    /// scalars are secret, as [`fixed_msm`](Self::fixed_msm), or in
+    /// constant time with the `ct` feature.
+    pub(crate) fn secret_fixed_msm(
+        &self,
+        pc_gens: &PedersenGens<G>,
+        fixed: FixedTerms<'_, G::ScalarField>,
+    ) -> G::Group {
+        #[cfg(feature = "ct")]
+        {
+            let n = fixed.g.len() / fixed.parties;
+            let range = fixed.start..fixed.start + n;
+            let mut bases = vec![pc_gens.B, pc_gens.B_blinding];
+            let mut scalars = vec![fixed.b, fixed.b_blinding];
+            if n > 0 {
+                for (j, g_j) in fixed.g.chunks(n).enumerate() {
+                    bases.extend_from_slice(&self.G_vec[j][range.clone()]);
+                    scalars.extend_from_slice(g_j);
+                }
+                for (j, h_j) in fixed.h.chunks(n).enumerate() {
+                    bases.extend_from_slice(&self.H_vec[j][range.clone()]);
+                    scalars.extend_from_slice(h_j);
+                }
+            }
+            let result = crate::ct::msm(&bases, &scalars);
+            zeroize::Zeroize::zeroize(&mut scalars);
+            result
+        }
+        #[cfg(not(feature = "ct"))]
+        self.fixed_msm(pc_gens, fixed, &[], &[])
+    }
+
+    /// Returns whether fixed-base tables were computed for `pc_gens`.
+    pub(crate) fn has_fixed_base_tables(&self, pc_gens: &PedersenGens<G>) -> bool {
+        self.fixed_base_tables(pc_gens).is_some()
+    }
+
+    /// Returns the fixed-base tables, if they were computed for `pc_gens`.
+    fn fixed_base_tables(&self, pc_gens: &PedersenGens<G>) -> Option<&FixedBaseTables<G>> {
+        self.tables
+            .as_deref()
+            .filter(|tables| tables.pedersen == (pc_gens.B, pc_gens.B_blinding))
+    }
+
     /// Returns j-th share of generators, with an appropriate
     /// slice of vectors G and H for the j-th range proof.
     pub fn share(&self, j: usize) -> BulletproofGensShare<'_, G> {
@@ -217,6 +412,14 @@ impl<G: AffineRepr> BulletproofGens<G> {
                     .take(new_capacity - self.gens_capacity),
             );
         }
+        if let Some(tables) = self.tables.as_mut().map(Arc::make_mut) {
+            for (table, G_j) in tables.G_tables.iter_mut().zip(&self.G_vec) {
+                table.extend(&G_j[table.len()..]);
+            }
+            for (table, H_j) in tables.H_tables.iter_mut().zip(&self.H_vec) {
+                table.extend(&H_j[table.len()..]);
+            }
+        }
         self.gens_capacity = new_capacity;
     }
 
@@ -243,6 +446,53 @@ impl<G: AffineRepr> BulletproofGens<G> {
     }
 }
 
+impl<G: AffineRepr> CanonicalSerialize for BulletproofGens<G> {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.gens_capacity
+            .serialize_with_mode(&mut writer, compress)?;
+        self.party_capacity
+            .serialize_with_mode(&mut writer, compress)?;
+        self.G_vec.serialize_with_mode(&mut writer, compress)?;
+        self.H_vec.serialize_with_mode(&mut writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.gens_capacity.serialized_size(compress)
+            + self.party_capacity.serialized_size(compress)
+            + self.G_vec.serialized_size(compress)
+            + self.H_vec.serialized_size(compress)
+    }
+}
+
+impl<G: AffineRepr> Valid for BulletproofGens<G> {
+    fn check(&self) -> Result<(), SerializationError> {
+        self.G_vec.check()?;
+        self.H_vec.check()
+    }
+}
+
+impl<G: AffineRepr> CanonicalDeserialize for BulletproofGens<G> {
+    /// Deserializes the generators, with the default [`AdaptiveMsm`] backend.
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        Ok(BulletproofGens {
+            gens_capacity: usize::deserialize_with_mode(&mut reader, compress, validate)?,
+            party_capacity: usize::deserialize_with_mode(&mut reader, compress, validate)?,
+            G_vec: Vec::deserialize_with_mode(&mut reader, compress, validate)?,
+            H_vec: Vec::deserialize_with_mode(&mut reader, compress, validate)?,
+            msm_backend: Arc::new(AdaptiveMsm),
+            tables: None,
+        })
+    }
+}
+
 struct AggregatedGensIter<'a, G: AffineRepr> {
     array: &'a Vec<Vec<G>>,
     n: usize,
@@ -307,6 +557,22 @@ impl<'a, G: AffineRepr> BulletproofGensShare<'a, G> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn serialization_roundtrip() {
+        type G = ark_secq256k1::Affine;
+
+        let gens = BulletproofGens::<G>::new(16, 2);
+        let mut bytes = Vec::new();
+        gens.serialize_compressed(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), gens.compressed_size());
+
+        let decoded = BulletproofGens::<G>::deserialize_compressed(&bytes[..]).unwrap();
+        assert_eq!(decoded.gens_capacity, 16);
+        assert_eq!(decoded.party_capacity, 2);
+        assert!(decoded.G(16, 2).eq(gens.G(16, 2)));
+        assert!(decoded.H(16, 2).eq(gens.H(16, 2)));
+    }
+
     #[test]
     fn aggregated_gens_iter_matches_flat_map() {
         type G = ark_secq256k1::Affine;
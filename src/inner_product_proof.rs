@@ -9,9 +9,13 @@ use ark_std::{
     ops::{MulAssign, Neg},
     vec::Vec,
 };
-use merlin::Transcript;
+use ark_std::{cfg_iter, cfg_iter_mut};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use zeroize::Zeroize;
 
 use crate::errors::ProofError;
+use crate::msm::{secret_msm, MsmBackend};
 use crate::transcript::TranscriptProtocol;
 
 #[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
@@ -33,9 +37,14 @@ impl<G: AffineRepr> InnerProductProof<G> {
     /// protocols).
     ///
     /// The lengths of the vectors must all be the same, and must all be
-    /// either 0 or a power of 2.
-    pub fn create(
-        transcript: &mut Transcript,
+    /// either 0 or a power of 2.  The multiscalar multiplications of the
+    /// rounds are computed with `msm`, or in constant time with the `ct`
+    /// feature, and `on_round` is called after
+    /// every round with the numbers of rounds done and of all rounds.
+    // The owned vectors are folded in place, so they do not fit a struct of inputs.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create<Tr: TranscriptProtocol<G> + ?Sized>(
+        transcript: &mut Tr,
         Q: &G,
         G_factors: &[G::ScalarField],
         H_factors: &[G::ScalarField],
@@ -43,7 +52,10 @@ impl<G: AffineRepr> InnerProductProof<G> {
         mut H_vec: Vec<G>,
         mut a_vec: Vec<G::ScalarField>,
         mut b_vec: Vec<G::ScalarField>,
+        msm: &dyn MsmBackend<G>,
+        on_round: &mut dyn FnMut(usize, usize),
     ) -> InnerProductProof<G> {
+        trace_span!("inner_product_proof", n = G_vec.len());
         // Create slices G, H, a, b backed by their respective
         // vectors.  This lets us reslice as we compress the lengths
         // of the vectors in the main loop below.
@@ -65,7 +77,7 @@ impl<G: AffineRepr> InnerProductProof<G> {
         // All of the input vectors must have a length that is a power of two.
         assert!(n.is_power_of_two());
 
-        <Transcript as TranscriptProtocol<G>>::innerproduct_domain_sep(transcript, n as u64);
+        TranscriptProtocol::<G>::innerproduct_domain_sep(transcript, n as u64);
 
         let lg_n = n.next_power_of_two().trailing_zeros() as usize;
         let mut L_vec = Vec::with_capacity(lg_n);
@@ -101,7 +113,7 @@ impl<G: AffineRepr> InnerProductProof<G> {
                 .chain(iter::once(c_L))
                 .collect::<Vec<G::ScalarField>>();
 
-            let L = G::Group::msm(&bases, &scalars).unwrap();
+            let L = secret_msm(msm, &bases, &scalars);
 
             let bases = G_L
                 .iter()
@@ -121,7 +133,7 @@ impl<G: AffineRepr> InnerProductProof<G> {
                 .chain(iter::once(c_R))
                 .collect::<Vec<G::ScalarField>>();
 
-            let R = G::Group::msm(&bases, &scalars).unwrap();
+            let R = secret_msm(msm, &bases, &scalars);
 
             let L = L.into_affine();
             let R = R.into_affine();
@@ -132,33 +144,20 @@ impl<G: AffineRepr> InnerProductProof<G> {
             transcript.append_point(b"L", &L);
             transcript.append_point(b"R", &R);
 
-            let u = <Transcript as TranscriptProtocol<G>>::challenge_scalar(transcript, b"u");
+            let u = TranscriptProtocol::<G>::challenge_scalar(transcript, b"u");
 
             let u_inv = u.inverse().unwrap();
 
-            for i in 0..n {
-                a_L[i] = a_L[i] * u + u_inv * a_R[i];
-                b_L[i] = b_L[i] * u_inv + u * b_R[i];
-
-                G_L[i] = G::Group::msm(
-                    &[G_L[i], G_R[i]],
-                    &[u_inv * G_factors[i], u * G_factors[n + i]],
-                )
-                .unwrap()
-                .into_affine();
-
-                H_L[i] = G::Group::msm(
-                    &[H_L[i], H_R[i]],
-                    &[u * H_factors[i], u_inv * H_factors[n + i]],
-                )
-                .unwrap()
-                .into_affine();
-            }
+            fold_scalars(a_L, a_R, u, u_inv);
+            fold_scalars(b_L, b_R, u_inv, u);
+            fold_points(G_L, G_R, |i| (u_inv * G_factors[i], u * G_factors[n + i]));
+            fold_points(H_L, H_R, |i| (u * H_factors[i], u_inv * H_factors[n + i]));
 
             a = a_L;
             b = b_L;
             G = G_L;
             H = H_L;
+            on_round(L_vec.len(), lg_n);
         }
 
         while n != 1 {
@@ -184,7 +183,7 @@ impl<G: AffineRepr> InnerProductProof<G> {
                 .map(|f| *f)
                 .collect::<Vec<G::ScalarField>>();
 
-            let L = G::Group::msm(&bases, &scalars).unwrap();
+            let L = secret_msm(msm, &bases, &scalars);
 
             let bases = G_L
                 .iter()
@@ -199,7 +198,7 @@ impl<G: AffineRepr> InnerProductProof<G> {
                 .map(|f| *f)
                 .collect::<Vec<G::ScalarField>>();
 
-            let R = G::Group::msm(&bases, &scalars).unwrap();
+            let R = secret_msm(msm, &bases, &scalars);
 
             let L = L.into_affine();
             let R = R.into_affine();
@@ -210,41 +209,40 @@ impl<G: AffineRepr> InnerProductProof<G> {
             transcript.append_point(b"L", &L);
             transcript.append_point(b"R", &R);
 
-            let u = <Transcript as TranscriptProtocol<G>>::challenge_scalar(transcript, b"u");
+            let u = TranscriptProtocol::<G>::challenge_scalar(transcript, b"u");
             let u_inv = u.inverse().unwrap();
 
-            for i in 0..n {
-                a_L[i] = a_L[i] * u + u_inv * a_R[i];
-                b_L[i] = b_L[i] * u_inv + u * b_R[i];
-                G_L[i] = G::Group::msm(&[G_L[i], G_R[i]], &[u_inv, u])
-                    .unwrap()
-                    .into_affine();
-                H_L[i] = G::Group::msm(&[H_L[i], H_R[i]], &[u, u_inv])
-                    .unwrap()
-                    .into_affine()
-            }
+            fold_scalars(a_L, a_R, u, u_inv);
+            fold_scalars(b_L, b_R, u_inv, u);
+            fold_points(G_L, G_R, |_| (u_inv, u));
+            fold_points(H_L, H_R, |_| (u, u_inv));
 
             a = a_L;
             b = b_L;
             G = G_L;
             H = H_L;
+            on_round(L_vec.len(), lg_n);
         }
 
-        InnerProductProof {
+        let proof = InnerProductProof {
             L_vec,
             R_vec,
             a: a[0],
             b: b[0],
-        }
+        };
+        // The folded vectors are secret, but for their last entries.
+        a_vec.zeroize();
+        b_vec.zeroize();
+        proof
     }
 
     /// Computes three vectors of verification scalars \\([u\_{i}^{2}]\\), \\([u\_{i}^{-2}]\\) and \\([s\_{i}]\\) for combined multiscalar multiplication
     /// in a parent protocol. See [inner product protocol notes](index.html#verification-equation) for details.
     /// The verifier must provide the input length \\(n\\) explicitly to avoid unbounded allocation within the inner product proof.
-    pub(crate) fn verification_scalars(
+    pub(crate) fn verification_scalars<Tr: TranscriptProtocol<G> + ?Sized>(
         &self,
         n: usize,
-        transcript: &mut Transcript,
+        transcript: &mut Tr,
     ) -> Result<
         (
             Vec<G::ScalarField>,
@@ -253,6 +251,18 @@ impl<G: AffineRepr> InnerProductProof<G> {
         ),
         ProofError,
     > {
+        let challenges = self.verification_challenges(n, transcript)?;
+        Ok(Self::scalars_from_challenges(challenges, n))
+    }
+
+    /// Recomputes the challenges \\(u\_k,\ldots,u\_1\\) from the proof
+    /// transcript, the only part of [`verification_scalars`](Self::verification_scalars)
+    /// which depends on the transcript.
+    pub(crate) fn verification_challenges<Tr: TranscriptProtocol<G> + ?Sized>(
+        &self,
+        n: usize,
+        transcript: &mut Tr,
+    ) -> Result<Vec<G::ScalarField>, ProofError> {
         let lg_n = self.L_vec.len();
         if lg_n >= 32 {
             // 4 billion multiplications should be enough for anyone
@@ -263,20 +273,31 @@ impl<G: AffineRepr> InnerProductProof<G> {
             return Err(ProofError::VerificationError);
         }
 
-        <Transcript as TranscriptProtocol<G>>::innerproduct_domain_sep(transcript, n as u64);
-
-        // 1. Recompute x_k,...,x_1 based on the proof transcript
+        TranscriptProtocol::<G>::innerproduct_domain_sep(transcript, n as u64);
 
         let mut challenges = Vec::with_capacity(lg_n);
         for (L, R) in self.L_vec.iter().zip(self.R_vec.iter()) {
             transcript.validate_and_append_point(b"L", L)?;
             transcript.validate_and_append_point(b"R", R)?;
-            challenges.push(<Transcript as TranscriptProtocol<G>>::challenge_scalar(
-                transcript, b"u",
-            ));
+            challenges.push(TranscriptProtocol::<G>::challenge_scalar(transcript, b"u"));
         }
+        Ok(challenges)
+    }
 
-        // 2. Compute 1/(u_k...u_1) and 1/u_k, ..., 1/u_1
+    /// Computes the verification scalars from the challenges returned by
+    /// [`verification_challenges`](Self::verification_challenges).
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn scalars_from_challenges(
+        mut challenges: Vec<G::ScalarField>,
+        n: usize,
+    ) -> (
+        Vec<G::ScalarField>,
+        Vec<G::ScalarField>,
+        Vec<G::ScalarField>,
+    ) {
+        let lg_n = challenges.len();
+
+        // 1. Compute 1/(u_k...u_1) and 1/u_k, ..., 1/u_1
 
         let mut challenges_inv = challenges.clone();
 
@@ -287,7 +308,7 @@ impl<G: AffineRepr> InnerProductProof<G> {
             allinv.mul_assign(f);
         }
 
-        // 3. Compute u_i^2 and (1/u_i)^2
+        // 2. Compute u_i^2 and (1/u_i)^2
 
         for i in 0..lg_n {
             // XXX missing square fn upstream
@@ -297,7 +318,7 @@ impl<G: AffineRepr> InnerProductProof<G> {
         let challenges_sq = challenges;
         let challenges_inv_sq = challenges_inv;
 
-        // 4. Compute s values inductively.
+        // 3. Compute s values inductively.
 
         let mut s = Vec::with_capacity(n);
         s.push(allinv);
@@ -310,7 +331,7 @@ impl<G: AffineRepr> InnerProductProof<G> {
             s.push(s[i - k] * u_lg_i_sq);
         }
 
-        Ok((challenges_sq, challenges_inv_sq, s))
+        (challenges_sq, challenges_inv_sq, s)
     }
 
     /// This method is for testing that proof generation work,
@@ -318,10 +339,10 @@ impl<G: AffineRepr> InnerProductProof<G> {
     /// method to combine inner product verification with other checks
     /// in a single multiscalar multiplication.
     #[allow(dead_code)]
-    pub fn verify<IG, IH>(
+    pub fn verify<Tr, IG, IH>(
         &self,
         n: usize,
-        transcript: &mut Transcript,
+        transcript: &mut Tr,
         G_factors: IG,
         H_factors: IH,
         P: &G,
@@ -330,6 +351,7 @@ impl<G: AffineRepr> InnerProductProof<G> {
         H: &[G],
     ) -> Result<(), ProofError>
     where
+        Tr: TranscriptProtocol<G> + ?Sized,
         IG: IntoIterator,
         IG::Item: Borrow<G::ScalarField>,
         IH: IntoIterator,
@@ -388,14 +410,40 @@ impl<G: AffineRepr> InnerProductProof<G> {
 /// \\]
 /// Panics if the lengths of \\(\mathbf{a}\\) and \\(\mathbf{b}\\) are not equal.
 pub fn inner_product<F: PrimeField>(a: &[F], b: &[F]) -> F {
-    let mut out = F::zero();
     if a.len() != b.len() {
         panic!("inner_product(a,b): lengths of vectors do not match");
     }
-    for i in 0..a.len() {
-        out += a[i] * b[i];
-    }
-    out
+    cfg_iter!(a)
+        .zip(cfg_iter!(b))
+        .map(|(a_i, b_i)| *a_i * b_i)
+        .sum()
+}
+
+/// Folds the halves of a vector of scalars as
+/// \\(\mathbf{lo}\_i \gets \mathbf{lo}\_i \cdot x + \mathbf{hi}\_i \cdot y\\).
+fn fold_scalars<F: PrimeField>(lo: &mut [F], hi: &[F], x: F, y: F) {
+    cfg_iter_mut!(lo)
+        .zip(cfg_iter!(hi))
+        .for_each(|(lo_i, hi_i)| *lo_i = *lo_i * x + y * hi_i);
+}
+
+/// Folds the halves of a vector of points as
+/// \\(\mathbf{lo}\_i \gets \mathbf{lo}\_i \cdot x\_i + \mathbf{hi}\_i \cdot y\_i\\),
+/// where `factors` returns \\((x\_i, y\_i)\\), normalizing the results
+/// with a single batch inversion.
+fn fold_points<G: AffineRepr, F>(lo: &mut [G], hi: &[G], factors: F)
+where
+    F: Fn(usize) -> (G::ScalarField, G::ScalarField) + Sync,
+{
+    let folded: Vec<G::Group> = cfg_iter!(lo)
+        .zip(cfg_iter!(hi))
+        .enumerate()
+        .map(|(i, (lo_i, hi_i))| {
+            let (x, y) = factors(i);
+            *lo_i * x + *hi_i * y
+        })
+        .collect();
+    lo.copy_from_slice(&G::Group::normalize_batch(&folded));
 }
 
 #[cfg(test)]
@@ -403,8 +451,10 @@ mod tests {
     use super::*;
     use ark_std::{io::Cursor, rand::SeedableRng, One, UniformRand};
 
+    use crate::msm::ArkworksMsm;
     use crate::util;
     use digest::Digest;
+    use merlin::Transcript;
     use rand_chacha::ChaChaRng;
     use sha3::Sha3_512;
 
@@ -449,17 +499,14 @@ mod tests {
         // y_inv is (the inverse of) a random challenge
         let y_inv = <G as AffineRepr>::ScalarField::rand(&mut rng);
         let H_factors: Vec<<G as AffineRepr>::ScalarField> =
-            util::exp_iter::<G>(y_inv).take(n).collect();
+            util::exp_iter(y_inv).take(n).collect();
 
         // P would be determined upstream, but we need a correct P to check the proof.
         //
         // To generate P = <a,G> + <b,H'> + <a,b> Q, compute
         //             P = <a,G> + <b',H> + <a,b> Q,
         // where b' = b \circ y^(-n)
-        let b_prime = b
-            .iter()
-            .zip(util::exp_iter::<G>(y_inv))
-            .map(|(bi, yi)| *bi * yi);
+        let b_prime = b.iter().zip(util::exp_iter(y_inv)).map(|(bi, yi)| *bi * yi);
         // a.iter() has Item=&Fr, need Item=Fr to chain with b_prime
         let a_prime = a.iter().cloned();
 
@@ -488,6 +535,8 @@ mod tests {
             H.clone(),
             a.clone(),
             b.clone(),
+            &ArkworksMsm,
+            &mut |_, _| {},
         );
 
         let mut verifier = Transcript::new(b"innerproducttest");
@@ -496,7 +545,7 @@ mod tests {
                 n,
                 &mut verifier,
                 iter::repeat(<G as AffineRepr>::ScalarField::one()).take(n),
-                util::exp_iter::<G>(y_inv).take(n),
+                util::exp_iter(y_inv).take(n),
                 &P,
                 &Q,
                 &G_,
@@ -518,7 +567,7 @@ mod tests {
                 n,
                 &mut verifier,
                 iter::repeat(<G as AffineRepr>::ScalarField::one()).take(n),
-                util::exp_iter::<G>(y_inv).take(n),
+                util::exp_iter(y_inv).take(n),
                 &P,
                 &Q,
                 &G_,
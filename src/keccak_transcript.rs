@@ -0,0 +1,276 @@
+//! The `keccak_transcript` module contains a Fiat-Shamir transcript
+//! built on Keccak-256, for verifiers re-implemented in EVM contracts,
+//! which have a cheap Keccak-256 but neither Merlin nor STROBE.
+
+#![deny(missing_docs)]
+
+use ark_ec::AffineRepr;
+use ark_ff::PrimeField;
+use ark_serialize::CanonicalSerialize;
+use ark_std::rand::{CryptoRng, RngCore, SeedableRng};
+use ark_std::{vec, vec::Vec, Zero};
+use rand_chacha::ChaChaRng;
+use sha3::{Digest, Keccak256};
+
+use crate::transcript::{ByteTranscript, TranscriptProtocol};
+
+/// A transcript whose state is a 32-byte Keccak-256 digest, with the
+/// same labels and domain separators as the Merlin transcripts.
+///
+/// Every operation hashes the current state with its inputs, where
+/// `u32(x)` is the 4-byte big-endian encoding of `x` and `||` is
+/// concatenation:
+///
+/// * [`KeccakTranscript::new`] starts from 32 zero bytes, then appends
+///   the message `label` with the label `b"dom-sep"`;
+/// * appending a `message` with a `label` replaces the state with
+///   `keccak256(state || u32(len(label)) || label || u32(len(message)) || message)`;
+/// * drawing `n` challenge bytes with a `label` first replaces the state
+///   with `keccak256(state || u32(len(label)) || label || u32(n))`, then
+///   returns the first `n` bytes of
+///   `keccak256(state || u32(0)) || keccak256(state || u32(1)) || ...`.
+///
+/// The values are appended in the encodings of the EVM: `u64`s and
+/// scalars as big-endian integers of 8 and 32 bytes, and points as their
+/// big-endian coordinates `x || y`, or 64 zero bytes for the identity.
+/// Elements of fields wider than 256 bits, which the EVM has no words
+/// for, are encoded in as many bytes as their serialization.  Challenge
+/// scalars are 64 challenge bytes, read as a big-endian integer reduced
+/// modulo the order of the group.
+///
+/// The witness RNG of the provers is a `ChaChaRng` seeded with
+/// `keccak256(state || witness || random)`, where the witness is the
+/// list of its labeled byte strings, encoded as the appended messages,
+/// and `random` is 32 bytes of the RNG of the caller.
+#[derive(Clone, Debug)]
+pub struct KeccakTranscript {
+    state: [u8; 32],
+}
+
+impl KeccakTranscript {
+    /// Creates a transcript with the domain separator `label`.
+    pub fn new(label: &'static [u8]) -> Self {
+        let mut transcript = KeccakTranscript { state: [0u8; 32] };
+        transcript.append_message(b"dom-sep", label);
+        transcript
+    }
+
+    /// Appends a `message` with the given `label`.
+    pub fn append_message(&mut self, label: &'static [u8], message: &[u8]) {
+        self.state = Keccak256::new()
+            .chain_update(self.state)
+            .chain_update(length(label))
+            .chain_update(label)
+            .chain_update(length(message))
+            .chain_update(message)
+            .finalize()
+            .into();
+    }
+
+    /// Appends a `u64` with the given `label`.
+    pub fn append_u64(&mut self, label: &'static [u8], x: u64) {
+        self.append_message(label, &x.to_be_bytes());
+    }
+
+    /// Fills `dest` with challenge bytes with the given `label`.
+    pub fn challenge_bytes(&mut self, label: &'static [u8], dest: &mut [u8]) {
+        self.state = Keccak256::new()
+            .chain_update(self.state)
+            .chain_update(length(label))
+            .chain_update(label)
+            .chain_update(length(dest))
+            .finalize()
+            .into();
+        for (i, chunk) in dest.chunks_mut(32).enumerate() {
+            let block = Keccak256::new()
+                .chain_update(self.state)
+                .chain_update((i as u32).to_be_bytes())
+                .finalize();
+            chunk.copy_from_slice(&block[..chunk.len()]);
+        }
+    }
+}
+
+impl ByteTranscript for KeccakTranscript {
+    fn append_message(&mut self, label: &'static [u8], message: &[u8]) {
+        KeccakTranscript::append_message(self, label, message);
+    }
+
+    fn append_u64(&mut self, label: &'static [u8], x: u64) {
+        KeccakTranscript::append_u64(self, label, x);
+    }
+
+    fn challenge_bytes(&mut self, label: &'static [u8], dest: &mut [u8]) {
+        KeccakTranscript::challenge_bytes(self, label, dest);
+    }
+}
+
+impl<G: AffineRepr> TranscriptProtocol<G> for KeccakTranscript {
+    type Rng = ChaChaRng;
+
+    fn append_scalar(&mut self, label: &'static [u8], scalar: &G::ScalarField) {
+        self.append_message(label, &be_bytes(scalar));
+    }
+
+    fn append_point(&mut self, label: &'static [u8], point: &G) {
+        self.append_message(label, &point_be_bytes(point));
+    }
+
+    fn challenge_scalar(&mut self, label: &'static [u8]) -> G::ScalarField {
+        let mut buf = [0u8; 64];
+        self.challenge_bytes(label, &mut buf);
+        G::ScalarField::from_be_bytes_mod_order(&buf)
+    }
+
+    fn forked_challenge_scalar(&self, label: &'static [u8]) -> G::ScalarField {
+        <Self as TranscriptProtocol<G>>::challenge_scalar(&mut self.clone(), label)
+    }
+
+    fn witness_rng<R: RngCore + CryptoRng>(
+        &self,
+        witness: &[(&'static [u8], Vec<u8>)],
+        rng: &mut R,
+    ) -> ChaChaRng {
+        let mut hasher = Keccak256::new().chain_update(self.state);
+        for (label, bytes) in witness {
+            hasher.update(length(label));
+            hasher.update(label);
+            hasher.update(length(bytes));
+            hasher.update(bytes);
+        }
+        let mut random = [0u8; 32];
+        rng.fill_bytes(&mut random);
+        ChaChaRng::from_seed(hasher.chain_update(random).finalize().into())
+    }
+}
+
+/// Returns the 4-byte big-endian length of `bytes`.
+fn length(bytes: &[u8]) -> [u8; 4] {
+    (bytes.len() as u32).to_be_bytes()
+}
+
+/// Returns the big-endian encoding of the field element `x`, of 32 bytes
+/// for fields of up to 256 bits, as the EVM words, and of the length of
+/// its serialization otherwise.
+pub(crate) fn be_bytes<F: CanonicalSerialize>(x: &F) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(32);
+    x.serialize_uncompressed(&mut bytes).unwrap();
+    bytes.resize(bytes.len().max(32), 0);
+    bytes.reverse();
+    bytes
+}
+
+/// Returns the big-endian coordinates `x || y` of `point`, or as many
+/// zero bytes for the identity.
+pub(crate) fn point_be_bytes<G: AffineRepr>(point: &G) -> Vec<u8> {
+    match point.xy() {
+        Some((x, y)) => [be_bytes(x), be_bytes(y)].concat(),
+        None => vec![0; 2 * be_bytes(&G::BaseField::zero()).len()],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::generators::PedersenGens;
+
+    type G = ark_secq256k1::Affine;
+    type F = ark_secq256k1::Fr;
+
+    #[test]
+    fn keccak_transcript_spec() {
+        let mut transcript = KeccakTranscript::new(b"KeccakTest");
+        transcript.append_u64(b"n", 7);
+        let mut challenge = [0u8; 40];
+        transcript.challenge_bytes(b"c", &mut challenge);
+
+        // The same operations, spelled out.
+        let keccak = |parts: &[&[u8]]| -> [u8; 32] {
+            let mut hasher = Keccak256::new();
+            for part in parts {
+                hasher.update(part);
+            }
+            hasher.finalize().into()
+        };
+        let state = keccak(&[
+            &[0u8; 32],
+            &[0, 0, 0, 7],
+            b"dom-sep",
+            &[0, 0, 0, 10],
+            b"KeccakTest",
+        ]);
+        let state = keccak(&[
+            &state,
+            &[0, 0, 0, 1],
+            b"n",
+            &[0, 0, 0, 8],
+            &7u64.to_be_bytes(),
+        ]);
+        let state = keccak(&[&state, &[0, 0, 0, 1], b"c", &[0, 0, 0, 40]]);
+        let expected = [
+            keccak(&[&state, &[0, 0, 0, 0]]),
+            keccak(&[&state, &[0, 0, 0, 1]]),
+        ];
+        assert_eq!(challenge[..32], expected[0]);
+        assert_eq!(challenge[32..], expected[1][..8]);
+        assert_eq!(transcript.state, state);
+    }
+
+    #[test]
+    fn keccak_transcript_challenges() {
+        let point = PedersenGens::<G>::default().B_blinding;
+        let mut a = KeccakTranscript::new(b"KeccakTest");
+        let mut b = a.clone();
+        <KeccakTranscript as TranscriptProtocol<G>>::append_point(&mut a, b"P", &point);
+        <KeccakTranscript as TranscriptProtocol<G>>::append_point(&mut b, b"P", &G::zero());
+        let mut c = a.clone();
+
+        let x: F = <KeccakTranscript as TranscriptProtocol<G>>::challenge_scalar(&mut a, b"x");
+        let y: F = <KeccakTranscript as TranscriptProtocol<G>>::challenge_scalar(&mut b, b"x");
+        assert_ne!(x, y);
+        // Challenges depend on the transcript, not on randomness.
+        let z: F = <KeccakTranscript as TranscriptProtocol<G>>::challenge_scalar(&mut c, b"x");
+        assert_eq!(x, z);
+        assert!(
+            <KeccakTranscript as TranscriptProtocol<G>>::validate_and_append_point(
+                &mut c,
+                b"P",
+                &G::zero()
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn keccak_transcript_wide_coordinates() {
+        use ark_bls12_381::{Fq, G1Affine};
+        use ark_ff::{BigInteger, Field};
+
+        // Two points of a 381-bit base field whose x-coordinates only
+        // differ in their bytes above 256 bits.
+        let point = G1Affine::generator();
+        let (x, y) = point.xy().unwrap();
+        let other = G1Affine::new_unchecked(*x + Fq::from(2u64).pow([256]), *y);
+        assert_eq!(
+            x.into_bigint().to_bytes_le()[..32],
+            other.x.into_bigint().to_bytes_le()[..32]
+        );
+
+        let challenge = |point: &G1Affine| {
+            let mut transcript = KeccakTranscript::new(b"KeccakTest");
+            <KeccakTranscript as TranscriptProtocol<G1Affine>>::append_point(
+                &mut transcript,
+                b"P",
+                point,
+            );
+            <KeccakTranscript as TranscriptProtocol<G1Affine>>::challenge_scalar(
+                &mut transcript,
+                b"x",
+            )
+        };
+        assert_ne!(challenge(&point), challenge(&other));
+        assert_eq!(point_be_bytes(&point).len(), 96);
+        assert_eq!(point_be_bytes(&G1Affine::zero()).len(), 96);
+    }
+}
@@ -2,15 +2,48 @@
 
 pub mod curve;
 
+#[macro_use]
 mod util;
 
+mod cross_curve;
+#[cfg(feature = "ct")]
+mod ct;
+mod dalek_transcript;
+mod dleq_proof;
 mod errors;
 mod generators;
 mod inner_product_proof;
+mod keccak_transcript;
+mod msm;
+mod opening_proof;
+mod recording_transcript;
 mod transcript;
+mod validation;
 
+pub use crate::cross_curve::CrossCurveEqualityProof;
+pub use crate::dalek_transcript::DalekCompatTranscript;
+pub use crate::dleq_proof::DleqProof;
 pub use crate::errors::ProofError;
 pub use crate::generators::{BulletproofGens, BulletproofGensShare, PedersenGens};
+pub use crate::keccak_transcript::KeccakTranscript;
+pub use crate::msm::{AdaptiveMsm, ArkworksMsm, BucketMsm, MsmBackend};
+pub use crate::opening_proof::OpeningProof;
+pub use crate::recording_transcript::{RecordingTranscript, TranscriptEvent};
+pub use crate::transcript::{ByteTranscript, TranscriptProtocol};
+pub use crate::validation::{point_from_bytes, IdentityPolicy, PointValidation};
 
 #[cfg(feature = "yoloproofs")]
 pub mod r1cs;
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+#[cfg(feature = "python")]
+mod python;
+
+// The browser has no threads for the rayon global pool.
+#[cfg(all(feature = "wasm", feature = "parallel", target_arch = "wasm32"))]
+compile_error!("the `parallel` feature is not supported on wasm32 targets");
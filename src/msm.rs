@@ -0,0 +1,457 @@
+//! The `msm` module contains the API for plugging a multiscalar
+//! multiplication implementation into the provers and verifiers.
+
+#![deny(missing_docs)]
+
+use ark_ec::{AffineRepr, CurveGroup, Group, VariableBaseMSM};
+use ark_ff::{BigInteger, PrimeField, Zero};
+#[cfg(feature = "std")]
+use ark_std::UniformRand;
+use ark_std::{cfg_into_iter, vec, vec::Vec};
+#[cfg(feature = "std")]
+use rand_core::RngCore;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// A multiscalar multiplication implementation, e.g. on a GPU or an
+/// FPGA, used for the commitments of the R1CS prover, the rounds of the
+/// inner-product proof, and the final check of the R1CS verifiers.
+///
+/// With the `ct` feature, the multiplications of the secret scalars of
+/// the provers are computed in constant time instead of with the backend.
+///
+/// The backend is attached to the [`BulletproofGens`](crate::BulletproofGens)
+/// with [`BulletproofGens::with_msm_backend`](crate::BulletproofGens::with_msm_backend),
+/// so that it is used by every proof created or verified with these
+/// generators.
+pub trait MsmBackend<G: AffineRepr>: Send + Sync {
+    /// Returns \\(\sum\_i s\_i \cdot P\_i\\) for the `scalars`
+    /// \\(s\_i\\) and the `bases` \\(P\_i\\), which have the same length.
+    fn msm(&self, bases: &[G], scalars: &[G::ScalarField]) -> G::Group;
+
+    /// Returns the multiscalar multiplication of the terms of all the
+    /// pairs of `bases` and `scalars` slices in `terms`.
+    ///
+    /// This lets callers borrow the generators in place rather than
+    /// copying them next to the other points of a multiplication.  The
+    /// default implementation sums the multiplications of the pairs;
+    /// backends can override it to share their work between the pairs.
+    fn msm_slices(&self, terms: &[(&[G], &[G::ScalarField])]) -> G::Group {
+        terms
+            .iter()
+            .filter(|(bases, _)| !bases.is_empty())
+            .map(|(bases, scalars)| self.msm(bases, scalars))
+            .sum()
+    }
+}
+
+/// An [`MsmBackend`] using the multiscalar multiplication of arkworks,
+/// which [`AdaptiveMsm`] uses for large multiplications.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ArkworksMsm;
+
+impl<G: AffineRepr> MsmBackend<G> for ArkworksMsm {
+    fn msm(&self, bases: &[G], scalars: &[G::ScalarField]) -> G::Group {
+        debug_assert_eq!(bases.len(), scalars.len());
+        G::Group::msm_unchecked(bases, scalars)
+    }
+}
+
+/// The largest number of terms for which [`AdaptiveMsm`] uses Straus's
+/// method, measured to be faster than the bucket method up to there.
+const STRAUS_THRESHOLD: usize = 32;
+
+/// The window of the signed digits of the scalars in Straus's method.
+const STRAUS_WINDOW: usize = 4;
+
+/// The largest window of [`BucketMsm`], whose \\(2^{16} - 1\\) buckets per
+/// window already outnumber the terms of any practical multiplication.
+const MAX_BUCKET_WINDOW: usize = 16;
+
+/// The default [`MsmBackend`], which computes small multiscalar
+/// multiplications with Straus's method, and larger ones with
+/// [`ArkworksMsm`].
+///
+/// The bucket method of arkworks has a fixed cost per window, which
+/// dominates for the handful of terms of the commitments of small
+/// circuits, of the verification of tiny proofs, and of the sigma
+/// protocols of this crate; Straus's method shares the doublings between
+/// the terms, and only costs a table of small multiples per term.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct AdaptiveMsm;
+
+impl<G: AffineRepr> MsmBackend<G> for AdaptiveMsm {
+    fn msm(&self, bases: &[G], scalars: &[G::ScalarField]) -> G::Group {
+        if bases.len() <= STRAUS_THRESHOLD {
+            straus_msm(bases, scalars)
+        } else {
+            ArkworksMsm.msm(bases, scalars)
+        }
+    }
+
+    fn msm_slices(&self, terms: &[(&[G], &[G::ScalarField])]) -> G::Group {
+        if terms.iter().map(|(bases, _)| bases.len()).sum::<usize>() > STRAUS_THRESHOLD {
+            return terms
+                .iter()
+                .filter(|(bases, _)| !bases.is_empty())
+                .map(|(bases, scalars)| self.msm(bases, scalars))
+                .sum();
+        }
+        let bases: Vec<G> = terms
+            .iter()
+            .flat_map(|(bases, _)| bases.iter())
+            .copied()
+            .collect();
+        let scalars: Vec<G::ScalarField> = terms
+            .iter()
+            .flat_map(|(_, scalars)| scalars.iter())
+            .copied()
+            .collect();
+        straus_msm(&bases, &scalars)
+    }
+}
+
+/// An [`MsmBackend`] computing multiscalar multiplications with the
+/// bucket (Pippenger) method, with a given window size.
+///
+/// The window of [`ArkworksMsm`] is derived from the number of terms
+/// alone, which is not the fastest one on every machine: the best window
+/// also depends on the relative costs of additions and of the memory
+/// accesses to the buckets.  The window of this backend can be set
+/// explicitly, or measured on the machine with
+/// [`BucketMsm::calibrate`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BucketMsm {
+    window: usize,
+}
+
+impl BucketMsm {
+    /// Returns a backend using windows of `window` bits, i.e.
+    /// \\(2^w - 1\\) buckets per window.
+    ///
+    /// The window is clamped between 1 and 16 bits.
+    pub fn new(window: usize) -> Self {
+        BucketMsm {
+            window: window.clamp(1, MAX_BUCKET_WINDOW),
+        }
+    }
+
+    /// Returns the window size of this backend.
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// Returns the backend with the fastest window size for
+    /// multiscalar multiplications of `terms` terms on this machine,
+    /// timing one multiplication of random points and scalars per
+    /// candidate window.
+    ///
+    /// The calibration should be run with the typical size of the
+    /// verified batches, since the best window grows with the number of
+    /// terms.
+    ///
+    /// This is not available on wasm32 targets, which have no clock.
+    #[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+    pub fn calibrate<G: AffineRepr, R: RngCore>(terms: usize, rng: &mut R) -> Self {
+        let bases: Vec<G> = (0..terms).map(|_| G::rand(rng)).collect();
+        let scalars: Vec<G::ScalarField> = (0..terms).map(|_| G::ScalarField::rand(rng)).collect();
+        // Windows beyond log2(terms) + 4 have far more buckets than terms.
+        let max_window = (usize::BITS - terms.leading_zeros()) as usize + 4;
+        (1..=max_window.min(MAX_BUCKET_WINDOW))
+            .map(BucketMsm::new)
+            .min_by_key(|backend| {
+                let start = std::time::Instant::now();
+                backend.msm(&bases, &scalars);
+                start.elapsed()
+            })
+            .unwrap()
+    }
+}
+
+impl<G: AffineRepr> MsmBackend<G> for BucketMsm {
+    fn msm(&self, bases: &[G], scalars: &[G::ScalarField]) -> G::Group {
+        self.msm_slices(&[(bases, scalars)])
+    }
+
+    fn msm_slices(&self, terms: &[(&[G], &[G::ScalarField])]) -> G::Group {
+        let w = self.window;
+        let bits = G::ScalarField::MODULUS_BIT_SIZE as usize;
+        let bases = || terms.iter().flat_map(|(bases, _)| bases.iter());
+        let scalars: Vec<Vec<bool>> = terms
+            .iter()
+            .flat_map(|(_, scalars)| scalars.iter())
+            .map(|s| s.into_bigint().to_bits_le())
+            .collect();
+
+        // The sum of the terms of each window, from the lowest one.
+        let window_sums: Vec<G::Group> = cfg_into_iter!((0..bits).step_by(w).collect::<Vec<_>>())
+            .map(|low| {
+                let high = (low + w).min(bits);
+                let mut buckets = vec![G::Group::zero(); (1 << w) - 1];
+                for (base, scalar) in bases().zip(&scalars) {
+                    let digit = scalar[low..high]
+                        .iter()
+                        .rev()
+                        .fold(0usize, |acc, bit| (acc << 1) | *bit as usize);
+                    if digit != 0 {
+                        buckets[digit - 1] += base;
+                    }
+                }
+                // Sums the buckets weighted by their digits, with running sums.
+                let mut sum = G::Group::zero();
+                let mut running = G::Group::zero();
+                for bucket in buckets.into_iter().rev() {
+                    running += bucket;
+                    sum += running;
+                }
+                sum
+            })
+            .collect();
+
+        let mut result = G::Group::zero();
+        for sum in window_sums.into_iter().rev() {
+            for _ in 0..w {
+                result.double_in_place();
+            }
+            result += sum;
+        }
+        result
+    }
+}
+
+/// Returns the multiscalar multiplication of secret `scalars`, such as
+/// the witness or the blinding factors of a prover, with the `backend`,
+/// or in constant time with the `ct` feature.
+pub(crate) fn secret_msm<G: AffineRepr>(
+    backend: &dyn MsmBackend<G>,
+    bases: &[G],
+    scalars: &[G::ScalarField],
+) -> G::Group {
+    #[cfg(feature = "ct")]
+    {
+        let _ = backend;
+        crate::ct::msm(bases, scalars)
+    }
+    #[cfg(not(feature = "ct"))]
+    backend.msm(bases, scalars)
+}
+
+/// Computes a multiscalar multiplication with Straus's method, over the
+/// width-[`STRAUS_WINDOW`] non-adjacent forms of the scalars.
+fn straus_msm<G: AffineRepr>(bases: &[G], scalars: &[G::ScalarField]) -> G::Group {
+    let nafs: Vec<Vec<i64>> = scalars
+        .iter()
+        .map(|s| s.into_bigint().find_wnaf(STRAUS_WINDOW).unwrap())
+        .collect();
+
+    // The odd multiples P, 3P, ..., (2^(w-1) - 1)P of every base,
+    // followed by their negations, in affine form for mixed additions.
+    let multiples = 1 << (STRAUS_WINDOW - 2);
+    let mut tables = Vec::with_capacity(2 * bases.len() * multiples);
+    for base in bases {
+        let double = base.into_group().double();
+        let mut multiple = base.into_group();
+        let start = tables.len();
+        for _ in 0..multiples {
+            tables.push(multiple);
+            multiple += double;
+        }
+        for k in start..start + multiples {
+            tables.push(-tables[k]);
+        }
+    }
+    let tables = G::Group::normalize_batch(&tables);
+
+    let len = nafs.iter().map(Vec::len).max().unwrap_or(0);
+    let mut result = G::Group::zero();
+    for i in (0..len).rev() {
+        result.double_in_place();
+        for (naf, table) in nafs.iter().zip(tables.chunks(2 * multiples)) {
+            match naf.get(i).copied().unwrap_or(0) {
+                0 => {}
+                d if d > 0 => result += table[(d / 2) as usize],
+                d => result += table[multiples + (-d / 2) as usize],
+            }
+        }
+    }
+    result
+}
+
+/// Precomputed multiples of fixed bases, for the terms of these bases in
+/// multiscalar multiplications.
+///
+/// The scalars are recoded in signed digits of `window` bits, and every
+/// base has a row of the multiples \\(d \cdot 2^{wj} P\\) for
+/// \\(1 \le d \le 2^{w-1}\\) per digit \\(j\\), so that a term
+/// costs one mixed addition per non-zero digit, without doublings.
+#[derive(Clone)]
+pub(crate) struct FixedBaseTable<G: AffineRepr> {
+    window: usize,
+    rows: Vec<G>,
+}
+
+impl<G: AffineRepr> FixedBaseTable<G> {
+    /// Precomputes the table of `bases` for digits of `window` bits.
+    pub(crate) fn new(bases: &[G], window: usize) -> Self {
+        assert!((2..=16).contains(&window), "invalid fixed-base window");
+        let mut table = FixedBaseTable {
+            window,
+            rows: Vec::new(),
+        };
+        table.extend(bases);
+        table
+    }
+
+    /// Returns the number of digits of the recoded scalars.
+    fn digits(&self) -> usize {
+        G::ScalarField::MODULUS_BIT_SIZE as usize / self.window + 1
+    }
+
+    /// Returns the number of points of the table of a base.
+    fn row_len(&self) -> usize {
+        self.digits() << (self.window - 1)
+    }
+
+    /// Returns the number of bases of the table.
+    pub(crate) fn len(&self) -> usize {
+        self.rows.len() / self.row_len()
+    }
+
+    /// Appends the tables of `bases`.
+    pub(crate) fn extend(&mut self, bases: &[G]) {
+        let half = 1 << (self.window - 1);
+        let mut rows = Vec::with_capacity(bases.len() * self.row_len());
+        for base in bases {
+            let mut power = base.into_group();
+            for _ in 0..self.digits() {
+                let mut multiple = power;
+                for _ in 0..half {
+                    rows.push(multiple);
+                    multiple += power;
+                }
+                for _ in 0..self.window {
+                    power.double_in_place();
+                }
+            }
+        }
+        self.rows.extend(G::Group::normalize_batch(&rows));
+    }
+
+    /// Returns \\(\sum s\_i \cdot P\_i\\) for the `terms`
+    /// \\((i, s\_i)\\), where \\(P\_i\\) is the `i`-th base.
+    pub(crate) fn msm<I>(&self, terms: I) -> G::Group
+    where
+        I: IntoIterator<Item = (usize, G::ScalarField)>,
+    {
+        let half = 1i64 << (self.window - 1);
+        let mut result = G::Group::zero();
+        for (i, scalar) in terms {
+            let row = &self.rows[i * self.row_len()..(i + 1) * self.row_len()];
+            let bits = scalar.into_bigint().to_bits_le();
+            let mut carry = 0;
+            for j in 0..self.digits() {
+                let low = (j * self.window).min(bits.len());
+                let high = ((j + 1) * self.window).min(bits.len());
+                let chunk = bits[low..high]
+                    .iter()
+                    .rev()
+                    .fold(0i64, |acc, bit| (acc << 1) | *bit as i64);
+                let mut digit = chunk + carry;
+                carry = 0;
+                if digit > half {
+                    digit -= 1 << self.window;
+                    carry = 1;
+                }
+                let entries = &row[(j << (self.window - 1))..((j + 1) << (self.window - 1))];
+                match digit {
+                    0 => {}
+                    d if d > 0 => result += entries[(d - 1) as usize],
+                    d => result -= entries[(-d - 1) as usize].into_group(),
+                }
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_std::{One, UniformRand};
+
+    type G = ark_secq256k1::Affine;
+    type F = ark_secq256k1::Fr;
+
+    #[test]
+    fn straus_matches_arkworks() {
+        let mut rng = ark_std::test_rng();
+        for n in [0, 1, 2, 7, STRAUS_THRESHOLD, STRAUS_THRESHOLD + 1] {
+            let bases: Vec<G> = (0..n).map(|_| G::rand(&mut rng)).collect();
+            let mut scalars: Vec<F> = (0..n).map(|_| F::rand(&mut rng)).collect();
+            if n > 2 {
+                scalars[0] = F::zero();
+                scalars[1] = F::one();
+                scalars[2] = -F::one();
+            }
+            let expected = ArkworksMsm.msm(&bases, &scalars);
+            assert_eq!(straus_msm(&bases, &scalars), expected);
+            assert_eq!(AdaptiveMsm.msm(&bases, &scalars), expected);
+        }
+    }
+
+    #[test]
+    fn msm_slices_match_msm() {
+        let mut rng = ark_std::test_rng();
+        let bases: Vec<G> = (0..50).map(|_| G::rand(&mut rng)).collect();
+        let scalars: Vec<F> = (0..50).map(|_| F::rand(&mut rng)).collect();
+        for split in [(0, 0), (3, 10), (10, 40), (20, 50)] {
+            let terms = [
+                (&bases[..split.0], &scalars[..split.0]),
+                (&bases[split.0..split.1], &scalars[split.0..split.1]),
+                (&bases[split.1..], &scalars[split.1..]),
+            ];
+            let expected = ArkworksMsm.msm(&bases, &scalars);
+            assert_eq!(ArkworksMsm.msm_slices(&terms), expected);
+            assert_eq!(AdaptiveMsm.msm_slices(&terms), expected);
+            assert_eq!(BucketMsm::new(5).msm_slices(&terms), expected);
+
+            let prefix = ArkworksMsm.msm(&bases[..split.1], &scalars[..split.1]);
+            assert_eq!(AdaptiveMsm.msm_slices(&terms[..2]), prefix);
+        }
+    }
+
+    #[test]
+    fn bucket_matches_arkworks() {
+        let mut rng = ark_std::test_rng();
+        let bases: Vec<G> = (0..40).map(|_| G::rand(&mut rng)).collect();
+        let mut scalars: Vec<F> = (0..40).map(|_| F::rand(&mut rng)).collect();
+        scalars[0] = F::zero();
+        scalars[1] = -F::one();
+        let expected = ArkworksMsm.msm(&bases, &scalars);
+        for window in [1, 3, 8, 13] {
+            assert_eq!(BucketMsm::new(window).msm(&bases, &scalars), expected);
+        }
+        assert_eq!(BucketMsm::new(0).window(), 1);
+        assert_eq!(BucketMsm::new(40).window(), 16);
+        let calibrated = BucketMsm::calibrate::<G, _>(bases.len(), &mut rng);
+        assert!((1..=10).contains(&calibrated.window()));
+        assert_eq!(calibrated.msm(&bases, &scalars), expected);
+    }
+
+    #[test]
+    fn fixed_base_table_matches_arkworks() {
+        let mut rng = ark_std::test_rng();
+        let bases: Vec<G> = (0..6).map(|_| G::rand(&mut rng)).collect();
+        let mut scalars: Vec<F> = (0..6).map(|_| F::rand(&mut rng)).collect();
+        scalars[0] = F::zero();
+        scalars[1] = F::one();
+        scalars[2] = -F::one();
+        let expected = ArkworksMsm.msm(&bases, &scalars);
+        for window in [2, 4, 5, 8] {
+            let mut table = FixedBaseTable::new(&bases[..4], window);
+            table.extend(&bases[4..]);
+            assert_eq!(table.len(), bases.len());
+            assert_eq!(table.msm(scalars.iter().copied().enumerate()), expected);
+        }
+    }
+}
@@ -0,0 +1,236 @@
+#![allow(non_snake_case)]
+//! Definition of proofs of knowledge of the openings of Pedersen commitments.
+
+use ark_ec::AffineRepr;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::{
+    io::Cursor,
+    rand::{CryptoRng, RngCore},
+    vec::Vec,
+    One, UniformRand, Zero,
+};
+use merlin::Transcript;
+use zeroize::Zeroizing;
+
+use crate::errors::ProofError;
+use crate::generators::PedersenGens;
+use crate::msm::{AdaptiveMsm, MsmBackend};
+use crate::transcript::TranscriptProtocol;
+
+/// A Schnorr-style proof of knowledge of the openings \\((v_i, \tilde{v}_i)\\)
+/// of Pedersen commitments \\(V_i = v_i B + \tilde{v}_i \tilde{B}\\).
+///
+/// The proof is created and verified over a Merlin transcript, so it can
+/// share the transcript of an [`R1CSProof`](::r1cs::R1CSProof) about the
+/// same commitments: the challenge then depends on the whole transcript,
+/// and the domain separator of the opening proof keeps it apart from the
+/// challenges of the other protocols.
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct OpeningProof<G: AffineRepr> {
+    /// Commitments \\(R_i = a_i B + b_i \tilde{B}\\) to the nonces.
+    R: Vec<G>,
+    /// Responses \\(a_i + e v_i\\) for the values.
+    s_v: Vec<G::ScalarField>,
+    /// Responses \\(b_i + e \tilde{v}_i\\) for the blinding factors.
+    s_blinding: Vec<G::ScalarField>,
+}
+
+impl<G: AffineRepr> OpeningProof<G> {
+    /// Creates a proof of knowledge of the openings
+    /// `(values[i], blindings[i])` of the commitments
+    /// `pc_gens.commit(values[i], blindings[i])`.
+    ///
+    /// The commitments are appended to the `transcript`, so the verifier
+    /// does not need to append them separately.
+    ///
+    /// Returns an error if the number of blinding factors does not match
+    /// the number of values.
+    pub fn create<R: CryptoRng + RngCore>(
+        transcript: &mut Transcript,
+        pc_gens: &PedersenGens<G>,
+        values: &[G::ScalarField],
+        blindings: &[G::ScalarField],
+        rng: &mut R,
+    ) -> Result<OpeningProof<G>, ProofError> {
+        if values.len() != blindings.len() {
+            return Err(ProofError::WrongNumBlindingFactors);
+        }
+
+        <Transcript as TranscriptProtocol<G>>::opening_domain_sep(transcript, values.len() as u64);
+        for (v, v_blinding) in values.iter().zip(blindings.iter()) {
+            transcript.append_point(b"V", &pc_gens.commit(*v, *v_blinding));
+        }
+
+        // Rekey the nonce generator with the blinding factors, like the
+        // R1CS prover does with its witness.
+        let mut rng = {
+            let mut builder = transcript.build_rng();
+            for v_blinding in blindings {
+                let mut bytes = Zeroizing::new(Vec::with_capacity(v_blinding.uncompressed_size()));
+                v_blinding.serialize_uncompressed(&mut *bytes).unwrap();
+                builder = builder.rekey_with_witness_bytes(b"v_blinding", &bytes);
+            }
+            builder.finalize(rng)
+        };
+
+        let nonces: Vec<(G::ScalarField, G::ScalarField)> = values
+            .iter()
+            .map(|_| {
+                (
+                    G::ScalarField::rand(&mut rng),
+                    G::ScalarField::rand(&mut rng),
+                )
+            })
+            .collect();
+
+        let R: Vec<G> = nonces.iter().map(|(a, b)| pc_gens.commit(*a, *b)).collect();
+        for R_i in &R {
+            transcript.append_point(b"R", R_i);
+        }
+
+        let e = <Transcript as TranscriptProtocol<G>>::challenge_scalar(transcript, b"e");
+
+        let s_v = nonces
+            .iter()
+            .zip(values.iter())
+            .map(|((a, _), v)| *a + e * v)
+            .collect();
+        let s_blinding = nonces
+            .iter()
+            .zip(blindings.iter())
+            .map(|((_, b), v_blinding)| *b + e * v_blinding)
+            .collect();
+
+        Ok(OpeningProof { R, s_v, s_blinding })
+    }
+
+    /// Verifies the proof of knowledge of the openings of the `commitments`.
+    ///
+    /// The `transcript` must be in the same state as the one passed to
+    /// [`OpeningProof::create`].
+    pub fn verify(
+        &self,
+        transcript: &mut Transcript,
+        pc_gens: &PedersenGens<G>,
+        commitments: &[G],
+    ) -> Result<(), ProofError> {
+        let n = commitments.len();
+        if self.R.len() != n || self.s_v.len() != n || self.s_blinding.len() != n {
+            return Err(ProofError::VerificationError);
+        }
+
+        <Transcript as TranscriptProtocol<G>>::opening_domain_sep(transcript, n as u64);
+        for V in commitments {
+            transcript.append_point(b"V", V);
+        }
+        for R_i in &self.R {
+            transcript.validate_and_append_point(b"R", R_i)?;
+        }
+
+        let e = <Transcript as TranscriptProtocol<G>>::challenge_scalar(transcript, b"e");
+
+        // Check s_v * B + s_blinding * B_blinding == R + e * V
+        for (((V, R_i), s_v), s_blinding) in commitments
+            .iter()
+            .zip(self.R.iter())
+            .zip(self.s_v.iter())
+            .zip(self.s_blinding.iter())
+        {
+            let check = AdaptiveMsm.msm(
+                &[pc_gens.B, pc_gens.B_blinding, *R_i, *V],
+                &[*s_v, *s_blinding, -G::ScalarField::one(), -e],
+            );
+            if !check.is_zero() {
+                return Err(ProofError::VerificationError);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Serializes the proof into a byte array.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ProofError> {
+        let mut cursor = Cursor::new(Vec::new());
+        self.serialize_compressed(&mut cursor)?;
+        Ok(cursor.into_inner())
+    }
+
+    /// Deserializes the proof from a byte slice.
+    ///
+    /// Returns an error if the byte slice cannot be parsed into an `OpeningProof`.
+    pub fn from_bytes(slice: &[u8]) -> Result<OpeningProof<G>, ProofError> {
+        let mut cursor = Cursor::new(slice);
+        OpeningProof::deserialize_compressed(&mut cursor).map_err(|_| ProofError::FormatError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type G = ark_secq256k1::Affine;
+    type F = ark_secq256k1::Fr;
+
+    fn openings(n: u64) -> (Vec<F>, Vec<F>) {
+        let mut rng = rand::thread_rng();
+        let values = (0..n).map(F::from).collect();
+        let blindings = (0..n).map(|_| F::rand(&mut rng)).collect();
+        (values, blindings)
+    }
+
+    #[test]
+    fn opening_proof_roundtrip() {
+        let pc_gens = PedersenGens::<G>::default();
+        let mut rng = rand::thread_rng();
+        let (values, blindings) = openings(3);
+        let commitments: Vec<G> = values
+            .iter()
+            .zip(blindings.iter())
+            .map(|(v, b)| pc_gens.commit(*v, *b))
+            .collect();
+
+        let mut transcript = Transcript::new(b"OpeningProofTest");
+        let proof =
+            OpeningProof::create(&mut transcript, &pc_gens, &values, &blindings, &mut rng).unwrap();
+        let proof = OpeningProof::<G>::from_bytes(&proof.to_bytes().unwrap()).unwrap();
+
+        let mut transcript = Transcript::new(b"OpeningProofTest");
+        assert!(proof
+            .verify(&mut transcript, &pc_gens, &commitments)
+            .is_ok());
+
+        // A different transcript gives a different challenge.
+        let mut transcript = Transcript::new(b"OtherTest");
+        assert!(proof
+            .verify(&mut transcript, &pc_gens, &commitments)
+            .is_err());
+
+        // The proof does not hold for other commitments.
+        let mut transcript = Transcript::new(b"OpeningProofTest");
+        let mut other = commitments.clone();
+        other.swap(0, 1);
+        assert!(proof.verify(&mut transcript, &pc_gens, &other).is_err());
+
+        let mut transcript = Transcript::new(b"OpeningProofTest");
+        assert!(proof
+            .verify(&mut transcript, &pc_gens, &commitments[..2])
+            .is_err());
+    }
+
+    #[test]
+    fn opening_proof_wrong_blindings() {
+        let pc_gens = PedersenGens::<G>::default();
+        let mut rng = rand::thread_rng();
+        let (values, blindings) = openings(2);
+
+        let mut transcript = Transcript::new(b"OpeningProofTest");
+        assert!(OpeningProof::<G>::create(
+            &mut transcript,
+            &pc_gens,
+            &values,
+            &blindings[..1],
+            &mut rng
+        )
+        .is_err());
+    }
+}
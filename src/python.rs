@@ -0,0 +1,283 @@
+//! The `bulletproofs_bs257` Python module, so that scripts can create
+//! and verify range proofs and the proofs of the
+//! [`statements`](crate::r1cs::statements) module offline.
+//!
+//! The module works on `bytes` over the secq256k1 curve: blindings are
+//! 32-byte little-endian scalars, while commitments and proofs use the
+//! compressed encodings of `ark-serialize`.  Amounts are Python integers
+//! below \\(2^{64}\\).  Every function takes the `label` of the transcript,
+//! i.e. the proofs are made with `Transcript::new(label)`, so that proofs
+//! created by Rust code can be verified from Python.  Verification
+//! functions return whether the proof is valid, and raise `ValueError`
+//! if an input cannot be parsed.
+
+use ark_ff::UniformRand;
+use ark_secq256k1::{Affine, Fr};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::{borrow::Cow, boxed::Box, string::ToString, vec::Vec};
+use merlin::Transcript;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use std::sync::Mutex;
+
+use crate::r1cs::{statements, R1CSError, R1CSProof};
+use crate::{BulletproofGens, PedersenGens};
+
+type Bytes = Cow<'static, [u8]>;
+
+/// Returns a uniformly random scalar, e.g. to use as a blinding factor.
+#[pyfunction]
+fn random_scalar() -> Bytes {
+    to_bytes(&Fr::rand(&mut rand::thread_rng()))
+}
+
+/// Returns the Pedersen commitment to `value` with `blinding`.
+#[pyfunction]
+fn commit(value: u64, blinding: &[u8]) -> PyResult<Bytes> {
+    let blinding = from_bytes::<Fr>(blinding)?;
+    let commitment = PedersenGens::<Affine>::default().commit(Fr::from(value), blinding);
+    Ok(to_bytes(&commitment))
+}
+
+/// Proves that `value` is in the range `[0, 2^n)`.
+///
+/// Returns the proof and the commitment to `value`.
+#[pyfunction]
+fn prove_in_range(label: &[u8], value: u64, blinding: &[u8], n: usize) -> PyResult<(Bytes, Bytes)> {
+    let (proof, commitment) = statements::prove_in_range::<Affine, _>(
+        &PedersenGens::default(),
+        &gens(n),
+        &mut transcript(label),
+        Fr::from(value),
+        from_bytes(blinding)?,
+        n,
+        &mut rand::thread_rng(),
+    )
+    .map_err(value_error)?;
+    Ok((proof_bytes(&proof)?, to_bytes(&commitment)))
+}
+
+/// Verifies a proof created by `prove_in_range`.
+#[pyfunction]
+fn verify_in_range(label: &[u8], proof: &[u8], commitment: &[u8], n: usize) -> PyResult<bool> {
+    let result = statements::verify_in_range(
+        &PedersenGens::default(),
+        &gens(n),
+        &mut transcript(label),
+        &from_proof_bytes(proof)?,
+        &from_bytes::<Affine>(commitment)?,
+        n,
+    );
+    Ok(result.is_ok())
+}
+
+/// Proves that `a` is greater than `b`, and that both are in the range
+/// `[0, 2^n)`.
+///
+/// Returns the proof and the commitments to `a` and `b`.
+#[pyfunction]
+fn prove_greater_than(
+    label: &[u8],
+    a: (u64, Vec<u8>),
+    b: (u64, Vec<u8>),
+    n: usize,
+) -> PyResult<(Bytes, Bytes, Bytes)> {
+    let (proof, (a, b)) = statements::prove_greater_than::<Affine, _>(
+        &PedersenGens::default(),
+        &gens(3 * n),
+        &mut transcript(label),
+        (Fr::from(a.0), from_bytes(&a.1)?),
+        (Fr::from(b.0), from_bytes(&b.1)?),
+        n,
+        &mut rand::thread_rng(),
+    )
+    .map_err(value_error)?;
+    Ok((proof_bytes(&proof)?, to_bytes(&a), to_bytes(&b)))
+}
+
+/// Verifies a proof created by `prove_greater_than`.
+#[pyfunction]
+fn verify_greater_than(label: &[u8], proof: &[u8], a: &[u8], b: &[u8], n: usize) -> PyResult<bool> {
+    let result = statements::verify_greater_than(
+        &PedersenGens::default(),
+        &gens(3 * n),
+        &mut transcript(label),
+        &from_proof_bytes(proof)?,
+        (&from_bytes::<Affine>(a)?, &from_bytes::<Affine>(b)?),
+        n,
+    );
+    Ok(result.is_ok())
+}
+
+/// Proves that two commitments with the blindings `a_blinding` and
+/// `b_blinding` commit to the same `value`.
+///
+/// Returns the proof and the two commitments.
+#[pyfunction]
+fn prove_equal(
+    label: &[u8],
+    value: u64,
+    a_blinding: &[u8],
+    b_blinding: &[u8],
+) -> PyResult<(Bytes, Bytes, Bytes)> {
+    let (proof, (a, b)) = statements::prove_equal::<Affine, _>(
+        &PedersenGens::default(),
+        &gens(0),
+        &mut transcript(label),
+        Fr::from(value),
+        (from_bytes(a_blinding)?, from_bytes(b_blinding)?),
+        &mut rand::thread_rng(),
+    )
+    .map_err(value_error)?;
+    Ok((proof_bytes(&proof)?, to_bytes(&a), to_bytes(&b)))
+}
+
+/// Verifies a proof created by `prove_equal`.
+#[pyfunction]
+fn verify_equal(label: &[u8], proof: &[u8], a: &[u8], b: &[u8]) -> PyResult<bool> {
+    let result = statements::verify_equal(
+        &PedersenGens::default(),
+        &gens(0),
+        &mut transcript(label),
+        &from_proof_bytes(proof)?,
+        (&from_bytes::<Affine>(a)?, &from_bytes::<Affine>(b)?),
+    );
+    Ok(result.is_ok())
+}
+
+/// Proves that `sum(inputs) = sum(outputs) + fee`, and that every output
+/// is in the range `[0, 2^n)`, where `inputs` and `outputs` are lists of
+/// `(amount, blinding)` pairs.
+///
+/// Returns the proof and the lists of the commitments to the inputs and
+/// to the outputs.
+#[pyfunction]
+#[allow(clippy::type_complexity)]
+fn prove_balance(
+    label: &[u8],
+    inputs: Vec<(u64, Vec<u8>)>,
+    outputs: Vec<(u64, Vec<u8>)>,
+    fee: u64,
+    n: usize,
+) -> PyResult<(Bytes, Vec<Bytes>, Vec<Bytes>)> {
+    let openings = |values: Vec<(u64, Vec<u8>)>| {
+        values
+            .into_iter()
+            .map(|(v, blinding)| Ok((Fr::from(v), from_bytes(&blinding)?)))
+            .collect::<PyResult<Vec<_>>>()
+    };
+    let (proof, (inputs, outputs)) = statements::prove_balance::<Affine, _>(
+        &PedersenGens::default(),
+        &gens(n * outputs.len()),
+        &mut transcript(label),
+        (&openings(inputs)?, &openings(outputs)?),
+        fee,
+        n,
+        &mut rand::thread_rng(),
+    )
+    .map_err(value_error)?;
+    Ok((
+        proof_bytes(&proof)?,
+        inputs.iter().map(to_bytes).collect(),
+        outputs.iter().map(to_bytes).collect(),
+    ))
+}
+
+/// Verifies a proof created by `prove_balance`.
+#[pyfunction]
+fn verify_balance(
+    label: &[u8],
+    proof: &[u8],
+    inputs: Vec<Vec<u8>>,
+    outputs: Vec<Vec<u8>>,
+    fee: u64,
+    n: usize,
+) -> PyResult<bool> {
+    let points = |commitments: Vec<Vec<u8>>| {
+        commitments
+            .iter()
+            .map(|c| from_bytes::<Affine>(c))
+            .collect::<PyResult<Vec<_>>>()
+    };
+    let result = statements::verify_balance(
+        &PedersenGens::default(),
+        &gens(n * outputs.len()),
+        &mut transcript(label),
+        &from_proof_bytes(proof)?,
+        (&points(inputs)?, &points(outputs)?),
+        fee,
+        n,
+    );
+    Ok(result.is_ok())
+}
+
+/// Bulletproofs over secq256k1.
+#[pymodule]
+fn bulletproofs_bs257(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(random_scalar, m)?)?;
+    m.add_function(wrap_pyfunction!(commit, m)?)?;
+    m.add_function(wrap_pyfunction!(prove_in_range, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_in_range, m)?)?;
+    m.add_function(wrap_pyfunction!(prove_greater_than, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_greater_than, m)?)?;
+    m.add_function(wrap_pyfunction!(prove_equal, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_equal, m)?)?;
+    m.add_function(wrap_pyfunction!(prove_balance, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_balance, m)?)?;
+    Ok(())
+}
+
+/// Returns generators for statements of `n` multipliers.
+fn gens(n: usize) -> BulletproofGens<Affine> {
+    BulletproofGens::new(n.max(1).next_power_of_two(), 1)
+}
+
+/// Returns `Transcript::new(label)`.
+///
+/// Merlin only takes static labels, so every distinct label is leaked
+/// once and reused afterwards.
+fn transcript(label: &[u8]) -> Transcript {
+    static LABELS: Mutex<Vec<&'static [u8]>> = Mutex::new(Vec::new());
+    let mut labels = LABELS.lock().unwrap();
+    let label = match labels.iter().find(|l| **l == label) {
+        Some(l) => *l,
+        None => {
+            let l: &'static [u8] = Box::leak(label.into());
+            labels.push(l);
+            l
+        }
+    };
+    Transcript::new(label)
+}
+
+fn value_error(e: R1CSError) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+fn to_bytes<T: CanonicalSerialize>(value: &T) -> Bytes {
+    let mut bytes = Vec::new();
+    value.serialize_compressed(&mut bytes).unwrap();
+    Cow::Owned(bytes)
+}
+
+fn from_bytes<T: CanonicalDeserialize>(mut bytes: &[u8]) -> PyResult<T> {
+    let value =
+        T::deserialize_compressed(&mut bytes).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    if !bytes.is_empty() {
+        return Err(PyValueError::new_err(
+            "trailing bytes after the encoded value",
+        ));
+    }
+    Ok(value)
+}
+
+fn proof_bytes(proof: &R1CSProof<Affine>) -> PyResult<Bytes> {
+    let bytes = proof
+        .to_bytes()
+        .map_err(|e| PyValueError::new_err(e.to_string()))?;
+    Ok(Cow::Owned(bytes))
+}
+
+fn from_proof_bytes(bytes: &[u8]) -> PyResult<R1CSProof<Affine>> {
+    R1CSProof::from_bytes(bytes).map_err(value_error)
+}
@@ -0,0 +1,145 @@
+//! Adapter for circuits written against the `ConstraintSynthesizer`
+//! interface of arkworks, as proved with Groth16 or Marlin, so that they
+//! can be proved with Bulletproofs, without a trusted setup.
+//!
+//! The `ConstraintSystemRef` of arkworks is a concrete type rather than a
+//! trait, so a circuit is synthesized into an arkworks constraint system
+//! first.  Its constraint matrices, and its assignment on the prover, are
+//! then added to a [`ConstraintSystem`] like those of a
+//! [`CircomCircuit`]: the instance variables become public inputs, and the
+//! witness variables become allocated variables.
+
+use ark_ff::PrimeField;
+use ark_relations::r1cs::{self as ark, ConstraintSynthesizer, SynthesisMode};
+use ark_std::{string::ToString, vec::Vec};
+
+use super::circom::Terms;
+use super::{CircomCircuit, ConstraintSystem, R1CSError, Variable};
+
+/// A circuit implementing the arkworks `ConstraintSynthesizer`, with its
+/// constraints and, on the prover, its assignment.
+///
+/// ```text
+/// let circuit = ArkCircuit::assign(MyCircuit { x: Some(x), y: Some(y) })?;
+///
+/// let mut prover = Prover::new(&pc_gens, Transcript::new(b"circuit"));
+/// let public: Vec<_> = circuit
+///     .public_values()
+///     .unwrap()
+///     .iter()
+///     .map(|value| prover.public_input(*value))
+///     .collect();
+/// circuit.synthesize(&mut prover, &public)?;
+///
+/// let circuit = ArkCircuit::setup(MyCircuit { x: None, y: None })?;
+/// let mut verifier = Verifier::new(Transcript::new(b"circuit"));
+/// let public: Vec<_> = y.iter().map(|value| verifier.public_input(*value)).collect();
+/// circuit.synthesize(&mut verifier, &public)?;
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArkCircuit<F: PrimeField> {
+    circuit: CircomCircuit<F>,
+    witness: Option<Vec<F>>,
+}
+
+impl<F: PrimeField> ArkCircuit<F> {
+    /// Synthesizes the constraints of `circuit`, without its assignment,
+    /// for the verifier.
+    pub fn setup<C: ConstraintSynthesizer<F>>(circuit: C) -> Result<Self, R1CSError> {
+        Self::generate(circuit, SynthesisMode::Setup)
+    }
+
+    /// Synthesizes the constraints of `circuit` and its assignment, for
+    /// the prover.
+    pub fn assign<C: ConstraintSynthesizer<F>>(circuit: C) -> Result<Self, R1CSError> {
+        Self::generate(
+            circuit,
+            SynthesisMode::Prove {
+                construct_matrices: true,
+            },
+        )
+    }
+
+    fn generate<C: ConstraintSynthesizer<F>>(
+        circuit: C,
+        mode: SynthesisMode,
+    ) -> Result<Self, R1CSError> {
+        let cs = ark::ConstraintSystem::<F>::new_ref();
+        cs.set_mode(mode);
+        circuit
+            .generate_constraints(cs.clone())
+            .map_err(|e| R1CSError::GadgetError {
+                description: e.to_string(),
+            })?;
+        cs.finalize();
+
+        let matrices = cs.to_matrices().ok_or_else(|| R1CSError::GadgetError {
+            description: "the arkworks constraint system has no matrices".to_string(),
+        })?;
+        // The matrices already index the constant one, the instance
+        // variables and the witness variables in the order of our wires.
+        let terms = |row: &Vec<(F, usize)>| -> Terms<F> {
+            row.iter().map(|(coeff, wire)| (*wire, *coeff)).collect()
+        };
+        let constraints = matrices
+            .a
+            .iter()
+            .zip(&matrices.b)
+            .zip(&matrices.c)
+            .map(|((a, b), c)| [terms(a), terms(b), terms(c)])
+            .collect();
+        let circuit = CircomCircuit::from_constraints(
+            matrices.num_instance_variables + matrices.num_witness_variables,
+            matrices.num_instance_variables - 1,
+            constraints,
+        );
+
+        let witness = match mode {
+            SynthesisMode::Setup => None,
+            _ => cs.borrow().map(|cs| {
+                cs.instance_assignment
+                    .iter()
+                    .chain(&cs.witness_assignment)
+                    .copied()
+                    .collect()
+            }),
+        };
+        Ok(ArkCircuit { circuit, witness })
+    }
+
+    /// Returns the number of instance variables, without the constant
+    /// one.
+    pub fn public_len(&self) -> usize {
+        self.circuit.public_len()
+    }
+
+    /// Returns the number of constraints.
+    pub fn constraints_len(&self) -> usize {
+        self.circuit.constraints_len()
+    }
+
+    /// Returns the values of the instance variables, without the
+    /// constant one, or `None` if the circuit was synthesized by
+    /// [`setup`](Self::setup).
+    pub fn public_values(&self) -> Option<&[F]> {
+        self.witness
+            .as_ref()
+            .map(|witness| self.circuit.public_values(witness))
+    }
+
+    /// Adds the circuit to the constraint system `cs`, with its
+    /// assignment if it was synthesized by [`assign`](Self::assign).
+    ///
+    /// The `public` variables are those of the instance variables, as
+    /// returned by `public_input` for each of the
+    /// [`public_values`](Self::public_values).  Returns the variables of
+    /// the constant one, the instance variables and the witness
+    /// variables, in this order.
+    pub fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        public: &[Variable<F>],
+    ) -> Result<Vec<Variable<F>>, R1CSError> {
+        self.circuit.synthesize(cs, public, self.witness.as_deref())
+    }
+}
@@ -0,0 +1,237 @@
+#![allow(non_snake_case)]
+//! Verification artifacts of prepared circuits, for verifiers
+//! re-implemented outside of this crate, e.g. in Solidity contracts.
+
+use ark_ec::AffineRepr;
+use ark_std::{fmt::Write, format, string::String, string::ToString, vec::Vec};
+
+use super::R1CSProof;
+use crate::errors::R1CSError;
+use crate::keccak_transcript::{be_bytes, point_be_bytes};
+use crate::util::hex;
+
+/// A group of points of the verification multiscalar multiplication,
+/// in the order of [`VerificationArtifacts::msm_order`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MsmTerm {
+    /// The Pedersen base `pc_gens.B`.
+    B,
+    /// The Pedersen blinding base `pc_gens.B_blinding`.
+    BBlinding,
+    /// The \\(\mathbf G\\) generators of [`VerificationArtifacts::G`].
+    G,
+    /// The \\(\mathbf H\\) generators of [`VerificationArtifacts::H`].
+    H,
+    /// The proof element `A_I1`.
+    AI1,
+    /// The proof element `A_O1`.
+    AO1,
+    /// The proof element `S1`.
+    S1,
+    /// The proof element `A_I2`.
+    AI2,
+    /// The proof element `A_O2`.
+    AO2,
+    /// The proof element `S2`.
+    S2,
+    /// The high-level variable commitments, in the order they were
+    /// committed.
+    V,
+    /// The proof element `T_1`.
+    T1,
+    /// The proof element `T_3`.
+    T3,
+    /// The proof element `T_4`.
+    T4,
+    /// The proof element `T_5`.
+    T5,
+    /// The proof element `T_6`.
+    T6,
+    /// The `L_vec` of the inner-product proof.
+    L,
+    /// The `R_vec` of the inner-product proof.
+    R,
+}
+
+impl MsmTerm {
+    /// Returns the name of the points, as in [`VerificationScalars`](::r1cs::VerificationScalars).
+    pub fn name(&self) -> &'static str {
+        match self {
+            MsmTerm::B => "B",
+            MsmTerm::BBlinding => "B_blinding",
+            MsmTerm::G => "G",
+            MsmTerm::H => "H",
+            MsmTerm::AI1 => "A_I1",
+            MsmTerm::AO1 => "A_O1",
+            MsmTerm::S1 => "S1",
+            MsmTerm::AI2 => "A_I2",
+            MsmTerm::AO2 => "A_O2",
+            MsmTerm::S2 => "S2",
+            MsmTerm::V => "V",
+            MsmTerm::T1 => "T_1",
+            MsmTerm::T3 => "T_3",
+            MsmTerm::T4 => "T_4",
+            MsmTerm::T5 => "T_5",
+            MsmTerm::T6 => "T_6",
+            MsmTerm::L => "L_vec",
+            MsmTerm::R => "R_vec",
+        }
+    }
+}
+
+/// Everything a verifier of a fixed circuit needs besides the proofs and
+/// the commitments, as exported by
+/// [`PreparedCircuit::export_artifacts`](::r1cs::PreparedCircuit::export_artifacts).
+///
+/// A proof is valid if the multiscalar multiplication of the points
+/// listed by [`msm_order`](Self::msm_order), as returned by
+/// [`msm_points`](Self::msm_points), with the
+/// [`VerificationScalars`](::r1cs::VerificationScalars) of the proof is the
+/// identity.  The scalars are derived from the transcript and from the
+/// constraint weights flattened with the challenge `z`: the weight of
+/// the `i`-th variable of a kind is the sum of `z^(q+1) * coeff` over
+/// the entries `(q, i, coeff)` of its matrix, and the high-level
+/// variable and constant weights are negated.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerificationArtifacts<G: AffineRepr> {
+    /// The digest of the structure of the circuit, see
+    /// [`PreparedCircuit::circuit_digest`](::r1cs::PreparedCircuit::circuit_digest).
+    pub circuit_digest: [u8; 32],
+    /// The number of multipliers of the circuit.
+    pub multipliers: usize,
+    /// The number of multipliers padded to a power of two, which is the
+    /// number of `G` and `H` generators.
+    pub padded_multipliers: usize,
+    /// The number of parties whose generators the multipliers use.
+    pub parties: usize,
+    /// The number of high-level variables of the circuit.
+    pub commitments: usize,
+    /// The number of constraints of the circuit.
+    pub constraints: usize,
+    /// The Pedersen base `pc_gens.B`.
+    pub B: G,
+    /// The Pedersen blinding base `pc_gens.B_blinding`.
+    pub B_blinding: G,
+    /// The \\(\mathbf G\\) generators of the multipliers, laid out over the
+    /// parties.
+    pub G: Vec<G>,
+    /// The \\(\mathbf H\\) generators of the multipliers, laid out over the
+    /// parties.
+    pub H: Vec<G>,
+    /// Weights `(constraint, multiplier, coefficient)` of the left inputs.
+    pub W_L: Vec<(usize, usize, G::ScalarField)>,
+    /// Weights `(constraint, multiplier, coefficient)` of the right inputs.
+    pub W_R: Vec<(usize, usize, G::ScalarField)>,
+    /// Weights `(constraint, multiplier, coefficient)` of the outputs.
+    pub W_O: Vec<(usize, usize, G::ScalarField)>,
+    /// Weights `(constraint, commitment, coefficient)` of the high-level
+    /// variables.
+    pub W_V: Vec<(usize, usize, G::ScalarField)>,
+    /// Weights `(constraint, coefficient)` of the constant terms.
+    pub W_c: Vec<(usize, G::ScalarField)>,
+    /// The groups of points of the verification multiscalar
+    /// multiplication, in order, with their numbers of points.
+    pub msm_order: Vec<(MsmTerm, usize)>,
+}
+
+impl<G: AffineRepr> VerificationArtifacts<G> {
+    /// Returns the points of the verification multiscalar multiplication
+    /// of `proof`, in the order of [`msm_order`](Self::msm_order), which
+    /// matches that of [`VerificationScalars::to_vec`](::r1cs::VerificationScalars::to_vec).
+    ///
+    /// Returns an error if the numbers of `commitments` or of rounds of
+    /// the inner-product proof do not match the circuit.
+    pub fn msm_points(&self, proof: &R1CSProof<G>, commitments: &[G]) -> Result<Vec<G>, R1CSError> {
+        let mut points = Vec::new();
+        for (term, len) in &self.msm_order {
+            let group: &[G] = match term {
+                MsmTerm::B => ark_std::slice::from_ref(&self.B),
+                MsmTerm::BBlinding => ark_std::slice::from_ref(&self.B_blinding),
+                MsmTerm::G => &self.G,
+                MsmTerm::H => &self.H,
+                MsmTerm::AI1 => ark_std::slice::from_ref(&proof.A_I1),
+                MsmTerm::AO1 => ark_std::slice::from_ref(&proof.A_O1),
+                MsmTerm::S1 => ark_std::slice::from_ref(&proof.S1),
+                MsmTerm::AI2 => ark_std::slice::from_ref(&proof.A_I2),
+                MsmTerm::AO2 => ark_std::slice::from_ref(&proof.A_O2),
+                MsmTerm::S2 => ark_std::slice::from_ref(&proof.S2),
+                MsmTerm::V => commitments,
+                MsmTerm::T1 => ark_std::slice::from_ref(&proof.T_1),
+                MsmTerm::T3 => ark_std::slice::from_ref(&proof.T_3),
+                MsmTerm::T4 => ark_std::slice::from_ref(&proof.T_4),
+                MsmTerm::T5 => ark_std::slice::from_ref(&proof.T_5),
+                MsmTerm::T6 => ark_std::slice::from_ref(&proof.T_6),
+                MsmTerm::L => &proof.ipp_proof.L_vec,
+                MsmTerm::R => &proof.ipp_proof.R_vec,
+            };
+            if group.len() != *len {
+                return Err(R1CSError::InvalidProofShape {
+                    description: "the proof does not match the verification artifacts".to_string(),
+                });
+            }
+            points.extend_from_slice(group);
+        }
+        Ok(points)
+    }
+
+    /// Returns the artifacts as a JSON object, in the encodings of the
+    /// EVM: points as the hexadecimal big-endian coordinates `x || y`, or
+    /// 64 zero bytes for the identity, and scalars as 32-byte big-endian
+    /// integers, as appended to a [`KeccakTranscript`](crate::KeccakTranscript).
+    pub fn to_json(&self) -> String {
+        let point = |p: &G| format!("\"{}\"", hex(&point_be_bytes(p)));
+        let scalar = |s: &G::ScalarField| format!("\"{}\"", hex(&be_bytes(s)));
+        let points = |ps: &[G]| ps.iter().map(point).collect::<Vec<_>>().join(", ");
+        let weights = |ws: &[(usize, usize, G::ScalarField)]| {
+            ws.iter()
+                .map(|(q, i, coeff)| format!("[{}, {}, {}]", q, i, scalar(coeff)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        let mut json = String::new();
+        write!(
+            json,
+            "{{\"circuit_digest\": \"{}\", \"multipliers\": {}, \"padded_multipliers\": {}, \
+             \"parties\": {}, \"commitments\": {}, \"constraints\": {}, ",
+            hex(&self.circuit_digest),
+            self.multipliers,
+            self.padded_multipliers,
+            self.parties,
+            self.commitments,
+            self.constraints,
+        )
+        .unwrap();
+        write!(
+            json,
+            "\"B\": {}, \"B_blinding\": {}, \"G\": [{}], \"H\": [{}], ",
+            point(&self.B),
+            point(&self.B_blinding),
+            points(&self.G),
+            points(&self.H),
+        )
+        .unwrap();
+        let W_c: Vec<String> = self
+            .W_c
+            .iter()
+            .map(|(q, coeff)| format!("[{}, {}]", q, scalar(coeff)))
+            .collect();
+        write!(
+            json,
+            "\"W_L\": [{}], \"W_R\": [{}], \"W_O\": [{}], \"W_V\": [{}], \"W_c\": [{}], ",
+            weights(&self.W_L),
+            weights(&self.W_R),
+            weights(&self.W_O),
+            weights(&self.W_V),
+            W_c.join(", "),
+        )
+        .unwrap();
+        let msm_order: Vec<String> = self
+            .msm_order
+            .iter()
+            .map(|(term, len)| format!("[\"{}\", {}]", term.name(), len))
+            .collect();
+        write!(json, "\"msm_order\": [{}]}}", msm_order.join(", ")).unwrap();
+        json
+    }
+}
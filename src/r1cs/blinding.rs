@@ -0,0 +1,39 @@
+//! Definition of the sources of the blinding factors of the prover.
+
+use ark_ff::PrimeField;
+use rand_core::{CryptoRng, RngCore};
+
+/// A source of the blinding factors used by a [`Prover`](::r1cs::Prover).
+///
+/// By default, the prover draws the blinding factors of its commitments
+/// from the caller and those of the proof from an RNG.  Implementing this
+/// trait lets custodial setups and hardware wallets derive the blinding
+/// factors on the secure element, e.g. from a key that never leaves it,
+/// while the host synthesizes the circuit, with
+/// [`Prover::commit_with_blinding_source`](::r1cs::Prover::commit_with_blinding_source)
+/// and [`Prover::prove_with_blinding_source`](::r1cs::Prover::prove_with_blinding_source).
+///
+/// Every blinding factor must be uniformly random and used only once:
+/// a source returning predictable or repeated factors leaks the witness.
+///
+/// Any cryptographic RNG is a `BlindingSource` drawing fresh random
+/// factors.
+pub trait BlindingSource<F: PrimeField> {
+    /// Returns the blinding factor of the `index`-th high-level
+    /// commitment of the prover.
+    fn commitment_blinding(&mut self, index: usize) -> F;
+
+    /// Returns the next blinding factor of the proof, for the round of
+    /// the protocol identified by `label`.
+    fn proof_blinding(&mut self, label: &'static [u8]) -> F;
+}
+
+impl<F: PrimeField, R: CryptoRng + RngCore> BlindingSource<F> for R {
+    fn commitment_blinding(&mut self, _index: usize) -> F {
+        F::rand(self)
+    }
+
+    fn proof_blinding(&mut self, _label: &'static [u8]) -> F {
+        F::rand(self)
+    }
+}
@@ -0,0 +1,331 @@
+//! Import of circuits compiled by circom, from their binary `.r1cs`
+//! files and the `.wtns` files of their witnesses, so that existing
+//! circom circuits can be proved with Bulletproofs.
+//!
+//! The wires of a circom circuit are, in this order, the constant one,
+//! the public outputs, the public inputs, the private inputs and the
+//! internal wires.  The public outputs and inputs become public inputs of
+//! the constraint system, and every other wire becomes an allocated
+//! variable.  A constraint `A * B = C` becomes a multiplier of `A` and
+//! `B` whose output is constrained to `C`, or a linear constraint when
+//! `A` or `B` is a constant.
+
+use ark_ff::PrimeField;
+use ark_std::{iter, string::ToString, vec::Vec};
+
+use super::export::{field_size, le_bytes};
+use super::{ConstraintSystem, LinearCombination, R1CSError, Variable};
+
+/// The terms `(wire, coefficient)` of a linear combination of wires.
+pub(super) type Terms<F> = Vec<(usize, F)>;
+
+/// A circuit compiled by circom, read from its `.r1cs` file.
+///
+/// ```text
+/// let circuit = CircomCircuit::from_r1cs(&r1cs)?;
+/// let witness = circuit.read_witness(&wtns)?;
+///
+/// let mut prover = Prover::new(&pc_gens, Transcript::new(b"circuit"));
+/// let public: Vec<_> = circuit
+///     .public_values(&witness)
+///     .iter()
+///     .map(|value| prover.public_input(*value))
+///     .collect();
+/// circuit.synthesize(&mut prover, &public, Some(&witness))?;
+/// let proof = prover.prove(&mut rng, &bp_gens)?;
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CircomCircuit<F: PrimeField> {
+    wires: usize,
+    public_outputs: usize,
+    public_inputs: usize,
+    private_inputs: usize,
+    constraints: Vec<[Terms<F>; 3]>,
+}
+
+impl<F: PrimeField> CircomCircuit<F> {
+    /// Reads a circuit from the contents of a `.r1cs` file.
+    ///
+    /// Returns an error if the file is malformed, or if its prime is not
+    /// the modulus of `F`.
+    pub fn from_r1cs(bytes: &[u8]) -> Result<Self, R1CSError> {
+        let sections = read_file(bytes, b"r1cs", 1)?;
+
+        let mut header = Reader(section(&sections, 1)?);
+        let n8 = read_prime::<F>(&mut header)?;
+        let wires = header.u32()? as usize;
+        let public_outputs = header.u32()? as usize;
+        let public_inputs = header.u32()? as usize;
+        let private_inputs = header.u32()? as usize;
+        let _labels = header.u64()?;
+        let len = header.u32()? as usize;
+        header.finish()?;
+        let inputs = public_outputs
+            .checked_add(public_inputs)
+            .and_then(|n| n.checked_add(private_inputs))
+            .and_then(|n| n.checked_add(1));
+        if inputs.is_none_or(|inputs| inputs > wires) {
+            return Err(error("the inputs of the .r1cs file exceed its wires"));
+        }
+
+        let mut body = Reader(section(&sections, 2)?);
+        // Every constraint takes at least 12 bytes, which bounds the
+        // allocation by the size of the file.
+        let mut constraints = Vec::with_capacity(len.min(body.0.len() / 12));
+        for _ in 0..len {
+            let mut lc = || -> Result<Terms<F>, R1CSError> {
+                let terms = body.u32()? as usize;
+                let mut lc = Vec::with_capacity(terms.min(body.0.len() / (4 + n8)));
+                for _ in 0..terms {
+                    let wire = body.u32()? as usize;
+                    if wire >= wires {
+                        return Err(error("a constraint of the .r1cs file uses an unknown wire"));
+                    }
+                    lc.push((wire, body.field(n8)?));
+                }
+                Ok(lc)
+            };
+            constraints.push([lc()?, lc()?, lc()?]);
+        }
+        body.finish()?;
+
+        Ok(CircomCircuit {
+            wires,
+            public_outputs,
+            public_inputs,
+            private_inputs,
+            constraints,
+        })
+    }
+
+    /// Returns a circuit of `wires` wires, of which the first
+    /// `public_inputs` after the constant one are public, with the
+    /// `constraints` `[A, B, C]`.
+    #[cfg(feature = "ark-relations")]
+    pub(super) fn from_constraints(
+        wires: usize,
+        public_inputs: usize,
+        constraints: Vec<[Terms<F>; 3]>,
+    ) -> Self {
+        CircomCircuit {
+            wires,
+            public_outputs: 0,
+            public_inputs,
+            private_inputs: wires - 1 - public_inputs,
+            constraints,
+        }
+    }
+
+    /// Returns the number of wires, including the constant one.
+    pub fn wires(&self) -> usize {
+        self.wires
+    }
+
+    /// Returns the number of public wires, i.e. of public outputs and
+    /// public inputs.
+    pub fn public_len(&self) -> usize {
+        self.public_outputs + self.public_inputs
+    }
+
+    /// Returns the number of private inputs, which are included in the
+    /// private wires.
+    pub fn private_inputs_len(&self) -> usize {
+        self.private_inputs
+    }
+
+    /// Returns the number of constraints.
+    pub fn constraints_len(&self) -> usize {
+        self.constraints.len()
+    }
+
+    /// Reads the values of the wires from the contents of a `.wtns` file
+    /// of a witness of this circuit.
+    ///
+    /// Returns an error if the file is malformed, if its prime is not the
+    /// modulus of `F`, or if it does not assign every wire of the circuit.
+    pub fn read_witness(&self, bytes: &[u8]) -> Result<Vec<F>, R1CSError> {
+        let sections = read_file(bytes, b"wtns", 2)?;
+
+        let mut header = Reader(section(&sections, 1)?);
+        let n8 = read_prime::<F>(&mut header)?;
+        if header.u32()? as usize != self.wires {
+            return Err(error(
+                "the .wtns file does not match the wires of the circuit",
+            ));
+        }
+        header.finish()?;
+
+        let mut body = Reader(section(&sections, 2)?);
+        let mut values = Vec::with_capacity(self.wires.min(body.0.len() / n8));
+        for _ in 0..self.wires {
+            values.push(body.field(n8)?);
+        }
+        body.finish()?;
+
+        if values[0] != F::one() {
+            return Err(error("the first wire of the .wtns file is not one"));
+        }
+        Ok(values)
+    }
+
+    /// Returns the values of the public wires in the `witness`, in the
+    /// order they must be passed to [`synthesize`](Self::synthesize).
+    ///
+    /// Panics if the `witness` does not assign the public wires.
+    pub fn public_values<'a>(&self, witness: &'a [F]) -> &'a [F] {
+        &witness[1..1 + self.public_len()]
+    }
+
+    /// Adds the circuit to the constraint system `cs`.
+    ///
+    /// The `public` variables are those of the public wires, as returned
+    /// by `public_input` for each of the
+    /// [`public_values`](Self::public_values) on the prover and the
+    /// verifier.  The prover passes the values of all the wires as the
+    /// `witness`, and the verifier passes `None`.
+    ///
+    /// Returns the variables of all the wires, so that the caller can
+    /// further constrain them, e.g. to equal committed values.
+    pub fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        public: &[Variable<F>],
+        witness: Option<&[F]>,
+    ) -> Result<Vec<Variable<F>>, R1CSError> {
+        if public.len() != self.public_len() {
+            return Err(error("wrong number of public variables for the circuit"));
+        }
+        if witness.is_some_and(|witness| witness.len() != self.wires) {
+            return Err(error("the witness does not match the wires of the circuit"));
+        }
+        let first_private = 1 + self.public_len();
+        let private = cs.allocate_vec(
+            self.wires - first_private,
+            witness.map(|witness| &witness[first_private..]),
+        )?;
+        let vars: Vec<_> = iter::once(Variable::One())
+            .chain(public.iter().copied())
+            .chain(private)
+            .collect();
+
+        let lc = |terms: &Terms<F>| -> LinearCombination<F> {
+            terms
+                .iter()
+                .map(|(wire, coeff)| (vars[*wire], *coeff))
+                .collect()
+        };
+        for [a, b, c] in &self.constraints {
+            match (constant(a), constant(b)) {
+                (Some(a), _) => cs.constrain(lc(b) * a - lc(c)),
+                (_, Some(b)) => cs.constrain(lc(a) * b - lc(c)),
+                (None, None) => {
+                    let (_, _, o) = cs.multiply(lc(a), lc(b));
+                    cs.constrain(o - lc(c));
+                }
+            }
+        }
+        Ok(vars)
+    }
+}
+
+/// Returns the value of the linear combination of wires `terms` if it
+/// only uses the constant wire.
+fn constant<F: PrimeField>(terms: &Terms<F>) -> Option<F> {
+    terms.iter().try_fold(F::zero(), |sum, (wire, coeff)| {
+        (*wire == 0).then(|| sum + coeff)
+    })
+}
+
+fn error(description: &str) -> R1CSError {
+    R1CSError::GadgetError {
+        description: description.to_string(),
+    }
+}
+
+/// Reads the header of a binary file with the `magic` bytes and
+/// `version`, and returns its sections `(type, contents)`.
+fn read_file<'a>(
+    bytes: &'a [u8],
+    magic: &[u8; 4],
+    version: u32,
+) -> Result<Vec<(u32, &'a [u8])>, R1CSError> {
+    let mut file = Reader(bytes);
+    if file.take(4)? != magic || file.u32()? != version {
+        return Err(error("unsupported file format or version"));
+    }
+    let len = file.u32()?;
+    let mut sections = Vec::new();
+    for _ in 0..len {
+        let kind = file.u32()?;
+        let size = usize::try_from(file.u64()?).map_err(|_| truncated())?;
+        sections.push((kind, file.take(size)?));
+    }
+    file.finish()?;
+    Ok(sections)
+}
+
+/// Returns the contents of the only section of type `kind`.
+fn section<'a>(sections: &[(u32, &'a [u8])], kind: u32) -> Result<&'a [u8], R1CSError> {
+    let mut matching = sections.iter().filter(|(k, _)| *k == kind);
+    match (matching.next(), matching.next()) {
+        (Some((_, contents)), None) => Ok(contents),
+        _ => Err(error("missing or duplicate section")),
+    }
+}
+
+/// Reads the size of the field elements and the prime of a header, and
+/// checks that they match `F`.
+fn read_prime<F: PrimeField>(header: &mut Reader) -> Result<usize, R1CSError> {
+    let n8 = header.u32()? as usize;
+    if n8 != field_size::<F>() || header.take(n8)? != le_bytes(&F::MODULUS, n8) {
+        return Err(error(
+            "the prime of the file is not the modulus of the field",
+        ));
+    }
+    Ok(n8)
+}
+
+fn truncated() -> R1CSError {
+    error("truncated or malformed file")
+}
+
+/// Reads little-endian values from the front of a byte slice.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], R1CSError> {
+        if n > self.0.len() {
+            return Err(truncated());
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32, R1CSError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, R1CSError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    /// Reads an `n8`-byte field element, which must be reduced.
+    fn field<F: PrimeField>(&mut self, n8: usize) -> Result<F, R1CSError> {
+        let bytes = self.take(n8)?;
+        let x = F::from_le_bytes_mod_order(bytes);
+        if le_bytes(&x.into_bigint(), n8) != bytes {
+            return Err(error("a field element of the file is not reduced"));
+        }
+        Ok(x)
+    }
+
+    /// Checks that all the bytes have been read.
+    fn finish(&self) -> Result<(), R1CSError> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(truncated())
+        }
+    }
+}
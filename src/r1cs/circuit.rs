@@ -0,0 +1,416 @@
+//! Definition of circuit descriptions, which record the allocations
+//! and constraints of a circuit once, so that they can be replayed
+//! into both the prover and the verifier.
+
+use ark_ff::PrimeField;
+use ark_std::{string::ToString, vec, vec::Vec};
+use digest::Digest;
+use merlin::Transcript;
+use serde::de::{Deserialize, Deserializer, Error as _};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use sha3::Sha3_256;
+
+use super::{ConstraintSystem, LinearCombination, R1CSError, Variable};
+use crate::transcript::ByteTranscript;
+
+/// A single recorded call to the [`ConstraintSystem`] API.
+///
+/// Variables inside the recorded linear combinations are numbered
+/// relative to the [`CircuitDescription`] that recorded them.
+#[derive(Clone, Debug, PartialEq, serde_derive::Serialize, serde_derive::Deserialize)]
+#[serde(bound = "")]
+pub enum Operation<F: PrimeField> {
+    /// A call to [`ConstraintSystem::multiply`] with the given left and right inputs.
+    Multiply(LinearCombination<F>, LinearCombination<F>),
+    /// A call to [`ConstraintSystem::allocate`], which consumes one witness value.
+    Allocate,
+    /// A call to [`ConstraintSystem::allocate_multiplier`], which consumes two witness values.
+    AllocateMultiplier,
+    /// A call to [`ConstraintSystem::constrain`] with the given linear combination.
+    Constrain(LinearCombination<F>),
+}
+
+/// A description of a circuit, recorded once and replayed into a
+/// [`Prover`](::r1cs::Prover) or a [`Verifier`](::r1cs::Verifier).
+///
+/// Gadgets written against the [`ConstraintSystem`] trait record into a
+/// `CircuitDescription` exactly as they would build constraints in a
+/// prover or verifier.  The recorded description does not depend on the
+/// witness: assignments passed to `allocate` and `allocate_multiplier`
+/// while recording are discarded, and must be supplied again to
+/// [`CircuitDescription::replay`] by the prover.
+///
+/// This makes sure that the prover and the verifier build exactly the
+/// same constraints, instead of running two copies of the gadget code
+/// which could drift apart.
+///
+/// Randomized constraints depend on challenges that are only known
+/// while proving or verifying, so they cannot be recorded.  Transcript
+/// operations performed through [`ConstraintSystem::transcript`] while
+/// recording go to a scratch transcript and are not replayed.
+///
+/// Descriptions serialize with serde as the number of high-level
+/// variables and the recorded operations, so that they can be stored or
+/// generated by external tooling, see [`CircuitAssignment`](::r1cs::CircuitAssignment).
+pub struct CircuitDescription<F: PrimeField> {
+    /// Number of high-level variables the circuit expects.
+    committed: usize,
+    /// The recorded operations, in order.
+    operations: Vec<Operation<F>>,
+    /// Number of multipliers allocated so far.
+    num_vars: usize,
+    /// Index of a pending multiplier that's not fully assigned yet.
+    pending_multiplier: Option<usize>,
+    /// Scratch transcript leased to gadgets while recording.
+    transcript: Transcript,
+}
+
+impl<F: PrimeField> Default for CircuitDescription<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: PrimeField> CircuitDescription<F> {
+    /// Creates an empty circuit description.
+    pub fn new() -> Self {
+        CircuitDescription {
+            committed: 0,
+            operations: Vec::new(),
+            num_vars: 0,
+            pending_multiplier: None,
+            transcript: Transcript::new(b"CircuitDescription"),
+        }
+    }
+
+    /// Declares a high-level variable of the circuit.
+    ///
+    /// When replaying, the `i`-th declared variable is substituted by the
+    /// `i`-th of the `inputs` passed to [`CircuitDescription::replay`],
+    /// typically the variables returned by `Prover::commit` or `Verifier::commit`.
+    pub fn commit(&mut self) -> Variable<F> {
+        let i = self.committed;
+        self.committed += 1;
+        Variable::Committed(i)
+    }
+
+    /// Returns the number of high-level variables of the circuit.
+    pub fn commitments_len(&self) -> usize {
+        self.committed
+    }
+
+    /// Returns the recorded operations, in order.
+    pub fn operations(&self) -> &[Operation<F>] {
+        &self.operations
+    }
+
+    /// Returns the number of witness values expected by [`CircuitDescription::replay`].
+    pub fn witness_len(&self) -> usize {
+        self.operations
+            .iter()
+            .map(|op| match op {
+                Operation::Allocate => 1,
+                Operation::AllocateMultiplier => 2,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    /// Replays the recorded circuit into the constraint system `cs`.
+    ///
+    /// # Inputs
+    ///
+    /// The `inputs` are the variables substituted for the high-level
+    /// variables declared with [`CircuitDescription::commit`], in order.
+    ///
+    /// The `witness` holds the assignments of the allocated variables
+    /// in allocation order: one value per `allocate` call and two values
+    /// (left and right) per `allocate_multiplier` call.  The prover must
+    /// provide it, while the verifier passes `None`.
+    pub fn replay<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        inputs: &[Variable<F>],
+        witness: Option<&[F]>,
+    ) -> Result<(), R1CSError> {
+        if inputs.len() != self.committed {
+            return Err(R1CSError::GadgetError {
+                description: "wrong number of inputs for the circuit description".to_string(),
+            });
+        }
+        if let Some(w) = witness {
+            if w.len() != self.witness_len() {
+                return Err(R1CSError::GadgetError {
+                    description: "wrong number of witness values for the circuit description"
+                        .to_string(),
+                });
+            }
+        }
+
+        let mut map = VariableMap::new(self.num_vars, inputs);
+        let mut values = witness.map(|w| w.iter().copied());
+        let mut next_value = || values.as_mut().and_then(|v| v.next());
+
+        // Index of the next recorded multiplier, and the recorded index
+        // and replayed variable of a pending multiplier, mirroring the
+        // allocation order used while recording.
+        let mut i = 0;
+        let mut pending: Option<(usize, Variable<F>)> = None;
+
+        for op in self.operations.iter() {
+            match op {
+                Operation::Multiply(left, right) => {
+                    let left = map.translate(left)?;
+                    let right = map.translate(right)?;
+                    map.insert_multiplier(i, cs.multiply(left, right));
+                    i += 1;
+                }
+                Operation::Allocate => {
+                    let var = cs.allocate(next_value())?;
+                    match pending.take() {
+                        None => {
+                            map.left[i] = Some(var);
+                            pending = Some((i, var));
+                            i += 1;
+                        }
+                        Some((j, left)) => map.insert_allocated_pair(j, left, var),
+                    }
+                }
+                Operation::AllocateMultiplier => {
+                    let assignments = match (next_value(), next_value()) {
+                        (Some(l), Some(r)) => Some((l, r)),
+                        _ => None,
+                    };
+                    map.insert_multiplier(i, cs.allocate_multiplier(assignments)?);
+                    i += 1;
+                }
+                Operation::Constrain(lc) => {
+                    let lc = map.translate(lc)?;
+                    cs.constrain(lc);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Maps the variables of a recorded circuit to the variables of the
+/// constraint system it is replayed into.
+struct VariableMap<'a, F: PrimeField> {
+    inputs: &'a [Variable<F>],
+    left: Vec<Option<Variable<F>>>,
+    right: Vec<Option<Variable<F>>>,
+    output: Vec<Option<Variable<F>>>,
+}
+
+impl<'a, F: PrimeField> VariableMap<'a, F> {
+    fn new(n: usize, inputs: &'a [Variable<F>]) -> Self {
+        VariableMap {
+            inputs,
+            left: vec![None; n],
+            right: vec![None; n],
+            output: vec![None; n],
+        }
+    }
+
+    fn insert_multiplier(&mut self, i: usize, (l, r, o): (Variable<F>, Variable<F>, Variable<F>)) {
+        self.left[i] = Some(l);
+        self.right[i] = Some(r);
+        self.output[i] = Some(o);
+    }
+
+    /// Records the right variable of a multiplier allocated by two
+    /// `allocate` calls.  The output of the recorded multiplier is only
+    /// known if both variables belong to the same multiplier of the
+    /// target constraint system.
+    fn insert_allocated_pair(&mut self, i: usize, left: Variable<F>, right: Variable<F>) {
+        let output = match (left, right) {
+            (Variable::MultiplierLeft(i), Variable::MultiplierRight(j)) if i == j => {
+                Some(Variable::MultiplierOutput(i))
+            }
+            _ => None,
+        };
+        self.right[i] = Some(right);
+        self.output[i] = output;
+    }
+
+    fn get(&self, var: &Variable<F>) -> Result<Variable<F>, R1CSError> {
+        let mapped = match var {
+            Variable::Committed(i) => self.inputs.get(*i).copied(),
+            Variable::MultiplierLeft(i) => self.left.get(*i).copied().flatten(),
+            Variable::MultiplierRight(i) => self.right.get(*i).copied().flatten(),
+            Variable::MultiplierOutput(i) => self.output.get(*i).copied().flatten(),
+            other => Some(*other),
+        };
+        mapped.ok_or_else(|| R1CSError::GadgetError {
+            description: "circuit description refers to a variable that cannot be replayed"
+                .to_string(),
+        })
+    }
+
+    fn translate(&self, lc: &LinearCombination<F>) -> Result<LinearCombination<F>, R1CSError> {
+        lc.terms
+            .iter()
+            .map(|(var, coeff)| Ok((self.get(var)?, *coeff)))
+            .collect::<Result<Vec<_>, R1CSError>>()
+            .map(|terms| terms.into_iter().collect())
+    }
+}
+
+impl<F: PrimeField> ConstraintSystem<F> for CircuitDescription<F> {
+    fn transcript(&mut self) -> &mut dyn ByteTranscript {
+        &mut self.transcript
+    }
+
+    fn multiply(
+        &mut self,
+        left: LinearCombination<F>,
+        right: LinearCombination<F>,
+    ) -> (Variable<F>, Variable<F>, Variable<F>) {
+        // The constraints binding the inputs to the multiplier are
+        // added by the target constraint system when replaying.
+        self.operations.push(Operation::Multiply(left, right));
+        let i = self.num_vars;
+        self.num_vars += 1;
+
+        (
+            Variable::MultiplierLeft(i),
+            Variable::MultiplierRight(i),
+            Variable::MultiplierOutput(i),
+        )
+    }
+
+    fn allocate(&mut self, _: Option<F>) -> Result<Variable<F>, R1CSError> {
+        self.operations.push(Operation::Allocate);
+        match self.pending_multiplier {
+            None => {
+                let i = self.num_vars;
+                self.num_vars += 1;
+                self.pending_multiplier = Some(i);
+                Ok(Variable::MultiplierLeft(i))
+            }
+            Some(i) => {
+                self.pending_multiplier = None;
+                Ok(Variable::MultiplierRight(i))
+            }
+        }
+    }
+
+    fn allocate_multiplier(
+        &mut self,
+        _: Option<(F, F)>,
+    ) -> Result<(Variable<F>, Variable<F>, Variable<F>), R1CSError> {
+        self.operations.push(Operation::AllocateMultiplier);
+        let i = self.num_vars;
+        self.num_vars += 1;
+        Ok((
+            Variable::MultiplierLeft(i),
+            Variable::MultiplierRight(i),
+            Variable::MultiplierOutput(i),
+        ))
+    }
+
+    fn multipliers_len(&self) -> usize {
+        self.num_vars
+    }
+
+    fn constrain(&mut self, lc: LinearCombination<F>) {
+        self.operations.push(Operation::Constrain(lc));
+    }
+}
+
+impl<F: PrimeField> Clone for CircuitDescription<F> {
+    fn clone(&self) -> Self {
+        CircuitDescription {
+            committed: self.committed,
+            operations: self.operations.clone(),
+            num_vars: self.num_vars,
+            pending_multiplier: self.pending_multiplier,
+            transcript: Transcript::new(b"CircuitDescription"),
+        }
+    }
+}
+
+impl<F: PrimeField> Serialize for CircuitDescription<F> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("CircuitDescription", 2)?;
+        state.serialize_field("commitments", &self.committed)?;
+        state.serialize_field("operations", &self.operations)?;
+        state.end()
+    }
+}
+
+impl<'de, F: PrimeField> Deserialize<'de> for CircuitDescription<F> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde_derive::Deserialize)]
+        #[serde(bound = "", rename = "CircuitDescription")]
+        struct Repr<F: PrimeField> {
+            commitments: usize,
+            operations: Vec<Operation<F>>,
+        }
+
+        // Record the operations again, to recompute the allocation state.
+        let repr = Repr::<F>::deserialize(deserializer)?;
+        let mut circuit = CircuitDescription::new();
+        circuit.committed = repr.commitments;
+        for op in repr.operations {
+            match op {
+                Operation::Multiply(left, right) => {
+                    circuit.multiply(left, right);
+                }
+                Operation::Allocate => {
+                    circuit.allocate(None).map_err(D::Error::custom)?;
+                }
+                Operation::AllocateMultiplier => {
+                    circuit
+                        .allocate_multiplier(None)
+                        .map_err(D::Error::custom)?;
+                }
+                Operation::Constrain(lc) => circuit.constrain(lc),
+            }
+        }
+        Ok(circuit)
+    }
+}
+
+/// Computes a digest of the structure of a circuit with `n` multipliers,
+/// `m` high-level variables and the given `constraints`.
+///
+/// The digest covers every term of every constraint, in order, so any
+/// change to the circuit (a different coefficient, variable, or
+/// ordering of the constraints) yields a different digest.
+pub(super) fn circuit_digest<F: PrimeField>(
+    n: usize,
+    m: usize,
+    constraints: &[LinearCombination<F>],
+) -> [u8; 32] {
+    let mut hash = Sha3_256::new();
+    Digest::update(&mut hash, b"r1cs circuit v1");
+    Digest::update(&mut hash, (n as u64).to_le_bytes());
+    Digest::update(&mut hash, (m as u64).to_le_bytes());
+    Digest::update(&mut hash, (constraints.len() as u64).to_le_bytes());
+
+    let mut bytes = Vec::new();
+    for lc in constraints {
+        Digest::update(&mut hash, (lc.terms.len() as u64).to_le_bytes());
+        for (var, coeff) in &lc.terms {
+            let (tag, index) = match var {
+                Variable::Committed(i) => (0u8, *i),
+                Variable::MultiplierLeft(i) => (1u8, *i),
+                Variable::MultiplierRight(i) => (2u8, *i),
+                Variable::MultiplierOutput(i) => (3u8, *i),
+                Variable::Public(i) => (5u8, *i),
+                _ => (4u8, 0),
+            };
+            Digest::update(&mut hash, [tag]);
+            Digest::update(&mut hash, (index as u64).to_le_bytes());
+
+            bytes.clear();
+            coeff.serialize_uncompressed(&mut bytes).unwrap();
+            Digest::update(&mut hash, &bytes);
+        }
+    }
+
+    hash.finalize().into()
+}
@@ -2,7 +2,9 @@
 
 use super::{LinearCombination, R1CSError, Variable};
 use ark_ff::PrimeField;
-use merlin::Transcript;
+use ark_std::{string::ToString, vec::Vec};
+
+use crate::transcript::ByteTranscript;
 
 /// The interface for a constraint system, abstracting over the prover
 /// and verifier's roles.
@@ -20,7 +22,7 @@ pub trait ConstraintSystem<F: PrimeField> {
     /// Leases the proof transcript to the user, so they can
     /// add extra data to which the proof must be bound, but which
     /// is not available before creation of the constraint system.
-    fn transcript(&mut self) -> &mut Transcript;
+    fn transcript(&mut self) -> &mut dyn ByteTranscript;
 
     /// Allocate and constrain multiplication variables.
     ///
@@ -74,6 +76,166 @@ pub trait ConstraintSystem<F: PrimeField> {
     /// lc = 0
     /// ```
     fn constrain(&mut self, lc: LinearCombination<F>);
+
+    /// Enforce the explicit constraints that
+    /// ```text
+    /// lc = 0
+    /// ```
+    /// for every `lc` of `lcs`.
+    ///
+    /// This is equivalent to calling [`ConstraintSystem::constrain`] on
+    /// every linear combination, but lets the constraint system add
+    /// them in bulk.
+    fn constrain_all<I>(&mut self, lcs: I)
+    where
+        I: IntoIterator<Item = LinearCombination<F>>,
+        Self: Sized,
+    {
+        for lc in lcs {
+            self.constrain(lc);
+        }
+    }
+
+    /// Allocate `n` variables, with the given `assignments` if any.
+    ///
+    /// This is equivalent to `n` calls to [`ConstraintSystem::allocate`],
+    /// but lets the constraint system allocate them in bulk.
+    ///
+    /// Returns an error if `assignments` does not have `n` elements.
+    fn allocate_vec(
+        &mut self,
+        n: usize,
+        assignments: Option<&[F]>,
+    ) -> Result<Vec<Variable<F>>, R1CSError> {
+        check_assignments_len(n, assignments)?;
+        (0..n)
+            .map(|i| self.allocate(assignments.map(|a| a[i])))
+            .collect()
+    }
+
+    /// Enforce the explicit constraint that
+    /// ```text
+    /// lc = 0
+    /// ```
+    /// like [`ConstraintSystem::constrain`], for anything convertible to
+    /// a linear combination.
+    fn enforce_zero<L>(&mut self, lc: L)
+    where
+        L: Into<LinearCombination<F>>,
+        Self: Sized,
+    {
+        self.constrain(lc.into());
+    }
+
+    /// Enforce the explicit constraint that
+    /// ```text
+    /// a = b
+    /// ```
+    fn enforce_equal<A, B>(&mut self, a: A, b: B)
+    where
+        A: Into<LinearCombination<F>>,
+        B: Into<LinearCombination<F>>,
+        Self: Sized,
+    {
+        self.constrain(a.into() - b.into());
+    }
+
+    /// Enforce that `v` is either `0` or `1`, using one multiplier for
+    /// ```text
+    /// v * (1 - v) = 0
+    /// ```
+    fn enforce_boolean<L>(&mut self, v: L)
+    where
+        L: Into<LinearCombination<F>>,
+        Self: Sized,
+    {
+        let v = v.into();
+        let (_, _, o) = self.multiply(v.clone(), LinearCombination::from(F::one()) - v);
+        self.constrain(o.into());
+    }
+
+    /// Returns the linear combination
+    /// ```text
+    /// sum_i c_i * v_i
+    /// ```
+    /// of the `terms` `(v_i, c_i)`.
+    fn linear_combine(&self, terms: &[(Variable<F>, F)]) -> LinearCombination<F> {
+        terms.iter().collect()
+    }
+
+    /// Returns the linear combination `lc + c`.
+    fn add_constant<L>(&self, lc: L, c: F) -> LinearCombination<F>
+    where
+        L: Into<LinearCombination<F>>,
+        Self: Sized,
+    {
+        lc.into() + c
+    }
+
+    /// Enters a namespace named `name`, nested in the current one.
+    ///
+    /// Namespaces do not change the constraints: they only name the
+    /// parts of a circuit for tools such as the
+    /// [`Profiler`](::r1cs::Profiler), so the default implementation
+    /// does nothing.  Every call must be matched by a call to
+    /// [`ConstraintSystem::pop_namespace`].
+    fn push_namespace(&mut self, _name: &str) {}
+
+    /// Leaves the namespace entered by the matching call to
+    /// [`ConstraintSystem::push_namespace`].
+    fn pop_namespace(&mut self) {}
+}
+
+/// Lets gadgets, which are generic over sized constraint systems, be
+/// synthesized into a `&mut dyn ConstraintSystem`, such as the one passed
+/// to the closures of [`prove`](::r1cs::prove) and [`verify`](::r1cs::verify).
+impl<F: PrimeField, CS: ConstraintSystem<F> + ?Sized> ConstraintSystem<F> for &mut CS {
+    fn transcript(&mut self) -> &mut dyn ByteTranscript {
+        (**self).transcript()
+    }
+
+    fn multiply(
+        &mut self,
+        left: LinearCombination<F>,
+        right: LinearCombination<F>,
+    ) -> (Variable<F>, Variable<F>, Variable<F>) {
+        (**self).multiply(left, right)
+    }
+
+    fn allocate(&mut self, assignment: Option<F>) -> Result<Variable<F>, R1CSError> {
+        (**self).allocate(assignment)
+    }
+
+    fn allocate_multiplier(
+        &mut self,
+        input_assignments: Option<(F, F)>,
+    ) -> Result<(Variable<F>, Variable<F>, Variable<F>), R1CSError> {
+        (**self).allocate_multiplier(input_assignments)
+    }
+
+    fn multipliers_len(&self) -> usize {
+        (**self).multipliers_len()
+    }
+
+    fn constrain(&mut self, lc: LinearCombination<F>) {
+        (**self).constrain(lc)
+    }
+
+    fn allocate_vec(
+        &mut self,
+        n: usize,
+        assignments: Option<&[F]>,
+    ) -> Result<Vec<Variable<F>>, R1CSError> {
+        (**self).allocate_vec(n, assignments)
+    }
+
+    fn push_namespace(&mut self, name: &str) {
+        (**self).push_namespace(name)
+    }
+
+    fn pop_namespace(&mut self) {
+        (**self).pop_namespace()
+    }
 }
 
 /// An extension to the constraint system trait that permits randomized constraints.
@@ -94,6 +256,16 @@ pub trait RandomizableConstraintSystem<F: PrimeField>: ConstraintSystem<F> {
     /// If the constraint system’s low-level variables are committed already,
     /// the callback is invoked immediately and its result is return from this method.
     ///
+    /// Every callback is invoked exactly once, in the order they were specified,
+    /// so callbacks can take ownership of the data they need, such as witness
+    /// tables, and consume it.
+    ///
+    /// The prover's and the verifier's `RandomizedCS` are randomizable
+    /// themselves: a callback can specify further randomized constraints,
+    /// which are added in a new phase, after the multipliers allocated by
+    /// the callbacks of the current phase are committed.  Every phase
+    /// adds three points to the proof.
+    ///
     /// ### Usage
     ///
     /// Inside the closure you can generate one or more challenges using `challenge_scalar` method.
@@ -106,7 +278,7 @@ pub trait RandomizableConstraintSystem<F: PrimeField>: ConstraintSystem<F> {
     /// ```
     fn specify_randomized_constraints<FF>(&mut self, callback: FF) -> Result<(), R1CSError>
     where
-        FF: 'static + Fn(&mut Self::RandomizedCS) -> Result<(), R1CSError>;
+        FF: 'static + FnOnce(&mut Self::RandomizedCS) -> Result<(), R1CSError>;
 }
 
 /// Represents a constraint system in the second phase:
@@ -132,4 +304,42 @@ pub trait RandomizedConstraintSystem<F: PrimeField>: ConstraintSystem<F> {
     /// })
     /// ```
     fn challenge_scalar(&mut self, label: &'static [u8]) -> F;
+
+    /// Generates `n` independent challenge scalars.
+    ///
+    /// The number of challenges and the index of each challenge are
+    /// appended to the transcript before it is generated, so gadgets
+    /// needing one challenge per element (e.g. per column of a table)
+    /// get distinct challenges bound to their position, instead of
+    /// deriving them from a single one.
+    ///
+    /// ```text
+    /// cs.specify_randomized_constraints(move |cs| {
+    ///     let w = cs.challenge_scalars(b"column challenges", columns);
+    ///     // ...
+    /// })
+    /// ```
+    fn challenge_scalars(&mut self, label: &'static [u8], n: usize) -> Vec<F> {
+        self.transcript().append_u64(b"challenges", n as u64);
+        (0..n)
+            .map(|i| {
+                self.transcript().append_u64(b"challenge index", i as u64);
+                self.challenge_scalar(label)
+            })
+            .collect()
+    }
+}
+
+/// Checks that the `assignments` passed to
+/// [`ConstraintSystem::allocate_vec`] match the number of variables.
+pub(super) fn check_assignments_len<F>(
+    n: usize,
+    assignments: Option<&[F]>,
+) -> Result<(), R1CSError> {
+    match assignments {
+        Some(a) if a.len() != n => Err(R1CSError::GadgetError {
+            description: "number of assignments does not match the number of variables".to_string(),
+        }),
+        _ => Ok(()),
+    }
 }
@@ -0,0 +1,108 @@
+//! Definition of circuit shapes, from which the sizes and costs of
+//! their proofs are estimated before proving.
+
+use ark_ec::AffineRepr;
+use ark_ff::Zero;
+use ark_serialize::CanonicalSerialize;
+use core::mem::size_of;
+
+/// The size of a circuit, from which [`CircuitShape::proof_size`],
+/// [`CircuitShape::verifier_msm_len`] and
+/// [`CircuitShape::prover_memory`] estimate the costs of its proofs,
+/// e.g. to size machines or set fees.
+///
+/// The number of multipliers of a circuit is reported by a
+/// [`Profiler`](::r1cs::Profiler).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CircuitShape {
+    /// The number of multipliers, including the entries of the vector
+    /// commitments and the multipliers of the randomized constraints.
+    pub multipliers: usize,
+    /// The number of high-level variable commitments.
+    pub commitments: usize,
+    /// The number of vector commitments.
+    pub vector_commitments: usize,
+    /// The number of randomization phases, i.e. of nested calls to
+    /// [`RandomizableConstraintSystem::specify_randomized_constraints`](::r1cs::RandomizableConstraintSystem::specify_randomized_constraints).
+    pub randomization_phases: usize,
+}
+
+impl CircuitShape {
+    /// Returns the padded number of multipliers, i.e. the number of
+    /// generators of each kind used by the proofs.
+    pub fn padded_n(&self) -> usize {
+        self.multipliers.next_power_of_two()
+    }
+
+    /// Returns the number of rounds of the inner-product proof.
+    fn rounds(&self) -> usize {
+        self.padded_n().trailing_zeros() as usize
+    }
+
+    /// Returns the number of randomization phases after the second one.
+    fn extra_phases(&self) -> usize {
+        self.randomization_phases.saturating_sub(1)
+    }
+
+    /// Returns the exact size in bytes of the proofs of the circuit, as
+    /// serialized by [`R1CSProof::to_bytes`](::r1cs::R1CSProof::to_bytes).
+    pub fn proof_size<G: AffineRepr>(&self) -> usize {
+        let point = G::zero().compressed_size();
+        let scalar = G::ScalarField::zero().compressed_size();
+        let length = 0u64.compressed_size();
+        let flag = false.compressed_size();
+
+        // A_I1, A_O1, S1, A_I2, A_O2, S2, T_1, T_3, T_4, T_5 and T_6.
+        let mut points = 11 + 3 * self.extra_phases() + 2 * self.rounds();
+        if self.vector_commitments > 0 {
+            points += 1;
+        }
+        // t_x, t_x_blinding, e_blinding, and a and b of the inner-product
+        // proof; the lengths of extra_phases, L_vec and R_vec.
+        points * point + 5 * scalar + 3 * length + flag
+    }
+
+    /// Returns the number of terms of the multiscalar multiplication
+    /// verifying a proof of the circuit, i.e. the length of
+    /// [`Verifier::verification_msm`](::r1cs::Verifier::verification_msm).
+    ///
+    /// In a batch, the `2 + 2 * padded_n` terms of the generators are
+    /// shared by the proofs.
+    pub fn verifier_msm_len(&self) -> usize {
+        let mut len = 2 + 2 * self.padded_n();
+        len += 6 + 3 * self.extra_phases();
+        len += self.commitments + self.vector_commitments;
+        if self.vector_commitments > 0 {
+            len += 1;
+        }
+        len + 5 + 2 * self.rounds()
+    }
+
+    /// Returns an estimate of the peak memory in bytes used by the
+    /// prover for the vectors of the proof, in streaming mode with
+    /// `chunk_size` if given (see
+    /// [`Prover::set_chunk_size`](::r1cs::Prover::set_chunk_size)).
+    ///
+    /// This accounts for the witness and blinding vectors, the
+    /// flattened constraints, the vector polynomials, and the vectors
+    /// and generators of the inner-product proof.  It leaves out the
+    /// linear combinations of the constraints, whose size depends on
+    /// the circuit, and the scratch memory of the multiscalar
+    /// multiplications.
+    pub fn prover_memory<G: AffineRepr>(&self, chunk_size: Option<usize>) -> usize {
+        let n = self.multipliers;
+        let padded_n = self.padded_n();
+        let chunk = chunk_size.unwrap_or(n).min(n);
+
+        // a_L, a_R, a_O, s_L, s_R and the weights W_L, W_R, W_O.
+        let witness = 8 * n;
+        // The powers of y^-1, the evaluations of l(x) and r(x), and the
+        // factors of the G and H generators.
+        let ipp_vectors = 5 * padded_n;
+        // The 8 coefficient vectors of l(x) and r(x), one chunk at a time.
+        let polys = 8 * chunk;
+
+        (witness + ipp_vectors + polys) * size_of::<G::ScalarField>()
+            + 2 * padded_n * size_of::<G>()
+    }
+}
@@ -0,0 +1,171 @@
+//! Export of constraint systems in the binary `.r1cs` and `.wtns`
+//! formats of circom, so that circuits built with this crate can be
+//! audited or re-proved with other proof systems, e.g. with snarkjs.
+//!
+//! The multipliers and the linear constraints of a circuit are exported
+//! as rank-1 constraints `A * B = C` over the wires
+//!
+//! ```text
+//! 1, public inputs, high-level variables, a_L, a_R, a_O
+//! ```
+//!
+//! in this order: wire 0 is the constant one, the public inputs are the
+//! public inputs of the circuit, the high-level variables are its
+//! private inputs, and the inputs and outputs of the multipliers are its
+//! internal wires.  The `i`-th multiplier is exported as the constraint
+//! `a_L[i] * a_R[i] = a_O[i]`, followed by every linear constraint `lc`
+//! as `0 * 0 = lc`.  Each wire is labeled with its own index.
+
+use ark_ff::{BigInteger, PrimeField};
+use ark_std::{collections::BTreeMap, string::ToString, vec::Vec};
+
+use super::{LinearCombination, R1CSError, Variable};
+
+/// The number of wires and of each kind of variable of a circuit.
+#[derive(Clone, Copy)]
+pub(super) struct Wires {
+    /// Number of public inputs.
+    pub(super) public: usize,
+    /// Number of high-level variables.
+    pub(super) committed: usize,
+    /// Number of multipliers.
+    pub(super) multipliers: usize,
+}
+
+impl Wires {
+    /// Returns the total number of wires, including the constant one.
+    fn len(&self) -> usize {
+        1 + self.public + self.committed + 3 * self.multipliers
+    }
+
+    /// Returns the wire of `var`.
+    fn wire<F: PrimeField>(&self, var: &Variable<F>) -> Result<u32, R1CSError> {
+        let (p, m, n) = (self.public, self.committed, self.multipliers);
+        let wire = match var {
+            Variable::One() => 0,
+            Variable::Public(i) if *i < p => 1 + i,
+            Variable::Committed(i) if *i < m => 1 + p + i,
+            Variable::MultiplierLeft(i) if *i < n => 1 + p + m + i,
+            Variable::MultiplierRight(i) if *i < n => 1 + p + m + n + i,
+            Variable::MultiplierOutput(i) if *i < n => 1 + p + m + 2 * n + i,
+            _ => {
+                return Err(R1CSError::GadgetError {
+                    description: "a constraint uses an unknown variable".to_string(),
+                })
+            }
+        };
+        Ok(wire as u32)
+    }
+}
+
+/// Returns the `.r1cs` encoding of the circuit of the `constraints`
+/// over the `wires`.
+pub(super) fn r1cs_bytes<F: PrimeField>(
+    constraints: &[LinearCombination<F>],
+    wires: Wires,
+) -> Result<Vec<u8>, R1CSError> {
+    let n = wires.multipliers;
+    if u32::try_from(wires.len()).is_err() || u32::try_from(n + constraints.len()).is_err() {
+        return Err(R1CSError::GadgetError {
+            description: "the circuit is too large for the .r1cs format".to_string(),
+        });
+    }
+    let n8 = field_size::<F>();
+    let one = |wire: usize| BTreeMap::from([(wire as u32, F::one())]);
+    let zero = BTreeMap::<u32, F>::new();
+
+    let mut body = Vec::new();
+    let start = 1 + wires.public + wires.committed;
+    for i in 0..n {
+        write_lc(&mut body, &one(start + i), n8);
+        write_lc(&mut body, &one(start + n + i), n8);
+        write_lc(&mut body, &one(start + 2 * n + i), n8);
+    }
+    for lc in constraints {
+        // Merge the terms of the same wire, which readers of the format
+        // may not accumulate.
+        let mut merged = BTreeMap::new();
+        for (var, coeff) in &lc.terms {
+            *merged.entry(wires.wire(var)?).or_insert_with(F::zero) += coeff;
+        }
+        merged.retain(|_, coeff| !coeff.is_zero());
+        write_lc(&mut body, &zero, n8);
+        write_lc(&mut body, &zero, n8);
+        write_lc(&mut body, &merged, n8);
+    }
+
+    let mut header = Vec::new();
+    write_u32(&mut header, n8 as u32);
+    header.extend_from_slice(&le_bytes(&F::MODULUS, n8));
+    write_u32(&mut header, wires.len() as u32);
+    // No public outputs.
+    write_u32(&mut header, 0);
+    write_u32(&mut header, wires.public as u32);
+    write_u32(&mut header, wires.committed as u32);
+    header.extend_from_slice(&(wires.len() as u64).to_le_bytes());
+    write_u32(&mut header, (n + constraints.len()) as u32);
+
+    let mut labels = Vec::with_capacity(8 * wires.len());
+    for wire in 0..wires.len() as u64 {
+        labels.extend_from_slice(&wire.to_le_bytes());
+    }
+
+    Ok(file(b"r1cs", 1, &[(1, header), (2, body), (3, labels)]))
+}
+
+/// Returns the `.wtns` encoding of the values of the `wires`, starting
+/// with the constant one.
+pub(super) fn wtns_bytes<F: PrimeField>(values: &[F]) -> Vec<u8> {
+    let n8 = field_size::<F>();
+    let mut header = Vec::new();
+    write_u32(&mut header, n8 as u32);
+    header.extend_from_slice(&le_bytes(&F::MODULUS, n8));
+    write_u32(&mut header, values.len() as u32);
+
+    let mut body = Vec::with_capacity(n8 * values.len());
+    for value in values {
+        body.extend_from_slice(&le_bytes(&value.into_bigint(), n8));
+    }
+
+    file(b"wtns", 2, &[(1, header), (2, body)])
+}
+
+/// Returns the number of bytes of the encoding of the elements of `F`,
+/// a multiple of 8.
+pub(super) fn field_size<F: PrimeField>() -> usize {
+    (F::MODULUS_BIT_SIZE as usize).div_ceil(64) * 8
+}
+
+/// Returns the `n8`-byte little-endian encoding of `x`.
+pub(super) fn le_bytes<B: BigInteger>(x: &B, n8: usize) -> Vec<u8> {
+    let mut bytes = x.to_bytes_le();
+    bytes.resize(n8, 0);
+    bytes
+}
+
+fn write_u32(bytes: &mut Vec<u8>, x: u32) {
+    bytes.extend_from_slice(&x.to_le_bytes());
+}
+
+/// Appends the linear combination `lc` of wires.
+fn write_lc<F: PrimeField>(bytes: &mut Vec<u8>, lc: &BTreeMap<u32, F>, n8: usize) {
+    write_u32(bytes, lc.len() as u32);
+    for (wire, coeff) in lc {
+        write_u32(bytes, *wire);
+        bytes.extend_from_slice(&le_bytes(&coeff.into_bigint(), n8));
+    }
+}
+
+/// Returns a binary file with the `magic` bytes, `version` and sections
+/// `(type, contents)`.
+fn file(magic: &[u8; 4], version: u32, sections: &[(u32, Vec<u8>)]) -> Vec<u8> {
+    let mut bytes = magic.to_vec();
+    write_u32(&mut bytes, version);
+    write_u32(&mut bytes, sections.len() as u32);
+    for (kind, contents) in sections {
+        write_u32(&mut bytes, *kind);
+        bytes.extend_from_slice(&(contents.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(contents);
+    }
+    bytes
+}
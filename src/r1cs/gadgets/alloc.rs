@@ -0,0 +1,102 @@
+//! Allocation of structured witnesses.
+
+use ark_ff::{Fp, FpConfig, PrimeField};
+use ark_std::{fmt::Debug, vec::Vec};
+
+use super::{AllocatedScalar, Boolean};
+use crate::r1cs::{ConstraintSystem, R1CSError};
+
+/// A value which can be allocated as variables of a constraint system.
+///
+/// With the `derive` feature, `#[derive(AllocVar)]` implements the trait
+/// for structs whose fields all implement it, such as
+///
+/// ```text
+/// #[derive(AllocVar)]
+/// struct Transfer {
+///     amount: u64,
+///     asset: Fr,
+///     nonce: [u64; 4],
+/// }
+/// ```
+///
+/// The derive also defines the struct `TransferVar<F>` of the allocated
+/// variables, with the fields of `Transfer` and the same visibility, and
+/// `Transfer::allocate(cs, value)` allocates the fields in declaration
+/// order.  Generic structs, enums and structs without fields are not
+/// supported.
+pub trait AllocVar<F: PrimeField> {
+    /// The allocated variables of the value.
+    type Var: Clone + Debug;
+
+    /// Allocates the variables of a value in `cs`, with the assignments
+    /// of `value` for the prover, or without assignments for the
+    /// verifier, which passes `None`.
+    fn allocate<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        value: Option<&Self>,
+    ) -> Result<Self::Var, R1CSError>;
+}
+
+/// Allocates the field element as a single variable.  This covers the
+/// prime fields of arkworks: a blanket impl over `PrimeField` would
+/// conflict with the impls for the integers.
+impl<P: FpConfig<N>, const N: usize> AllocVar<Fp<P, N>> for Fp<P, N> {
+    type Var = AllocatedScalar<Fp<P, N>>;
+
+    fn allocate<CS: ConstraintSystem<Fp<P, N>>>(
+        cs: &mut CS,
+        value: Option<&Self>,
+    ) -> Result<Self::Var, R1CSError> {
+        AllocatedScalar::allocate(cs, value.copied())
+    }
+}
+
+macro_rules! impl_alloc_var_for_integer {
+    ($($t:ty),*) => {
+        $(
+            /// Allocates the integer as a single variable, without
+            /// constraining its range.
+            impl<F: PrimeField> AllocVar<F> for $t {
+                type Var = AllocatedScalar<F>;
+
+                fn allocate<CS: ConstraintSystem<F>>(
+                    cs: &mut CS,
+                    value: Option<&Self>,
+                ) -> Result<Self::Var, R1CSError> {
+                    AllocatedScalar::allocate(cs, value.map(|v| F::from(*v)))
+                }
+            }
+        )*
+    };
+}
+
+impl_alloc_var_for_integer!(u8, u16, u32, u64, u128);
+
+/// Allocates the boolean as a [`Boolean`], constrained to be `0` or `1`.
+impl<F: PrimeField> AllocVar<F> for bool {
+    type Var = Boolean<F>;
+
+    fn allocate<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        value: Option<&Self>,
+    ) -> Result<Self::Var, R1CSError> {
+        Boolean::allocate(cs, value.copied())
+    }
+}
+
+impl<F: PrimeField, T: AllocVar<F>, const N: usize> AllocVar<F> for [T; N] {
+    type Var = [T::Var; N];
+
+    fn allocate<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        value: Option<&Self>,
+    ) -> Result<Self::Var, R1CSError> {
+        let vars = (0..N)
+            .map(|i| T::allocate(cs, value.map(|v| &v[i])))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(vars
+            .try_into()
+            .unwrap_or_else(|_| unreachable!("allocated exactly N variables")))
+    }
+}
@@ -0,0 +1,68 @@
+//! Conservation of committed amounts in confidential transactions.
+
+use ark_ff::PrimeField;
+use ark_std::{string::ToString, vec::Vec};
+
+use super::{AllocatedScalar, BitDecomposition, Gadget};
+use crate::r1cs::{ConstraintSystem, LinearCombination, R1CSError};
+
+/// Enforces that the amounts of a confidential transaction balance, that
+/// is `sum(inputs) = sum(outputs) + fee`, and that every output is in
+/// the range `[0, 2^n)`.
+///
+/// The range checks of the outputs cost `n` multipliers each, while
+/// the balance itself is a single linear constraint.  Without them, an
+/// output of `-x` would let the transaction create `x` out of thin air.
+///
+/// The inputs are not range-checked, as they are the outputs of earlier
+/// transactions which were already range-checked when they were
+/// created.  The fee is a linear combination, so it can be either a
+/// public constant or a variable, in which case the caller is
+/// responsible for checking its range.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BalanceConservation {
+    n: usize,
+}
+
+impl BalanceConservation {
+    /// Creates a gadget for outputs of `n` bits.
+    pub fn new(n: usize) -> Self {
+        BalanceConservation { n }
+    }
+}
+
+impl<F: PrimeField> Gadget<F> for BalanceConservation {
+    type Input = (
+        Vec<AllocatedScalar<F>>,
+        Vec<AllocatedScalar<F>>,
+        LinearCombination<F>,
+    );
+    type Output = ();
+
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        (inputs, outputs, fee): Self::Input,
+    ) -> Result<(), R1CSError> {
+        // The outputs and the fee must not wrap around the field.
+        let total_bits = self.n + (usize::BITS - outputs.len().leading_zeros()) as usize;
+        if total_bits + 1 >= F::MODULUS_BIT_SIZE as usize {
+            return Err(R1CSError::GadgetError {
+                description: "balance bit width exceeds the field capacity".to_string(),
+            });
+        }
+
+        for output in &outputs {
+            BitDecomposition::new(self.n).synthesize(cs, *output)?;
+        }
+
+        // Enforce sum(inputs) - sum(outputs) - fee = 0
+        let balance = inputs.iter().fold(-fee, |lc, input| lc + input.variable);
+        let balance = outputs
+            .iter()
+            .fold(balance, |lc, output| lc - output.variable);
+        cs.constrain(balance);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,386 @@
+//! Arithmetic on big unsigned integers, split into limbs.
+
+use ark_ff::PrimeField;
+use ark_std::{string::ToString, vec, vec::Vec};
+use num_bigint::{BigInt, BigUint, Sign};
+
+use super::bits::{decompose, recompose};
+use super::AllocatedScalar;
+use crate::r1cs::{ConstraintSystem, LinearCombination, R1CSError};
+
+/// A big unsigned integer in the constraint system, as little-endian
+/// limbs of `limb_bits` bits, together with its value, which is only
+/// known to the prover.
+///
+/// The limbs are always range-checked, so an integer of `n` limbs is in
+/// the range `[0, 2^(n * limb_bits))`.  Results of operations are
+/// allocated and range-checked in turn, and their relation to the
+/// operands is enforced by comparing the limbs of both sides one by one,
+/// propagating the carries.  This works for any modulus known only at
+/// proving time, such as a committed RSA modulus.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BigUintVar<F: PrimeField> {
+    limbs: Vec<LinearCombination<F>>,
+    value: Option<BigUint>,
+    limb_bits: usize,
+}
+
+impl<F: PrimeField> BigUintVar<F> {
+    /// Allocates a new integer of `num_limbs` limbs of `limb_bits` bits,
+    /// using `num_limbs * limb_bits` multipliers.
+    pub fn allocate<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        value: Option<BigUint>,
+        limb_bits: usize,
+        num_limbs: usize,
+    ) -> Result<Self, R1CSError> {
+        check_limb_bits::<F>(limb_bits)?;
+        if value
+            .as_ref()
+            .is_some_and(|v| v.bits() as usize > limb_bits * num_limbs)
+        {
+            return Err(R1CSError::GadgetError {
+                description: "big integer does not fit in its limbs".to_string(),
+            });
+        }
+        let limb_values = value.as_ref().map(|v| to_limbs(v, limb_bits, num_limbs));
+
+        let mut limbs = Vec::with_capacity(num_limbs);
+        for i in 0..num_limbs {
+            let limb = limb_values.as_ref().map(|l| F::from(l[i].clone()));
+            limbs.push(recompose(&decompose(cs, limb, limb_bits)?));
+        }
+        Ok(BigUintVar {
+            limbs,
+            value,
+            limb_bits,
+        })
+    }
+
+    /// Creates an integer from existing `limbs` of `limb_bits` bits, such
+    /// as committed variables, and enforces that they are in range.
+    pub fn from_limbs<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        limbs: &[AllocatedScalar<F>],
+        limb_bits: usize,
+    ) -> Result<Self, R1CSError> {
+        check_limb_bits::<F>(limb_bits)?;
+        let value = limbs
+            .iter()
+            .rev()
+            .try_fold(BigUint::default(), |acc, limb| {
+                Some((acc << limb_bits) + Into::<BigUint>::into(limb.assignment?))
+            });
+        for limb in limbs {
+            let bits = decompose(cs, limb.assignment, limb_bits)?;
+            cs.constrain(recompose(&bits) - limb.variable);
+        }
+        Ok(BigUintVar {
+            limbs: limbs.iter().map(|l| l.variable.into()).collect(),
+            value,
+            limb_bits,
+        })
+    }
+
+    /// Returns a constant integer with limbs of `limb_bits` bits.
+    pub fn constant(value: &BigUint, limb_bits: usize) -> Result<Self, R1CSError> {
+        check_limb_bits::<F>(limb_bits)?;
+        let num_limbs = (value.bits() as usize).div_ceil(limb_bits).max(1);
+        Ok(BigUintVar {
+            limbs: to_limbs(value, limb_bits, num_limbs)
+                .into_iter()
+                .map(|l| F::from(l).into())
+                .collect(),
+            value: Some(value.clone()),
+            limb_bits,
+        })
+    }
+
+    /// Returns the value of the integer, if known.
+    pub fn value(&self) -> Option<&BigUint> {
+        self.value.as_ref()
+    }
+
+    /// Returns the little-endian limbs of the integer.
+    pub fn limbs(&self) -> &[LinearCombination<F>] {
+        &self.limbs
+    }
+
+    /// Returns the number of bits of every limb.
+    pub fn limb_bits(&self) -> usize {
+        self.limb_bits
+    }
+
+    /// Returns `self + other`.
+    pub fn add<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        other: &Self,
+    ) -> Result<Self, R1CSError> {
+        self.check_compatible(other)?;
+        let num_limbs = self.limbs.len().max(other.limbs.len()) + 1;
+        let value = self
+            .value
+            .as_ref()
+            .zip(other.value.as_ref())
+            .map(|(a, b)| a + b);
+        let sum = Self::allocate(cs, value, self.limb_bits, num_limbs)?;
+
+        let lhs = Poly::from(self).add(&Poly::from(other));
+        lhs.enforce_equal(cs, &Poly::from(&sum), self.limb_bits)?;
+        Ok(sum)
+    }
+
+    /// Returns `self * other`, using one multiplier for every pair of
+    /// limbs, in addition to the cost of the product.
+    pub fn mul<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        other: &Self,
+    ) -> Result<Self, R1CSError> {
+        self.check_compatible(other)?;
+        let num_limbs = self.limbs.len() + other.limbs.len();
+        let value = self
+            .value
+            .as_ref()
+            .zip(other.value.as_ref())
+            .map(|(a, b)| a * b);
+        let product = Self::allocate(cs, value, self.limb_bits, num_limbs)?;
+
+        let lhs = Poly::from(self).mul(cs, &Poly::from(other));
+        lhs.enforce_equal(cs, &Poly::from(&product), self.limb_bits)?;
+        Ok(product)
+    }
+
+    /// Returns `self mod modulus`, by allocating the quotient `q` and the
+    /// remainder `r`, and enforcing `self = q * modulus + r` and
+    /// `r < modulus`.
+    ///
+    /// The modulus may be allocated as well, and must not be zero.
+    pub fn rem<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        modulus: &Self,
+    ) -> Result<Self, R1CSError> {
+        self.check_compatible(modulus)?;
+        let (q, r) = match (&self.value, &modulus.value) {
+            (Some(_), Some(m)) if *m == BigUint::default() => {
+                return Err(R1CSError::GadgetError {
+                    description: "big integer modulus is zero".to_string(),
+                })
+            }
+            (Some(a), Some(m)) => (Some(a / m), Some(a % m)),
+            _ => (None, None),
+        };
+        let q = Self::allocate(cs, q, self.limb_bits, self.limbs.len())?;
+        let r = Self::allocate(cs, r, self.limb_bits, modulus.limbs.len())?;
+
+        // Enforce self = q * modulus + r
+        let rhs = Poly::from(&q)
+            .mul(cs, &Poly::from(modulus))
+            .add(&Poly::from(&r));
+        Poly::from(self).enforce_equal(cs, &rhs, self.limb_bits)?;
+
+        // Enforce r < modulus, as d + r + 1 = modulus for some d >= 0.
+        let d = r
+            .value
+            .as_ref()
+            .zip(modulus.value.as_ref())
+            .map(|(r, m)| m - r - 1u32);
+        let d = Self::allocate(cs, d, self.limb_bits, modulus.limbs.len())?;
+        let one = Self::constant(&BigUint::from(1u32), self.limb_bits)?;
+        let lhs = Poly::from(&d).add(&Poly::from(&r)).add(&Poly::from(&one));
+        lhs.enforce_equal(cs, &Poly::from(modulus), self.limb_bits)?;
+
+        Ok(r)
+    }
+
+    /// Returns `self * other mod modulus`.
+    pub fn mul_mod<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        other: &Self,
+        modulus: &Self,
+    ) -> Result<Self, R1CSError> {
+        self.mul(cs, other)?.rem(cs, modulus)
+    }
+
+    /// Enforces that `self` and `other` are the same integer.
+    pub fn enforce_equal<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        other: &Self,
+    ) -> Result<(), R1CSError> {
+        self.check_compatible(other)?;
+        Poly::from(self).enforce_equal(cs, &Poly::from(other), self.limb_bits)
+    }
+
+    fn check_compatible(&self, other: &Self) -> Result<(), R1CSError> {
+        if self.limb_bits != other.limb_bits {
+            return Err(R1CSError::GadgetError {
+                description: "big integers have different limb sizes".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Limbs must be small enough for products of limbs, and sums of those,
+/// not to wrap around the field.
+fn check_limb_bits<F: PrimeField>(limb_bits: usize) -> Result<(), R1CSError> {
+    if limb_bits == 0 || 2 * limb_bits + 32 >= F::MODULUS_BIT_SIZE as usize {
+        return Err(R1CSError::GadgetError {
+            description: "big integer limbs are too large for the field".to_string(),
+        });
+    }
+    Ok(())
+}
+
+fn to_limbs(value: &BigUint, limb_bits: usize, num_limbs: usize) -> Vec<BigUint> {
+    let mask = (BigUint::from(1u32) << limb_bits) - 1u32;
+    (0..num_limbs)
+        .map(|i| (value >> (i * limb_bits)) & &mask)
+        .collect()
+}
+
+fn to_field<F: PrimeField>(x: &BigInt) -> F {
+    let magnitude = F::from(x.magnitude().clone());
+    match x.sign() {
+        Sign::Minus => -magnitude,
+        _ => magnitude,
+    }
+}
+
+/// An integer as a polynomial in `2^limb_bits`, whose coefficients are
+/// not reduced: they are only known to be less than `2^max_bits`.
+struct Poly<F: PrimeField> {
+    coeffs: Vec<LinearCombination<F>>,
+    values: Option<Vec<BigInt>>,
+    max_bits: usize,
+}
+
+impl<F: PrimeField> From<&BigUintVar<F>> for Poly<F> {
+    fn from(x: &BigUintVar<F>) -> Self {
+        Poly {
+            coeffs: x.limbs.clone(),
+            values: x.value.as_ref().map(|v| {
+                to_limbs(v, x.limb_bits, x.limbs.len())
+                    .into_iter()
+                    .map(BigInt::from)
+                    .collect()
+            }),
+            max_bits: x.limb_bits,
+        }
+    }
+}
+
+impl<F: PrimeField> Poly<F> {
+    fn add(&self, other: &Self) -> Self {
+        let n = self.coeffs.len().max(other.coeffs.len());
+        let coeff = |p: &Self, i: usize| p.coeffs.get(i).cloned().unwrap_or_default();
+        let coeffs = (0..n).map(|i| coeff(self, i) + coeff(other, i)).collect();
+        let values = self
+            .values
+            .as_ref()
+            .zip(other.values.as_ref())
+            .map(|(a, b)| {
+                let value = |v: &[BigInt], i: usize| v.get(i).cloned().unwrap_or_default();
+                (0..n).map(|i| value(a, i) + value(b, i)).collect()
+            });
+        Poly {
+            coeffs,
+            values,
+            max_bits: self.max_bits.max(other.max_bits) + 1,
+        }
+    }
+
+    fn mul<CS: ConstraintSystem<F>>(&self, cs: &mut CS, other: &Self) -> Self {
+        let n = self.coeffs.len() + other.coeffs.len() - 1;
+        let mut coeffs = vec![LinearCombination::default(); n];
+        for (i, a) in self.coeffs.iter().enumerate() {
+            for (j, b) in other.coeffs.iter().enumerate() {
+                let (_, _, o) = cs.multiply(a.clone(), b.clone());
+                coeffs[i + j] = coeffs[i + j].clone() + o;
+            }
+        }
+        let values = self
+            .values
+            .as_ref()
+            .zip(other.values.as_ref())
+            .map(|(a, b)| {
+                let mut values = vec![BigInt::default(); n];
+                for (i, a) in a.iter().enumerate() {
+                    for (j, b) in b.iter().enumerate() {
+                        values[i + j] += a * b;
+                    }
+                }
+                values
+            });
+        let terms = self.coeffs.len().min(other.coeffs.len());
+        Poly {
+            coeffs,
+            values,
+            max_bits: self.max_bits
+                + other.max_bits
+                + (usize::BITS - terms.leading_zeros()) as usize,
+        }
+    }
+
+    /// Enforces that both polynomials evaluate to the same integer at
+    /// `2^limb_bits`, by enforcing `d_i + c_(i-1) = c_i * 2^limb_bits` for
+    /// the differences `d_i` of the coefficients and range-checked
+    /// carries `c_i`, with the last carry being zero.
+    fn enforce_equal<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        other: &Self,
+        limb_bits: usize,
+    ) -> Result<(), R1CSError> {
+        let max_bits = self.max_bits.max(other.max_bits);
+        if max_bits + 2 >= F::MODULUS_BIT_SIZE as usize {
+            return Err(R1CSError::GadgetError {
+                description: "big integer limbs are too large for the field".to_string(),
+            });
+        }
+        let carry_bits = max_bits.saturating_sub(limb_bits) + 2;
+        let carry_offset = BigInt::from(1u32) << (carry_bits - 1);
+        let shift = to_field::<F>(&(BigInt::from(1u32) << limb_bits));
+
+        let diff = self.add(&other.neg());
+        let mut carry = LinearCombination::default();
+        let mut carry_value = Some(BigInt::default());
+        let n = diff.coeffs.len();
+        for (i, d) in diff.coeffs.into_iter().enumerate() {
+            let total = d + carry;
+            if i + 1 == n {
+                cs.constrain(total);
+                break;
+            }
+            let next_value = diff
+                .values
+                .as_ref()
+                .zip(carry_value)
+                .map(|(v, c)| (&v[i] + c) >> limb_bits);
+
+            // Allocate the carry shifted into [0, 2^carry_bits).
+            let shifted = next_value
+                .as_ref()
+                .map(|c| to_field::<F>(&(c + &carry_offset)));
+            let bits = decompose(cs, shifted, carry_bits)?;
+            let next = recompose(&bits) - to_field::<F>(&carry_offset);
+            cs.constrain(total - next.clone() * shift);
+
+            carry = next;
+            carry_value = next_value;
+        }
+        Ok(())
+    }
+
+    fn neg(&self) -> Self {
+        Poly {
+            coeffs: self.coeffs.iter().map(|c| -c.clone()).collect(),
+            values: self.values.as_ref().map(|v| v.iter().map(|x| -x).collect()),
+            max_bits: self.max_bits,
+        }
+    }
+}
@@ -0,0 +1,88 @@
+//! Bit decomposition of allocated variables.
+
+use ark_ff::{BigInteger, PrimeField};
+use ark_std::vec::Vec;
+
+use super::{AllocatedScalar, Gadget};
+use crate::r1cs::{ConstraintSystem, LinearCombination, R1CSError};
+
+/// Decomposes a variable into `n` bits, enforcing that it is in the
+/// range `[0, 2^n)`.
+///
+/// Each bit `b` is allocated together with its complement `1 - b` in a
+/// single multiplier, whose output is constrained to zero, so the
+/// decomposition costs `n` multipliers.  The bits are returned in
+/// little-endian order.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BitDecomposition {
+    n: usize,
+}
+
+impl BitDecomposition {
+    /// Creates a gadget decomposing a variable into `n` bits.
+    pub fn new(n: usize) -> Self {
+        BitDecomposition { n }
+    }
+
+    /// Returns the number of bits of the decomposition.
+    pub fn bits(&self) -> usize {
+        self.n
+    }
+}
+
+impl<F: PrimeField> Gadget<F> for BitDecomposition {
+    type Input = AllocatedScalar<F>;
+    type Output = Vec<AllocatedScalar<F>>;
+
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        v: AllocatedScalar<F>,
+    ) -> Result<Self::Output, R1CSError> {
+        let bits = decompose(cs, v.assignment, self.n)?;
+
+        // Enforce that v = Sum(b_i * 2^i, i = 0..n-1)
+        cs.constrain(recompose(&bits) - v.variable);
+
+        Ok(bits)
+    }
+}
+
+/// Allocates the `n` little-endian bits of `value`, constraining each of
+/// them to be boolean, but not their recomposition.
+pub(super) fn decompose<F: PrimeField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    value: Option<F>,
+    n: usize,
+) -> Result<Vec<AllocatedScalar<F>>, R1CSError> {
+    let repr = value.map(|v| v.into_bigint());
+    let mut bits = Vec::with_capacity(n);
+
+    for i in 0..n {
+        let bit = repr.map(|r| if r.get_bit(i) { F::one() } else { F::zero() });
+
+        // Create low-level variables and add them to constraints
+        let (a, b, o) = cs.allocate_multiplier(bit.map(|b| (F::one() - b, b)))?;
+
+        // Enforce a * b = 0, so one of (a,b) is zero
+        cs.constrain(o.into());
+
+        // Enforce that a = 1 - b, so they both are 1 or 0.
+        cs.constrain(a + b - LinearCombination::from(F::one()));
+
+        bits.push(AllocatedScalar::new(b, bit));
+    }
+
+    Ok(bits)
+}
+
+/// Returns the linear combination `Sum(b_i * 2^i)` of little-endian `bits`.
+pub(super) fn recompose<F: PrimeField>(bits: &[AllocatedScalar<F>]) -> LinearCombination<F> {
+    let mut exp_2 = F::one();
+    let mut lc = LinearCombination::default();
+    for bit in bits {
+        lc = lc + bit.variable * exp_2;
+        exp_2.double_in_place();
+    }
+    lc
+}
@@ -0,0 +1,165 @@
+//! Boolean logic over constrained bits.
+
+use ark_ff::PrimeField;
+use ark_std::{string::ToString, vec::Vec};
+
+use super::AllocatedScalar;
+use crate::r1cs::{ConstraintSystem, LinearCombination, R1CSError};
+
+/// A boolean value in the constraint system.
+///
+/// A `Boolean` is a linear combination which is known to evaluate to `0`
+/// or `1`, together with its value, which is only known to the prover.
+/// Keeping a linear combination rather than a variable makes negation
+/// and constants free: only [`Boolean::and`] and the operations built on
+/// it allocate a multiplier.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Boolean<F: PrimeField> {
+    lc: LinearCombination<F>,
+    value: Option<bool>,
+}
+
+impl<F: PrimeField> Boolean<F> {
+    /// Allocates a new boolean with the given `value`, constraining it
+    /// to be `0` or `1`.  This costs a single multiplier.
+    pub fn allocate<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        value: Option<bool>,
+    ) -> Result<Self, R1CSError> {
+        let bit = value.map(F::from);
+        let (a, b, o) = cs.allocate_multiplier(bit.map(|b| (F::one() - b, b)))?;
+
+        // Enforce a * b = 0 and a = 1 - b, so b is 0 or 1.
+        cs.constrain(o.into());
+        cs.constrain(a + b - F::one());
+
+        Ok(Boolean {
+            lc: b.into(),
+            value,
+        })
+    }
+
+    /// Returns a constant boolean.
+    pub fn constant(value: bool) -> Self {
+        Boolean {
+            lc: F::from(value).into(),
+            value: Some(value),
+        }
+    }
+
+    /// Wraps a variable which is already constrained to be `0` or `1`,
+    /// such as the bits of a [`BitDecomposition`](super::BitDecomposition)
+    /// or the flag returned by a [`Compare`](super::Compare) gadget.
+    ///
+    /// This does not add any constraint: use [`Boolean::enforce`] first
+    /// if the variable is not known to be boolean.
+    pub fn from_bit(bit: AllocatedScalar<F>) -> Result<Self, R1CSError> {
+        let value = match bit.assignment {
+            None => None,
+            Some(v) if v.is_zero() => Some(false),
+            Some(v) if v.is_one() => Some(true),
+            Some(_) => {
+                return Err(R1CSError::GadgetError {
+                    description: "assignment of a boolean variable is not 0 or 1".to_string(),
+                })
+            }
+        };
+        Ok(Boolean {
+            lc: bit.variable.into(),
+            value,
+        })
+    }
+
+    /// Wraps a linear combination which is already known to evaluate to
+    /// `0` or `1`, such as the output of a bitwise function computed by
+    /// another gadget, without adding any constraint.
+    pub(super) fn from_lc_unchecked(lc: LinearCombination<F>, value: Option<bool>) -> Self {
+        Boolean { lc, value }
+    }
+
+    /// Enforces that `x` is `0` or `1`, using a single multiplier.
+    pub fn enforce<CS: ConstraintSystem<F>>(cs: &mut CS, x: LinearCombination<F>) {
+        let (_, _, o) = cs.multiply(x.clone(), LinearCombination::from(F::one()) - x);
+        cs.constrain(o.into());
+    }
+
+    /// Returns the linear combination of the boolean.
+    pub fn lc(&self) -> LinearCombination<F> {
+        self.lc.clone()
+    }
+
+    /// Returns the value of the boolean, if known.
+    pub fn value(&self) -> Option<bool> {
+        self.value
+    }
+
+    /// Returns `NOT self`, without any constraint.
+    pub fn not(&self) -> Self {
+        Boolean {
+            lc: LinearCombination::from(F::one()) - self.lc.clone(),
+            value: self.value.map(|v| !v),
+        }
+    }
+
+    /// Returns `a AND b`, using a single multiplier.
+    pub fn and<CS: ConstraintSystem<F>>(cs: &mut CS, a: &Self, b: &Self) -> Self {
+        let (_, _, o) = cs.multiply(a.lc.clone(), b.lc.clone());
+        Boolean {
+            lc: o.into(),
+            value: a.value.zip(b.value).map(|(a, b)| a & b),
+        }
+    }
+
+    /// Returns `a NAND b`, using a single multiplier.
+    pub fn nand<CS: ConstraintSystem<F>>(cs: &mut CS, a: &Self, b: &Self) -> Self {
+        Self::and(cs, a, b).not()
+    }
+
+    /// Returns `a OR b`, using a single multiplier.
+    pub fn or<CS: ConstraintSystem<F>>(cs: &mut CS, a: &Self, b: &Self) -> Self {
+        // a OR b = NOT (NOT a AND NOT b)
+        Self::and(cs, &a.not(), &b.not()).not()
+    }
+
+    /// Returns `a XOR b`, using a single multiplier.
+    pub fn xor<CS: ConstraintSystem<F>>(cs: &mut CS, a: &Self, b: &Self) -> Self {
+        // a XOR b = a + b - 2ab
+        let (_, _, o) = cs.multiply(a.lc.clone(), b.lc.clone());
+        Boolean {
+            lc: a.lc.clone() + b.lc.clone() - o * F::from(2u64),
+            value: a.value.zip(b.value).map(|(a, b)| a ^ b),
+        }
+    }
+
+    /// Returns the AND of all `inputs`, using `inputs.len() - 1`
+    /// multipliers arranged in a balanced tree.
+    ///
+    /// The AND of no inputs is `true`.
+    pub fn and_all<CS: ConstraintSystem<F>>(cs: &mut CS, inputs: &[Self]) -> Self {
+        match inputs.len() {
+            0 => Self::constant(true),
+            1 => inputs[0].clone(),
+            n => {
+                let (left, right) = inputs.split_at(n / 2);
+                let left = Self::and_all(cs, left);
+                let right = Self::and_all(cs, right);
+                Self::and(cs, &left, &right)
+            }
+        }
+    }
+
+    /// Returns the OR of all `inputs`, using `inputs.len() - 1`
+    /// multipliers arranged in a balanced tree.
+    ///
+    /// The OR of no inputs is `false`.
+    pub fn or_all<CS: ConstraintSystem<F>>(cs: &mut CS, inputs: &[Self]) -> Self {
+        let negated: Vec<Self> = inputs.iter().map(|b| b.not()).collect();
+        Self::and_all(cs, &negated).not()
+    }
+}
+
+impl<F: PrimeField> From<Boolean<F>> for LinearCombination<F> {
+    fn from(b: Boolean<F>) -> Self {
+        b.lc
+    }
+}
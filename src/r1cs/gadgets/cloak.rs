@@ -0,0 +1,294 @@
+//! Confidential multi-asset transfers, following the Cloak protocol.
+//!
+//! A value is a pair of a quantity and a flavor (asset type).  The
+//! [`cloak`] gadget proves that a list of input values and a list of
+//! output values have the same total quantity for every flavor, by
+//! grouping each side by flavor with a [`value_shuffle`], merging each
+//! group into a single value with [`k_mix`], and checking that both
+//! sides merge to the same values up to a final shuffle.
+
+use ark_ff::PrimeField;
+use ark_std::{string::ToString, vec::Vec};
+
+use super::bits::{decompose, recompose};
+use super::enforce_tuple_permutation;
+use crate::r1cs::{
+    ConstraintSystem, LinearCombination, R1CSError, RandomizableConstraintSystem,
+    RandomizedConstraintSystem, Variable,
+};
+
+/// The assignment of a value: a quantity of some flavor.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Value<F: PrimeField> {
+    /// The quantity of the value.
+    pub quantity: F,
+    /// The flavor (asset type) of the value.
+    pub flavor: F,
+}
+
+impl<F: PrimeField> Value<F> {
+    /// Creates a value with the given `quantity` and `flavor`.
+    pub fn new(quantity: u64, flavor: F) -> Self {
+        Value {
+            quantity: F::from(quantity),
+            flavor,
+        }
+    }
+
+    /// Returns the zero value, of quantity and flavor zero.
+    pub fn zero() -> Self {
+        Value {
+            quantity: F::zero(),
+            flavor: F::zero(),
+        }
+    }
+}
+
+/// A value in the constraint system, together with its assignment,
+/// which is only known to the prover.
+#[derive(Copy, Clone, Debug)]
+pub struct AllocatedValue<F: PrimeField> {
+    /// The variable of the quantity.
+    pub quantity: Variable<F>,
+    /// The variable of the flavor.
+    pub flavor: Variable<F>,
+    /// The assignment of the value, if known.
+    pub assignment: Option<Value<F>>,
+}
+
+impl<F: PrimeField> AllocatedValue<F> {
+    /// Creates a value from its variables, such as the variables of two
+    /// high-level commitments.
+    pub fn new(quantity: Variable<F>, flavor: Variable<F>, assignment: Option<Value<F>>) -> Self {
+        AllocatedValue {
+            quantity,
+            flavor,
+            assignment,
+        }
+    }
+
+    /// Allocates a new value, using a single multiplier.
+    pub fn allocate<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        assignment: Option<Value<F>>,
+    ) -> Result<Self, R1CSError> {
+        let (quantity, flavor, _) =
+            cs.allocate_multiplier(assignment.map(|v| (v.quantity, v.flavor)))?;
+        Ok(AllocatedValue {
+            quantity,
+            flavor,
+            assignment,
+        })
+    }
+
+    /// Allocates a value constrained to be zero, used for padding.
+    pub fn zero<CS: ConstraintSystem<F>>(cs: &mut CS) -> Result<Self, R1CSError> {
+        let value = Self::allocate(cs, Some(Value::zero()))?;
+        cs.constrain(value.quantity.into());
+        cs.constrain(value.flavor.into());
+        Ok(value)
+    }
+
+    /// Enforces that the quantity is in the range `[0, 2^n)`, using `n`
+    /// multipliers.
+    pub fn enforce_range<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        n: usize,
+    ) -> Result<(), R1CSError> {
+        let bits = decompose(cs, self.assignment.map(|v| v.quantity), n)?;
+        cs.constrain(recompose(&bits) - self.quantity);
+        Ok(())
+    }
+
+    fn lcs(&self) -> Vec<LinearCombination<F>> {
+        ark_std::vec![self.quantity.into(), self.flavor.into()]
+    }
+}
+
+/// Enforces that the values `y` are a permutation of the values `x`.
+pub fn value_shuffle<F: PrimeField, CS: RandomizableConstraintSystem<F>>(
+    cs: &mut CS,
+    x: &[AllocatedValue<F>],
+    y: &[AllocatedValue<F>],
+) -> Result<(), R1CSError> {
+    enforce_tuple_permutation(
+        cs,
+        x.iter().map(|v| v.lcs()).collect(),
+        y.iter().map(|v| v.lcs()).collect(),
+    )
+}
+
+/// Merges adjacent values of the same flavor, and returns the merged
+/// values.
+///
+/// The `k` inputs are merged in a chain of `k - 1` mixes, where each mix
+/// either passes its two inputs through, or merges them into a zero
+/// value and a value with the total quantity.  When the inputs are
+/// grouped by flavor, the prover merges each group, so the outputs are
+/// the total of every group together with zero values.  This costs
+/// `2 * k - 2` multipliers.
+pub fn k_mix<F: PrimeField, CS: RandomizableConstraintSystem<F>>(
+    cs: &mut CS,
+    inputs: &[AllocatedValue<F>],
+) -> Result<Vec<AllocatedValue<F>>, R1CSError> {
+    if inputs.len() <= 1 {
+        return Ok(inputs.to_vec());
+    }
+
+    // Compute the outputs C_i and the intermediate values D_i of the
+    // mixes (D_(i-1), A_(i+1)) -> (C_i, D_i), with D_(-1) = A_0 and the
+    // last output being the last intermediate value.
+    let assignments: Option<Vec<Value<F>>> = inputs.iter().map(|v| v.assignment).collect();
+    let (mut outputs, mut mids) = (Vec::new(), Vec::new());
+    let mut mid = assignments.as_ref().map(|a| a[0]);
+    for (i, input) in inputs.iter().enumerate().skip(1) {
+        let (c, d) = match (mid, input.assignment) {
+            (Some(a), Some(b)) if a.flavor == b.flavor => (
+                Some(Value::zero()),
+                Some(Value {
+                    quantity: a.quantity + b.quantity,
+                    flavor: a.flavor,
+                }),
+            ),
+            (a, b) => (a, b),
+        };
+        outputs.push(AllocatedValue::allocate(cs, c)?);
+        let d = AllocatedValue::allocate(cs, d)?;
+        if i + 1 < inputs.len() {
+            mids.push(d);
+        } else {
+            outputs.push(d);
+        }
+        mid = d.assignment;
+    }
+
+    let inputs = inputs.to_vec();
+    let outputs_copy = outputs.clone();
+    cs.specify_randomized_constraints(move |cs| {
+        let w = cs.challenge_scalar(b"k-mix challenge");
+        let k = inputs.len();
+        for i in 0..k - 1 {
+            let a = if i == 0 { inputs[0] } else { mids[i - 1] };
+            let d = if i + 2 < k {
+                mids[i]
+            } else {
+                outputs_copy[k - 1]
+            };
+            mix(cs, w, a, inputs[i + 1], outputs_copy[i], d);
+        }
+        Ok(())
+    })?;
+
+    Ok(outputs)
+}
+
+/// Enforces that `(c, d)` is either `(a, b)`, or a merge of `a` and `b`
+/// of the same flavor into the zero value and their total, using a
+/// single multiplier:
+///
+/// ```text
+/// ((a.q - c.q) + (a.f - c.f) w + (b.q - d.q) w^2 + (b.f - d.f) w^3) *
+/// (c.q + c.f w + (a.f - b.f) w^2 + (d.f - a.f) w^3 + (a.q + b.q - d.q) w^4) = 0
+/// ```
+fn mix<F: PrimeField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    w: F,
+    a: AllocatedValue<F>,
+    b: AllocatedValue<F>,
+    c: AllocatedValue<F>,
+    d: AllocatedValue<F>,
+) {
+    let combine = |terms: Vec<LinearCombination<F>>| {
+        let mut w_j = F::one();
+        let mut lc = LinearCombination::default();
+        for term in terms {
+            lc = lc + term * w_j;
+            w_j *= w;
+        }
+        lc
+    };
+
+    let pass = combine(ark_std::vec![
+        a.quantity - c.quantity,
+        a.flavor - c.flavor,
+        b.quantity - d.quantity,
+        b.flavor - d.flavor,
+    ]);
+    let merge = combine(ark_std::vec![
+        c.quantity.into(),
+        c.flavor.into(),
+        a.flavor - b.flavor,
+        d.flavor - a.flavor,
+        a.quantity + b.quantity - d.quantity,
+    ]);
+    let (_, _, o) = cs.multiply(pass, merge);
+    cs.constrain(o.into());
+}
+
+/// Enforces that the `outputs` have the same total quantity as the
+/// `inputs` for every flavor, and that every output quantity is in the
+/// range `[0, 2^64)`.
+///
+/// Every flavor of the inputs must have a nonzero total, and appear in
+/// the outputs.  The inputs are assumed to be in range, for instance as
+/// the outputs of a previous transaction.
+pub fn cloak<F: PrimeField, CS: RandomizableConstraintSystem<F>>(
+    cs: &mut CS,
+    inputs: &[AllocatedValue<F>],
+    outputs: &[AllocatedValue<F>],
+) -> Result<(), R1CSError> {
+    if inputs.is_empty() || outputs.is_empty() {
+        return Err(R1CSError::GadgetError {
+            description: "cloak needs at least one input and one output".to_string(),
+        });
+    }
+
+    let mut merged_inputs = group_and_merge(cs, inputs)?;
+    let mut merged_outputs = group_and_merge(cs, outputs)?;
+    let k = inputs.len().max(outputs.len());
+    for merged in [&mut merged_inputs, &mut merged_outputs] {
+        while merged.len() < k {
+            merged.push(AllocatedValue::zero(cs)?);
+        }
+    }
+    value_shuffle(cs, &merged_inputs, &merged_outputs)?;
+
+    for output in outputs {
+        output.enforce_range(cs, 64)?;
+    }
+    Ok(())
+}
+
+/// Shuffles `values` so that the values of the same flavor are adjacent,
+/// and merges them with [`k_mix`].
+fn group_and_merge<F: PrimeField, CS: RandomizableConstraintSystem<F>>(
+    cs: &mut CS,
+    values: &[AllocatedValue<F>],
+) -> Result<Vec<AllocatedValue<F>>, R1CSError> {
+    let grouped: Vec<Option<Value<F>>> = match values
+        .iter()
+        .map(|v| v.assignment)
+        .collect::<Option<Vec<_>>>()
+    {
+        Some(mut assignments) => {
+            let mut grouped = Vec::with_capacity(assignments.len());
+            while let Some(first) = assignments.first().copied() {
+                let (same, rest) = assignments
+                    .into_iter()
+                    .partition(|v| v.flavor == first.flavor);
+                grouped.extend::<Vec<_>>(same);
+                assignments = rest;
+            }
+            grouped.into_iter().map(Some).collect()
+        }
+        None => ark_std::vec![None; values.len()],
+    };
+
+    let grouped = grouped
+        .into_iter()
+        .map(|v| AllocatedValue::allocate(cs, v))
+        .collect::<Result<Vec<_>, _>>()?;
+    value_shuffle(cs, values, &grouped)?;
+    k_mix(cs, &grouped)
+}
@@ -0,0 +1,119 @@
+//! Comparisons of allocated variables.
+
+use ark_ff::PrimeField;
+use ark_std::string::ToString;
+
+use super::bits::{decompose, recompose};
+use super::{AllocatedScalar, Gadget};
+use crate::r1cs::{ConstraintSystem, LinearCombination, R1CSError};
+
+/// The relation checked by a [`Compare`] gadget.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    /// `a < b`
+    LessThan,
+    /// `a <= b`
+    LessOrEqual,
+    /// `a > b`
+    GreaterThan,
+    /// `a >= b`
+    GreaterOrEqual,
+}
+
+/// Compares two variables `a` and `b` which are known to fit in `n` bits.
+///
+/// Every comparison is reduced to `x >= y + k` for `k` in `{0, 1}`,
+/// which holds if and only if `x - y - k` is in the range `[0, 2^n)`.
+///
+/// As a [`Gadget`], `Compare` returns a boolean variable that is `1` if
+/// the comparison holds and `0` otherwise, using `n + 1` multipliers.
+/// [`Compare::enforce`] instead requires the comparison to hold, using
+/// `n` multipliers.
+///
+/// The gadget does not check that `a` and `b` fit in `n` bits: the
+/// caller must guarantee it, for example with a
+/// [`BitDecomposition`](super::BitDecomposition), otherwise the result
+/// is meaningless.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Compare {
+    op: Comparison,
+    n: usize,
+}
+
+impl Compare {
+    /// Creates a gadget checking `a op b` for `n`-bit values `a` and `b`.
+    pub fn new(op: Comparison, n: usize) -> Self {
+        Compare { op, n }
+    }
+
+    /// Adds constraints to `cs` enforcing that `a op b` holds.
+    pub fn enforce<F: PrimeField, CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        a: AllocatedScalar<F>,
+        b: AllocatedScalar<F>,
+    ) -> Result<(), R1CSError> {
+        self.check_bits::<F>(self.n)?;
+
+        // Enforce that x - y - k is in [0, 2^n)
+        let (diff, assignment) = self.difference(a, b, F::zero());
+        let bits = decompose(cs, assignment, self.n)?;
+        cs.constrain(recompose(&bits) - diff);
+
+        Ok(())
+    }
+
+    /// Returns the linear combination `x - y - k + offset` and its
+    /// assignment, where `a op b` is equivalent to `x >= y + k`.
+    fn difference<F: PrimeField>(
+        &self,
+        a: AllocatedScalar<F>,
+        b: AllocatedScalar<F>,
+        offset: F,
+    ) -> (LinearCombination<F>, Option<F>) {
+        let (x, y, k) = match self.op {
+            Comparison::LessThan => (b, a, F::one()),
+            Comparison::LessOrEqual => (b, a, F::zero()),
+            Comparison::GreaterThan => (a, b, F::one()),
+            Comparison::GreaterOrEqual => (a, b, F::zero()),
+        };
+        let lc = x.variable - y.variable + LinearCombination::from(offset - k);
+        let assignment = match (x.assignment, y.assignment) {
+            (Some(x), Some(y)) => Some(x - y + offset - k),
+            _ => None,
+        };
+        (lc, assignment)
+    }
+
+    /// Checks that `bits`-bit values fit in the field without wrapping around.
+    fn check_bits<F: PrimeField>(&self, bits: usize) -> Result<(), R1CSError> {
+        if bits + 1 >= F::MODULUS_BIT_SIZE as usize {
+            return Err(R1CSError::GadgetError {
+                description: "comparison bit width exceeds the field capacity".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl<F: PrimeField> Gadget<F> for Compare {
+    type Input = (AllocatedScalar<F>, AllocatedScalar<F>);
+    type Output = AllocatedScalar<F>;
+
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        (a, b): Self::Input,
+    ) -> Result<AllocatedScalar<F>, R1CSError> {
+        self.check_bits::<F>(self.n + 1)?;
+
+        // x - y - k + 2^n is in [0, 2^(n+1)), and its top bit
+        // is set if and only if x - y - k >= 0.
+        let offset = F::from(2u64).pow([self.n as u64]);
+        let (diff, assignment) = self.difference(a, b, offset);
+        let bits = decompose(cs, assignment, self.n + 1)?;
+        cs.constrain(recompose(&bits) - diff);
+
+        Ok(bits[self.n])
+    }
+}
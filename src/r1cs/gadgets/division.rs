@@ -0,0 +1,92 @@
+//! Integer division with remainder.
+
+use ark_ff::PrimeField;
+use ark_std::string::ToString;
+use num_bigint::BigUint;
+
+use super::bits::{decompose, recompose};
+use super::{AllocatedScalar, Gadget};
+use crate::r1cs::{ConstraintSystem, R1CSError};
+
+/// Divides a variable `a` by a variable `b`, returning the quotient `q`
+/// and the remainder `r` of the Euclidean division of `a` by `b`.
+///
+/// The gadget allocates `q` and `r` and enforces `a = q * b + r` with
+/// `q` and `r` in the range `[0, 2^n)` and `r < b`, using `3 * n + 2`
+/// multipliers.  As the right-hand side cannot wrap around the field,
+/// this also enforces that `a` fits in `2 * n` bits and `b` in `n + 1`
+/// bits, and that `b` is not zero.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DivRem {
+    n: usize,
+}
+
+impl DivRem {
+    /// Creates a gadget dividing variables with quotients and remainders
+    /// of `n` bits.
+    pub fn new(n: usize) -> Self {
+        DivRem { n }
+    }
+}
+
+impl<F: PrimeField> Gadget<F> for DivRem {
+    type Input = (AllocatedScalar<F>, AllocatedScalar<F>);
+    type Output = (AllocatedScalar<F>, AllocatedScalar<F>);
+
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        (a, b): Self::Input,
+    ) -> Result<Self::Output, R1CSError> {
+        if 2 * self.n + 1 >= F::MODULUS_BIT_SIZE as usize {
+            return Err(R1CSError::GadgetError {
+                description: "division bit width exceeds the field capacity".to_string(),
+            });
+        }
+
+        let values = a.assignment.zip(b.assignment).map(|(a, b)| {
+            let (a, b): (BigUint, BigUint) = (a.into(), b.into());
+            if b == BigUint::default() {
+                (F::zero(), F::zero())
+            } else {
+                (F::from(&a / &b), F::from(&a % &b))
+            }
+        });
+
+        let q = AllocatedScalar::allocate(cs, values.map(|(q, _)| q))?;
+        let r = AllocatedScalar::allocate(cs, values.map(|(_, r)| r))?;
+
+        // Enforce that q and r are in [0, 2^n)
+        for x in [q, r] {
+            let bits = decompose(cs, x.assignment, self.n)?;
+            cs.constrain(recompose(&bits) - x.variable);
+        }
+
+        // Enforce a = q * b + r
+        let (_, _, qb) = cs.multiply(q.variable.into(), b.variable.into());
+        cs.constrain(qb + r.variable - a.variable);
+
+        // Enforce r < b, as b - r - 1 is in [0, 2^n)
+        let d = r
+            .assignment
+            .zip(b.assignment)
+            .map(|(r, b)| b - r - F::one());
+        let d_bits = decompose(cs, d, self.n)?;
+        cs.constrain(recompose(&d_bits) + r.variable + F::one() - b.variable);
+
+        Ok((q, r))
+    }
+}
+
+/// Returns the quotient and the remainder of `a` divided by `b`, for
+/// quotients and remainders of `n` bits.
+///
+/// This is a shorthand for synthesizing a [`DivRem`] gadget.
+pub fn div_rem<F: PrimeField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    a: AllocatedScalar<F>,
+    b: AllocatedScalar<F>,
+    n: usize,
+) -> Result<(AllocatedScalar<F>, AllocatedScalar<F>), R1CSError> {
+    DivRem::new(n).synthesize(cs, (a, b))
+}
@@ -0,0 +1,110 @@
+//! Inner products and matrix-vector products.
+
+use ark_ff::PrimeField;
+use ark_std::{string::ToString, vec, vec::Vec};
+
+use super::AllocatedScalar;
+use crate::r1cs::{
+    ConstraintSystem, LinearCombination, R1CSError, RandomizableConstraintSystem,
+    RandomizedConstraintSystem,
+};
+
+/// Returns the inner product of `a` and `b`, using one multiplier per
+/// pair of elements and half a multiplier for the result.
+pub fn dot_product<F: PrimeField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    a: &[AllocatedScalar<F>],
+    b: &[AllocatedScalar<F>],
+) -> Result<AllocatedScalar<F>, R1CSError> {
+    if a.len() != b.len() {
+        return Err(R1CSError::GadgetError {
+            description: "inner product of vectors of different lengths".to_string(),
+        });
+    }
+    let mut sum = LinearCombination::default();
+    for (a, b) in a.iter().zip(b) {
+        let (_, _, o) = cs.multiply(a.variable.into(), b.variable.into());
+        sum = sum + o;
+    }
+    let value = inner_product(a, b);
+    let result = AllocatedScalar::allocate(cs, value)?;
+    cs.constrain(sum - result.variable);
+    Ok(result)
+}
+
+/// Returns the product of a public `matrix` and the vector `x`, without
+/// any multiplier, as the linear combinations of its elements.
+pub fn constant_matrix_vector_product<F: PrimeField>(
+    matrix: &[Vec<F>],
+    x: &[AllocatedScalar<F>],
+) -> Result<Vec<LinearCombination<F>>, R1CSError> {
+    check_shape(matrix, x.len())?;
+    Ok(matrix
+        .iter()
+        .map(|row| row.iter().zip(x).map(|(a, x)| (x.variable, *a)).collect())
+        .collect())
+}
+
+/// Returns the product of a `matrix` of variables and the vector `x`.
+///
+/// Computing every inner product would cost one multiplier per element
+/// of the matrix.  Instead, the result `y` is allocated, and checked
+/// with Freivalds' technique: with a challenge `r`, the gadget enforces
+/// ```text
+/// Sum(r^i * y_i) = Sum((Sum(r^i * A_ij)) * x_j),
+/// ```
+/// which only costs one multiplier per column of the matrix, and half a
+/// multiplier per row.
+pub fn matrix_vector_product<F: PrimeField, CS: RandomizableConstraintSystem<F>>(
+    cs: &mut CS,
+    matrix: &[Vec<AllocatedScalar<F>>],
+    x: &[AllocatedScalar<F>],
+) -> Result<Vec<AllocatedScalar<F>>, R1CSError> {
+    check_shape(matrix, x.len())?;
+    let y = matrix
+        .iter()
+        .map(|row| AllocatedScalar::allocate(cs, inner_product(row, x)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let (matrix, x, result) = (matrix.to_vec(), x.to_vec(), y.clone());
+    cs.specify_randomized_constraints(move |cs| {
+        let r = cs.challenge_scalar(b"matrix-vector challenge");
+
+        let mut lhs = LinearCombination::default();
+        let mut columns = vec![LinearCombination::default(); x.len()];
+        let mut r_i = F::one();
+        for (row, y_i) in matrix.iter().zip(&result) {
+            lhs = lhs + y_i.variable * r_i;
+            for (column, a) in columns.iter_mut().zip(row) {
+                *column = column.clone() + a.variable * r_i;
+            }
+            r_i *= r;
+        }
+
+        let mut rhs = LinearCombination::default();
+        for (column, x_j) in columns.into_iter().zip(&x) {
+            let (_, _, o) = cs.multiply(column, x_j.variable.into());
+            rhs = rhs + o;
+        }
+        cs.constrain(lhs - rhs);
+        Ok(())
+    })?;
+
+    Ok(y)
+}
+
+fn inner_product<F: PrimeField>(a: &[AllocatedScalar<F>], b: &[AllocatedScalar<F>]) -> Option<F> {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| Some(a.assignment? * b.assignment?))
+        .sum()
+}
+
+fn check_shape<T>(matrix: &[Vec<T>], columns: usize) -> Result<(), R1CSError> {
+    if matrix.iter().any(|row| row.len() != columns) {
+        return Err(R1CSError::GadgetError {
+            description: "matrix rows must have the length of the vector".to_string(),
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,279 @@
+//! Arithmetic on the points of an embedded elliptic curve.
+//!
+//! The gadgets in this module operate on the points of a short
+//! Weierstrass curve whose base field is the scalar field of the
+//! constraint system, such as secp256k1 inside a proof over secq256k1.
+
+use ark_ec::short_weierstrass::{Affine, SWCurveConfig};
+use ark_ec::{AffineRepr, CurveGroup, Group};
+use ark_ff::PrimeField;
+use ark_std::string::ToString;
+use digest::Digest;
+use sha3::Sha3_512;
+
+use super::Boolean;
+use crate::r1cs::{ConstraintSystem, LinearCombination, R1CSError};
+
+/// A point of an embedded curve in the constraint system, as the linear
+/// combinations of its affine coordinates, together with its value,
+/// which is only known to the prover.
+///
+/// The point at infinity cannot be represented.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PointVar<F: PrimeField> {
+    /// The `x` coordinate of the point.
+    pub x: LinearCombination<F>,
+    /// The `y` coordinate of the point.
+    pub y: LinearCombination<F>,
+    /// The coordinates of the point, if known.
+    pub value: Option<(F, F)>,
+}
+
+impl<F: PrimeField> PointVar<F> {
+    /// Returns a constant point.
+    pub fn constant<C: SWCurveConfig<BaseField = F>>(p: &Affine<C>) -> Result<Self, R1CSError> {
+        if p.infinity {
+            return Err(R1CSError::GadgetError {
+                description: "the point at infinity cannot be represented".to_string(),
+            });
+        }
+        Ok(PointVar {
+            x: p.x.into(),
+            y: p.y.into(),
+            value: Some((p.x, p.y)),
+        })
+    }
+
+    /// Allocates a new point with the given `value`, and enforces that
+    /// it is on the curve `C`, using four multipliers.
+    pub fn allocate<C: SWCurveConfig<BaseField = F>, CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        value: Option<Affine<C>>,
+    ) -> Result<Self, R1CSError> {
+        if value.is_some_and(|p| p.infinity) {
+            return Err(R1CSError::GadgetError {
+                description: "the point at infinity cannot be represented".to_string(),
+            });
+        }
+        let value = value.map(|p| (p.x, p.y));
+        let x = cs.allocate(value.map(|(x, _)| x))?;
+        let y = cs.allocate(value.map(|(_, y)| y))?;
+
+        // Enforce y^2 = x^3 + a * x + b
+        let (_, _, yy) = cs.multiply(y.into(), y.into());
+        let (_, _, xx) = cs.multiply(x.into(), x.into());
+        let (_, _, xxx) = cs.multiply(xx.into(), x.into());
+        cs.constrain(xxx + x * C::COEFF_A + C::COEFF_B - yy);
+
+        Ok(PointVar {
+            x: x.into(),
+            y: y.into(),
+            value,
+        })
+    }
+
+    /// Returns the value of the point as a point of the curve `C`, if known.
+    pub fn to_affine<C: SWCurveConfig<BaseField = F>>(&self) -> Option<Affine<C>> {
+        self.value.map(|(x, y)| Affine::new_unchecked(x, y))
+    }
+
+    /// Returns `self + other`, using four multipliers.
+    ///
+    /// The points must have distinct `x` coordinates, which the gadget
+    /// enforces: in particular, it cannot double a point, nor add a
+    /// point to its opposite.
+    pub fn add_incomplete<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        other: &Self,
+    ) -> Result<Self, R1CSError> {
+        let dx = other.x.clone() - self.x.clone();
+        let dy = other.y.clone() - self.y.clone();
+        let dx_value = self.value.zip(other.value).map(|(p, q)| q.0 - p.0);
+        let dx_inv = dx_value.map(|dx| dx.inverse().unwrap_or_else(F::zero));
+        let lambda = self
+            .value
+            .zip(other.value)
+            .zip(dx_inv)
+            .map(|((p, q), inv)| (q.1 - p.1) * inv);
+
+        // Enforce dx * dx_inv = 1, so the x coordinates are distinct.
+        let (l, _, o) = cs.allocate_multiplier(dx_value.zip(dx_inv))?;
+        cs.constrain(l - dx.clone());
+        cs.constrain(o - F::one());
+
+        // Enforce dx * lambda = dy
+        let (l, lambda_var, o) = cs.allocate_multiplier(dx_value.zip(lambda))?;
+        cs.constrain(l - dx);
+        cs.constrain(o - dy);
+
+        // x3 = lambda^2 - x1 - x2
+        let (_, _, lambda2) = cs.multiply(lambda_var.into(), lambda_var.into());
+        let x3 = (lambda2 - self.x.clone() - other.x.clone()).simplify();
+
+        // y3 = lambda * (x1 - x3) - y1
+        let (_, _, t) = cs.multiply(lambda_var.into(), self.x.clone() - x3.clone());
+        let y3 = (t - self.y.clone()).simplify();
+
+        let value = self
+            .value
+            .zip(other.value)
+            .zip(lambda)
+            .map(|((p, q), lambda)| {
+                let x3 = lambda.square() - p.0 - q.0;
+                (x3, lambda * (p.0 - x3) - p.1)
+            });
+
+        Ok(PointVar {
+            x: x3,
+            y: y3,
+            value,
+        })
+    }
+
+    /// Returns `-self`, without any constraint.
+    pub fn neg(&self) -> Self {
+        PointVar {
+            x: self.x.clone(),
+            y: -self.y.clone(),
+            value: self.value.map(|(x, y)| (x, -y)),
+        }
+    }
+
+    /// Enforces that `self` and `other` are the same point.
+    pub fn enforce_equal<CS: ConstraintSystem<F>>(&self, cs: &mut CS, other: &Self) {
+        cs.constrain(self.x.clone() - other.x.clone());
+        cs.constrain(self.y.clone() - other.y.clone());
+    }
+
+    /// Returns `a` if `bit` is true and `b` otherwise, using two
+    /// multipliers.
+    pub fn select<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        bit: &Boolean<F>,
+        a: &Self,
+        b: &Self,
+    ) -> Self {
+        // x = b.x + bit * (a.x - b.x), and likewise for y.
+        let (_, _, dx) = cs.multiply(bit.lc(), a.x.clone() - b.x.clone());
+        let (_, _, dy) = cs.multiply(bit.lc(), a.y.clone() - b.y.clone());
+        PointVar {
+            x: (b.x.clone() + dx).simplify(),
+            y: (b.y.clone() + dy).simplify(),
+            value: bit
+                .value()
+                .zip(a.value)
+                .zip(b.value)
+                .map(|((bit, a), b)| if bit { a } else { b }),
+        }
+    }
+
+    /// Returns `2 * self` on the curve `C`, using four multipliers.
+    ///
+    /// The point must not have order two, which the gadget enforces.
+    pub fn double<C: SWCurveConfig<BaseField = F>, CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+    ) -> Result<Self, R1CSError> {
+        let lambda = self.value.map(|(x, y)| {
+            let y2_inv = y.double().inverse().unwrap_or_else(F::zero);
+            (x.square() * F::from(3u64) + C::COEFF_A) * y2_inv
+        });
+
+        // Enforce 2y * lambda = 3x^2 + a
+        let (_, _, xx) = cs.multiply(self.x.clone(), self.x.clone());
+        let two_y = self.y.clone() * F::from(2u64);
+        let (l, lambda_var, o) =
+            cs.allocate_multiplier(self.value.map(|(_, y)| y.double()).zip(lambda))?;
+        cs.constrain(l - two_y);
+        cs.constrain(o - xx * F::from(3u64) - C::COEFF_A);
+
+        // x3 = lambda^2 - 2x
+        let (_, _, lambda2) = cs.multiply(lambda_var.into(), lambda_var.into());
+        let x3 = (lambda2 - self.x.clone() * F::from(2u64)).simplify();
+
+        // y3 = lambda * (x - x3) - y
+        let (_, _, t) = cs.multiply(lambda_var.into(), self.x.clone() - x3.clone());
+        let y3 = (t - self.y.clone()).simplify();
+
+        let value = self.value.zip(lambda).map(|((x, y), lambda)| {
+            let x3 = lambda.square() - x.double();
+            (x3, lambda * (x - x3) - y)
+        });
+
+        Ok(PointVar {
+            x: x3,
+            y: y3,
+            value,
+        })
+    }
+
+    /// Returns `k * base` for a constant `base` on the curve `C`, where
+    /// `k` is given by its little-endian `bits`, using six multipliers
+    /// per bit.
+    ///
+    /// The accumulator starts from an independent offset point, which is
+    /// subtracted at the end, so the incomplete additions only fail with
+    /// negligible probability, unless `k * base` is the point at
+    /// infinity, which cannot be represented.
+    pub fn mul_fixed<C: SWCurveConfig<BaseField = F>, CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        base: &Affine<C>,
+        bits: &[Boolean<F>],
+    ) -> Result<Self, R1CSError> {
+        let offset = hash_to_curve::<C>(b"ScalarMulOffset", 0);
+        let mut acc = Self::constant(&offset)?;
+        let mut power = base.into_group();
+        for bit in bits {
+            let sum = acc.add_incomplete(cs, &Self::constant(&power.into_affine())?)?;
+            acc = Self::select(cs, bit, &sum, &acc);
+            power.double_in_place();
+        }
+        acc.add_incomplete(cs, &Self::constant(&-offset)?)
+    }
+
+    /// Returns `k * self` on the curve `C`, where `k` is given by its
+    /// little-endian `bits`, using ten multipliers per bit.
+    ///
+    /// As for [`PointVar::mul_fixed`], the result must not be the point
+    /// at infinity.
+    pub fn mul<C: SWCurveConfig<BaseField = F>, CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        bits: &[Boolean<F>],
+    ) -> Result<Self, R1CSError> {
+        let offset = hash_to_curve::<C>(b"ScalarMulOffset", 0);
+        let mut acc = Self::constant(&offset)?;
+        let mut shifted_offset = offset.into_group();
+        for bit in bits.iter().rev() {
+            acc = acc.double::<C, _>(cs)?;
+            let sum = acc.add_incomplete(cs, self)?;
+            acc = Self::select(cs, bit, &sum, &acc);
+            shifted_offset.double_in_place();
+        }
+        acc.add_incomplete(cs, &Self::constant(&-shifted_offset.into_affine())?)
+    }
+}
+
+/// Derives the `i`-th point of a family of independent points on the
+/// curve `C`, by hashing `label` to the curve with try-and-increment.
+pub(super) fn hash_to_curve<C: SWCurveConfig>(label: &[u8], i: u64) -> Affine<C>
+where
+    C::BaseField: PrimeField,
+{
+    let mut counter = 0u64;
+    loop {
+        let mut hash = Sha3_512::new();
+        Digest::update(&mut hash, label);
+        Digest::update(&mut hash, i.to_le_bytes());
+        Digest::update(&mut hash, counter.to_le_bytes());
+        let x = C::BaseField::from_le_bytes_mod_order(&hash.finalize());
+        if let Some(p) = Affine::<C>::get_point_from_x_unchecked(x, false) {
+            let p = p.clear_cofactor();
+            if !p.is_zero() {
+                return p;
+            }
+        }
+        counter += 1;
+    }
+}
@@ -0,0 +1,75 @@
+//! Verification of ECDSA signatures on an embedded curve.
+
+use ark_ec::short_weierstrass::{Affine, SWCurveConfig};
+use ark_ec::CurveGroup;
+use ark_ff::{BigInteger, Field, PrimeField};
+use ark_std::{string::ToString, vec::Vec};
+
+use super::{Boolean, Gadget, PointVar};
+use crate::r1cs::{ConstraintSystem, R1CSError};
+
+/// Proves knowledge of a valid ECDSA signature `(r, s)` on a message
+/// hash `z` under a public key `P`, where `s` and `P` are hidden.
+///
+/// The verification equation `R = z/s * G + r/s * P`, where `r` is the
+/// `x` coordinate of `R`, is rewritten as
+/// ```text
+/// s * T + U = P,  with  T = R / r  and  U = -z/r * G,
+/// ```
+/// so that `T` and `U` can be computed outside of the constraint system
+/// from the public `R` and `z`, and the gadget only needs a fixed-base
+/// scalar multiplication.  This reveals `R`, but not the public key nor
+/// `s`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EcdsaVerify<C: SWCurveConfig> {
+    t: Affine<C>,
+    u: Affine<C>,
+}
+
+impl<C: SWCurveConfig> EcdsaVerify<C>
+where
+    C::BaseField: PrimeField,
+{
+    /// Creates the gadget for the signature nonce point `r` of a
+    /// signature on the message hash `z`.
+    pub fn new(r: &Affine<C>, z: C::ScalarField) -> Result<Self, R1CSError> {
+        let r_x = C::ScalarField::from_le_bytes_mod_order(&r.x.into_bigint().to_bytes_le());
+        let r_inv = match r_x.inverse() {
+            Some(r_inv) if !r.infinity => r_inv,
+            _ => {
+                return Err(R1CSError::GadgetError {
+                    description: "invalid ECDSA signature point".to_string(),
+                })
+            }
+        };
+        Ok(EcdsaVerify {
+            t: (*r * r_inv).into_affine(),
+            u: (C::GENERATOR * -(z * r_inv)).into_affine(),
+        })
+    }
+
+    /// Returns the points `(T, U)` of the verification equation.
+    pub fn points(&self) -> (Affine<C>, Affine<C>) {
+        (self.t, self.u)
+    }
+}
+
+impl<C: SWCurveConfig> Gadget<C::BaseField> for EcdsaVerify<C>
+where
+    C::BaseField: PrimeField,
+{
+    /// The little-endian bits of `s`, and the public key.
+    type Input = (Vec<Boolean<C::BaseField>>, PointVar<C::BaseField>);
+    type Output = ();
+
+    fn synthesize<CS: ConstraintSystem<C::BaseField>>(
+        &self,
+        cs: &mut CS,
+        (s, public_key): Self::Input,
+    ) -> Result<(), R1CSError> {
+        let st = PointVar::mul_fixed::<C, _>(cs, &self.t, &s)?;
+        let p = st.add_incomplete(cs, &PointVar::constant(&self.u)?)?;
+        p.enforce_equal(cs, &public_key);
+        Ok(())
+    }
+}
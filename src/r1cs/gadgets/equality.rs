@@ -0,0 +1,85 @@
+//! Zero and equality tests of linear combinations.
+
+use ark_ff::PrimeField;
+
+use super::{AllocatedScalar, Gadget};
+use crate::r1cs::{ConstraintSystem, LinearCombination, R1CSError};
+
+/// Returns a boolean variable that is `1` if a linear combination
+/// evaluates to zero, and `0` otherwise.
+///
+/// The prover supplies the inverse `inv` of the value `x` (or zero, if
+/// `x` is zero) and the gadget enforces
+/// ```text
+/// x * inv = 1 - flag
+/// x * flag = 0
+/// ```
+/// so `flag` is `1` if `x` is zero and `0` otherwise.  This costs two
+/// multipliers.
+///
+/// The input is the linear combination and its assignment, which is
+/// only known to the prover.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct IsZero;
+
+impl<F: PrimeField> Gadget<F> for IsZero {
+    type Input = (LinearCombination<F>, Option<F>);
+    type Output = AllocatedScalar<F>;
+
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        (x, assignment): Self::Input,
+    ) -> Result<AllocatedScalar<F>, R1CSError> {
+        let inv = assignment.map(|x| x.inverse().unwrap_or_else(F::zero));
+        let flag = assignment.map(|x| if x.is_zero() { F::one() } else { F::zero() });
+
+        // Enforce x * inv = 1 - flag, where inv is a free variable.
+        let (l, _, o) = cs.allocate_multiplier(assignment.zip(inv))?;
+        cs.constrain(l - x.clone());
+
+        // Enforce x * flag = 0, where flag = 1 - x * inv.
+        let (_, flag_var, out) = cs.multiply(x, LinearCombination::from(F::one()) - o);
+        cs.constrain(out.into());
+
+        Ok(AllocatedScalar::new(flag_var, flag))
+    }
+}
+
+/// Returns a boolean variable that is `1` if `a` and `b` are equal, and
+/// `0` otherwise, using [`IsZero`] on their difference.
+pub fn is_equal<F: PrimeField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    a: AllocatedScalar<F>,
+    b: AllocatedScalar<F>,
+) -> Result<AllocatedScalar<F>, R1CSError> {
+    let diff = a.assignment.zip(b.assignment).map(|(a, b)| a - b);
+    IsZero.synthesize(cs, (a.variable - b.variable, diff))
+}
+
+/// Enforces that the linear combinations `a` and `b` are equal.
+pub fn enforce_equal<F: PrimeField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    a: LinearCombination<F>,
+    b: LinearCombination<F>,
+) {
+    cs.constrain(a - b);
+}
+
+/// Enforces that `a` and `b` are not equal, by proving that their
+/// difference has an inverse.  This costs a single multiplier.
+pub fn enforce_not_equal<F: PrimeField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    a: AllocatedScalar<F>,
+    b: AllocatedScalar<F>,
+) -> Result<(), R1CSError> {
+    let diff = a.assignment.zip(b.assignment).map(|(a, b)| a - b);
+    let inv = diff.map(|d| d.inverse().unwrap_or_else(F::zero));
+
+    // Enforce (a - b) * inv = 1
+    let (l, _, o) = cs.allocate_multiplier(diff.zip(inv))?;
+    cs.constrain(l - a.variable + b.variable);
+    cs.constrain(o - F::one());
+
+    Ok(())
+}
@@ -0,0 +1,225 @@
+//! Unsigned fixed-point arithmetic.
+
+use ark_ff::PrimeField;
+use ark_std::string::ToString;
+
+use super::bits::{decompose, recompose};
+use super::AllocatedScalar;
+use crate::r1cs::{ConstraintSystem, LinearCombination, R1CSError};
+
+/// The format of unsigned fixed-point numbers with `int_bits` integer
+/// bits and `frac_bits` fractional bits.
+///
+/// A number is represented by its raw value `x * 2^frac_bits`, which is
+/// an integer in the range `[0, 2^(int_bits + frac_bits))`.  Every
+/// operation range-checks its result, so that an overflow makes the
+/// proof fail rather than wrap around the field, and rounds towards
+/// zero.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FixedPoint {
+    int_bits: usize,
+    frac_bits: usize,
+}
+
+/// A fixed-point number in the constraint system, as the linear
+/// combination of its raw value, together with the raw value, which is
+/// only known to the prover.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FixedPointVar<F: PrimeField> {
+    /// The raw value of the number.
+    pub lc: LinearCombination<F>,
+    /// The raw value of the number, if known.
+    pub value: Option<u64>,
+}
+
+impl FixedPoint {
+    /// Creates the format with `int_bits` integer bits and `frac_bits`
+    /// fractional bits, which must add up to at most 64 bits.
+    pub fn new(int_bits: usize, frac_bits: usize) -> Result<Self, R1CSError> {
+        if int_bits + frac_bits == 0 || int_bits + frac_bits > 64 {
+            return Err(R1CSError::GadgetError {
+                description: "fixed-point numbers must have between 1 and 64 bits".to_string(),
+            });
+        }
+        Ok(FixedPoint {
+            int_bits,
+            frac_bits,
+        })
+    }
+
+    /// Returns the total number of bits of the numbers.
+    pub fn bits(&self) -> usize {
+        self.int_bits + self.frac_bits
+    }
+
+    /// Returns the number of fractional bits of the numbers.
+    pub fn frac_bits(&self) -> usize {
+        self.frac_bits
+    }
+
+    /// Returns the raw value of the integer `x`, if it is representable.
+    pub fn from_integer(&self, x: u64) -> Option<u64> {
+        let raw = (x as u128) << self.frac_bits;
+        self.fits(raw).then_some(raw as u64)
+    }
+
+    /// Returns the raw value of the ratio `num / den`, rounded towards
+    /// zero, if it is representable.
+    pub fn from_ratio(&self, num: u64, den: u64) -> Option<u64> {
+        let raw = ((num as u128) << self.frac_bits).checked_div(den as u128)?;
+        self.fits(raw).then_some(raw as u64)
+    }
+
+    /// Returns a constant number with the given raw value.
+    pub fn constant<F: PrimeField>(&self, raw: u64) -> Result<FixedPointVar<F>, R1CSError> {
+        self.check(raw as u128)?;
+        Ok(FixedPointVar {
+            lc: F::from(raw).into(),
+            value: Some(raw),
+        })
+    }
+
+    /// Allocates a new number with the given raw value, using
+    /// [`FixedPoint::bits`] multipliers.
+    pub fn allocate<F: PrimeField, CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        raw: Option<u64>,
+    ) -> Result<FixedPointVar<F>, R1CSError> {
+        self.range_checked(cs, raw.map(u128::from), self.bits())
+            .map(|(lc, _)| FixedPointVar { lc, value: raw })
+    }
+
+    /// Converts a variable holding a raw value, such as a committed
+    /// variable, into a number, and enforces that it is in range.
+    pub fn from_scalar<F: PrimeField, CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        x: AllocatedScalar<F>,
+    ) -> Result<FixedPointVar<F>, R1CSError> {
+        let bits = decompose(cs, x.assignment, self.bits())?;
+        cs.constrain(recompose(&bits) - x.variable);
+        let value = bits.iter().rev().try_fold(0u64, |acc, bit| {
+            Some((acc << 1) | bit.assignment?.is_one() as u64)
+        });
+        Ok(FixedPointVar {
+            lc: x.variable.into(),
+            value,
+        })
+    }
+
+    /// Returns `a + b`, using [`FixedPoint::bits`] multipliers.
+    pub fn add<F: PrimeField, CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        a: &FixedPointVar<F>,
+        b: &FixedPointVar<F>,
+    ) -> Result<FixedPointVar<F>, R1CSError> {
+        let value = a.value.zip(b.value).map(|(a, b)| a as u128 + b as u128);
+        let sum = self.allocate_result(cs, value)?;
+        cs.constrain(sum.lc.clone() - a.lc.clone() - b.lc.clone());
+        Ok(sum)
+    }
+
+    /// Returns `a - b`, using [`FixedPoint::bits`] multipliers.  The
+    /// proof fails if `a < b`.
+    pub fn sub<F: PrimeField, CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        a: &FixedPointVar<F>,
+        b: &FixedPointVar<F>,
+    ) -> Result<FixedPointVar<F>, R1CSError> {
+        let value = a.value.zip(b.value).map(|(a, b)| a.wrapping_sub(b) as u128);
+        let difference = self.allocate_result(cs, value)?;
+        cs.constrain(difference.lc.clone() + b.lc.clone() - a.lc.clone());
+        Ok(difference)
+    }
+
+    /// Returns `a * b`, using `bits + frac_bits + 1` multipliers.
+    ///
+    /// The raw product `c` is enforced by `a * b = c * 2^frac_bits + r`
+    /// with `0 <= r < 2^frac_bits`.
+    pub fn mul<F: PrimeField, CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        a: &FixedPointVar<F>,
+        b: &FixedPointVar<F>,
+    ) -> Result<FixedPointVar<F>, R1CSError> {
+        let product = a.value.zip(b.value).map(|(a, b)| a as u128 * b as u128);
+        let (_, _, o) = cs.multiply(a.lc.clone(), b.lc.clone());
+
+        let c = self.allocate_result(cs, product.map(|p| p >> self.frac_bits))?;
+        let mask = (1u128 << self.frac_bits) - 1;
+        let (r, _) = self.range_checked(cs, product.map(|p| p & mask), self.frac_bits)?;
+        cs.constrain(c.lc.clone() * F::from(1u128 << self.frac_bits) + r - o);
+        Ok(c)
+    }
+
+    /// Returns `a / b`, using `3 * bits + 1` multipliers.  The proof
+    /// fails if `b` is zero.
+    ///
+    /// The raw quotient `c` is enforced by `a * 2^frac_bits = c * b + r`
+    /// with `0 <= r < b`.
+    pub fn div<F: PrimeField, CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        a: &FixedPointVar<F>,
+        b: &FixedPointVar<F>,
+    ) -> Result<FixedPointVar<F>, R1CSError> {
+        let values = a.value.zip(b.value).map(|(a, b)| {
+            let num = (a as u128) << self.frac_bits;
+            match b {
+                0 => (0, 0),
+                b => (num / b as u128, num % b as u128),
+            }
+        });
+
+        let c = self.allocate_result(cs, values.map(|(c, _)| c))?;
+        let (r, _) = self.range_checked(cs, values.map(|(_, r)| r), self.bits())?;
+        let (_, _, cb) = cs.multiply(c.lc.clone(), b.lc.clone());
+        cs.constrain(cb + r.clone() - a.lc.clone() * F::from(1u128 << self.frac_bits));
+
+        // Enforce r < b, as b - r - 1 >= 0.
+        let d = values
+            .zip(b.value)
+            .map(|((_, r), b)| (b as u128).wrapping_sub(r + 1));
+        let (d, _) = self.range_checked(cs, d, self.bits())?;
+        cs.constrain(d + r + F::one() - b.lc.clone());
+        Ok(c)
+    }
+
+    /// Allocates the result of an operation, which must be in range.
+    fn allocate_result<F: PrimeField, CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        value: Option<u128>,
+    ) -> Result<FixedPointVar<F>, R1CSError> {
+        let (lc, value) = self.range_checked(cs, value, self.bits())?;
+        Ok(FixedPointVar { lc, value })
+    }
+
+    /// Allocates `value` in `n` bits.  An out of range value is truncated,
+    /// so that the proof fails.
+    fn range_checked<F: PrimeField, CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        value: Option<u128>,
+        n: usize,
+    ) -> Result<(LinearCombination<F>, Option<u64>), R1CSError> {
+        let bits = decompose(cs, value.map(F::from), n)?;
+        Ok((recompose(&bits), value.map(|v| v as u64)))
+    }
+
+    fn fits(&self, raw: u128) -> bool {
+        raw >> self.bits() == 0
+    }
+
+    fn check(&self, raw: u128) -> Result<(), R1CSError> {
+        if !self.fits(raw) {
+            return Err(R1CSError::GadgetError {
+                description: "fixed-point value out of range".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
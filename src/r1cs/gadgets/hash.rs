@@ -0,0 +1,185 @@
+//! Interface of algebraic hash functions usable inside the constraint system.
+
+use ark_ff::{BigInteger, PrimeField};
+use ark_std::vec::Vec;
+use digest::Digest;
+use sha3::Sha3_512;
+
+use crate::r1cs::{ConstraintSystem, LinearCombination, R1CSError};
+
+/// A hash function over field elements, with a native implementation
+/// and a matching gadget.
+///
+/// Implementations must make sure that [`CircuitHash::synthesize`]
+/// constrains its output to be equal to [`CircuitHash::hash`] of the
+/// values of its inputs.
+pub trait CircuitHash<F: PrimeField> {
+    /// Computes the hash of `inputs` natively.
+    fn hash(&self, inputs: &[F]) -> F;
+
+    /// Adds constraints to `cs` computing the hash of `inputs`, and
+    /// returns the linear combination evaluating to the hash.
+    ///
+    /// The `values` of the inputs are only known to the prover, and are
+    /// needed by hash functions which allocate intermediate variables.
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        inputs: &[LinearCombination<F>],
+        values: Option<&[F]>,
+    ) -> Result<LinearCombination<F>, R1CSError>;
+}
+
+impl<F: PrimeField, H: CircuitHash<F>> CircuitHash<F> for &H {
+    fn hash(&self, inputs: &[F]) -> F {
+        (**self).hash(inputs)
+    }
+
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        inputs: &[LinearCombination<F>],
+        values: Option<&[F]>,
+    ) -> Result<LinearCombination<F>, R1CSError> {
+        (**self).synthesize(cs, inputs, values)
+    }
+}
+
+/// Derives `count` field elements from SHA3-512 in counter mode over `label`.
+pub(super) fn derive_constants<F: PrimeField>(label: &[u8], count: usize) -> Vec<F> {
+    (0..count as u64)
+        .map(|i| {
+            let mut hash = Sha3_512::new();
+            Digest::update(&mut hash, label);
+            Digest::update(&mut hash, i.to_le_bytes());
+            F::from_le_bytes_mod_order(&hash.finalize())
+        })
+        .collect()
+}
+
+/// Returns the `t x t` Cauchy matrix `M[i][j] = 1 / (i + t + j)`, which is MDS.
+pub(super) fn cauchy_mds<F: PrimeField>(t: usize) -> Vec<Vec<F>> {
+    (0..t)
+        .map(|i| {
+            (0..t)
+                .map(|j| F::from((i + t + j) as u64).inverse().unwrap())
+                .collect()
+        })
+        .collect()
+}
+
+/// Returns the product of the `matrix` with the `state`, natively.
+pub(super) fn mix<F: PrimeField>(matrix: &[Vec<F>], state: &[F]) -> Vec<F> {
+    matrix
+        .iter()
+        .map(|row| row.iter().zip(state).map(|(m, s)| *m * s).sum())
+        .collect()
+}
+
+/// Returns the product of the `matrix` with the `state`, in the constraint system.
+pub(super) fn mix_gadget<F: PrimeField>(
+    matrix: &[Vec<F>],
+    state: &[LinearCombination<F>],
+) -> Vec<LinearCombination<F>> {
+    matrix
+        .iter()
+        .map(|row| {
+            row.iter()
+                .zip(state)
+                .fold(LinearCombination::default(), |acc, (m, s)| {
+                    acc + s.clone() * *m
+                })
+                .simplify()
+        })
+        .collect()
+}
+
+/// Returns the little-endian limbs of `p - 1`.
+fn modulus_minus_one<F: PrimeField>() -> Vec<u64> {
+    let mut p_minus_one = F::MODULUS;
+    p_minus_one.sub_with_borrow(&F::BigInt::from(1u64));
+    p_minus_one.as_ref().to_vec()
+}
+
+/// Returns the remainder of the division of little-endian `limbs` by `d`.
+fn rem_u64(limbs: &[u64], d: u64) -> u64 {
+    limbs
+        .iter()
+        .rev()
+        .fold(0u128, |rem, limb| ((rem << 64) | *limb as u128) % d as u128) as u64
+}
+
+/// Checks that `x -> x^alpha` is a permutation of `F`, that is, that
+/// `alpha` is coprime with `p - 1`.
+pub(super) fn is_valid_alpha<F: PrimeField>(alpha: u64) -> bool {
+    if alpha < 3 {
+        return false;
+    }
+    let p_minus_one = modulus_minus_one::<F>();
+    // gcd(alpha, p - 1) = 1 if and only if (p - 1) mod q != 0 for
+    // every prime factor q of alpha.
+    let mut a = alpha;
+    let mut q = 2;
+    while a > 1 {
+        if a.is_multiple_of(q) {
+            if rem_u64(&p_minus_one, q) == 0 {
+                return false;
+            }
+            while a.is_multiple_of(q) {
+                a /= q;
+            }
+        }
+        q += 1;
+    }
+    true
+}
+
+/// Returns the little-endian limbs of the inverse of `alpha` modulo
+/// `p - 1`, so that `x -> x^alpha_inv` is the inverse of `x -> x^alpha`.
+pub(super) fn alpha_inverse<F: PrimeField>(alpha: u64) -> Option<Vec<u64>> {
+    if !is_valid_alpha::<F>(alpha) {
+        return None;
+    }
+    // Find k such that k * (p - 1) + 1 is divisible by alpha, which
+    // then is alpha * alpha_inv.
+    let p_minus_one = modulus_minus_one::<F>();
+    let r = rem_u64(&p_minus_one, alpha);
+    let k = (1..alpha).find(|k| (k * r + 1).is_multiple_of(alpha))?;
+
+    let mut limbs = Vec::with_capacity(p_minus_one.len() + 1);
+    let mut carry = 1u128;
+    for limb in p_minus_one {
+        let t = limb as u128 * k as u128 + carry;
+        limbs.push(t as u64);
+        carry = t >> 64;
+    }
+    limbs.push(carry as u64);
+
+    let mut rem = 0u128;
+    for limb in limbs.iter_mut().rev() {
+        let t = (rem << 64) | *limb as u128;
+        *limb = (t / alpha as u128) as u64;
+        rem = t % alpha as u128;
+    }
+    Some(limbs)
+}
+
+/// Computes `x^alpha` in the constraint system with square-and-multiply,
+/// using about `log2(alpha) + popcount(alpha)` multipliers.
+pub(super) fn pow_gadget<F: PrimeField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    x: LinearCombination<F>,
+    alpha: u64,
+) -> LinearCombination<F> {
+    let bits = 64 - alpha.leading_zeros();
+    let mut acc = x.clone();
+    for i in (0..bits - 1).rev() {
+        let (_, _, sq) = cs.multiply(acc.clone(), acc);
+        acc = sq.into();
+        if (alpha >> i) & 1 == 1 {
+            let (_, _, o) = cs.multiply(acc, x.clone());
+            acc = o.into();
+        }
+    }
+    acc
+}
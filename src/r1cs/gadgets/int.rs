@@ -0,0 +1,226 @@
+//! Signed integers in two's complement.
+
+use ark_ff::PrimeField;
+use ark_std::{string::ToString, vec::Vec};
+
+use super::bits::{decompose, recompose};
+use super::{AllocatedScalar, Boolean};
+use crate::r1cs::{ConstraintSystem, LinearCombination, R1CSError};
+
+/// A signed integer of `n` bits in the constraint system, in the range
+/// `[-2^(n-1), 2^(n-1))`, together with its value, which is only known
+/// to the prover.
+///
+/// The integer is the field element `x`, where negative integers are
+/// represented by `p - |x|`.  Its two's complement bits are obtained by
+/// decomposing `x + 2^(n-1)` into `n` bits, which also enforces the
+/// range, and flipping the top bit.  Every arithmetic operation
+/// range-checks its result the same way, so that an overflow makes the
+/// proof fail, at a cost of `n` multipliers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Int<F: PrimeField> {
+    lc: LinearCombination<F>,
+    bits: Vec<Boolean<F>>,
+    value: Option<i64>,
+}
+
+impl<F: PrimeField> Int<F> {
+    /// Allocates a new integer of `n` bits with the given `value`.
+    pub fn allocate<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        value: Option<i64>,
+        n: usize,
+    ) -> Result<Self, R1CSError> {
+        check_bits::<F>(n)?;
+        Self::range_checked(cs, value.map(i128::from), n)
+    }
+
+    /// Converts a variable, such as a committed variable, into an integer
+    /// of `n` bits, and enforces that it is in range.
+    pub fn from_scalar<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        x: AllocatedScalar<F>,
+        n: usize,
+    ) -> Result<Self, R1CSError> {
+        let value = x.assignment.map(|x| {
+            // Interpret the field element as the closest integer to zero.
+            let neg: num_bigint::BigUint = (-x).into();
+            let pos: num_bigint::BigUint = x.into();
+            match (u64::try_from(pos), u64::try_from(neg)) {
+                (Ok(pos), _) => pos as i128,
+                (_, Ok(neg)) => -(neg as i128),
+                _ => i128::MAX,
+            }
+        });
+        check_bits::<F>(n)?;
+        let int = Self::range_checked(cs, value, n)?;
+        cs.constrain(int.lc.clone() - x.variable);
+        Ok(int)
+    }
+
+    /// Returns a constant integer of `n` bits.
+    pub fn constant(value: i64, n: usize) -> Result<Self, R1CSError> {
+        check_bits::<F>(n)?;
+        if !fits(value as i128, n) {
+            return Err(R1CSError::GadgetError {
+                description: "signed integer out of range".to_string(),
+            });
+        }
+        let bits = (0..n)
+            .map(|i| Boolean::constant((value >> i) & 1 == 1))
+            .collect();
+        Ok(Int {
+            lc: to_field::<F>(value as i128).into(),
+            bits,
+            value: Some(value),
+        })
+    }
+
+    /// Returns the linear combination of the integer.
+    pub fn lc(&self) -> LinearCombination<F> {
+        self.lc.clone()
+    }
+
+    /// Returns the value of the integer, if known.
+    pub fn value(&self) -> Option<i64> {
+        self.value
+    }
+
+    /// Returns the number of bits of the integer.
+    pub fn bits(&self) -> usize {
+        self.bits.len()
+    }
+
+    /// Returns the little-endian two's complement bits of the integer.
+    pub fn to_bits(&self) -> &[Boolean<F>] {
+        &self.bits
+    }
+
+    /// Returns whether the integer is negative, without any constraint.
+    pub fn is_negative(&self) -> Boolean<F> {
+        self.bits[self.bits.len() - 1].clone()
+    }
+
+    /// Returns `a + b`.
+    pub fn add<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        a: &Self,
+        b: &Self,
+    ) -> Result<Self, R1CSError> {
+        let n = check_same_bits(a, b)?;
+        let value = a.value.zip(b.value).map(|(a, b)| a as i128 + b as i128);
+        let sum = Self::range_checked(cs, value, n)?;
+        cs.constrain(sum.lc.clone() - a.lc.clone() - b.lc.clone());
+        Ok(sum)
+    }
+
+    /// Returns `a - b`.
+    pub fn sub<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        a: &Self,
+        b: &Self,
+    ) -> Result<Self, R1CSError> {
+        let n = check_same_bits(a, b)?;
+        let value = a.value.zip(b.value).map(|(a, b)| a as i128 - b as i128);
+        let difference = Self::range_checked(cs, value, n)?;
+        cs.constrain(difference.lc.clone() - a.lc.clone() + b.lc.clone());
+        Ok(difference)
+    }
+
+    /// Returns `-self`.  The proof fails for `-2^(n-1)`.
+    pub fn neg<CS: ConstraintSystem<F>>(&self, cs: &mut CS) -> Result<Self, R1CSError> {
+        let negated = Self::range_checked(cs, self.value.map(|v| -(v as i128)), self.bits())?;
+        cs.constrain(negated.lc.clone() + self.lc.clone());
+        Ok(negated)
+    }
+
+    /// Returns `|self|`, using one more multiplier.  The proof fails for
+    /// `-2^(n-1)`.
+    pub fn abs<CS: ConstraintSystem<F>>(&self, cs: &mut CS) -> Result<Self, R1CSError> {
+        let abs = Self::range_checked(cs, self.value.map(|v| (v as i128).abs()), self.bits())?;
+
+        // abs = x * (1 - 2 * is_negative)
+        let sign = LinearCombination::from(F::one()) - self.is_negative().lc() * F::from(2u64);
+        let (_, _, o) = cs.multiply(self.lc.clone(), sign);
+        cs.constrain(abs.lc.clone() - o);
+        Ok(abs)
+    }
+
+    /// Returns whether `a < b`, using `n + 1` multipliers.
+    pub fn less_than<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        a: &Self,
+        b: &Self,
+    ) -> Result<Boolean<F>, R1CSError> {
+        // a - b is in [-2^n, 2^n), so it fits in n + 1 bits.
+        let n = check_same_bits(a, b)?;
+        let value = a.value.zip(b.value).map(|(a, b)| a as i128 - b as i128);
+        let difference = Self::range_checked(cs, value, n + 1)?;
+        cs.constrain(difference.lc.clone() - a.lc.clone() + b.lc.clone());
+        Ok(difference.is_negative())
+    }
+
+    /// Returns whether `a <= b`, using `n + 1` multipliers.
+    pub fn less_or_equal<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        a: &Self,
+        b: &Self,
+    ) -> Result<Boolean<F>, R1CSError> {
+        Ok(Self::less_than(cs, b, a)?.not())
+    }
+
+    /// Allocates an integer of `n` bits with the given value.  An out of
+    /// range value is truncated, so that the proof fails.
+    fn range_checked<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        value: Option<i128>,
+        n: usize,
+    ) -> Result<Self, R1CSError> {
+        let offset = to_field::<F>(1i128 << (n - 1));
+        let bits = decompose(cs, value.map(|v| to_field::<F>(v) + offset), n)?;
+        let lc = recompose(&bits) - offset;
+
+        let mut bits = bits
+            .into_iter()
+            .map(Boolean::from_bit)
+            .collect::<Result<Vec<_>, _>>()?;
+        bits[n - 1] = bits[n - 1].not();
+
+        Ok(Int {
+            lc,
+            bits,
+            value: value.filter(|v| fits(*v, n.min(64))).map(|v| v as i64),
+        })
+    }
+}
+
+fn check_bits<F: PrimeField>(n: usize) -> Result<(), R1CSError> {
+    if n == 0 || n > 64 || n + 3 >= F::MODULUS_BIT_SIZE as usize {
+        return Err(R1CSError::GadgetError {
+            description: "signed integers must have between 1 and 64 bits".to_string(),
+        });
+    }
+    Ok(())
+}
+
+fn check_same_bits<F: PrimeField>(a: &Int<F>, b: &Int<F>) -> Result<usize, R1CSError> {
+    if a.bits() != b.bits() {
+        return Err(R1CSError::GadgetError {
+            description: "signed integers have different bit widths".to_string(),
+        });
+    }
+    Ok(a.bits())
+}
+
+fn fits(value: i128, n: usize) -> bool {
+    let offset = 1i128 << (n - 1);
+    (-offset..offset).contains(&value)
+}
+
+fn to_field<F: PrimeField>(x: i128) -> F {
+    if x < 0 {
+        -F::from(x.unsigned_abs())
+    } else {
+        F::from(x as u128)
+    }
+}
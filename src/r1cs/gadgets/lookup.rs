@@ -0,0 +1,147 @@
+//! Lookups in public tables, using a log-derivative argument over
+//! randomized constraints.
+
+use ark_ff::PrimeField;
+use ark_std::{string::ToString, vec, vec::Vec};
+
+use super::AllocatedScalar;
+use crate::r1cs::{
+    ConstraintSystem, LinearCombination, R1CSError, RandomizableConstraintSystem,
+    RandomizedConstraintSystem,
+};
+
+/// A public table of rows of `width` field elements.
+///
+/// [`LookupTable::enforce_rows`] proves that every row of variables is a
+/// row of the table.  The prover first commits to the multiplicity `m_j`
+/// of every table row `t_j` among the looked-up rows `x_i`; then, with a
+/// challenge `z`, the gadget checks the log-derivative identity
+/// ```text
+/// Sum(1 / (z - x_i)) = Sum(m_j / (z - t_j)),
+/// ```
+/// where rows are compressed to field elements with another challenge.
+/// Every looked-up row costs a single multiplier, and every table row
+/// half a multiplier, so looking up many values in a small table, such
+/// as bytes, is much cheaper than a bit decomposition of each of them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LookupTable<F: PrimeField> {
+    rows: Vec<Vec<F>>,
+}
+
+impl<F: PrimeField> LookupTable<F> {
+    /// Creates a table from its `rows`, which must be nonempty and all
+    /// have the same width.
+    pub fn new(rows: Vec<Vec<F>>) -> Result<Self, R1CSError> {
+        let width = rows.first().map_or(0, |r| r.len());
+        if width == 0 || rows.iter().any(|r| r.len() != width) {
+            return Err(R1CSError::GadgetError {
+                description: "lookup table rows must be nonempty and of the same width".to_string(),
+            });
+        }
+        Ok(LookupTable { rows })
+    }
+
+    /// Creates a table of a single column of `values`.
+    pub fn from_values(values: Vec<F>) -> Result<Self, R1CSError> {
+        Self::new(values.into_iter().map(|v| vec![v]).collect())
+    }
+
+    /// Returns the rows of the table.
+    pub fn rows(&self) -> &[Vec<F>] {
+        &self.rows
+    }
+
+    /// Enforces that every input is a value of a single-column table.
+    pub fn enforce<CS: RandomizableConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        inputs: &[AllocatedScalar<F>],
+    ) -> Result<(), R1CSError> {
+        let rows: Vec<_> = inputs.iter().map(|x| vec![*x]).collect();
+        self.enforce_rows(cs, &rows)
+    }
+
+    /// Enforces that every row of `inputs` is a row of the table.
+    ///
+    /// The prover returns an error if an input row is not in the table.
+    pub fn enforce_rows<CS: RandomizableConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        inputs: &[Vec<AllocatedScalar<F>>],
+    ) -> Result<(), R1CSError> {
+        let width = self.rows[0].len();
+        if inputs.iter().any(|r| r.len() != width) {
+            return Err(R1CSError::GadgetError {
+                description: "looked-up rows must have the width of the table".to_string(),
+            });
+        }
+
+        // Count the multiplicity of every table row, attributing the
+        // duplicates of a table row to its first occurrence.
+        let values: Option<Vec<Vec<F>>> = inputs
+            .iter()
+            .map(|r| r.iter().map(|x| x.assignment).collect())
+            .collect();
+        let multiplicities = match &values {
+            Some(values) => {
+                let mut counts = vec![0u64; self.rows.len()];
+                for value in values {
+                    match self.rows.iter().position(|r| r == value) {
+                        Some(j) => counts[j] += 1,
+                        None => {
+                            return Err(R1CSError::GadgetError {
+                                description: "looked-up row is not in the table".to_string(),
+                            })
+                        }
+                    }
+                }
+                counts.into_iter().map(|c| Some(F::from(c))).collect()
+            }
+            None => vec![None; self.rows.len()],
+        };
+        let multiplicities = multiplicities
+            .into_iter()
+            .map(|m| AllocatedScalar::allocate(cs, m))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let table = self.rows.clone();
+        let inputs = inputs.to_vec();
+        cs.specify_randomized_constraints(move |cs| {
+            let w = cs.challenge_scalar(b"lookup row challenge");
+            let z = cs.challenge_scalar(b"lookup challenge");
+
+            let compress = |row: &[F]| row.iter().rev().fold(F::zero(), |acc, x| acc * w + x);
+
+            // Sum(1 / (z - x_i)), allocating the inverses.
+            let mut lhs = LinearCombination::default();
+            for row in &inputs {
+                let lc = row
+                    .iter()
+                    .rev()
+                    .fold(LinearCombination::default(), |acc, x| acc * w + x.variable);
+                let value: Option<Vec<F>> = row.iter().map(|x| x.assignment).collect();
+                let diff = value.map(|v| z - compress(&v));
+                let inv = diff.map(|d| d.inverse().unwrap_or_else(F::zero));
+
+                let (l, r, o) = cs.allocate_multiplier(diff.zip(inv))?;
+                cs.constrain(l - (LinearCombination::from(z) - lc));
+                cs.constrain(o - F::one());
+                lhs = lhs + r;
+            }
+
+            // Sum(m_j / (z - t_j)), where the denominators are constants.
+            let mut rhs = LinearCombination::default();
+            for (row, m) in table.iter().zip(&multiplicities) {
+                let inv = (z - compress(row))
+                    .inverse()
+                    .ok_or(R1CSError::GadgetError {
+                        description: "lookup challenge is a table row".to_string(),
+                    })?;
+                rhs = rhs + m.variable * inv;
+            }
+
+            cs.constrain(lhs - rhs);
+            Ok(())
+        })
+    }
+}
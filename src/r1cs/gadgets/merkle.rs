@@ -0,0 +1,222 @@
+//! Merkle tree membership.
+
+use ark_ff::PrimeField;
+use ark_std::{string::ToString, vec, vec::Vec};
+
+use super::{AllocatedScalar, CircuitHash, Gadget};
+use crate::r1cs::{ConstraintSystem, LinearCombination, R1CSError};
+
+/// One level of a Merkle authentication path.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerkleLevel<F: PrimeField> {
+    /// Position of the current node among its siblings, in `[0, arity)`.
+    pub position: usize,
+    /// The `arity - 1` siblings of the current node, in order.
+    pub siblings: Vec<F>,
+}
+
+impl<F: PrimeField> MerkleLevel<F> {
+    /// Returns the children of the parent node, with `node` inserted
+    /// at its position among the siblings.
+    fn children(&self, node: F) -> Vec<F> {
+        let mut children = self.siblings.clone();
+        children.insert(self.position, node);
+        children
+    }
+}
+
+/// A Merkle tree of fixed `arity` and `depth`, computed natively.
+///
+/// The leaves are padded with zeros up to `arity^depth`.
+#[derive(Clone, Debug)]
+pub struct MerkleTree<F: PrimeField, H: CircuitHash<F>> {
+    hash: H,
+    arity: usize,
+    /// The nodes of each level, from the leaves up to the root.
+    levels: Vec<Vec<F>>,
+}
+
+impl<F: PrimeField, H: CircuitHash<F>> MerkleTree<F, H> {
+    /// Builds a tree of the given `arity` and `depth` over `leaves`.
+    pub fn new(hash: H, arity: usize, depth: usize, leaves: &[F]) -> Result<Self, R1CSError> {
+        let capacity = (arity as u64)
+            .checked_pow(depth as u32)
+            .filter(|_| arity >= 2)
+            .ok_or_else(|| R1CSError::GadgetError {
+                description: "invalid Merkle tree arity or depth".to_string(),
+            })?;
+        if leaves.len() as u64 > capacity {
+            return Err(R1CSError::GadgetError {
+                description: "too many leaves for the Merkle tree".to_string(),
+            });
+        }
+
+        let mut level = leaves.to_vec();
+        level.resize(capacity as usize, F::zero());
+        let mut levels = vec![level];
+        for _ in 0..depth {
+            let next = levels
+                .last()
+                .unwrap()
+                .chunks(arity)
+                .map(|children| hash.hash(children))
+                .collect();
+            levels.push(next);
+        }
+
+        Ok(MerkleTree {
+            hash,
+            arity,
+            levels,
+        })
+    }
+
+    /// Returns the root of the tree.
+    pub fn root(&self) -> F {
+        self.levels.last().unwrap()[0]
+    }
+
+    /// Returns the authentication path of the leaf at `index`.
+    pub fn path(&self, mut index: usize) -> Option<Vec<MerkleLevel<F>>> {
+        if index >= self.levels[0].len() {
+            return None;
+        }
+        let mut path = Vec::with_capacity(self.levels.len() - 1);
+        for level in &self.levels[..self.levels.len() - 1] {
+            let start = index - index % self.arity;
+            let mut siblings = level[start..start + self.arity].to_vec();
+            siblings.remove(index % self.arity);
+            path.push(MerkleLevel {
+                position: index % self.arity,
+                siblings,
+            });
+            index /= self.arity;
+        }
+        Some(path)
+    }
+
+    /// Returns the hash function of the tree.
+    pub fn hash(&self) -> &H {
+        &self.hash
+    }
+}
+
+/// Computes the root of a Merkle tree from a `leaf` and its authentication `path`.
+pub fn merkle_root<F: PrimeField, H: CircuitHash<F>>(
+    hash: &H,
+    leaf: F,
+    path: &[MerkleLevel<F>],
+) -> F {
+    path.iter()
+        .fold(leaf, |node, level| hash.hash(&level.children(node)))
+}
+
+/// Computes the root of a Merkle tree of the given `arity` and `depth`
+/// from a leaf and its authentication path.
+///
+/// The input is the leaf variable, and its authentication path, which
+/// is only known to the prover.  The gadget returns the linear
+/// combination evaluating to the root; use [`MerkleMembership::enforce_root`]
+/// to prove membership under a public root.
+///
+/// At each level, the prover allocates the `arity` children of the parent
+/// node together with a one-hot selector of the position of the current
+/// node, and the gadget enforces that the selected child is the current
+/// node.  This costs `2 * arity` multipliers per level in addition to
+/// the hash, and hides the position of the leaf.
+#[derive(Clone, Debug)]
+pub struct MerkleMembership<H> {
+    hash: H,
+    arity: usize,
+    depth: usize,
+}
+
+impl<H> MerkleMembership<H> {
+    /// Creates a gadget for trees of the given `arity` and `depth`.
+    pub fn new(hash: H, arity: usize, depth: usize) -> Self {
+        MerkleMembership { hash, arity, depth }
+    }
+
+    /// Enforces that `leaf` is a member of the tree with the public `root`.
+    pub fn enforce_root<F: PrimeField, CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        leaf: AllocatedScalar<F>,
+        path: Option<Vec<MerkleLevel<F>>>,
+        root: F,
+    ) -> Result<(), R1CSError>
+    where
+        H: CircuitHash<F>,
+    {
+        let computed = self.synthesize(cs, (leaf, path))?;
+        cs.constrain(computed - root);
+        Ok(())
+    }
+}
+
+impl<F: PrimeField, H: CircuitHash<F>> Gadget<F> for MerkleMembership<H> {
+    type Input = (AllocatedScalar<F>, Option<Vec<MerkleLevel<F>>>);
+    type Output = LinearCombination<F>;
+
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        (leaf, path): Self::Input,
+    ) -> Result<LinearCombination<F>, R1CSError> {
+        if self.arity < 2 {
+            return Err(R1CSError::GadgetError {
+                description: "Merkle tree arity must be at least 2".to_string(),
+            });
+        }
+        if let Some(path) = &path {
+            let well_formed = path.len() == self.depth
+                && path.iter().all(|level| {
+                    level.position < self.arity && level.siblings.len() == self.arity - 1
+                });
+            if !well_formed {
+                return Err(R1CSError::GadgetError {
+                    description: "malformed Merkle authentication path".to_string(),
+                });
+            }
+        }
+
+        let mut node: LinearCombination<F> = leaf.variable.into();
+        let mut value = leaf.assignment;
+
+        for d in 0..self.depth {
+            let level = path.as_ref().map(|p| &p[d]);
+            let children_values = level.zip(value).map(|(level, v)| level.children(v));
+
+            let mut children = Vec::with_capacity(self.arity);
+            let mut selected = LinearCombination::default();
+            let mut selectors = LinearCombination::default();
+            for j in 0..self.arity {
+                let e = level.map(|level| F::from((level.position == j) as u64));
+                let c = children_values.as_ref().map(|c| c[j]);
+
+                // Allocate the selector e_j and the child c_j, with e_j * c_j.
+                let (e_var, c_var, o) = cs.allocate_multiplier(e.zip(c))?;
+
+                // Enforce that e_j is 0 or 1.
+                let (_, _, b) =
+                    cs.multiply(e_var.into(), LinearCombination::from(F::one()) - e_var);
+                cs.constrain(b.into());
+
+                children.push(c_var.into());
+                selected = selected + o;
+                selectors = selectors + e_var;
+            }
+
+            // Exactly one selector is set, and it selects the current node.
+            cs.constrain(selectors - F::one());
+            cs.constrain(selected - node);
+
+            node = self
+                .hash
+                .synthesize(cs, &children, children_values.as_deref())?;
+            value = children_values.map(|c| self.hash.hash(&c));
+        }
+
+        Ok(node)
+    }
+}
@@ -0,0 +1,127 @@
+//! The MiMC block cipher and hash function, natively and as a gadget.
+
+use ark_ff::PrimeField;
+use ark_std::{string::ToString, vec, vec::Vec};
+
+use super::hash::{derive_constants, is_valid_alpha, pow_gadget};
+use super::CircuitHash;
+use crate::r1cs::{ConstraintSystem, LinearCombination, R1CSError};
+
+/// The MiMC block cipher with exponent `e`, and the hash function built
+/// from it in Miyaguchi-Preneel mode.
+///
+/// Every round computes `x -> (x + k + c_i)^e`, with `c_0 = 0` and the
+/// other round constants derived from SHA3-512, and the key is added
+/// once more after the last round.  The hash of `x_1, ..., x_n` is
+/// `h_n`, where `h_0 = 0` and `h_i = E_{h_(i-1)}(x_i) + h_(i-1) + x_i`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MiMC<F: PrimeField> {
+    exponent: u64,
+    constants: Vec<F>,
+}
+
+impl<F: PrimeField> MiMC<F> {
+    /// Creates the cipher with the given `exponent` and number of `rounds`.
+    ///
+    /// Returns an error if `x -> x^exponent` is not a permutation of `F`.
+    pub fn new(exponent: u64, rounds: usize) -> Result<Self, R1CSError> {
+        if rounds == 0 || !is_valid_alpha::<F>(exponent) {
+            return Err(R1CSError::GadgetError {
+                description: "invalid MiMC parameters".to_string(),
+            });
+        }
+
+        let mut label = Vec::new();
+        label.extend_from_slice(b"MiMC");
+        label.extend_from_slice(&exponent.to_le_bytes());
+        label.extend_from_slice(&(rounds as u64).to_le_bytes());
+        let mut constants = derive_constants(&label, rounds);
+        constants[0] = F::zero();
+
+        Ok(MiMC {
+            exponent,
+            constants,
+        })
+    }
+
+    /// Creates the cipher with the given `exponent` and the standard
+    /// number of rounds `ceil(log_e(p))`.
+    pub fn with_exponent(exponent: u64) -> Result<Self, R1CSError> {
+        // Smallest r such that exponent^r >= 2^MODULUS_BIT_SIZE, computed
+        // on little-endian limbs.
+        let bits = F::MODULUS_BIT_SIZE as usize;
+        let mut power = vec![1u64; 1];
+        let mut rounds = 0;
+        while exponent >= 2
+            && power.len() * 64 - power.last().unwrap().leading_zeros() as usize <= bits
+        {
+            let mut carry = 0u128;
+            for limb in power.iter_mut() {
+                let t = *limb as u128 * exponent as u128 + carry;
+                *limb = t as u64;
+                carry = t >> 64;
+            }
+            if carry > 0 {
+                power.push(carry as u64);
+            }
+            rounds += 1;
+        }
+        Self::new(exponent, rounds)
+    }
+
+    /// Creates MiMC-7, with exponent 7 and the standard number of rounds.
+    ///
+    /// This fails for fields where `7` divides `p - 1`, such as the
+    /// scalar field of secq256k1; use [`MiMC::with_exponent`] with
+    /// another exponent for those.
+    pub fn mimc7() -> Result<Self, R1CSError> {
+        Self::with_exponent(7)
+    }
+
+    /// Returns the number of rounds.
+    pub fn rounds(&self) -> usize {
+        self.constants.len()
+    }
+
+    /// Encrypts `x` under the key `k`.
+    pub fn encrypt(&self, mut x: F, k: F) -> F {
+        for c in &self.constants {
+            x = (x + k + c).pow([self.exponent]);
+        }
+        x + k
+    }
+
+    /// Encrypts `x` under the key `k` in the constraint system, using
+    /// about `rounds * log2(e)` multipliers.
+    pub fn encrypt_gadget<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        mut x: LinearCombination<F>,
+        k: LinearCombination<F>,
+    ) -> LinearCombination<F> {
+        for c in &self.constants {
+            x = pow_gadget(cs, x + k.clone() + *c, self.exponent);
+        }
+        x + k
+    }
+}
+
+impl<F: PrimeField> CircuitHash<F> for MiMC<F> {
+    fn hash(&self, inputs: &[F]) -> F {
+        inputs
+            .iter()
+            .fold(F::zero(), |h, x| self.encrypt(*x, h) + h + x)
+    }
+
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        inputs: &[LinearCombination<F>],
+        _: Option<&[F]>,
+    ) -> Result<LinearCombination<F>, R1CSError> {
+        Ok(inputs.iter().fold(LinearCombination::default(), |h, x| {
+            let e = self.encrypt_gadget(cs, x.clone(), h.clone());
+            (e + h + x.clone()).simplify()
+        }))
+    }
+}
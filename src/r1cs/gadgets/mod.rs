@@ -0,0 +1,185 @@
+//! Reusable gadgets for the constraint system.
+//!
+//! A gadget is a piece of circuit logic written once against the
+//! [`ConstraintSystem`] trait, so that the prover and the verifier build
+//! the same constraints from the same code.  Gadgets implement the
+//! [`Gadget`] trait, which gives them a uniform interface and lets them
+//! be composed with [`Gadget::then`].
+
+use ark_ff::PrimeField;
+
+mod alloc;
+mod balance;
+mod bigint;
+mod bits;
+mod boolean;
+mod cloak;
+mod comparison;
+mod division;
+mod dot_product;
+mod ecc;
+mod ecdsa;
+mod equality;
+mod fixed_point;
+mod hash;
+mod int;
+mod lookup;
+mod merkle;
+mod mimc;
+mod pedersen_hash;
+mod permutation;
+mod poseidon;
+mod rescue;
+mod set_membership;
+mod sha256;
+mod uint32;
+
+pub use self::alloc::AllocVar;
+pub use self::balance::BalanceConservation;
+pub use self::bigint::BigUintVar;
+pub use self::bits::BitDecomposition;
+pub use self::boolean::Boolean;
+pub use self::cloak::{cloak, k_mix, value_shuffle, AllocatedValue, Value};
+pub use self::comparison::{Compare, Comparison};
+pub use self::division::{div_rem, DivRem};
+pub use self::dot_product::{constant_matrix_vector_product, dot_product, matrix_vector_product};
+pub use self::ecc::PointVar;
+pub use self::ecdsa::EcdsaVerify;
+pub use self::equality::{enforce_equal, enforce_not_equal, is_equal, IsZero};
+pub use self::fixed_point::{FixedPoint, FixedPointVar};
+pub use self::hash::CircuitHash;
+pub use self::int::Int;
+pub use self::lookup::LookupTable;
+pub use self::merkle::{merkle_root, MerkleLevel, MerkleMembership, MerkleTree};
+pub use self::mimc::MiMC;
+pub use self::pedersen_hash::PedersenHash;
+pub use self::permutation::{enforce_permutation, enforce_tuple_permutation};
+pub use self::poseidon::{Poseidon, PoseidonParams};
+pub use self::rescue::{Rescue, RescueParams};
+pub use self::set_membership::{CommittedSetMembership, SetMembership};
+pub use self::sha256::{sha256_compress, sha256_compress_gadget, SHA256_IV};
+pub use self::uint32::UInt32;
+#[cfg(feature = "derive")]
+pub use ark_bulletproofs_derive::{AllocVar, Gadget};
+
+use super::{ConstraintSystem, LinearCombination, R1CSError, Variable};
+
+/// A reusable piece of circuit logic.
+///
+/// The gadget's own parameters (such as a bit width, or round
+/// constants) live in `self`, while the variables it operates on are
+/// passed as `inputs` to [`Gadget::synthesize`], together with their
+/// assignments when the gadget is synthesized by the prover.
+///
+/// With the `derive` feature, `#[derive(Gadget)]` implements the trait
+/// for structs of gadgets, which are synthesized in declaration order,
+/// each on the outputs of the previous one, like a [`Chain`], and in a
+/// namespace named after its field:
+///
+/// ```text
+/// #[derive(Gadget)]
+/// struct BoundedSum {
+///     sum: Sum,
+///     range: BitDecomposition,
+/// }
+/// ```
+pub trait Gadget<F: PrimeField> {
+    /// The variables (and assignments, if any) the gadget operates on.
+    type Input;
+
+    /// The variables (and assignments, if any) produced by the gadget.
+    type Output;
+
+    /// Adds the constraints of the gadget to `cs`.
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        inputs: Self::Input,
+    ) -> Result<Self::Output, R1CSError>;
+
+    /// Composes this gadget with `next`, which is synthesized on the
+    /// outputs of this gadget.
+    fn then<G: Gadget<F, Input = Self::Output>>(self, next: G) -> Chain<Self, G>
+    where
+        Self: Sized,
+    {
+        Chain {
+            first: self,
+            second: next,
+        }
+    }
+}
+
+impl<F: PrimeField, G: Gadget<F>> Gadget<F> for &G {
+    type Input = G::Input;
+    type Output = G::Output;
+
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        inputs: Self::Input,
+    ) -> Result<Self::Output, R1CSError> {
+        (**self).synthesize(cs, inputs)
+    }
+}
+
+/// Two gadgets synthesized one after the other, created with [`Gadget::then`].
+#[derive(Clone, Debug)]
+pub struct Chain<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<F: PrimeField, A: Gadget<F>, B: Gadget<F, Input = A::Output>> Gadget<F> for Chain<A, B> {
+    type Input = A::Input;
+    type Output = B::Output;
+
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        inputs: Self::Input,
+    ) -> Result<Self::Output, R1CSError> {
+        let outputs = self.first.synthesize(cs, inputs)?;
+        self.second.synthesize(cs, outputs)
+    }
+}
+
+/// A variable together with its assignment, which is only known to the prover.
+///
+/// This is the usual input and output type of gadgets: the prover
+/// passes `Some` assignment, while the verifier passes `None`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AllocatedScalar<F: PrimeField> {
+    /// The variable in the constraint system.
+    pub variable: Variable<F>,
+    /// The assignment of the variable, if known.
+    pub assignment: Option<F>,
+}
+
+impl<F: PrimeField> AllocatedScalar<F> {
+    /// Creates an `AllocatedScalar` from an existing `variable`.
+    pub fn new(variable: Variable<F>, assignment: Option<F>) -> Self {
+        AllocatedScalar {
+            variable,
+            assignment,
+        }
+    }
+
+    /// Allocates a new variable in `cs` with the given `assignment`.
+    pub fn allocate<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        assignment: Option<F>,
+    ) -> Result<Self, R1CSError> {
+        let variable = cs.allocate(assignment)?;
+        Ok(AllocatedScalar {
+            variable,
+            assignment,
+        })
+    }
+}
+
+impl<F: PrimeField> From<AllocatedScalar<F>> for LinearCombination<F> {
+    fn from(s: AllocatedScalar<F>) -> Self {
+        s.variable.into()
+    }
+}
@@ -0,0 +1,230 @@
+//! The windowed Pedersen hash over an embedded curve.
+
+use ark_ec::short_weierstrass::{Affine, Projective, SWCurveConfig};
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::{BigInteger, PrimeField};
+use ark_std::{string::ToString, vec::Vec, One};
+
+use super::bits::{decompose, recompose};
+use super::ecc::hash_to_curve;
+use super::{Boolean, CircuitHash, PointVar};
+use crate::r1cs::{ConstraintSystem, LinearCombination, R1CSError};
+
+/// The windowed Pedersen hash of bit strings, on a curve `C` whose base
+/// field is the scalar field of the constraint system.
+///
+/// The message is split into windows of `window` bits, and the hash is
+/// ```text
+/// Q + sum_i (m_i + 1) * G_i
+/// ```
+/// where `m_i` is the value of the `i`-th window, and `Q` and `G_i` are
+/// independent generators derived by hashing to the curve.  Starting from
+/// `Q` and adding `m_i + 1` rather than `m_i` keeps the accumulator away
+/// from the point at infinity, so the gadget can use incomplete addition.
+///
+/// In the constraint system, every window costs `2^window - window - 1`
+/// multipliers to look up its point and four multipliers to add it, in
+/// addition to the cost of the message bits.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PedersenHash<C: SWCurveConfig> {
+    window: usize,
+    offset: Affine<C>,
+    /// For each window, the points `(m + 1) * G_i` for every value `m`.
+    tables: Vec<Vec<Affine<C>>>,
+}
+
+impl<C: SWCurveConfig> PedersenHash<C>
+where
+    C::BaseField: PrimeField,
+{
+    /// Creates a hash function for messages of up to `max_bits` bits,
+    /// split into windows of `window` bits.
+    pub fn new(window: usize, max_bits: usize) -> Result<Self, R1CSError> {
+        if window == 0 || window > 8 {
+            return Err(R1CSError::GadgetError {
+                description: "Pedersen hash window must be between 1 and 8 bits".to_string(),
+            });
+        }
+
+        let label = [&b"PedersenHash"[..], &(window as u64).to_le_bytes()].concat();
+        let windows = max_bits.div_ceil(window);
+        let tables = (0..windows as u64)
+            .map(|i| {
+                let g = hash_to_curve::<C>(&label, i).into_group();
+                let mut table = Vec::with_capacity(1 << window);
+                let mut acc = g;
+                for _ in 0..1 << window {
+                    table.push(acc);
+                    acc += g;
+                }
+                Projective::normalize_batch(&table)
+            })
+            .collect();
+
+        Ok(PedersenHash {
+            window,
+            offset: hash_to_curve::<C>(&label, u64::MAX),
+            tables,
+        })
+    }
+
+    /// Returns the maximum length of the messages, in bits.
+    pub fn max_bits(&self) -> usize {
+        self.tables.len() * self.window
+    }
+
+    /// Computes the hash of `bits` natively.
+    pub fn hash_bits(&self, bits: &[bool]) -> Result<Affine<C>, R1CSError> {
+        self.check_len(bits.len())?;
+        let mut acc = self.offset.into_group();
+        for (chunk, table) in bits.chunks(self.window).zip(&self.tables) {
+            let m = chunk
+                .iter()
+                .rev()
+                .fold(0usize, |m, bit| (m << 1) | *bit as usize);
+            acc += table[m];
+        }
+        Ok(acc.into_affine())
+    }
+
+    /// Computes the hash of `bits` in the constraint system.
+    pub fn hash_bits_gadget<CS: ConstraintSystem<C::BaseField>>(
+        &self,
+        cs: &mut CS,
+        bits: &[Boolean<C::BaseField>],
+    ) -> Result<PointVar<C::BaseField>, R1CSError> {
+        self.check_len(bits.len())?;
+        let mut acc = PointVar::constant(&self.offset)?;
+        for (chunk, table) in bits.chunks(self.window).zip(&self.tables) {
+            let mut chunk = chunk.to_vec();
+            chunk.resize(self.window, Boolean::constant(false));
+            let point = lookup(cs, &chunk, table);
+            acc = acc.add_incomplete(cs, &point)?;
+        }
+        Ok(acc)
+    }
+
+    fn check_len(&self, len: usize) -> Result<(), R1CSError> {
+        if len > self.max_bits() {
+            return Err(R1CSError::GadgetError {
+                description: "message too long for the Pedersen hash".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Returns the point of `table` at the index given by the little-endian
+/// `bits`, as a multilinear polynomial in the bits.
+fn lookup<C: SWCurveConfig, CS: ConstraintSystem<C::BaseField>>(
+    cs: &mut CS,
+    bits: &[Boolean<C::BaseField>],
+    table: &[Affine<C>],
+) -> PointVar<C::BaseField>
+where
+    C::BaseField: PrimeField,
+{
+    let n = table.len();
+
+    // Monomials: the product of the bits set in each index.
+    let mut monomials: Vec<LinearCombination<C::BaseField>> = Vec::with_capacity(n);
+    monomials.push(C::BaseField::one().into());
+    for s in 1..n {
+        let top = usize::BITS - 1 - s.leading_zeros();
+        let rest = s ^ (1 << top);
+        let monomial = if rest == 0 {
+            bits[top as usize].lc()
+        } else {
+            let (_, _, o) = cs.multiply(monomials[rest].clone(), bits[top as usize].lc());
+            o.into()
+        };
+        monomials.push(monomial);
+    }
+
+    // Coefficients of the multilinear polynomials, by Moebius inversion.
+    let mut cx: Vec<_> = table.iter().map(|p| p.x).collect();
+    let mut cy: Vec<_> = table.iter().map(|p| p.y).collect();
+    for j in 0..bits.len() {
+        for s in 0..n {
+            if s & (1 << j) != 0 {
+                cx[s] = cx[s] - cx[s ^ (1 << j)];
+                cy[s] = cy[s] - cy[s ^ (1 << j)];
+            }
+        }
+    }
+
+    let x = monomials
+        .iter()
+        .zip(&cx)
+        .fold(LinearCombination::default(), |acc, (m, c)| {
+            acc + m.clone() * *c
+        });
+    let y = monomials
+        .iter()
+        .zip(&cy)
+        .fold(LinearCombination::default(), |acc, (m, c)| {
+            acc + m.clone() * *c
+        });
+    let value = bits
+        .iter()
+        .rev()
+        .try_fold(0usize, |m, bit| Some((m << 1) | bit.value()? as usize))
+        .map(|m| (table[m].x, table[m].y));
+
+    PointVar {
+        x: x.simplify(),
+        y: y.simplify(),
+        value,
+    }
+}
+
+/// Hashes field elements by hashing the concatenation of their
+/// little-endian bits, and returns the `x` coordinate of the hash.
+///
+/// The bits of every input are not checked to be the canonical encoding
+/// of the input, so the prover could use the encoding of `x + p` instead
+/// of `x` when it fits in `MODULUS_BIT_SIZE` bits.  This yields a
+/// different hash, so it does not help the prover to forge membership
+/// of `x` in a commitment tree.
+///
+/// # Panics
+///
+/// [`CircuitHash::hash`] panics if the inputs exceed the maximum length
+/// of the messages.
+impl<C: SWCurveConfig> CircuitHash<C::BaseField> for PedersenHash<C>
+where
+    C::BaseField: PrimeField,
+{
+    fn hash(&self, inputs: &[C::BaseField]) -> C::BaseField {
+        let n = C::BaseField::MODULUS_BIT_SIZE as usize;
+        let bits: Vec<bool> = inputs
+            .iter()
+            .flat_map(|x| {
+                let repr = x.into_bigint();
+                (0..n).map(move |i| repr.get_bit(i))
+            })
+            .collect();
+        self.hash_bits(&bits).unwrap().x
+    }
+
+    fn synthesize<CS: ConstraintSystem<C::BaseField>>(
+        &self,
+        cs: &mut CS,
+        inputs: &[LinearCombination<C::BaseField>],
+        values: Option<&[C::BaseField]>,
+    ) -> Result<LinearCombination<C::BaseField>, R1CSError> {
+        let n = C::BaseField::MODULUS_BIT_SIZE as usize;
+        self.check_len(inputs.len() * n)?;
+
+        let mut bits = Vec::with_capacity(inputs.len() * n);
+        for (i, x) in inputs.iter().enumerate() {
+            let input_bits = decompose(cs, values.map(|v| v[i]), n)?;
+            cs.constrain(recompose(&input_bits) - x.clone());
+            for bit in input_bits {
+                bits.push(Boolean::from_bit(bit)?);
+            }
+        }
+
+        Ok(self.hash_bits_gadget(cs, &bits)?.x)
+    }
+}
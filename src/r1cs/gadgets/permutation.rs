@@ -0,0 +1,105 @@
+//! Permutation (shuffle) arguments over randomized constraints.
+
+use ark_ff::PrimeField;
+use ark_std::{string::ToString, vec::Vec};
+
+use crate::r1cs::{
+    ConstraintSystem, LinearCombination, R1CSError, RandomizableConstraintSystem,
+    RandomizedConstraintSystem,
+};
+
+/// Enforces that `y` is a permutation of `x`.
+///
+/// Once all the variables are committed, the verifier's challenge `z` is
+/// used to check the grand-product identity
+/// `Prod(x_i - z) = Prod(y_i - z)`, which holds for a random `z` only if
+/// the two vectors are equal as multisets.  This costs `2 * (k - 1)`
+/// multipliers for vectors of length `k`.
+pub fn enforce_permutation<F: PrimeField, CS: RandomizableConstraintSystem<F>>(
+    cs: &mut CS,
+    x: Vec<LinearCombination<F>>,
+    y: Vec<LinearCombination<F>>,
+) -> Result<(), R1CSError> {
+    if x.len() != y.len() {
+        return Err(R1CSError::GadgetError {
+            description: "a permutation needs vectors of the same length".to_string(),
+        });
+    }
+    if x.len() <= 1 {
+        for (x, y) in x.into_iter().zip(y) {
+            cs.constrain(y - x);
+        }
+        return Ok(());
+    }
+
+    cs.specify_randomized_constraints(move |cs| {
+        let z = cs.challenge_scalar(b"permutation challenge");
+        enforce_equal_products(cs, &x, &y, z);
+        Ok(())
+    })
+}
+
+/// Enforces that the tuples `y` are a permutation of the tuples `x`, so
+/// that the components of each tuple move together.
+///
+/// Every tuple `t` is compressed to `Sum(w^j * t_j)` with a first
+/// challenge `w`, before applying the grand-product check of
+/// [`enforce_permutation`] with a second challenge.
+pub fn enforce_tuple_permutation<F: PrimeField, CS: RandomizableConstraintSystem<F>>(
+    cs: &mut CS,
+    x: Vec<Vec<LinearCombination<F>>>,
+    y: Vec<Vec<LinearCombination<F>>>,
+) -> Result<(), R1CSError> {
+    let width = x.first().or(y.first()).map_or(0, |t| t.len());
+    if x.len() != y.len() || x.iter().chain(&y).any(|t| t.len() != width) {
+        return Err(R1CSError::GadgetError {
+            description: "a permutation needs the same number of tuples of the same width"
+                .to_string(),
+        });
+    }
+    if width <= 1 || x.len() <= 1 {
+        let flatten = |v: Vec<Vec<_>>| v.into_iter().flatten().collect();
+        return enforce_permutation(cs, flatten(x), flatten(y));
+    }
+
+    cs.specify_randomized_constraints(move |cs| {
+        let w = cs.challenge_scalar(b"permutation tuple challenge");
+        let z = cs.challenge_scalar(b"permutation challenge");
+        let compress = |tuples: &[Vec<LinearCombination<F>>]| -> Vec<_> {
+            tuples
+                .iter()
+                .map(|t| {
+                    let mut w_j = F::one();
+                    let mut lc = LinearCombination::default();
+                    for t_j in t {
+                        lc = lc + t_j.clone() * w_j;
+                        w_j *= w;
+                    }
+                    lc
+                })
+                .collect()
+        };
+        enforce_equal_products(cs, &compress(&x), &compress(&y), z);
+        Ok(())
+    })
+}
+
+/// Enforces `Prod(x_i - z) = Prod(y_i - z)` for vectors of the same
+/// length of at least 2.
+fn enforce_equal_products<F: PrimeField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    x: &[LinearCombination<F>],
+    y: &[LinearCombination<F>],
+    z: F,
+) {
+    let mut product = |v: &[LinearCombination<F>]| {
+        let (_, _, first) = cs.multiply(v[0].clone() - z, v[1].clone() - z);
+        v[2..].iter().fold(first, |prev, v_i| {
+            let (_, _, o) = cs.multiply(prev.into(), v_i.clone() - z);
+            o
+        })
+    };
+    let x_product = product(x);
+    let y_product = product(y);
+    cs.constrain(x_product - y_product);
+}
@@ -0,0 +1,197 @@
+//! The Poseidon hash function, natively and as a gadget.
+
+use ark_ff::PrimeField;
+use ark_std::{string::ToString, vec, vec::Vec};
+
+use super::hash::{cauchy_mds, derive_constants, is_valid_alpha, mix, mix_gadget, pow_gadget};
+use super::CircuitHash;
+use crate::r1cs::{ConstraintSystem, LinearCombination, R1CSError};
+
+/// Parameters of a Poseidon permutation of width `t` over the field `F`.
+///
+/// The round constants are derived from SHA3-512 in counter mode over
+/// a label binding the width, S-box exponent and number of rounds, and
+/// the MDS matrix is the Cauchy matrix `M[i][j] = 1 / (i + t + j)`.  Both
+/// are nothing-up-my-sleeve numbers, so the same parameters can be
+/// regenerated for any field, including the scalar fields of the curves
+/// supported by this crate.  They differ from the constants of the
+/// reference implementation of the Poseidon paper, so the hashes do not
+/// match those of other Poseidon implementations.
+///
+/// Unlike the reference implementation, the matrix is not checked for
+/// the infinitely long subspace trails of Grassi, Rechberger and
+/// Schofnegger, which let some matrices bypass the partial rounds.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PoseidonParams<F: PrimeField> {
+    /// Width of the permutation.
+    pub t: usize,
+    /// Exponent of the S-box `x -> x^alpha`.
+    pub alpha: u64,
+    /// Number of full rounds, half of them before the partial rounds.
+    pub full_rounds: usize,
+    /// Number of partial rounds.
+    pub partial_rounds: usize,
+    /// Round constants, `t` per round.
+    pub round_constants: Vec<Vec<F>>,
+    /// The `t x t` MDS matrix.
+    pub mds: Vec<Vec<F>>,
+}
+
+impl<F: PrimeField> PoseidonParams<F> {
+    /// Generates parameters with the given width, S-box exponent and
+    /// number of rounds.
+    ///
+    /// The numbers of rounds are not checked: they should be computed
+    /// for the field, the width and the S-box with the round numbers
+    /// script of the Poseidon paper, e.g. 8 full rounds and 57 partial
+    /// rounds for `t = 3` and `alpha = 5` over fields of about 255 bits,
+    /// for 128-bit security.
+    ///
+    /// Returns an error if the parameters are malformed, or if `alpha`
+    /// is not coprime with `p - 1`, since the S-box would not be a
+    /// permutation.
+    pub fn new(
+        t: usize,
+        alpha: u64,
+        full_rounds: usize,
+        partial_rounds: usize,
+    ) -> Result<Self, R1CSError> {
+        if t < 2 || !full_rounds.is_multiple_of(2) || !is_valid_alpha::<F>(alpha) {
+            return Err(R1CSError::GadgetError {
+                description: "invalid Poseidon parameters".to_string(),
+            });
+        }
+
+        let mut label = Vec::new();
+        label.extend_from_slice(b"PoseidonParams");
+        for x in [t as u64, alpha, full_rounds as u64, partial_rounds as u64] {
+            label.extend_from_slice(&x.to_le_bytes());
+        }
+        let round_constants = derive_constants(&label, (full_rounds + partial_rounds) * t)
+            .chunks(t)
+            .map(|c| c.to_vec())
+            .collect();
+
+        let mds = cauchy_mds(t);
+
+        Ok(PoseidonParams {
+            t,
+            alpha,
+            full_rounds,
+            partial_rounds,
+            round_constants,
+            mds,
+        })
+    }
+
+    /// Returns the number of inputs absorbed per permutation.
+    pub fn rate(&self) -> usize {
+        self.t - 1
+    }
+
+    /// Applies the Poseidon permutation to `state`.
+    pub fn permute(&self, state: &mut [F]) {
+        let half_full = self.full_rounds / 2;
+        for (r, constants) in self.round_constants.iter().enumerate() {
+            for (s, c) in state.iter_mut().zip(constants) {
+                *s += c;
+            }
+            if r < half_full || r >= half_full + self.partial_rounds {
+                for s in state.iter_mut() {
+                    *s = s.pow([self.alpha]);
+                }
+            } else {
+                state[0] = state[0].pow([self.alpha]);
+            }
+            let mixed = mix(&self.mds, state);
+            state.copy_from_slice(&mixed);
+        }
+    }
+}
+
+/// The Poseidon hash function, as a sponge with a capacity of one
+/// field element.
+///
+/// The capacity element is initialized with the number of inputs, which
+/// are then absorbed `rate` at a time, and the hash is the first rate
+/// element of the final state.  The gadget applies the S-box with
+/// square-and-multiply, so every S-box costs about `log2(alpha) + 1`
+/// multipliers: with 8 full and 57 partial rounds of `x^5` over a width
+/// of 3, hashing up to 2 inputs costs less than 300 multipliers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Poseidon<F: PrimeField> {
+    params: PoseidonParams<F>,
+}
+
+impl<F: PrimeField> Poseidon<F> {
+    /// Creates the hash function from the given parameters.
+    pub fn new(params: PoseidonParams<F>) -> Self {
+        Poseidon { params }
+    }
+
+    /// Returns the parameters of the hash function.
+    pub fn params(&self) -> &PoseidonParams<F> {
+        &self.params
+    }
+
+    /// Applies the Poseidon permutation to `state` in the constraint system.
+    fn permute_gadget<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        state: &mut [LinearCombination<F>],
+    ) {
+        let params = &self.params;
+        let half_full = params.full_rounds / 2;
+        for (r, constants) in params.round_constants.iter().enumerate() {
+            for (s, c) in state.iter_mut().zip(constants) {
+                *s = s.clone() + *c;
+            }
+            if r < half_full || r >= half_full + params.partial_rounds {
+                for s in state.iter_mut() {
+                    *s = pow_gadget(cs, s.clone(), params.alpha);
+                }
+            } else {
+                state[0] = pow_gadget(cs, state[0].clone(), params.alpha);
+            }
+            let mixed = mix_gadget(&params.mds, state);
+            state.clone_from_slice(&mixed);
+        }
+    }
+}
+
+impl<F: PrimeField> CircuitHash<F> for Poseidon<F> {
+    fn hash(&self, inputs: &[F]) -> F {
+        let mut state = vec![F::zero(); self.params.t];
+        state[0] = F::from(inputs.len() as u64);
+        if inputs.is_empty() {
+            self.params.permute(&mut state);
+        }
+        for chunk in inputs.chunks(self.params.rate()) {
+            for (s, x) in state[1..].iter_mut().zip(chunk) {
+                *s += x;
+            }
+            self.params.permute(&mut state);
+        }
+        state[1]
+    }
+
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        inputs: &[LinearCombination<F>],
+        _: Option<&[F]>,
+    ) -> Result<LinearCombination<F>, R1CSError> {
+        let mut state = vec![LinearCombination::default(); self.params.t];
+        state[0] = F::from(inputs.len() as u64).into();
+        if inputs.is_empty() {
+            self.permute_gadget(cs, &mut state);
+        }
+        for chunk in inputs.chunks(self.params.rate()) {
+            for (s, x) in state[1..].iter_mut().zip(chunk) {
+                *s = s.clone() + x.clone();
+            }
+            self.permute_gadget(cs, &mut state);
+        }
+        Ok(state.swap_remove(1))
+    }
+}
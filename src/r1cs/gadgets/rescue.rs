@@ -0,0 +1,217 @@
+//! The Rescue hash function, natively and as a gadget.
+
+use ark_ff::PrimeField;
+use ark_std::{string::ToString, vec, vec::Vec};
+
+use super::hash::{alpha_inverse, cauchy_mds, derive_constants, mix, mix_gadget, pow_gadget};
+use super::CircuitHash;
+use crate::r1cs::{ConstraintSystem, LinearCombination, R1CSError};
+
+/// Parameters of a Rescue permutation of width `t` over the field `F`.
+///
+/// Every round applies the S-box `x -> x^alpha` to the whole state,
+/// mixes it with the MDS matrix and adds round constants, then does the
+/// same with the inverse S-box `x -> x^(1/alpha)`.  The constants are
+/// derived from SHA3-512 and the MDS matrix is the same Cauchy matrix
+/// as for [`PoseidonParams`](super::PoseidonParams).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RescueParams<F: PrimeField> {
+    /// Width of the permutation.
+    pub t: usize,
+    /// Exponent of the S-box `x -> x^alpha`.
+    pub alpha: u64,
+    /// Little-endian limbs of the inverse of `alpha` modulo `p - 1`.
+    pub alpha_inv: Vec<u64>,
+    /// Number of rounds, each made of two steps.
+    pub rounds: usize,
+    /// Round constants, `t` per step.
+    pub round_constants: Vec<Vec<F>>,
+    /// The `t x t` MDS matrix.
+    pub mds: Vec<Vec<F>>,
+}
+
+impl<F: PrimeField> RescueParams<F> {
+    /// Generates parameters with the given width, S-box exponent and
+    /// number of rounds.
+    ///
+    /// Returns an error if the parameters are malformed, or if `alpha`
+    /// is not coprime with `p - 1`.
+    pub fn new(t: usize, alpha: u64, rounds: usize) -> Result<Self, R1CSError> {
+        let alpha_inv = alpha_inverse::<F>(alpha)
+            .filter(|_| t >= 2 && rounds > 0)
+            .ok_or_else(|| R1CSError::GadgetError {
+                description: "invalid Rescue parameters".to_string(),
+            })?;
+
+        let mut label = Vec::new();
+        label.extend_from_slice(b"RescueParams");
+        for x in [t as u64, alpha, rounds as u64] {
+            label.extend_from_slice(&x.to_le_bytes());
+        }
+        let round_constants = derive_constants(&label, 2 * rounds * t)
+            .chunks(t)
+            .map(|c| c.to_vec())
+            .collect();
+
+        Ok(RescueParams {
+            t,
+            alpha,
+            alpha_inv,
+            rounds,
+            round_constants,
+            mds: cauchy_mds(t),
+        })
+    }
+
+    /// Generates the default parameters of width `t`, with the smallest
+    /// of 3, 5, 7 and 11 which is coprime with `p - 1` as the S-box
+    /// exponent, and 12 rounds, a conservative choice for 128-bit
+    /// security over fields of about 256 bits.
+    pub fn with_width(t: usize) -> Result<Self, R1CSError> {
+        let alpha = [3u64, 5, 7, 11]
+            .iter()
+            .copied()
+            .find(|a| alpha_inverse::<F>(*a).is_some())
+            .ok_or_else(|| R1CSError::GadgetError {
+                description: "no suitable Rescue S-box for this field".to_string(),
+            })?;
+        Self::new(t, alpha, 12)
+    }
+
+    /// Returns the number of inputs absorbed per permutation.
+    pub fn rate(&self) -> usize {
+        self.t - 1
+    }
+
+    /// Applies the Rescue permutation to `state`.
+    pub fn permute(&self, state: &mut [F]) {
+        for (step, constants) in self.round_constants.iter().enumerate() {
+            for s in state.iter_mut() {
+                *s = if step % 2 == 0 {
+                    s.pow([self.alpha])
+                } else {
+                    s.pow(&self.alpha_inv)
+                };
+            }
+            let mixed = mix(&self.mds, state);
+            for ((s, m), c) in state.iter_mut().zip(mixed).zip(constants) {
+                *s = m + c;
+            }
+        }
+    }
+}
+
+/// The Rescue hash function, as a sponge with a capacity of one field
+/// element, absorbing inputs like [`Poseidon`](super::Poseidon).
+///
+/// The inverse S-box is computed by the prover, and the gadget allocates
+/// its output `y` and enforces `y^alpha = x`, so both S-boxes cost about
+/// `log2(alpha) + 1` multipliers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rescue<F: PrimeField> {
+    params: RescueParams<F>,
+}
+
+impl<F: PrimeField> Rescue<F> {
+    /// Creates the hash function from the given parameters.
+    pub fn new(params: RescueParams<F>) -> Self {
+        Rescue { params }
+    }
+
+    /// Returns the parameters of the hash function.
+    pub fn params(&self) -> &RescueParams<F> {
+        &self.params
+    }
+
+    /// Applies the Rescue permutation to `state` in the constraint
+    /// system, tracking the `values` of the state for the prover.
+    fn permute_gadget<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        state: &mut [LinearCombination<F>],
+        values: &mut Option<Vec<F>>,
+    ) -> Result<(), R1CSError> {
+        let params = &self.params;
+        for (step, constants) in params.round_constants.iter().enumerate() {
+            for (i, s) in state.iter_mut().enumerate() {
+                if step % 2 == 0 {
+                    *s = pow_gadget(cs, s.clone(), params.alpha);
+                } else {
+                    // Allocate y = x^(1/alpha) and enforce y^alpha = x.
+                    let y = values.as_ref().map(|v| v[i].pow(&params.alpha_inv));
+                    let y_var = cs.allocate(y)?;
+                    let y_alpha = pow_gadget(cs, y_var.into(), params.alpha);
+                    cs.constrain(y_alpha - s.clone());
+                    *s = y_var.into();
+                }
+            }
+            let mixed = mix_gadget(&params.mds, state);
+            for ((s, m), c) in state.iter_mut().zip(mixed).zip(constants) {
+                *s = m + *c;
+            }
+
+            if let Some(v) = values.as_mut() {
+                for s in v.iter_mut() {
+                    *s = if step % 2 == 0 {
+                        s.pow([params.alpha])
+                    } else {
+                        s.pow(&params.alpha_inv)
+                    };
+                }
+                let mixed = mix(&params.mds, v);
+                for ((s, m), c) in v.iter_mut().zip(mixed).zip(constants) {
+                    *s = m + c;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<F: PrimeField> CircuitHash<F> for Rescue<F> {
+    fn hash(&self, inputs: &[F]) -> F {
+        let mut state = vec![F::zero(); self.params.t];
+        state[0] = F::from(inputs.len() as u64);
+        if inputs.is_empty() {
+            self.params.permute(&mut state);
+        }
+        for chunk in inputs.chunks(self.params.rate()) {
+            for (s, x) in state[1..].iter_mut().zip(chunk) {
+                *s += x;
+            }
+            self.params.permute(&mut state);
+        }
+        state[1]
+    }
+
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        inputs: &[LinearCombination<F>],
+        values: Option<&[F]>,
+    ) -> Result<LinearCombination<F>, R1CSError> {
+        let t = self.params.t;
+        let mut state = vec![LinearCombination::default(); t];
+        state[0] = F::from(inputs.len() as u64).into();
+        let mut state_values = values.map(|_| {
+            let mut v = vec![F::zero(); t];
+            v[0] = F::from(inputs.len() as u64);
+            v
+        });
+
+        if inputs.is_empty() {
+            self.permute_gadget(cs, &mut state, &mut state_values)?;
+        }
+        let rate = self.params.rate();
+        for (n, chunk) in inputs.chunks(rate).enumerate() {
+            for (i, x) in chunk.iter().enumerate() {
+                state[1 + i] = state[1 + i].clone() + x.clone();
+                if let (Some(v), Some(values)) = (state_values.as_mut(), values) {
+                    v[1 + i] += values[n * rate + i];
+                }
+            }
+            self.permute_gadget(cs, &mut state, &mut state_values)?;
+        }
+        Ok(state.swap_remove(1))
+    }
+}
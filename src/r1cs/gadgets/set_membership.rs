@@ -0,0 +1,81 @@
+//! Membership of a variable in a public or committed set.
+
+use ark_ff::PrimeField;
+use ark_std::{string::ToString, vec::Vec};
+
+use super::{AllocatedScalar, Gadget};
+use crate::r1cs::{ConstraintSystem, LinearCombination, R1CSError};
+
+/// Enforces that a variable is a member of a public set.
+///
+/// The gadget enforces that the product of the differences between
+/// the variable and every element of the set is zero, using
+/// `set.len() - 1` multipliers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SetMembership<F: PrimeField> {
+    set: Vec<F>,
+}
+
+impl<F: PrimeField> SetMembership<F> {
+    /// Creates a gadget enforcing membership in `set`.
+    pub fn new(set: Vec<F>) -> Self {
+        SetMembership { set }
+    }
+
+    /// Returns the elements of the set.
+    pub fn set(&self) -> &[F] {
+        &self.set
+    }
+}
+
+impl<F: PrimeField> Gadget<F> for SetMembership<F> {
+    type Input = AllocatedScalar<F>;
+    type Output = ();
+
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        v: AllocatedScalar<F>,
+    ) -> Result<(), R1CSError> {
+        enforce_zero_product(cs, self.set.iter().map(|s| v.variable - *s))
+    }
+}
+
+/// Enforces that a variable is a member of a committed set.
+///
+/// The input is the variable and the elements of the set, which are
+/// typically committed high-level variables.  Like [`SetMembership`],
+/// this uses `set.len() - 1` multipliers.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CommittedSetMembership;
+
+impl<F: PrimeField> Gadget<F> for CommittedSetMembership {
+    type Input = (AllocatedScalar<F>, Vec<AllocatedScalar<F>>);
+    type Output = ();
+
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        (v, set): Self::Input,
+    ) -> Result<(), R1CSError> {
+        enforce_zero_product(cs, set.iter().map(|s| v.variable - s.variable))
+    }
+}
+
+/// Enforces that the product of the given linear combinations is zero.
+fn enforce_zero_product<F: PrimeField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    mut factors: impl Iterator<Item = LinearCombination<F>>,
+) -> Result<(), R1CSError> {
+    let first = factors.next().ok_or_else(|| R1CSError::GadgetError {
+        description: "set membership in an empty set".to_string(),
+    })?;
+
+    let product = factors.fold(first, |product, factor| {
+        let (_, _, o) = cs.multiply(product, factor);
+        o.into()
+    });
+    cs.constrain(product);
+
+    Ok(())
+}
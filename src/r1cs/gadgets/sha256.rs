@@ -0,0 +1,190 @@
+//! The SHA-256 compression function, natively and as a gadget.
+
+use ark_ff::PrimeField;
+use ark_std::{string::ToString, vec::Vec};
+
+use super::{Boolean, UInt32};
+use crate::r1cs::{ConstraintSystem, R1CSError};
+
+/// The initial hash value of SHA-256.
+pub const SHA256_IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// The round constants of SHA-256.
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// Applies the SHA-256 compression function to `state` with a 16-word
+/// message `block`, natively.
+pub fn sha256_compress(state: &mut [u32; 8], block: &[u32; 16]) {
+    let mut w = [0u32; 64];
+    w[..16].copy_from_slice(block);
+    for i in 16..64 {
+        let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+        let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+        w[i] = w[i - 16]
+            .wrapping_add(s0)
+            .wrapping_add(w[i - 7])
+            .wrapping_add(s1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+    for i in 0..64 {
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let ch = (e & f) ^ (!e & g);
+        let t1 = h
+            .wrapping_add(s1)
+            .wrapping_add(ch)
+            .wrapping_add(K[i])
+            .wrapping_add(w[i]);
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let t2 = s0.wrapping_add(maj);
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.wrapping_add(t2);
+    }
+
+    for (s, x) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+        *s = s.wrapping_add(x);
+    }
+}
+
+/// Applies the SHA-256 compression function to `state` with a 16-word
+/// message `block` in the constraint system, and returns the new state.
+///
+/// The words are [`UInt32`]s, so the message and the state may be
+/// allocated, constant, or computed by other gadgets.  A compression
+/// costs about 30000 multipliers.
+pub fn sha256_compress_gadget<F: PrimeField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    state: &[UInt32<F>],
+    block: &[UInt32<F>],
+) -> Result<Vec<UInt32<F>>, R1CSError> {
+    if state.len() != 8 || block.len() != 16 {
+        return Err(R1CSError::GadgetError {
+            description: "SHA-256 compression needs 8 state words and 16 message words".to_string(),
+        });
+    }
+
+    let mut w = block.to_vec();
+    for i in 16..64 {
+        let s0 = xor3(
+            cs,
+            &w[i - 15].rotr(7),
+            &w[i - 15].rotr(18),
+            &w[i - 15].shr(3),
+        );
+        let s1 = xor3(
+            cs,
+            &w[i - 2].rotr(17),
+            &w[i - 2].rotr(19),
+            &w[i - 2].shr(10),
+        );
+        let wi = UInt32::add_many(cs, &[w[i - 16].clone(), s0, w[i - 7].clone(), s1])?;
+        w.push(wi);
+    }
+
+    let mut v = state.to_vec();
+    for i in 0..64 {
+        let (a, b, c, e, f, g) = (&v[0], &v[1], &v[2], &v[4], &v[5], &v[6]);
+
+        let s1 = xor3(cs, &e.rotr(6), &e.rotr(11), &e.rotr(25));
+        let ch = bitwise3(cs, e, f, g, ch);
+        let s0 = xor3(cs, &a.rotr(2), &a.rotr(13), &a.rotr(22));
+        let maj = bitwise3(cs, a, b, c, maj);
+
+        let t1 = [v[7].clone(), s1, ch, UInt32::constant(K[i]), w[i].clone()];
+        let e = UInt32::add_many(cs, &[&t1[..], &[v[3].clone()]].concat())?;
+        let a = UInt32::add_many(cs, &[&t1[..], &[s0, maj]].concat())?;
+
+        v.pop();
+        v.insert(0, a);
+        v[4] = e;
+    }
+
+    state
+        .iter()
+        .zip(v)
+        .map(|(s, x)| UInt32::add_many(cs, &[s.clone(), x]))
+        .collect()
+}
+
+/// Returns `a XOR b XOR c`, using 64 multipliers.
+fn xor3<F: PrimeField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    a: &UInt32<F>,
+    b: &UInt32<F>,
+    c: &UInt32<F>,
+) -> UInt32<F> {
+    let ab = UInt32::xor(cs, a, b);
+    UInt32::xor(cs, &ab, c)
+}
+
+/// A function of three bits, computed in the constraint system.
+type BitFunction<F, CS> = fn(&mut CS, &Boolean<F>, &Boolean<F>, &Boolean<F>) -> Boolean<F>;
+
+/// Applies a bitwise function of three bits to three integers.
+fn bitwise3<F: PrimeField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    a: &UInt32<F>,
+    b: &UInt32<F>,
+    c: &UInt32<F>,
+    f: BitFunction<F, CS>,
+) -> UInt32<F> {
+    let bits = a
+        .bits()
+        .iter()
+        .zip(b.bits())
+        .zip(c.bits())
+        .map(|((a, b), c)| f(cs, a, b, c))
+        .collect();
+    UInt32::from_bits(bits).unwrap()
+}
+
+/// `Ch(e, f, g) = g + e * (f - g)`, using one multiplier.
+fn ch<F: PrimeField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    e: &Boolean<F>,
+    f: &Boolean<F>,
+    g: &Boolean<F>,
+) -> Boolean<F> {
+    let (_, _, o) = cs.multiply(e.lc(), f.lc() - g.lc());
+    let value = e
+        .value()
+        .zip(f.value())
+        .zip(g.value())
+        .map(|((e, f), g)| if e { f } else { g });
+    Boolean::from_lc_unchecked(g.lc() + o, value)
+}
+
+/// `Maj(a, b, c) = b * c + a * (b + c - 2 * b * c)`, using two multipliers.
+fn maj<F: PrimeField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    a: &Boolean<F>,
+    b: &Boolean<F>,
+    c: &Boolean<F>,
+) -> Boolean<F> {
+    let (_, _, bc) = cs.multiply(b.lc(), c.lc());
+    let (_, _, o) = cs.multiply(a.lc(), b.lc() + c.lc() - bc * F::from(2u64));
+    let value = a
+        .value()
+        .zip(b.value())
+        .zip(c.value())
+        .map(|((a, b), c)| (a & b) | (a & c) | (b & c));
+    Boolean::from_lc_unchecked(o + bc, value)
+}
@@ -0,0 +1,174 @@
+//! 32-bit unsigned integers as little-endian bits.
+
+use ark_ff::PrimeField;
+use ark_std::{string::ToString, vec::Vec};
+
+use super::bits::{decompose, recompose};
+use super::Boolean;
+use crate::r1cs::{ConstraintSystem, LinearCombination, R1CSError};
+
+/// A 32-bit unsigned integer in the constraint system, as its 32
+/// little-endian bits, together with its value, which is only known to
+/// the prover.
+///
+/// Bitwise operations cost at most one multiplier per bit, rotations and
+/// shifts are free, and additions modulo `2^32` cost one multiplier per
+/// bit of the sum, including its carry bits.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UInt32<F: PrimeField> {
+    bits: Vec<Boolean<F>>,
+    value: Option<u32>,
+}
+
+impl<F: PrimeField> UInt32<F> {
+    /// Allocates a new integer with the given `value`, using 32 multipliers.
+    pub fn allocate<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        value: Option<u32>,
+    ) -> Result<Self, R1CSError> {
+        let bits = (0..32)
+            .map(|i| Boolean::allocate(cs, value.map(|v| (v >> i) & 1 == 1)))
+            .collect::<Result<_, _>>()?;
+        Ok(UInt32 { bits, value })
+    }
+
+    /// Returns a constant integer.
+    pub fn constant(value: u32) -> Self {
+        UInt32 {
+            bits: (0..32)
+                .map(|i| Boolean::constant((value >> i) & 1 == 1))
+                .collect(),
+            value: Some(value),
+        }
+    }
+
+    /// Creates an integer from its 32 little-endian `bits`.
+    pub fn from_bits(bits: Vec<Boolean<F>>) -> Result<Self, R1CSError> {
+        if bits.len() != 32 {
+            return Err(R1CSError::GadgetError {
+                description: "a 32-bit integer needs exactly 32 bits".to_string(),
+            });
+        }
+        let value = bits
+            .iter()
+            .rev()
+            .try_fold(0u32, |v, bit| Some((v << 1) | bit.value()? as u32));
+        Ok(UInt32 { bits, value })
+    }
+
+    /// Returns the little-endian bits of the integer.
+    pub fn bits(&self) -> &[Boolean<F>] {
+        &self.bits
+    }
+
+    /// Returns the value of the integer, if known.
+    pub fn value(&self) -> Option<u32> {
+        self.value
+    }
+
+    /// Returns the linear combination `Sum(b_i * 2^i)` of the bits.
+    pub fn lc(&self) -> LinearCombination<F> {
+        let mut exp_2 = F::one();
+        let mut lc = LinearCombination::default();
+        for bit in &self.bits {
+            lc = lc + bit.lc() * exp_2;
+            exp_2.double_in_place();
+        }
+        lc
+    }
+
+    /// Rotates the integer right by `n` bits.
+    pub fn rotr(&self, n: usize) -> Self {
+        UInt32 {
+            bits: (0..32).map(|i| self.bits[(i + n) % 32].clone()).collect(),
+            value: self.value.map(|v| v.rotate_right(n as u32)),
+        }
+    }
+
+    /// Shifts the integer right by `n` bits.
+    pub fn shr(&self, n: usize) -> Self {
+        UInt32 {
+            bits: (0..32)
+                .map(|i| match self.bits.get(i + n) {
+                    Some(bit) => bit.clone(),
+                    None => Boolean::constant(false),
+                })
+                .collect(),
+            value: self.value.map(|v| v.checked_shr(n as u32).unwrap_or(0)),
+        }
+    }
+
+    /// Returns the bitwise `NOT` of the integer.
+    pub fn not(&self) -> Self {
+        UInt32 {
+            bits: self.bits.iter().map(|b| b.not()).collect(),
+            value: self.value.map(|v| !v),
+        }
+    }
+
+    /// Returns `a XOR b`, using 32 multipliers.
+    pub fn xor<CS: ConstraintSystem<F>>(cs: &mut CS, a: &Self, b: &Self) -> Self {
+        Self::bitwise(cs, a, b, Boolean::xor, |a, b| a ^ b)
+    }
+
+    /// Returns `a AND b`, using 32 multipliers.
+    pub fn and<CS: ConstraintSystem<F>>(cs: &mut CS, a: &Self, b: &Self) -> Self {
+        Self::bitwise(cs, a, b, Boolean::and, |a, b| a & b)
+    }
+
+    /// Returns `a OR b`, using 32 multipliers.
+    pub fn or<CS: ConstraintSystem<F>>(cs: &mut CS, a: &Self, b: &Self) -> Self {
+        Self::bitwise(cs, a, b, Boolean::or, |a, b| a | b)
+    }
+
+    fn bitwise<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        a: &Self,
+        b: &Self,
+        gadget: impl Fn(&mut CS, &Boolean<F>, &Boolean<F>) -> Boolean<F>,
+        native: impl Fn(u32, u32) -> u32,
+    ) -> Self {
+        UInt32 {
+            bits: a
+                .bits
+                .iter()
+                .zip(&b.bits)
+                .map(|(a, b)| gadget(cs, a, b))
+                .collect(),
+            value: a.value.zip(b.value).map(|(a, b)| native(a, b)),
+        }
+    }
+
+    /// Returns the sum of `operands` modulo `2^32`.
+    ///
+    /// The sum is decomposed into 32 bits and `ceil(log2(operands.len()))`
+    /// carry bits, which costs one multiplier per bit.
+    pub fn add_many<CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        operands: &[Self],
+    ) -> Result<Self, R1CSError> {
+        if operands.is_empty() {
+            return Ok(Self::constant(0));
+        }
+        let carry_bits = (usize::BITS - (operands.len() - 1).leading_zeros()) as usize;
+
+        let sum = operands
+            .iter()
+            .fold(LinearCombination::default(), |acc, x| acc + x.lc());
+        let value = operands
+            .iter()
+            .try_fold(0u64, |acc, x| Some(acc + x.value? as u64));
+
+        let bits = decompose(cs, value.map(F::from), 32 + carry_bits)?;
+        cs.constrain(recompose(&bits) - sum);
+
+        let bits = bits[..32]
+            .iter()
+            .map(|bit| Boolean::from_bit(*bit))
+            .collect::<Result<_, _>>()?;
+        Ok(UInt32 {
+            bits,
+            value: value.map(|v| v as u32),
+        })
+    }
+}
@@ -0,0 +1,64 @@
+//! Definition of the limits of the sizes of the proofs that verifiers
+//! accept.
+
+use crate::errors::R1CSError;
+
+/// Upper bounds on the sizes of the proofs accepted by a
+/// [`Verifier`](::r1cs::Verifier), so that an untrusted proof cannot
+/// make a node compute a gigantic multiscalar multiplication.
+///
+/// The limits are set with
+/// [`Verifier::set_limits`](::r1cs::Verifier::set_limits), and checked
+/// before the verifier allocates anything for the proof: a proof or a
+/// circuit exceeding one of them is rejected with
+/// [`R1CSError::LimitExceeded`].  By default, nothing is limited.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerifierLimits {
+    /// The maximum number of multipliers of the circuit, including the
+    /// entries of the vector commitments and the multipliers of the
+    /// randomized constraints.
+    pub max_multipliers: usize,
+    /// The maximum number of high-level variable and vector commitments.
+    pub max_commitments: usize,
+    /// The maximum number of rounds of the inner-product proof.
+    pub max_ipp_rounds: usize,
+}
+
+impl Default for VerifierLimits {
+    fn default() -> Self {
+        VerifierLimits {
+            max_multipliers: usize::MAX,
+            max_commitments: usize::MAX,
+            max_ipp_rounds: usize::MAX,
+        }
+    }
+}
+
+impl VerifierLimits {
+    /// Checks the number of multipliers against the limit.
+    pub(super) fn check_multipliers(&self, multipliers: usize) -> Result<(), R1CSError> {
+        check("multipliers", self.max_multipliers, multipliers)
+    }
+
+    /// Checks the number of commitments against the limit.
+    pub(super) fn check_commitments(&self, commitments: usize) -> Result<(), R1CSError> {
+        check("commitments", self.max_commitments, commitments)
+    }
+
+    /// Checks the number of rounds of the inner-product proof against
+    /// the limit.
+    pub(super) fn check_ipp_rounds(&self, rounds: usize) -> Result<(), R1CSError> {
+        check("inner-product rounds", self.max_ipp_rounds, rounds)
+    }
+}
+
+fn check(resource: &'static str, limit: usize, actual: usize) -> Result<(), R1CSError> {
+    if actual > limit {
+        return Err(R1CSError::LimitExceeded {
+            resource,
+            limit,
+            actual,
+        });
+    }
+    Ok(())
+}
@@ -2,7 +2,8 @@
 
 use ark_ff::PrimeField;
 use ark_std::{
-    iter::FromIterator,
+    collections::BTreeMap,
+    iter::{FromIterator, Sum},
     ops::{Add, Mul, Neg, Sub},
     vec,
     vec::Vec,
@@ -22,6 +23,9 @@ pub enum Variable<F: PrimeField> {
     MultiplierOutput(usize),
     /// Represents the constant 1.
     One(),
+    /// Represents a public input, whose value is known to both the
+    /// prover and the verifier.
+    Public(usize),
     /// Phantom.
     Phantom(PhantomData<F>),
 }
@@ -42,6 +46,34 @@ impl<F: PrimeField> From<F> for LinearCombination<F> {
     }
 }
 
+impl<F: PrimeField> From<&Variable<F>> for LinearCombination<F> {
+    fn from(v: &Variable<F>) -> LinearCombination<F> {
+        LinearCombination::from(*v)
+    }
+}
+
+impl<F: PrimeField> From<&LinearCombination<F>> for LinearCombination<F> {
+    fn from(lc: &LinearCombination<F>) -> LinearCombination<F> {
+        lc.clone()
+    }
+}
+
+impl<F: PrimeField> Variable<F> {
+    /// Returns a key identifying the variable, to merge the terms of the
+    /// same variable.
+    fn key(&self) -> (u8, usize) {
+        match self {
+            Variable::Committed(i) => (0, *i),
+            Variable::MultiplierLeft(i) => (1, *i),
+            Variable::MultiplierRight(i) => (2, *i),
+            Variable::MultiplierOutput(i) => (3, *i),
+            Variable::One() => (4, 0),
+            Variable::Public(i) => (5, *i),
+            Variable::Phantom(_) => (6, 0),
+        }
+    }
+}
+
 // Arithmetic on variables produces linear combinations
 
 impl<F: PrimeField> Neg for Variable<F> {
@@ -92,6 +124,33 @@ impl<F: PrimeField> Default for LinearCombination<F> {
     }
 }
 
+impl<F: PrimeField> LinearCombination<F> {
+    /// Merges the terms of the same variable and drops the terms with a
+    /// zero coefficient.
+    ///
+    /// Arithmetic on linear combinations simply concatenates their terms,
+    /// so this keeps linear combinations that are repeatedly mixed
+    /// (such as the state of a hash function) from growing unboundedly.
+    pub fn simplify(self) -> Self {
+        let mut terms: Vec<(Variable<F>, F)> = Vec::with_capacity(self.terms.len());
+        let mut positions: BTreeMap<(u8, usize), usize> = BTreeMap::new();
+        for (var, coeff) in self.terms {
+            match positions.get(&var.key()) {
+                Some(&i) => terms[i].1 += coeff,
+                None => {
+                    positions.insert(var.key(), terms.len());
+                    terms.push((var, coeff));
+                }
+            }
+        }
+        terms.retain(|(_, coeff)| !coeff.is_zero());
+        LinearCombination { terms }
+    }
+}
+
+/// Linear combinations built from their terms are
+/// [simplified](LinearCombination::simplify), so that every variable
+/// appears in a single term.
 impl<F: PrimeField> FromIterator<(Variable<F>, F)> for LinearCombination<F> {
     fn from_iter<T>(iter: T) -> Self
     where
@@ -100,6 +159,7 @@ impl<F: PrimeField> FromIterator<(Variable<F>, F)> for LinearCombination<F> {
         LinearCombination {
             terms: iter.into_iter().collect(),
         }
+        .simplify()
     }
 }
 
@@ -108,9 +168,13 @@ impl<'a, F: PrimeField> FromIterator<&'a (Variable<F>, F)> for LinearCombination
     where
         T: IntoIterator<Item = &'a (Variable<F>, F)>,
     {
-        LinearCombination {
-            terms: iter.into_iter().cloned().collect(),
-        }
+        iter.into_iter().cloned().collect()
+    }
+}
+
+impl<F: PrimeField, L: Into<LinearCombination<F>>> Sum<L> for LinearCombination<F> {
+    fn sum<I: Iterator<Item = L>>(iter: I) -> Self {
+        iter.fold(LinearCombination::default(), |acc, lc| acc + lc)
     }
 }
 
@@ -161,3 +225,85 @@ impl<F: PrimeField, S: Into<F>> Mul<S> for LinearCombination<F> {
         self
     }
 }
+
+// Arithmetic on references to linear combinations
+
+impl<F: PrimeField, L: Into<LinearCombination<F>>> Add<L> for &LinearCombination<F> {
+    type Output = LinearCombination<F>;
+
+    fn add(self, rhs: L) -> Self::Output {
+        self.clone() + rhs
+    }
+}
+
+impl<F: PrimeField, L: Into<LinearCombination<F>>> Sub<L> for &LinearCombination<F> {
+    type Output = LinearCombination<F>;
+
+    fn sub(self, rhs: L) -> Self::Output {
+        self.clone() - rhs
+    }
+}
+
+impl<F: PrimeField> Neg for &LinearCombination<F> {
+    type Output = LinearCombination<F>;
+
+    fn neg(self) -> Self::Output {
+        -self.clone()
+    }
+}
+
+impl<F: PrimeField, S: Into<F>> Mul<S> for &LinearCombination<F> {
+    type Output = LinearCombination<F>;
+
+    fn mul(self, other: S) -> Self::Output {
+        self.clone() * other
+    }
+}
+
+/// Builds a [`LinearCombination`] from an arithmetic expression.
+///
+/// The expression is a sum of terms, each either a variable (or linear
+/// combination) `v`, a scaled variable `c * v`, or a constant `c`, where
+/// coefficients and constants are literals or parenthesized expressions
+/// convertible into the field, and variables are identifiers or
+/// parenthesized expressions:
+/// ```ignore
+/// let lc = lc!(3 * a + b - 2 * c + 5);
+/// let lc = lc!((x.variable) - (k) * y - 1);
+/// ```
+///
+/// Variables are used by value, so linear combinations must be cloned
+/// to be used more than once.
+#[macro_export]
+macro_rules! lc {
+    (@acc ($acc:expr)) => {
+        $acc
+    };
+    (@acc ($acc:expr) + $c:tt * $v:tt $($rest:tt)*) => {
+        $crate::lc!(@acc ($acc + $v * $c) $($rest)*)
+    };
+    (@acc ($acc:expr) - $c:tt * $v:tt $($rest:tt)*) => {
+        $crate::lc!(@acc ($acc - $v * $c) $($rest)*)
+    };
+    (@acc ($acc:expr) + $c:literal $($rest:tt)*) => {
+        $crate::lc!(@acc ($acc + $crate::r1cs::Variable::One() * $c) $($rest)*)
+    };
+    (@acc ($acc:expr) - $c:literal $($rest:tt)*) => {
+        $crate::lc!(@acc ($acc - $crate::r1cs::Variable::One() * $c) $($rest)*)
+    };
+    (@acc ($acc:expr) + $v:tt $($rest:tt)*) => {
+        $crate::lc!(@acc ($acc + $v) $($rest)*)
+    };
+    (@acc ($acc:expr) - $v:tt $($rest:tt)*) => {
+        $crate::lc!(@acc ($acc - $v) $($rest)*)
+    };
+    () => {
+        $crate::r1cs::LinearCombination::default()
+    };
+    (- $($tokens:tt)+) => {
+        $crate::lc!(@acc ($crate::r1cs::LinearCombination::default()) - $($tokens)+)
+    };
+    ($($tokens:tt)+) => {
+        $crate::lc!(@acc ($crate::r1cs::LinearCombination::default()) + $($tokens)+)
+    };
+}
@@ -0,0 +1,101 @@
+//! Timings and counts of proofs, reported by
+//! [`Prover::prove_with_report`](::r1cs::Prover::prove_with_report) and
+//! [`Verifier::verify_with_report`](::r1cs::Verifier::verify_with_report)
+//! when the `metrics` feature is enabled.
+//!
+//! Without the feature, [`Metrics`] is empty and only runs the measured
+//! closures, so that the provers and verifiers are instrumented without
+//! conditional compilation.
+
+#[cfg(feature = "metrics")]
+use std::{cell::Cell, rc::Rc, time::Instant};
+
+/// The timings and counts of a proof or of a verification.
+///
+/// The report is meant to track performance across releases, so its
+/// timings are wall-clock durations in milliseconds, which only compare
+/// across runs on the same machine.
+#[cfg(feature = "metrics")]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PerfReport {
+    /// The time spent flattening the constraints with the challenge `z`.
+    pub flatten_ms: f64,
+    /// The time spent in multiscalar multiplications: the commitments of
+    /// the prover, or the single check of the verifier.  The streaming
+    /// verifier computes the scalars of the generators while multiplying
+    /// them, so that time counts here.
+    pub msm_ms: f64,
+    /// The time spent on the inner-product proof: creating it, for the
+    /// prover, or deriving the scalars computed ahead of the
+    /// multiscalar multiplication from its challenges, for the verifier.
+    pub ipp_ms: f64,
+    /// The number of scalars multiplied by the multiscalar
+    /// multiplications counted in `msm_ms`.
+    pub scalar_count: usize,
+    /// The number of points of the proof.
+    pub point_count: usize,
+}
+
+/// The steps of a proof whose time is reported.
+#[derive(Clone, Copy)]
+pub(super) enum Step {
+    Flatten,
+    Msm,
+    Ipp,
+}
+
+/// Accumulates the [`PerfReport`] of a proof, if enabled.
+#[derive(Clone, Default)]
+pub(super) struct Metrics {
+    #[cfg(feature = "metrics")]
+    report: Option<Rc<Cell<PerfReport>>>,
+}
+
+impl Metrics {
+    /// Returns metrics recording into a new report.
+    #[cfg(feature = "metrics")]
+    pub(super) fn enabled() -> Self {
+        Metrics {
+            report: Some(Rc::default()),
+        }
+    }
+
+    /// Returns the report recorded so far.
+    #[cfg(feature = "metrics")]
+    pub(super) fn report(&self) -> PerfReport {
+        self.report.as_ref().map(|r| r.get()).unwrap_or_default()
+    }
+
+    /// Runs `f`, adding its duration to the time of `step`.
+    pub(super) fn time<T>(&self, step: Step, f: impl FnOnce() -> T) -> T {
+        #[cfg(feature = "metrics")]
+        if let Some(report) = &self.report {
+            let start = Instant::now();
+            let result = f();
+            let ms = start.elapsed().as_secs_f64() * 1e3;
+            let mut r = report.get();
+            match step {
+                Step::Flatten => r.flatten_ms += ms,
+                Step::Msm => r.msm_ms += ms,
+                Step::Ipp => r.ipp_ms += ms,
+            }
+            report.set(r);
+            return result;
+        }
+        let _ = step;
+        f()
+    }
+
+    /// Adds `scalars` multiplied scalars and `points` points of the proof
+    /// to the counts.
+    pub(super) fn count(&self, scalars: usize, points: usize) {
+        #[cfg(feature = "metrics")]
+        if let Some(report) = &self.report {
+            let mut r = report.get();
+            r.scalar_count += scalars;
+            r.point_count += points;
+            report.set(r);
+        }
+        let _ = (scalars, points);
+    }
+}
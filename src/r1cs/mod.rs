@@ -1,18 +1,56 @@
 mod notes {}
 
+#[cfg(feature = "ark-relations")]
+mod arkworks;
+mod artifacts;
+mod blinding;
+mod circom;
+mod circuit;
 mod constraint_system;
+mod estimate;
+mod export;
+mod limits;
 mod linear_combination;
+mod metrics;
+mod oneshot;
+mod prepared;
+mod profiler;
 mod proof;
 mod prover;
+mod serialization;
 mod verifier;
 
+pub mod gadgets;
+pub mod mpc;
+pub mod solvency;
+pub mod statements;
+pub mod test_utils;
+#[cfg(feature = "test-vectors")]
+pub mod test_vectors;
+
+#[cfg(feature = "ark-relations")]
+pub use self::arkworks::ArkCircuit;
+pub use self::artifacts::{MsmTerm, VerificationArtifacts};
+pub use self::blinding::BlindingSource;
+pub use self::circom::CircomCircuit;
+pub use self::circuit::{CircuitDescription, Operation};
 pub use self::constraint_system::{
     ConstraintSystem, RandomizableConstraintSystem, RandomizedConstraintSystem,
 };
+pub use self::estimate::CircuitShape;
+pub use self::limits::VerifierLimits;
 pub use self::linear_combination::{LinearCombination, Variable};
+#[cfg(feature = "metrics")]
+pub use self::metrics::PerfReport;
+pub use self::oneshot::{prove, verify};
+pub use self::prepared::PreparedCircuit;
+pub use self::profiler::{NamespaceProfile, Profiler};
 pub use self::proof::R1CSProof;
-pub use self::prover::Prover;
-pub use self::verifier::batch_verify;
-pub use self::verifier::Verifier;
+#[cfg(feature = "diagnostics")]
+pub use self::prover::UnsatisfiedConstraint;
+pub use self::prover::{Prover, ProverRounds, ProvingStage, RoundCommitments};
+pub use self::serialization::CircuitAssignment;
+pub use self::verifier::{batch_verify, batch_verify_and_identify};
+pub use self::verifier::{VerificationScalars, Verifier};
 
 pub use crate::errors::R1CSError;
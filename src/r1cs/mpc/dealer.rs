@@ -0,0 +1,494 @@
+#![allow(non_snake_case)]
+//! The dealer side of the multiparty R1CS proving protocol.
+//!
+//! The dealer coordinates the parties: it aggregates their commitments,
+//! computes the challenges from the transcript, and assembles the proof
+//! from their [`ProofShare`]s.  It goes through the states
+//! [`DealerAwaitingWitnessCommitments`], [`DealerAwaitingPolyCommitments`]
+//! and [`DealerAwaitingProofShares`].
+
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::{Field, PrimeField};
+use ark_std::{iter, ops::Range, vec, vec::Vec, One, Zero};
+use merlin::Transcript;
+
+use super::circuit_gens;
+use super::messages::*;
+use crate::errors::MPCError;
+use crate::generators::{BulletproofGens, PedersenGens};
+use crate::inner_product_proof::{inner_product, InnerProductProof};
+use crate::r1cs::verifier::phase_factors;
+use crate::r1cs::{PreparedCircuit, R1CSProof};
+use crate::transcript::TranscriptProtocol;
+use crate::util;
+
+/// Used to construct a dealer for the multiparty proving protocol.
+pub struct Dealer {}
+
+impl Dealer {
+    /// Creates a dealer coordinating `parties` parties proving
+    /// `circuit` for the high-level variable `commitments`.
+    ///
+    /// As with [`PreparedCircuit::verify`], the `transcript` must be in
+    /// the same state as the one the verifier starts from.  It may be
+    /// any transcript, which the dealer clones to check the proof.
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new<'a, 'b, G: AffineRepr, T: TranscriptProtocol<G> + Clone>(
+        pc_gens: &'b PedersenGens<G>,
+        bp_gens: &'b BulletproofGens<G>,
+        transcript: &'a mut T,
+        circuit: &'b PreparedCircuit<G::ScalarField>,
+        commitments: &'b [G],
+        parties: usize,
+    ) -> Result<DealerAwaitingWitnessCommitments<'a, 'b, G, T>, MPCError> {
+        // Every party holds a share of all the multipliers, and the first
+        // one adds the public parts.
+        let padded_n = circuit.multipliers_len().next_power_of_two();
+        let shares = (0..parties).map(|j| (0..padded_n, j == 0)).collect();
+        Dealer::start(pc_gens, bp_gens, transcript, circuit, commitments, shares)
+    }
+
+    /// Creates a dealer coordinating the parties proving the sub-circuits
+    /// of the aggregated `circuit`, for the high-level variable
+    /// `commitments` of all the sub-circuits, in order.
+    pub fn new_aggregated<'a, 'b, G: AffineRepr, T: TranscriptProtocol<G> + Clone>(
+        pc_gens: &'b PedersenGens<G>,
+        bp_gens: &'b BulletproofGens<G>,
+        transcript: &'a mut T,
+        circuit: &'b PreparedCircuit<G::ScalarField>,
+        commitments: &'b [G],
+    ) -> Result<DealerAwaitingWitnessCommitments<'a, 'b, G, T>, MPCError> {
+        // Every party holds its own block of multipliers.
+        let shares = (0..circuit.parties())
+            .map(|j| {
+                let (range, _, _) = circuit.block(j).ok_or(MPCError::InvalidAggregation)?;
+                Ok((range, true))
+            })
+            .collect::<Result<_, _>>()?;
+        Dealer::start(pc_gens, bp_gens, transcript, circuit, commitments, shares)
+    }
+
+    fn start<'a, 'b, G: AffineRepr, T: TranscriptProtocol<G> + Clone>(
+        pc_gens: &'b PedersenGens<G>,
+        bp_gens: &'b BulletproofGens<G>,
+        transcript: &'a mut T,
+        circuit: &'b PreparedCircuit<G::ScalarField>,
+        commitments: &'b [G],
+        shares: Vec<(Range<usize>, bool)>,
+    ) -> Result<DealerAwaitingWitnessCommitments<'a, 'b, G, T>, MPCError> {
+        if commitments.len() != circuit.commitments_len() {
+            return Err(MPCError::WrongNumBitCommitments);
+        }
+        circuit_gens(bp_gens, circuit)?;
+
+        // Keep the initial state of the transcript to check the proof.
+        let initial_transcript = transcript.clone();
+
+        <T as TranscriptProtocol<G>>::r1cs_domain_sep(transcript);
+        for V in commitments {
+            transcript.append_point(b"V", V);
+        }
+        transcript.append_u64(b"m", commitments.len() as u64);
+
+        Ok(DealerAwaitingWitnessCommitments {
+            pc_gens,
+            bp_gens,
+            transcript,
+            initial_transcript,
+            circuit,
+            commitments,
+            shares,
+        })
+    }
+}
+
+/// A dealer waiting for the parties to commit to their shares of the
+/// witness.
+pub struct DealerAwaitingWitnessCommitments<'a, 'b, G: AffineRepr, T = Transcript> {
+    pc_gens: &'b PedersenGens<G>,
+    bp_gens: &'b BulletproofGens<G>,
+    transcript: &'a mut T,
+    initial_transcript: T,
+    circuit: &'b PreparedCircuit<G::ScalarField>,
+    commitments: &'b [G],
+    /// Ranges of the padded multipliers of the parties' shares, and
+    /// whether they include the public parts of the polynomials.
+    shares: Vec<(Range<usize>, bool)>,
+}
+
+impl<'a, 'b, G: AffineRepr, T: TranscriptProtocol<G> + Clone>
+    DealerAwaitingWitnessCommitments<'a, 'b, G, T>
+{
+    /// Receives the parties' [`WitnessCommitment`]s, in the order of
+    /// their indices, and returns the [`WitnessChallenge`] to send them.
+    #[allow(clippy::type_complexity)]
+    pub fn receive_witness_commitments(
+        self,
+        witness_commitments: Vec<WitnessCommitment<G>>,
+    ) -> Result<
+        (
+            DealerAwaitingPolyCommitments<'a, 'b, G, T>,
+            WitnessChallenge<G::ScalarField>,
+        ),
+        MPCError,
+    > {
+        if witness_commitments.len() != self.shares.len() {
+            return Err(MPCError::WrongNumWitnessCommitments);
+        }
+
+        let A_I: G = witness_commitments
+            .iter()
+            .map(|wc| wc.A_I)
+            .sum::<G::Group>()
+            .into_affine();
+        let A_O: G = witness_commitments
+            .iter()
+            .map(|wc| wc.A_O)
+            .sum::<G::Group>()
+            .into_affine();
+        let S: G = witness_commitments
+            .iter()
+            .map(|wc| wc.S)
+            .sum::<G::Group>()
+            .into_affine();
+
+        self.transcript.append_point(b"A_I1", &A_I);
+        self.transcript.append_point(b"A_O1", &A_O);
+        self.transcript.append_point(b"S1", &S);
+
+        <T as TranscriptProtocol<G>>::r1cs_1phase_domain_sep(self.transcript);
+
+        // There is no randomization phase, so its commitments are the identity.
+        self.transcript.append_point(b"A_I2", &G::zero());
+        self.transcript.append_point(b"A_O2", &G::zero());
+        self.transcript.append_point(b"S2", &G::zero());
+
+        let y = <T as TranscriptProtocol<G>>::challenge_scalar(self.transcript, b"y");
+        let z = <T as TranscriptProtocol<G>>::challenge_scalar(self.transcript, b"z");
+        let witness_challenge = WitnessChallenge { y, z };
+
+        Ok((
+            DealerAwaitingPolyCommitments {
+                previous: self,
+                witness_commitments,
+                A_I,
+                A_O,
+                S,
+                witness_challenge,
+            },
+            witness_challenge,
+        ))
+    }
+}
+
+/// A dealer waiting for the parties to commit to their shares of
+/// \\(t(x)\\).
+pub struct DealerAwaitingPolyCommitments<'a, 'b, G: AffineRepr, T = Transcript> {
+    previous: DealerAwaitingWitnessCommitments<'a, 'b, G, T>,
+    witness_commitments: Vec<WitnessCommitment<G>>,
+    A_I: G,
+    A_O: G,
+    S: G,
+    witness_challenge: WitnessChallenge<G::ScalarField>,
+}
+
+impl<'a, 'b, G: AffineRepr, T: TranscriptProtocol<G> + Clone>
+    DealerAwaitingPolyCommitments<'a, 'b, G, T>
+{
+    /// Receives the parties' [`PolyCommitment`]s, in the order of their
+    /// indices, and returns the [`PolyChallenge`] to send them.
+    #[allow(clippy::type_complexity)]
+    pub fn receive_poly_commitments(
+        self,
+        poly_commitments: Vec<PolyCommitment<G>>,
+    ) -> Result<
+        (
+            DealerAwaitingProofShares<'a, 'b, G, T>,
+            PolyChallenge<G::ScalarField>,
+        ),
+        MPCError,
+    > {
+        if poly_commitments.len() != self.previous.shares.len() {
+            return Err(MPCError::WrongNumPolyCommitments);
+        }
+
+        let sum = |f: fn(&PolyCommitment<G>) -> G| -> G {
+            poly_commitments
+                .iter()
+                .map(f)
+                .sum::<G::Group>()
+                .into_affine()
+        };
+        let T_1 = sum(|pc| pc.T_1);
+        let T_3 = sum(|pc| pc.T_3);
+        let T_4 = sum(|pc| pc.T_4);
+        let T_5 = sum(|pc| pc.T_5);
+        let T_6 = sum(|pc| pc.T_6);
+
+        let transcript = &mut *self.previous.transcript;
+        transcript.append_point(b"T_1", &T_1);
+        transcript.append_point(b"T_3", &T_3);
+        transcript.append_point(b"T_4", &T_4);
+        transcript.append_point(b"T_5", &T_5);
+        transcript.append_point(b"T_6", &T_6);
+
+        // Challenge combining the (empty) randomization phase.
+        let u = <T as TranscriptProtocol<G>>::challenge_scalar(transcript, b"u");
+        let x = <T as TranscriptProtocol<G>>::challenge_scalar(transcript, b"x");
+        let poly_challenge = PolyChallenge { x };
+
+        Ok((
+            DealerAwaitingProofShares {
+                previous: self,
+                T_1,
+                T_3,
+                T_4,
+                T_5,
+                T_6,
+                u,
+                poly_challenge,
+            },
+            poly_challenge,
+        ))
+    }
+}
+
+/// A dealer waiting for the parties' [`ProofShare`]s to assemble the
+/// proof.
+pub struct DealerAwaitingProofShares<'a, 'b, G: AffineRepr, T = Transcript> {
+    previous: DealerAwaitingPolyCommitments<'a, 'b, G, T>,
+    T_1: G,
+    T_3: G,
+    T_4: G,
+    T_5: G,
+    T_6: G,
+    u: G::ScalarField,
+    poly_challenge: PolyChallenge<G::ScalarField>,
+}
+
+impl<'a, 'b, G: AffineRepr, T: TranscriptProtocol<G> + Clone>
+    DealerAwaitingProofShares<'a, 'b, G, T>
+{
+    /// Assembles the proof from the parties' `proof_shares`, in the
+    /// order of their indices, and checks it.
+    ///
+    /// If the proof does not verify, the dealer audits each share
+    /// against the party's commitments, and reports the parties whose
+    /// shares are inconsistent in [`MPCError::MalformedProofShares`].
+    pub fn receive_shares(
+        mut self,
+        proof_shares: &[ProofShare<G::ScalarField>],
+    ) -> Result<R1CSProof<G>, MPCError> {
+        let proof = self.assemble_shares(proof_shares)?;
+
+        let witness = &self.previous.previous;
+        let mut transcript = witness.initial_transcript.clone();
+        if witness
+            .circuit
+            .verify(
+                &mut transcript,
+                witness.commitments,
+                &proof,
+                witness.pc_gens,
+                witness.bp_gens,
+            )
+            .is_ok()
+        {
+            return Ok(proof);
+        }
+
+        // The proof is invalid, so find the parties at fault.
+        let bad_shares = proof_shares
+            .iter()
+            .enumerate()
+            .filter(|(j, share)| !self.audit_share(*j, share))
+            .map(|(j, _)| j)
+            .collect::<Vec<_>>();
+        Err(MPCError::MalformedProofShares { bad_shares })
+    }
+
+    /// Assembles the proof from the parties' `proof_shares` without
+    /// checking it.
+    ///
+    /// This is cheaper than [`DealerAwaitingProofShares::receive_shares`],
+    /// but the resulting proof is invalid if any party misbehaved: it
+    /// should only be used when the parties are trusted, or the proof is
+    /// checked by other means.
+    pub fn receive_trusted_shares(
+        mut self,
+        proof_shares: &[ProofShare<G::ScalarField>],
+    ) -> Result<R1CSProof<G>, MPCError> {
+        self.assemble_shares(proof_shares)
+    }
+
+    fn assemble_shares(
+        &mut self,
+        proof_shares: &[ProofShare<G::ScalarField>],
+    ) -> Result<R1CSProof<G>, MPCError> {
+        let poly = &mut self.previous;
+        let witness = &mut poly.previous;
+        if proof_shares.len() != witness.shares.len() {
+            return Err(MPCError::WrongNumProofShares);
+        }
+
+        let n = witness.circuit.multipliers_len();
+        let padded_n = n.next_power_of_two();
+        let pad = padded_n - n;
+
+        let bad_shares = proof_shares
+            .iter()
+            .zip(witness.shares.iter())
+            .enumerate()
+            .filter(|(_, (share, (range, _)))| {
+                share.l_vec.len() != range.len() || share.r_vec.len() != range.len()
+            })
+            .map(|(j, _)| j)
+            .collect::<Vec<_>>();
+        if !bad_shares.is_empty() {
+            return Err(MPCError::MalformedProofShares { bad_shares });
+        }
+
+        let mut l_vec = vec![G::ScalarField::zero(); padded_n];
+        let mut r_vec = vec![G::ScalarField::zero(); padded_n];
+        let mut t_x_blinding = G::ScalarField::zero();
+        let mut e_blinding = G::ScalarField::zero();
+        for (share, (range, _)) in proof_shares.iter().zip(witness.shares.iter()) {
+            for (l, l_j) in l_vec[range.clone()].iter_mut().zip(share.l_vec.iter()) {
+                *l += l_j;
+            }
+            for (r, r_j) in r_vec[range.clone()].iter_mut().zip(share.r_vec.iter()) {
+                *r += r_j;
+            }
+            t_x_blinding += share.t_x_blinding;
+            e_blinding += share.e_blinding;
+        }
+        let t_x = inner_product(&l_vec, &r_vec);
+
+        let transcript = &mut *witness.transcript;
+        <T as TranscriptProtocol<G>>::append_scalar(transcript, b"t_x", &t_x);
+        <T as TranscriptProtocol<G>>::append_scalar(transcript, b"t_x_blinding", &t_x_blinding);
+        <T as TranscriptProtocol<G>>::append_scalar(transcript, b"e_blinding", &e_blinding);
+
+        // Get a challenge value to combine statements for the IPP
+        let w: G::ScalarField = <T as TranscriptProtocol<G>>::challenge_scalar(transcript, b"w");
+        let Q = witness.pc_gens.B.mul_bigint(w.into_bigint());
+
+        let y_inv = poly.witness_challenge.y.inverse().unwrap();
+        let G_factors = phase_factors(&[n, n], &[self.u], (0, G::ScalarField::one()), pad);
+        let H_factors = util::exp_iter(y_inv)
+            .zip(G_factors.iter())
+            .map(|(y, u_or_1)| y * u_or_1)
+            .collect::<Vec<_>>();
+
+        let (G_vec, H_vec) = circuit_gens(witness.bp_gens, witness.circuit)?;
+        let ipp_proof = InnerProductProof::create(
+            transcript,
+            &Q.into_affine(),
+            &G_factors,
+            &H_factors,
+            G_vec,
+            H_vec,
+            l_vec,
+            r_vec,
+            witness.bp_gens.msm_backend(),
+            &mut |_, _| {},
+        );
+
+        Ok(R1CSProof {
+            A_I1: poly.A_I,
+            A_O1: poly.A_O,
+            S1: poly.S,
+            S_C: None,
+            A_I2: G::zero(),
+            A_O2: G::zero(),
+            S2: G::zero(),
+            extra_phases: Vec::new(),
+            T_1: self.T_1,
+            T_3: self.T_3,
+            T_4: self.T_4,
+            T_5: self.T_5,
+            T_6: self.T_6,
+            t_x,
+            t_x_blinding,
+            e_blinding,
+            ipp_proof,
+        })
+    }
+
+    /// Checks that the share of the party `index` is consistent with its
+    /// witness commitment, i.e. that
+    /// \\[
+    /// \langle l_j, \mathbf{G} \rangle + \langle y^{-n} \circ r_j, \mathbf{H} \rangle
+    /// = x A_{I,j} + x^2 A_{O,j} + x^3 S_j - e_j \tilde{B} + P_j,
+    /// \\]
+    /// over the party's range of generators, where \\(P_j\\) commits to
+    /// the public parts of the polynomials if the party adds them.
+    ///
+    /// The shares of \\(t(x)\\) are not audited, since they are not
+    /// individually related to the commitments.
+    fn audit_share(&self, index: usize, share: &ProofShare<G::ScalarField>) -> bool {
+        let poly = &self.previous;
+        let witness = &poly.previous;
+        let commitment = &poly.witness_commitments[index];
+
+        let (range, public) = &witness.shares[index];
+        let x = self.poly_challenge.x;
+        let y_inv = poly.witness_challenge.y.inverse().unwrap();
+        let (wL, wR, wO, _, _) = witness
+            .circuit
+            .flattened_constraints(&poly.witness_challenge.z);
+        // The weights of the padding multipliers are zero.
+        let w = |w: &[G::ScalarField], i: usize| w.get(i).copied().unwrap_or_default();
+
+        let public = if *public {
+            G::ScalarField::one()
+        } else {
+            G::ScalarField::zero()
+        };
+
+        let exp_y_inv = || util::exp_iter(y_inv).skip(range.start);
+        let g_scalars = share
+            .l_vec
+            .iter()
+            .zip(exp_y_inv())
+            .zip(range.clone())
+            .map(|((l_i, y_inv_i), i)| *l_i - public * x * y_inv_i * w(&wR, i));
+        let h_scalars =
+            share
+                .r_vec
+                .iter()
+                .zip(exp_y_inv())
+                .zip(range.clone())
+                .map(|((r_i, y_inv_i), i)| {
+                    let public_i = y_inv_i * (w(&wO, i) + x * w(&wL, i)) - G::ScalarField::one();
+                    y_inv_i * r_i - public * public_i
+                });
+
+        let (G_vec, H_vec) = match circuit_gens(witness.bp_gens, witness.circuit) {
+            Ok(gens) => gens,
+            Err(_) => return false,
+        };
+        let scalars = g_scalars
+            .chain(h_scalars)
+            .chain(iter::once(share.e_blinding))
+            .chain(iter::once(-x))
+            .chain(iter::once(-x * x))
+            .chain(iter::once(-x * x * x))
+            .collect::<Vec<_>>();
+        let points = G_vec[range.clone()]
+            .iter()
+            .chain(H_vec[range.clone()].iter())
+            .chain(iter::once(&witness.pc_gens.B_blinding))
+            .chain(iter::once(&commitment.A_I))
+            .chain(iter::once(&commitment.A_O))
+            .chain(iter::once(&commitment.S))
+            .copied()
+            .collect::<Vec<_>>();
+
+        witness
+            .bp_gens
+            .msm_backend()
+            .msm(&points, &scalars)
+            .is_zero()
+    }
+}
@@ -0,0 +1,86 @@
+#![allow(non_snake_case)]
+//! The messages exchanged by the dealer and the parties of the
+//! multiparty R1CS proving protocol.
+
+use ark_ec::AffineRepr;
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::vec::Vec;
+use subtle::{Choice, ConstantTimeEq};
+
+use crate::util::ct_eq_serialized;
+
+/// A party's commitments to its shares of the witness and of the
+/// blinding vectors.
+#[derive(Copy, Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct WitnessCommitment<G: AffineRepr> {
+    /// Commitment to the shares of the inputs of the multipliers.
+    pub(super) A_I: G,
+    /// Commitment to the shares of the outputs of the multipliers.
+    pub(super) A_O: G,
+    /// Commitment to the shares of the blinding vectors.
+    pub(super) S: G,
+}
+
+/// The challenges combining the constraints and the multipliers, sent by
+/// the dealer after receiving the witness commitments.
+#[derive(Copy, Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct WitnessChallenge<F: PrimeField> {
+    pub(super) y: F,
+    pub(super) z: F,
+}
+
+/// A party's commitments to its shares of the coefficients of
+/// \\(t(x)\\), except \\(t_2\\).
+#[derive(Copy, Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct PolyCommitment<G: AffineRepr> {
+    pub(super) T_1: G,
+    pub(super) T_3: G,
+    pub(super) T_4: G,
+    pub(super) T_5: G,
+    pub(super) T_6: G,
+}
+
+/// The challenge at which the polynomials are evaluated, sent by the
+/// dealer after receiving the polynomial commitments.
+#[derive(Copy, Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct PolyChallenge<F: PrimeField> {
+    pub(super) x: F,
+}
+
+/// A party's shares of the evaluations \\(l(x)\\) and \\(r(x)\\), and of
+/// the blinding factors of the proof.
+///
+/// The evaluations are masked by the blinding vectors, so the dealer
+/// learns nothing about the witness from the shares.
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct ProofShare<F: PrimeField> {
+    pub(super) l_vec: Vec<F>,
+    pub(super) r_vec: Vec<F>,
+    pub(super) t_x_blinding: F,
+    pub(super) e_blinding: F,
+}
+
+/// Implements `PartialEq` and [`ConstantTimeEq`] over the encodings of
+/// commitments, so that they are compared in constant time.
+macro_rules! impl_ct_eq_for_commitments {
+    ($($t:ident),*) => {
+        $(
+            impl<G: AffineRepr> ConstantTimeEq for $t<G> {
+                fn ct_eq(&self, other: &Self) -> Choice {
+                    ct_eq_serialized(self, other)
+                }
+            }
+
+            impl<G: AffineRepr> PartialEq for $t<G> {
+                fn eq(&self, other: &Self) -> bool {
+                    self.ct_eq(other).into()
+                }
+            }
+
+            impl<G: AffineRepr> Eq for $t<G> {}
+        )*
+    };
+}
+
+impl_ct_eq_for_commitments!(WitnessCommitment, PolyCommitment);
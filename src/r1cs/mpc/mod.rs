@@ -0,0 +1,83 @@
+//! The `mpc` module contains the API for proving a circuit with a
+//! witness that is secret-shared among several parties.
+//!
+//! Each party holds an additive [`WitnessShare`] of the witness of a
+//! [`PreparedCircuit`](::r1cs::PreparedCircuit), so that no single party
+//! learns the full witness, e.g. for threshold custody of the values
+//! of a confidential transfer.  A dealer, which learns nothing about the
+//! witness, aggregates the parties' commitments and computes the
+//! challenges, mirroring the transcript of the verifier, and assembles
+//! the final [`R1CSProof`](::r1cs::R1CSProof) from the parties' shares.
+//!
+//! The protocol proceeds as follows, where each step consumes the state
+//! of the previous one:
+//!
+//! 1. Each party commits to its witness share with [`Party::new`], and
+//!    the dealer aggregates the [`WitnessCommitment`]s.
+//! 2. The parties apply the [`WitnessChallenge`], which gives them their
+//!    shares of the polynomials \\(l(x)\\) and \\(r(x)\\).  The
+//!    coefficients of \\(t(x) = \langle l(x), r(x) \rangle\\) are not
+//!    linear in the shares, so the parties compute shares of them with
+//!    the multiplication of their own MPC framework, e.g. with Beaver
+//!    triples, and commit to them.
+//! 3. The parties apply the [`PolyChallenge`] and send their
+//!    [`ProofShare`]s, from which the dealer computes the inner-product
+//!    proof.
+//!
+//! The dealer checks the assembled proof, and identifies the parties
+//! whose shares are inconsistent with their commitments.  The protocol
+//! is secure against a semi-honest dealer: the parties only check that
+//! the challenges are non-zero.
+//!
+//! Only circuits without randomized constraints can be prepared, so the
+//! protocol does not support randomized constraints, nor vector
+//! commitments.
+//!
+//! The same protocol aggregates the proofs of several parties, each
+//! holding the whole witness of its own circuit, into a single proof of
+//! the circuit combined with [`PreparedCircuit::aggregate`](::r1cs::PreparedCircuit::aggregate):
+//! the parties are constructed with [`Party::new_aggregated`], the dealer
+//! with [`Dealer::new_aggregated`], and since the parties' polynomials
+//! are disjoint, each of them computes its share of \(t(x)\) on its own
+//! with [`PartyAwaitingPolyShare::commit_aggregated_poly`].
+
+mod dealer;
+mod messages;
+mod party;
+
+pub use self::dealer::{
+    Dealer, DealerAwaitingPolyCommitments, DealerAwaitingProofShares,
+    DealerAwaitingWitnessCommitments,
+};
+pub use self::messages::{
+    PolyChallenge, PolyCommitment, ProofShare, WitnessChallenge, WitnessCommitment,
+};
+pub use self::party::{
+    Party, PartyAwaitingPolyChallenge, PartyAwaitingPolyShare, PartyAwaitingWitnessChallenge,
+    WitnessShare,
+};
+
+pub use crate::errors::MPCError;
+
+use ark_ec::AffineRepr;
+use ark_std::vec::Vec;
+
+use crate::generators::BulletproofGens;
+use crate::r1cs::PreparedCircuit;
+
+/// Returns the \\(\mathbf G\\) and \\(\mathbf H\\) generators of the
+/// padded multipliers of `circuit`.
+fn circuit_gens<G: AffineRepr>(
+    bp_gens: &BulletproofGens<G>,
+    circuit: &PreparedCircuit<G::ScalarField>,
+) -> Result<(Vec<G>, Vec<G>), MPCError> {
+    let parties = circuit.parties();
+    let n = circuit.multipliers_len().next_power_of_two() / parties;
+    if bp_gens.gens_capacity < n || bp_gens.party_capacity < parties {
+        return Err(MPCError::InvalidGeneratorsLength);
+    }
+    Ok((
+        bp_gens.G(n, parties).copied().collect(),
+        bp_gens.H(n, parties).copied().collect(),
+    ))
+}
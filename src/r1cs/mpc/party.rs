@@ -0,0 +1,458 @@
+#![allow(non_snake_case)]
+//! The party side of the multiparty R1CS proving protocol.
+//!
+//! A party holds an additive share of the witness, or the witness of its
+//! own sub-circuit of an aggregated circuit, and goes through the states
+//! [`PartyAwaitingWitnessChallenge`], [`PartyAwaitingPolyShare`] and
+//! [`PartyAwaitingPolyChallenge`] before producing its [`ProofShare`].
+
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::{Field, PrimeField};
+use ark_std::{iter, ops::Range, vec::Vec, One, UniformRand, Zero};
+use rand_core::{CryptoRng, RngCore};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use super::circuit_gens;
+use super::messages::*;
+use crate::errors::MPCError;
+use crate::generators::{BulletproofGens, PedersenGens};
+use crate::msm::secret_msm;
+use crate::r1cs::PreparedCircuit;
+use crate::util;
+
+/// A party's additive share of the witness of a circuit.
+///
+/// The shares of all the parties sum to the witness of the circuit: the
+/// assignments of the multipliers, and the blinding factors of the
+/// commitments to the high-level variables.  Since the constraints are
+/// linear, the shares do not need to satisfy them individually.
+///
+/// The witness of a sub-circuit of an aggregated circuit is the share of
+/// its party, with the shares of the other parties being zero.
+pub struct WitnessShare<F: PrimeField> {
+    /// Shares of the left inputs of the multipliers.
+    pub a_L: Vec<F>,
+    /// Shares of the right inputs of the multipliers.
+    pub a_R: Vec<F>,
+    /// Shares of the outputs of the multipliers.
+    pub a_O: Vec<F>,
+    /// Shares of the blinding factors of the high-level variables.
+    pub v_blinding: Vec<F>,
+}
+
+/// Overwrite the shares with null bytes when they go out of scope.
+impl<F: PrimeField> Drop for WitnessShare<F> {
+    fn drop(&mut self) {
+        self.a_L.zeroize();
+        self.a_R.zeroize();
+        self.a_O.zeroize();
+        self.v_blinding.zeroize();
+    }
+}
+
+impl<F: PrimeField> ZeroizeOnDrop for WitnessShare<F> {}
+
+/// Used to construct a party for the multiparty proving protocol.
+pub struct Party {}
+
+impl Party {
+    /// Constructs the party with the given `index` and commits to its
+    /// `share` of the witness of `circuit`.
+    ///
+    /// The party with index 0 also accounts for the public parts of the
+    /// polynomials, so there must be exactly one of them.
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new<'a, G: AffineRepr, R: CryptoRng + RngCore>(
+        index: usize,
+        pc_gens: &'a PedersenGens<G>,
+        bp_gens: &'a BulletproofGens<G>,
+        circuit: &'a PreparedCircuit<G::ScalarField>,
+        share: WitnessShare<G::ScalarField>,
+        rng: &mut R,
+    ) -> Result<(PartyAwaitingWitnessChallenge<'a, G>, WitnessCommitment<G>), MPCError> {
+        let n = circuit.multipliers_len();
+        let m = circuit.commitments_len();
+        Party::commit(
+            pc_gens,
+            bp_gens,
+            circuit,
+            (0..n.next_power_of_two(), n),
+            0..m,
+            index == 0,
+            share,
+            rng,
+        )
+    }
+
+    /// Constructs the party proving the `index`-th sub-circuit of the
+    /// aggregated `circuit`, and commits to its `witness`.
+    ///
+    /// The `witness` holds the assignments of the multipliers and the
+    /// blinding factors of the high-level variables of the sub-circuit
+    /// only.
+    pub fn new_aggregated<'a, G: AffineRepr, R: CryptoRng + RngCore>(
+        index: usize,
+        pc_gens: &'a PedersenGens<G>,
+        bp_gens: &'a BulletproofGens<G>,
+        circuit: &'a PreparedCircuit<G::ScalarField>,
+        witness: WitnessShare<G::ScalarField>,
+        rng: &mut R,
+    ) -> Result<(PartyAwaitingWitnessChallenge<'a, G>, WitnessCommitment<G>), MPCError> {
+        let (range, v_range, n) = circuit.block(index).ok_or(MPCError::InvalidAggregation)?;
+        Party::commit(
+            pc_gens,
+            bp_gens,
+            circuit,
+            (range, n),
+            v_range,
+            true,
+            witness,
+            rng,
+        )
+    }
+
+    /// Commits to the `share` of the `n` first multipliers of `range`,
+    /// and of the high-level variables of `v_range`.
+    #[allow(clippy::too_many_arguments)]
+    fn commit<'a, G: AffineRepr, R: CryptoRng + RngCore>(
+        pc_gens: &'a PedersenGens<G>,
+        bp_gens: &'a BulletproofGens<G>,
+        circuit: &'a PreparedCircuit<G::ScalarField>,
+        (range, n): (Range<usize>, usize),
+        v_range: Range<usize>,
+        public: bool,
+        mut share: WitnessShare<G::ScalarField>,
+        rng: &mut R,
+    ) -> Result<(PartyAwaitingWitnessChallenge<'a, G>, WitnessCommitment<G>), MPCError> {
+        if share.a_L.len() != n
+            || share.a_R.len() != n
+            || share.a_O.len() != n
+            || share.v_blinding.len() != v_range.len()
+        {
+            return Err(MPCError::WrongWitnessLength);
+        }
+        let (G_vec, H_vec) = circuit_gens(bp_gens, circuit)?;
+        let (G_vec, H_vec) = (&G_vec[range.clone()], &H_vec[range.clone()]);
+
+        // The padding multipliers are zero.
+        share.a_L.resize(range.len(), G::ScalarField::zero());
+        share.a_R.resize(range.len(), G::ScalarField::zero());
+        share.a_O.resize(range.len(), G::ScalarField::zero());
+
+        // The blinding vectors are zero on the padding multipliers of the
+        // whole circuit, which belong to its last phase.
+        let mut blinding_vec = || -> Vec<G::ScalarField> {
+            range
+                .clone()
+                .map(|i| {
+                    if i < circuit.multipliers_len() {
+                        G::ScalarField::rand(rng)
+                    } else {
+                        G::ScalarField::zero()
+                    }
+                })
+                .collect()
+        };
+        let s_L = blinding_vec();
+        let s_R = blinding_vec();
+
+        let i_blinding = G::ScalarField::rand(rng);
+        let o_blinding = G::ScalarField::rand(rng);
+        let s_blinding = G::ScalarField::rand(rng);
+
+        // A_I = <a_L, G> + <a_R, H> + i_blinding * B_blinding
+        let A_I = secret_msm(
+            bp_gens.msm_backend(),
+            &iter::once(&pc_gens.B_blinding)
+                .chain(G_vec.iter())
+                .chain(H_vec.iter())
+                .copied()
+                .collect::<Vec<G>>(),
+            &iter::once(&i_blinding)
+                .chain(share.a_L.iter())
+                .chain(share.a_R.iter())
+                .copied()
+                .collect::<Vec<G::ScalarField>>(),
+        )
+        .into_affine();
+
+        // A_O = <a_O, G> + o_blinding * B_blinding
+        let A_O = secret_msm(
+            bp_gens.msm_backend(),
+            &iter::once(&pc_gens.B_blinding)
+                .chain(G_vec.iter())
+                .copied()
+                .collect::<Vec<G>>(),
+            &iter::once(&o_blinding)
+                .chain(share.a_O.iter())
+                .copied()
+                .collect::<Vec<G::ScalarField>>(),
+        )
+        .into_affine();
+
+        // S = <s_L, G> + <s_R, H> + s_blinding * B_blinding
+        let S = secret_msm(
+            bp_gens.msm_backend(),
+            &iter::once(&pc_gens.B_blinding)
+                .chain(G_vec.iter())
+                .chain(H_vec.iter())
+                .copied()
+                .collect::<Vec<G>>(),
+            &iter::once(&s_blinding)
+                .chain(s_L.iter())
+                .chain(s_R.iter())
+                .copied()
+                .collect::<Vec<G::ScalarField>>(),
+        )
+        .into_affine();
+
+        let next_state = PartyAwaitingWitnessChallenge {
+            pc_gens,
+            circuit,
+            range,
+            v_range,
+            public,
+            share,
+            i_blinding,
+            o_blinding,
+            s_blinding,
+            s_L,
+            s_R,
+        };
+        Ok((next_state, WitnessCommitment { A_I, A_O, S }))
+    }
+}
+
+/// A party which has committed to its share of the witness and is
+/// waiting for the [`WitnessChallenge`] from the dealer.
+pub struct PartyAwaitingWitnessChallenge<'a, G: AffineRepr> {
+    pc_gens: &'a PedersenGens<G>,
+    circuit: &'a PreparedCircuit<G::ScalarField>,
+    /// Range of the padded multipliers of the share.
+    range: Range<usize>,
+    /// Range of the high-level variables of the share.
+    v_range: Range<usize>,
+    /// Whether the party adds the public parts of the polynomials on its range.
+    public: bool,
+    share: WitnessShare<G::ScalarField>,
+    i_blinding: G::ScalarField,
+    o_blinding: G::ScalarField,
+    s_blinding: G::ScalarField,
+    s_L: Vec<G::ScalarField>,
+    s_R: Vec<G::ScalarField>,
+}
+
+impl<'a, G: AffineRepr> PartyAwaitingWitnessChallenge<'a, G> {
+    /// Receives the challenges `y` and `z` and computes the party's share
+    /// of the polynomials \\(l(x)\\) and \\(r(x)\\).
+    pub fn apply_challenge(
+        self,
+        wc: &WitnessChallenge<G::ScalarField>,
+    ) -> Result<PartyAwaitingPolyShare<'a, G>, MPCError> {
+        // A zero challenge would annihilate the constraints.
+        if wc.y.is_zero() || wc.z.is_zero() {
+            return Err(MPCError::MaliciousDealer);
+        }
+
+        let len = self.range.len();
+        let (wL, wR, wO, wV, _) = self.circuit.flattened_constraints(&wc.z);
+        // The weights of the padding multipliers are zero.
+        let w = |w: &[G::ScalarField], i: usize| w.get(i).copied().unwrap_or_default();
+
+        let public = if self.public {
+            G::ScalarField::one()
+        } else {
+            G::ScalarField::zero()
+        };
+
+        let mut l_poly = util::VecPoly3::<G::ScalarField>::zero(len);
+        let mut r_poly = util::VecPoly3::<G::ScalarField>::zero(len);
+
+        let mut exp_y = wc.y.pow([self.range.start as u64]); // y^n starting at the range
+        let y_inv = wc.y.inverse().unwrap();
+        let mut exp_y_inv = y_inv.pow([self.range.start as u64]);
+
+        for (i, p) in self.range.clone().enumerate() {
+            // l_poly.0 = 0
+            // l_poly.1 = a_L + y^-n * (z * z^Q * W_R)
+            l_poly.1[i] = self.share.a_L[i] + public * exp_y_inv * w(&wR, p);
+            // l_poly.2 = a_O
+            l_poly.2[i] = self.share.a_O[i];
+            // l_poly.3 = s_L
+            l_poly.3[i] = self.s_L[i];
+            // r_poly.0 = (z * z^Q * W_O) - y^n
+            r_poly.0[i] = public * (w(&wO, p) - exp_y);
+            // r_poly.1 = y^n * a_R + (z * z^Q * W_L)
+            r_poly.1[i] = exp_y * self.share.a_R[i] + public * w(&wL, p);
+            // r_poly.2 = 0
+            // r_poly.3 = y^n * s_R
+            r_poly.3[i] = exp_y * self.s_R[i];
+
+            exp_y *= wc.y; // y^i -> y^(i+1)
+            exp_y_inv *= y_inv;
+        }
+
+        let wV = wV[self.v_range.clone()].to_vec();
+        Ok(PartyAwaitingPolyShare {
+            previous: self,
+            wV,
+            l_poly,
+            r_poly,
+        })
+    }
+}
+
+/// Overwrite the blinding factors with null bytes when they go out of scope.
+impl<'a, G: AffineRepr> Drop for PartyAwaitingWitnessChallenge<'a, G> {
+    fn drop(&mut self) {
+        self.i_blinding.zeroize();
+        self.o_blinding.zeroize();
+        self.s_blinding.zeroize();
+        self.s_L.zeroize();
+        self.s_R.zeroize();
+    }
+}
+
+impl<'a, G: AffineRepr> ZeroizeOnDrop for PartyAwaitingWitnessChallenge<'a, G> {}
+
+/// A party which has computed its share of the polynomials \\(l(x)\\)
+/// and \\(r(x)\\), and is waiting for its share of their inner product
+/// \\(t(x)\\).
+///
+/// The coefficients of \\(t(x)\\) are sums of inner products of the
+/// coefficients of \\(l(x)\\) and \\(r(x)\\), and so are not linear in the
+/// shares: the parties compute their shares of them with the
+/// multiplication of their MPC framework, e.g. with Beaver triples, from
+/// the shares returned by [`PartyAwaitingPolyShare::l_poly`] and
+/// [`PartyAwaitingPolyShare::r_poly`].
+///
+/// The parties proving the sub-circuits of an aggregated circuit hold
+/// disjoint parts of the polynomials, so they compute their shares on
+/// their own with [`PartyAwaitingPolyShare::commit_aggregated_poly`].
+pub struct PartyAwaitingPolyShare<'a, G: AffineRepr> {
+    previous: PartyAwaitingWitnessChallenge<'a, G>,
+    wV: Vec<G::ScalarField>,
+    l_poly: util::VecPoly3<G::ScalarField>,
+    r_poly: util::VecPoly3<G::ScalarField>,
+}
+
+impl<'a, G: AffineRepr> PartyAwaitingPolyShare<'a, G> {
+    /// Returns the party's shares of the coefficients of \\(l(x)\\), from
+    /// the constant one, over the padded multipliers.
+    pub fn l_poly(&self) -> [&[G::ScalarField]; 4] {
+        [
+            &self.l_poly.0,
+            &self.l_poly.1,
+            &self.l_poly.2,
+            &self.l_poly.3,
+        ]
+    }
+
+    /// Returns the party's shares of the coefficients of \\(r(x)\\), from
+    /// the constant one, over the padded multipliers.
+    pub fn r_poly(&self) -> [&[G::ScalarField]; 4] {
+        [
+            &self.r_poly.0,
+            &self.r_poly.1,
+            &self.r_poly.2,
+            &self.r_poly.3,
+        ]
+    }
+
+    /// Commits to the party's share `t` of the coefficients
+    /// \\(t_1, \dots, t_6\\) of \\(t(x)\\).
+    ///
+    /// The share of \\(t_2\\) is not committed, since the verifier
+    /// computes the commitment to \\(t_2\\) from the commitments to the
+    /// high-level variables.
+    pub fn commit_poly<R: CryptoRng + RngCore>(
+        self,
+        t: [G::ScalarField; 6],
+        rng: &mut R,
+    ) -> (PartyAwaitingPolyChallenge<'a, G>, PolyCommitment<G>) {
+        // t_2_blinding = <z*z^Q, W_V * v_blinding>
+        let t_2_blinding: G::ScalarField = self
+            .wV
+            .iter()
+            .zip(self.previous.share.v_blinding.iter())
+            .map(|(c, v_blinding)| *v_blinding * c)
+            .sum();
+
+        let t_blinding_poly = util::Poly6::<G::ScalarField> {
+            t1: G::ScalarField::rand(rng),
+            t2: t_2_blinding,
+            t3: G::ScalarField::rand(rng),
+            t4: G::ScalarField::rand(rng),
+            t5: G::ScalarField::rand(rng),
+            t6: G::ScalarField::rand(rng),
+        };
+
+        let pc_gens = self.previous.pc_gens;
+        let poly_commitment = PolyCommitment {
+            T_1: pc_gens.commit(t[0], t_blinding_poly.t1),
+            T_3: pc_gens.commit(t[2], t_blinding_poly.t3),
+            T_4: pc_gens.commit(t[3], t_blinding_poly.t4),
+            T_5: pc_gens.commit(t[4], t_blinding_poly.t5),
+            T_6: pc_gens.commit(t[5], t_blinding_poly.t6),
+        };
+
+        let next_state = PartyAwaitingPolyChallenge {
+            previous: self,
+            t_blinding_poly,
+        };
+        (next_state, poly_commitment)
+    }
+
+    /// Commits to the inner product of the party's own polynomials, which
+    /// is its share of \\(t(x)\\) when it proves a sub-circuit of an
+    /// aggregated circuit, see [`Party::new_aggregated`].
+    pub fn commit_aggregated_poly<R: CryptoRng + RngCore>(
+        self,
+        rng: &mut R,
+    ) -> (PartyAwaitingPolyChallenge<'a, G>, PolyCommitment<G>) {
+        let t_poly = util::VecPoly3::special_inner_product(&self.l_poly, &self.r_poly);
+        let t = [
+            t_poly.t1, t_poly.t2, t_poly.t3, t_poly.t4, t_poly.t5, t_poly.t6,
+        ];
+        self.commit_poly(t, rng)
+    }
+}
+
+/// A party which has committed to its share of \\(t(x)\\) and is waiting
+/// for the [`PolyChallenge`] from the dealer.
+pub struct PartyAwaitingPolyChallenge<'a, G: AffineRepr> {
+    previous: PartyAwaitingPolyShare<'a, G>,
+    t_blinding_poly: util::Poly6<G::ScalarField>,
+}
+
+impl<'a, G: AffineRepr> PartyAwaitingPolyChallenge<'a, G> {
+    /// Receives the challenge `x` and computes the party's
+    /// [`ProofShare`].
+    pub fn apply_challenge(
+        self,
+        pc: &PolyChallenge<G::ScalarField>,
+    ) -> Result<ProofShare<G::ScalarField>, MPCError> {
+        // A zero challenge would annihilate the blinding vectors.
+        if pc.x.is_zero() {
+            return Err(MPCError::MaliciousDealer);
+        }
+
+        let x = pc.x;
+        let poly = &self.previous;
+        let party = &poly.previous;
+
+        let t_x_blinding = self.t_blinding_poly.eval(x);
+        let e_blinding = x * (party.i_blinding + x * (party.o_blinding + x * party.s_blinding));
+
+        Ok(ProofShare {
+            l_vec: poly.l_poly.eval(x),
+            r_vec: poly.r_poly.eval(x),
+            t_x_blinding,
+            e_blinding,
+        })
+    }
+}
+
+/// The blinding factors of \\(t(x)\\) are overwritten with null bytes by
+/// their polynomial when it goes out of scope.
+impl<'a, G: AffineRepr> ZeroizeOnDrop for PartyAwaitingPolyChallenge<'a, G> {}
@@ -0,0 +1,105 @@
+//! One-shot proving and verifying of circuits written as closures.
+//!
+//! The prover and the verifier of a statement usually build its
+//! constraints in two separate code paths, which can drift apart.  The
+//! [`prove`] and [`verify`] functions instead take the same closure,
+//! which builds the constraints over the committed values as
+//! [`AllocatedScalar`]s: with their assignments on the prover and
+//! without on the verifier.  Both also bind the
+//! [circuit digest](Prover::circuit_digest) to the transcript, so a
+//! verifying closure which builds different constraints fails to verify.
+//!
+//! ```text
+//! let circuit = |cs: &mut dyn ConstraintSystem<Fr>, committed: &[AllocatedScalar<Fr>]| {
+//!     let (_, _, o) = cs.multiply(committed[0].into(), committed[1].into());
+//!     cs.constrain(o - committed[2].variable);
+//!     Ok(())
+//! };
+//! let (proof, commitments) = r1cs::prove(
+//!     &pc_gens, &bp_gens, Transcript::new(b"product"), &[a, b, c], &mut rng, &circuit,
+//! )?;
+//! r1cs::verify(
+//!     &pc_gens, &bp_gens, Transcript::new(b"product"), &proof, &commitments, &circuit,
+//! )?;
+//! ```
+
+use ark_ec::AffineRepr;
+use ark_ff::UniformRand;
+use ark_std::vec::Vec;
+use rand_core::{CryptoRng, RngCore};
+
+use super::gadgets::AllocatedScalar;
+use super::{ConstraintSystem, Prover, R1CSError, R1CSProof, Verifier};
+use crate::generators::{BulletproofGens, PedersenGens};
+use crate::transcript::TranscriptProtocol;
+
+/// Commits to the `values` with random blinding factors, builds the
+/// constraints of `circuit` over them, and proves the resulting
+/// statement.
+///
+/// Returns the proof and the commitments to the `values`, in order.
+/// Gadgets can be synthesized into the `&mut dyn ConstraintSystem` passed
+/// to the `circuit` by reference, as `gadget.synthesize(&mut cs, inputs)`.
+/// The circuit cannot use randomized constraints.
+pub fn prove<G, T, R, C>(
+    pc_gens: &PedersenGens<G>,
+    bp_gens: &BulletproofGens<G>,
+    transcript: T,
+    values: &[G::ScalarField],
+    rng: &mut R,
+    circuit: C,
+) -> Result<(R1CSProof<G>, Vec<G>), R1CSError>
+where
+    G: AffineRepr,
+    T: TranscriptProtocol<G>,
+    R: CryptoRng + RngCore,
+    C: Fn(
+        &mut dyn ConstraintSystem<G::ScalarField>,
+        &[AllocatedScalar<G::ScalarField>],
+    ) -> Result<(), R1CSError>,
+{
+    let mut prover = Prover::new(pc_gens, transcript);
+    let (commitments, committed): (Vec<_>, Vec<_>) = values
+        .iter()
+        .map(|value| {
+            let (commitment, var) = prover.commit(*value, G::ScalarField::rand(rng));
+            (commitment, AllocatedScalar::new(var, Some(*value)))
+        })
+        .unzip();
+
+    circuit(&mut prover, &committed)?;
+    prover.append_circuit_digest();
+    let proof = prover.prove(rng, bp_gens)?;
+
+    Ok((proof, commitments))
+}
+
+/// Builds the constraints of `circuit` over the `commitments`, and
+/// verifies the `proof` of the resulting statement, created by [`prove`]
+/// with the same `circuit`.
+pub fn verify<G, T, C>(
+    pc_gens: &PedersenGens<G>,
+    bp_gens: &BulletproofGens<G>,
+    transcript: T,
+    proof: &R1CSProof<G>,
+    commitments: &[G],
+    circuit: C,
+) -> Result<(), R1CSError>
+where
+    G: AffineRepr,
+    T: TranscriptProtocol<G>,
+    C: Fn(
+        &mut dyn ConstraintSystem<G::ScalarField>,
+        &[AllocatedScalar<G::ScalarField>],
+    ) -> Result<(), R1CSError>,
+{
+    let mut verifier = Verifier::new(transcript);
+    let committed: Vec<_> = commitments
+        .iter()
+        .map(|commitment| AllocatedScalar::new(verifier.commit(*commitment), None))
+        .collect();
+
+    circuit(&mut verifier, &committed)?;
+    verifier.append_circuit_digest();
+    verifier.verify(proof, pc_gens, bp_gens)
+}
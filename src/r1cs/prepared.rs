@@ -0,0 +1,420 @@
+#![allow(non_snake_case)]
+//! Definition of prepared circuits, for amortized verification of
+//! many proofs of the same circuit.
+
+use ark_ec::AffineRepr;
+use ark_ff::PrimeField;
+use ark_std::{iter, ops::Range, string::ToString, vec, vec::Vec, Zero};
+use digest::Digest;
+use sha3::Sha3_256;
+
+use super::artifacts::{MsmTerm, VerificationArtifacts};
+use super::circuit::circuit_digest;
+use super::verifier::{
+    circuit_verification_scalars, proof_points, CircuitLayout, FlattenedConstraints,
+};
+use super::{LinearCombination, R1CSProof, Variable};
+
+use crate::errors::R1CSError;
+use crate::generators::{BulletproofGens, PedersenGens};
+use crate::transcript::TranscriptProtocol;
+use crate::validation::{reject_identity, IdentityPolicy};
+
+/// A circuit whose constraints have been synthesized once, so that
+/// proofs of it can be verified without re-synthesizing the
+/// constraints for every proof.
+///
+/// A `PreparedCircuit` is created with [`Verifier::prepare`](::r1cs::Verifier::prepare)
+/// after the gadgets have added their constraints to the verifier.
+/// The constraints are stored sparsely, grouped by the kind of variable
+/// they weight, so that flattening them for a proof is a single pass
+/// over the non-zero weights.
+///
+/// Only circuits without randomized constraints can be prepared,
+/// since those depend on the challenges of each individual proof.
+/// Transcript operations performed by gadgets while building the
+/// circuit are not recorded: the caller is responsible for bringing
+/// the transcript passed to [`PreparedCircuit::verify`] into the same
+/// state as the one passed to [`Verifier::new`](::r1cs::Verifier::new).
+///
+/// The circuits of several parties can be combined with
+/// [`PreparedCircuit::aggregate`] into a single circuit, proven by the
+/// parties together with the [`mpc`](::r1cs::mpc) protocol.
+#[derive(Clone, Debug)]
+pub struct PreparedCircuit<F: PrimeField> {
+    /// Number of multipliers.
+    n: usize,
+    /// Number of high-level variables.
+    m: usize,
+    /// Number of constraints.
+    q: usize,
+    /// Weights `(constraint, multiplier, coefficient)` of the left inputs.
+    W_L: Vec<(usize, usize, F)>,
+    /// Weights `(constraint, multiplier, coefficient)` of the right inputs.
+    W_R: Vec<(usize, usize, F)>,
+    /// Weights `(constraint, multiplier, coefficient)` of the outputs.
+    W_O: Vec<(usize, usize, F)>,
+    /// Weights `(constraint, commitment, coefficient)` of the high-level variables.
+    W_V: Vec<(usize, usize, F)>,
+    /// Weights `(constraint, coefficient)` of the constant terms.
+    W_c: Vec<(usize, F)>,
+    /// Numbers of multipliers and of high-level variables of the
+    /// sub-circuits of an aggregated circuit, empty otherwise.
+    blocks: Vec<(usize, usize)>,
+    /// Commitments rejected when they are the identity.
+    identity_policy: IdentityPolicy,
+    /// Digest of the structure of the circuit.
+    digest: [u8; 32],
+}
+
+impl<F: PrimeField> PreparedCircuit<F> {
+    /// Creates a prepared circuit from the `constraints` of a constraint
+    /// system with `n` multipliers and `m` high-level variables.
+    pub(super) fn new(constraints: &[LinearCombination<F>], n: usize, m: usize) -> Self {
+        let mut circuit = PreparedCircuit {
+            n,
+            m,
+            q: constraints.len(),
+            W_L: Vec::new(),
+            W_R: Vec::new(),
+            W_O: Vec::new(),
+            W_V: Vec::new(),
+            W_c: Vec::new(),
+            blocks: Vec::new(),
+            identity_policy: IdentityPolicy::default(),
+            digest: circuit_digest(n, m, constraints),
+        };
+
+        for (q, lc) in constraints.iter().enumerate() {
+            for (var, coeff) in &lc.terms {
+                match var {
+                    Variable::MultiplierLeft(i) => circuit.W_L.push((q, *i, *coeff)),
+                    Variable::MultiplierRight(i) => circuit.W_R.push((q, *i, *coeff)),
+                    Variable::MultiplierOutput(i) => circuit.W_O.push((q, *i, *coeff)),
+                    Variable::Committed(i) => circuit.W_V.push((q, *i, *coeff)),
+                    Variable::One() => circuit.W_c.push((q, *coeff)),
+                    _ => {}
+                }
+            }
+        }
+
+        circuit
+    }
+
+    /// Returns the number of multipliers in the circuit.
+    pub fn multipliers_len(&self) -> usize {
+        self.n
+    }
+
+    /// Returns the number of high-level variables in the circuit.
+    pub fn commitments_len(&self) -> usize {
+        self.m
+    }
+
+    /// Returns the number of constraints in the circuit.
+    pub fn constraints_len(&self) -> usize {
+        self.q
+    }
+
+    /// Returns the digest of the structure of the circuit.
+    ///
+    /// This is the [`Verifier::circuit_digest`](::r1cs::Verifier::circuit_digest)
+    /// of the circuit when it was prepared, or for an aggregated circuit
+    /// the SHA3-256 hash of `b"r1cs aggregated circuit v1"`, the number
+    /// of circuits as a little-endian `u64` and the digests of the
+    /// circuits, in order.
+    pub fn circuit_digest(&self) -> [u8; 32] {
+        self.digest
+    }
+
+    /// Sets which commitments [`PreparedCircuit::verify`] rejects when
+    /// they are the identity, see
+    /// [`Verifier::set_identity_policy`](::r1cs::Verifier::set_identity_policy).
+    ///
+    /// A prepared circuit has no randomization phase, so only the policy
+    /// of the commitments applies.  An aggregated circuit rejects identity
+    /// commitments if one of its circuits did.
+    pub fn set_identity_policy(&mut self, policy: IdentityPolicy) {
+        self.identity_policy = policy;
+    }
+
+    /// Returns the number of parties whose generators the multipliers
+    /// use: the number of sub-circuits of an aggregated circuit, or 1.
+    pub fn parties(&self) -> usize {
+        self.blocks.len().max(1)
+    }
+
+    /// Aggregates the `circuits` of several parties into a single
+    /// circuit, whose proofs prove all of them at once.
+    ///
+    /// The multipliers of the `j`-th circuit, padded to the same power
+    /// of two, use the generators of `bp_gens.share(j)`, and its
+    /// high-level variables and constraints come after those of the
+    /// previous circuits.  The number of circuits must be a power of two.
+    pub fn aggregate(circuits: &[PreparedCircuit<F>]) -> Result<Self, R1CSError> {
+        if !circuits.len().is_power_of_two() {
+            return Err(R1CSError::GadgetError {
+                description: "the number of aggregated circuits must be a power of two".to_string(),
+            });
+        }
+        if circuits.iter().any(|circuit| !circuit.blocks.is_empty()) {
+            return Err(R1CSError::GadgetError {
+                description: "aggregated circuits cannot be aggregated again".to_string(),
+            });
+        }
+
+        let block_n = circuits
+            .iter()
+            .map(|circuit| circuit.n.next_power_of_two())
+            .max()
+            .unwrap();
+        let mut aggregated = PreparedCircuit {
+            n: block_n * circuits.len(),
+            m: 0,
+            q: 0,
+            W_L: Vec::new(),
+            W_R: Vec::new(),
+            W_O: Vec::new(),
+            W_V: Vec::new(),
+            W_c: Vec::new(),
+            blocks: Vec::with_capacity(circuits.len()),
+            identity_policy: IdentityPolicy::default(),
+            digest: aggregated_digest(circuits),
+        };
+
+        for (j, circuit) in circuits.iter().enumerate() {
+            let (n, m, q) = (j * block_n, aggregated.m, aggregated.q);
+            let shift = |weights: &[(usize, usize, F)], offset: usize| {
+                weights
+                    .iter()
+                    .map(move |(c, i, coeff)| (c + q, i + offset, *coeff))
+                    .collect::<Vec<_>>()
+            };
+            aggregated.W_L.extend(shift(&circuit.W_L, n));
+            aggregated.W_R.extend(shift(&circuit.W_R, n));
+            aggregated.W_O.extend(shift(&circuit.W_O, n));
+            aggregated.W_V.extend(shift(&circuit.W_V, m));
+            aggregated
+                .W_c
+                .extend(circuit.W_c.iter().map(|(c, coeff)| (c + q, *coeff)));
+
+            aggregated.m += circuit.m;
+            aggregated.q += circuit.q;
+            aggregated.blocks.push((circuit.n, circuit.m));
+            aggregated.identity_policy.commitments |= circuit.identity_policy.commitments;
+        }
+
+        Ok(aggregated)
+    }
+
+    /// Returns the ranges of the multipliers and of the high-level
+    /// variables of the `j`-th sub-circuit of an aggregated circuit, and
+    /// its number of multipliers.
+    pub(super) fn block(&self, j: usize) -> Option<(Range<usize>, Range<usize>, usize)> {
+        let (n, _) = *self.blocks.get(j)?;
+        let block_n = self.n / self.blocks.len();
+        let m_start: usize = self.blocks[..j].iter().map(|(_, m)| m).sum();
+        Some((
+            j * block_n..(j + 1) * block_n,
+            m_start..m_start + self.blocks[j].1,
+            n,
+        ))
+    }
+
+    /// Same as `flatten_constraints` in the verifier, but computed from
+    /// the cached sparse weights.
+    pub(super) fn flattened_constraints(&self, z: &F) -> FlattenedConstraints<F> {
+        let mut exp_z = Vec::with_capacity(self.q);
+        let mut z_q = *z;
+        for _ in 0..self.q {
+            exp_z.push(z_q);
+            z_q *= z;
+        }
+
+        let mut wL = vec![F::zero(); self.n];
+        let mut wR = vec![F::zero(); self.n];
+        let mut wO = vec![F::zero(); self.n];
+        let mut wV = vec![F::zero(); self.m];
+        let mut wc = F::zero();
+
+        for (q, i, coeff) in &self.W_L {
+            wL[*i] += exp_z[*q] * coeff;
+        }
+        for (q, i, coeff) in &self.W_R {
+            wR[*i] += exp_z[*q] * coeff;
+        }
+        for (q, i, coeff) in &self.W_O {
+            wO[*i] += exp_z[*q] * coeff;
+        }
+        for (q, i, coeff) in &self.W_V {
+            wV[*i] -= exp_z[*q] * coeff;
+        }
+        for (q, coeff) in &self.W_c {
+            wc -= exp_z[*q] * coeff;
+        }
+
+        (wL, wR, wO, wV, wc)
+    }
+
+    /// Verifies a `proof` of this circuit for the given high-level
+    /// variable `commitments`.
+    ///
+    /// This is equivalent to creating a [`Verifier`](::r1cs::Verifier) with
+    /// the `transcript`, committing the `commitments`, re-synthesizing the
+    /// circuit and calling [`Verifier::verify`](::r1cs::Verifier::verify).
+    pub fn verify<G: AffineRepr<ScalarField = F>, T: TranscriptProtocol<G> + ?Sized>(
+        &self,
+        transcript: &mut T,
+        commitments: &[G],
+        proof: &R1CSProof<G>,
+        pc_gens: &PedersenGens<G>,
+        bp_gens: &BulletproofGens<G>,
+    ) -> Result<(), R1CSError> {
+        if commitments.len() != self.m {
+            return Err(R1CSError::GadgetError {
+                description: "wrong number of commitments for the prepared circuit".to_string(),
+            });
+        }
+        let capacity = bp_gens.gens_capacity.saturating_mul(self.parties());
+        proof.check_shape(false, capacity)?;
+        if self.identity_policy.commitments {
+            reject_identity(b"V", commitments)?;
+        }
+        // There is no randomization phase.
+        if !proof.extra_phases.is_empty() {
+            return Err(R1CSError::InvalidProofShape {
+                description: "randomization phases in a proof of a prepared circuit".to_string(),
+            });
+        }
+
+        TranscriptProtocol::<G>::r1cs_domain_sep(transcript);
+        for V in commitments {
+            transcript.append_point(b"V", V);
+        }
+        transcript.append_u64(b"m", self.m as u64);
+
+        transcript.validate_and_append_point(b"A_I1", &proof.A_I1)?;
+        transcript.validate_and_append_point(b"A_O1", &proof.A_O1)?;
+        transcript.validate_and_append_point(b"S1", &proof.S1)?;
+
+        TranscriptProtocol::<G>::r1cs_1phase_domain_sep(transcript);
+
+        // There is no randomization phase, so these points are the identity.
+        transcript.append_point(b"A_I2", &proof.A_I2);
+        transcript.append_point(b"A_O2", &proof.A_O2);
+        transcript.append_point(b"S2", &proof.S2);
+
+        let scalars = circuit_verification_scalars(
+            transcript,
+            proof,
+            bp_gens,
+            CircuitLayout {
+                phase_ends: &[self.n, self.n],
+                vector_lens: &[],
+                parties: self.parties(),
+            },
+            |z| self.flattened_constraints(z),
+            None,
+        )?;
+        let points: Vec<G> = proof_points(proof, commitments, &[]).copied().collect();
+        let proof_scalars: Vec<G::ScalarField> = scalars.proof_scalars().collect();
+
+        let mega_check = bp_gens.fixed_msm(
+            pc_gens,
+            scalars.fixed_terms(self.parties()),
+            &points,
+            &proof_scalars,
+        );
+
+        if !mega_check.is_zero() {
+            return Err(R1CSError::VerificationError);
+        }
+
+        Ok(())
+    }
+
+    /// Exports everything an external verifier of this circuit needs
+    /// besides the proofs and the commitments: the generators used by
+    /// the proofs, the digest and the sparse weights of the circuit, and
+    /// the order of the verification multiscalar multiplication.
+    ///
+    /// Returns an error if `bp_gens` does not have enough generators for
+    /// the circuit.
+    pub fn export_artifacts<G: AffineRepr<ScalarField = F>>(
+        &self,
+        pc_gens: &PedersenGens<G>,
+        bp_gens: &BulletproofGens<G>,
+    ) -> Result<VerificationArtifacts<G>, R1CSError> {
+        let parties = self.parties();
+        let padded_n = self.n.next_power_of_two();
+        if bp_gens.gens_capacity < padded_n / parties {
+            return Err(R1CSError::InsufficientGenerators {
+                capacity: bp_gens.gens_capacity,
+                required: padded_n / parties,
+            });
+        }
+        if bp_gens.party_capacity < parties {
+            return Err(R1CSError::InvalidGeneratorsLength);
+        }
+
+        let rounds = padded_n.trailing_zeros() as usize;
+        let msm_order = [
+            (MsmTerm::B, 1),
+            (MsmTerm::BBlinding, 1),
+            (MsmTerm::G, padded_n),
+            (MsmTerm::H, padded_n),
+            (MsmTerm::AI1, 1),
+            (MsmTerm::AO1, 1),
+            (MsmTerm::S1, 1),
+            (MsmTerm::AI2, 1),
+            (MsmTerm::AO2, 1),
+            (MsmTerm::S2, 1),
+            (MsmTerm::V, self.m),
+        ]
+        .into_iter()
+        .chain(
+            [
+                MsmTerm::T1,
+                MsmTerm::T3,
+                MsmTerm::T4,
+                MsmTerm::T5,
+                MsmTerm::T6,
+            ]
+            .map(|t| (t, 1)),
+        )
+        .chain(iter::once((MsmTerm::L, rounds)))
+        .chain(iter::once((MsmTerm::R, rounds)))
+        .collect();
+
+        Ok(VerificationArtifacts {
+            circuit_digest: self.digest,
+            multipliers: self.n,
+            padded_multipliers: padded_n,
+            parties,
+            commitments: self.m,
+            constraints: self.q,
+            B: pc_gens.B,
+            B_blinding: pc_gens.B_blinding,
+            G: bp_gens.G(padded_n / parties, parties).copied().collect(),
+            H: bp_gens.H(padded_n / parties, parties).copied().collect(),
+            W_L: self.W_L.clone(),
+            W_R: self.W_R.clone(),
+            W_O: self.W_O.clone(),
+            W_V: self.W_V.clone(),
+            W_c: self.W_c.clone(),
+            msm_order,
+        })
+    }
+}
+
+/// Returns the digest of the aggregation of `circuits`, see
+/// [`PreparedCircuit::circuit_digest`].
+fn aggregated_digest<F: PrimeField>(circuits: &[PreparedCircuit<F>]) -> [u8; 32] {
+    let mut hash = Sha3_256::new();
+    Digest::update(&mut hash, b"r1cs aggregated circuit v1");
+    Digest::update(&mut hash, (circuits.len() as u64).to_le_bytes());
+    for circuit in circuits {
+        Digest::update(&mut hash, circuit.digest);
+    }
+    hash.finalize().into()
+}
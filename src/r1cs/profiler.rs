@@ -0,0 +1,165 @@
+//! Definition of the constraint profiler, which counts the multipliers
+//! and constraints contributed by each namespace of a circuit.
+
+use ark_ff::PrimeField;
+use ark_std::{
+    fmt,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::marker::PhantomData;
+
+use super::{ConstraintSystem, LinearCombination, R1CSError, Variable};
+use crate::transcript::ByteTranscript;
+
+/// The cost of a namespace, as reported by [`Profiler::report`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NamespaceProfile {
+    /// The path of the namespace: the names of the enclosing namespaces
+    /// and of the namespace itself, separated by `/`.
+    pub path: String,
+    /// The number of multipliers allocated in the namespace, including
+    /// its nested namespaces.
+    pub multipliers: usize,
+    /// The number of constraints added in the namespace, including its
+    /// nested namespaces and the two constraints added by every call to
+    /// [`ConstraintSystem::multiply`].
+    pub constraints: usize,
+}
+
+impl fmt::Display for NamespaceProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} multipliers, {} constraints",
+            self.path, self.multipliers, self.constraints
+        )
+    }
+}
+
+/// A [`ConstraintSystem`] wrapping another one, which counts the
+/// multipliers and constraints contributed by each namespace.
+///
+/// Gadgets delimit their namespaces with
+/// [`ConstraintSystem::push_namespace`] and
+/// [`ConstraintSystem::pop_namespace`], which do nothing in other
+/// constraint systems.  Synthesizing a circuit into a `Profiler`
+/// wrapping a [`Prover`](::r1cs::Prover), a
+/// [`Verifier`](::r1cs::Verifier) or a
+/// [`CircuitDescription`](::r1cs::CircuitDescription) builds the same
+/// circuit, and [`Profiler::report`] then shows where its cost comes
+/// from.
+///
+/// Randomized constraints are added through the wrapped constraint
+/// system when the proof is created or verified, so they are not
+/// counted.
+pub struct Profiler<'a, F: PrimeField, CS: ConstraintSystem<F>> {
+    cs: &'a mut CS,
+    /// The paths of the namespaces currently entered, innermost last.
+    stack: Vec<String>,
+    /// The profiles of the namespaces, in the order they were entered.
+    profiles: Vec<NamespaceProfile>,
+    /// The cost of the whole circuit.
+    total: NamespaceProfile,
+    _field: PhantomData<F>,
+}
+
+impl<'a, F: PrimeField, CS: ConstraintSystem<F>> Profiler<'a, F, CS> {
+    /// Wraps the constraint system `cs`.
+    pub fn new(cs: &'a mut CS) -> Self {
+        Profiler {
+            cs,
+            stack: Vec::new(),
+            profiles: Vec::new(),
+            total: NamespaceProfile::default(),
+            _field: PhantomData,
+        }
+    }
+
+    /// Returns the profiles of the namespaces, in the order they were
+    /// first entered.  A namespace entered several times is reported
+    /// once, with the sum of its costs.
+    pub fn report(&self) -> &[NamespaceProfile] {
+        &self.profiles
+    }
+
+    /// Returns the cost of everything added through the profiler.
+    pub fn total(&self) -> &NamespaceProfile {
+        &self.total
+    }
+
+    /// Adds a cost to the whole circuit and to the entered namespaces.
+    fn record(&mut self, multipliers: usize, constraints: usize) {
+        self.total.multipliers += multipliers;
+        self.total.constraints += constraints;
+        for path in &self.stack {
+            let profile = self
+                .profiles
+                .iter_mut()
+                .find(|p| p.path == *path)
+                .expect("entered namespaces are profiled");
+            profile.multipliers += multipliers;
+            profile.constraints += constraints;
+        }
+    }
+}
+
+impl<'a, F: PrimeField, CS: ConstraintSystem<F>> ConstraintSystem<F> for Profiler<'a, F, CS> {
+    fn transcript(&mut self) -> &mut dyn ByteTranscript {
+        self.cs.transcript()
+    }
+
+    fn multiply(
+        &mut self,
+        left: LinearCombination<F>,
+        right: LinearCombination<F>,
+    ) -> (Variable<F>, Variable<F>, Variable<F>) {
+        self.record(1, 2);
+        self.cs.multiply(left, right)
+    }
+
+    fn allocate(&mut self, assignment: Option<F>) -> Result<Variable<F>, R1CSError> {
+        let before = self.cs.multipliers_len();
+        let var = self.cs.allocate(assignment)?;
+        self.record(self.cs.multipliers_len() - before, 0);
+        Ok(var)
+    }
+
+    fn allocate_multiplier(
+        &mut self,
+        input_assignments: Option<(F, F)>,
+    ) -> Result<(Variable<F>, Variable<F>, Variable<F>), R1CSError> {
+        let vars = self.cs.allocate_multiplier(input_assignments)?;
+        self.record(1, 0);
+        Ok(vars)
+    }
+
+    fn multipliers_len(&self) -> usize {
+        self.cs.multipliers_len()
+    }
+
+    fn constrain(&mut self, lc: LinearCombination<F>) {
+        self.record(0, 1);
+        self.cs.constrain(lc)
+    }
+
+    fn push_namespace(&mut self, name: &str) {
+        let path = match self.stack.last() {
+            Some(parent) => [parent.as_str(), name].join("/"),
+            None => name.to_string(),
+        };
+        if !self.profiles.iter().any(|p| p.path == path) {
+            self.profiles.push(NamespaceProfile {
+                path: path.clone(),
+                ..NamespaceProfile::default()
+            });
+        }
+        self.stack.push(path);
+        self.cs.push_namespace(name);
+    }
+
+    fn pop_namespace(&mut self) {
+        self.stack.pop();
+        self.cs.pop_namespace();
+    }
+}
@@ -1,10 +1,16 @@
 #![allow(non_snake_case)]
 //! Definition of the proof struct.
 
-use crate::{errors::R1CSError, inner_product_proof::InnerProductProof, ProofError};
+use crate::{
+    errors::R1CSError, inner_product_proof::InnerProductProof, PointValidation, ProofError,
+};
 use ark_ec::AffineRepr;
-use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-use ark_std::{io::Cursor, vec::Vec};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress};
+use ark_std::{fmt, io::Cursor, string::ToString, vec::Vec};
+
+use subtle::{Choice, ConstantTimeEq};
+
+use crate::util::{ct_eq_serialized, hex};
 
 /// A proof of some statement specified by a
 /// [`ConstraintSystem`](::r1cs::ConstraintSystem).
@@ -22,7 +28,12 @@ use ark_std::{io::Cursor, vec::Vec};
 /// the constraint system using
 /// [`VerifierCS::verify`](::r1cs::VerifierCS::verify) to verify the
 /// proof.
-#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+///
+/// The proof is formatted, with both `{}` and `{:?}`, as the hexadecimal
+/// encoding of its [`to_bytes`](R1CSProof::to_bytes).  Proofs are
+/// compared, with `==` and [`ConstantTimeEq`], in constant time over
+/// their encodings.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
 #[allow(non_snake_case)]
 pub struct R1CSProof<G: AffineRepr> {
     /// Commitment to the values of input wires in the first phase.
@@ -31,12 +42,18 @@ pub struct R1CSProof<G: AffineRepr> {
     pub(super) A_O1: G,
     /// Commitment to the blinding factors in the first phase.
     pub(super) S1: G,
+    /// Commitment to the blinding factors of the entries of the vector
+    /// commitments, if there are any.
+    pub(super) S_C: Option<G>,
     /// Commitment to the values of input wires in the second phase.
     pub(super) A_I2: G,
     /// Commitment to the values of output wires in the second phase.
     pub(super) A_O2: G,
     /// Commitment to the blinding factors in the second phase.
     pub(super) S2: G,
+    /// Commitments \\((A_I, A_O, S)\\) of every randomization phase
+    /// after the second one, in order.
+    pub(super) extra_phases: Vec<(G, G, G)>,
     /// Commitment to the \\(t_1\\) coefficient of \\( t(x) \\)
     pub(super) T_1: G,
     /// Commitment to the \\(t_3\\) coefficient of \\( t(x) \\)
@@ -59,8 +76,9 @@ pub struct R1CSProof<G: AffineRepr> {
 }
 
 impl<G: AffineRepr> R1CSProof<G> {
-    /// Serializes the proof into a byte array of 1 version byte + \\((13 or 16) + 2k\\) 32-byte elements,
-    /// where \\(k=\lceil \log_2(n) \rceil\\) and \\(n\\) is the number of multiplication gates.
+    /// Serializes the proof into a byte array of 1 version byte + \\((13 or 16) + 3p + 2k\\) 32-byte elements,
+    /// where \\(k=\lceil \log_2(n) \rceil\\), \\(n\\) is the number of multiplication gates
+    /// and \\(p\\) is the number of randomization phases after the second one.
     ///
     /// # Layout
     ///
@@ -68,6 +86,10 @@ impl<G: AffineRepr> R1CSProof<G> {
     /// * 1 version byte indicating whether the proof contains second-phase commitments or not,
     /// * 8 or 11 compressed Ristretto points \\(A_{I1},A_{O1},S_1,(A_{I2},A_{O2},S_2),T_1,...,T_6\\)
     ///   (\\(A_{I2},A_{O2},S_2\\) are skipped if there were no multipliers added in the randomized phase),
+    /// * a flag byte followed, if the circuit has vector commitments, by the point \\(S_C\\),
+    ///   right after \\(S_1\\),
+    /// * the number \\(p\\) of further phases, followed by their \\(3p\\) points
+    ///   \\(A_{I},A_{O},S\\), right after \\(S_2\\),
     /// * three scalars \\(t_x, \tilde{t}_x, \tilde{e}\\),
     /// * \\(k\\) pairs of compressed Ristretto points \\(L_0,R_0\dots,L_{k-1},R_{k-1}\\),
     /// * two scalars \\(a, b\\).
@@ -77,16 +99,120 @@ impl<G: AffineRepr> R1CSProof<G> {
         Ok(cursor.into_inner())
     }
 
+    /// Returns the number of bytes of [`to_bytes`](R1CSProof::to_bytes),
+    /// without serializing the proof.
+    pub fn serialized_size(&self) -> usize {
+        self.compressed_size()
+    }
+
+    /// Returns the depth of the inner-product proof, i.e. its number of
+    /// rounds, which is \\(\lceil \log_2(n) \rceil\\) for \\(n\\)
+    /// multipliers, or zero without multipliers.
+    pub fn ipp_depth(&self) -> usize {
+        self.ipp_proof.L_vec.len()
+    }
+
+    /// Returns the number of multipliers padded to a power of two, i.e.
+    /// the number of generators the inner-product proof is over.
+    pub fn padded_multipliers(&self) -> usize {
+        1 << self.ipp_depth()
+    }
+
+    /// Returns the number of randomization phases of the proof, two for
+    /// every proof but those with further phases of randomized
+    /// constraints.
+    pub fn phases(&self) -> usize {
+        2 + self.extra_phases.len()
+    }
+
+    /// Returns whether the proof has the commitment \\(S_C\\) of the
+    /// blinding factors of vector commitments.
+    pub fn has_vector_commitments(&self) -> bool {
+        self.S_C.is_some()
+    }
+
     /// Deserializes the proof from a byte slice.
     ///
     /// Returns an error if the byte slice cannot be parsed into a `R1CSProof`.
+    /// Every point is checked to be on the curve and in the prime-order
+    /// subgroup, as with [`PointValidation::AlwaysCheck`].
     pub fn from_bytes(slice: &[u8]) -> Result<R1CSProof<G>, R1CSError> {
+        R1CSProof::from_bytes_with_validation(slice, PointValidation::AlwaysCheck)
+    }
+
+    /// Deserializes the proof from a byte slice, checking its points
+    /// according to `validation`.
+    ///
+    /// Returns an error if the byte slice cannot be parsed into a
+    /// `R1CSProof`, or if one of its points fails the checks.
+    pub fn from_bytes_with_validation(
+        slice: &[u8],
+        validation: PointValidation,
+    ) -> Result<R1CSProof<G>, R1CSError> {
         let mut cursor = Cursor::new(slice);
-        let proof = R1CSProof::<G>::deserialize_compressed(&mut cursor);
-        if proof.is_ok() {
-            Ok(proof.unwrap())
-        } else {
-            Err(R1CSError::FormatError)
+        R1CSProof::<G>::deserialize_with_mode(&mut cursor, Compress::Yes, validation.mode())
+            .map_err(|_| R1CSError::FormatError)
+    }
+
+    /// Checks the lengths of the vectors of the proof against the circuit
+    /// it is verified for, before any expensive work: the proof must have
+    /// the commitment \(S_C\) if and only if the circuit has vector
+    /// commitments, and its inner-product proof must have as many points
+    /// \(L\) as \(R\), for no more multipliers than `capacity`.
+    ///
+    /// The number of rounds of the inner-product proof and of the
+    /// randomization phases are checked once the circuit is complete.
+    pub(super) fn check_shape(
+        &self,
+        vector_commitments: bool,
+        capacity: usize,
+    ) -> Result<(), R1CSError> {
+        let invalid = |description: &str| {
+            Err(R1CSError::InvalidProofShape {
+                description: description.to_string(),
+            })
+        };
+        match (vector_commitments, self.S_C.is_some()) {
+            (true, false) => return invalid("missing commitment S_C to the vector commitments"),
+            (false, true) => {
+                return invalid("unexpected commitment S_C without vector commitments")
+            }
+            _ => {}
+        }
+        let (L_vec, R_vec) = (&self.ipp_proof.L_vec, &self.ipp_proof.R_vec);
+        if L_vec.len() != R_vec.len() {
+            return invalid("inner-product proof with different numbers of L and R points");
         }
+        if L_vec.len() >= usize::BITS as usize || 1 << L_vec.len() > capacity.max(1) {
+            return invalid("inner-product proof for more multipliers than the generators");
+        }
+        Ok(())
     }
 }
+
+impl<G: AffineRepr> fmt::Display for R1CSProof<G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes = self.to_bytes().map_err(|_| fmt::Error)?;
+        f.write_str(&hex(&bytes))
+    }
+}
+
+impl<G: AffineRepr> fmt::Debug for R1CSProof<G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "R1CSProof({})", self)
+    }
+}
+
+impl<G: AffineRepr> ConstantTimeEq for R1CSProof<G> {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        ct_eq_serialized(self, other)
+    }
+}
+
+impl<G: AffineRepr> PartialEq for R1CSProof<G> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl<G: AffineRepr> Eq for R1CSProof<G> {}
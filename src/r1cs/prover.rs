@@ -1,22 +1,38 @@
 #![allow(non_snake_case)]
 
-use ark_ec::{AffineRepr, CurveGroup, VariableBaseMSM};
-use ark_ff::{Field, PrimeField, UniformRand};
-use ark_serialize::CanonicalSerialize;
-use ark_std::{borrow::BorrowMut, boxed::Box, mem, vec, vec::Vec, One, Zero};
-use clear_on_drop::clear::Clear;
-use merlin::Transcript;
+extern crate alloc;
+
+use alloc::sync::Arc;
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::{Field, PrimeField};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+#[cfg(feature = "diagnostics")]
+use ark_std::string::String;
+use ark_std::{boxed::Box, iter, mem, string::ToString, vec, vec::Vec, One, Zero};
+use core::sync::atomic::AtomicBool;
 use rand_core::{CryptoRng, RngCore};
-
+use subtle::{Choice, ConstantTimeEq};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+use super::blinding::BlindingSource;
+use super::circuit::circuit_digest;
+use super::constraint_system::check_assignments_len;
+use super::export::{r1cs_bytes, wtns_bytes, Wires};
+#[cfg(feature = "metrics")]
+use super::metrics::PerfReport;
+use super::metrics::{Metrics, Step};
+use super::mpc::{Dealer, Party, WitnessShare};
+use super::verifier::{check_cancelled, phase_factors, proof_points};
 use super::{
-    ConstraintSystem, LinearCombination, R1CSProof, RandomizableConstraintSystem,
+    ConstraintSystem, LinearCombination, PreparedCircuit, R1CSProof, RandomizableConstraintSystem,
     RandomizedConstraintSystem, Variable,
 };
 
 use crate::errors::R1CSError;
-use crate::generators::{BulletproofGens, PedersenGens};
+use crate::generators::{BulletproofGens, FixedTerms, PedersenGens};
 use crate::inner_product_proof::InnerProductProof;
-use crate::transcript::TranscriptProtocol;
+use crate::transcript::{ByteTranscript, TranscriptProtocol};
+use crate::util::ct_eq_serialized;
 
 /// A [`ConstraintSystem`] implementation for use by the prover.
 ///
@@ -27,23 +43,68 @@ use crate::transcript::TranscriptProtocol;
 /// When all constraints are added, the proving code calls `prove`
 /// which consumes the `Prover` instance, samples random challenges
 /// that instantiate the randomized constraints, and creates a complete proof.
-pub struct Prover<'g, G: AffineRepr, T: BorrowMut<Transcript>> {
+pub struct Prover<'g, G: AffineRepr, T: TranscriptProtocol<G>> {
     transcript: T,
     pc_gens: &'g PedersenGens<G>,
     /// The constraints accumulated so far.
     constraints: Vec<LinearCombination<G::ScalarField>>,
     /// Secret data
     secrets: Secrets<G>,
+    /// Number of multipliers whose left inputs are committed by vector
+    /// commitments.
+    vector_len: usize,
+    /// Values of the public inputs.
+    public: Vec<G::ScalarField>,
 
     /// This list holds closures that will be called in the second phase of the protocol,
     /// when non-randomized variables are committed.
     deferred_constraints:
-        Vec<Box<dyn Fn(&mut RandomizingProver<'g, G, T>) -> Result<(), R1CSError>>>,
+        Vec<Box<dyn FnOnce(&mut RandomizingProver<'g, G, T>) -> Result<(), R1CSError>>>,
 
     /// Index of a pending multiplier that's not fully assigned yet.
     pending_multiplier: Option<usize>,
+
+    /// Whether constraints are checked against the witness when added.
+    strict: bool,
+    /// Index of the first constraint found unsatisfied in strict mode.
+    unsatisfied: Option<usize>,
+
+    /// Number of multipliers processed per pass in streaming mode.
+    chunk_size: Option<usize>,
+
+    /// Callback reporting the stages of the proof as they are completed.
+    progress: Option<Box<dyn FnMut(ProvingStage) + 'g>>,
+
+    /// Flag aborting the proof when set.
+    cancel: Option<Arc<AtomicBool>>,
+
+    /// Timings and counts of the proof, if reported.
+    metrics: Metrics,
+
+    /// Labels of the constraints, with the index of the first constraint
+    /// each label applies to.
+    #[cfg(feature = "diagnostics")]
+    labels: Vec<(usize, String)>,
+}
+
+/// A constraint that is not satisfied by the witness of a [`Prover`],
+/// as reported by [`Prover::unsatisfied_constraints`].
+#[cfg(feature = "diagnostics")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnsatisfiedConstraint<F: PrimeField> {
+    /// The index of the constraint, in the order in which the
+    /// constraints were added.
+    pub index: usize,
+    /// The label of the constraint, if any.
+    pub label: Option<String>,
+    /// The value of the linear combination of the constraint, which
+    /// should be zero.
+    pub residual: F,
 }
 
+/// A randomized-phase callback borrowed for the duration of a proof.
+type ScopedCallback<'s, CS> = &'s mut dyn FnMut(&mut CS) -> Result<(), R1CSError>;
+
 /// Separate struct to implement Drop trait for (for zeroing),
 /// so that compiler does not prohibit us from moving the Transcript out of `prove()`.
 struct Secrets<G: AffineRepr> {
@@ -57,6 +118,8 @@ struct Secrets<G: AffineRepr> {
     v: Vec<G::ScalarField>,
     /// High-level witness data (blinding openings to V commitments)
     v_blinding: Vec<G::ScalarField>,
+    /// Blinding openings to the vector commitments
+    vector_blinding: Vec<G::ScalarField>,
 }
 
 /// Prover in the randomizing phase.
@@ -66,38 +129,143 @@ struct Secrets<G: AffineRepr> {
 /// monomorphize the closures for the proving and verifying code.
 /// However, this type cannot be instantiated by the user and therefore can only be used within
 /// the callback provided to `specify_randomized_constraints`.
-pub struct RandomizingProver<'g, G: AffineRepr, T: BorrowMut<Transcript>> {
+pub struct RandomizingProver<'g, G: AffineRepr, T: TranscriptProtocol<G>> {
+    prover: Prover<'g, G, T>,
+}
+
+/// Commitments to the multipliers allocated in one randomization phase,
+/// with their blinding factors.
+struct PhaseCommitments<G: AffineRepr> {
+    A_I: G,
+    A_O: G,
+    S: G,
+    i_blinding: G::ScalarField,
+    o_blinding: G::ScalarField,
+    s_blinding: G::ScalarField,
+    s_L: Vec<G::ScalarField>,
+    s_R: Vec<G::ScalarField>,
+}
+
+/// Overwrite the blinding vectors with null bytes when they go out of scope.
+impl<G: AffineRepr> Drop for PhaseCommitments<G> {
+    fn drop(&mut self) {
+        self.i_blinding.zeroize();
+        self.o_blinding.zeroize();
+        self.s_blinding.zeroize();
+        self.s_L.zeroize();
+        self.s_R.zeroize();
+    }
+}
+
+impl<G: AffineRepr> ZeroizeOnDrop for PhaseCommitments<G> {}
+
+/// The commitments a [`ProverRounds`] sends in one round of the protocol.
+///
+/// Commitments are compared, with `==` and [`ConstantTimeEq`], in
+/// constant time over their encodings.
+#[derive(Copy, Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct RoundCommitments<G: AffineRepr> {
+    /// Commitment to the inputs of the multipliers of the round.
+    pub A_I: G,
+    /// Commitment to the outputs of the multipliers of the round.
+    pub A_O: G,
+    /// Commitment to the blinding vectors of the round.
+    pub S: G,
+}
+
+impl<G: AffineRepr> ConstantTimeEq for RoundCommitments<G> {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        ct_eq_serialized(self, other)
+    }
+}
+
+impl<G: AffineRepr> PartialEq for RoundCommitments<G> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl<G: AffineRepr> Eq for RoundCommitments<G> {}
+
+/// A stage of the creation of a proof, reported to the callback set with
+/// [`Prover::set_progress_callback`] once it is completed.
+///
+/// The stages are reported in order: the first round, every
+/// randomization phase, the commitments to \\(t(x)\\), every round of the
+/// inner-product proof, which make up most of the proving time for large
+/// circuits, and the end of the proof.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ProvingStage {
+    /// The multipliers allocated before the randomized constraints were
+    /// committed.
+    FirstRound,
+    /// The multipliers of the given randomization phase, counted from 1,
+    /// were committed.  The first randomization phase is always
+    /// committed, even when the circuit has no randomized constraints.
+    RandomizationPhase(usize),
+    /// The coefficients of the polynomial \\(t(x)\\) were committed.
+    PolynomialCommitments,
+    /// The given round of the inner-product proof, counted from 1, was
+    /// computed, out of `rounds` rounds.
+    InnerProductRound {
+        /// The number of rounds computed so far.
+        round: usize,
+        /// The number of rounds of the inner-product proof.
+        rounds: usize,
+    },
+    /// The proof is complete.
+    Done,
+}
+
+/// A [`Prover`] that is producing its proof one round at a time.
+///
+/// Created by [`Prover::commit_first_round`].  Each round commits to the
+/// multipliers of one phase of the circuit: the first round to those
+/// allocated before any randomized constraint, and every following round,
+/// after drawing the challenges of the randomized constraints from the
+/// transcript, to the multipliers those constraints allocate.  Once no
+/// round is left, [`ProverRounds::finalize`] draws the remaining
+/// challenges and produces the proof.
+///
+/// The state between rounds holds the transcript and the randomized
+/// constraints still to be added, which are closures, so it lives in
+/// memory; the [`RoundCommitments`] of each round are plain points which
+/// can be serialized and sent to other parties.
+pub struct ProverRounds<'g, G: AffineRepr, T: TranscriptProtocol<G>> {
     prover: Prover<'g, G, T>,
+    /// Commitments of the first phase, with their blinding factors.
+    first: PhaseCommitments<G>,
+    /// Commitment to the blinding vectors of the vector commitments, if
+    /// any, with its blinding factor.
+    S_C: Option<(G, G::ScalarField)>,
+    /// Commitments of the following phases, with their blinding factors.
+    phases: Vec<PhaseCommitments<G>>,
+    /// Number of multipliers allocated at the end of each phase.
+    phase_ends: Vec<usize>,
 }
 
 /// Overwrite secrets with null bytes when they go out of scope.
+///
+/// Zeroizing a `Vec` overwrites its entries and its spare capacity, but
+/// not the buffers it was moved out of while growing.
 impl<G: AffineRepr> Drop for Secrets<G> {
     fn drop(&mut self) {
-        self.v.clear();
-        self.v_blinding.clear();
-
-        // Important: due to how ClearOnDrop auto-implements InitializableFromZeroed
-        // for T: Default, calling .clear() on Vec compiles, but does not
-        // clear the content. Instead, it only clears the Vec's header.
-        // Clearing the underlying buffer item-by-item will do the job, but will
-        // keep the header as-is, which is fine since the header does not contain secrets.
-        for e in self.a_L.iter_mut() {
-            e.clear();
-        }
-        for e in self.a_R.iter_mut() {
-            e.clear();
-        }
-        for e in self.a_O.iter_mut() {
-            e.clear();
-        }
+        self.a_L.zeroize();
+        self.a_R.zeroize();
+        self.a_O.zeroize();
+        self.v.zeroize();
+        self.v_blinding.zeroize();
+        self.vector_blinding.zeroize();
     }
 }
 
-impl<'g, G: AffineRepr, T: BorrowMut<Transcript>> ConstraintSystem<G::ScalarField>
+impl<G: AffineRepr> ZeroizeOnDrop for Secrets<G> {}
+
+impl<'g, G: AffineRepr, T: TranscriptProtocol<G>> ConstraintSystem<G::ScalarField>
     for Prover<'g, G, T>
 {
-    fn transcript(&mut self) -> &mut Transcript {
-        self.transcript.borrow_mut()
+    fn transcript(&mut self) -> &mut dyn ByteTranscript {
+        &mut self.transcript
     }
 
     fn multiply(
@@ -186,32 +354,65 @@ impl<'g, G: AffineRepr, T: BorrowMut<Transcript>> ConstraintSystem<G::ScalarFiel
         self.secrets.a_L.len()
     }
 
+    fn constrain_all<I>(&mut self, lcs: I)
+    where
+        I: IntoIterator<Item = LinearCombination<G::ScalarField>>,
+    {
+        let lcs = lcs.into_iter();
+        if self.strict {
+            for lc in lcs {
+                self.constrain(lc);
+            }
+        } else {
+            self.constraints.extend(lcs);
+        }
+    }
+
+    fn allocate_vec(
+        &mut self,
+        n: usize,
+        assignments: Option<&[G::ScalarField]>,
+    ) -> Result<Vec<Variable<G::ScalarField>>, R1CSError> {
+        check_assignments_len(n, assignments)?;
+        let assignments = assignments.ok_or(R1CSError::MissingAssignment)?;
+
+        let new_multipliers = n.div_ceil(2);
+        self.secrets.a_L.reserve(new_multipliers);
+        self.secrets.a_R.reserve(new_multipliers);
+        self.secrets.a_O.reserve(new_multipliers);
+        assignments
+            .iter()
+            .map(|a| self.allocate(Some(*a)))
+            .collect()
+    }
+
     fn constrain(&mut self, lc: LinearCombination<G::ScalarField>) {
-        // TODO: check that the linear combinations are valid
-        // (e.g. that variables are valid, that the linear combination evals to 0 for prover, etc).
+        if self.strict && self.unsatisfied.is_none() && !self.is_satisfied(&lc) {
+            self.unsatisfied = Some(self.constraints.len());
+        }
         self.constraints.push(lc);
     }
 }
 
-impl<'g, G: AffineRepr, T: BorrowMut<Transcript>> RandomizableConstraintSystem<G::ScalarField>
+impl<'g, G: AffineRepr, T: TranscriptProtocol<G>> RandomizableConstraintSystem<G::ScalarField>
     for Prover<'g, G, T>
 {
     type RandomizedCS = RandomizingProver<'g, G, T>;
 
     fn specify_randomized_constraints<F>(&mut self, callback: F) -> Result<(), R1CSError>
     where
-        F: 'static + Fn(&mut Self::RandomizedCS) -> Result<(), R1CSError>,
+        F: 'static + FnOnce(&mut Self::RandomizedCS) -> Result<(), R1CSError>,
     {
         self.deferred_constraints.push(Box::new(callback));
         Ok(())
     }
 }
 
-impl<'g, G: AffineRepr, T: BorrowMut<Transcript>> ConstraintSystem<G::ScalarField>
+impl<'g, G: AffineRepr, T: TranscriptProtocol<G>> ConstraintSystem<G::ScalarField>
     for RandomizingProver<'g, G, T>
 {
-    fn transcript(&mut self) -> &mut Transcript {
-        self.prover.transcript.borrow_mut()
+    fn transcript(&mut self) -> &mut dyn ByteTranscript {
+        &mut self.prover.transcript
     }
 
     fn multiply(
@@ -254,20 +455,49 @@ impl<'g, G: AffineRepr, T: BorrowMut<Transcript>> ConstraintSystem<G::ScalarFiel
     fn constrain(&mut self, lc: LinearCombination<G::ScalarField>) {
         self.prover.constrain(lc)
     }
+
+    fn constrain_all<I>(&mut self, lcs: I)
+    where
+        I: IntoIterator<Item = LinearCombination<G::ScalarField>>,
+    {
+        self.prover.constrain_all(lcs)
+    }
+
+    fn allocate_vec(
+        &mut self,
+        n: usize,
+        assignments: Option<&[G::ScalarField]>,
+    ) -> Result<Vec<Variable<G::ScalarField>>, R1CSError> {
+        self.prover.allocate_vec(n, assignments)
+    }
+}
+
+/// Randomized constraints specified in a randomization phase are added
+/// in the next phase, after the multipliers of the current phase are
+/// committed.
+impl<'g, G: AffineRepr, T: TranscriptProtocol<G>> RandomizableConstraintSystem<G::ScalarField>
+    for RandomizingProver<'g, G, T>
+{
+    type RandomizedCS = Self;
+
+    fn specify_randomized_constraints<F>(&mut self, callback: F) -> Result<(), R1CSError>
+    where
+        F: 'static + FnOnce(&mut Self::RandomizedCS) -> Result<(), R1CSError>,
+    {
+        self.prover.deferred_constraints.push(Box::new(callback));
+        Ok(())
+    }
 }
 
-impl<'g, G: AffineRepr, T: BorrowMut<Transcript>> RandomizedConstraintSystem<G::ScalarField>
+impl<'g, G: AffineRepr, T: TranscriptProtocol<G>> RandomizedConstraintSystem<G::ScalarField>
     for RandomizingProver<'g, G, T>
 {
     fn challenge_scalar(&mut self, label: &'static [u8]) -> G::ScalarField {
-        <Transcript as TranscriptProtocol<G>>::challenge_scalar(
-            self.prover.transcript.borrow_mut(),
-            label,
-        )
+        TranscriptProtocol::<G>::challenge_scalar(&mut self.prover.transcript, label)
     }
 }
 
-impl<'g, G: AffineRepr, T: BorrowMut<Transcript>> Prover<'g, G, T> {
+impl<'g, G: AffineRepr, T: TranscriptProtocol<G>> Prover<'g, G, T> {
     /// Construct an empty constraint system with specified external
     /// input variables.
     ///
@@ -279,8 +509,9 @@ impl<'g, G: AffineRepr, T: BorrowMut<Transcript>> Prover<'g, G, T> {
     /// the number of multiplication constraints that will eventually
     /// be added into the constraint system.
     ///
-    /// The `transcript` parameter is a Merlin proof transcript.  The
-    /// `ProverCS` holds onto the `&mut Transcript` until it consumes
+    /// The `transcript` parameter is a proof transcript implementing
+    /// [`TranscriptProtocol`], e.g. a Merlin `Transcript` or a mutable
+    /// reference to one.  The `ProverCS` holds onto the transcript until it consumes
     /// itself during [`ProverCS::prove`], releasing its borrow of the
     /// transcript.  This ensures that the transcript cannot be
     /// altered except by the `ProverCS` before proving is complete.
@@ -289,7 +520,7 @@ impl<'g, G: AffineRepr, T: BorrowMut<Transcript>> Prover<'g, G, T> {
     ///
     /// Returns a new `Prover` instance.
     pub fn new(pc_gens: &'g PedersenGens<G>, mut transcript: T) -> Self {
-        <Transcript as TranscriptProtocol<G>>::r1cs_domain_sep(transcript.borrow_mut());
+        TranscriptProtocol::<G>::r1cs_domain_sep(&mut transcript);
 
         Prover {
             pc_gens,
@@ -297,16 +528,132 @@ impl<'g, G: AffineRepr, T: BorrowMut<Transcript>> Prover<'g, G, T> {
             secrets: Secrets {
                 v: Vec::new(),
                 v_blinding: Vec::new(),
+                vector_blinding: Vec::new(),
                 a_L: Vec::new(),
                 a_R: Vec::new(),
                 a_O: Vec::new(),
             },
+            vector_len: 0,
+            public: Vec::new(),
             constraints: Vec::new(),
             deferred_constraints: Vec::new(),
             pending_multiplier: None,
+            strict: false,
+            unsatisfied: None,
+            chunk_size: None,
+            progress: None,
+            cancel: None,
+            metrics: Metrics::default(),
+            #[cfg(feature = "diagnostics")]
+            labels: Vec::new(),
         }
     }
 
+    /// Enables or disables strict mode, which is disabled by default.
+    ///
+    /// In strict mode, the prover evaluates every constraint against its
+    /// witness when the constraint is added, and [`Prover::prove`] fails
+    /// with [`R1CSError::UnsatisfiedConstraint`] naming the first
+    /// unsatisfied constraint, instead of producing a proof that fails
+    /// verification.  Constraints referring to variables that do not
+    /// exist are also reported as unsatisfied.
+    ///
+    /// This costs an evaluation of every constraint, so it is meant for
+    /// debugging circuits and their witness generation.
+    pub fn set_strict_mode(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Enables streaming mode with passes over `chunk_size` multipliers,
+    /// or disables it with `None`, which is the default.
+    ///
+    /// In streaming mode, the commitments to the multipliers and the
+    /// coefficients of the polynomial \\(t(x)\\) are computed in passes
+    /// over chunks of `chunk_size` multipliers, so that the scratch memory
+    /// of the multiscalar multiplications and of the vector polynomials
+    /// \\(l(x)\\) and \\(r(x)\\) is bounded by the chunk size rather than
+    /// by the size of the circuit.  The witness itself, the evaluations
+    /// of \\(l(x)\\) and \\(r(x)\\) and the generators folded by the
+    /// inner-product proof remain linear in the number of multipliers.
+    ///
+    /// The polynomials are computed twice, so this only pays off for
+    /// circuits with millions of multipliers.  Proofs are the same with
+    /// and without streaming.
+    pub fn set_chunk_size(&mut self, chunk_size: Option<usize>) {
+        assert!(chunk_size != Some(0), "chunk size must be positive");
+        self.chunk_size = chunk_size;
+    }
+
+    /// Sets a callback called with every [`ProvingStage`] of the proof
+    /// once it is completed, e.g. to show the progress of long proofs.
+    ///
+    /// The callback is called from the thread creating the proof, between
+    /// the stages, so it should return quickly.
+    pub fn set_progress_callback<F: FnMut(ProvingStage) + 'g>(&mut self, callback: F) {
+        self.progress = Some(Box::new(callback));
+    }
+
+    /// Sets a flag which aborts the proof with [`R1CSError::Cancelled`]
+    /// once it is set, e.g. by a user interface thread.
+    ///
+    /// The flag is checked between the stages of the proof reported to
+    /// the progress callback: before the first round, before every
+    /// randomization phase, before the commitments to \\(t(x)\\) and
+    /// before the inner-product proof.
+    pub fn set_cancellation_flag(&mut self, flag: Arc<AtomicBool>) {
+        self.cancel = Some(flag);
+    }
+
+    /// Calls the progress callback, if any, with `stage`.
+    fn report(&mut self, stage: ProvingStage) {
+        if let Some(progress) = self.progress.as_mut() {
+            progress(stage);
+        }
+    }
+
+    /// Returns the index of the first unsatisfied constraint found so
+    /// far in strict mode, if any.
+    pub fn first_unsatisfied_constraint(&self) -> Option<usize> {
+        self.unsatisfied
+    }
+
+    /// Labels the constraints added from now on with `label`, until the
+    /// next call, so that [`Prover::unsatisfied_constraints`] can tell
+    /// which part of the circuit they come from.
+    #[cfg(feature = "diagnostics")]
+    pub fn label(&mut self, label: &str) {
+        self.labels
+            .push((self.constraints.len(), label.to_string()));
+    }
+
+    /// Evaluates every constraint added so far against the witness, and
+    /// returns the unsatisfied ones with their labels and the values
+    /// their linear combinations evaluate to.
+    ///
+    /// Randomized constraints are not included, since they are only
+    /// added when the proof is created.
+    #[cfg(feature = "diagnostics")]
+    pub fn unsatisfied_constraints(&self) -> Vec<UnsatisfiedConstraint<G::ScalarField>> {
+        self.constraints
+            .iter()
+            .enumerate()
+            .filter(|(_, lc)| !self.is_satisfied(lc))
+            .map(|(index, lc)| {
+                let label = self
+                    .labels
+                    .iter()
+                    .rev()
+                    .find(|(start, _)| *start <= index)
+                    .map(|(_, label)| label.clone());
+                UnsatisfiedConstraint {
+                    index,
+                    label,
+                    residual: self.eval(lc),
+                }
+            })
+            .collect()
+    }
+
     /// Creates commitment to a high-level variable and adds it to the transcript.
     ///
     /// # Inputs
@@ -335,11 +682,176 @@ impl<'g, G: AffineRepr, T: BorrowMut<Transcript>> Prover<'g, G, T> {
 
         // Add the commitment to the transcript.
         let V = self.pc_gens.commit(v, v_blinding);
-        self.transcript.borrow_mut().append_point(b"V", &V);
+        self.transcript.append_point(b"V", &V);
 
         (V, Variable::Committed(i))
     }
 
+    /// Same as [`Prover::commit`], but takes the blinding factor of the
+    /// commitment from `source`.
+    pub fn commit_with_blinding_source<S: BlindingSource<G::ScalarField>>(
+        &mut self,
+        v: G::ScalarField,
+        source: &mut S,
+    ) -> (G, Variable<G::ScalarField>) {
+        let v_blinding = source.commitment_blinding(self.secrets.v.len());
+        self.commit(v, v_blinding)
+    }
+
+    /// Creates a vector commitment to `values` and adds it to the transcript.
+    ///
+    /// The values are committed with the Bulletproofs generators as
+    /// \\( \langle \mathbf{v}, \mathbf{G} \rangle + \tilde{v} B_{blinding} \\),
+    /// using a single group element for the whole vector, and become the
+    /// left inputs of the next `values.len()` multipliers.  Vector
+    /// commitments must therefore be added before any multiplier is
+    /// allocated, and in the same order as the verifier adds them with
+    /// [`Verifier::commit_vec`](::r1cs::Verifier::commit_vec).
+    ///
+    /// # Returns
+    ///
+    /// Returns a pair of the vector commitment and a list of [`Variable`]s
+    /// corresponding to the values, which can be used to form constraints.
+    #[allow(clippy::type_complexity)]
+    pub fn commit_vec(
+        &mut self,
+        bp_gens: &BulletproofGens<G>,
+        values: &[G::ScalarField],
+        blinding: G::ScalarField,
+    ) -> Result<(G, Vec<Variable<G::ScalarField>>), R1CSError> {
+        let start = self.secrets.a_L.len();
+        if start != self.vector_len {
+            return Err(R1CSError::GadgetError {
+                description: "vector commitments must precede all multipliers".to_string(),
+            });
+        }
+        let end = start + values.len();
+        if bp_gens.gens_capacity < end {
+            return Err(R1CSError::InsufficientGenerators {
+                capacity: bp_gens.gens_capacity,
+                required: end,
+            });
+        }
+
+        // C = <v, G> + v_blinding * B_blinding
+        let C = self.commit_multipliers(bp_gens, blinding, values, &[], start);
+
+        self.vector_len = end;
+        self.secrets.vector_blinding.push(blinding);
+        self.secrets.a_L.extend_from_slice(values);
+        self.secrets.a_R.resize(end, G::ScalarField::zero());
+        self.secrets.a_O.resize(end, G::ScalarField::zero());
+
+        // Add the commitment to the transcript.
+        self.transcript.append_point(b"C", &C);
+
+        Ok((C, (start..end).map(Variable::MultiplierLeft).collect()))
+    }
+
+    /// Adds a public input with the given `value` and appends it to the
+    /// transcript.
+    ///
+    /// Unlike a high-level variable, a public input needs no Pedersen
+    /// commitment, since its value is known to the verifier.  The
+    /// verifier must add the same public inputs with
+    /// [`Verifier::public_input`](::r1cs::Verifier::public_input), in the
+    /// same order relative to the commitments.
+    ///
+    /// # Returns
+    ///
+    /// Returns a [`Variable`] corresponding to the public input, which can
+    /// be used to form constraints.
+    pub fn public_input(&mut self, value: G::ScalarField) -> Variable<G::ScalarField> {
+        let i = self.public.len();
+        self.public.push(value);
+
+        // Add the value to the transcript.
+        TranscriptProtocol::<G>::append_scalar(&mut self.transcript, b"P", &value);
+
+        Variable::Public(i)
+    }
+
+    /// Returns a digest of the structure of the circuit built so far:
+    /// the number of multipliers and high-level variables, and every
+    /// term of every constraint.
+    ///
+    /// Randomized constraints are not included, since they are only
+    /// added when the proof is created.
+    pub fn circuit_digest(&self) -> [u8; 32] {
+        circuit_digest(
+            self.secrets.a_L.len(),
+            self.secrets.v.len(),
+            &self.constraints,
+        )
+    }
+
+    /// Appends the [`circuit_digest`](Prover::circuit_digest) of the circuit
+    /// built so far to the transcript.
+    ///
+    /// This binds the proof to the exact structure of the circuit, so
+    /// that a proof for one circuit never verifies against a different
+    /// one.  It must be called after all the (non-randomized) constraints
+    /// have been added, and at the same point by the prover and the verifier.
+    pub fn append_circuit_digest(&mut self) {
+        let digest = self.circuit_digest();
+        TranscriptProtocol::<G>::r1cs_circuit_digest(&mut self.transcript, &digest);
+    }
+
+    /// Returns the circuit built so far in the binary `.r1cs` format of
+    /// circom, so that it can be audited or re-proved with other proof
+    /// systems, e.g. with snarkjs.
+    ///
+    /// The circuit is exported as rank-1 constraints `A * B = C` over the
+    /// wires `1, public inputs, high-level variables, a_L, a_R, a_O`, in
+    /// this order: the public inputs are the public inputs of the
+    /// circuit, the high-level variables its private inputs, and the
+    /// multipliers its internal wires.  The `i`-th multiplier is exported
+    /// as `a_L[i] * a_R[i] = a_O[i]`, followed by every linear constraint
+    /// `lc` as `0 * 0 = lc`.
+    ///
+    /// Returns an error if the circuit has randomized constraints, since
+    /// those depend on the challenges of the proof.
+    pub fn export_r1cs(&self) -> Result<Vec<u8>, R1CSError> {
+        self.check_exportable()?;
+        r1cs_bytes(&self.constraints, self.wires())
+    }
+
+    /// Returns the values of the wires of the circuit built so far, in
+    /// the order of [`Prover::export_r1cs`], in the binary `.wtns` format
+    /// of circom.
+    ///
+    /// Returns an error if the circuit has randomized constraints.
+    pub fn export_witness(&self) -> Result<Vec<u8>, R1CSError> {
+        self.check_exportable()?;
+        let values: Vec<G::ScalarField> = iter::once(G::ScalarField::one())
+            .chain(self.public.iter().copied())
+            .chain(self.secrets.v.iter().copied())
+            .chain(self.secrets.a_L.iter().copied())
+            .chain(self.secrets.a_R.iter().copied())
+            .chain(self.secrets.a_O.iter().copied())
+            .collect();
+        Ok(wtns_bytes(&values))
+    }
+
+    /// Returns an error if the circuit cannot be exported.
+    fn check_exportable(&self) -> Result<(), R1CSError> {
+        if !self.deferred_constraints.is_empty() {
+            return Err(R1CSError::GadgetError {
+                description: "circuits with randomized constraints cannot be exported".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns the numbers of the variables of the circuit built so far.
+    fn wires(&self) -> Wires {
+        Wires {
+            public: self.public.len(),
+            committed: self.secrets.v.len(),
+            multipliers: self.secrets.a_L.len(),
+        }
+    }
+
     /// Use a challenge, `z`, to flatten the constraints in the
     /// constraint system into vectors used for proving and
     /// verification.
@@ -360,6 +872,7 @@ impl<'g, G: AffineRepr, T: BorrowMut<Transcript>> Prover<'g, G, T> {
         Vec<G::ScalarField>,
         Vec<G::ScalarField>,
     ) {
+        trace_span!("flatten_constraints", constraints = self.constraints.len());
         let n = self.secrets.a_L.len();
         let m = self.secrets.v.len();
 
@@ -384,7 +897,7 @@ impl<'g, G: AffineRepr, T: BorrowMut<Transcript>> Prover<'g, G, T> {
                     Variable::Committed(i) => {
                         wV[*i] -= exp_z * coeff;
                     }
-                    Variable::One() => {
+                    Variable::One() | Variable::Public(_) => {
                         // The prover doesn't need to handle constant terms
                     }
                     _ => {}
@@ -407,40 +920,197 @@ impl<'g, G: AffineRepr, T: BorrowMut<Transcript>> Prover<'g, G, T> {
                         Variable::MultiplierOutput(i) => self.secrets.a_O[*i],
                         Variable::Committed(i) => self.secrets.v[*i],
                         Variable::One() => G::ScalarField::one(),
+                        Variable::Public(i) => self.public[*i],
                         _ => G::ScalarField::zero(),
                     }
             })
             .sum()
     }
 
+    /// Checks that every variable of `lc` exists, and that `lc`
+    /// evaluates to zero.
+    fn is_satisfied(&self, lc: &LinearCombination<G::ScalarField>) -> bool {
+        let valid = lc.terms.iter().all(|(var, _)| match var {
+            Variable::MultiplierLeft(i)
+            | Variable::MultiplierRight(i)
+            | Variable::MultiplierOutput(i) => *i < self.secrets.a_L.len(),
+            Variable::Committed(i) => *i < self.secrets.v.len(),
+            Variable::Public(i) => *i < self.public.len(),
+            _ => true,
+        });
+        valid && self.eval(lc).is_zero()
+    }
+
+    /// Fails if strict mode found an unsatisfied constraint.
+    fn check_unsatisfied(&self) -> Result<(), R1CSError> {
+        match self.unsatisfied {
+            Some(index) => Err(R1CSError::UnsatisfiedConstraint { index }),
+            None => Ok(()),
+        }
+    }
+
     /// Calls all remembered callbacks with an API that
     /// allows generating challenge scalars.
-    fn create_randomized_constraints(mut self) -> Result<Self, R1CSError> {
+    ///
+    /// The `scoped` callback, if any, is called last.
+    fn create_randomized_constraints(
+        mut self,
+        scoped: Option<ScopedCallback<'_, RandomizingProver<'g, G, T>>>,
+    ) -> Result<Self, R1CSError> {
         // Clear the pending multiplier (if any) because it was committed into A_L/A_R/S.
         self.pending_multiplier = None;
 
-        if self.deferred_constraints.len() == 0 {
-            <Transcript as TranscriptProtocol<G>>::r1cs_1phase_domain_sep(
-                self.transcript.borrow_mut(),
-            );
+        if self.deferred_constraints.len() == 0 && scoped.is_none() {
+            TranscriptProtocol::<G>::r1cs_1phase_domain_sep(&mut self.transcript);
             Ok(self)
         } else {
-            <Transcript as TranscriptProtocol<G>>::r1cs_2phase_domain_sep(
-                self.transcript.borrow_mut(),
-            );
-            // Note: the wrapper could've used &mut instead of ownership,
-            // but specifying lifetimes for boxed closures is not going to be nice,
-            // so we move the self into wrapper and then move it back out afterwards.
-            let mut callbacks = mem::replace(&mut self.deferred_constraints, Vec::new());
-            let mut wrapped_self = RandomizingProver { prover: self };
-            for callback in callbacks.drain(..) {
-                callback(&mut wrapped_self)?;
+            TranscriptProtocol::<G>::r1cs_2phase_domain_sep(&mut self.transcript);
+            self.call_deferred_constraints(scoped)
+        }
+    }
+
+    /// Calls the callbacks specified during the previous randomization
+    /// phase, in the randomization phase number `phase`.
+    fn create_next_phase_constraints(mut self, phase: u64) -> Result<Self, R1CSError> {
+        // Clear the pending multiplier (if any) because it was committed in the previous phase.
+        self.pending_multiplier = None;
+
+        TranscriptProtocol::<G>::r1cs_nphase_domain_sep(&mut self.transcript, phase);
+        self.call_deferred_constraints(None)
+    }
+
+    /// Calls the deferred callbacks, then the `scoped` one.  The
+    /// callbacks they specify in turn are deferred to the next phase.
+    fn call_deferred_constraints(
+        mut self,
+        scoped: Option<ScopedCallback<'_, RandomizingProver<'g, G, T>>>,
+    ) -> Result<Self, R1CSError> {
+        // Note: the wrapper could've used &mut instead of ownership,
+        // but specifying lifetimes for boxed closures is not going to be nice,
+        // so we move the self into wrapper and then move it back out afterwards.
+        let mut callbacks = mem::replace(&mut self.deferred_constraints, Vec::new());
+        let mut wrapped_self = RandomizingProver { prover: self };
+        for callback in callbacks.drain(..) {
+            callback(&mut wrapped_self)?;
+        }
+        if let Some(callback) = scoped {
+            callback(&mut wrapped_self)?;
+        }
+        Ok(wrapped_self.prover)
+    }
+
+    /// Returns the commitment `<g, G> + <h, H> + blinding * B_blinding`
+    /// over the generators from `start`, where `h` is empty or as long as
+    /// `g`, computed in chunks in streaming mode.
+    fn commit_multipliers(
+        &self,
+        bp_gens: &BulletproofGens<G>,
+        blinding: G::ScalarField,
+        g: &[G::ScalarField],
+        h: &[G::ScalarField],
+        start: usize,
+    ) -> G {
+        self.metrics.count(1 + g.len() + h.len(), 0);
+        self.metrics.time(Step::Msm, || {
+            let chunk = self.chunk_size.unwrap_or(g.len()).max(1);
+            let mut commitment = G::Group::zero();
+            let mut blinding = Some(blinding);
+            for offset in (0..g.len().max(1)).step_by(chunk) {
+                let end = (offset + chunk).min(g.len());
+                let h = if h.is_empty() { h } else { &h[offset..end] };
+                let fixed = FixedTerms::blinded(
+                    blinding.take().unwrap_or_else(G::ScalarField::zero),
+                    &g[offset..end],
+                    h,
+                    start + offset,
+                );
+                commitment += bp_gens.secret_fixed_msm(self.pc_gens, fixed);
             }
-            Ok(wrapped_self.prover)
+            commitment.into_affine()
+        })
+    }
+
+    /// Commits to the multipliers allocated since the multiplier `start`,
+    /// in a randomization phase.
+    fn commit_phase<S: BlindingSource<G::ScalarField>>(
+        &self,
+        bp_gens: &BulletproofGens<G>,
+        start: usize,
+        source: &mut S,
+    ) -> Result<PhaseCommitments<G>, R1CSError> {
+        let end = self.secrets.a_L.len();
+        if bp_gens.gens_capacity < end {
+            return Err(R1CSError::InsufficientGenerators {
+                capacity: bp_gens.gens_capacity,
+                required: end,
+            });
+        }
+
+        if start == end {
+            // Since we are using zero blinding factors and
+            // there are no variables to commit,
+            // the commitments _must_ be identity points,
+            // so we can hardcode them saving 3 mults+compressions.
+            return Ok(PhaseCommitments {
+                A_I: G::zero(),
+                A_O: G::zero(),
+                S: G::zero(),
+                i_blinding: G::ScalarField::zero(),
+                o_blinding: G::ScalarField::zero(),
+                s_blinding: G::ScalarField::zero(),
+                s_L: Vec::new(),
+                s_R: Vec::new(),
+            });
         }
+
+        let i_blinding = source.proof_blinding(b"i_blinding");
+        let o_blinding = source.proof_blinding(b"o_blinding");
+        let s_blinding = source.proof_blinding(b"s_blinding");
+
+        let s_L: Vec<G::ScalarField> = (start..end)
+            .map(|_| source.proof_blinding(b"s_L"))
+            .collect();
+        let s_R: Vec<G::ScalarField> = (start..end)
+            .map(|_| source.proof_blinding(b"s_R"))
+            .collect();
+
+        // A_I = <a_L, G> + <a_R, H> + i_blinding * B_blinding
+        let A_I = self.commit_multipliers(
+            bp_gens,
+            i_blinding,
+            &self.secrets.a_L[start..],
+            &self.secrets.a_R[start..],
+            start,
+        );
+
+        // A_O = <a_O, G> + o_blinding * B_blinding
+        let A_O =
+            self.commit_multipliers(bp_gens, o_blinding, &self.secrets.a_O[start..], &[], start);
+
+        // S = <s_L, G> + <s_R, H> + s_blinding * B_blinding
+        let S = self.commit_multipliers(bp_gens, s_blinding, &s_L, &s_R, start);
+
+        Ok(PhaseCommitments {
+            A_I,
+            A_O,
+            S,
+            i_blinding,
+            o_blinding,
+            s_blinding,
+            s_L,
+            s_R,
+        })
     }
 
     /// Consume this `ConstraintSystem` to produce a proof.
+    ///
+    /// All the blinding factors of the proof are drawn from `prng`,
+    /// after it is mixed with the transcript and the witness by a
+    /// [`TranscriptRng`](merlin::TranscriptRng): the prover has no
+    /// internal source of randomness, so a seeded `prng` gives
+    /// reproducible proofs, and a hardware or audited `prng` can be used
+    /// in production.  The blinding factors of the commitments are
+    /// chosen by the caller in [`Prover::commit`].
     pub fn prove<R: CryptoRng + RngCore>(
         self,
         prng: &mut R,
@@ -450,289 +1120,671 @@ impl<'g, G: AffineRepr, T: BorrowMut<Transcript>> Prover<'g, G, T> {
             .map(|(proof, _transcript)| proof)
     }
 
+    /// Consume this `ConstraintSystem` to produce a proof whose blinding
+    /// factors are all derived from `seed`.
+    ///
+    /// The seed keys the RNG passed to the [`TranscriptRng`](merlin::TranscriptRng)
+    /// of the prover, which also binds the transcript and the witness, so
+    /// the same seed gives the same proof for the same statement and
+    /// witness, and unrelated blinding factors otherwise.  This is meant
+    /// for reproducible test fixtures: reusing a seed across different
+    /// witnesses for the same commitments leaks them.
+    #[cfg(feature = "deterministic")]
+    pub fn prove_deterministic(
+        self,
+        seed: [u8; 32],
+        bp_gens: &BulletproofGens<G>,
+    ) -> Result<R1CSProof<G>, R1CSError> {
+        use rand_chacha::{rand_core::SeedableRng, ChaChaRng};
+
+        let mut prng = ChaChaRng::from_seed(seed);
+        self.prove(&mut prng, bp_gens)
+    }
+
+    /// Same as [`Prover::prove`], but also returns the timings and counts
+    /// of the proof.
+    #[cfg(feature = "metrics")]
+    pub fn prove_with_report<R: CryptoRng + RngCore>(
+        mut self,
+        prng: &mut R,
+        bp_gens: &BulletproofGens<G>,
+    ) -> Result<(R1CSProof<G>, PerfReport), R1CSError> {
+        let metrics = Metrics::enabled();
+        self.metrics = metrics.clone();
+        let proof = self.prove(prng, bp_gens)?;
+        Ok((proof, metrics.report()))
+    }
+
     /// Consume this `ConstraintSystem` to produce a proof. Returns the proof and the transcript passed in `Prover::new`.
     pub fn prove_and_return_transcript<R: CryptoRng + RngCore>(
+        self,
+        prng: &mut R,
+        bp_gens: &BulletproofGens<G>,
+    ) -> Result<(R1CSProof<G>, T), R1CSError> {
+        self.create_proof(prng, bp_gens, None)
+    }
+
+    /// Consume this `ConstraintSystem` to produce a proof, adding the
+    /// randomized constraints specified by `callback` after those of
+    /// [`RandomizableConstraintSystem::specify_randomized_constraints`].
+    ///
+    /// Unlike the callbacks passed to `specify_randomized_constraints`,
+    /// `callback` is called before this function returns, so it does not
+    /// need to be `'static` and can borrow local state, such as the
+    /// witness of the gadget, instead of cloning it.  The verifier must
+    /// add the same constraints with
+    /// [`Verifier::verify_with_randomized_constraints`](::r1cs::Verifier::verify_with_randomized_constraints).
+    pub fn prove_with_randomized_constraints<R, C>(
+        self,
+        prng: &mut R,
+        bp_gens: &BulletproofGens<G>,
+        callback: C,
+    ) -> Result<R1CSProof<G>, R1CSError>
+    where
+        R: CryptoRng + RngCore,
+        C: FnOnce(&mut RandomizingProver<'g, G, T>) -> Result<(), R1CSError>,
+    {
+        let mut callback = Some(callback);
+        let mut scoped = |cs: &mut RandomizingProver<'g, G, T>| (callback.take().unwrap())(cs);
+        self.create_proof(prng, bp_gens, Some(&mut scoped))
+            .map(|(proof, _transcript)| proof)
+    }
+
+    /// Consume this `ConstraintSystem` to produce a proof, drawing all of
+    /// its blinding factors from `source`.
+    ///
+    /// Unlike [`Prover::prove`], the factors are not mixed with the
+    /// transcript and the witness, so `source` alone is responsible for
+    /// their unpredictability.
+    pub fn prove_with_blinding_source<S: BlindingSource<G::ScalarField>>(
+        mut self,
+        source: &mut S,
+        bp_gens: &BulletproofGens<G>,
+    ) -> Result<R1CSProof<G>, R1CSError> {
+        self.begin_proof()?;
+        self.finish_proof(source, bp_gens, None)
+            .map(|(proof, _transcript)| proof)
+    }
+
+    /// Consumes `provers` of the same circuit, each with its own witness,
+    /// and creates a single proof of all of them, whose size is
+    /// logarithmic in the total number of multipliers.
+    ///
+    /// The proof is verified by the circuit synthesized by a
+    /// [`Verifier`](::r1cs::Verifier), prepared and aggregated
+    /// `provers.len()` times with [`PreparedCircuit::aggregate`], for the
+    /// commitments of all the provers in order and a transcript in the
+    /// same state as `transcript`.  The transcripts of the provers are
+    /// not used.
+    ///
+    /// The number of provers must be a power of two, and their circuit
+    /// cannot have randomized constraints, public inputs or vector
+    /// commitments, since it must be prepared.
+    pub fn prove_aggregated<Tr: TranscriptProtocol<G> + Clone, R: CryptoRng + RngCore>(
+        mut provers: Vec<Self>,
+        transcript: &mut Tr,
+        prng: &mut R,
+        bp_gens: &BulletproofGens<G>,
+    ) -> Result<R1CSProof<G>, R1CSError> {
+        let first = provers.first().ok_or_else(|| R1CSError::GadgetError {
+            description: "there must be at least one prover to aggregate".to_string(),
+        })?;
+        let digest = first.circuit_digest();
+        let pc_gens = first.pc_gens;
+
+        for prover in &mut provers {
+            if !prover.deferred_constraints.is_empty()
+                || !prover.public.is_empty()
+                || prover.vector_len > 0
+            {
+                return Err(R1CSError::GadgetError {
+                    description: "aggregated circuits must be prepared".to_string(),
+                });
+            }
+            if prover.circuit_digest() != digest {
+                return Err(R1CSError::GadgetError {
+                    description: "aggregated provers must have the same circuit".to_string(),
+                });
+            }
+            prover.check_unsatisfied()?;
+        }
+
+        let circuit = PreparedCircuit::new(
+            &provers[0].constraints,
+            provers[0].secrets.a_L.len(),
+            provers[0].secrets.v.len(),
+        );
+        let circuit = PreparedCircuit::aggregate(&vec![circuit; provers.len()])?;
+
+        let commitments: Vec<G> = provers
+            .iter()
+            .flat_map(|prover| {
+                prover
+                    .secrets
+                    .v
+                    .iter()
+                    .zip(prover.secrets.v_blinding.iter())
+                    .map(|(v, v_blinding)| pc_gens.commit(*v, *v_blinding))
+            })
+            .collect();
+
+        // Mix the witnesses of all the provers into the RNG, as in `prove`.
+        let mut witness: Vec<_> = provers.iter().flat_map(|prover| prover.witness()).collect();
+        let mut rng = TranscriptProtocol::<G>::witness_rng(&*transcript, &witness, prng);
+        witness.iter_mut().for_each(|(_, bytes)| bytes.zeroize());
+
+        // Run the aggregation protocol with every prover as a party.
+        let dealer = Dealer::new_aggregated(pc_gens, bp_gens, transcript, &circuit, &commitments)?;
+        let mut parties = Vec::with_capacity(provers.len());
+        let mut witness_commitments = Vec::with_capacity(provers.len());
+        for (j, prover) in provers.iter().enumerate() {
+            let witness = WitnessShare {
+                a_L: prover.secrets.a_L.clone(),
+                a_R: prover.secrets.a_R.clone(),
+                a_O: prover.secrets.a_O.clone(),
+                v_blinding: prover.secrets.v_blinding.clone(),
+            };
+            let (party, commitment) =
+                Party::new_aggregated(j, pc_gens, bp_gens, &circuit, witness, &mut rng)?;
+            parties.push(party);
+            witness_commitments.push(commitment);
+        }
+
+        let (dealer, witness_challenge) =
+            dealer.receive_witness_commitments(witness_commitments)?;
+        let mut parties_awaiting_poly_challenge = Vec::with_capacity(parties.len());
+        let mut poly_commitments = Vec::with_capacity(parties.len());
+        for party in parties {
+            let (party, commitment) = party
+                .apply_challenge(&witness_challenge)?
+                .commit_aggregated_poly(&mut rng);
+            parties_awaiting_poly_challenge.push(party);
+            poly_commitments.push(commitment);
+        }
+
+        let (dealer, poly_challenge) = dealer.receive_poly_commitments(poly_commitments)?;
+        let proof_shares = parties_awaiting_poly_challenge
+            .into_iter()
+            .map(|party| party.apply_challenge(&poly_challenge))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(dealer.receive_trusted_shares(&proof_shares)?)
+    }
+
+    fn create_proof<R: CryptoRng + RngCore>(
         mut self,
         prng: &mut R,
         bp_gens: &BulletproofGens<G>,
+        scoped: Option<ScopedCallback<'_, RandomizingProver<'g, G, T>>>,
     ) -> Result<(R1CSProof<G>, T), R1CSError> {
-        use crate::util;
-        use ark_std::iter;
+        self.begin_proof()?;
+        let mut rng = self.witness_rng(prng);
+        self.finish_proof(&mut rng, bp_gens, scoped)
+    }
+
+    /// Checks the constraints added so far, if in strict mode, and
+    /// appends the number of high-level variables to the transcript.
+    fn begin_proof(&mut self) -> Result<(), R1CSError> {
+        self.check_unsatisfied()?;
+        check_cancelled(&self.cancel)?;
 
         // Commit a length _suffix_ for the number of high-level variables.
         // We cannot do this in advance because user can commit variables one-by-one,
         // but this suffix provides safe disambiguation because each variable
         // is prefixed with a separate label.
         self.transcript
-            .borrow_mut()
             .append_u64(b"m", self.secrets.v.len() as u64);
+        Ok(())
+    }
 
-        // Create a `TranscriptRng` from the high-level witness data
+    /// Creates an RNG keyed with the transcript, the witness and `prng`.
+    fn witness_rng<R: CryptoRng + RngCore>(&mut self, prng: &mut R) -> T::Rng {
+        // Create an RNG from the witness data
         //
-        // The prover wants to rekey the RNG with its witness data.
+        // The prover wants to rekey the RNG with its witness data, so
+        // that its blinding factors stay unpredictable even if `prng`
+        // is weak, as long as the witness is secret.
         //
         // This consists of the high level witness data (the v's and
-        // v_blinding's), as well as the low-level witness data (a_L,
-        // a_R, a_O).  Since the low-level data should (hopefully) be
-        // determined by the high-level data, it doesn't give any
-        // extra entropy for reseeding the RNG.
+        // v_blinding's, and the blinding factors of the vector
+        // commitments), as well as the low-level witness data (a_L,
+        // a_R, a_O).  When the circuit has high-level variables, the
+        // low-level data should (hopefully) be determined by them, but
+        // circuits may also have no commitments at all, and keep their
+        // whole witness in the multipliers, so we commit both.
         //
         // Since the v_blindings should be random scalars (in order to
         // protect the v's in the commitments), we don't gain much by
         // committing the v's as well as the v_blinding's.
-        let mut rng = {
-            let mut builder = self.transcript.borrow_mut().build_rng();
-
-            // Commit the blinding factors for the input wires
-            for v_b in &self.secrets.v_blinding {
-                let mut bytes = Vec::new();
-                v_b.serialize_uncompressed(&mut bytes).unwrap();
-                builder = builder.rekey_with_witness_bytes(b"v_blinding", &bytes);
-            }
+        let mut witness = self.witness();
+        let rng = self.transcript.witness_rng(&witness, prng);
+        witness.iter_mut().for_each(|(_, bytes)| bytes.zeroize());
+        rng
+    }
 
-            builder.finalize(prng)
-        };
+    /// Returns the labeled witness data keying the RNG, see `witness_rng`.
+    ///
+    /// The buffers are allocated at their final size, so that serializing
+    /// leaves no copies behind, and must be zeroized after use.
+    fn witness(&self) -> Vec<(&'static [u8], Vec<u8>)> {
+        let mut witness = Vec::new();
+        let size = G::ScalarField::zero().uncompressed_size();
+
+        // Commit the blinding factors for the input wires
+        for v_b in &self.secrets.v_blinding {
+            let mut bytes = Vec::with_capacity(size);
+            v_b.serialize_uncompressed(&mut bytes).unwrap();
+            witness.push((&b"v_blinding"[..], bytes));
+        }
+
+        // Commit the blinding factors for the vector commitments
+        for v_b in &self.secrets.vector_blinding {
+            let mut bytes = Vec::with_capacity(size);
+            v_b.serialize_uncompressed(&mut bytes).unwrap();
+            witness.push((&b"vector_blinding"[..], bytes));
+        }
 
+        // Commit the first-phase low-level witness
+        let a = self
+            .secrets
+            .a_L
+            .iter()
+            .chain(self.secrets.a_R.iter())
+            .chain(self.secrets.a_O.iter());
+        let mut bytes = Vec::with_capacity(size * a.clone().count());
+        for a in a {
+            a.serialize_uncompressed(&mut bytes).unwrap();
+        }
+        witness.push((&b"a"[..], bytes));
+        witness
+    }
+
+    /// Produces the proof, once the transcript holds the commitments to
+    /// the high-level variables, drawing its blinding factors from
+    /// `source`.
+    fn finish_proof<S: BlindingSource<G::ScalarField>>(
+        self,
+        source: &mut S,
+        bp_gens: &BulletproofGens<G>,
+        scoped: Option<ScopedCallback<'_, RandomizingProver<'g, G, T>>>,
+    ) -> Result<(R1CSProof<G>, T), R1CSError> {
+        trace_span!("prove");
+        self.first_round(source, bp_gens)?
+            .second_round(source, bp_gens, scoped)?
+            .finalize(source, bp_gens)
+    }
+
+    /// Consume this `ConstraintSystem` and commit to the multipliers of
+    /// the first phase, drawing the blinding factors from `source`.
+    ///
+    /// This is the first round of [`Prover::prove_with_blinding_source`]:
+    /// the returned [`ProverRounds`] commits to the following phases one
+    /// at a time, with [`ProverRounds::next_round`], and produces the
+    /// proof with [`ProverRounds::finalize`].  The commitments of each
+    /// round can be sent away, e.g. to an air-gapped signer or to the
+    /// other parties of an MPC, before continuing with the next one.
+    pub fn commit_first_round<S: BlindingSource<G::ScalarField>>(
+        mut self,
+        source: &mut S,
+        bp_gens: &BulletproofGens<G>,
+    ) -> Result<ProverRounds<'g, G, T>, R1CSError> {
+        self.begin_proof()?;
+        self.first_round(source, bp_gens)
+    }
+
+    fn first_round<S: BlindingSource<G::ScalarField>>(
+        mut self,
+        source: &mut S,
+        bp_gens: &BulletproofGens<G>,
+    ) -> Result<ProverRounds<'g, G, T>, R1CSError> {
         // Commit to the first-phase low-level witness variables.
         let n1 = self.secrets.a_L.len();
 
         if bp_gens.gens_capacity < n1 {
-            return Err(R1CSError::InvalidGeneratorsLength);
+            return Err(R1CSError::InsufficientGenerators {
+                capacity: bp_gens.gens_capacity,
+                required: n1,
+            });
         }
 
-        // We are performing a single-party circuit proof, so party index is 0.
-        let gens = bp_gens.share(0);
+        let i_blinding1 = source.proof_blinding(b"i_blinding");
+        let o_blinding1 = source.proof_blinding(b"o_blinding");
+        let s_blinding1 = source.proof_blinding(b"s_blinding");
 
-        let i_blinding1 = G::ScalarField::rand(&mut rng);
-        let o_blinding1 = G::ScalarField::rand(&mut rng);
-        let s_blinding1 = G::ScalarField::rand(&mut rng);
+        let s_L1: Vec<G::ScalarField> = (0..n1).map(|_| source.proof_blinding(b"s_L")).collect();
+        let s_R1: Vec<G::ScalarField> = (0..n1).map(|_| source.proof_blinding(b"s_R")).collect();
 
-        let mut s_L1: Vec<G::ScalarField> =
-            (0..n1).map(|_| G::ScalarField::rand(&mut rng)).collect();
-        let mut s_R1: Vec<G::ScalarField> =
-            (0..n1).map(|_| G::ScalarField::rand(&mut rng)).collect();
+        // The inputs of the first `vector_len` multipliers are committed
+        // by the vector commitments, and their blinding factors by S_C.
+        let L = self.vector_len;
 
         // A_I = <a_L, G> + <a_R, H> + i_blinding * B_blinding
-        let A_I1 = G::Group::msm(
-            &iter::once(&self.pc_gens.B_blinding)
-                .chain(gens.G(n1))
-                .chain(gens.H(n1))
-                .map(|f| f.clone())
-                .collect::<Vec<G>>(),
-            &iter::once(&i_blinding1)
-                .chain(self.secrets.a_L.iter())
-                .chain(self.secrets.a_R.iter())
-                .map(|f| *f)
-                .collect::<Vec<G::ScalarField>>(),
-        )
-        .unwrap()
-        .into_affine();
+        let A_I1 = self.commit_multipliers(
+            bp_gens,
+            i_blinding1,
+            &self.secrets.a_L[L..],
+            &self.secrets.a_R[L..],
+            L,
+        );
 
         // A_O = <a_O, G> + o_blinding * B_blinding
-        let A_O1 = G::Group::msm(
-            &iter::once(&self.pc_gens.B_blinding)
-                .chain(gens.G(n1))
-                .map(|f| f.clone())
-                .collect::<Vec<G>>(),
-            &iter::once(&o_blinding1)
-                .chain(self.secrets.a_O.iter())
-                .map(|f| *f)
-                .collect::<Vec<G::ScalarField>>(),
-        )
-        .unwrap()
-        .into_affine();
+        let A_O1 = self.commit_multipliers(bp_gens, o_blinding1, &self.secrets.a_O[L..], &[], L);
 
         // S = <s_L, G> + <s_R, H> + s_blinding * B_blinding
-        let S1 = G::Group::msm(
-            &iter::once(&self.pc_gens.B_blinding)
-                .chain(gens.G(n1))
-                .chain(gens.H(n1))
-                .map(|f| f.clone())
-                .collect::<Vec<G>>(),
-            &iter::once(&s_blinding1)
-                .chain(s_L1.iter())
-                .chain(s_R1.iter())
-                .map(|f| *f)
-                .collect::<Vec<G::ScalarField>>(),
-        )
-        .unwrap()
-        .into_affine();
+        let S1 = self.commit_multipliers(bp_gens, s_blinding1, &s_L1[L..], &s_R1[L..], L);
 
-        let transcript = self.transcript.borrow_mut();
+        // S_C = <s_L, G> + <s_R, H> + s_blinding * B_blinding
+        // over the multipliers of the vector commitments.
+        let (S_C, s_blinding_C) = if self.secrets.vector_blinding.is_empty() {
+            (None, G::ScalarField::zero())
+        } else {
+            let s_blinding_C = source.proof_blinding(b"s_blinding_C");
+            let S_C = self.commit_multipliers(bp_gens, s_blinding_C, &s_L1[..L], &s_R1[..L], 0);
+            (Some(S_C), s_blinding_C)
+        };
+
+        let transcript = &mut self.transcript;
         transcript.append_point(b"A_I1", &A_I1);
         transcript.append_point(b"A_O1", &A_O1);
         transcript.append_point(b"S1", &S1);
+        if let Some(S_C) = &S_C {
+            transcript.append_point(b"S_C", S_C);
+        }
 
-        // Process the remaining constraints.
-        self = self.create_randomized_constraints()?;
+        self.report(ProvingStage::FirstRound);
+
+        Ok(ProverRounds {
+            prover: self,
+            first: PhaseCommitments {
+                A_I: A_I1,
+                A_O: A_O1,
+                S: S1,
+                i_blinding: i_blinding1,
+                o_blinding: o_blinding1,
+                s_blinding: s_blinding1,
+                s_L: s_L1,
+                s_R: s_R1,
+            },
+            S_C: S_C.map(|S_C| (S_C, s_blinding_C)),
+            phases: Vec::new(),
+            phase_ends: vec![n1],
+        })
+    }
+}
 
-        // Pad zeros to the next power of two (or do that implicitly when creating vectors)
+impl<'g, G: AffineRepr, T: TranscriptProtocol<G>> ProverRounds<'g, G, T> {
+    /// Returns the commitments of the last round.
+    pub fn commitments(&self) -> RoundCommitments<G> {
+        let phase = self.phases.last().unwrap_or(&self.first);
+        RoundCommitments {
+            A_I: phase.A_I,
+            A_O: phase.A_O,
+            S: phase.S,
+        }
+    }
 
-        // If the number of multiplications is not 0 or a power of 2, then pad the circuit.
-        let n = self.secrets.a_L.len();
-        let n2 = n - n1;
-        let padded_n = self.secrets.a_L.len().next_power_of_two();
-        let pad = padded_n - n;
+    /// Returns the commitment to the blinding vectors of the vector
+    /// commitments, which is sent in the first round if the circuit has
+    /// vector commitments.
+    pub fn vector_commitments(&self) -> Option<G> {
+        self.S_C.map(|(S_C, _)| S_C)
+    }
 
-        if bp_gens.gens_capacity < padded_n {
-            return Err(R1CSError::InvalidGeneratorsLength);
+    /// Returns whether the prover has rounds left before
+    /// [`ProverRounds::finalize`].
+    ///
+    /// The second round is always sent, even when the circuit has no
+    /// randomized constraints, in which case its commitments are the
+    /// identity.
+    pub fn has_next_round(&self) -> bool {
+        self.phases.is_empty() || !self.prover.deferred_constraints.is_empty()
+    }
+
+    /// Adds the randomized constraints of the next round, with challenges
+    /// drawn from the transcript, and commits to the multipliers they
+    /// allocate, drawing the blinding factors from `source`.
+    ///
+    /// Returns an error if there is no round left.
+    pub fn next_round<S: BlindingSource<G::ScalarField>>(
+        self,
+        source: &mut S,
+        bp_gens: &BulletproofGens<G>,
+    ) -> Result<Self, R1CSError> {
+        if self.phases.is_empty() {
+            self.second_round(source, bp_gens, None)
+        } else if !self.prover.deferred_constraints.is_empty() {
+            self.later_round(source, bp_gens)
+        } else {
+            Err(R1CSError::GadgetError {
+                description: "the prover has no round left".to_string(),
+            })
         }
+    }
 
-        // Commit to the second-phase low-level witness variables
+    fn second_round<S: BlindingSource<G::ScalarField>>(
+        mut self,
+        source: &mut S,
+        bp_gens: &BulletproofGens<G>,
+        scoped: Option<ScopedCallback<'_, RandomizingProver<'g, G, T>>>,
+    ) -> Result<Self, R1CSError> {
+        check_cancelled(&self.prover.cancel)?;
+        // Process the remaining constraints, committing to the multipliers
+        // of every randomization phase before the challenges of the next one.
+        self.prover = self.prover.create_randomized_constraints(scoped)?;
+        self.prover.check_unsatisfied()?;
+
+        // Commit to the second-phase low-level witness variables.
+        // These are identity points in the 1-phase unrandomized case.
+        let phase2 = self
+            .prover
+            .commit_phase(bp_gens, self.phase_ends[0], source)?;
+        self.phase_ends.push(self.prover.secrets.a_L.len());
+
+        let transcript = &mut self.prover.transcript;
+        transcript.append_point(b"A_I2", &phase2.A_I);
+        transcript.append_point(b"A_O2", &phase2.A_O);
+        transcript.append_point(b"S2", &phase2.S);
+
+        self.phases.push(phase2);
+        self.prover.report(ProvingStage::RandomizationPhase(1));
+        Ok(self)
+    }
 
-        let has_2nd_phase_commitments = n2 > 0;
+    fn later_round<S: BlindingSource<G::ScalarField>>(
+        mut self,
+        source: &mut S,
+        bp_gens: &BulletproofGens<G>,
+    ) -> Result<Self, R1CSError> {
+        check_cancelled(&self.prover.cancel)?;
+        self.prover = self
+            .prover
+            .create_next_phase_constraints(self.phases.len() as u64 + 2)?;
+        self.prover.check_unsatisfied()?;
+
+        let start = self.phase_ends[self.phases.len()];
+        let phase = self.prover.commit_phase(bp_gens, start, source)?;
+        self.phase_ends.push(self.prover.secrets.a_L.len());
+
+        let transcript = &mut self.prover.transcript;
+        transcript.append_point(b"A_I", &phase.A_I);
+        transcript.append_point(b"A_O", &phase.A_O);
+        transcript.append_point(b"S", &phase.S);
+
+        self.phases.push(phase);
+        self.prover
+            .report(ProvingStage::RandomizationPhase(self.phases.len()));
+        Ok(self)
+    }
 
-        let (i_blinding2, o_blinding2, s_blinding2) = if has_2nd_phase_commitments {
-            (
-                G::ScalarField::rand(&mut rng),
-                G::ScalarField::rand(&mut rng),
-                G::ScalarField::rand(&mut rng),
-            )
-        } else {
-            (
-                G::ScalarField::zero(),
-                G::ScalarField::zero(),
-                G::ScalarField::zero(),
-            )
-        };
+    /// Commits to the rounds that are left, if any, and produces the
+    /// proof, drawing the remaining blinding factors from `source`.
+    /// Returns the proof and the transcript passed in `Prover::new`.
+    pub fn finalize<S: BlindingSource<G::ScalarField>>(
+        mut self,
+        source: &mut S,
+        bp_gens: &BulletproofGens<G>,
+    ) -> Result<(R1CSProof<G>, T), R1CSError> {
+        use crate::util;
 
-        let mut s_L2: Vec<G::ScalarField> =
-            (0..n2).map(|_| G::ScalarField::rand(&mut rng)).collect();
-        let mut s_R2: Vec<G::ScalarField> =
-            (0..n2).map(|_| G::ScalarField::rand(&mut rng)).collect();
+        while self.has_next_round() {
+            self = self.next_round(source, bp_gens)?;
+        }
 
-        let (A_I2, A_O2, S2) = if has_2nd_phase_commitments {
-            (
-                // A_I = <a_L, G> + <a_R, H> + i_blinding * B_blinding
-                G::Group::msm(
-                    &iter::once(&self.pc_gens.B_blinding)
-                        .chain(gens.G(n).skip(n1))
-                        .chain(gens.H(n).skip(n1))
-                        .map(|f| f.clone())
-                        .collect::<Vec<G>>(),
-                    &iter::once(&i_blinding2)
-                        .chain(self.secrets.a_L.iter().skip(n1))
-                        .chain(self.secrets.a_R.iter().skip(n1))
-                        .map(|f| *f)
-                        .collect::<Vec<G::ScalarField>>(),
-                )
-                .unwrap()
-                .into_affine(),
-                // A_O = <a_O, G> + o_blinding * B_blinding
-                G::Group::msm(
-                    &iter::once(&self.pc_gens.B_blinding)
-                        .chain(gens.G(n).skip(n1))
-                        .map(|f| f.clone())
-                        .collect::<Vec<G>>(),
-                    &iter::once(&o_blinding2)
-                        .chain(self.secrets.a_O.iter().skip(n1))
-                        .map(|f| *f)
-                        .collect::<Vec<G::ScalarField>>(),
-                )
-                .unwrap()
-                .into_affine(),
-                // S = <s_L, G> + <s_R, H> + s_blinding * B_blinding
-                G::Group::msm(
-                    &iter::once(&self.pc_gens.B_blinding)
-                        .chain(gens.G(n).skip(n1))
-                        .chain(gens.H(n).skip(n1))
-                        .map(|f| f.clone())
-                        .collect::<Vec<G>>(),
-                    &iter::once(&s_blinding2)
-                        .chain(s_L2.iter())
-                        .chain(s_R2.iter())
-                        .map(|f| *f)
-                        .collect::<Vec<G::ScalarField>>(),
-                )
-                .unwrap()
-                .into_affine(),
-            )
-        } else {
-            // Since we are using zero blinding factors and
-            // there are no variables to commit,
-            // the commitments _must_ be identity points,
-            // so we can hardcode them saving 3 mults+compressions.
-            (G::zero(), G::zero(), G::zero())
+        let ProverRounds {
+            mut prover,
+            first,
+            S_C,
+            phases,
+            phase_ends,
+        } = self;
+        let (S_C, s_blinding_C) = match S_C {
+            Some((S_C, s_blinding_C)) => (Some(S_C), Zeroizing::new(s_blinding_C)),
+            None => (None, Zeroizing::new(G::ScalarField::zero())),
         };
+        let L = prover.vector_len;
 
-        let transcript = self.transcript.borrow_mut();
-        transcript.append_point(b"A_I2", &A_I2);
-        transcript.append_point(b"A_O2", &A_O2);
-        transcript.append_point(b"S2", &S2);
+        // We are performing a single-party circuit proof, so party index is 0.
+        let gens = bp_gens.share(0);
 
-        // 4. Compute blinded vector polynomials l(x) and r(x)
+        // Pad zeros to the next power of two (or do that implicitly when creating vectors)
 
-        let y: G::ScalarField =
-            <Transcript as TranscriptProtocol<G>>::challenge_scalar(transcript, b"y");
-        let z = <Transcript as TranscriptProtocol<G>>::challenge_scalar(transcript, b"z");
+        // If the number of multiplications is not 0 or a power of 2, then pad the circuit.
+        let n = prover.secrets.a_L.len();
+        let padded_n = prover.secrets.a_L.len().next_power_of_two();
+        let pad = padded_n - n;
 
-        let (wL, wR, wO, wV) = self.flattened_constraints(&z);
+        if bp_gens.gens_capacity < padded_n {
+            return Err(R1CSError::InsufficientGenerators {
+                capacity: bp_gens.gens_capacity,
+                required: padded_n,
+            });
+        }
+        check_cancelled(&prover.cancel)?;
 
-        let mut l_poly = util::VecPoly3::<G>::zero(n);
-        let mut r_poly = util::VecPoly3::<G>::zero(n);
+        let transcript = &mut prover.transcript;
 
-        let mut exp_y = G::ScalarField::one(); // y^n starting at n=0
-        let y_inv = y.inverse().unwrap();
-        let exp_y_inv = util::exp_iter::<G>(y_inv)
-            .take(padded_n)
-            .collect::<Vec<_>>();
+        // 4. Compute blinded vector polynomials l(x) and r(x)
 
-        let sLsR = s_L1
-            .iter()
-            .chain(s_L2.iter())
-            .zip(s_R1.iter().chain(s_R2.iter()));
-        for (i, (sl, sr)) in sLsR.enumerate() {
-            // l_poly.0 = 0
-            // l_poly.1 = a_L + y^-n * (z * z^Q * W_R)
-            l_poly.1[i] = self.secrets.a_L[i] + exp_y_inv[i] * wR[i];
-            // l_poly.2 = a_O
-            l_poly.2[i] = self.secrets.a_O[i];
-            // l_poly.3 = s_L
-            l_poly.3[i] = *sl;
-            // r_poly.0 = (z * z^Q * W_O) - y^n
-            r_poly.0[i] = wO[i] - exp_y;
-            // r_poly.1 = y^n * a_R + (z * z^Q * W_L)
-            r_poly.1[i] = exp_y * self.secrets.a_R[i] + wL[i];
-            // r_poly.2 = 0
-            // r_poly.3 = y^n * s_R
-            r_poly.3[i] = exp_y * sr;
+        let y: G::ScalarField = TranscriptProtocol::<G>::challenge_scalar(transcript, b"y");
+        let z = TranscriptProtocol::<G>::challenge_scalar(transcript, b"z");
 
-            exp_y = exp_y * y; // y^i -> y^(i+1)
-        }
+        let metrics = prover.metrics.clone();
+        let (wL, wR, wO, wV) = metrics.time(Step::Flatten, || prover.flattened_constraints(&z));
 
-        let t_poly = util::VecPoly3::special_inner_product(&l_poly, &r_poly);
+        let y_inv = y.inverse().unwrap();
+        let exp_y_inv = util::exp_iter(y_inv).take(padded_n).collect::<Vec<_>>();
+
+        // Passes over the polynomials l(x) and r(x) of the multipliers, in
+        // chunks of `chunk` multipliers, returning y^n.
+        let chunk = prover.chunk_size.unwrap_or(n).max(1);
+        let secrets = &prover.secrets;
+        let for_each_chunk = |f: &mut dyn FnMut(util::VecPoly3<_>, util::VecPoly3<_>)| {
+            let mut sLsR = first
+                .s_L
+                .iter()
+                .chain(phases.iter().flat_map(|phase| phase.s_L.iter()))
+                .zip(
+                    first
+                        .s_R
+                        .iter()
+                        .chain(phases.iter().flat_map(|phase| phase.s_R.iter())),
+                );
+            let mut exp_y = G::ScalarField::one(); // y^n starting at n=0
+            for start in (0..n).step_by(chunk) {
+                let len = chunk.min(n - start);
+                let mut l_poly = util::VecPoly3::<G::ScalarField>::zero(len);
+                let mut r_poly = util::VecPoly3::<G::ScalarField>::zero(len);
+                for (k, (sl, sr)) in sLsR.by_ref().take(len).enumerate() {
+                    let i = start + k;
+                    // l_poly.0 = 0
+                    // l_poly.1 = a_L + y^-n * (z * z^Q * W_R)
+                    l_poly.1[k] = secrets.a_L[i] + exp_y_inv[i] * wR[i];
+                    // l_poly.2 = a_O
+                    l_poly.2[k] = secrets.a_O[i];
+                    // l_poly.3 = s_L
+                    l_poly.3[k] = *sl;
+                    // r_poly.0 = (z * z^Q * W_O) - y^n
+                    r_poly.0[k] = wO[i] - exp_y;
+                    // r_poly.1 = y^n * a_R + (z * z^Q * W_L)
+                    r_poly.1[k] = exp_y * secrets.a_R[i] + wL[i];
+                    // r_poly.2 = 0
+                    // r_poly.3 = y^n * s_R
+                    r_poly.3[k] = exp_y * sr;
+
+                    exp_y = exp_y * y; // y^i -> y^(i+1)
+                }
+                f(l_poly, r_poly);
+            }
+            exp_y
+        };
+
+        // Without streaming, the polynomials of the single chunk are kept
+        // for their evaluation at x.
+        let mut t_poly = util::Poly6::<G::ScalarField>::zero();
+        let mut polys = None;
+        let mut exp_y = for_each_chunk(&mut |l_poly, r_poly| {
+            t_poly += util::VecPoly3::special_inner_product(&l_poly, &r_poly);
+            if chunk >= n {
+                polys = Some((l_poly, r_poly));
+            }
+        });
 
-        let t_1_blinding = G::ScalarField::rand(&mut rng);
-        let t_3_blinding = G::ScalarField::rand(&mut rng);
-        let t_4_blinding = G::ScalarField::rand(&mut rng);
-        let t_5_blinding = G::ScalarField::rand(&mut rng);
-        let t_6_blinding = G::ScalarField::rand(&mut rng);
+        let t_1_blinding = source.proof_blinding(b"t_blinding");
+        let t_3_blinding = source.proof_blinding(b"t_blinding");
+        let t_4_blinding = source.proof_blinding(b"t_blinding");
+        let t_5_blinding = source.proof_blinding(b"t_blinding");
+        let t_6_blinding = source.proof_blinding(b"t_blinding");
 
-        let T_1 = self.pc_gens.commit(t_poly.t1, t_1_blinding);
-        let T_3 = self.pc_gens.commit(t_poly.t3, t_3_blinding);
-        let T_4 = self.pc_gens.commit(t_poly.t4, t_4_blinding);
-        let T_5 = self.pc_gens.commit(t_poly.t5, t_5_blinding);
-        let T_6 = self.pc_gens.commit(t_poly.t6, t_6_blinding);
+        let pc_gens = prover.pc_gens;
+        let (T_1, T_3, T_4, T_5, T_6) = metrics.time(Step::Msm, || {
+            (
+                pc_gens.commit(t_poly.t1, t_1_blinding),
+                pc_gens.commit(t_poly.t3, t_3_blinding),
+                pc_gens.commit(t_poly.t4, t_4_blinding),
+                pc_gens.commit(t_poly.t5, t_5_blinding),
+                pc_gens.commit(t_poly.t6, t_6_blinding),
+            )
+        });
+        metrics.count(10, 0);
 
-        let transcript = self.transcript.borrow_mut();
+        let transcript = &mut prover.transcript;
         transcript.append_point(b"T_1", &T_1);
         transcript.append_point(b"T_3", &T_3);
         transcript.append_point(b"T_4", &T_4);
         transcript.append_point(b"T_5", &T_5);
         transcript.append_point(b"T_6", &T_6);
 
-        let u = <Transcript as TranscriptProtocol<G>>::challenge_scalar(transcript, b"u");
-        let x = <Transcript as TranscriptProtocol<G>>::challenge_scalar(transcript, b"x");
+        // The transcript is borrowed until the end of the proof, so the
+        // progress is reported through the field.
+        let mut report = |stage| {
+            if let Some(progress) = prover.progress.as_mut() {
+                progress(stage);
+            }
+        };
+        report(ProvingStage::PolynomialCommitments);
+
+        // Challenges combining the commitments of the randomization phases.
+        let mut u = Vec::with_capacity(phases.len());
+        for _ in 0..phases.len() {
+            u.push(TranscriptProtocol::<G>::challenge_scalar(transcript, b"u"));
+        }
+        // Challenge combining the vector commitments, if any.
+        let c = if S_C.is_none() {
+            G::ScalarField::one()
+        } else {
+            TranscriptProtocol::<G>::challenge_scalar(transcript, b"c")
+        };
+        let x = TranscriptProtocol::<G>::challenge_scalar(transcript, b"x");
 
         // t_2_blinding = <z*z^Q, W_V * v_blinding>
         // in the t_x_blinding calculations, line 76.
         let t_2_blinding: G::ScalarField = wV
             .iter()
-            .zip(self.secrets.v_blinding.iter())
+            .zip(prover.secrets.v_blinding.iter())
             .map(|(c, v_blinding)| *v_blinding * c)
             .sum();
 
-        let t_blinding_poly = util::Poly6::<G> {
+        let t_blinding_poly = util::Poly6::<G::ScalarField> {
             t1: t_1_blinding,
             t2: t_2_blinding,
             t3: t_3_blinding,
@@ -743,10 +1795,19 @@ impl<'g, G: AffineRepr, T: BorrowMut<Transcript>> Prover<'g, G, T> {
 
         let t_x = t_poly.eval(x);
         let t_x_blinding = t_blinding_poly.eval(x);
-        let mut l_vec = l_poly.eval(x);
+        let (mut l_vec, mut r_vec) = match polys {
+            Some((l_poly, r_poly)) => (l_poly.eval(x), r_poly.eval(x)),
+            None => {
+                let mut l_vec = Vec::with_capacity(padded_n);
+                let mut r_vec = Vec::with_capacity(padded_n);
+                for_each_chunk(&mut |l_poly, r_poly| {
+                    l_vec.append(&mut l_poly.eval(x));
+                    r_vec.append(&mut r_poly.eval(x));
+                });
+                (l_vec, r_vec)
+            }
+        };
         l_vec.append(&mut vec![G::ScalarField::zero(); pad]);
-
-        let mut r_vec = r_poly.eval(x);
         r_vec.append(&mut vec![G::ScalarField::zero(); pad]);
 
         // XXX this should refer to the notes to explain why this is correct
@@ -755,68 +1816,67 @@ impl<'g, G: AffineRepr, T: BorrowMut<Transcript>> Prover<'g, G, T> {
             exp_y = exp_y * y; // y^i -> y^(i+1)
         }
 
-        let i_blinding = i_blinding1 + u * i_blinding2;
-        let o_blinding = o_blinding1 + u * o_blinding2;
-        let s_blinding = s_blinding1 + u * s_blinding2;
+        let mut i_blinding = Zeroizing::new(first.i_blinding);
+        let mut o_blinding = Zeroizing::new(first.o_blinding);
+        let mut s_blinding = Zeroizing::new(first.s_blinding);
+        for (phase, u_k) in phases.iter().zip(u.iter()) {
+            *i_blinding += *u_k * phase.i_blinding;
+            *o_blinding += *u_k * phase.o_blinding;
+            *s_blinding += *u_k * phase.s_blinding;
+        }
+        let vector_blinding: Zeroizing<G::ScalarField> =
+            Zeroizing::new(prover.secrets.vector_blinding.iter().sum());
+        *i_blinding += c * *vector_blinding;
+        *s_blinding += c * *s_blinding_C;
+
+        let e_blinding = x * (*i_blinding + x * (*o_blinding + x * *s_blinding));
 
-        let e_blinding = x * (i_blinding + x * (o_blinding + x * s_blinding));
+        TranscriptProtocol::<G>::append_scalar(transcript, b"t_x", &t_x);
+        TranscriptProtocol::<G>::append_scalar(transcript, b"t_x_blinding", &t_x_blinding);
+        TranscriptProtocol::<G>::append_scalar(transcript, b"e_blinding", &e_blinding);
 
-        <Transcript as TranscriptProtocol<G>>::append_scalar(transcript, b"t_x", &t_x);
-        <Transcript as TranscriptProtocol<G>>::append_scalar(
-            transcript,
-            b"t_x_blinding",
-            &t_x_blinding,
-        );
-        <Transcript as TranscriptProtocol<G>>::append_scalar(
-            transcript,
-            b"e_blinding",
-            &e_blinding,
-        );
+        check_cancelled(&prover.cancel)?;
 
         // Get a challenge value to combine statements for the IPP
-        let w: G::ScalarField =
-            <Transcript as TranscriptProtocol<G>>::challenge_scalar(transcript, b"w");
-        let Q = self.pc_gens.B.mul_bigint(w.into_bigint());
+        let w: G::ScalarField = TranscriptProtocol::<G>::challenge_scalar(transcript, b"w");
+        let Q = prover.pc_gens.B.mul_bigint(w.into_bigint());
 
-        let G_factors = iter::repeat(G::ScalarField::one())
-            .take(n1)
-            .chain(iter::repeat(u).take(n2 + pad))
-            .collect::<Vec<_>>();
+        let G_factors = phase_factors(&phase_ends, &u, (L, c), pad);
         let H_factors = exp_y_inv
             .into_iter()
             .zip(G_factors.iter())
             .map(|(y, u_or_1)| y * u_or_1)
             .collect::<Vec<_>>();
 
-        let ipp_proof = InnerProductProof::create(
-            transcript,
-            &Q.into_affine(),
-            &G_factors,
-            &H_factors,
-            gens.G(padded_n).cloned().collect(),
-            gens.H(padded_n).cloned().collect(),
-            l_vec,
-            r_vec,
-        );
+        let ipp_proof = metrics.time(Step::Ipp, || {
+            InnerProductProof::create(
+                transcript,
+                &Q.into_affine(),
+                &G_factors,
+                &H_factors,
+                gens.G(padded_n).cloned().collect(),
+                gens.H(padded_n).cloned().collect(),
+                l_vec,
+                r_vec,
+                bp_gens.msm_backend(),
+                &mut |round, rounds| report(ProvingStage::InnerProductRound { round, rounds }),
+            )
+        });
+        report(ProvingStage::Done);
 
-        // We do not yet have a ClearOnDrop wrapper for Vec<Fr>.
-        // When PR 202 [1] is merged, we can simply wrap s_L and s_R at the point of creation.
-        // [1] https://github.com/dalek-cryptography/curve25519-dalek/pull/202
-        for scalar in s_L1
-            .iter_mut()
-            .chain(s_L2.iter_mut())
-            .chain(s_R1.iter_mut())
-            .chain(s_R2.iter_mut())
-        {
-            scalar.clear();
-        }
+        let extra_phases = phases[1..]
+            .iter()
+            .map(|phase| (phase.A_I, phase.A_O, phase.S))
+            .collect();
         let proof = R1CSProof {
-            A_I1,
-            A_O1,
-            S1,
-            A_I2,
-            A_O2,
-            S2,
+            A_I1: first.A_I,
+            A_O1: first.A_O,
+            S1: first.S,
+            S_C,
+            A_I2: phases[0].A_I,
+            A_O2: phases[0].A_O,
+            S2: phases[0].S,
+            extra_phases,
             T_1,
             T_3,
             T_4,
@@ -827,6 +1887,7 @@ impl<'g, G: AffineRepr, T: BorrowMut<Transcript>> Prover<'g, G, T> {
             e_blinding,
             ipp_proof,
         };
-        Ok((proof, self.transcript))
+        metrics.count(0, proof_points(&proof, &[], &[]).count());
+        Ok((proof, prover.transcript))
     }
 }
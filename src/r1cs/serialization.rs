@@ -0,0 +1,136 @@
+//! Serde representations of circuits and of their assignments, so that
+//! circuits can be generated by external tooling, stored as e.g. JSON,
+//! and replayed into a prover or a verifier.
+//!
+//! Field elements are represented as the decimal strings of their
+//! canonical integers, as in the JSON files of circom and snarkjs.
+//! Variables are represented as `{"Committed": 0}`, `{"MultiplierLeft": 1}`,
+//! `"One"` and so on, and a linear combination as the sequence of its
+//! terms `[variable, coefficient]`.
+
+use ark_ff::PrimeField;
+use ark_std::{str::FromStr, string::String, vec::Vec};
+use num_bigint::BigUint;
+use serde::de::{Deserializer, Error as _};
+use serde::ser::{Error as _, Serializer};
+use serde::{Deserialize, Serialize};
+
+use super::{LinearCombination, Variable};
+
+/// The values of the variables of a
+/// [`CircuitDescription`](::r1cs::CircuitDescription), which the prover
+/// needs to replay it.
+#[derive(Clone, Debug, PartialEq, Eq, serde_derive::Serialize, serde_derive::Deserialize)]
+#[serde(bound = "")]
+pub struct CircuitAssignment<F: PrimeField> {
+    /// The values of the high-level variables, in the order they were
+    /// declared, to be committed by the prover.
+    #[serde(with = "scalars")]
+    pub inputs: Vec<F>,
+    /// The values of the allocated variables, as passed to
+    /// [`CircuitDescription::replay`](::r1cs::CircuitDescription::replay).
+    #[serde(with = "scalars")]
+    pub witness: Vec<F>,
+}
+
+/// A field element, represented as the decimal string of its canonical
+/// integer.
+struct Scalar<F>(F);
+
+impl<F: PrimeField> Serialize for Scalar<F> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let n: BigUint = self.0.into();
+        serializer.collect_str(&n)
+    }
+}
+
+impl<'de, F: PrimeField> Deserialize<'de> for Scalar<F> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        // Only canonical integers: digits, without signs or separators,
+        // less than the modulus.
+        if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(D::Error::custom("a field element is not a decimal integer"));
+        }
+        let n = BigUint::from_str(&s).map_err(D::Error::custom)?;
+        if n >= F::MODULUS.into() {
+            return Err(D::Error::custom("a field element is not reduced"));
+        }
+        Ok(Scalar(F::from(n)))
+    }
+}
+
+/// Serde functions for vectors of field elements.
+mod scalars {
+    use super::*;
+
+    pub fn serialize<F: PrimeField, S: Serializer>(
+        values: &[F],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(values.iter().map(|value| Scalar(*value)))
+    }
+
+    pub fn deserialize<'de, F: PrimeField, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<F>, D::Error> {
+        let values = Vec::<Scalar<F>>::deserialize(deserializer)?;
+        Ok(values.into_iter().map(|value| value.0).collect())
+    }
+}
+
+/// The representation of a [`Variable`], without the phantom variant.
+#[derive(serde_derive::Serialize, serde_derive::Deserialize)]
+#[serde(rename = "Variable")]
+enum VariableRepr {
+    Committed(usize),
+    MultiplierLeft(usize),
+    MultiplierRight(usize),
+    MultiplierOutput(usize),
+    One,
+    Public(usize),
+}
+
+impl<F: PrimeField> Serialize for Variable<F> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let repr = match *self {
+            Variable::Committed(i) => VariableRepr::Committed(i),
+            Variable::MultiplierLeft(i) => VariableRepr::MultiplierLeft(i),
+            Variable::MultiplierRight(i) => VariableRepr::MultiplierRight(i),
+            Variable::MultiplierOutput(i) => VariableRepr::MultiplierOutput(i),
+            Variable::One() => VariableRepr::One,
+            Variable::Public(i) => VariableRepr::Public(i),
+            Variable::Phantom(_) => return Err(S::Error::custom("phantom variable")),
+        };
+        repr.serialize(serializer)
+    }
+}
+
+impl<'de, F: PrimeField> Deserialize<'de> for Variable<F> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match VariableRepr::deserialize(deserializer)? {
+            VariableRepr::Committed(i) => Variable::Committed(i),
+            VariableRepr::MultiplierLeft(i) => Variable::MultiplierLeft(i),
+            VariableRepr::MultiplierRight(i) => Variable::MultiplierRight(i),
+            VariableRepr::MultiplierOutput(i) => Variable::MultiplierOutput(i),
+            VariableRepr::One => Variable::One(),
+            VariableRepr::Public(i) => Variable::Public(i),
+        })
+    }
+}
+
+impl<F: PrimeField> Serialize for LinearCombination<F> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.terms.iter().map(|(var, coeff)| (var, Scalar(*coeff))))
+    }
+}
+
+impl<'de, F: PrimeField> Deserialize<'de> for LinearCombination<F> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let terms = Vec::<(Variable<F>, Scalar<F>)>::deserialize(deserializer)?;
+        Ok(terms
+            .into_iter()
+            .map(|(var, coeff)| (var, coeff.0))
+            .collect())
+    }
+}
@@ -0,0 +1,285 @@
+//! Proofs of solvency for custodians such as exchanges.
+//!
+//! The custodian commits to the balance of every user with a Pedersen
+//! commitment, and publishes a [`LiabilitiesTree`]: a Merkle sum tree
+//! whose every node holds a hash of its children and the sum of their
+//! commitments, so that the root commits to the total liabilities.
+//!
+//! - [`prove_solvency`] proves to an auditor that every committed
+//!   balance is in range, so that no negative balance offsets the total,
+//!   and that the total liabilities do not exceed the assets.
+//! - [`InclusionProof::verify`] lets every user check that their own
+//!   balance is included in the tree, given its opening.
+//!
+//! Balances and the identities of the users stay hidden: the leaves only
+//! reveal a hash of the user identifier salted with the blinding factor
+//! of the commitment.
+
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::PrimeField;
+use ark_serialize::CanonicalSerialize;
+use ark_std::{string::ToString, vec, vec::Vec};
+use digest::Digest;
+use merlin::Transcript;
+use rand_core::{CryptoRng, RngCore};
+use sha3::Sha3_256;
+
+use super::gadgets::{AllocatedScalar, BitDecomposition, Gadget};
+use super::{ConstraintSystem, LinearCombination, Prover, R1CSError, R1CSProof, Verifier};
+use crate::{BulletproofGens, PedersenGens};
+
+/// A node of a [`LiabilitiesTree`]: a hash, and a commitment to the sum
+/// of the balances below the node.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LiabilitiesNode<G: AffineRepr> {
+    /// The hash of the node.
+    pub hash: [u8; 32],
+    /// The commitment to the sum of the balances below the node.
+    pub commitment: G,
+}
+
+/// A Merkle sum tree of commitments to the balances of the users.
+///
+/// The tree is padded with empty leaves, committing to zero with a zero
+/// blinding factor, to a power of two leaves.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LiabilitiesTree<G: AffineRepr> {
+    /// The levels of the tree, from the leaves to the root.
+    levels: Vec<Vec<LiabilitiesNode<G>>>,
+    /// The number of leaves before padding.
+    len: usize,
+}
+
+/// A proof that a leaf is included in a [`LiabilitiesTree`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InclusionProof<G: AffineRepr> {
+    /// The index of the leaf.
+    pub index: usize,
+    /// The siblings of the path from the leaf to the root.
+    pub siblings: Vec<LiabilitiesNode<G>>,
+}
+
+impl<G: AffineRepr> LiabilitiesNode<G> {
+    /// Returns the leaf of a user with the given identifier, and the
+    /// opening of the commitment to their balance.
+    pub fn leaf(
+        pc_gens: &PedersenGens<G>,
+        user: &[u8],
+        balance: u64,
+        blinding: G::ScalarField,
+    ) -> Self {
+        let commitment = pc_gens.commit(G::ScalarField::from(balance), blinding);
+        let mut hash = Sha3_256::new();
+        Digest::update(&mut hash, b"solvency leaf");
+        Digest::update(&mut hash, (user.len() as u64).to_le_bytes());
+        Digest::update(&mut hash, user);
+        Digest::update(&mut hash, serialize(&blinding));
+        Digest::update(&mut hash, serialize(&commitment));
+        LiabilitiesNode {
+            hash: hash.finalize().into(),
+            commitment,
+        }
+    }
+
+    fn empty() -> Self {
+        LiabilitiesNode {
+            hash: [0u8; 32],
+            commitment: G::zero(),
+        }
+    }
+
+    fn parent(left: &Self, right: &Self) -> Self {
+        let mut hash = Sha3_256::new();
+        Digest::update(&mut hash, b"solvency node");
+        for child in [left, right] {
+            Digest::update(&mut hash, child.hash);
+            Digest::update(&mut hash, serialize(&child.commitment));
+        }
+        LiabilitiesNode {
+            hash: hash.finalize().into(),
+            commitment: (left.commitment + right.commitment).into_affine(),
+        }
+    }
+}
+
+impl<G: AffineRepr> LiabilitiesTree<G> {
+    /// Builds the tree from its `leaves`.
+    pub fn new(leaves: Vec<LiabilitiesNode<G>>) -> Self {
+        let len = leaves.len();
+        let mut level = leaves;
+        level.resize(len.next_power_of_two(), LiabilitiesNode::empty());
+
+        let mut levels = vec![level];
+        while levels[levels.len() - 1].len() > 1 {
+            let next = levels[levels.len() - 1]
+                .chunks(2)
+                .map(|pair| LiabilitiesNode::parent(&pair[0], &pair[1]))
+                .collect();
+            levels.push(next);
+        }
+        LiabilitiesTree { levels, len }
+    }
+
+    /// Returns the root of the tree, which commits to the total
+    /// liabilities.
+    pub fn root(&self) -> LiabilitiesNode<G> {
+        self.levels[self.levels.len() - 1][0]
+    }
+
+    /// Returns the leaves of the tree, without padding.
+    pub fn leaves(&self) -> &[LiabilitiesNode<G>] {
+        &self.levels[0][..self.len]
+    }
+
+    /// Returns the proof of inclusion of the leaf at `index`.
+    pub fn inclusion_proof(&self, index: usize) -> Option<InclusionProof<G>> {
+        if index >= self.len {
+            return None;
+        }
+        let siblings = self.levels[..self.levels.len() - 1]
+            .iter()
+            .enumerate()
+            .map(|(depth, level)| level[(index >> depth) ^ 1])
+            .collect();
+        Some(InclusionProof { index, siblings })
+    }
+}
+
+impl<G: AffineRepr> InclusionProof<G> {
+    /// Verifies that the leaf of the user with the given identifier and
+    /// balance opening is included in the tree with the given `root`.
+    pub fn verify(
+        &self,
+        pc_gens: &PedersenGens<G>,
+        user: &[u8],
+        balance: u64,
+        blinding: G::ScalarField,
+        root: &LiabilitiesNode<G>,
+    ) -> Result<(), R1CSError> {
+        let mut node = LiabilitiesNode::leaf(pc_gens, user, balance, blinding);
+        for (depth, sibling) in self.siblings.iter().enumerate() {
+            node = match (self.index >> depth) & 1 {
+                0 => LiabilitiesNode::parent(&node, sibling),
+                _ => LiabilitiesNode::parent(sibling, &node),
+            };
+        }
+        if node != *root {
+            return Err(R1CSError::VerificationError);
+        }
+        Ok(())
+    }
+}
+
+/// Proves that every balance of the `liabilities` is in the range
+/// `[0, 2^n)`, and that their sum is at most `assets`.
+///
+/// The `liabilities` are given as the identifier of every user, their
+/// balance and the blinding factor of its commitment.  Returns the proof
+/// and the liabilities tree, using `(m + 1) * n + ceil(log2(m))`
+/// multipliers for `m` users.
+pub fn prove_solvency<G: AffineRepr, R: RngCore + CryptoRng>(
+    pc_gens: &PedersenGens<G>,
+    bp_gens: &BulletproofGens<G>,
+    transcript: &mut Transcript,
+    liabilities: &[(&[u8], u64, G::ScalarField)],
+    assets: u64,
+    n: usize,
+    rng: &mut R,
+) -> Result<(R1CSProof<G>, LiabilitiesTree<G>), R1CSError> {
+    let tree = LiabilitiesTree::new(
+        liabilities
+            .iter()
+            .map(|(user, balance, blinding)| {
+                LiabilitiesNode::leaf(pc_gens, user, *balance, *blinding)
+            })
+            .collect(),
+    );
+
+    solvency_domain_sep(transcript, &tree, assets, n);
+    let mut prover = Prover::new(pc_gens, transcript);
+    let balances = liabilities
+        .iter()
+        .map(|(_, balance, blinding)| {
+            let value = G::ScalarField::from(*balance);
+            let (_, variable) = prover.commit(value, *blinding);
+            AllocatedScalar::new(variable, Some(value))
+        })
+        .collect::<Vec<_>>();
+    solvency_gadget(&mut prover, &balances, assets, n)?;
+
+    Ok((prover.prove(rng, bp_gens)?, tree))
+}
+
+/// Verifies a proof created by [`prove_solvency`] against the published
+/// liabilities tree.
+pub fn verify_solvency<G: AffineRepr>(
+    pc_gens: &PedersenGens<G>,
+    bp_gens: &BulletproofGens<G>,
+    transcript: &mut Transcript,
+    proof: &R1CSProof<G>,
+    tree: &LiabilitiesTree<G>,
+    assets: u64,
+    n: usize,
+) -> Result<(), R1CSError> {
+    // Recompute the tree from its leaves, so its sums are consistent.
+    if LiabilitiesTree::new(tree.leaves().to_vec()) != *tree {
+        return Err(R1CSError::VerificationError);
+    }
+
+    solvency_domain_sep(transcript, tree, assets, n);
+    let mut verifier = Verifier::new(transcript);
+    let balances = tree
+        .leaves()
+        .iter()
+        .map(|leaf| AllocatedScalar::new(verifier.commit(leaf.commitment), None))
+        .collect::<Vec<_>>();
+    solvency_gadget(&mut verifier, &balances, assets, n)?;
+
+    verifier.verify(proof, pc_gens, bp_gens)
+}
+
+fn solvency_domain_sep<G: AffineRepr>(
+    transcript: &mut Transcript,
+    tree: &LiabilitiesTree<G>,
+    assets: u64,
+    n: usize,
+) {
+    transcript.append_message(b"dom-sep", b"solvency");
+    transcript.append_message(b"root", &tree.root().hash);
+    transcript.append_u64(b"assets", assets);
+    transcript.append_u64(b"n", n as u64);
+}
+
+fn solvency_gadget<F: PrimeField, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    balances: &[AllocatedScalar<F>],
+    assets: u64,
+    n: usize,
+) -> Result<(), R1CSError> {
+    let total_bits = n + (usize::BITS - balances.len().leading_zeros()) as usize;
+    if total_bits + 1 >= F::MODULUS_BIT_SIZE as usize {
+        return Err(R1CSError::GadgetError {
+            description: "solvency bit width exceeds the field capacity".to_string(),
+        });
+    }
+
+    let mut total = LinearCombination::default();
+    let mut total_value = Some(F::zero());
+    for balance in balances {
+        BitDecomposition::new(n).synthesize(cs, *balance)?;
+        total = total + balance.variable;
+        total_value = total_value.zip(balance.assignment).map(|(t, b)| t + b);
+    }
+
+    // Enforce assets - total >= 0
+    let surplus = AllocatedScalar::allocate(cs, total_value.map(|t| F::from(assets) - t))?;
+    cs.constrain(total + surplus.variable - F::from(assets));
+    BitDecomposition::new(total_bits).synthesize(cs, surplus)?;
+    Ok(())
+}
+
+fn serialize<T: CanonicalSerialize>(x: &T) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    x.serialize_compressed(&mut bytes).unwrap();
+    bytes
+}
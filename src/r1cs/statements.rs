@@ -0,0 +1,350 @@
+//! One-call proofs of common statements about Pedersen commitments.
+//!
+//! Every function of this module builds a small circuit over the
+//! commitments internally, so that the most common statements can be
+//! proven without writing any constraint.  Each `prove_*` function
+//! returns the proof together with the commitments to the values, and
+//! the matching `verify_*` function checks the proof against those
+//! commitments.  The prover and the verifier must use transcripts in
+//! the same state, and generators with enough capacity for the
+//! statement, which is documented on each function.
+
+use ark_ec::AffineRepr;
+use ark_ff::PrimeField;
+use ark_std::vec::Vec;
+use merlin::Transcript;
+use rand_core::{CryptoRng, RngCore};
+
+use super::gadgets::{
+    enforce_equal, AllocatedScalar, BalanceConservation, BitDecomposition, Compare, Comparison,
+    Gadget,
+};
+use super::{ConstraintSystem, LinearCombination, Prover, R1CSError, R1CSProof, Verifier};
+use crate::{BulletproofGens, PedersenGens};
+
+/// Proves that the committed `value` is in the range `[0, 2^n)`, using
+/// `n` multipliers.
+///
+/// Returns the proof and the commitment to `value` with `blinding`.
+pub fn prove_in_range<G: AffineRepr, R: RngCore + CryptoRng>(
+    pc_gens: &PedersenGens<G>,
+    bp_gens: &BulletproofGens<G>,
+    transcript: &mut Transcript,
+    value: G::ScalarField,
+    blinding: G::ScalarField,
+    n: usize,
+    rng: &mut R,
+) -> Result<(R1CSProof<G>, G), R1CSError> {
+    let (proof, commitments) = prove(
+        pc_gens,
+        bp_gens,
+        transcript,
+        &InRange { n },
+        &[(value, blinding)],
+        rng,
+    )?;
+    Ok((proof, commitments[0]))
+}
+
+/// Verifies a proof created by [`prove_in_range`].
+pub fn verify_in_range<G: AffineRepr>(
+    pc_gens: &PedersenGens<G>,
+    bp_gens: &BulletproofGens<G>,
+    transcript: &mut Transcript,
+    proof: &R1CSProof<G>,
+    commitment: &G,
+    n: usize,
+) -> Result<(), R1CSError> {
+    verify(
+        pc_gens,
+        bp_gens,
+        transcript,
+        &InRange { n },
+        proof,
+        &[*commitment],
+    )
+}
+
+/// Proves that the committed `a` is greater than the committed `b`, and
+/// that both are in the range `[0, 2^n)`, using `3 * n` multipliers.
+///
+/// Returns the proof and the commitments to `a` and `b`.
+pub fn prove_greater_than<G: AffineRepr, R: RngCore + CryptoRng>(
+    pc_gens: &PedersenGens<G>,
+    bp_gens: &BulletproofGens<G>,
+    transcript: &mut Transcript,
+    (a, a_blinding): (G::ScalarField, G::ScalarField),
+    (b, b_blinding): (G::ScalarField, G::ScalarField),
+    n: usize,
+    rng: &mut R,
+) -> Result<(R1CSProof<G>, (G, G)), R1CSError> {
+    let (proof, commitments) = prove(
+        pc_gens,
+        bp_gens,
+        transcript,
+        &GreaterThan { n },
+        &[(a, a_blinding), (b, b_blinding)],
+        rng,
+    )?;
+    Ok((proof, (commitments[0], commitments[1])))
+}
+
+/// Verifies a proof created by [`prove_greater_than`].
+pub fn verify_greater_than<G: AffineRepr>(
+    pc_gens: &PedersenGens<G>,
+    bp_gens: &BulletproofGens<G>,
+    transcript: &mut Transcript,
+    proof: &R1CSProof<G>,
+    (a, b): (&G, &G),
+    n: usize,
+) -> Result<(), R1CSError> {
+    verify(
+        pc_gens,
+        bp_gens,
+        transcript,
+        &GreaterThan { n },
+        proof,
+        &[*a, *b],
+    )
+}
+
+/// Proves that two commitments with different blindings commit to the
+/// same `value`, without any multiplier.
+///
+/// Returns the proof and the two commitments.
+pub fn prove_equal<G: AffineRepr, R: RngCore + CryptoRng>(
+    pc_gens: &PedersenGens<G>,
+    bp_gens: &BulletproofGens<G>,
+    transcript: &mut Transcript,
+    value: G::ScalarField,
+    (a_blinding, b_blinding): (G::ScalarField, G::ScalarField),
+    rng: &mut R,
+) -> Result<(R1CSProof<G>, (G, G)), R1CSError> {
+    let (proof, commitments) = prove(
+        pc_gens,
+        bp_gens,
+        transcript,
+        &Equal,
+        &[(value, a_blinding), (value, b_blinding)],
+        rng,
+    )?;
+    Ok((proof, (commitments[0], commitments[1])))
+}
+
+/// Verifies a proof created by [`prove_equal`].
+pub fn verify_equal<G: AffineRepr>(
+    pc_gens: &PedersenGens<G>,
+    bp_gens: &BulletproofGens<G>,
+    transcript: &mut Transcript,
+    proof: &R1CSProof<G>,
+    (a, b): (&G, &G),
+) -> Result<(), R1CSError> {
+    verify(pc_gens, bp_gens, transcript, &Equal, proof, &[*a, *b])
+}
+
+/// Proves that the committed amounts of a confidential transaction
+/// balance, that is `sum(inputs) = sum(outputs) + fee` for a public
+/// `fee`, and that every output is in the range `[0, 2^n)`, using `n`
+/// multipliers per output.
+///
+/// The `inputs` and `outputs` are given as the amounts and their
+/// blindings.  Returns the proof and the commitments to the inputs and
+/// to the outputs.
+#[allow(clippy::type_complexity)]
+pub fn prove_balance<G: AffineRepr, R: RngCore + CryptoRng>(
+    pc_gens: &PedersenGens<G>,
+    bp_gens: &BulletproofGens<G>,
+    transcript: &mut Transcript,
+    (inputs, outputs): (
+        &[(G::ScalarField, G::ScalarField)],
+        &[(G::ScalarField, G::ScalarField)],
+    ),
+    fee: u64,
+    n: usize,
+    rng: &mut R,
+) -> Result<(R1CSProof<G>, (Vec<G>, Vec<G>)), R1CSError> {
+    let statement = Balance {
+        inputs: inputs.len(),
+        fee,
+        n,
+    };
+    let openings = [inputs, outputs].concat();
+    let (proof, mut commitments) = prove(pc_gens, bp_gens, transcript, &statement, &openings, rng)?;
+    let output_commitments = commitments.split_off(inputs.len());
+    Ok((proof, (commitments, output_commitments)))
+}
+
+/// Verifies a proof created by [`prove_balance`].
+pub fn verify_balance<G: AffineRepr>(
+    pc_gens: &PedersenGens<G>,
+    bp_gens: &BulletproofGens<G>,
+    transcript: &mut Transcript,
+    proof: &R1CSProof<G>,
+    (inputs, outputs): (&[G], &[G]),
+    fee: u64,
+    n: usize,
+) -> Result<(), R1CSError> {
+    let statement = Balance {
+        inputs: inputs.len(),
+        fee,
+        n,
+    };
+    let commitments = [inputs, outputs].concat();
+    verify(
+        pc_gens,
+        bp_gens,
+        transcript,
+        &statement,
+        proof,
+        &commitments,
+    )
+}
+
+/// A statement about committed values.
+trait Statement<F: PrimeField>: Gadget<F, Input = Vec<AllocatedScalar<F>>, Output = ()> {
+    /// Appends the statement and its parameters to the transcript.
+    fn domain_sep(&self, transcript: &mut Transcript);
+}
+
+struct InRange {
+    n: usize,
+}
+
+impl<F: PrimeField> Gadget<F> for InRange {
+    type Input = Vec<AllocatedScalar<F>>;
+    type Output = ();
+
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        inputs: Self::Input,
+    ) -> Result<(), R1CSError> {
+        BitDecomposition::new(self.n).synthesize(cs, inputs[0])?;
+        Ok(())
+    }
+}
+
+impl<F: PrimeField> Statement<F> for InRange {
+    fn domain_sep(&self, transcript: &mut Transcript) {
+        transcript.append_message(b"dom-sep", b"statement in-range");
+        transcript.append_u64(b"n", self.n as u64);
+    }
+}
+
+struct GreaterThan {
+    n: usize,
+}
+
+impl<F: PrimeField> Gadget<F> for GreaterThan {
+    type Input = Vec<AllocatedScalar<F>>;
+    type Output = ();
+
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        inputs: Self::Input,
+    ) -> Result<(), R1CSError> {
+        for x in &inputs {
+            BitDecomposition::new(self.n).synthesize(cs, *x)?;
+        }
+        Compare::new(Comparison::GreaterThan, self.n).enforce(cs, inputs[0], inputs[1])
+    }
+}
+
+impl<F: PrimeField> Statement<F> for GreaterThan {
+    fn domain_sep(&self, transcript: &mut Transcript) {
+        transcript.append_message(b"dom-sep", b"statement greater-than");
+        transcript.append_u64(b"n", self.n as u64);
+    }
+}
+
+struct Equal;
+
+impl<F: PrimeField> Gadget<F> for Equal {
+    type Input = Vec<AllocatedScalar<F>>;
+    type Output = ();
+
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        inputs: Self::Input,
+    ) -> Result<(), R1CSError> {
+        enforce_equal(cs, inputs[0].into(), inputs[1].into());
+        Ok(())
+    }
+}
+
+impl<F: PrimeField> Statement<F> for Equal {
+    fn domain_sep(&self, transcript: &mut Transcript) {
+        transcript.append_message(b"dom-sep", b"statement equal");
+    }
+}
+
+struct Balance {
+    inputs: usize,
+    fee: u64,
+    n: usize,
+}
+
+impl<F: PrimeField> Gadget<F> for Balance {
+    type Input = Vec<AllocatedScalar<F>>;
+    type Output = ();
+
+    fn synthesize<CS: ConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        mut inputs: Self::Input,
+    ) -> Result<(), R1CSError> {
+        let outputs = inputs.split_off(self.inputs);
+        let fee = LinearCombination::from(F::from(self.fee));
+        BalanceConservation::new(self.n).synthesize(cs, (inputs, outputs, fee))
+    }
+}
+
+impl<F: PrimeField> Statement<F> for Balance {
+    fn domain_sep(&self, transcript: &mut Transcript) {
+        transcript.append_message(b"dom-sep", b"statement balance");
+        transcript.append_u64(b"inputs", self.inputs as u64);
+        transcript.append_u64(b"fee", self.fee);
+        transcript.append_u64(b"n", self.n as u64);
+    }
+}
+
+fn prove<G: AffineRepr, S: Statement<G::ScalarField>, R: RngCore + CryptoRng>(
+    pc_gens: &PedersenGens<G>,
+    bp_gens: &BulletproofGens<G>,
+    transcript: &mut Transcript,
+    statement: &S,
+    openings: &[(G::ScalarField, G::ScalarField)],
+    rng: &mut R,
+) -> Result<(R1CSProof<G>, Vec<G>), R1CSError> {
+    statement.domain_sep(transcript);
+    let mut prover = Prover::new(pc_gens, transcript);
+    let (commitments, inputs) = openings
+        .iter()
+        .map(|(v, v_blinding)| {
+            let (commitment, variable) = prover.commit(*v, *v_blinding);
+            (commitment, AllocatedScalar::new(variable, Some(*v)))
+        })
+        .unzip();
+    statement.synthesize(&mut prover, inputs)?;
+    Ok((prover.prove(rng, bp_gens)?, commitments))
+}
+
+fn verify<G: AffineRepr, S: Statement<G::ScalarField>>(
+    pc_gens: &PedersenGens<G>,
+    bp_gens: &BulletproofGens<G>,
+    transcript: &mut Transcript,
+    statement: &S,
+    proof: &R1CSProof<G>,
+    commitments: &[G],
+) -> Result<(), R1CSError> {
+    statement.domain_sep(transcript);
+    let mut verifier = Verifier::new(transcript);
+    let inputs = commitments
+        .iter()
+        .map(|commitment| AllocatedScalar::new(verifier.commit(*commitment), None))
+        .collect();
+    statement.synthesize(&mut verifier, inputs)?;
+    verifier.verify(proof, pc_gens, bp_gens)
+}
@@ -0,0 +1,273 @@
+//! Utilities for testing gadgets without creating proofs.
+
+#![allow(non_snake_case)]
+
+use ark_ff::PrimeField;
+use ark_std::{string::ToString, vec::Vec};
+use merlin::Transcript;
+use rand_chacha::ChaChaRng;
+use rand_core::SeedableRng;
+
+use super::{
+    ConstraintSystem, LinearCombination, R1CSError, RandomizableConstraintSystem,
+    RandomizedConstraintSystem, Variable,
+};
+use crate::transcript::ByteTranscript;
+
+/// A [`ConstraintSystem`] which only tracks the witness and evaluates
+/// every constraint as soon as it is added.
+///
+/// Synthesizing a gadget into a `MockCS` checks whether its constraints
+/// are satisfied by the witness without creating and verifying a proof,
+/// which is much faster, and [`MockCS::first_unsatisfied`] tells which
+/// constraint fails instead of a bare
+/// [`VerificationError`](R1CSError::VerificationError).
+///
+/// Randomized constraints are added as soon as they are specified, with
+/// challenges drawn from the transcript of the `MockCS`.  They only
+/// depend on the values committed and allocated so far, so gadgets must
+/// not rely on this to bind them to values allocated later.
+pub struct MockCS<F: PrimeField> {
+    transcript: Transcript,
+    a_L: Vec<F>,
+    a_R: Vec<F>,
+    a_O: Vec<F>,
+    v: Vec<F>,
+    public: Vec<F>,
+    /// Number of multipliers whose left inputs are vector entries.
+    vector_len: usize,
+    /// Index of a pending multiplier that's not fully assigned yet.
+    pending_multiplier: Option<usize>,
+    /// The number of constraints added so far.
+    num_constraints: usize,
+    /// The unsatisfied constraints and the values of their linear
+    /// combinations.
+    unsatisfied: Vec<(usize, F)>,
+}
+
+impl<F: PrimeField> Default for MockCS<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: PrimeField> MockCS<F> {
+    /// Creates an empty constraint system.
+    pub fn new() -> Self {
+        MockCS {
+            transcript: Transcript::new(b"MockCS"),
+            a_L: Vec::new(),
+            a_R: Vec::new(),
+            a_O: Vec::new(),
+            v: Vec::new(),
+            public: Vec::new(),
+            vector_len: 0,
+            pending_multiplier: None,
+            num_constraints: 0,
+            unsatisfied: Vec::new(),
+        }
+    }
+
+    /// Adds a high-level variable with the value `v`, as
+    /// [`Prover::commit`](::r1cs::Prover::commit) would.
+    pub fn commit(&mut self, v: F) -> Variable<F> {
+        let i = self.v.len();
+        self.v.push(v);
+        Variable::Committed(i)
+    }
+
+    /// Adds the `values` of a vector commitment as the left inputs of the
+    /// next multipliers, as
+    /// [`Prover::commit_vec`](::r1cs::Prover::commit_vec) would.
+    pub fn commit_vec(&mut self, values: &[F]) -> Result<Vec<Variable<F>>, R1CSError> {
+        let start = self.a_L.len();
+        if start != self.vector_len {
+            return Err(R1CSError::GadgetError {
+                description: "vector commitments must precede all multipliers".to_string(),
+            });
+        }
+        let end = start + values.len();
+        self.vector_len = end;
+        self.a_L.extend_from_slice(values);
+        self.a_R.resize(end, F::zero());
+        self.a_O.resize(end, F::zero());
+        Ok((start..end).map(Variable::MultiplierLeft).collect())
+    }
+
+    /// Adds a public input with the value `v`, as
+    /// [`Prover::public_input`](::r1cs::Prover::public_input) would.
+    pub fn public_input(&mut self, v: F) -> Variable<F> {
+        let i = self.public.len();
+        self.public.push(v);
+        Variable::Public(i)
+    }
+
+    /// Returns the value of the linear combination `lc`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `lc` refers to a variable that does not exist.
+    pub fn eval(&self, lc: &LinearCombination<F>) -> F {
+        lc.terms
+            .iter()
+            .map(|(var, coeff)| {
+                *coeff
+                    * match var {
+                        Variable::MultiplierLeft(i) => self.a_L[*i],
+                        Variable::MultiplierRight(i) => self.a_R[*i],
+                        Variable::MultiplierOutput(i) => self.a_O[*i],
+                        Variable::Committed(i) => self.v[*i],
+                        Variable::One() => F::one(),
+                        Variable::Public(i) => self.public[*i],
+                        _ => F::zero(),
+                    }
+            })
+            .sum()
+    }
+
+    /// Returns the number of constraints added so far.
+    pub fn constraints_len(&self) -> usize {
+        self.num_constraints
+    }
+
+    /// Returns `true` if every constraint added so far is satisfied.
+    pub fn is_satisfied(&self) -> bool {
+        self.unsatisfied.is_empty()
+    }
+
+    /// Returns the index of the first unsatisfied constraint, if any, in
+    /// the order in which the constraints were added.
+    pub fn first_unsatisfied(&self) -> Option<usize> {
+        self.unsatisfied.first().map(|(index, _)| *index)
+    }
+
+    /// Returns the indices of the unsatisfied constraints, and the values
+    /// of their linear combinations.
+    pub fn unsatisfied(&self) -> &[(usize, F)] {
+        &self.unsatisfied
+    }
+
+    fn is_valid(&self, var: &Variable<F>) -> bool {
+        match var {
+            Variable::MultiplierLeft(i)
+            | Variable::MultiplierRight(i)
+            | Variable::MultiplierOutput(i) => *i < self.a_L.len(),
+            Variable::Committed(i) => *i < self.v.len(),
+            Variable::Public(i) => *i < self.public.len(),
+            _ => true,
+        }
+    }
+}
+
+impl<F: PrimeField> ConstraintSystem<F> for MockCS<F> {
+    fn transcript(&mut self) -> &mut dyn ByteTranscript {
+        &mut self.transcript
+    }
+
+    fn multiply(
+        &mut self,
+        mut left: LinearCombination<F>,
+        mut right: LinearCombination<F>,
+    ) -> (Variable<F>, Variable<F>, Variable<F>) {
+        let l = self.eval(&left);
+        let r = self.eval(&right);
+
+        let i = self.a_L.len();
+        self.a_L.push(l);
+        self.a_R.push(r);
+        self.a_O.push(l * r);
+
+        let (l_var, r_var, o_var) = (
+            Variable::MultiplierLeft(i),
+            Variable::MultiplierRight(i),
+            Variable::MultiplierOutput(i),
+        );
+        left.terms.push((l_var, -F::one()));
+        right.terms.push((r_var, -F::one()));
+        self.constrain(left);
+        self.constrain(right);
+
+        (l_var, r_var, o_var)
+    }
+
+    fn allocate(&mut self, assignment: Option<F>) -> Result<Variable<F>, R1CSError> {
+        let scalar = assignment.ok_or(R1CSError::MissingAssignment)?;
+
+        match self.pending_multiplier {
+            None => {
+                let i = self.a_L.len();
+                self.pending_multiplier = Some(i);
+                self.a_L.push(scalar);
+                self.a_R.push(F::zero());
+                self.a_O.push(F::zero());
+                Ok(Variable::MultiplierLeft(i))
+            }
+            Some(i) => {
+                self.pending_multiplier = None;
+                self.a_R[i] = scalar;
+                self.a_O[i] = self.a_L[i] * self.a_R[i];
+                Ok(Variable::MultiplierRight(i))
+            }
+        }
+    }
+
+    fn allocate_multiplier(
+        &mut self,
+        input_assignments: Option<(F, F)>,
+    ) -> Result<(Variable<F>, Variable<F>, Variable<F>), R1CSError> {
+        let (l, r) = input_assignments.ok_or(R1CSError::MissingAssignment)?;
+
+        let i = self.a_L.len();
+        self.a_L.push(l);
+        self.a_R.push(r);
+        self.a_O.push(l * r);
+
+        Ok((
+            Variable::MultiplierLeft(i),
+            Variable::MultiplierRight(i),
+            Variable::MultiplierOutput(i),
+        ))
+    }
+
+    fn multipliers_len(&self) -> usize {
+        self.a_L.len()
+    }
+
+    fn constrain(&mut self, lc: LinearCombination<F>) {
+        let index = self.num_constraints;
+        self.num_constraints += 1;
+        if !lc.terms.iter().all(|(var, _)| self.is_valid(var)) {
+            // Report constraints on variables that do not exist with a
+            // zero residual, as they cannot be evaluated.
+            self.unsatisfied.push((index, F::zero()));
+            return;
+        }
+        let residual = self.eval(&lc);
+        if !residual.is_zero() {
+            self.unsatisfied.push((index, residual));
+        }
+    }
+}
+
+impl<F: PrimeField> RandomizableConstraintSystem<F> for MockCS<F> {
+    type RandomizedCS = Self;
+
+    fn specify_randomized_constraints<FF>(&mut self, callback: FF) -> Result<(), R1CSError>
+    where
+        FF: 'static + FnOnce(&mut Self::RandomizedCS) -> Result<(), R1CSError>,
+    {
+        // Allocations made in the randomized phase start new multipliers.
+        self.pending_multiplier = None;
+        callback(self)
+    }
+}
+
+impl<F: PrimeField> RandomizedConstraintSystem<F> for MockCS<F> {
+    fn challenge_scalar(&mut self, label: &'static [u8]) -> F {
+        let mut buf = [0u8; 32];
+        self.transcript.challenge_bytes(label, &mut buf);
+
+        let mut prng = ChaChaRng::from_seed(buf);
+        F::rand(&mut prng)
+    }
+}
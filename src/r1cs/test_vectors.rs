@@ -0,0 +1,268 @@
+//! Golden test vectors, so that implementations of the protocol in other
+//! languages can cross-check their proofs against this crate.
+//!
+//! Each [`TestVector`] is a proof of one of a fixed set of
+//! [`VectorCircuit`]s, created deterministically from a seed: the same
+//! version of the crate always produces the same bytes.  A vector records
+//! the witness and blinding factors, the compressed commitments and the
+//! proof, as well as a digest of the final state of the transcript, i.e.
+//! 32 bytes of challenge with the label `b"test-vector digest"` drawn
+//! after proving, which matches the one drawn after verifying.
+//!
+//! The proofs use the transcript `Transcript::new(b"TestVectors")` and
+//! the generators `BulletproofGens::new(16, 1)`.
+
+use ark_ec::AffineRepr;
+use ark_ff::{PrimeField, UniformRand};
+use ark_serialize::CanonicalSerialize;
+use ark_std::{string::String, vec::Vec};
+use merlin::Transcript;
+use rand_chacha::ChaChaRng;
+use rand_core::SeedableRng;
+
+use super::gadgets::{enforce_permutation, AllocatedScalar, BitDecomposition, Gadget};
+use super::{
+    LinearCombination, Prover, R1CSError, R1CSProof, RandomizableConstraintSystem, Variable,
+    Verifier,
+};
+use crate::util::hex;
+use crate::{BulletproofGens, PedersenGens};
+
+/// The label of the transcripts of the test vectors.
+const TRANSCRIPT_LABEL: &[u8] = b"TestVectors";
+
+/// The capacity of the generators of the test vectors.
+const GENS_CAPACITY: usize = 16;
+
+/// The fixed circuits of the test vectors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VectorCircuit {
+    /// Proves that the third committed value is the product of the first
+    /// two, with a single multiplier.
+    Multiply,
+    /// Proves that the committed value is in the range `[0, 2^8)`.
+    Range,
+    /// Proves that the last three committed values are a permutation of
+    /// the first three, with randomized constraints.
+    Shuffle,
+}
+
+impl VectorCircuit {
+    /// All the circuits, in the order of [`generate_all`].
+    pub const ALL: [VectorCircuit; 3] = [
+        VectorCircuit::Multiply,
+        VectorCircuit::Range,
+        VectorCircuit::Shuffle,
+    ];
+
+    /// Returns the name of the circuit.
+    pub fn name(&self) -> &'static str {
+        match self {
+            VectorCircuit::Multiply => "multiply",
+            VectorCircuit::Range => "range",
+            VectorCircuit::Shuffle => "shuffle",
+        }
+    }
+
+    /// Returns the committed values of the test vector of the circuit.
+    pub fn values(&self) -> &'static [u64] {
+        match self {
+            VectorCircuit::Multiply => &[3, 4, 12],
+            VectorCircuit::Range => &[42],
+            VectorCircuit::Shuffle => &[7, 3, 5, 5, 7, 3],
+        }
+    }
+
+    /// Returns the seed of the test vector of the circuit: its name,
+    /// padded with zeros.
+    pub fn seed(&self) -> [u8; 32] {
+        let mut seed = [0u8; 32];
+        seed[..self.name().len()].copy_from_slice(self.name().as_bytes());
+        seed
+    }
+
+    /// Adds the constraints of the circuit over the committed `vars`,
+    /// whose `values` are known to the prover.
+    fn synthesize<F: PrimeField, CS: RandomizableConstraintSystem<F>>(
+        &self,
+        cs: &mut CS,
+        vars: Vec<Variable<F>>,
+        values: Option<&[u64]>,
+    ) -> Result<(), R1CSError> {
+        match self {
+            VectorCircuit::Multiply => {
+                let (_, _, o) = cs.multiply(vars[0].into(), vars[1].into());
+                cs.constrain(o - vars[2]);
+                Ok(())
+            }
+            VectorCircuit::Range => {
+                let v = AllocatedScalar::new(vars[0], values.map(|v| F::from(v[0])));
+                BitDecomposition::new(8).synthesize(cs, v)?;
+                Ok(())
+            }
+            VectorCircuit::Shuffle => {
+                let lcs: Vec<LinearCombination<F>> = vars.into_iter().map(Into::into).collect();
+                let (x, y) = lcs.split_at(3);
+                enforce_permutation(cs, x.to_vec(), y.to_vec())
+            }
+        }
+    }
+}
+
+/// A proof of a [`VectorCircuit`], with all the inputs from which it
+/// was created.
+///
+/// Scalars and points are in the compressed encodings of
+/// `ark-serialize`, i.e. 32-byte little-endian scalars.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestVector {
+    /// The circuit of the proof.
+    pub circuit: VectorCircuit,
+    /// The seed of the blinding factors and of the proof.
+    pub seed: [u8; 32],
+    /// The committed values.
+    pub values: Vec<u64>,
+    /// The blinding factors of the commitments.
+    pub blindings: Vec<Vec<u8>>,
+    /// The commitments to the values.
+    pub commitments: Vec<Vec<u8>>,
+    /// The proof, as serialized by [`R1CSProof::to_bytes`].
+    pub proof: Vec<u8>,
+    /// The 32-byte challenge drawn from the transcript after proving.
+    pub transcript_digest: [u8; 32],
+}
+
+impl TestVector {
+    /// Returns the vector as a JSON object with hexadecimal byte strings.
+    pub fn to_json(&self) -> String {
+        let list = |items: &[Vec<u8>]| {
+            let items: Vec<String> = items.iter().map(|i| format!("\"{}\"", hex(i))).collect();
+            format!("[{}]", items.join(", "))
+        };
+        let values: Vec<String> = self.values.iter().map(|v| v.to_string()).collect();
+        format!(
+            "{{\"circuit\": \"{}\", \"seed\": \"{}\", \"values\": [{}], \"blindings\": {}, \
+             \"commitments\": {}, \"proof\": \"{}\", \"transcript_digest\": \"{}\"}}",
+            self.circuit.name(),
+            hex(&self.seed),
+            values.join(", "),
+            list(&self.blindings),
+            list(&self.commitments),
+            hex(&self.proof),
+            hex(&self.transcript_digest),
+        )
+    }
+}
+
+/// Creates the test vector of `circuit`.
+///
+/// The blinding factors are drawn from a `ChaChaRng` seeded with
+/// [`VectorCircuit::seed`], and the proof is created with
+/// [`Prover::prove_deterministic`] from the same seed.
+pub fn generate<G: AffineRepr>(circuit: VectorCircuit) -> Result<TestVector, R1CSError> {
+    let pc_gens = PedersenGens::<G>::default();
+    let bp_gens = BulletproofGens::new(GENS_CAPACITY, 1);
+    let seed = circuit.seed();
+    let mut rng = ChaChaRng::from_seed(seed);
+    let values = circuit.values();
+    let blindings: Vec<G::ScalarField> = values
+        .iter()
+        .map(|_| G::ScalarField::rand(&mut rng))
+        .collect();
+
+    let mut transcript = Transcript::new(TRANSCRIPT_LABEL);
+    let mut prover = Prover::new(&pc_gens, &mut transcript);
+    let (commitments, vars): (Vec<G>, Vec<_>) = values
+        .iter()
+        .zip(&blindings)
+        .map(|(v, blinding)| prover.commit(G::ScalarField::from(*v), *blinding))
+        .unzip();
+    circuit.synthesize(&mut prover, vars, Some(values))?;
+    let proof = prover.prove_deterministic(seed, &bp_gens)?;
+
+    Ok(TestVector {
+        circuit,
+        seed,
+        values: values.to_vec(),
+        blindings: blindings.iter().map(to_bytes).collect(),
+        commitments: commitments.iter().map(to_bytes).collect(),
+        proof: proof.to_bytes()?,
+        transcript_digest: digest(&mut transcript),
+    })
+}
+
+/// Creates the test vectors of all the [`VectorCircuit`]s.
+pub fn generate_all<G: AffineRepr>() -> Result<Vec<TestVector>, R1CSError> {
+    VectorCircuit::ALL
+        .iter()
+        .map(|c| generate::<G>(*c))
+        .collect()
+}
+
+/// Verifies the proof of `vector`, and checks that the transcript of the
+/// verifier ends with the digest of the vector.
+pub fn check<G: AffineRepr>(vector: &TestVector) -> Result<(), R1CSError> {
+    let pc_gens = PedersenGens::<G>::default();
+    let bp_gens = BulletproofGens::new(GENS_CAPACITY, 1);
+    let proof = R1CSProof::<G>::from_bytes(&vector.proof)?;
+
+    let mut transcript = Transcript::new(TRANSCRIPT_LABEL);
+    let mut verifier = Verifier::new(&mut transcript);
+    let mut vars = Vec::with_capacity(vector.commitments.len());
+    for commitment in &vector.commitments {
+        let commitment = crate::point_from_bytes(commitment, Default::default())?;
+        vars.push(verifier.commit(commitment));
+    }
+    vector.circuit.synthesize(&mut verifier, vars, None)?;
+    verifier.verify(&proof, &pc_gens, &bp_gens)?;
+
+    if digest(&mut transcript) != vector.transcript_digest {
+        return Err(R1CSError::VerificationError);
+    }
+    Ok(())
+}
+
+/// Draws the digest of the final state of the `transcript`.
+fn digest(transcript: &mut Transcript) -> [u8; 32] {
+    let mut digest = [0u8; 32];
+    transcript.challenge_bytes(b"test-vector digest", &mut digest);
+    digest
+}
+
+fn to_bytes<T: CanonicalSerialize>(value: &T) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    value.serialize_compressed(&mut bytes).unwrap();
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type G = ark_secq256k1::Affine;
+
+    /// The transcript digests of the vectors over secq256k1, which change
+    /// with any change to the proofs.
+    const DIGESTS: [&str; 3] = [
+        "3976eca4a806ece21f72fafd5deb1e88e51770eb0baf96d62f1689945e6efbec",
+        "3ac5e73e47c13cff5ee67648163b53cb8bd781e7b14498312f1752d97ae346dd",
+        "ba5598aa1e0dd2d56eb28a287668c47484146e6f35292e6c92a91800ae7be537",
+    ];
+
+    #[test]
+    fn test_vectors_are_reproducible() {
+        let vectors = generate_all::<G>().unwrap();
+        assert_eq!(vectors, generate_all::<G>().unwrap());
+        for (vector, digest) in vectors.iter().zip(DIGESTS) {
+            assert_eq!(hex(&vector.transcript_digest), digest);
+        }
+        for vector in &vectors {
+            assert!(check::<G>(vector).is_ok());
+            assert!(vector.to_json().contains(&hex(&vector.proof)));
+
+            let mut tampered = vector.clone();
+            tampered.transcript_digest[0] ^= 1;
+            assert!(check::<G>(&tampered).is_err());
+        }
+    }
+}
@@ -1,26 +1,39 @@
 #![allow(non_snake_case)]
 
-use ark_ec::{AffineRepr, VariableBaseMSM};
-use ark_ff::{Field, UniformRand};
+extern crate alloc;
+
+use alloc::sync::Arc;
+use ark_ec::AffineRepr;
+use ark_ff::{Field, PrimeField, UniformRand};
 use ark_std::{
-    borrow::BorrowMut,
     boxed::Box,
     iter, mem,
     rand::{CryptoRng, RngCore},
+    string::ToString,
     vec,
     vec::Vec,
     One, Zero,
 };
-use merlin::Transcript;
-
+use ark_std::{cfg_into_iter, cfg_iter_mut};
+use core::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use super::circuit::circuit_digest;
+use super::export::{r1cs_bytes, Wires};
+#[cfg(feature = "metrics")]
+use super::metrics::PerfReport;
+use super::metrics::{Metrics, Step};
 use super::{
-    ConstraintSystem, LinearCombination, R1CSProof, RandomizableConstraintSystem,
-    RandomizedConstraintSystem, Variable,
+    ConstraintSystem, LinearCombination, PreparedCircuit, R1CSProof, RandomizableConstraintSystem,
+    RandomizedConstraintSystem, Variable, VerifierLimits,
 };
 
 use crate::errors::R1CSError;
-use crate::generators::{BulletproofGens, PedersenGens};
-use crate::transcript::TranscriptProtocol;
+use crate::generators::{BulletproofGens, FixedTerms, PedersenGens};
+use crate::msm::MsmBackend;
+use crate::transcript::{ByteTranscript, TranscriptProtocol};
+use crate::validation::{reject_identity, IdentityPolicy};
 
 /// A [`ConstraintSystem`] implementation for use by the verifier.
 ///
@@ -31,7 +44,7 @@ use crate::transcript::TranscriptProtocol;
 /// When all constraints are added, the verifying code calls `verify`
 /// which consumes the `Verifier` instance, samples random challenges
 /// that instantiate the randomized constraints, and verifies the proof.
-pub struct Verifier<G: AffineRepr, T: BorrowMut<Transcript>> {
+pub struct Verifier<G: AffineRepr, T: TranscriptProtocol<G>> {
     transcript: T,
     constraints: Vec<LinearCombination<G::ScalarField>>,
 
@@ -44,17 +57,39 @@ pub struct Verifier<G: AffineRepr, T: BorrowMut<Transcript>> {
     /// variable assignments.
     num_vars: usize,
     V: Vec<G>,
+    /// Vector commitments to the left inputs of the first multipliers.
+    C: Vec<G>,
+    /// Lengths of the vector commitments.
+    vector_lens: Vec<usize>,
+    /// Values of the public inputs.
+    public: Vec<G::ScalarField>,
 
     /// This list holds closures that will be called in the second phase of the protocol,
     /// when non-randomized variables are committed.
     /// After that, the option will flip to None and additional calls to `randomize_constraints`
     /// will invoke closures immediately.
-    deferred_constraints: Vec<Box<dyn Fn(&mut RandomizingVerifier<G, T>) -> Result<(), R1CSError>>>,
+    deferred_constraints:
+        Vec<Box<dyn FnOnce(&mut RandomizingVerifier<G, T>) -> Result<(), R1CSError>>>,
 
     /// Index of a pending multiplier that's not fully assigned yet.
     pending_multiplier: Option<usize>,
+
+    /// Flag aborting the verification when set.
+    cancel: Option<Arc<AtomicBool>>,
+
+    /// Commitments rejected when they are the identity.
+    identity_policy: IdentityPolicy,
+
+    /// Limits of the sizes of the proofs and of the circuit.
+    limits: VerifierLimits,
+
+    /// Timings and counts of the verification, if reported.
+    metrics: Metrics,
 }
 
+/// A randomized-phase callback borrowed for the duration of a verification.
+type ScopedCallback<'s, CS> = &'s mut dyn FnMut(&mut CS) -> Result<(), R1CSError>;
+
 /// Verifier in the randomizing phase.
 ///
 /// Note: this type is exported because it is used to specify the associated type
@@ -62,13 +97,128 @@ pub struct Verifier<G: AffineRepr, T: BorrowMut<Transcript>> {
 /// monomorphize the closures for the proving and verifying code.
 /// However, this type cannot be instantiated by the user and therefore can only be used within
 /// the callback provided to `specify_randomized_constraints`.
-pub struct RandomizingVerifier<G: AffineRepr, T: BorrowMut<Transcript>> {
+pub struct RandomizingVerifier<G: AffineRepr, T: TranscriptProtocol<G>> {
     verifier: Verifier<G, T>,
 }
 
-impl<T: BorrowMut<Transcript>, G: AffineRepr> ConstraintSystem<G::ScalarField> for Verifier<G, T> {
-    fn transcript(&mut self) -> &mut Transcript {
-        self.transcript.borrow_mut()
+/// Scalars of the single multiscalar multiplication which verifies an
+/// [`R1CSProof`], as returned by [`Verifier::verification_scalars`].
+///
+/// Each field is named after the point it multiplies:
+/// * `b` and `b_blinding` multiply `pc_gens.B` and `pc_gens.B_blinding`,
+/// * `g` and `h` multiply the first `g.len()` generators of
+///   `bp_gens.share(0).G` and `bp_gens.share(0).H`,
+/// * `A_I1`, ..., `T_6`, `L_vec` and `R_vec` multiply the proof elements
+///   of the same name,
+/// * `V` multiplies the high-level variable commitments, in the order
+///   they were committed to the verifier,
+/// * `C` multiplies the vector commitments, in the order they were
+///   committed to the verifier, and `S_C` the proof element of the same
+///   name, which is only present if there are vector commitments.
+///
+/// The proof is valid if and only if the sum of all these products is the identity.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VerificationScalars<F: PrimeField> {
+    /// Scalar for the Pedersen base `B`.
+    pub b: F,
+    /// Scalar for the Pedersen blinding base `B_blinding`.
+    pub b_blinding: F,
+    /// Scalars for the \\(\mathbf G\\) generators (`padded_n` of them).
+    pub g: Vec<F>,
+    /// Scalars for the \\(\mathbf H\\) generators (`padded_n` of them).
+    pub h: Vec<F>,
+    /// Scalar for `A_I1`.
+    pub A_I1: F,
+    /// Scalar for `A_O1`.
+    pub A_O1: F,
+    /// Scalar for `S1`.
+    pub S1: F,
+    /// Scalar for `A_I2`.
+    pub A_I2: F,
+    /// Scalar for `A_O2`.
+    pub A_O2: F,
+    /// Scalar for `S2`.
+    pub S2: F,
+    /// Scalars for the `(A_I, A_O, S)` commitments of every
+    /// randomization phase after the second one.
+    pub extra_phases: Vec<(F, F, F)>,
+    /// Scalars for the high-level variable commitments.
+    pub V: Vec<F>,
+    /// Scalars for the vector commitments.
+    pub C: Vec<F>,
+    /// Scalar for `S_C`, if there are vector commitments.
+    pub S_C: Option<F>,
+    /// Scalar for `T_1`.
+    pub T_1: F,
+    /// Scalar for `T_3`.
+    pub T_3: F,
+    /// Scalar for `T_4`.
+    pub T_4: F,
+    /// Scalar for `T_5`.
+    pub T_5: F,
+    /// Scalar for `T_6`.
+    pub T_6: F,
+    /// Scalars for the inner-product proof's `L_vec`.
+    pub L_vec: Vec<F>,
+    /// Scalars for the inner-product proof's `R_vec`.
+    pub R_vec: Vec<F>,
+}
+
+impl<F: PrimeField> VerificationScalars<F> {
+    /// Returns the number of generators \\(\mathbf G\\) (and \\(\mathbf H\\))
+    /// used by the proof, i.e. the padded number of multipliers.
+    pub fn padded_n(&self) -> usize {
+        self.g.len()
+    }
+
+    /// Returns all scalars in a single vector, in the order
+    /// `b, b_blinding, g, h, A_I1, A_O1, S1, A_I2, A_O2, S2, extra_phases,
+    /// V, C, S_C, T_1, T_3, T_4, T_5, T_6, L_vec, R_vec`.
+    pub fn to_vec(&self) -> Vec<F> {
+        iter::once(self.b)
+            .chain(iter::once(self.b_blinding))
+            .chain(self.g.iter().cloned())
+            .chain(self.h.iter().cloned())
+            .chain(self.proof_scalars())
+            .collect()
+    }
+
+    /// Returns the scalars of the Pedersen bases and of the generators,
+    /// laid out over the generators of `parties` parties.
+    pub(super) fn fixed_terms(&self, parties: usize) -> FixedTerms<'_, F> {
+        FixedTerms {
+            b: self.b,
+            b_blinding: self.b_blinding,
+            g: &self.g,
+            h: &self.h,
+            start: 0,
+            parties,
+        }
+    }
+
+    /// Returns the scalars of the points specific to the proof, i.e. all
+    /// scalars after those of the generators, in the order of
+    /// [`proof_points`].
+    pub(super) fn proof_scalars(&self) -> impl Iterator<Item = F> + '_ {
+        [self.A_I1, self.A_O1, self.S1, self.A_I2, self.A_O2, self.S2]
+            .into_iter()
+            .chain(
+                self.extra_phases
+                    .iter()
+                    .flat_map(|(A_I, A_O, S)| [*A_I, *A_O, *S]),
+            )
+            .chain(self.V.iter().cloned())
+            .chain(self.C.iter().cloned())
+            .chain(self.S_C)
+            .chain([self.T_1, self.T_3, self.T_4, self.T_5, self.T_6])
+            .chain(self.L_vec.iter().cloned())
+            .chain(self.R_vec.iter().cloned())
+    }
+}
+
+impl<T: TranscriptProtocol<G>, G: AffineRepr> ConstraintSystem<G::ScalarField> for Verifier<G, T> {
+    fn transcript(&mut self) -> &mut dyn ByteTranscript {
+        &mut self.transcript
     }
 
     fn multiply(
@@ -147,27 +297,34 @@ impl<T: BorrowMut<Transcript>, G: AffineRepr> ConstraintSystem<G::ScalarField> f
         // evals to 0 for prover, etc).
         self.constraints.push(lc);
     }
+
+    fn constrain_all<I>(&mut self, lcs: I)
+    where
+        I: IntoIterator<Item = LinearCombination<G::ScalarField>>,
+    {
+        self.constraints.extend(lcs);
+    }
 }
 
-impl<T: BorrowMut<Transcript>, G: AffineRepr> RandomizableConstraintSystem<G::ScalarField>
+impl<T: TranscriptProtocol<G>, G: AffineRepr> RandomizableConstraintSystem<G::ScalarField>
     for Verifier<G, T>
 {
     type RandomizedCS = RandomizingVerifier<G, T>;
 
     fn specify_randomized_constraints<F>(&mut self, callback: F) -> Result<(), R1CSError>
     where
-        F: 'static + Fn(&mut Self::RandomizedCS) -> Result<(), R1CSError>,
+        F: 'static + FnOnce(&mut Self::RandomizedCS) -> Result<(), R1CSError>,
     {
         self.deferred_constraints.push(Box::new(callback));
         Ok(())
     }
 }
 
-impl<T: BorrowMut<Transcript>, G: AffineRepr> ConstraintSystem<G::ScalarField>
+impl<T: TranscriptProtocol<G>, G: AffineRepr> ConstraintSystem<G::ScalarField>
     for RandomizingVerifier<G, T>
 {
-    fn transcript(&mut self) -> &mut Transcript {
-        self.verifier.transcript.borrow_mut()
+    fn transcript(&mut self) -> &mut dyn ByteTranscript {
+        &mut self.verifier.transcript
     }
 
     fn multiply(
@@ -210,27 +367,49 @@ impl<T: BorrowMut<Transcript>, G: AffineRepr> ConstraintSystem<G::ScalarField>
     fn constrain(&mut self, lc: LinearCombination<G::ScalarField>) {
         self.verifier.constrain(lc)
     }
+
+    fn constrain_all<I>(&mut self, lcs: I)
+    where
+        I: IntoIterator<Item = LinearCombination<G::ScalarField>>,
+    {
+        self.verifier.constrain_all(lcs)
+    }
+}
+
+/// Randomized constraints specified in a randomization phase are added
+/// in the next phase, after the multipliers of the current phase are
+/// committed.
+impl<T: TranscriptProtocol<G>, G: AffineRepr> RandomizableConstraintSystem<G::ScalarField>
+    for RandomizingVerifier<G, T>
+{
+    type RandomizedCS = Self;
+
+    fn specify_randomized_constraints<F>(&mut self, callback: F) -> Result<(), R1CSError>
+    where
+        F: 'static + FnOnce(&mut Self::RandomizedCS) -> Result<(), R1CSError>,
+    {
+        self.verifier.deferred_constraints.push(Box::new(callback));
+        Ok(())
+    }
 }
 
-impl<T: BorrowMut<Transcript>, G: AffineRepr> RandomizedConstraintSystem<G::ScalarField>
+impl<T: TranscriptProtocol<G>, G: AffineRepr> RandomizedConstraintSystem<G::ScalarField>
     for RandomizingVerifier<G, T>
 {
     fn challenge_scalar(&mut self, label: &'static [u8]) -> G::ScalarField {
-        <Transcript as TranscriptProtocol<G>>::challenge_scalar(
-            self.verifier.transcript.borrow_mut(),
-            label,
-        )
+        TranscriptProtocol::<G>::challenge_scalar(&mut self.verifier.transcript, label)
     }
 }
 
-impl<G: AffineRepr, T: BorrowMut<Transcript>> Verifier<G, T> {
+impl<G: AffineRepr, T: TranscriptProtocol<G>> Verifier<G, T> {
     /// Construct an empty constraint system with specified external
     /// input variables.
     ///
     /// # Inputs
     ///
-    /// The `transcript` parameter is a Merlin proof transcript.  The
-    /// `VerifierCS` holds onto the `&mut Transcript` until it consumes
+    /// The `transcript` parameter is a proof transcript implementing
+    /// [`TranscriptProtocol`], e.g. a Merlin `Transcript` or a mutable
+    /// reference to one.  The `VerifierCS` holds onto the transcript until it consumes
     /// itself during [`VerifierCS::verify`], releasing its borrow of the
     /// transcript.  This ensures that the transcript cannot be
     /// altered except by the `VerifierCS` before proving is complete.
@@ -250,18 +429,56 @@ impl<G: AffineRepr, T: BorrowMut<Transcript>> Verifier<G, T> {
     /// The second element is a list of [`Variable`]s corresponding to
     /// the external inputs, which can be used to form constraints.
     pub fn new(mut transcript: T) -> Self {
-        <Transcript as TranscriptProtocol<G>>::r1cs_domain_sep(transcript.borrow_mut());
+        TranscriptProtocol::<G>::r1cs_domain_sep(&mut transcript);
 
         Verifier {
             transcript,
             num_vars: 0,
             V: Vec::new(),
+            C: Vec::new(),
+            vector_lens: Vec::new(),
+            public: Vec::new(),
             constraints: Vec::new(),
             deferred_constraints: Vec::new(),
             pending_multiplier: None,
+            cancel: None,
+            identity_policy: IdentityPolicy::default(),
+            limits: VerifierLimits::default(),
+            metrics: Metrics::default(),
         }
     }
 
+    /// Sets a flag which aborts the verification with
+    /// [`R1CSError::Cancelled`] once it is set, e.g. from another thread.
+    ///
+    /// The flag is checked between the stages of the verification: before
+    /// each randomization phase and before the final multiscalar
+    /// multiplication.
+    pub fn set_cancellation_flag(&mut self, flag: Arc<AtomicBool>) {
+        self.cancel = Some(flag);
+    }
+
+    /// Sets which commitments the verification rejects with
+    /// [`R1CSError::InvalidPoint`] when they are the identity, on top of
+    /// the points of the proof that are always checked.
+    ///
+    /// The policy is kept by the [`PreparedCircuit`] created with
+    /// [`Verifier::prepare`].
+    pub fn set_identity_policy(&mut self, policy: IdentityPolicy) {
+        self.identity_policy = policy;
+    }
+
+    /// Sets the limits of the sizes of the proofs and of the circuit,
+    /// beyond which the verification fails with
+    /// [`R1CSError::LimitExceeded`] before allocating anything for the
+    /// proof.
+    ///
+    /// The number of multipliers is checked once the constraints of each
+    /// randomization phase are added, before those of the next phase.
+    pub fn set_limits(&mut self, limits: VerifierLimits) {
+        self.limits = limits;
+    }
+
     /// Creates commitment to a high-level variable and adds it to the transcript.
     ///
     /// # Inputs
@@ -281,263 +498,333 @@ impl<G: AffineRepr, T: BorrowMut<Transcript>> Verifier<G, T> {
         self.V.push(commitment);
 
         // Add the commitment to the transcript.
-        self.transcript.borrow_mut().append_point(b"V", &commitment);
+        self.transcript.append_point(b"V", &commitment);
 
         Variable::Committed(i)
     }
 
-    /// Use a challenge, `z`, to flatten the constraints in the
-    /// constraint system into vectors used for proving and
-    /// verification.
+    /// Adds a vector commitment to `n` values and adds it to the transcript.
+    ///
+    /// # Inputs
     ///
-    /// # Output
+    /// The `commitment` parameter is a commitment to the values with the
+    /// Bulletproofs generators, as created by
+    /// [`Prover::commit_vec`](::r1cs::Prover::commit_vec).  The values are
+    /// the left inputs of the next `n` multipliers, so vector commitments
+    /// must be added before any multiplier is allocated.
     ///
-    /// Returns a tuple of
-    /// ```text
-    /// (wL, wR, wO, wV, wc)
-    /// ```
-    /// where `w{L,R,O}` is \\( z \cdot z^Q \cdot W_{L,R,O} \\).
+    /// # Returns
     ///
-    /// This has the same logic as `ProverCS::flattened_constraints()`
-    /// but also computes the constant terms (which the prover skips
-    /// because they're not needed to construct the proof).
-    fn flattened_constraints(
+    /// Returns a list of [`Variable`]s corresponding to the values, which
+    /// can be used to form constraints.
+    pub fn commit_vec(
         &mut self,
-        z: &G::ScalarField,
-    ) -> (
-        Vec<G::ScalarField>,
-        Vec<G::ScalarField>,
-        Vec<G::ScalarField>,
-        Vec<G::ScalarField>,
-        G::ScalarField,
-    ) {
-        let n = self.num_vars;
-        let m = self.V.len();
-
-        let mut wL = vec![G::ScalarField::zero(); n];
-        let mut wR = vec![G::ScalarField::zero(); n];
-        let mut wO = vec![G::ScalarField::zero(); n];
-        let mut wV = vec![G::ScalarField::zero(); m];
-        let mut wc = G::ScalarField::zero();
-
-        let mut exp_z = *z;
-        for lc in self.constraints.iter() {
-            for (var, coeff) in &lc.terms {
-                match var {
-                    Variable::MultiplierLeft(i) => {
-                        wL[*i] += exp_z * coeff;
-                    }
-                    Variable::MultiplierRight(i) => {
-                        wR[*i] += exp_z * coeff;
-                    }
-                    Variable::MultiplierOutput(i) => {
-                        wO[*i] += exp_z * coeff;
-                    }
-                    Variable::Committed(i) => {
-                        wV[*i] -= exp_z * coeff;
-                    }
-                    Variable::One() => {
-                        wc -= exp_z * coeff;
-                    }
-                    _ => {}
-                }
-            }
-            exp_z *= z;
+        commitment: G,
+        n: usize,
+    ) -> Result<Vec<Variable<G::ScalarField>>, R1CSError> {
+        let start = self.num_vars;
+        if start != self.vector_lens.iter().sum::<usize>() {
+            return Err(R1CSError::GadgetError {
+                description: "vector commitments must precede all multipliers".to_string(),
+            });
         }
+        self.num_vars += n;
+        self.C.push(commitment);
+        self.vector_lens.push(n);
+
+        // Add the commitment to the transcript.
+        self.transcript.append_point(b"C", &commitment);
+
+        Ok((start..start + n).map(Variable::MultiplierLeft).collect())
+    }
+
+    /// Adds a public input with the given `value` and appends it to the
+    /// transcript.
+    ///
+    /// Unlike a high-level variable, a public input needs no Pedersen
+    /// commitment: its value is known to the verifier, which folds its
+    /// weight into the constant term of the constraints.  The prover must
+    /// add the same public inputs with
+    /// [`Prover::public_input`](::r1cs::Prover::public_input), in the same
+    /// order relative to the commitments.
+    ///
+    /// # Returns
+    ///
+    /// Returns a [`Variable`] corresponding to the public input, which can
+    /// be used to form constraints.
+    pub fn public_input(&mut self, value: G::ScalarField) -> Variable<G::ScalarField> {
+        let i = self.public.len();
+        self.public.push(value);
+
+        // Add the value to the transcript.
+        TranscriptProtocol::<G>::append_scalar(&mut self.transcript, b"P", &value);
+
+        Variable::Public(i)
+    }
+
+    /// Returns a digest of the structure of the circuit built so far:
+    /// the number of multipliers and high-level variables, and every
+    /// term of every constraint.
+    ///
+    /// Randomized constraints are not included, since they are only
+    /// added when the proof is verified.
+    pub fn circuit_digest(&self) -> [u8; 32] {
+        circuit_digest(self.num_vars, self.V.len(), &self.constraints)
+    }
+
+    /// Appends the [`circuit_digest`](Verifier::circuit_digest) of the circuit
+    /// built so far to the transcript.
+    ///
+    /// This binds the proof to the exact structure of the circuit, so
+    /// that a proof for one circuit never verifies against a different
+    /// one.  It must be called after all the (non-randomized) constraints
+    /// have been added, and at the same point by the verifier and the prover.
+    pub fn append_circuit_digest(&mut self) {
+        let digest = self.circuit_digest();
+        TranscriptProtocol::<G>::r1cs_circuit_digest(&mut self.transcript, &digest);
+    }
 
-        (wL, wR, wO, wV, wc)
+    /// Returns the circuit built so far in the binary `.r1cs` format of
+    /// circom, with the same wires and constraints as
+    /// [`Prover::export_r1cs`](::r1cs::Prover::export_r1cs).
+    ///
+    /// Returns an error if the circuit has randomized constraints, since
+    /// those depend on the challenges of the proof.
+    pub fn export_r1cs(&self) -> Result<Vec<u8>, R1CSError> {
+        if !self.deferred_constraints.is_empty() {
+            return Err(R1CSError::GadgetError {
+                description: "circuits with randomized constraints cannot be exported".to_string(),
+            });
+        }
+        let wires = Wires {
+            public: self.public.len(),
+            committed: self.V.len(),
+            multipliers: self.num_vars,
+        };
+        r1cs_bytes(&self.constraints, wires)
     }
 
     /// Calls all remembered callbacks with an API that
     /// allows generating challenge scalars.
-    fn create_randomized_constraints(mut self) -> Result<Self, R1CSError> {
+    ///
+    /// The `scoped` callback, if any, is called last.
+    fn create_randomized_constraints(
+        mut self,
+        scoped: Option<ScopedCallback<'_, RandomizingVerifier<G, T>>>,
+    ) -> Result<Self, R1CSError> {
         // Clear the pending multiplier (if any) because it was committed into A_L/A_R/S.
         self.pending_multiplier = None;
 
-        if self.deferred_constraints.len() == 0 {
-            <Transcript as TranscriptProtocol<G>>::r1cs_1phase_domain_sep(
-                self.transcript.borrow_mut(),
-            );
+        if self.deferred_constraints.len() == 0 && scoped.is_none() {
+            TranscriptProtocol::<G>::r1cs_1phase_domain_sep(&mut self.transcript);
             Ok(self)
         } else {
-            <Transcript as TranscriptProtocol<G>>::r1cs_2phase_domain_sep(
-                self.transcript.borrow_mut(),
-            );
-            // Note: the wrapper could've used &mut instead of ownership,
-            // but specifying lifetimes for boxed closures is not going to be nice,
-            // so we move the self into wrapper and then move it back out afterwards.
-            let mut callbacks = mem::replace(&mut self.deferred_constraints, Vec::new());
-            let mut wrapped_self = RandomizingVerifier { verifier: self };
-            for callback in callbacks.drain(..) {
-                callback(&mut wrapped_self)?;
-            }
-            Ok(wrapped_self.verifier)
+            TranscriptProtocol::<G>::r1cs_2phase_domain_sep(&mut self.transcript);
+            self.call_deferred_constraints(scoped)
+        }
+    }
+
+    /// Calls the callbacks specified during the previous randomization
+    /// phase, in the randomization phase number `phase`.
+    fn create_next_phase_constraints(mut self, phase: u64) -> Result<Self, R1CSError> {
+        // Clear the pending multiplier (if any) because it was committed in the previous phase.
+        self.pending_multiplier = None;
+
+        TranscriptProtocol::<G>::r1cs_nphase_domain_sep(&mut self.transcript, phase);
+        self.call_deferred_constraints(None)
+    }
+
+    /// Calls the deferred callbacks, then the `scoped` one.  The
+    /// callbacks they specify in turn are deferred to the next phase.
+    fn call_deferred_constraints(
+        mut self,
+        scoped: Option<ScopedCallback<'_, RandomizingVerifier<G, T>>>,
+    ) -> Result<Self, R1CSError> {
+        // Note: the wrapper could've used &mut instead of ownership,
+        // but specifying lifetimes for boxed closures is not going to be nice,
+        // so we move the self into wrapper and then move it back out afterwards.
+        let mut callbacks = mem::replace(&mut self.deferred_constraints, Vec::new());
+        let mut wrapped_self = RandomizingVerifier { verifier: self };
+        for callback in callbacks.drain(..) {
+            callback(&mut wrapped_self)?;
         }
+        if let Some(callback) = scoped {
+            callback(&mut wrapped_self)?;
+        }
+        Ok(wrapped_self.verifier)
+    }
+
+    /// Consume this `Verifier` and compute the scalars of the single
+    /// multiscalar multiplication that checks `proof`.
+    ///
+    /// The proof is valid if and only if the multiscalar multiplication
+    /// of the returned [`VerificationScalars`] with the corresponding
+    /// points (see [`VerificationScalars`] for the pairing) is the identity.
+    /// This allows callers to fold the check into their own, larger
+    /// multiscalar multiplication, after scaling the scalars by a random
+    /// factor.
+    pub fn verification_scalars(
+        self,
+        proof: &R1CSProof<G>,
+        bp_gens: &BulletproofGens<G>,
+    ) -> Result<VerificationScalars<G::ScalarField>, R1CSError> {
+        self.compute_verification_scalars(proof, bp_gens, None, None)
+            .map(|(_, scalars)| scalars)
+    }
+
+    /// Same as `verification_scalars`, but also returns the verifier back,
+    /// so that its commitments and transcript can be used afterwards.
+    ///
+    /// The `scoped` callback, if any, adds randomized constraints after
+    /// the deferred ones.  The challenge `r`, if any, replaces the one
+    /// derived from the transcript to combine the checks of the proof.
+    pub(super) fn compute_verification_scalars(
+        self,
+        proof: &R1CSProof<G>,
+        bp_gens: &BulletproofGens<G>,
+        scoped: Option<ScopedCallback<'_, RandomizingVerifier<G, T>>>,
+        r: Option<G::ScalarField>,
+    ) -> Result<(Self, VerificationScalars<G::ScalarField>), R1CSError> {
+        let (verifier, challenges) =
+            self.compute_verification_challenges(proof, bp_gens, scoped, r)?;
+        let metrics = &verifier.metrics;
+        let flattened = metrics.time(Step::Flatten, || {
+            flatten_constraints(
+                &verifier.constraints,
+                verifier.num_vars,
+                verifier.V.len(),
+                &verifier.public,
+                &challenges.z,
+            )
+        });
+        let scalars = metrics.time(Step::Ipp, || challenges.scalars(proof, flattened));
+        check_cancelled(&verifier.cancel)?;
+        Ok((verifier, scalars))
     }
 
-    // Get scalars for single multiexponentiation verification
-    // Order is
-    // pc_gens.B
-    // pc_gens.B_blinding
-    // gens.G_vec
-    // gens.H_vec
-    // proof.A_I1
-    // proof.A_O1
-    // proof.S1
-    // proof.A_I2
-    // proof.A_O2
-    // proof.S2
-    // self.V
-    // T_1, T3, T4, T5, T6
-    // proof.ipp_proof.L_vec
-    // proof.ipp_proof.R_vec
-    pub(super) fn verification_scalars(
+    /// Same as `compute_verification_scalars`, but stops after deriving
+    /// the challenges from the transcript, see
+    /// [`circuit_verification_challenges`].
+    pub(super) fn compute_verification_challenges(
         mut self,
         proof: &R1CSProof<G>,
         bp_gens: &BulletproofGens<G>,
-    ) -> Result<(Self, Vec<G::ScalarField>), R1CSError> {
+        scoped: Option<ScopedCallback<'_, RandomizingVerifier<G, T>>>,
+        r: Option<G::ScalarField>,
+    ) -> Result<(Self, VerificationChallenges<G::ScalarField>), R1CSError> {
+        trace_span!("verification_challenges");
         // Commit a length _suffix_ for the number of high-level variables.
         // We cannot do this in advance because user can commit variables one-by-one,
         // but this suffix provides safe disambiguation because each variable
         // is prefixed with a separate label.
-        let transcript = self.transcript.borrow_mut();
+        check_cancelled(&self.cancel)?;
+        let limits = self.limits;
+        limits.check_ipp_rounds(proof.ipp_proof.L_vec.len())?;
+        limits.check_commitments(self.V.len() + self.C.len())?;
+        limits.check_multipliers(self.num_vars)?;
+        proof.check_shape(!self.C.is_empty(), bp_gens.gens_capacity)?;
+        let policy = self.identity_policy;
+        if policy.commitments {
+            reject_identity(b"V", &self.V)?;
+            reject_identity(b"C", &self.C)?;
+        }
+        let transcript = &mut self.transcript;
         transcript.append_u64(b"m", self.V.len() as u64);
 
         let n1 = self.num_vars;
         transcript.validate_and_append_point(b"A_I1", &proof.A_I1)?;
         transcript.validate_and_append_point(b"A_O1", &proof.A_O1)?;
         transcript.validate_and_append_point(b"S1", &proof.S1)?;
+        if let Some(S_C) = &proof.S_C {
+            transcript.validate_and_append_point(b"S_C", S_C)?;
+        }
 
-        // Process the remaining constraints.
-        self = self.create_randomized_constraints()?;
-
-        let transcript = self.transcript.borrow_mut();
-
-        // If the number of multiplications is not 0 or a power of 2, then pad the circuit.
-        let n = self.num_vars;
-        let n2 = n - n1;
-        let padded_n = self.num_vars.next_power_of_two();
-        let pad = padded_n - n;
-
-        use crate::inner_product_proof::inner_product;
-        use crate::util;
-
-        if bp_gens.gens_capacity < padded_n {
-            return Err(R1CSError::InvalidGeneratorsLength);
+        // Process the remaining constraints, appending the commitments of
+        // every randomization phase before the challenges of the next one.
+        self = self.create_randomized_constraints(scoped)?;
+        limits.check_multipliers(self.num_vars)?;
+        let mut phase_ends = vec![n1, self.num_vars];
+        if policy.phase_commitments && self.num_vars > n1 {
+            reject_identity(b"A_I2", [&proof.A_I2])?;
+            reject_identity(b"A_O2", [&proof.A_O2])?;
+            reject_identity(b"S2", [&proof.S2])?;
         }
 
         // These points are the identity in the 1-phase unrandomized case.
+        let transcript = &mut self.transcript;
         transcript.append_point(b"A_I2", &proof.A_I2);
         transcript.append_point(b"A_O2", &proof.A_O2);
         transcript.append_point(b"S2", &proof.S2);
 
-        let y: G::ScalarField =
-            <Transcript as TranscriptProtocol<G>>::challenge_scalar(transcript, b"y");
-        let z = <Transcript as TranscriptProtocol<G>>::challenge_scalar(transcript, b"z");
-
-        transcript.validate_and_append_point(b"T_1", &proof.T_1)?;
-        transcript.validate_and_append_point(b"T_3", &proof.T_3)?;
-        transcript.validate_and_append_point(b"T_4", &proof.T_4)?;
-        transcript.validate_and_append_point(b"T_5", &proof.T_5)?;
-        transcript.validate_and_append_point(b"T_6", &proof.T_6)?;
-
-        let u = <Transcript as TranscriptProtocol<G>>::challenge_scalar(transcript, b"u");
-        let x = <Transcript as TranscriptProtocol<G>>::challenge_scalar(transcript, b"x");
-
-        <Transcript as TranscriptProtocol<G>>::append_scalar(transcript, b"t_x", &proof.t_x);
-        <Transcript as TranscriptProtocol<G>>::append_scalar(
-            transcript,
-            b"t_x_blinding",
-            &proof.t_x_blinding,
-        );
-        <Transcript as TranscriptProtocol<G>>::append_scalar(
-            transcript,
-            b"e_blinding",
-            &proof.e_blinding,
-        );
-
-        let w: G::ScalarField =
-            <Transcript as TranscriptProtocol<G>>::challenge_scalar(transcript, b"w");
-
-        let (wL, wR, wO, wV, wc) = self.flattened_constraints(&z);
-
-        // Get IPP variables
-        let (u_sq, u_inv_sq, s) = proof
-            .ipp_proof
-            .verification_scalars(padded_n, self.transcript.borrow_mut())
-            .map_err(|_| R1CSError::VerificationError)?;
-
-        let a = proof.ipp_proof.a;
-        let b = proof.ipp_proof.b;
-
-        let y_inv = y.inverse().unwrap();
-        let y_inv_vec = util::exp_iter::<G>(y_inv)
-            .take(padded_n)
-            .collect::<Vec<G::ScalarField>>();
-        let yneg_wR = wR
-            .into_iter()
-            .zip(y_inv_vec.iter())
-            .map(|(wRi, exp_y_inv)| wRi * exp_y_inv)
-            .chain(iter::repeat(G::ScalarField::zero()).take(pad))
-            .collect::<Vec<G::ScalarField>>();
-
-        let delta = inner_product(&yneg_wR[0..n], &wL);
-
-        let u_for_g = iter::repeat(G::ScalarField::one())
-            .take(n1)
-            .chain(iter::repeat(u).take(n2 + pad));
-        let u_for_h = u_for_g.clone();
-
-        // define parameters for P check
-        let g_scalars: Vec<_> = yneg_wR
-            .iter()
-            .zip(u_for_g)
-            .zip(s.iter().take(padded_n))
-            .map(|((yneg_wRi, u_or_1), s_i)| u_or_1 * (x * yneg_wRi - a * s_i))
-            .collect();
-
-        let h_scalars: Vec<_> = y_inv_vec
-            .iter()
-            .zip(u_for_h)
-            .zip(s.iter().rev().take(padded_n))
-            .zip(
-                wL.into_iter()
-                    .chain(iter::repeat(G::ScalarField::zero()).take(pad)),
-            )
-            .zip(
-                wO.into_iter()
-                    .chain(iter::repeat(G::ScalarField::zero()).take(pad)),
-            )
-            .map(|((((y_inv_i, u_or_1), s_i_inv), wLi), wOi)| {
-                u_or_1 * (*y_inv_i * (x * wLi + wOi - b * s_i_inv) - G::ScalarField::one())
-            })
-            .collect();
+        for (A_I, A_O, S) in &proof.extra_phases {
+            if self.deferred_constraints.is_empty() {
+                return Err(R1CSError::InvalidProofShape {
+                    description: "more randomization phases than the circuit".to_string(),
+                });
+            }
+            check_cancelled(&self.cancel)?;
+            let start = self.num_vars;
+            self = self.create_next_phase_constraints(phase_ends.len() as u64 + 1)?;
+            limits.check_multipliers(self.num_vars)?;
+            phase_ends.push(self.num_vars);
+            if policy.phase_commitments && self.num_vars > start {
+                reject_identity(b"A_I", [A_I])?;
+                reject_identity(b"A_O", [A_O])?;
+                reject_identity(b"S", [S])?;
+            }
 
-        let r: G::ScalarField = <Transcript as TranscriptProtocol<G>>::challenge_scalar(
-            &mut self.transcript.borrow_mut().clone(),
-            b"r",
-        );
+            let transcript = &mut self.transcript;
+            transcript.append_point(b"A_I", A_I);
+            transcript.append_point(b"A_O", A_O);
+            transcript.append_point(b"S", S);
+        }
+        if !self.deferred_constraints.is_empty() {
+            return Err(R1CSError::InvalidProofShape {
+                description: "fewer randomization phases than the circuit".to_string(),
+            });
+        }
 
-        let xx = x * x;
-        let rxx = r * xx;
-        let xxx = x * xx;
+        let challenges = circuit_verification_challenges(
+            &mut self.transcript,
+            proof,
+            bp_gens,
+            CircuitLayout {
+                phase_ends: &phase_ends,
+                vector_lens: &self.vector_lens,
+                parties: 1,
+            },
+            r,
+        )?;
+        Ok((self, challenges))
+    }
 
-        // group the T_scalars and T_points together
-        let T_scalars = [r * x, rxx * x, rxx * xx, rxx * xxx, rxx * xx * xx];
-
-        let mut scalars: Vec<G::ScalarField> = vec![];
-        scalars.push(w * (proof.t_x - a * b) + r * (xx * (wc + delta) - proof.t_x));
-        scalars.push(-proof.e_blinding - r * proof.t_x_blinding);
-        scalars.extend_from_slice(&g_scalars);
-        scalars.extend_from_slice(&h_scalars);
-        scalars.extend_from_slice(&[x, xx, xxx, u * x, u * xx, u * xxx]);
-        for wVi in wV.iter() {
-            scalars.push(*wVi * rxx);
+    /// Consume this `Verifier` and cache the constraints of its circuit
+    /// into a [`PreparedCircuit`], which verifies proofs of the same circuit
+    /// without re-synthesizing it.
+    ///
+    /// Returns an error if the circuit has randomized constraints,
+    /// since those depend on the challenges of each proof, or public
+    /// inputs or vector commitments, since their values are specific to
+    /// each statement.
+    pub fn prepare(self) -> Result<PreparedCircuit<G::ScalarField>, R1CSError> {
+        if !self.deferred_constraints.is_empty() {
+            return Err(R1CSError::GadgetError {
+                description: "circuits with randomized constraints cannot be prepared".to_string(),
+            });
+        }
+        if !self.public.is_empty() {
+            return Err(R1CSError::GadgetError {
+                description: "circuits with public inputs cannot be prepared".to_string(),
+            });
+        }
+        if !self.C.is_empty() {
+            return Err(R1CSError::GadgetError {
+                description: "circuits with vector commitments cannot be prepared".to_string(),
+            });
         }
-        scalars.extend_from_slice(&T_scalars);
-        scalars.extend_from_slice(&u_sq);
-        scalars.extend_from_slice(&u_inv_sq);
-        Ok((self, scalars))
+        let mut prepared = PreparedCircuit::new(&self.constraints, self.num_vars, self.V.len());
+        prepared.set_identity_policy(self.identity_policy);
+        Ok(prepared)
     }
 
     /// Consume this `VerifierCS` and attempt to verify the supplied `proof`.
@@ -557,135 +844,1013 @@ impl<G: AffineRepr, T: BorrowMut<Transcript>> Verifier<G, T> {
     }
     /// Same as `verify`, but also returns the transcript back to the user.
     pub fn verify_and_return_transcript(
+        self,
+        proof: &R1CSProof<G>,
+        pc_gens: &PedersenGens<G>,
+        bp_gens: &BulletproofGens<G>,
+    ) -> Result<T, R1CSError> {
+        self.check_proof(proof, pc_gens, bp_gens, None, None)
+    }
+
+    /// Same as `verify`, but also returns the timings and counts of the
+    /// verification.
+    #[cfg(feature = "metrics")]
+    pub fn verify_with_report(
         mut self,
         proof: &R1CSProof<G>,
         pc_gens: &PedersenGens<G>,
         bp_gens: &BulletproofGens<G>,
+    ) -> Result<PerfReport, R1CSError> {
+        let metrics = Metrics::enabled();
+        self.metrics = metrics.clone();
+        self.check_proof(proof, pc_gens, bp_gens, None, None)?;
+        Ok(metrics.report())
+    }
+
+    /// Same as `verify`, but draws the challenge combining the checks of
+    /// the proof from `prng` instead of deriving it from the transcript.
+    ///
+    /// The challenge derived from the transcript is a deterministic
+    /// function of the proof, which a prover can compute in advance; a
+    /// challenge from the verifier's own RNG cannot be predicted, like
+    /// the factors drawn by [`batch_verify`].
+    pub fn verify_with_rng<R: CryptoRng + RngCore>(
+        self,
+        proof: &R1CSProof<G>,
+        pc_gens: &PedersenGens<G>,
+        bp_gens: &BulletproofGens<G>,
+        prng: &mut R,
+    ) -> Result<(), R1CSError> {
+        let r = G::ScalarField::rand(prng);
+        self.check_proof(proof, pc_gens, bp_gens, None, Some(r))
+            .map(|_| ())
+    }
+
+    /// Same as `verify`, but tuned for 32-bit and low-memory targets
+    /// such as IoT gateways verifying single proofs.
+    ///
+    /// The scalars of the generators are computed one at a time and
+    /// multiplied in chunks of `N` terms held in fixed-capacity buffers,
+    /// instead of in vectors of `padded_n` scalars and points: besides
+    /// the proof and the circuit, the only temporary vectors are the
+    /// `3n` weights of the flattened constraints.  Chunks of 16 to 32
+    /// terms are multiplied by the default backend with Straus's method,
+    /// whose tables only take a few points per term.  Fixed-base tables
+    /// of the generators, if any, are not used.
+    pub fn verify_compact<const N: usize>(
+        self,
+        proof: &R1CSProof<G>,
+        pc_gens: &PedersenGens<G>,
+        bp_gens: &BulletproofGens<G>,
+    ) -> Result<(), R1CSError> {
+        trace_span!("verify");
+        let (verifier, challenges) =
+            self.compute_verification_challenges(proof, bp_gens, None, None)?;
+        let flattened = flatten_constraints(
+            &verifier.constraints,
+            verifier.num_vars,
+            verifier.V.len(),
+            &verifier.public,
+            &challenges.z,
+        );
+        let terms = challenges.terms(
+            proof,
+            &flattened,
+            pc_gens,
+            bp_gens,
+            (&verifier.V, &verifier.C),
+        );
+        let mega_check = buffered_msm::<G, _, N>(bp_gens.msm_backend(), terms);
+        check_cancelled(&verifier.cancel)?;
+
+        if !mega_check.is_zero() {
+            return Err(R1CSError::VerificationError);
+        }
+        Ok(())
+    }
+
+    /// Consume this `Verifier` and attempt to verify the supplied `proof`,
+    /// adding the randomized constraints specified by `callback` after
+    /// those of
+    /// [`RandomizableConstraintSystem::specify_randomized_constraints`].
+    ///
+    /// This is the counterpart of
+    /// [`Prover::prove_with_randomized_constraints`](::r1cs::Prover::prove_with_randomized_constraints):
+    /// `callback` is called before this function returns, so it does not
+    /// need to be `'static` and can borrow local state.
+    pub fn verify_with_randomized_constraints<C>(
+        self,
+        proof: &R1CSProof<G>,
+        pc_gens: &PedersenGens<G>,
+        bp_gens: &BulletproofGens<G>,
+        callback: C,
+    ) -> Result<(), R1CSError>
+    where
+        C: FnOnce(&mut RandomizingVerifier<G, T>) -> Result<(), R1CSError>,
+    {
+        let mut callback = Some(callback);
+        let mut scoped = |cs: &mut RandomizingVerifier<G, T>| (callback.take().unwrap())(cs);
+        self.check_proof(proof, pc_gens, bp_gens, Some(&mut scoped), None)
+            .map(|_| ())
+    }
+
+    fn check_proof(
+        self,
+        proof: &R1CSProof<G>,
+        pc_gens: &PedersenGens<G>,
+        bp_gens: &BulletproofGens<G>,
+        scoped: Option<ScopedCallback<'_, RandomizingVerifier<G, T>>>,
+        r: Option<G::ScalarField>,
     ) -> Result<T, R1CSError> {
-        let (verifier, scalars) = self.verification_scalars(proof, bp_gens)?;
-        self = verifier;
-        let T_points = [proof.T_1, proof.T_3, proof.T_4, proof.T_5, proof.T_6];
-
-        // We are performing a single-party circuit proof, so party index is 0.
-        let gens = bp_gens.share(0);
-
-        let padded_n = self.num_vars.next_power_of_two();
-
-        let mega_check = G::Group::msm(
-            &iter::once(&pc_gens.B)
-                .chain(iter::once(&pc_gens.B_blinding))
-                .chain(gens.G(padded_n))
-                .chain(gens.H(padded_n))
-                .chain(iter::once(&proof.A_I1))
-                .chain(iter::once(&proof.A_O1))
-                .chain(iter::once(&proof.S1))
-                .chain(iter::once(&proof.A_I2))
-                .chain(iter::once(&proof.A_O2))
-                .chain(iter::once(&proof.S2))
-                .chain(self.V.iter())
-                .chain(T_points.iter())
-                .chain(proof.ipp_proof.L_vec.iter())
-                .chain(proof.ipp_proof.R_vec.iter())
-                .map(|f| f.clone())
-                .collect::<Vec<G>>(),
-            &scalars,
-        )
-        .unwrap();
+        trace_span!("verify");
+        if bp_gens.has_fixed_base_tables(pc_gens) {
+            return self.check_proof_with_tables(proof, pc_gens, bp_gens, scoped, r);
+        }
+
+        // The scalars of the generators are computed lazily and streamed
+        // to the multiscalar multiplication in bounded chunks, so that
+        // besides the `3n` weights of the flattened constraints, the
+        // memory of the verifier does not grow with the circuit.
+        let (verifier, challenges) =
+            self.compute_verification_challenges(proof, bp_gens, scoped, r)?;
+        let metrics = &verifier.metrics;
+        let flattened = metrics.time(Step::Flatten, || {
+            flatten_constraints(
+                &verifier.constraints,
+                verifier.num_vars,
+                verifier.V.len(),
+                &verifier.public,
+                &challenges.z,
+            )
+        });
+        let terms = metrics.time(Step::Ipp, || {
+            challenges.terms(
+                proof,
+                &flattened,
+                pc_gens,
+                bp_gens,
+                (&verifier.V, &verifier.C),
+            )
+        });
+        let points = proof_points(proof, &verifier.V, &verifier.C).count();
+        let padded_n = challenges.phase_ends[challenges.phase_ends.len() - 1].next_power_of_two();
+        metrics.count(
+            2 + 2 * padded_n + points,
+            points - verifier.V.len() - verifier.C.len(),
+        );
+        let mega_check = metrics.time(Step::Msm, || chunked_msm(bp_gens.msm_backend(), terms));
+        check_cancelled(&verifier.cancel)?;
+
+        if !mega_check.is_zero() {
+            return Err(R1CSError::VerificationError);
+        }
+
+        Ok(verifier.transcript)
+    }
+
+    /// Same as `check_proof`, but collects the verification scalars to
+    /// multiply the generators with their fixed-base tables.
+    fn check_proof_with_tables(
+        self,
+        proof: &R1CSProof<G>,
+        pc_gens: &PedersenGens<G>,
+        bp_gens: &BulletproofGens<G>,
+        scoped: Option<ScopedCallback<'_, RandomizingVerifier<G, T>>>,
+        r: Option<G::ScalarField>,
+    ) -> Result<T, R1CSError> {
+        let (verifier, scalars) = self.compute_verification_scalars(proof, bp_gens, scoped, r)?;
+        let points: Vec<G> = proof_points(proof, &verifier.V, &verifier.C)
+            .copied()
+            .collect();
+        let proof_scalars: Vec<G::ScalarField> = scalars.proof_scalars().collect();
+
+        let metrics = &verifier.metrics;
+        metrics.count(
+            2 + scalars.g.len() + scalars.h.len() + points.len(),
+            points.len() - verifier.V.len() - verifier.C.len(),
+        );
+        let mega_check = metrics.time(Step::Msm, || {
+            bp_gens.fixed_msm(pc_gens, scalars.fixed_terms(1), &points, &proof_scalars)
+        });
 
         if !mega_check.is_zero() {
             return Err(R1CSError::VerificationError);
         }
 
-        Ok(self.transcript)
+        Ok(verifier.transcript)
+    }
+
+    /// Consume this `Verifier` and return the points and scalars of the
+    /// multiscalar multiplication which verifies `proof`.
+    ///
+    /// The proof is valid if and only if the multiscalar multiplication of
+    /// the returned points and scalars is the identity. Callers can merge
+    /// this check with other checks (e.g. sigma protocols from other crates)
+    /// into a single multiscalar multiplication, after scaling the scalars
+    /// by a random factor.
+    pub fn verification_msm(
+        self,
+        proof: &R1CSProof<G>,
+        pc_gens: &PedersenGens<G>,
+        bp_gens: &BulletproofGens<G>,
+    ) -> Result<(Vec<G>, Vec<G::ScalarField>), R1CSError> {
+        self.compute_verification_msm(proof, pc_gens, bp_gens, None, None)
+            .map(|(_, points, scalars)| (points, scalars))
+    }
+
+    /// Same as `verification_msm`, but also returns the verifier back.
+    #[allow(clippy::type_complexity)]
+    fn compute_verification_msm(
+        self,
+        proof: &R1CSProof<G>,
+        pc_gens: &PedersenGens<G>,
+        bp_gens: &BulletproofGens<G>,
+        scoped: Option<ScopedCallback<'_, RandomizingVerifier<G, T>>>,
+        r: Option<G::ScalarField>,
+    ) -> Result<(Self, Vec<G>, Vec<G::ScalarField>), R1CSError> {
+        let (verifier, scalars) = self.compute_verification_scalars(proof, bp_gens, scoped, r)?;
+        let points = verification_points(
+            proof,
+            &verifier.V,
+            &verifier.C,
+            pc_gens,
+            bp_gens,
+            scalars.padded_n(),
+            1,
+        );
+
+        Ok((verifier, points, scalars.to_vec()))
+    }
+}
+
+/// Returns [`R1CSError::Cancelled`] if the cancellation `flag` is set.
+pub(super) fn check_cancelled(flag: &Option<Arc<AtomicBool>>) -> Result<(), R1CSError> {
+    match flag {
+        Some(flag) if flag.load(Ordering::Relaxed) => Err(R1CSError::Cancelled),
+        _ => Ok(()),
+    }
+}
+
+/// Returns the points of the verification multiscalar multiplication,
+/// in the same order as [`VerificationScalars::to_vec`].
+///
+/// The `padded_n` multipliers are laid out over the generators of
+/// `parties` parties, as in an aggregated proof.
+pub(super) fn verification_points<G: AffineRepr>(
+    proof: &R1CSProof<G>,
+    V: &[G],
+    C: &[G],
+    pc_gens: &PedersenGens<G>,
+    bp_gens: &BulletproofGens<G>,
+    padded_n: usize,
+    parties: usize,
+) -> Vec<G> {
+    iter::once(&pc_gens.B)
+        .chain(iter::once(&pc_gens.B_blinding))
+        .chain(bp_gens.G(padded_n / parties, parties))
+        .chain(bp_gens.H(padded_n / parties, parties))
+        .chain(proof_points(proof, V, C))
+        .copied()
+        .collect()
+}
+
+/// Returns the points specific to `proof`, i.e. all points of the
+/// verification multiscalar multiplication after the generators.
+pub(super) fn proof_points<'a, G: AffineRepr>(
+    proof: &'a R1CSProof<G>,
+    V: &'a [G],
+    C: &'a [G],
+) -> impl Iterator<Item = &'a G> {
+    [
+        &proof.A_I1,
+        &proof.A_O1,
+        &proof.S1,
+        &proof.A_I2,
+        &proof.A_O2,
+        &proof.S2,
+    ]
+    .into_iter()
+    .chain(
+        proof
+            .extra_phases
+            .iter()
+            .flat_map(|(A_I, A_O, S)| [A_I, A_O, S]),
+    )
+    .chain(V.iter())
+    .chain(C.iter())
+    .chain(proof.S_C.iter())
+    .chain([&proof.T_1, &proof.T_3, &proof.T_4, &proof.T_5, &proof.T_6])
+    .chain(proof.ipp_proof.L_vec.iter())
+    .chain(proof.ipp_proof.R_vec.iter())
+}
+
+/// The flattened constraint weights `(wL, wR, wO, wV, wc)`,
+/// see [`flatten_constraints`].
+pub(super) type FlattenedConstraints<F> = (Vec<F>, Vec<F>, Vec<F>, Vec<F>, F);
+
+/// Use a challenge, `z`, to flatten the `constraints` of a constraint
+/// system with `n` multipliers, `m` high-level variables and the
+/// `public` input values into vectors used for proving and verification.
+///
+/// # Output
+///
+/// Returns a tuple of
+/// ```text
+/// (wL, wR, wO, wV, wc)
+/// ```
+/// where `w{L,R,O}` is \\( z \cdot z^Q \cdot W_{L,R,O} \\).
+///
+/// This has the same logic as `ProverCS::flattened_constraints()`
+/// but also computes the constant terms (which the prover skips
+/// because they're not needed to construct the proof).  The terms of
+/// the public inputs are constant and are folded into `wc`.
+pub(super) fn flatten_constraints<F: PrimeField>(
+    constraints: &[LinearCombination<F>],
+    n: usize,
+    m: usize,
+    public: &[F],
+    z: &F,
+) -> FlattenedConstraints<F> {
+    trace_span!("flatten_constraints", constraints = constraints.len());
+    let mut wL = vec![F::zero(); n];
+    let mut wR = vec![F::zero(); n];
+    let mut wO = vec![F::zero(); n];
+    let mut wV = vec![F::zero(); m];
+    let mut wc = F::zero();
+
+    let mut exp_z = *z;
+    for lc in constraints.iter() {
+        for (var, coeff) in &lc.terms {
+            match var {
+                Variable::MultiplierLeft(i) => {
+                    wL[*i] += exp_z * coeff;
+                }
+                Variable::MultiplierRight(i) => {
+                    wR[*i] += exp_z * coeff;
+                }
+                Variable::MultiplierOutput(i) => {
+                    wO[*i] += exp_z * coeff;
+                }
+                Variable::Committed(i) => {
+                    wV[*i] -= exp_z * coeff;
+                }
+                Variable::One() => {
+                    wc -= exp_z * coeff;
+                }
+                Variable::Public(i) => {
+                    wc -= exp_z * coeff * public[*i];
+                }
+                _ => {}
+            }
+        }
+        exp_z *= z;
+    }
+
+    (wL, wR, wO, wV, wc)
+}
+
+/// Returns the factors of the generators of the multipliers, which
+/// combine the commitments of the phases ending at the multipliers
+/// `phase_ends` with the challenges `u` of the randomization phases,
+/// followed by `pad` factors for the padding multipliers.
+///
+/// The first `vector_len` multipliers, whose left inputs are committed
+/// by the vector commitments, are further combined with the challenge `c`.
+pub(super) fn phase_factors<F: Field>(
+    phase_ends: &[usize],
+    u: &[F],
+    (vector_len, c): (usize, F),
+    pad: usize,
+) -> Vec<F> {
+    phase_factor_iter(phase_ends, u, (vector_len, c), pad).collect()
+}
+
+/// Same as [`phase_factors`], but returns the factors lazily.
+fn phase_factor_iter<'a, F: Field>(
+    phase_ends: &'a [usize],
+    u: &'a [F],
+    (vector_len, c): (usize, F),
+    pad: usize,
+) -> impl Iterator<Item = F> + 'a {
+    let last = u.last().copied().unwrap_or_else(F::one);
+    iter::repeat_n(F::one(), phase_ends[0])
+        .chain(
+            phase_ends
+                .windows(2)
+                .zip(u.iter())
+                .flat_map(|(bounds, u_k)| iter::repeat_n(*u_k, bounds[1] - bounds[0])),
+        )
+        .chain(iter::repeat_n(last, pad))
+        .enumerate()
+        .map(move |(i, factor)| if i < vector_len { factor * c } else { factor })
+}
+
+/// The layout of the multipliers of a circuit, as needed to verify its
+/// proofs.
+#[derive(Copy, Clone)]
+pub(super) struct CircuitLayout<'a> {
+    /// The multipliers at which the commitment phases end.
+    pub(super) phase_ends: &'a [usize],
+    /// The lengths of the vector commitments of the first multipliers.
+    pub(super) vector_lens: &'a [usize],
+    /// The number of parties over whose generators the multipliers are
+    /// laid out, as in an aggregated proof.
+    pub(super) parties: usize,
+}
+
+/// Computes the verification scalars of `proof` for a circuit with the
+/// `layout`, after the commitments of all its phases have been appended
+/// to the `transcript`.
+///
+/// The `flatten` callback maps the challenge `z` to the flattened
+/// constraint weights `(wL, wR, wO, wV, wc)` of the circuit.  The
+/// challenge `r` combining the checks of the proof is derived from the
+/// transcript unless it is given.
+pub(super) fn circuit_verification_scalars<G, Tr, F>(
+    transcript: &mut Tr,
+    proof: &R1CSProof<G>,
+    bp_gens: &BulletproofGens<G>,
+    layout: CircuitLayout<'_>,
+    flatten: F,
+    r: Option<G::ScalarField>,
+) -> Result<VerificationScalars<G::ScalarField>, R1CSError>
+where
+    G: AffineRepr,
+    Tr: TranscriptProtocol<G> + ?Sized,
+    F: FnOnce(&G::ScalarField) -> FlattenedConstraints<G::ScalarField>,
+{
+    let challenges = circuit_verification_challenges(transcript, proof, bp_gens, layout, r)?;
+    let flattened = flatten(&challenges.z);
+    Ok(challenges.scalars(proof, flattened))
+}
+
+/// The challenges of the verification of a proof, from which its
+/// verification scalars are computed without the transcript.
+pub(super) struct VerificationChallenges<F: PrimeField> {
+    phase_ends: Vec<usize>,
+    vector_lens: Vec<usize>,
+    y: F,
+    /// The challenge `z` of the flattened constraints.
+    pub(super) z: F,
+    u: Vec<F>,
+    c: F,
+    x: F,
+    w: F,
+    r: F,
+    /// The challenges of the inner-product proof.
+    ipp: Vec<F>,
+}
+
+/// Derives the [`VerificationChallenges`] of `proof` from the `transcript`,
+/// see [`circuit_verification_scalars`].
+///
+/// This is the only step of the verification of a proof which depends on
+/// the transcript, so that the remaining ones can run concurrently for
+/// several proofs.
+pub(super) fn circuit_verification_challenges<G: AffineRepr, Tr: TranscriptProtocol<G> + ?Sized>(
+    transcript: &mut Tr,
+    proof: &R1CSProof<G>,
+    bp_gens: &BulletproofGens<G>,
+    layout: CircuitLayout<'_>,
+    r: Option<G::ScalarField>,
+) -> Result<VerificationChallenges<G::ScalarField>, R1CSError> {
+    let CircuitLayout {
+        phase_ends,
+        vector_lens,
+        parties,
+    } = layout;
+    // If the number of multiplications is not 0 or a power of 2, then pad the circuit.
+    let n = phase_ends[phase_ends.len() - 1];
+    let padded_n = n.next_power_of_two();
+
+    if bp_gens.gens_capacity < padded_n / parties {
+        return Err(R1CSError::InsufficientGenerators {
+            capacity: bp_gens.gens_capacity,
+            required: padded_n / parties,
+        });
+    }
+    if bp_gens.party_capacity < parties {
+        return Err(R1CSError::InvalidGeneratorsLength);
+    }
+
+    let y: G::ScalarField = TranscriptProtocol::<G>::challenge_scalar(transcript, b"y");
+    let z = TranscriptProtocol::<G>::challenge_scalar(transcript, b"z");
+
+    transcript.validate_and_append_point(b"T_1", &proof.T_1)?;
+    transcript.validate_and_append_point(b"T_3", &proof.T_3)?;
+    transcript.validate_and_append_point(b"T_4", &proof.T_4)?;
+    transcript.validate_and_append_point(b"T_5", &proof.T_5)?;
+    transcript.validate_and_append_point(b"T_6", &proof.T_6)?;
+
+    // Challenges combining the commitments of the randomization phases.
+    let u: Vec<G::ScalarField> = (1..phase_ends.len())
+        .map(|_| TranscriptProtocol::<G>::challenge_scalar(transcript, b"u"))
+        .collect();
+    // Challenge combining the vector commitments, if any.
+    let c = if vector_lens.is_empty() {
+        G::ScalarField::one()
+    } else {
+        TranscriptProtocol::<G>::challenge_scalar(transcript, b"c")
+    };
+    let x = TranscriptProtocol::<G>::challenge_scalar(transcript, b"x");
+
+    TranscriptProtocol::<G>::append_scalar(transcript, b"t_x", &proof.t_x);
+    TranscriptProtocol::<G>::append_scalar(transcript, b"t_x_blinding", &proof.t_x_blinding);
+    TranscriptProtocol::<G>::append_scalar(transcript, b"e_blinding", &proof.e_blinding);
+
+    let w: G::ScalarField = TranscriptProtocol::<G>::challenge_scalar(transcript, b"w");
+
+    // Get IPP challenges
+    // The lengths of L and R were checked to be equal by `check_shape`.
+    let rounds = padded_n.trailing_zeros() as usize;
+    let ipp_proof = &proof.ipp_proof;
+    if ipp_proof.L_vec.len() != rounds || ipp_proof.R_vec.len() != rounds {
+        return Err(R1CSError::InvalidInnerProductLength {
+            expected: rounds,
+            actual: ipp_proof.L_vec.len(),
+        });
+    }
+    let ipp = ipp_proof.verification_challenges(padded_n, transcript)?;
+
+    let r: G::ScalarField =
+        r.unwrap_or_else(|| TranscriptProtocol::<G>::forked_challenge_scalar(transcript, b"r"));
+
+    Ok(VerificationChallenges {
+        phase_ends: phase_ends.to_vec(),
+        vector_lens: vector_lens.to_vec(),
+        y,
+        z,
+        u,
+        c,
+        x,
+        w,
+        r,
+        ipp,
+    })
+}
+
+impl<F: PrimeField> VerificationChallenges<F> {
+    /// Computes the verification scalars of `proof` given the flattened
+    /// constraints of the circuit for the challenge `z`.
+    pub(super) fn scalars<G: AffineRepr<ScalarField = F>>(
+        self,
+        proof: &R1CSProof<G>,
+        (wL, wR, wO, wV, wc): FlattenedConstraints<F>,
+    ) -> VerificationScalars<F> {
+        let (u_sq, u_inv_sq, allinv) = self.ipp_challenges_sq::<G>();
+        let (g, h) = self
+            .generator_scalars(proof, (&wL, &wR, &wO), (&u_sq, &u_inv_sq, allinv))
+            .unzip();
+        let delta = self.delta(&wL, &wR);
+        VerificationScalars {
+            g,
+            h,
+            ..self.proof_scalars(proof, delta, &wV, wc, (u_sq, u_inv_sq))
+        }
+    }
+
+    /// Returns the squares of the challenges of the inner-product proof
+    /// and of their inverses, and the inverse of their product.
+    fn ipp_challenges_sq<G: AffineRepr<ScalarField = F>>(&self) -> (Vec<F>, Vec<F>, F) {
+        trace_span!("inner_product_challenges", rounds = self.ipp.len());
+        use crate::inner_product_proof::InnerProductProof;
+
+        // With a single scalar, only s_0 = 1/(u_k...u_1) is computed.
+        let (u_sq, u_inv_sq, allinv) =
+            InnerProductProof::<G>::scalars_from_challenges(self.ipp.clone(), 1);
+        (u_sq, u_inv_sq, allinv[0])
+    }
+
+    /// Returns \\(\delta(y, z)\\), the inner product of the weights `wR`,
+    /// scaled by the powers of \\(y^{-1}\\), with the weights `wL`.
+    fn delta(&self, wL: &[F], wR: &[F]) -> F {
+        let y_inv = self.y.inverse().unwrap();
+        wR.iter()
+            .zip(wL)
+            .zip(iter::successors(Some(F::one()), |exp| Some(*exp * y_inv)))
+            .map(|((wRi, wLi), exp_y_inv)| *wRi * exp_y_inv * wLi)
+            .sum()
+    }
+
+    /// Returns the pairs of scalars of the \\(\mathbf G\\) and
+    /// \\(\mathbf H\\) generators, lazily, given the weights of the
+    /// flattened constraints and the values returned by
+    /// `ipp_challenges_sq`.
+    ///
+    /// The scalars are computed one pair at a time, with a constant
+    /// number of multiplications per pair, so that they can be fed to a
+    /// multiscalar multiplication without storing `padded_n` of them.
+    fn generator_scalars<'a, G: AffineRepr<ScalarField = F>>(
+        &'a self,
+        proof: &R1CSProof<G>,
+        (wL, wR, wO): (&'a [F], &'a [F], &'a [F]),
+        (u_sq, u_inv_sq, allinv): (&[F], &[F], F),
+    ) -> impl Iterator<Item = (F, F)> + 'a {
+        let n = self.phase_ends[self.phase_ends.len() - 1];
+        let padded_n = n.next_power_of_two();
+        let (x, a, b) = (self.x, proof.ipp_proof.a, proof.ipp_proof.b);
+        let y_inv = self.y.inverse().unwrap();
+
+        // The scalar s_i of the inner-product proof is s_0 = allinv times
+        // the challenges u_j^2 of the bits j set in i, where the challenge
+        // of bit j is u_sq[lg_n - 1 - j].  Counting from i - 1 to i sets
+        // the bit t = trailing_zeros(i) and clears the bits below it,
+        // i.e. multiplies s_{i-1} by steps[t], while s_{padded_n-1-i} is
+        // multiplied by the inverse of steps[t].
+        let lg_n = u_sq.len();
+        let bit = |j: usize| lg_n - 1 - j;
+        let (mut steps, mut steps_inv) = (Vec::with_capacity(lg_n), Vec::with_capacity(lg_n));
+        let (mut cleared, mut cleared_inv) = (F::one(), F::one());
+        for t in 0..lg_n {
+            steps.push(u_sq[bit(t)] * cleared);
+            steps_inv.push(u_inv_sq[bit(t)] * cleared_inv);
+            cleared *= u_inv_sq[bit(t)];
+            cleared_inv *= u_sq[bit(t)];
+        }
+        // s_{padded_n-1} has all the bits set.
+        let mut s_i = allinv;
+        let mut s_i_inv = allinv * cleared_inv;
+
+        let vector_len = self.vector_lens.iter().sum();
+        let pad = padded_n - n;
+        let weight = |w: &'a [F]| w.iter().copied().chain(iter::repeat_n(F::zero(), pad));
+
+        phase_factor_iter(&self.phase_ends, &self.u, (vector_len, self.c), pad)
+            .zip(iter::successors(Some(F::one()), move |exp| {
+                Some(*exp * y_inv)
+            }))
+            .zip(weight(wL).zip(weight(wR)).zip(weight(wO)))
+            .enumerate()
+            .map(move |(i, ((u_or_1, y_inv_i), ((wLi, wRi), wOi)))| {
+                if i > 0 {
+                    let t = i.trailing_zeros() as usize;
+                    s_i *= steps[t];
+                    s_i_inv *= steps_inv[t];
+                }
+                let g_i = u_or_1 * (x * y_inv_i * wRi - a * s_i);
+                let h_i = u_or_1 * (y_inv_i * (x * wLi + wOi - b * s_i_inv) - F::one());
+                (g_i, h_i)
+            })
+    }
+
+    /// Computes the verification scalars of `proof` other than those of
+    /// the generators, which are left empty, given the inner product
+    /// `delta` of the weights of the flattened constraints and the
+    /// squares of the challenges of the inner-product proof and of their
+    /// inverses.
+    fn proof_scalars<G: AffineRepr<ScalarField = F>>(
+        &self,
+        proof: &R1CSProof<G>,
+        delta: F,
+        wV: &[F],
+        wc: F,
+        (u_sq, u_inv_sq): (Vec<F>, Vec<F>),
+    ) -> VerificationScalars<F> {
+        let (u, c, x, w, r) = (&self.u, self.c, self.x, self.w, self.r);
+        let (a, b) = (proof.ipp_proof.a, proof.ipp_proof.b);
+        let xx = x * x;
+        let rxx = r * xx;
+        let xxx = x * xx;
+
+        VerificationScalars {
+            b: w * (proof.t_x - a * b) + r * (xx * (wc + delta) - proof.t_x),
+            b_blinding: -proof.e_blinding - r * proof.t_x_blinding,
+            g: Vec::new(),
+            h: Vec::new(),
+            A_I1: x,
+            A_O1: xx,
+            S1: xxx,
+            A_I2: u[0] * x,
+            A_O2: u[0] * xx,
+            S2: u[0] * xxx,
+            extra_phases: u[1..]
+                .iter()
+                .map(|u_k| (*u_k * x, *u_k * xx, *u_k * xxx))
+                .collect(),
+            V: wV.iter().map(|wVi| *wVi * rxx).collect(),
+            C: self.vector_lens.iter().map(|_| c * x).collect(),
+            S_C: proof.S_C.map(|_| c * xxx),
+            T_1: r * x,
+            T_3: rxx * x,
+            T_4: rxx * xx,
+            T_5: rxx * xxx,
+            T_6: rxx * xx * xx,
+            L_vec: u_sq,
+            R_vec: u_inv_sq,
+        }
+    }
+
+    /// Returns the terms of the multiscalar multiplication checking
+    /// `proof`, i.e. the verification scalars paired with their points,
+    /// computing the scalars of the generators lazily.
+    fn terms<'a, G: AffineRepr<ScalarField = F>>(
+        &'a self,
+        proof: &'a R1CSProof<G>,
+        (wL, wR, wO, wV, wc): &'a FlattenedConstraints<F>,
+        pc_gens: &'a PedersenGens<G>,
+        bp_gens: &'a BulletproofGens<G>,
+        (V, C): (&'a [G], &'a [G]),
+    ) -> impl Iterator<Item = (&'a G, F)> + 'a {
+        let padded_n = self.phase_ends[self.phase_ends.len() - 1].next_power_of_two();
+        let (u_sq, u_inv_sq, allinv) = self.ipp_challenges_sq::<G>();
+        let generators = bp_gens
+            .G(padded_n, 1)
+            .zip(bp_gens.H(padded_n, 1))
+            .zip(self.generator_scalars(proof, (wL, wR, wO), (&u_sq, &u_inv_sq, allinv)))
+            .flat_map(|((G_i, H_i), (g_i, h_i))| [(G_i, g_i), (H_i, h_i)]);
+
+        let delta = self.delta(wL, wR);
+        let scalars = self.proof_scalars(proof, delta, wV, *wc, (u_sq, u_inv_sq));
+        let pedersen = [
+            (&pc_gens.B, scalars.b),
+            (&pc_gens.B_blinding, scalars.b_blinding),
+        ];
+        let proof_scalars: Vec<F> = scalars.proof_scalars().collect();
+        pedersen
+            .into_iter()
+            .chain(generators)
+            .chain(proof_points(proof, V, C).zip(proof_scalars))
     }
 }
 
 /// Batch verification of R1CS proofs
-pub fn batch_verify<'a, G: AffineRepr, I, R: CryptoRng + RngCore>(
+///
+/// The verifiers may own their transcripts or borrow them, as for
+/// [`Verifier::new`].
+///
+/// With the `parallel` feature, the verification scalars of the proofs
+/// are computed on several threads once their challenges are derived
+/// from the transcripts, and so is the combined multiscalar
+/// multiplication.
+pub fn batch_verify<'a, G: AffineRepr, T, I, R: CryptoRng + RngCore>(
+    prng: &mut R,
+    instances: I,
+    pc_gens: &PedersenGens<G>,
+    bp_gens: &BulletproofGens<G>,
+) -> Result<(), R1CSError>
+where
+    T: TranscriptProtocol<G>,
+    I: IntoIterator<Item = (Verifier<G, T>, &'a R1CSProof<G>)>,
+{
+    let pending = instances
+        .into_iter()
+        .map(|(verifier, proof)| PendingEntry::new(prng, verifier, proof, bp_gens))
+        .collect::<Result<Vec<_>, _>>()?;
+    let entries = finish_entries(pending);
+    let entries: Vec<&BatchEntry<G>> = entries.iter().collect();
+
+    if batch_check(&entries, pc_gens, bp_gens) {
+        Ok(())
+    } else {
+        Err(R1CSError::VerificationError)
+    }
+}
+
+/// Same as [`batch_verify`], but identifies the invalid proofs when the
+/// batch does not verify.
+///
+/// On failure the batch is bisected, reusing the random factors of the
+/// combined check, until the invalid proofs are isolated, so that a few
+/// invalid proofs in a large batch cost a logarithmic number of
+/// additional multiscalar multiplications each.  Proofs that cannot be
+/// processed at all (e.g. malformed points) are reported as invalid too.
+/// The indices of the invalid proofs, in the order of `instances`, are
+/// returned in [`R1CSError::BatchVerificationError`].
+pub fn batch_verify_and_identify<'a, G: AffineRepr, T, I, R: CryptoRng + RngCore>(
     prng: &mut R,
     instances: I,
     pc_gens: &PedersenGens<G>,
     bp_gens: &BulletproofGens<G>,
 ) -> Result<(), R1CSError>
 where
-    I: IntoIterator<Item = (Verifier<G, &'a mut Transcript>, &'a R1CSProof<G>)>,
+    T: TranscriptProtocol<G>,
+    I: IntoIterator<Item = (Verifier<G, T>, &'a R1CSProof<G>)>,
 {
-    let mut max_n_padded = 0;
-    let mut verifiers: Vec<Verifier<G, _>> = vec![];
-    let mut proofs: Vec<&R1CSProof<G>> = vec![];
-    let mut verification_scalars = vec![];
-    for (verifier, proof) in instances.into_iter() {
-        // verification_scalars method is mutable, need to run before obtaining verifier.num_vars
-        let (verifier, scalars) = verifier.verification_scalars(proof, bp_gens)?;
-        let n = verifier.num_vars.next_power_of_two();
-        if n > max_n_padded {
-            max_n_padded = n;
+    let mut invalid_proofs = vec![];
+    let mut indices = vec![];
+    let mut pending = vec![];
+    for (i, (verifier, proof)) in instances.into_iter().enumerate() {
+        match PendingEntry::new(prng, verifier, proof, bp_gens) {
+            Ok(entry) => {
+                indices.push(i);
+                pending.push(entry);
+            }
+            Err(_) => invalid_proofs.push(i),
         }
-        verification_scalars.push(scalars);
-        verifiers.push(verifier);
-        proofs.push(proof);
     }
-    let mut all_scalars = vec![];
-    let mut all_elems = vec![];
+    let entries = finish_entries(pending);
+    let entries: Vec<(usize, &BatchEntry<G>)> = indices.into_iter().zip(&entries).collect();
+
+    invalid_proofs.extend(bisect_invalid(&entries, pc_gens, bp_gens));
+    if invalid_proofs.is_empty() {
+        Ok(())
+    } else {
+        invalid_proofs.sort_unstable();
+        Err(R1CSError::BatchVerificationError { invalid_proofs })
+    }
+}
 
-    for _ in 0..(2 * max_n_padded + 2) {
-        all_scalars.push(G::ScalarField::zero());
+/// Returns the indices of the entries which do not verify, checking
+/// halves of `entries` recursively.
+fn bisect_invalid<G: AffineRepr>(
+    entries: &[(usize, &BatchEntry<'_, G>)],
+    pc_gens: &PedersenGens<G>,
+    bp_gens: &BulletproofGens<G>,
+) -> Vec<usize> {
+    let batch: Vec<&BatchEntry<G>> = entries.iter().map(|(_, e)| *e).collect();
+    if batch.is_empty() || batch_check(&batch, pc_gens, bp_gens) {
+        return vec![];
     }
-    all_elems.push(pc_gens.B);
-    all_elems.push(pc_gens.B_blinding);
-    let gens = bp_gens.share(0);
-    for G in gens.G(max_n_padded) {
-        all_elems.push(*G);
+    if let [(i, _)] = entries {
+        return vec![*i];
     }
-    for H in gens.H(max_n_padded) {
-        all_elems.push(*H);
+    let (left, right) = entries.split_at(entries.len() / 2);
+    let bisect = |half| bisect_invalid(half, pc_gens, bp_gens);
+    #[cfg(feature = "parallel")]
+    let (mut invalid, invalid_right) = rayon::join(|| bisect(left), || bisect(right));
+    #[cfg(not(feature = "parallel"))]
+    let (mut invalid, invalid_right) = (bisect(left), bisect(right));
+    invalid.extend(invalid_right);
+    invalid
+}
+
+/// A proof of a batch whose challenges are derived from its transcript,
+/// along with the constraints of its verifier.  Unlike the verifier, it
+/// can be sent to another thread to compute the verification scalars.
+struct PendingEntry<'a, G: AffineRepr> {
+    proof: &'a R1CSProof<G>,
+    challenges: VerificationChallenges<G::ScalarField>,
+    alpha: G::ScalarField,
+    constraints: Vec<LinearCombination<G::ScalarField>>,
+    num_vars: usize,
+    public: Vec<G::ScalarField>,
+    V: Vec<G>,
+    C: Vec<G>,
+}
+
+impl<'a, G: AffineRepr> PendingEntry<'a, G> {
+    fn new<T: TranscriptProtocol<G>, R: CryptoRng + RngCore>(
+        prng: &mut R,
+        verifier: Verifier<G, T>,
+        proof: &'a R1CSProof<G>,
+        bp_gens: &BulletproofGens<G>,
+    ) -> Result<Self, R1CSError> {
+        let r = G::ScalarField::rand(prng);
+        let (verifier, challenges) =
+            verifier.compute_verification_challenges(proof, bp_gens, None, Some(r))?;
+        Ok(PendingEntry {
+            proof,
+            challenges,
+            alpha: G::ScalarField::rand(prng),
+            constraints: verifier.constraints,
+            num_vars: verifier.num_vars,
+            public: verifier.public,
+            V: verifier.V,
+            C: verifier.C,
+        })
     }
 
-    for ((verifier, proof), scalars) in verifiers
-        .into_iter()
-        .zip(proofs.iter())
-        .zip(verification_scalars.iter())
-    {
-        let alpha = G::ScalarField::rand(prng);
-        let scaled_scalars: Vec<G::ScalarField> = scalars.into_iter().map(|s| alpha * s).collect();
-        let padded_n = verifier.num_vars.next_power_of_two();
-        all_scalars[0] += scaled_scalars[0]; // B
-        all_scalars[1] += scaled_scalars[1]; // B_blinding
-                                             // g values
-        for (i, s) in (&scaled_scalars[2..2 + padded_n]).iter().enumerate() {
-            all_scalars[i + 2] += *s;
+    fn finish(self) -> BatchEntry<'a, G> {
+        let flattened = flatten_constraints(
+            &self.constraints,
+            self.num_vars,
+            self.V.len(),
+            &self.public,
+            &self.challenges.z,
+        );
+        BatchEntry {
+            proof: self.proof,
+            scalars: self.challenges.scalars(self.proof, flattened),
+            alpha: self.alpha,
+            V: self.V,
+            C: self.C,
         }
-        // h values
-        for (i, s) in (&scaled_scalars[2 + padded_n..2 + 2 * padded_n])
-            .iter()
-            .enumerate()
-        {
-            all_scalars[2 + max_n_padded + i] += *s;
+    }
+}
+
+/// Computes the verification scalars of the `pending` entries, on
+/// several threads with the `parallel` feature.
+fn finish_entries<G: AffineRepr>(pending: Vec<PendingEntry<'_, G>>) -> Vec<BatchEntry<'_, G>> {
+    cfg_into_iter!(pending).map(PendingEntry::finish).collect()
+}
+
+/// The contribution of a single proof to a batch verification: its
+/// verification scalars, to be scaled by a random factor, and the
+/// commitments of its verifier.  The points of the proof are borrowed.
+struct BatchEntry<'a, G: AffineRepr> {
+    proof: &'a R1CSProof<G>,
+    scalars: VerificationScalars<G::ScalarField>,
+    alpha: G::ScalarField,
+    V: Vec<G>,
+    C: Vec<G>,
+}
+
+impl<'a, G: AffineRepr> BatchEntry<'a, G> {
+    /// Returns the points specific to the proof with their scaled scalars.
+    fn proof_terms(&self) -> impl Iterator<Item = (&G, G::ScalarField)> + '_ {
+        proof_points(self.proof, &self.V, &self.C)
+            .zip(self.scalars.proof_scalars().map(move |s| self.alpha * s))
+    }
+}
+
+/// The maximum number of terms of a single multiscalar multiplication
+/// in [`batch_check`], which bounds its memory usage for large batches.
+const BATCH_MSM_CHUNK: usize = 1 << 16;
+
+/// Checks the entries of a batch with one multiscalar multiplication,
+/// sharing the Pedersen and \(\mathbf G\), \(\mathbf H\) generators.
+///
+/// The scalars of the shared generators are accumulated in place and
+/// multiplied with the generators borrowed from `bp_gens`, and the
+/// remaining terms are streamed from the entries in bounded chunks.
+fn batch_check<G: AffineRepr>(
+    entries: &[&BatchEntry<'_, G>],
+    pc_gens: &PedersenGens<G>,
+    bp_gens: &BulletproofGens<G>,
+) -> bool {
+    let max_n_padded = entries
+        .iter()
+        .map(|e| e.scalars.padded_n())
+        .max()
+        .unwrap_or(0);
+    let mut b = G::ScalarField::zero();
+    let mut b_blinding = G::ScalarField::zero();
+    let mut g = vec![G::ScalarField::zero(); max_n_padded];
+    let mut h = vec![G::ScalarField::zero(); max_n_padded];
+    for entry in entries {
+        b += entry.alpha * entry.scalars.b;
+        b_blinding += entry.alpha * entry.scalars.b_blinding;
+    }
+    cfg_iter_mut!(g)
+        .zip(cfg_iter_mut!(h))
+        .enumerate()
+        .for_each(|(i, (g_i, h_i))| {
+            for entry in entries.iter().filter(|e| i < e.scalars.padded_n()) {
+                *g_i += entry.alpha * entry.scalars.g[i];
+                *h_i += entry.alpha * entry.scalars.h[i];
+            }
+        });
+
+    let fixed = FixedTerms {
+        b,
+        b_blinding,
+        g: &g,
+        h: &h,
+        start: 0,
+        parties: 1,
+    };
+    let proof_terms = entries.iter().flat_map(|e| e.proof_terms());
+    let result = bp_gens.fixed_msm(pc_gens, fixed, &[], &[])
+        + chunked_msm(bp_gens.msm_backend(), proof_terms);
+    result.is_zero()
+}
+
+/// Computes the multiscalar multiplication of `terms` with `msm`, in
+/// chunks of at most [`BATCH_MSM_CHUNK`] terms.
+fn chunked_msm<'a, G: AffineRepr, I>(msm: &dyn MsmBackend<G>, terms: I) -> G::Group
+where
+    I: Iterator<Item = (&'a G, G::ScalarField)>,
+{
+    trace_span!("msm");
+    let mut terms = terms.peekable();
+    let mut result = G::Group::zero();
+    let mut points = Vec::new();
+    let mut scalars = Vec::new();
+    while terms.peek().is_some() {
+        points.clear();
+        scalars.clear();
+        for (point, scalar) in terms.by_ref().take(BATCH_MSM_CHUNK) {
+            points.push(*point);
+            scalars.push(scalar);
         }
+        result += msm.msm(&points, &scalars);
+    }
+    result
+}
 
-        for s in (&scaled_scalars[2 + 2 * padded_n..]).iter() {
-            all_scalars.push(*s);
+/// Computes the multiscalar multiplication of `terms` with `msm`, in
+/// chunks of `N` terms held in arrays rather than in vectors.
+fn buffered_msm<'a, G: AffineRepr, I, const N: usize>(msm: &dyn MsmBackend<G>, terms: I) -> G::Group
+where
+    I: Iterator<Item = (&'a G, G::ScalarField)>,
+{
+    assert!(N > 0, "the chunks of the multiplication must not be empty");
+    trace_span!("msm");
+    let mut points = [G::zero(); N];
+    let mut scalars = [G::ScalarField::zero(); N];
+    let mut len = 0;
+    let mut result = G::Group::zero();
+    for (point, scalar) in terms {
+        points[len] = *point;
+        scalars[len] = scalar;
+        len += 1;
+        if len == N {
+            result += msm.msm(&points, &scalars);
+            len = 0;
         }
-        all_elems.push(proof.A_I1);
-        all_elems.push(proof.A_O1);
-        all_elems.push(proof.S1);
-        all_elems.push(proof.A_I2);
-        all_elems.push(proof.A_O2);
-        all_elems.push(proof.S2);
-        all_elems.extend_from_slice(verifier.V.as_slice());
-        all_elems.push(proof.T_1);
-        all_elems.push(proof.T_3);
-        all_elems.push(proof.T_4);
-        all_elems.push(proof.T_5);
-        all_elems.push(proof.T_6);
-        all_elems.extend_from_slice(&proof.ipp_proof.L_vec);
-        all_elems.extend_from_slice(&proof.ipp_proof.R_vec);
-    }
-
-    let multi_exp = G::Group::msm(&all_elems, &all_scalars).unwrap();
-    if !multi_exp.is_zero() {
-        Err(R1CSError::VerificationError)
-    } else {
-        Ok(())
     }
+    if len > 0 {
+        result += msm.msm(&points[..len], &scalars[..len]);
+    }
+    result
 }
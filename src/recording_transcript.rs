@@ -0,0 +1,141 @@
+//! The `recording_transcript` module contains a transcript wrapper that
+//! reports every operation to a sink supplied by the caller, to debug
+//! provers and verifiers that diverge over any transcript, not only the
+//! Merlin transcripts logged by the `debug-transcript` feature.
+
+#![deny(missing_docs)]
+
+use ark_ec::AffineRepr;
+use ark_serialize::CanonicalSerialize;
+use ark_std::rand::{CryptoRng, RngCore};
+use ark_std::vec::Vec;
+
+use crate::transcript::{ByteTranscript, TranscriptProtocol};
+
+/// An operation on a transcript, reported by a [`RecordingTranscript`].
+///
+/// Scalars and points are reported in their compressed encodings, and
+/// `u64`s in their little-endian encodings, whatever the encodings of the
+/// inner transcript.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TranscriptEvent {
+    /// A message, a `u64`, a scalar or a point appended with `label`.
+    Append {
+        /// The label of the message.
+        label: &'static [u8],
+        /// The encoding of the message.
+        bytes: Vec<u8>,
+    },
+    /// Challenge bytes or a challenge scalar drawn with `label`.
+    Challenge {
+        /// The label of the challenge.
+        label: &'static [u8],
+        /// The encoding of the challenge.
+        bytes: Vec<u8>,
+    },
+}
+
+/// A transcript forwarding every operation to the transcript `T`, and
+/// reporting it to the `sink` as a [`TranscriptEvent`].
+///
+/// Recording the prover and the verifier of a proof separately and
+/// comparing the two lists of events shows the first operation where they
+/// diverge.  The witness RNG and the forked challenges of the provers,
+/// which do not change the transcript, are not reported.  The domain
+/// separators are those of [`TranscriptProtocol`], appended through the
+/// messages of `T`.
+///
+/// The events contain the messages appended by the provers, so the sink
+/// must not be exposed in production.
+pub struct RecordingTranscript<T, S: FnMut(TranscriptEvent)> {
+    inner: T,
+    sink: S,
+}
+
+impl<T, S: FnMut(TranscriptEvent)> RecordingTranscript<T, S> {
+    /// Wraps the transcript `inner`, reporting its operations to `sink`.
+    pub fn new(inner: T, sink: S) -> Self {
+        RecordingTranscript { inner, sink }
+    }
+
+    /// Returns the inner transcript.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: ByteTranscript, S: FnMut(TranscriptEvent)> ByteTranscript for RecordingTranscript<T, S> {
+    fn append_message(&mut self, label: &'static [u8], message: &[u8]) {
+        self.inner.append_message(label, message);
+        (self.sink)(TranscriptEvent::Append {
+            label,
+            bytes: message.to_vec(),
+        });
+    }
+
+    fn append_u64(&mut self, label: &'static [u8], x: u64) {
+        self.inner.append_u64(label, x);
+        (self.sink)(TranscriptEvent::Append {
+            label,
+            bytes: x.to_le_bytes().to_vec(),
+        });
+    }
+
+    fn challenge_bytes(&mut self, label: &'static [u8], dest: &mut [u8]) {
+        self.inner.challenge_bytes(label, dest);
+        (self.sink)(TranscriptEvent::Challenge {
+            label,
+            bytes: dest.to_vec(),
+        });
+    }
+}
+
+impl<G: AffineRepr, T: TranscriptProtocol<G>, S: FnMut(TranscriptEvent)> TranscriptProtocol<G>
+    for RecordingTranscript<T, S>
+{
+    type Rng = T::Rng;
+
+    fn append_scalar(&mut self, label: &'static [u8], scalar: &G::ScalarField) {
+        self.inner.append_scalar(label, scalar);
+        (self.sink)(TranscriptEvent::Append {
+            label,
+            bytes: compressed(scalar),
+        });
+    }
+
+    fn append_point(&mut self, label: &'static [u8], point: &G) {
+        self.inner.append_point(label, point);
+        (self.sink)(TranscriptEvent::Append {
+            label,
+            bytes: compressed(point),
+        });
+    }
+
+    fn challenge_scalar(&mut self, label: &'static [u8]) -> G::ScalarField {
+        let challenge = self.inner.challenge_scalar(label);
+        (self.sink)(TranscriptEvent::Challenge {
+            label,
+            bytes: compressed(&challenge),
+        });
+        challenge
+    }
+
+    fn forked_challenge_scalar(&self, label: &'static [u8]) -> G::ScalarField {
+        self.inner.forked_challenge_scalar(label)
+    }
+
+    fn witness_rng<R: RngCore + CryptoRng>(
+        &self,
+        witness: &[(&'static [u8], Vec<u8>)],
+        rng: &mut R,
+    ) -> T::Rng {
+        self.inner.witness_rng(witness, rng)
+    }
+}
+
+/// Returns the compressed encoding of `value`.
+fn compressed<V: CanonicalSerialize>(value: &V) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(value.compressed_size());
+    value.serialize_compressed(&mut bytes).unwrap();
+    bytes
+}
@@ -1,28 +1,122 @@
-//! Defines a `TranscriptProtocol` trait for using a Merlin transcript.
+//! Defines the `ByteTranscript` and `TranscriptProtocol` traits of the
+//! Fiat-Shamir transcripts of the proofs, and implements them for Merlin
+//! transcripts.
+//!
+//! The provers and verifiers only use transcripts through these traits,
+//! so that other constructions, e.g. the [`KeccakTranscript`] for EVM
+//! verifiers or an algebraic sponge such as Poseidon for recursive
+//! verifiers, can be plugged in instead of Merlin.
+//!
+//! [`KeccakTranscript`]: crate::KeccakTranscript
+//!
+//! For Merlin transcripts, the domain separators and labels are those of the upstream dalek
+//! `bulletproofs` crate, but points are appended in their uncompressed
+//! encodings, and challenges are sampled with a `ChaChaRng` seeded with
+//! 32 bytes of the transcript.  The [`DalekCompatTranscript`] wraps a
+//! Merlin transcript with the encodings of the dalek crate instead, so
+//! that the transcripts match those of its implementations in other
+//! languages: compressed points, and challenges reduced from 64 bytes
+//! of the transcript.
+//!
+//! [`DalekCompatTranscript`]: crate::DalekCompatTranscript
 
 use ark_ec::AffineRepr;
 use ark_serialize::CanonicalSerialize;
-use ark_std::{rand::SeedableRng, vec::Vec, UniformRand};
-use merlin::Transcript;
+use ark_std::rand::{CryptoRng, RngCore};
+use ark_std::vec::Vec;
+use ark_std::{rand::SeedableRng, UniformRand};
+use merlin::{Transcript, TranscriptRng};
 use rand_chacha::ChaChaRng;
 
 use crate::errors::ProofError;
 
-pub trait TranscriptProtocol<G: AffineRepr> {
+/// The byte-level operations of a transcript, which do not depend on
+/// the group of the proofs.
+///
+/// This is the transcript that constraint systems lease to gadgets with
+/// [`ConstraintSystem::transcript`](crate::r1cs::ConstraintSystem::transcript).
+pub trait ByteTranscript {
+    /// Append a `message` with the given `label`.
+    fn append_message(&mut self, label: &'static [u8], message: &[u8]);
+
+    /// Append a `u64` with the given `label`.
+    fn append_u64(&mut self, label: &'static [u8], x: u64);
+
+    /// Fill `dest` with challenge bytes with the given `label`.
+    fn challenge_bytes(&mut self, label: &'static [u8], dest: &mut [u8]);
+}
+
+/// A Fiat-Shamir transcript of proofs over the group `G`.
+///
+/// Implementations provide the encodings of scalars and points, the
+/// derivation of challenge scalars and the witness RNG of the provers;
+/// the domain separators are defined once, on top of the byte-level
+/// operations of [`ByteTranscript`].  The provers and verifiers accept
+/// owned transcripts as well as mutable references to them.
+pub trait TranscriptProtocol<G: AffineRepr>: ByteTranscript {
+    /// The RNG of the provers, keyed with the transcript and the witness.
+    type Rng: RngCore + CryptoRng;
+
     /// Append a domain separator for an `n`-bit, `m`-party range proof.
-    fn rangeproof_domain_sep(&mut self, n: u64, m: u64);
+    fn rangeproof_domain_sep(&mut self, n: u64, m: u64) {
+        self.append_message(b"dom-sep", b"rangeproof v1");
+        self.append_u64(b"n", n);
+        self.append_u64(b"m", m);
+    }
 
     /// Append a domain separator for a length-`n` inner product proof.
-    fn innerproduct_domain_sep(&mut self, n: u64);
+    fn innerproduct_domain_sep(&mut self, n: u64) {
+        self.append_message(b"dom-sep", b"ipp v1");
+        self.append_u64(b"n", n);
+    }
 
     /// Append a domain separator for a constraint system.
-    fn r1cs_domain_sep(&mut self);
+    fn r1cs_domain_sep(&mut self) {
+        self.append_message(b"dom-sep", b"r1cs v1");
+    }
 
     /// Commit a domain separator for a CS without randomized constraints.
-    fn r1cs_1phase_domain_sep(&mut self);
+    fn r1cs_1phase_domain_sep(&mut self) {
+        self.append_message(b"dom-sep", b"r1cs-1phase");
+    }
 
     /// Commit a domain separator for a CS with randomized constraints.
-    fn r1cs_2phase_domain_sep(&mut self);
+    fn r1cs_2phase_domain_sep(&mut self) {
+        self.append_message(b"dom-sep", b"r1cs-2phase");
+    }
+
+    /// Commit a domain separator for the randomization phase number
+    /// `phase` (starting from 3) of a CS with more than two phases.
+    fn r1cs_nphase_domain_sep(&mut self, phase: u64) {
+        self.append_message(b"dom-sep", b"r1cs-nphase");
+        self.append_u64(b"phase", phase);
+    }
+
+    /// Append the digest of the structure of a constraint system.
+    fn r1cs_circuit_digest(&mut self, digest: &[u8; 32]) {
+        self.append_message(b"dom-sep", b"r1cs-circuit");
+        self.append_message(b"circuit", digest);
+    }
+
+    /// Append a domain separator for a proof of the openings of `n`
+    /// Pedersen commitments.
+    fn opening_domain_sep(&mut self, n: u64) {
+        self.append_message(b"dom-sep", b"opening v1");
+        self.append_u64(b"n", n);
+    }
+
+    /// Append a domain separator for a proof that commitments in two
+    /// groups commit to the same `n`-bit value.
+    fn cross_curve_domain_sep(&mut self, n: u64) {
+        self.append_message(b"dom-sep", b"cross-curve v1");
+        self.append_u64(b"n", n);
+    }
+
+    /// Append a domain separator for a proof of equality of discrete
+    /// logarithms with respect to two bases.
+    fn dleq_domain_sep(&mut self) {
+        self.append_message(b"dom-sep", b"dleq v1");
+    }
 
     /// Append a `scalar` with the given `label`.
     fn append_scalar(&mut self, label: &'static [u8], scalar: &G::ScalarField);
@@ -36,67 +130,128 @@ pub trait TranscriptProtocol<G: AffineRepr> {
         &mut self,
         label: &'static [u8],
         point: &G,
-    ) -> Result<(), ProofError>;
+    ) -> Result<(), ProofError> {
+        if point.is_zero() {
+            Err(ProofError::InvalidPoint { label })
+        } else {
+            self.append_point(label, point);
+            Ok(())
+        }
+    }
 
     /// Compute a `label`ed challenge variable.
     fn challenge_scalar(&mut self, label: &'static [u8]) -> G::ScalarField;
+
+    /// Compute a `label`ed challenge variable from a copy of the
+    /// transcript, leaving the transcript itself unchanged.
+    fn forked_challenge_scalar(&self, label: &'static [u8]) -> G::ScalarField;
+
+    /// Create an RNG keyed with the transcript, the `witness`, as a list
+    /// of labeled secret byte strings, and 32 bytes from `rng`.
+    ///
+    /// The blinding factors of the provers are drawn from this RNG, so
+    /// they stay unpredictable when `rng` is weak, as long as the witness
+    /// is secret.  The transcript is not changed.
+    fn witness_rng<R: RngCore + CryptoRng>(
+        &self,
+        witness: &[(&'static [u8], Vec<u8>)],
+        rng: &mut R,
+    ) -> Self::Rng;
 }
 
-impl<G: AffineRepr> TranscriptProtocol<G> for Transcript {
-    fn rangeproof_domain_sep(&mut self, n: u64, m: u64) {
-        self.append_message(b"dom-sep", b"rangeproof v1");
-        self.append_u64(b"n", n);
-        self.append_u64(b"m", m);
+impl<T: ByteTranscript + ?Sized> ByteTranscript for &mut T {
+    fn append_message(&mut self, label: &'static [u8], message: &[u8]) {
+        (**self).append_message(label, message);
     }
 
-    fn innerproduct_domain_sep(&mut self, n: u64) {
-        self.append_message(b"dom-sep", b"ipp v1");
-        self.append_u64(b"n", n);
+    fn append_u64(&mut self, label: &'static [u8], x: u64) {
+        (**self).append_u64(label, x);
     }
 
-    fn r1cs_domain_sep(&mut self) {
-        self.append_message(b"dom-sep", b"r1cs v1");
+    fn challenge_bytes(&mut self, label: &'static [u8], dest: &mut [u8]) {
+        (**self).challenge_bytes(label, dest);
     }
+}
 
-    fn r1cs_1phase_domain_sep(&mut self) {
-        self.append_message(b"dom-sep", b"r1cs-1phase");
+impl<G: AffineRepr, T: TranscriptProtocol<G> + ?Sized> TranscriptProtocol<G> for &mut T {
+    type Rng = T::Rng;
+
+    fn append_scalar(&mut self, label: &'static [u8], scalar: &G::ScalarField) {
+        (**self).append_scalar(label, scalar);
     }
 
-    fn r1cs_2phase_domain_sep(&mut self) {
-        self.append_message(b"dom-sep", b"r1cs-2phase");
+    fn append_point(&mut self, label: &'static [u8], point: &G) {
+        (**self).append_point(label, point);
+    }
+
+    fn challenge_scalar(&mut self, label: &'static [u8]) -> G::ScalarField {
+        (**self).challenge_scalar(label)
     }
 
+    fn forked_challenge_scalar(&self, label: &'static [u8]) -> G::ScalarField {
+        (**self).forked_challenge_scalar(label)
+    }
+
+    fn witness_rng<R: RngCore + CryptoRng>(
+        &self,
+        witness: &[(&'static [u8], Vec<u8>)],
+        rng: &mut R,
+    ) -> Self::Rng {
+        (**self).witness_rng(witness, rng)
+    }
+}
+
+impl ByteTranscript for Transcript {
+    fn append_message(&mut self, label: &'static [u8], message: &[u8]) {
+        Transcript::append_message(self, label, message);
+    }
+
+    fn append_u64(&mut self, label: &'static [u8], x: u64) {
+        Transcript::append_u64(self, label, x);
+    }
+
+    fn challenge_bytes(&mut self, label: &'static [u8], dest: &mut [u8]) {
+        Transcript::challenge_bytes(self, label, dest);
+    }
+}
+
+impl<G: AffineRepr> TranscriptProtocol<G> for Transcript {
+    type Rng = TranscriptRng;
+
     fn append_scalar(&mut self, label: &'static [u8], scalar: &G::ScalarField) {
         let mut bytes = Vec::new();
         scalar.serialize_uncompressed(&mut bytes).unwrap();
-        self.append_message(label, &bytes);
+        Transcript::append_message(self, label, &bytes);
     }
 
     fn append_point(&mut self, label: &'static [u8], point: &G) {
         let mut bytes = Vec::new();
         point.serialize_uncompressed(&mut bytes).unwrap();
-        self.append_message(label, &bytes);
-    }
-
-    fn validate_and_append_point(
-        &mut self,
-        label: &'static [u8],
-        point: &G,
-    ) -> Result<(), ProofError> {
-        if point.is_zero() {
-            Err(ProofError::VerificationError)
-        } else {
-            let mut bytes = Vec::new();
-            point.serialize_uncompressed(&mut bytes).unwrap();
-            Ok(self.append_message(label, &bytes))
-        }
+        Transcript::append_message(self, label, &bytes);
     }
 
     fn challenge_scalar(&mut self, label: &'static [u8]) -> G::ScalarField {
         let mut buf = [0u8; 32];
-        self.challenge_bytes(label, &mut buf);
+        Transcript::challenge_bytes(self, label, &mut buf);
 
         let mut prng = ChaChaRng::from_seed(buf);
         G::ScalarField::rand(&mut prng)
     }
+
+    fn forked_challenge_scalar(&self, label: &'static [u8]) -> G::ScalarField {
+        <Transcript as TranscriptProtocol<G>>::challenge_scalar(&mut self.clone(), label)
+    }
+
+    fn witness_rng<R: RngCore + CryptoRng>(
+        &self,
+        witness: &[(&'static [u8], Vec<u8>)],
+        rng: &mut R,
+    ) -> TranscriptRng {
+        witness
+            .iter()
+            .fold(self.build_rng(), |builder, (label, bytes)| {
+                builder.rekey_with_witness_bytes(label, bytes)
+            })
+            .finalize(rng)
+    }
 }
@@ -1,46 +1,54 @@
 #![deny(missing_docs)]
 #![allow(non_snake_case)]
 
-use ark_ec::AffineRepr;
-use ark_std::{vec, vec::Vec, One, Zero};
-use clear_on_drop::clear::Clear;
+use ark_ff::PrimeField;
+use ark_std::{cfg_into_iter, ops::AddAssign, vec, vec::Vec};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+#[cfg(feature = "yoloproofs")]
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
 use crate::inner_product_proof::inner_product;
 
+/// Enters a `tracing` span at the debug level, with the given name and
+/// fields, until the end of the current scope.  Does nothing unless the
+/// `tracing` feature is enabled.
+macro_rules! trace_span {
+    ($name:literal $(, $($fields:tt)*)?) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!($name $(, $($fields)*)?).entered();
+    };
+}
+
 /// Represents a degree-3 vector polynomial
 /// \\(\mathbf{a} + \mathbf{b} \cdot x + \mathbf{c} \cdot x^2 + \mathbf{d} \cdot x^3 \\).
 #[cfg(feature = "yoloproofs")]
-pub struct VecPoly3<G: AffineRepr>(
-    pub Vec<G::ScalarField>,
-    pub Vec<G::ScalarField>,
-    pub Vec<G::ScalarField>,
-    pub Vec<G::ScalarField>,
-);
+pub struct VecPoly3<F: PrimeField>(pub Vec<F>, pub Vec<F>, pub Vec<F>, pub Vec<F>);
 
 /// Represents a degree-6 scalar polynomial, without the zeroth degree
 /// \\(a \cdot x + b \cdot x^2 + c \cdot x^3 + d \cdot x^4 + e \cdot x^5 + f \cdot x^6\\)
 #[cfg(feature = "yoloproofs")]
-pub struct Poly6<G: AffineRepr> {
-    pub t1: G::ScalarField,
-    pub t2: G::ScalarField,
-    pub t3: G::ScalarField,
-    pub t4: G::ScalarField,
-    pub t5: G::ScalarField,
-    pub t6: G::ScalarField,
+pub struct Poly6<F: PrimeField> {
+    pub t1: F,
+    pub t2: F,
+    pub t3: F,
+    pub t4: F,
+    pub t5: F,
+    pub t6: F,
 }
 
-/// Provides an iterator over the powers of a `Fr`.
+/// Provides an iterator over the powers of a field element.
 ///
 /// This struct is created by the `exp_iter` function.
-pub struct FrExp<G: AffineRepr> {
-    x: G::ScalarField,
-    next_exp_x: G::ScalarField,
+pub struct FrExp<F: PrimeField> {
+    x: F,
+    next_exp_x: F,
 }
 
-impl<G: AffineRepr> Iterator for FrExp<G> {
-    type Item = G::ScalarField;
+impl<F: PrimeField> Iterator for FrExp<F> {
+    type Item = F;
 
-    fn next(&mut self) -> Option<G::ScalarField> {
+    fn next(&mut self) -> Option<F> {
         let exp_x = self.next_exp_x;
         self.next_exp_x *= self.x;
         Some(exp_x)
@@ -52,19 +60,47 @@ impl<G: AffineRepr> Iterator for FrExp<G> {
 }
 
 /// Return an iterator of the powers of `x`.
-pub fn exp_iter<G: AffineRepr>(x: G::ScalarField) -> FrExp<G> {
-    let next_exp_x = G::ScalarField::one();
+pub fn exp_iter<F: PrimeField>(x: F) -> FrExp<F> {
+    let next_exp_x = F::one();
     FrExp { x, next_exp_x }
 }
 
+/// Returns the lowercase hexadecimal encoding of `bytes`.
+#[cfg(feature = "yoloproofs")]
+pub fn hex(bytes: &[u8]) -> ark_std::string::String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    bytes
+        .iter()
+        .flat_map(|b| [DIGITS[(b >> 4) as usize], DIGITS[(b & 0xf) as usize]])
+        .map(char::from)
+        .collect()
+}
+
+/// Compares the compressed encodings of `a` and `b` in constant time.
+///
+/// Only the lengths of the encodings, which are public, may leak.
+#[cfg(feature = "yoloproofs")]
+pub fn ct_eq_serialized<T: ark_serialize::CanonicalSerialize>(a: &T, b: &T) -> subtle::Choice {
+    use subtle::ConstantTimeEq;
+
+    let encode = |value: &T| {
+        let mut bytes = Vec::with_capacity(value.compressed_size());
+        value
+            .serialize_compressed(&mut bytes)
+            .expect("serializing into a vector cannot fail");
+        bytes
+    };
+    encode(a).as_slice().ct_eq(encode(b).as_slice())
+}
+
 #[cfg(feature = "yoloproofs")]
-impl<G: AffineRepr> VecPoly3<G> {
+impl<F: PrimeField> VecPoly3<F> {
     pub fn zero(n: usize) -> Self {
         VecPoly3(
-            vec![G::ScalarField::zero(); n],
-            vec![G::ScalarField::zero(); n],
-            vec![G::ScalarField::zero(); n],
-            vec![G::ScalarField::zero(); n],
+            vec![F::zero(); n],
+            vec![F::zero(); n],
+            vec![F::zero(); n],
+            vec![F::zero(); n],
         )
     }
 
@@ -72,7 +108,7 @@ impl<G: AffineRepr> VecPoly3<G> {
     /// - `lhs.0` is zero;
     /// - `rhs.2` is zero;
     /// This is the case in the constraint system proof.
-    pub fn special_inner_product(lhs: &Self, rhs: &Self) -> Poly6<G> {
+    pub fn special_inner_product(lhs: &Self, rhs: &Self) -> Poly6<F> {
         // TODO: make checks that l_poly.0 and r_poly.2 are zero.
 
         let t1 = inner_product(&lhs.1, &rhs.0);
@@ -92,63 +128,95 @@ impl<G: AffineRepr> VecPoly3<G> {
         }
     }
 
-    pub fn eval(&self, x: G::ScalarField) -> Vec<G::ScalarField> {
-        let n = self.0.len();
-        let mut out = vec![G::ScalarField::zero(); n];
-        for i in 0..n {
-            out[i] = self.0[i] + x * (self.1[i] + x * (self.2[i] + x * self.3[i]));
-        }
-        out
+    pub fn eval(&self, x: F) -> Vec<F> {
+        cfg_into_iter!(0..self.0.len())
+            .map(|i| self.0[i] + x * (self.1[i] + x * (self.2[i] + x * self.3[i])))
+            .collect()
     }
 }
 
 #[cfg(feature = "yoloproofs")]
-impl<G: AffineRepr> Poly6<G> {
-    pub fn eval(&self, x: G::ScalarField) -> G::ScalarField {
+impl<F: PrimeField> Poly6<F> {
+    pub fn zero() -> Self {
+        Poly6 {
+            t1: F::zero(),
+            t2: F::zero(),
+            t3: F::zero(),
+            t4: F::zero(),
+            t5: F::zero(),
+            t6: F::zero(),
+        }
+    }
+
+    pub fn eval(&self, x: F) -> F {
         x * (self.t1 + x * (self.t2 + x * (self.t3 + x * (self.t4 + x * (self.t5 + x * self.t6)))))
     }
 }
 
 #[cfg(feature = "yoloproofs")]
-impl<G: AffineRepr> Drop for VecPoly3<G> {
+impl<F: PrimeField> AddAssign for Poly6<F> {
+    fn add_assign(&mut self, rhs: Self) {
+        self.t1 += rhs.t1;
+        self.t2 += rhs.t2;
+        self.t3 += rhs.t3;
+        self.t4 += rhs.t4;
+        self.t5 += rhs.t5;
+        self.t6 += rhs.t6;
+    }
+}
+
+#[cfg(feature = "yoloproofs")]
+impl<F: PrimeField> Zeroize for VecPoly3<F> {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+        self.1.zeroize();
+        self.2.zeroize();
+        self.3.zeroize();
+    }
+}
+
+#[cfg(feature = "yoloproofs")]
+impl<F: PrimeField> Drop for VecPoly3<F> {
     fn drop(&mut self) {
-        for e in self.0.iter_mut() {
-            e.clear();
-        }
-        for e in self.1.iter_mut() {
-            e.clear();
-        }
-        for e in self.2.iter_mut() {
-            e.clear();
-        }
-        for e in self.3.iter_mut() {
-            e.clear();
-        }
+        self.zeroize();
     }
 }
 
 #[cfg(feature = "yoloproofs")]
-impl<G: AffineRepr> Drop for Poly6<G> {
+impl<F: PrimeField> ZeroizeOnDrop for VecPoly3<F> {}
+
+#[cfg(feature = "yoloproofs")]
+impl<F: PrimeField> Zeroize for Poly6<F> {
+    fn zeroize(&mut self) {
+        self.t1.zeroize();
+        self.t2.zeroize();
+        self.t3.zeroize();
+        self.t4.zeroize();
+        self.t5.zeroize();
+        self.t6.zeroize();
+    }
+}
+
+#[cfg(feature = "yoloproofs")]
+impl<F: PrimeField> Drop for Poly6<F> {
     fn drop(&mut self) {
-        self.t1.clear();
-        self.t2.clear();
-        self.t3.clear();
-        self.t4.clear();
-        self.t5.clear();
-        self.t6.clear();
+        self.zeroize();
     }
 }
 
+#[cfg(feature = "yoloproofs")]
+impl<F: PrimeField> ZeroizeOnDrop for Poly6<F> {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ark_std::Zero;
 
     #[test]
     fn exp_2_is_powers_of_2() {
-        type G = ark_secq256k1::Affine;
         type F = ark_secq256k1::Fr;
 
-        let exp_2: Vec<_> = exp_iter::<G>(F::from(2u64)).take(4).collect();
+        let exp_2: Vec<_> = exp_iter(F::from(2u64)).take(4).collect();
 
         assert_eq!(exp_2[0], F::from(1u64));
         assert_eq!(exp_2[1], F::from(2u64));
@@ -156,6 +224,24 @@ mod tests {
         assert_eq!(exp_2[3], F::from(8u64));
     }
 
+    #[test]
+    #[cfg(feature = "yoloproofs")]
+    fn special_inner_product_evaluates_to_inner_product() {
+        use ark_std::UniformRand;
+
+        // The polynomials are generic over the field, e.g. that of ed25519.
+        type F = ark_ed25519::Fr;
+
+        let mut rng = ark_std::test_rng();
+        let mut rand_vec = || (0..4).map(|_| F::rand(&mut rng)).collect::<Vec<_>>();
+        let l = VecPoly3(vec![F::zero(); 4], rand_vec(), rand_vec(), rand_vec());
+        let r = VecPoly3(rand_vec(), rand_vec(), vec![F::zero(); 4], rand_vec());
+        let t = VecPoly3::special_inner_product(&l, &r);
+
+        let x = F::rand(&mut rng);
+        assert_eq!(t.eval(x), inner_product(&l.eval(x), &r.eval(x)));
+    }
+
     #[test]
     fn test_inner_product() {
         type F = ark_secq256k1::Fr;
@@ -166,13 +252,15 @@ mod tests {
     }
 
     #[test]
-    fn vec_of_scalars_clear_on_drop() {
+    fn vec_of_scalars_zeroize() {
+        use zeroize::Zeroize;
+
         type F = ark_secq256k1::Fr;
 
         let mut v = vec![F::from(24u64), F::from(42u64)];
 
         for e in v.iter_mut() {
-            e.clear();
+            e.zeroize();
         }
 
         fn flat_slice<T>(x: &[T]) -> &[u8] {
@@ -0,0 +1,143 @@
+//! The `validation` module contains the policies for checking the points
+//! of proofs and commitments parsed from bytes.
+
+#![deny(missing_docs)]
+
+use ark_ec::AffineRepr;
+use ark_serialize::{Compress, Validate};
+
+use crate::errors::ProofError;
+
+/// How the points of proofs and commitments are checked when they are
+/// parsed from bytes.
+///
+/// Consensus-critical verifiers parse bytes from untrusted parties, and
+/// must check that every point is on the curve and in the prime-order
+/// subgroup, as [`PointValidation::AlwaysCheck`] does.  Internal
+/// pipelines reading back bytes they wrote themselves, or that were
+/// already checked, can skip these checks with
+/// [`PointValidation::TrustedInput`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PointValidation {
+    /// Checks that every point is on the curve and in the prime-order
+    /// subgroup.  This is the default.
+    #[default]
+    AlwaysCheck,
+    /// Only decompresses the points, without the subgroup checks.
+    ///
+    /// Verifying a proof with points outside of the prime-order subgroup
+    /// is unsound, so this policy must only be used for bytes that cannot
+    /// come from an attacker.
+    TrustedInput,
+}
+
+impl PointValidation {
+    /// Returns the `ark-serialize` validation mode of the policy.
+    pub(crate) fn mode(self) -> Validate {
+        match self {
+            PointValidation::AlwaysCheck => Validate::Yes,
+            PointValidation::TrustedInput => Validate::No,
+        }
+    }
+}
+
+/// Parses a compressed point, e.g. a commitment, from `bytes`, checking
+/// it according to `validation`.
+///
+/// Returns an error if the bytes are not exactly the encoding of a point,
+/// or if the point fails the checks of `validation`.
+pub fn point_from_bytes<G: AffineRepr>(
+    mut bytes: &[u8],
+    validation: PointValidation,
+) -> Result<G, ProofError> {
+    let point = G::deserialize_with_mode(&mut bytes, Compress::Yes, validation.mode())
+        .map_err(|_| ProofError::FormatError)?;
+    if !bytes.is_empty() {
+        return Err(ProofError::FormatError);
+    }
+    Ok(point)
+}
+
+/// Which commitments, beyond the points of the proofs that are always
+/// checked, the R1CS verifiers reject when they are the identity.
+///
+/// The verifiers always reject proofs whose first-phase commitments,
+/// commitments to the coefficients of \\(t(x)\\) or inner-product points are
+/// the identity.  The identity is a valid commitment otherwise, e.g. to
+/// zero with a zero blinding factor, but several protocols treat it as
+/// malformed, and can reject it with this policy rather than screening
+/// the bytes themselves.  By default, nothing more is rejected.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct IdentityPolicy {
+    /// Rejects identity high-level variable commitments \\(V\\) and
+    /// vector commitments \\(C\\).
+    pub commitments: bool,
+    /// Rejects identity commitments \\(A_I, A_O, S\\) of the randomization
+    /// phases that allocate multipliers.  The commitments of the phases
+    /// without multipliers are always the identity.
+    pub phase_commitments: bool,
+}
+
+impl IdentityPolicy {
+    /// Returns the policy rejecting every identity commitment that a
+    /// valid proof cannot have.
+    pub fn strict() -> Self {
+        IdentityPolicy {
+            commitments: true,
+            phase_commitments: true,
+        }
+    }
+}
+
+/// Returns an error naming `label` if one of the `points` is the identity.
+pub(crate) fn reject_identity<'a, G: AffineRepr>(
+    label: &'static [u8],
+    points: impl IntoIterator<Item = &'a G>,
+) -> Result<(), ProofError> {
+    if points.into_iter().any(|point| point.is_zero()) {
+        return Err(ProofError::InvalidPoint { label });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+    use ark_std::vec::Vec;
+
+    use crate::generators::PedersenGens;
+
+    type G = ark_secq256k1::Affine;
+
+    #[test]
+    fn point_from_bytes_policies() {
+        let mut bytes = Vec::new();
+        PedersenGens::<G>::default()
+            .B_blinding
+            .serialize_compressed(&mut bytes)
+            .unwrap();
+        for validation in [PointValidation::AlwaysCheck, PointValidation::TrustedInput] {
+            let point = point_from_bytes::<G>(&bytes, validation).unwrap();
+            assert_eq!(point, PedersenGens::<G>::default().B_blinding);
+        }
+
+        // Trailing bytes, and coordinates that are not on the curve, are
+        // rejected whatever the policy.
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        let off_curve = (0u8..=255)
+            .map(|x| {
+                let mut b = bytes.clone();
+                b[0] = x;
+                b
+            })
+            .find(|b| G::deserialize_compressed(&b[..]).is_err())
+            .unwrap();
+        for validation in [PointValidation::AlwaysCheck, PointValidation::TrustedInput] {
+            assert!(point_from_bytes::<G>(&trailing, validation).is_err());
+            assert!(point_from_bytes::<G>(&off_curve, validation).is_err());
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! Bindings for WebAssembly, so that browser wallets can create and
+//! verify proofs client-side.
+//!
+//! The bindings work on byte slices over the secq256k1 curve: scalars
+//! are 32-byte little-endian encodings, while points and proofs use the
+//! compressed encodings of `ark-serialize`.  Proofs are bound to the
+//! `label` passed by the caller, which must be the same when proving
+//! and verifying.
+//!
+//! Randomness comes from the `crypto.getRandomValues` API of the
+//! browser, through the `js` feature of `getrandom`, and the bindings
+//! never spawn threads.
+
+use ark_ff::UniformRand;
+use ark_secq256k1::{Affine, Fr};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::{string::ToString, vec::Vec};
+use merlin::Transcript;
+use rand::rngs::OsRng;
+use wasm_bindgen::prelude::*;
+
+use crate::r1cs::{statements, R1CSProof};
+use crate::{BulletproofGens, PedersenGens};
+
+/// A range proof together with the commitment to the proven value.
+#[wasm_bindgen]
+pub struct RangeProof {
+    proof: Vec<u8>,
+    commitment: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl RangeProof {
+    /// Returns the serialized proof.
+    #[wasm_bindgen(getter)]
+    pub fn proof(&self) -> Vec<u8> {
+        self.proof.clone()
+    }
+
+    /// Returns the compressed commitment to the proven value.
+    #[wasm_bindgen(getter)]
+    pub fn commitment(&self) -> Vec<u8> {
+        self.commitment.clone()
+    }
+}
+
+/// Returns a uniformly random scalar, e.g. to use as a blinding factor.
+#[wasm_bindgen]
+pub fn random_scalar() -> Vec<u8> {
+    to_bytes(&Fr::rand(&mut OsRng))
+}
+
+/// Returns the compressed Pedersen commitment to `value` with `blinding`.
+#[wasm_bindgen]
+pub fn commit(value: u64, blinding: &[u8]) -> Result<Vec<u8>, JsError> {
+    let blinding = from_bytes::<Fr>(blinding)?;
+    let commitment = PedersenGens::<Affine>::default().commit(Fr::from(value), blinding);
+    Ok(to_bytes(&commitment))
+}
+
+/// Proves that `value` is in the range `[0, 2^bits)`, for `bits`
+/// between 1 and 64, committing to it with `blinding`.
+#[wasm_bindgen]
+pub fn prove_in_range(
+    value: u64,
+    blinding: &[u8],
+    bits: usize,
+    label: &[u8],
+) -> Result<RangeProof, JsError> {
+    check_bits(bits)?;
+    let blinding = from_bytes::<Fr>(blinding)?;
+    let (proof, commitment) = statements::prove_in_range::<Affine, _>(
+        &PedersenGens::default(),
+        &gens(bits),
+        &mut transcript(label),
+        Fr::from(value),
+        blinding,
+        bits,
+        &mut OsRng,
+    )
+    .map_err(|e| JsError::new(&e.to_string()))?;
+    Ok(RangeProof {
+        proof: proof.to_bytes().map_err(|e| JsError::new(&e.to_string()))?,
+        commitment: to_bytes(&commitment),
+    })
+}
+
+/// Verifies a range proof created by [`prove_in_range`] with the same
+/// `bits` and `label`.
+///
+/// Returns whether the proof is valid, or an error if the proof or the
+/// commitment cannot be parsed.
+#[wasm_bindgen]
+pub fn verify_in_range(
+    proof: &[u8],
+    commitment: &[u8],
+    bits: usize,
+    label: &[u8],
+) -> Result<bool, JsError> {
+    check_bits(bits)?;
+    let proof = R1CSProof::<Affine>::from_bytes(proof).map_err(|e| JsError::new(&e.to_string()))?;
+    let commitment = from_bytes::<Affine>(commitment)?;
+    Ok(statements::verify_in_range(
+        &PedersenGens::default(),
+        &gens(bits),
+        &mut transcript(label),
+        &proof,
+        &commitment,
+        bits,
+    )
+    .is_ok())
+}
+
+/// Returns generators for a range proof of `bits` bits, which the
+/// statement pads to a power of two multipliers.
+fn gens(bits: usize) -> BulletproofGens<Affine> {
+    BulletproofGens::new(bits.next_power_of_two(), 1)
+}
+
+fn transcript(label: &[u8]) -> Transcript {
+    let mut transcript = Transcript::new(b"WasmBindings");
+    transcript.append_message(b"label", label);
+    transcript
+}
+
+fn check_bits(bits: usize) -> Result<(), JsError> {
+    if (1..=64).contains(&bits) {
+        Ok(())
+    } else {
+        Err(JsError::new("the number of bits must be between 1 and 64"))
+    }
+}
+
+fn to_bytes<T: CanonicalSerialize>(value: &T) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    value.serialize_compressed(&mut bytes).unwrap();
+    bytes
+}
+
+fn from_bytes<T: CanonicalDeserialize>(mut bytes: &[u8]) -> Result<T, JsError> {
+    let value = T::deserialize_compressed(&mut bytes).map_err(|e| JsError::new(&e.to_string()))?;
+    if !bytes.is_empty() {
+        return Err(JsError::new("trailing bytes after the encoded value"));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn range_proof_roundtrip() {
+        let blinding = random_scalar();
+        let range_proof = prove_in_range(42, &blinding, 8, b"test").unwrap();
+        assert_eq!(range_proof.commitment(), commit(42, &blinding).unwrap());
+
+        let (proof, commitment) = (range_proof.proof(), range_proof.commitment());
+        assert!(verify_in_range(&proof, &commitment, 8, b"test").unwrap());
+        assert!(!verify_in_range(&proof, &commitment, 8, b"other").unwrap());
+        let other = commit(43, &blinding).unwrap();
+        assert!(!verify_in_range(&proof, &other, 8, b"test").unwrap());
+    }
+
+    #[test]
+    fn range_proof_roundtrip_with_odd_width() {
+        let blinding = random_scalar();
+        for bits in [3, 63] {
+            let range_proof = prove_in_range(5, &blinding, bits, b"test").unwrap();
+            let (proof, commitment) = (range_proof.proof(), range_proof.commitment());
+            assert!(verify_in_range(&proof, &commitment, bits, b"test").unwrap());
+        }
+    }
+}
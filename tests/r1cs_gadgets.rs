@@ -0,0 +1,1700 @@
+#![allow(non_snake_case)]
+
+use ark_bulletproofs::{
+    r1cs::{gadgets::*, test_utils::MockCS, *},
+    BulletproofGens, PedersenGens,
+};
+use ark_ff::{Field, UniformRand};
+use ark_secq256k1::{Affine, Fr};
+use merlin::Transcript;
+
+/// Proves and verifies the statement built by `circuit` over the
+/// commitments to `values`.
+fn prove_and_verify<C>(values: &[Fr], circuit: &C) -> Result<(), R1CSError>
+where
+    C: Gadget<Fr, Input = Vec<AllocatedScalar<Fr>>, Output = ()>,
+{
+    prove_and_verify_with_capacity(values, circuit, 1024)
+}
+
+/// Like [`prove_and_verify`], for circuits with up to `capacity`
+/// multipliers.
+fn prove_and_verify_with_capacity<C>(
+    values: &[Fr],
+    circuit: &C,
+    capacity: usize,
+) -> Result<(), R1CSError>
+where
+    C: Gadget<Fr, Input = Vec<AllocatedScalar<Fr>>, Output = ()>,
+{
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(capacity, 1);
+
+    let (proof, commitments) = {
+        let mut prover_transcript = Transcript::new(b"GadgetTest");
+        let mut rng = rand::thread_rng();
+        let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+
+        let (commitments, inputs): (Vec<_>, Vec<_>) = values
+            .iter()
+            .map(|v| {
+                let (com, var) = prover.commit(*v, Fr::rand(&mut rng));
+                (com, AllocatedScalar::new(var, Some(*v)))
+            })
+            .unzip();
+        circuit.synthesize(&mut prover, inputs)?;
+
+        (prover.prove(&mut rng, &bp_gens)?, commitments)
+    };
+
+    let mut verifier_transcript = Transcript::new(b"GadgetTest");
+    let mut verifier = Verifier::new(&mut verifier_transcript);
+    let inputs = commitments
+        .iter()
+        .map(|V| AllocatedScalar::new(verifier.commit(*V), None))
+        .collect();
+    circuit.synthesize(&mut verifier, inputs)?;
+
+    verifier.verify(&proof, &pc_gens, &bp_gens)
+}
+
+// Bit decomposition gadget
+
+/// Decomposes the single input into `n` bits and checks the returned
+/// assignments against `expected`.
+struct DecomposeCircuit {
+    n: usize,
+    expected: Option<u64>,
+}
+
+impl Gadget<Fr> for DecomposeCircuit {
+    type Input = Vec<AllocatedScalar<Fr>>;
+    type Output = ();
+
+    fn synthesize<CS: ConstraintSystem<Fr>>(
+        &self,
+        cs: &mut CS,
+        inputs: Self::Input,
+    ) -> Result<(), R1CSError> {
+        let bits = BitDecomposition::new(self.n).synthesize(cs, inputs[0])?;
+        assert_eq!(bits.len(), self.n);
+        if let (Some(v), Some(_)) = (self.expected, inputs[0].assignment) {
+            for (i, bit) in bits.iter().enumerate() {
+                assert_eq!(bit.assignment, Some(Fr::from((v >> i) & 1)));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn bit_decomposition_gadget() {
+    for (v, n) in [(0u64, 1), (1, 1), (5, 3), (200, 8), (u64::MAX, 64)] {
+        let circuit = DecomposeCircuit {
+            n,
+            expected: Some(v),
+        };
+        assert!(prove_and_verify(&[Fr::from(v)], &circuit).is_ok());
+    }
+    for (v, n) in [(2u64, 1), (8, 3), (256, 8)] {
+        let circuit = DecomposeCircuit { n, expected: None };
+        assert!(prove_and_verify(&[Fr::from(v)], &circuit).is_err());
+    }
+    let circuit = DecomposeCircuit {
+        n: 8,
+        expected: None,
+    };
+    assert!(prove_and_verify(&[-Fr::from(1u64)], &circuit).is_err());
+}
+
+// Comparison gadgets
+
+/// Compares the two inputs, either enforcing the comparison or
+/// constraining its flag to `expected`.
+struct CompareCircuit {
+    op: Comparison,
+    expected: Option<bool>,
+}
+
+impl Gadget<Fr> for CompareCircuit {
+    type Input = Vec<AllocatedScalar<Fr>>;
+    type Output = ();
+
+    fn synthesize<CS: ConstraintSystem<Fr>>(
+        &self,
+        cs: &mut CS,
+        inputs: Self::Input,
+    ) -> Result<(), R1CSError> {
+        let gadget = Compare::new(self.op, 16);
+        match self.expected {
+            None => gadget.enforce(cs, inputs[0], inputs[1]),
+            Some(expected) => {
+                let flag = gadget.synthesize(cs, (inputs[0], inputs[1]))?;
+                cs.constrain(flag.variable - Fr::from(expected));
+                Ok(())
+            }
+        }
+    }
+}
+
+#[test]
+fn comparison_gadgets() {
+    use Comparison::*;
+
+    let pairs = [(3u64, 5u64), (5, 5), (5, 3), (0, 65535), (65535, 0)];
+    for (a, b) in pairs {
+        let values = [Fr::from(a), Fr::from(b)];
+        for (op, holds) in [
+            (LessThan, a < b),
+            (LessOrEqual, a <= b),
+            (GreaterThan, a > b),
+            (GreaterOrEqual, a >= b),
+        ] {
+            let enforce = CompareCircuit { op, expected: None };
+            assert_eq!(prove_and_verify(&values, &enforce).is_ok(), holds);
+
+            let flag = CompareCircuit {
+                op,
+                expected: Some(holds),
+            };
+            assert!(prove_and_verify(&values, &flag).is_ok());
+        }
+    }
+
+    // The flag cannot be flipped.
+    let flag = CompareCircuit {
+        op: LessThan,
+        expected: Some(true),
+    };
+    assert!(prove_and_verify(&[Fr::from(5u64), Fr::from(3u64)], &flag).is_err());
+}
+
+// Zero and equality gadgets
+
+enum EqualityCheck {
+    IsEqual(bool),
+    IsZeroDifference(bool),
+    Equal,
+    NotEqual,
+}
+
+impl Gadget<Fr> for EqualityCheck {
+    type Input = Vec<AllocatedScalar<Fr>>;
+    type Output = ();
+
+    fn synthesize<CS: ConstraintSystem<Fr>>(
+        &self,
+        cs: &mut CS,
+        inputs: Self::Input,
+    ) -> Result<(), R1CSError> {
+        let (a, b) = (inputs[0], inputs[1]);
+        match self {
+            EqualityCheck::IsEqual(expected) => {
+                let flag = is_equal(cs, a, b)?;
+                cs.constrain(flag.variable - Fr::from(*expected));
+            }
+            EqualityCheck::IsZeroDifference(expected) => {
+                let diff = a.assignment.zip(b.assignment).map(|(a, b)| a - b);
+                let flag = IsZero.synthesize(cs, (a.variable - b.variable, diff))?;
+                cs.constrain(flag.variable - Fr::from(*expected));
+            }
+            EqualityCheck::Equal => enforce_equal(cs, a.into(), b.into()),
+            EqualityCheck::NotEqual => enforce_not_equal(cs, a, b)?,
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn equality_gadgets() {
+    use EqualityCheck::*;
+
+    for (a, b) in [(7u64, 7u64), (7, 8), (0, 0), (0, 1)] {
+        let values = [Fr::from(a), Fr::from(b)];
+        let equal = a == b;
+
+        assert!(prove_and_verify(&values, &IsEqual(equal)).is_ok());
+        assert!(prove_and_verify(&values, &IsEqual(!equal)).is_err());
+        assert!(prove_and_verify(&values, &IsZeroDifference(equal)).is_ok());
+        assert!(prove_and_verify(&values, &IsZeroDifference(!equal)).is_err());
+        assert_eq!(prove_and_verify(&values, &Equal).is_ok(), equal);
+        assert_eq!(prove_and_verify(&values, &NotEqual).is_ok(), !equal);
+    }
+}
+
+// Boolean gadgets
+
+/// Checks every boolean operation on the two committed bits `a` and `b`
+/// against the truth table, with the expected `AND` flipped if `flip` is set.
+struct BooleanCircuit {
+    a: bool,
+    b: bool,
+    flip: bool,
+}
+
+impl Gadget<Fr> for BooleanCircuit {
+    type Input = Vec<AllocatedScalar<Fr>>;
+    type Output = ();
+
+    fn synthesize<CS: ConstraintSystem<Fr>>(
+        &self,
+        cs: &mut CS,
+        inputs: Self::Input,
+    ) -> Result<(), R1CSError> {
+        let mut bits = Vec::new();
+        for input in inputs {
+            Boolean::enforce(cs, input.into());
+            bits.push(Boolean::from_bit(input)?);
+        }
+        let (a, b) = (&bits[0], &bits[1]);
+        let c = Boolean::allocate(cs, a.value().zip(b.value()).map(|(a, b)| a | b))?;
+        let (x, y) = (self.a, self.b);
+
+        let results = [
+            (a.not(), !x),
+            (Boolean::and(cs, a, b), (x & y) ^ self.flip),
+            (Boolean::nand(cs, a, b), !(x & y)),
+            (Boolean::or(cs, a, b), x | y),
+            (Boolean::xor(cs, a, b), x ^ y),
+            (
+                Boolean::and_all(cs, &[a.clone(), b.clone(), c.clone()]),
+                x & y,
+            ),
+            (Boolean::or_all(cs, &[a.clone(), b.clone(), a.not()]), true),
+            (Boolean::and_all(cs, &[]), true),
+            (Boolean::or_all(cs, &[]), false),
+            (c, x | y),
+        ];
+        for (result, expected) in results {
+            if !self.flip {
+                if let Some(value) = result.value() {
+                    assert_eq!(value, expected);
+                }
+            }
+            cs.constrain(result.lc() - Fr::from(expected));
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn boolean_gadgets() {
+    for (a, b) in [(false, false), (false, true), (true, false), (true, true)] {
+        let values = [Fr::from(a), Fr::from(b)];
+        let circuit = BooleanCircuit { a, b, flip: false };
+        assert!(prove_and_verify(&values, &circuit).is_ok());
+        let circuit = BooleanCircuit { a, b, flip: true };
+        assert!(prove_and_verify(&values, &circuit).is_err());
+    }
+
+    // Non-boolean inputs are rejected.
+    let circuit = BooleanCircuit {
+        a: false,
+        b: false,
+        flip: false,
+    };
+    assert!(prove_and_verify(&[Fr::from(2u64), Fr::from(0u64)], &circuit).is_err());
+}
+
+// Set membership gadgets
+
+/// Checks that the first input is in the public `set`, and in the set
+/// committed as the remaining inputs.
+struct MembershipCircuit {
+    set: Vec<u64>,
+}
+
+impl Gadget<Fr> for MembershipCircuit {
+    type Input = Vec<AllocatedScalar<Fr>>;
+    type Output = ();
+
+    fn synthesize<CS: ConstraintSystem<Fr>>(
+        &self,
+        cs: &mut CS,
+        inputs: Self::Input,
+    ) -> Result<(), R1CSError> {
+        let set = self.set.iter().map(|s| Fr::from(*s)).collect();
+        SetMembership::new(set).synthesize(cs, inputs[0])?;
+        CommittedSetMembership.synthesize(cs, (inputs[0], inputs[1..].to_vec()))
+    }
+}
+
+#[test]
+fn set_membership_gadgets() {
+    let set: Vec<u64> = (0..500).map(|i| i * 7 + 3).collect();
+    let circuit = MembershipCircuit { set: set.clone() };
+    let mut values: Vec<Fr> = set.iter().map(|s| Fr::from(*s)).collect();
+
+    for v in [3u64, 10, 3496] {
+        values.insert(0, Fr::from(v));
+        assert!(prove_and_verify(&values, &circuit).is_ok());
+        values.remove(0);
+    }
+    for v in [0u64, 4, 3497] {
+        values.insert(0, Fr::from(v));
+        assert!(prove_and_verify(&values, &circuit).is_err());
+        values.remove(0);
+    }
+
+    let empty = MembershipCircuit { set: Vec::new() };
+    assert!(prove_and_verify(&[Fr::from(3u64)], &empty).is_err());
+}
+
+// Merkle membership gadget
+
+/// A toy algebraic hash, `acc = (acc + x_i + i + 1)^2` over the inputs.
+struct ToyHash;
+
+impl CircuitHash<Fr> for ToyHash {
+    fn hash(&self, inputs: &[Fr]) -> Fr {
+        inputs
+            .iter()
+            .enumerate()
+            .fold(Fr::from(0u64), |acc, (i, x)| {
+                let t = acc + x + Fr::from(i as u64 + 1);
+                t * t
+            })
+    }
+
+    fn synthesize<CS: ConstraintSystem<Fr>>(
+        &self,
+        cs: &mut CS,
+        inputs: &[LinearCombination<Fr>],
+        _: Option<&[Fr]>,
+    ) -> Result<LinearCombination<Fr>, R1CSError> {
+        Ok(inputs
+            .iter()
+            .enumerate()
+            .fold(LinearCombination::default(), |acc, (i, x)| {
+                let t = acc + x.clone() + Fr::from(i as u64 + 1);
+                let (_, _, o) = cs.multiply(t.clone(), t);
+                o.into()
+            }))
+    }
+}
+
+/// Proves that the single input is a leaf of a tree with the given
+/// `root`, using `path` for the prover.
+struct MerkleCircuit {
+    arity: usize,
+    depth: usize,
+    path: Vec<MerkleLevel<Fr>>,
+    root: Fr,
+}
+
+impl Gadget<Fr> for MerkleCircuit {
+    type Input = Vec<AllocatedScalar<Fr>>;
+    type Output = ();
+
+    fn synthesize<CS: ConstraintSystem<Fr>>(
+        &self,
+        cs: &mut CS,
+        inputs: Self::Input,
+    ) -> Result<(), R1CSError> {
+        let path = inputs[0].assignment.map(|_| self.path.clone());
+        MerkleMembership::new(ToyHash, self.arity, self.depth)
+            .enforce_root(cs, inputs[0], path, self.root)
+    }
+}
+
+#[test]
+fn merkle_membership_gadget() {
+    for (arity, depth) in [(2, 4), (3, 3), (4, 2)] {
+        let leaves: Vec<Fr> = (0..16u64).map(|i| Fr::from(i * i + 1)).collect();
+        let tree = MerkleTree::new(ToyHash, arity, depth, &leaves).unwrap();
+        let root = tree.root();
+
+        for index in [0, 7, 15] {
+            let path = tree.path(index).unwrap();
+            assert_eq!(merkle_root(&ToyHash, leaves[index], &path), root);
+
+            let circuit = MerkleCircuit {
+                arity,
+                depth,
+                path: path.clone(),
+                root,
+            };
+            assert!(prove_and_verify(&[leaves[index]], &circuit).is_ok());
+            assert!(prove_and_verify(&[leaves[index] + Fr::from(1u64)], &circuit).is_err());
+
+            let circuit = MerkleCircuit {
+                arity,
+                depth,
+                path,
+                root: root + Fr::from(1u64),
+            };
+            assert!(prove_and_verify(&[leaves[index]], &circuit).is_err());
+        }
+    }
+
+    assert!(MerkleTree::new(ToyHash, 2, 2, &[Fr::from(0u64); 5]).is_err());
+}
+
+// Poseidon hash gadget
+
+/// Checks that the hash of the inputs is the public `expected` value.
+struct HashCircuit<H> {
+    hash: H,
+    expected: Fr,
+}
+
+impl<H: CircuitHash<Fr>> Gadget<Fr> for HashCircuit<H> {
+    type Input = Vec<AllocatedScalar<Fr>>;
+    type Output = ();
+
+    fn synthesize<CS: ConstraintSystem<Fr>>(
+        &self,
+        cs: &mut CS,
+        inputs: Self::Input,
+    ) -> Result<(), R1CSError> {
+        let values: Option<Vec<_>> = inputs.iter().map(|x| x.assignment).collect();
+        let inputs: Vec<_> = inputs.into_iter().map(|x| x.into()).collect();
+        let hash = self.hash.synthesize(cs, &inputs, values.as_deref())?;
+        cs.constrain(hash - self.expected);
+        Ok(())
+    }
+}
+
+/// Checks the gadget of `hash` against its native implementation.
+fn hash_gadget_helper<H: CircuitHash<Fr>>(hash: &H) {
+    for n in [0u64, 1, 2, 3] {
+        let values: Vec<Fr> = (0..n).map(|i| Fr::from(i + 10)).collect();
+        let expected = hash.hash(&values);
+        let circuit = HashCircuit { hash, expected };
+        assert!(prove_and_verify(&values, &circuit).is_ok());
+
+        let circuit = HashCircuit {
+            hash,
+            expected: expected + Fr::from(1u64),
+        };
+        assert!(prove_and_verify(&values, &circuit).is_err());
+    }
+
+    // Inputs are not interchangeable, and their number is bound.
+    let (a, b) = (Fr::from(1u64), Fr::from(2u64));
+    assert_ne!(hash.hash(&[a, b]), hash.hash(&[b, a]));
+    assert_ne!(hash.hash(&[a]), hash.hash(&[a, Fr::from(0u64)]));
+}
+
+#[test]
+fn poseidon_gadget() {
+    hash_gadget_helper(&Poseidon::new(
+        PoseidonParams::<Fr>::new(3, 5, 8, 57).unwrap(),
+    ));
+}
+
+#[test]
+fn poseidon_params_for_supported_fields() {
+    fn check<F: ark_ff::PrimeField>() {
+        // The smallest S-box exponent which is a permutation of `F`.
+        let new = |t| {
+            [3, 5, 7, 11]
+                .iter()
+                .find_map(|alpha| PoseidonParams::<F>::new(t, *alpha, 8, 57).ok())
+                .unwrap()
+        };
+        let params = new(3);
+        assert_eq!(params.round_constants.len(), 8 + 57);
+        assert_eq!(params, new(3));
+        assert_ne!(params, new(5));
+    }
+    check::<ark_secq256k1::Fr>();
+    check::<ark_secp256k1::Fr>();
+    check::<ark_ed25519::Fr>();
+    check::<ark_bulletproofs::curve::zorro::Fr>();
+
+    // x^3 is not a permutation of the secp256k1 base field.
+    assert!(PoseidonParams::<Fr>::new(3, 3, 8, 84).is_err());
+}
+
+#[test]
+fn poseidon_merkle_membership() {
+    let poseidon = Poseidon::new(PoseidonParams::<Fr>::new(3, 5, 8, 57).unwrap());
+    let leaves: Vec<Fr> = (0..4u64).map(Fr::from).collect();
+    let tree = MerkleTree::new(&poseidon, 2, 2, &leaves).unwrap();
+    let path = tree.path(2).unwrap();
+    let root = tree.root();
+
+    struct Circuit<'a> {
+        poseidon: &'a Poseidon<Fr>,
+        path: Vec<MerkleLevel<Fr>>,
+        root: Fr,
+    }
+
+    impl<'a> Gadget<Fr> for Circuit<'a> {
+        type Input = Vec<AllocatedScalar<Fr>>;
+        type Output = ();
+
+        fn synthesize<CS: ConstraintSystem<Fr>>(
+            &self,
+            cs: &mut CS,
+            inputs: Self::Input,
+        ) -> Result<(), R1CSError> {
+            let path = inputs[0].assignment.map(|_| self.path.clone());
+            MerkleMembership::new(self.poseidon, 2, 2).enforce_root(cs, inputs[0], path, self.root)
+        }
+    }
+
+    let circuit = Circuit {
+        poseidon: &poseidon,
+        path,
+        root,
+    };
+    assert!(prove_and_verify(&[leaves[2]], &circuit).is_ok());
+    assert!(prove_and_verify(&[leaves[3]], &circuit).is_err());
+}
+
+// MiMC and Rescue hash gadgets
+
+#[test]
+fn mimc_gadget() {
+    let mimc = MiMC::<Fr>::with_exponent(5).unwrap();
+    assert_eq!(mimc.rounds(), 111);
+    hash_gadget_helper(&mimc);
+
+    // x^7 is not a permutation of the secp256k1 base field, but it is
+    // of the secp256k1 scalar field.
+    assert!(MiMC::<Fr>::mimc7().is_err());
+    assert_eq!(MiMC::<ark_secp256k1::Fr>::mimc7().unwrap().rounds(), 92);
+}
+
+#[test]
+fn rescue_gadget() {
+    let params = RescueParams::<Fr>::with_width(3).unwrap();
+
+    // The inverse S-box inverts the S-box.
+    let x = Fr::from(12345u64);
+    assert_eq!(x.pow([params.alpha]).pow(&params.alpha_inv), x);
+
+    hash_gadget_helper(&Rescue::new(params));
+}
+
+#[test]
+fn hash_params_for_supported_fields() {
+    fn check<F: ark_ff::PrimeField>() {
+        let params = RescueParams::<F>::with_width(3).unwrap();
+        let x = F::from(7u64);
+        assert_eq!(x.pow(&params.alpha_inv).pow([params.alpha]), x);
+        assert!(MiMC::<F>::with_exponent(params.alpha).is_ok());
+    }
+    check::<ark_secq256k1::Fr>();
+    check::<ark_secp256k1::Fr>();
+    check::<ark_ed25519::Fr>();
+    check::<ark_bulletproofs::curve::zorro::Fr>();
+}
+
+// Embedded curve and Pedersen hash gadgets
+
+/// Hashes the committed bits with a Pedersen hash on secp256k1, and
+/// checks the result against the public `expected` point.
+struct PedersenBitsCircuit {
+    hash: PedersenHash<ark_secp256k1::Config>,
+    expected: ark_secp256k1::Affine,
+}
+
+impl Gadget<Fr> for PedersenBitsCircuit {
+    type Input = Vec<AllocatedScalar<Fr>>;
+    type Output = ();
+
+    fn synthesize<CS: ConstraintSystem<Fr>>(
+        &self,
+        cs: &mut CS,
+        inputs: Self::Input,
+    ) -> Result<(), R1CSError> {
+        let mut bits = Vec::new();
+        for input in inputs {
+            Boolean::enforce(cs, input.into());
+            bits.push(Boolean::from_bit(input)?);
+        }
+        let point = self.hash.hash_bits_gadget(cs, &bits)?;
+        cs.constrain(point.x - self.expected.x);
+        cs.constrain(point.y - self.expected.y);
+        Ok(())
+    }
+}
+
+#[test]
+fn pedersen_hash_gadget() {
+    let hash = PedersenHash::<ark_secp256k1::Config>::new(3, 32).unwrap();
+    assert_eq!(hash.max_bits(), 33);
+
+    let bits: Vec<bool> = (0..20).map(|i| (i * 7) % 3 == 0).collect();
+    let values: Vec<Fr> = bits.iter().map(|b| Fr::from(*b)).collect();
+    let expected = hash.hash_bits(&bits).unwrap();
+    assert!(expected.is_on_curve());
+
+    let circuit = PedersenBitsCircuit {
+        hash: hash.clone(),
+        expected,
+    };
+    assert!(prove_and_verify(&values, &circuit).is_ok());
+
+    let mut flipped = bits.clone();
+    flipped[5] = !flipped[5];
+    assert_ne!(hash.hash_bits(&flipped).unwrap(), expected);
+    let values: Vec<Fr> = flipped.iter().map(|b| Fr::from(*b)).collect();
+    assert!(prove_and_verify(&values, &circuit).is_err());
+
+    assert!(hash.hash_bits(&[false; 34]).is_err());
+
+    // As a hash of field elements.
+    let hash = PedersenHash::<ark_secp256k1::Config>::new(3, 256).unwrap();
+    let x = Fr::from(123456789u64);
+    let circuit = HashCircuit {
+        expected: hash.hash(&[x]),
+        hash,
+    };
+    assert!(prove_and_verify(&[x], &circuit).is_ok());
+    assert!(prove_and_verify(&[x + Fr::from(1u64)], &circuit).is_err());
+}
+
+/// Adds the two committed points, given by their coordinates.
+struct PointAddCircuit {
+    expected: ark_secp256k1::Affine,
+}
+
+impl Gadget<Fr> for PointAddCircuit {
+    type Input = Vec<AllocatedScalar<Fr>>;
+    type Output = ();
+
+    fn synthesize<CS: ConstraintSystem<Fr>>(
+        &self,
+        cs: &mut CS,
+        inputs: Self::Input,
+    ) -> Result<(), R1CSError> {
+        let point = |x: AllocatedScalar<Fr>, y: AllocatedScalar<Fr>| PointVar {
+            x: x.into(),
+            y: y.into(),
+            value: x.assignment.zip(y.assignment),
+        };
+        let p = point(inputs[0], inputs[1]);
+        let q = point(inputs[2], inputs[3]);
+        let sum = p.add_incomplete(cs, &q)?;
+        cs.constrain(sum.x.clone() - self.expected.x);
+        cs.constrain(sum.y.clone() - self.expected.y);
+
+        // The sum is on the curve.
+        let r = PointVar::allocate::<ark_secp256k1::Config, _>(cs, sum.to_affine())?;
+        cs.constrain(r.x - sum.x);
+        cs.constrain(r.y - sum.y);
+        Ok(())
+    }
+}
+
+#[test]
+fn point_addition_gadget() {
+    use ark_ec::{AffineRepr, CurveGroup};
+
+    let g = ark_secp256k1::Affine::generator();
+    let p = (g * ark_secp256k1::Fr::from(5u64)).into_affine();
+    let q = (g * ark_secp256k1::Fr::from(7u64)).into_affine();
+    let expected = (g * ark_secp256k1::Fr::from(12u64)).into_affine();
+
+    let circuit = PointAddCircuit { expected };
+    assert!(prove_and_verify(&[p.x, p.y, q.x, q.y], &circuit).is_ok());
+
+    // Doubling is not supported by incomplete addition.
+    let circuit = PointAddCircuit {
+        expected: (g * ark_secp256k1::Fr::from(10u64)).into_affine(),
+    };
+    assert!(prove_and_verify(&[p.x, p.y, p.x, p.y], &circuit).is_err());
+}
+
+// SHA-256 compression gadget
+
+/// Converts committed values in `[0, 2^32)` to integers.
+fn to_uint32s<CS: ConstraintSystem<Fr>>(
+    cs: &mut CS,
+    inputs: &[AllocatedScalar<Fr>],
+) -> Result<Vec<UInt32<Fr>>, R1CSError> {
+    inputs
+        .iter()
+        .map(|x| {
+            let bits = BitDecomposition::new(32).synthesize(cs, *x)?;
+            UInt32::from_bits(
+                bits.into_iter()
+                    .map(Boolean::from_bit)
+                    .collect::<Result<_, _>>()?,
+            )
+        })
+        .collect()
+}
+
+/// Checks `UInt32` arithmetic on the two committed integers.
+struct UInt32Circuit {
+    expected: [u32; 4],
+}
+
+impl Gadget<Fr> for UInt32Circuit {
+    type Input = Vec<AllocatedScalar<Fr>>;
+    type Output = ();
+
+    fn synthesize<CS: ConstraintSystem<Fr>>(
+        &self,
+        cs: &mut CS,
+        inputs: Self::Input,
+    ) -> Result<(), R1CSError> {
+        let x = to_uint32s(cs, &inputs)?;
+        let (a, b) = (&x[0], &x[1]);
+        let results = [
+            UInt32::add_many(cs, &[a.clone(), b.clone(), UInt32::constant(0xffff_ffff)])?,
+            UInt32::xor(cs, &a.rotr(7), &b.shr(3)),
+            UInt32::and(cs, &a.not(), b),
+            UInt32::or(cs, a, &b.rotr(31)),
+        ];
+        for (r, e) in results.iter().zip(self.expected) {
+            cs.constrain(r.lc() - Fr::from(e));
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn uint32_gadget() {
+    let (a, b) = (0xdead_beefu32, 0x0123_4567u32);
+    let circuit = UInt32Circuit {
+        expected: [
+            a.wrapping_add(b).wrapping_sub(1),
+            a.rotate_right(7) ^ (b >> 3),
+            !a & b,
+            a | b.rotate_right(31),
+        ],
+    };
+    let values = [Fr::from(a), Fr::from(b)];
+    assert!(prove_and_verify(&values, &circuit).is_ok());
+    let values = [Fr::from(a), Fr::from(b + 1)];
+    assert!(prove_and_verify(&values, &circuit).is_err());
+}
+
+/// Compresses the committed 16-word block, starting from the SHA-256
+/// initial hash value.
+struct Sha256Circuit {
+    expected: [u32; 8],
+}
+
+impl Gadget<Fr> for Sha256Circuit {
+    type Input = Vec<AllocatedScalar<Fr>>;
+    type Output = ();
+
+    fn synthesize<CS: ConstraintSystem<Fr>>(
+        &self,
+        cs: &mut CS,
+        inputs: Self::Input,
+    ) -> Result<(), R1CSError> {
+        let block = to_uint32s(cs, &inputs)?;
+        let iv: Vec<_> = SHA256_IV.iter().map(|w| UInt32::constant(*w)).collect();
+        let state = sha256_compress_gadget(cs, &iv, &block)?;
+        for (s, e) in state.iter().zip(self.expected) {
+            cs.constrain(s.lc() - Fr::from(e));
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn sha256_compression_gadget() {
+    // The padded block of SHA-256("abc").
+    let mut block = [0u32; 16];
+    block[0] = 0x6162_6380;
+    block[15] = 24;
+    let mut state = SHA256_IV;
+    sha256_compress(&mut state, &block);
+    assert_eq!(
+        state,
+        [
+            0xba7816bf, 0x8f01cfea, 0x414140de, 0x5dae2223, 0xb00361a3, 0x96177a9c, 0xb410ff61,
+            0xf20015ad,
+        ]
+    );
+
+    let circuit = Sha256Circuit { expected: state };
+    let values: Vec<Fr> = block.iter().map(|w| Fr::from(*w)).collect();
+    assert!(prove_and_verify_with_capacity(&values, &circuit, 1 << 15).is_ok());
+
+    let mut values = values;
+    values[1] = Fr::from(1u64);
+    assert!(prove_and_verify_with_capacity(&values, &circuit, 1 << 15).is_err());
+}
+
+// Permutation gadgets
+
+/// Proves and verifies that the committed tuples `y` are a permutation
+/// of the committed tuples `x`.
+fn permutation_helper(x: &[Vec<u64>], y: &[Vec<u64>]) -> Result<(), R1CSError> {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(128, 1);
+
+    let (proof, x_commitments, y_commitments) = {
+        let mut prover_transcript = Transcript::new(b"PermutationTest");
+        let mut rng = rand::thread_rng();
+        let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+
+        let mut commit = |v: &[Vec<u64>]| -> (Vec<Vec<_>>, Vec<Vec<_>>) {
+            v.iter()
+                .map(|t| {
+                    t.iter()
+                        .map(|v| {
+                            let (com, var) = prover.commit(Fr::from(*v), Fr::rand(&mut rng));
+                            (com, LinearCombination::from(var))
+                        })
+                        .unzip()
+                })
+                .unzip()
+        };
+        let (x_commitments, x_vars) = commit(x);
+        let (y_commitments, y_vars) = commit(y);
+        enforce_tuple_permutation(&mut prover, x_vars, y_vars)?;
+
+        let proof = prover.prove(&mut rng, &bp_gens)?;
+        (proof, x_commitments, y_commitments)
+    };
+
+    let mut verifier_transcript = Transcript::new(b"PermutationTest");
+    let mut verifier = Verifier::new(&mut verifier_transcript);
+    let mut commit = |v: &[Vec<Affine>]| -> Vec<Vec<_>> {
+        v.iter()
+            .map(|t| {
+                t.iter()
+                    .map(|V| LinearCombination::from(verifier.commit(*V)))
+                    .collect()
+            })
+            .collect()
+    };
+    let x_vars = commit(&x_commitments);
+    let y_vars = commit(&y_commitments);
+    enforce_tuple_permutation(&mut verifier, x_vars, y_vars)?;
+
+    verifier.verify(&proof, &pc_gens, &bp_gens)
+}
+
+#[test]
+fn permutation_gadgets() {
+    let single = |v: &[u64]| -> Vec<Vec<u64>> { v.iter().map(|v| vec![*v]).collect() };
+
+    assert!(permutation_helper(&single(&[3]), &single(&[3])).is_ok());
+    assert!(permutation_helper(&single(&[3]), &single(&[4])).is_err());
+    assert!(permutation_helper(&single(&[1, 2, 3, 4, 5]), &single(&[4, 2, 5, 1, 3])).is_ok());
+    assert!(permutation_helper(&single(&[1, 2, 3, 4, 5]), &single(&[4, 2, 5, 1, 1])).is_err());
+    assert!(permutation_helper(&single(&[1, 2]), &single(&[1, 2, 3])).is_err());
+
+    // Tuples move as a whole.
+    let x = vec![vec![1, 10], vec![2, 20], vec![3, 30]];
+    assert!(permutation_helper(&x, &[vec![3, 30], vec![1, 10], vec![2, 20]]).is_ok());
+    assert!(permutation_helper(&x, &[vec![3, 10], vec![1, 30], vec![2, 20]]).is_err());
+    assert!(permutation_helper(&x, &[vec![3, 30], vec![1, 10], vec![2]]).is_err());
+}
+
+// Cloak gadgets
+
+/// Proves and verifies a cloak transaction from the committed `inputs`
+/// to the committed `outputs`.
+fn cloak_helper(inputs: &[Value<Fr>], outputs: &[Value<Fr>]) -> Result<(), R1CSError> {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(1024, 1);
+
+    let (proof, in_commitments, out_commitments) = {
+        let mut prover_transcript = Transcript::new(b"CloakTest");
+        let mut rng = rand::thread_rng();
+        let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+
+        let mut commit = |values: &[Value<Fr>]| -> (Vec<_>, Vec<_>) {
+            values
+                .iter()
+                .map(|value| {
+                    let (q_com, q_var) = prover.commit(value.quantity, Fr::rand(&mut rng));
+                    let (f_com, f_var) = prover.commit(value.flavor, Fr::rand(&mut rng));
+                    (
+                        (q_com, f_com),
+                        AllocatedValue::new(q_var, f_var, Some(*value)),
+                    )
+                })
+                .unzip()
+        };
+        let (in_commitments, in_values) = commit(inputs);
+        let (out_commitments, out_values) = commit(outputs);
+        cloak(&mut prover, &in_values, &out_values)?;
+
+        let proof = prover.prove(&mut rng, &bp_gens)?;
+        (proof, in_commitments, out_commitments)
+    };
+
+    let mut verifier_transcript = Transcript::new(b"CloakTest");
+    let mut verifier = Verifier::new(&mut verifier_transcript);
+    let mut commit = |commitments: &[(Affine, Affine)]| -> Vec<_> {
+        commitments
+            .iter()
+            .map(|(q, f)| AllocatedValue::new(verifier.commit(*q), verifier.commit(*f), None))
+            .collect()
+    };
+    let in_values = commit(&in_commitments);
+    let out_values = commit(&out_commitments);
+    cloak(&mut verifier, &in_values, &out_values)?;
+
+    verifier.verify(&proof, &pc_gens, &bp_gens)
+}
+
+#[test]
+fn cloak_gadgets() {
+    let values = |v: &[(u64, u64)]| -> Vec<Value<Fr>> {
+        v.iter()
+            .map(|(q, f)| Value::new(*q, Fr::from(*f)))
+            .collect()
+    };
+    let cloak_ok = |inputs: &[(u64, u64)], outputs: &[(u64, u64)]| {
+        cloak_helper(&values(inputs), &values(outputs)).is_ok()
+    };
+
+    // Moving values around.
+    assert!(cloak_ok(&[(5, 1)], &[(5, 1)]));
+    assert!(!cloak_ok(&[(5, 1)], &[(6, 1)]));
+    assert!(!cloak_ok(&[(5, 1)], &[(5, 2)]));
+    assert!(cloak_ok(&[(5, 1), (7, 2)], &[(7, 2), (5, 1)]));
+
+    // Merging and splitting.
+    assert!(cloak_ok(&[(5, 1), (7, 1)], &[(12, 1)]));
+    assert!(cloak_ok(&[(12, 1)], &[(5, 1), (7, 1)]));
+    assert!(!cloak_ok(&[(12, 1)], &[(5, 1), (8, 1)]));
+    assert!(cloak_ok(
+        &[(3, 1), (4, 2), (5, 1), (6, 3)],
+        &[(2, 1), (6, 3), (1, 2), (6, 1), (3, 2)],
+    ));
+    assert!(!cloak_ok(
+        &[(3, 1), (4, 2), (5, 1), (6, 3)],
+        &[(2, 1), (6, 3), (1, 1), (6, 1), (3, 2)],
+    ));
+
+    // Output quantities must be in range: 3 - 1 = 2 in the field, but
+    // -1 is not a valid quantity.
+    let outputs = [
+        Value {
+            quantity: -Fr::from(1u64),
+            flavor: Fr::from(1u64),
+        },
+        Value::new(3, Fr::from(1u64)),
+    ];
+    assert!(cloak_helper(&values(&[(2, 1)]), &outputs).is_err());
+    assert!(cloak_helper(&values(&[(2, 1)]), &[]).is_err());
+}
+
+// Scalar multiplication gadgets
+
+/// Multiplies the point with the committed coordinates (the first two
+/// inputs) by the committed scalar (the third input), and the curve
+/// generator by the same scalar.
+struct ScalarMulCircuit {
+    bits: usize,
+    expected: (ark_secp256k1::Affine, ark_secp256k1::Affine),
+}
+
+impl Gadget<Fr> for ScalarMulCircuit {
+    type Input = Vec<AllocatedScalar<Fr>>;
+    type Output = ();
+
+    fn synthesize<CS: ConstraintSystem<Fr>>(
+        &self,
+        cs: &mut CS,
+        inputs: Self::Input,
+    ) -> Result<(), R1CSError> {
+        use ark_ec::AffineRepr;
+        type C = ark_secp256k1::Config;
+
+        // The committed point is on the curve.
+        let committed = PointVar {
+            x: inputs[0].into(),
+            y: inputs[1].into(),
+            value: inputs[0].assignment.zip(inputs[1].assignment),
+        };
+        let p = PointVar::allocate::<C, _>(cs, committed.to_affine())?;
+        p.enforce_equal(cs, &committed);
+
+        let bits = BitDecomposition::new(self.bits)
+            .synthesize(cs, inputs[2])?
+            .into_iter()
+            .map(Boolean::from_bit)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let kp = p.mul::<C, _>(cs, &bits)?;
+        kp.enforce_equal(cs, &PointVar::constant(&self.expected.0)?);
+
+        let g = ark_secp256k1::Affine::generator();
+        let kg = PointVar::mul_fixed::<C, _>(cs, &g, &bits)?;
+        kg.enforce_equal(cs, &PointVar::constant(&self.expected.1)?);
+        Ok(())
+    }
+}
+
+#[test]
+fn scalar_multiplication_gadgets() {
+    use ark_ec::{AffineRepr, CurveGroup};
+    type Scalar = ark_secp256k1::Fr;
+
+    let g = ark_secp256k1::Affine::generator();
+    let p = (g * Scalar::from(1234567u64)).into_affine();
+    let k = 0xdead_beefu64;
+    let expected = (
+        (p * Scalar::from(k)).into_affine(),
+        (g * Scalar::from(k)).into_affine(),
+    );
+
+    let circuit = ScalarMulCircuit { bits: 32, expected };
+    assert!(prove_and_verify(&[p.x, p.y, Fr::from(k)], &circuit).is_ok());
+    assert!(prove_and_verify(&[p.x, p.y, Fr::from(k + 1)], &circuit).is_err());
+    assert!(prove_and_verify(&[p.x, -p.y, Fr::from(k)], &circuit).is_err());
+    assert!(prove_and_verify(&[p.x, p.x, Fr::from(k)], &circuit).is_err());
+}
+
+// ECDSA verification gadget
+
+/// Verifies an ECDSA signature whose `s` is the first input, under the
+/// public key with the committed coordinates (the last two inputs).
+struct EcdsaCircuit {
+    gadget: EcdsaVerify<ark_secp256k1::Config>,
+}
+
+impl Gadget<Fr> for EcdsaCircuit {
+    type Input = Vec<AllocatedScalar<Fr>>;
+    type Output = ();
+
+    fn synthesize<CS: ConstraintSystem<Fr>>(
+        &self,
+        cs: &mut CS,
+        inputs: Self::Input,
+    ) -> Result<(), R1CSError> {
+        let s = BitDecomposition::new(256)
+            .synthesize(cs, inputs[0])?
+            .into_iter()
+            .map(Boolean::from_bit)
+            .collect::<Result<Vec<_>, _>>()?;
+        let public_key = PointVar {
+            x: inputs[1].into(),
+            y: inputs[2].into(),
+            value: inputs[1].assignment.zip(inputs[2].assignment),
+        };
+        self.gadget.synthesize(cs, (s, public_key))
+    }
+}
+
+#[test]
+fn ecdsa_gadget() {
+    use ark_ec::{AffineRepr, CurveGroup};
+    use ark_ff::{BigInteger, PrimeField};
+    type Scalar = ark_secp256k1::Fr;
+
+    let mut rng = rand::thread_rng();
+    let g = ark_secp256k1::Affine::generator();
+    let d = Scalar::rand(&mut rng);
+    let public_key = (g * d).into_affine();
+
+    // Sign the message hash z.
+    let z = Scalar::from(0x1234_5678u64);
+    let k = Scalar::rand(&mut rng);
+    let r_point = (g * k).into_affine();
+    let r = Scalar::from_le_bytes_mod_order(&r_point.x.into_bigint().to_bytes_le());
+    let s = k.inverse().unwrap() * (z + r * d);
+    let s = Fr::from_le_bytes_mod_order(&s.into_bigint().to_bytes_le());
+
+    let circuit = EcdsaCircuit {
+        gadget: EcdsaVerify::new(&r_point, z).unwrap(),
+    };
+    let values = [s, public_key.x, public_key.y];
+    assert!(prove_and_verify_with_capacity(&values, &circuit, 2048).is_ok());
+
+    // Wrong message, key or signature.
+    let wrong_message = EcdsaCircuit {
+        gadget: EcdsaVerify::new(&r_point, z + Scalar::from(1u64)).unwrap(),
+    };
+    assert!(prove_and_verify_with_capacity(&values, &wrong_message, 2048).is_err());
+    let other_key = (g * (d + Scalar::from(1u64))).into_affine();
+    let values = [s, other_key.x, other_key.y];
+    assert!(prove_and_verify_with_capacity(&values, &circuit, 2048).is_err());
+    let values = [s + Fr::from(1u64), public_key.x, public_key.y];
+    assert!(prove_and_verify_with_capacity(&values, &circuit, 2048).is_err());
+
+    assert!(EcdsaVerify::new(&ark_secp256k1::Affine::identity(), z).is_err());
+}
+
+// Big integer gadgets
+
+/// Multiplies the committed integers `a` and `b`, given by their 64-bit
+/// limbs, modulo a constant modulus, and adds them.
+struct BigIntCircuit {
+    modulus: num_bigint::BigUint,
+    product: num_bigint::BigUint,
+    sum: num_bigint::BigUint,
+}
+
+impl Gadget<Fr> for BigIntCircuit {
+    type Input = Vec<AllocatedScalar<Fr>>;
+    type Output = ();
+
+    fn synthesize<CS: ConstraintSystem<Fr>>(
+        &self,
+        cs: &mut CS,
+        inputs: Self::Input,
+    ) -> Result<(), R1CSError> {
+        let (a, b) = inputs.split_at(inputs.len() / 2);
+        let a = BigUintVar::from_limbs(cs, a, 64)?;
+        let b = BigUintVar::from_limbs(cs, b, 64)?;
+        let modulus = BigUintVar::constant(&self.modulus, 64)?;
+
+        let product = a.mul_mod(cs, &b, &modulus)?;
+        product.enforce_equal(cs, &BigUintVar::constant(&self.product, 64)?)?;
+        let sum = a.add(cs, &b)?;
+        sum.enforce_equal(cs, &BigUintVar::constant(&self.sum, 64)?)
+    }
+}
+
+#[test]
+fn bigint_gadgets() {
+    use num_bigint::BigUint;
+
+    // The base field of the curve25519 scalar field, as a foreign field.
+    let modulus: BigUint = (BigUint::from(1u32) << 255) - BigUint::from(19u32);
+    let a = &modulus - BigUint::from(12345u32);
+    let b = (BigUint::from(1u32) << 200) + BigUint::from(987654321u64);
+    let limbs = |x: &BigUint| -> Vec<Fr> {
+        let mut digits = x.to_u64_digits();
+        digits.resize(4, 0);
+        digits.into_iter().map(Fr::from).collect()
+    };
+
+    let circuit = BigIntCircuit {
+        product: (&a * &b) % &modulus,
+        sum: &a + &b,
+        modulus: modulus.clone(),
+    };
+    let values = [limbs(&a), limbs(&b)].concat();
+    assert!(prove_and_verify_with_capacity(&values, &circuit, 4096).is_ok());
+
+    let values = [limbs(&a), limbs(&(&b + BigUint::from(1u32)))].concat();
+    assert!(prove_and_verify_with_capacity(&values, &circuit, 4096).is_err());
+
+    // Limbs out of range.
+    let mut values = [limbs(&a), limbs(&b)].concat();
+    values[0] += Fr::from(u64::MAX) + Fr::from(1u64);
+    values[1] -= Fr::from(1u64);
+    assert!(prove_and_verify_with_capacity(&values, &circuit, 4096).is_err());
+
+    let zero = BigIntCircuit {
+        modulus: BigUint::default(),
+        product: BigUint::default(),
+        sum: &a + &b,
+    };
+    let values = [limbs(&a), limbs(&b)].concat();
+    assert!(prove_and_verify_with_capacity(&values, &zero, 4096).is_err());
+}
+
+// Fixed-point gadgets
+
+/// Computes `principal * (1 + rate) / shares` for the committed raw
+/// values `principal`, `rate` and `shares`, and checks it against the
+/// expected raw value.
+struct InterestCircuit {
+    format: FixedPoint,
+    expected: u64,
+}
+
+impl Gadget<Fr> for InterestCircuit {
+    type Input = Vec<AllocatedScalar<Fr>>;
+    type Output = ();
+
+    fn synthesize<CS: ConstraintSystem<Fr>>(
+        &self,
+        cs: &mut CS,
+        inputs: Self::Input,
+    ) -> Result<(), R1CSError> {
+        let f = &self.format;
+        let principal = f.from_scalar(cs, inputs[0])?;
+        let rate = f.from_scalar(cs, inputs[1])?;
+        let shares = f.from_scalar(cs, inputs[2])?;
+
+        let one = f.constant(f.from_integer(1).unwrap())?;
+        let factor = f.add(cs, &one, &rate)?;
+        let total = f.mul(cs, &principal, &factor)?;
+        let share = f.div(cs, &total, &shares)?;
+        // The interest is nonnegative.
+        f.sub(cs, &total, &principal)?;
+
+        cs.constrain(share.lc - Fr::from(self.expected));
+        Ok(())
+    }
+}
+
+#[test]
+fn fixed_point_gadgets() {
+    let f = FixedPoint::new(32, 16).unwrap();
+    assert_eq!(f.from_integer(3), Some(3 << 16));
+    assert_eq!(f.from_integer(1 << 32), None);
+    assert_eq!(f.from_ratio(1, 4), Some(1 << 14));
+    assert_eq!(f.from_ratio(1, 0), None);
+    assert!(FixedPoint::new(40, 30).is_err());
+
+    // 1000.5 * 1.05 / 3 = 350.175
+    let principal = f.from_ratio(2001, 2).unwrap();
+    let rate = f.from_ratio(5, 100).unwrap();
+    let shares = f.from_integer(3).unwrap();
+    let total = ((principal as u128 * (f.from_integer(1).unwrap() + rate) as u128) >> 16) as u64;
+    let expected = (((total as u128) << 16) / shares as u128) as u64;
+    assert_eq!(expected >> 16, 350);
+
+    let circuit = InterestCircuit {
+        format: f,
+        expected,
+    };
+    let values = [Fr::from(principal), Fr::from(rate), Fr::from(shares)];
+    assert!(prove_and_verify(&values, &circuit).is_ok());
+
+    let values = [
+        Fr::from(principal + (1 << 8)),
+        Fr::from(rate),
+        Fr::from(shares),
+    ];
+    assert!(prove_and_verify(&values, &circuit).is_err());
+
+    // Division by zero.
+    let circuit = InterestCircuit {
+        format: f,
+        expected: 0,
+    };
+    let values = [Fr::from(principal), Fr::from(rate), Fr::from(0u64)];
+    assert!(prove_and_verify(&values, &circuit).is_err());
+
+    // Overflow of the product.
+    let big = f.from_integer((1 << 31) + 1).unwrap();
+    let values = [
+        Fr::from(big),
+        Fr::from(f.from_integer(1).unwrap()),
+        Fr::from(shares),
+    ];
+    let total = ((big as u128 * (2u128 << 16)) >> 16) as u64;
+    let circuit = InterestCircuit {
+        format: f,
+        expected: (((total as u128) << 16) / shares as u128) as u64,
+    };
+    assert!(prove_and_verify(&values, &circuit).is_err());
+}
+
+// Division gadgets
+
+/// Divides the first input by the second, and checks the quotient and
+/// the remainder against the expected values.
+struct DivRemCircuit {
+    n: usize,
+    expected: (u64, u64),
+}
+
+impl Gadget<Fr> for DivRemCircuit {
+    type Input = Vec<AllocatedScalar<Fr>>;
+    type Output = ();
+
+    fn synthesize<CS: ConstraintSystem<Fr>>(
+        &self,
+        cs: &mut CS,
+        inputs: Self::Input,
+    ) -> Result<(), R1CSError> {
+        let (q, r) = div_rem(cs, inputs[0], inputs[1], self.n)?;
+        cs.constrain(q.variable - Fr::from(self.expected.0));
+        cs.constrain(r.variable - Fr::from(self.expected.1));
+        Ok(())
+    }
+}
+
+#[test]
+fn div_rem_gadget() {
+    let check = |a: u64, b: u64, n: usize, expected: (u64, u64)| {
+        let circuit = DivRemCircuit { n, expected };
+        prove_and_verify(&[Fr::from(a), Fr::from(b)], &circuit).is_ok()
+    };
+
+    assert!(check(100, 7, 8, (14, 2)));
+    assert!(check(6, 7, 8, (0, 6)));
+    assert!(check(u32::MAX as u64, 1 << 16, 16, (0xffff, 0xffff)));
+    assert!(!check(100, 7, 8, (13, 9)));
+    assert!(!check(100, 7, 8, (14, 3)));
+
+    // The quotient does not fit.
+    assert!(!check(1000, 2, 8, (500, 0)));
+
+    // Division by zero.
+    assert!(!check(5, 0, 8, (0, 0)));
+
+    // Quotients and remainders of 128 bits could wrap around the field.
+    assert!(!check(5, 3, 128, (1, 2)));
+}
+
+// Signed integer gadgets
+
+/// Computes `a + b`, `a - b`, `|a - b|` and `a < b` on the committed
+/// signed integers `a` and `b`, and checks them against the expected
+/// values.
+struct IntCircuit {
+    n: usize,
+    expected: (i64, i64, i64, bool),
+}
+
+impl Gadget<Fr> for IntCircuit {
+    type Input = Vec<AllocatedScalar<Fr>>;
+    type Output = ();
+
+    fn synthesize<CS: ConstraintSystem<Fr>>(
+        &self,
+        cs: &mut CS,
+        inputs: Self::Input,
+    ) -> Result<(), R1CSError> {
+        let a = Int::from_scalar(cs, inputs[0], self.n)?;
+        let b = Int::from_scalar(cs, inputs[1], self.n)?;
+
+        let sum = Int::add(cs, &a, &b)?;
+        let delta = Int::sub(cs, &a, &b)?;
+        let abs = delta.abs(cs)?;
+        let lt = Int::less_than(cs, &a, &b)?;
+
+        let (s, d, m, l) = self.expected;
+        cs.constrain(sum.lc() - signed(s));
+        cs.constrain(delta.lc() - signed(d));
+        cs.constrain(abs.lc() - signed(m));
+        cs.constrain(lt.lc() - Fr::from(l));
+        Ok(())
+    }
+}
+
+/// Encodes a signed integer in the field.
+fn signed(x: i64) -> Fr {
+    if x < 0 {
+        -Fr::from(x.unsigned_abs())
+    } else {
+        Fr::from(x as u64)
+    }
+}
+
+#[test]
+fn signed_integer_gadgets() {
+    let check = |a: i64, b: i64, n: usize| {
+        let expected = (a + b, a - b, (a - b).abs(), a < b);
+        let circuit = IntCircuit { n, expected };
+        prove_and_verify(&[signed(a), signed(b)], &circuit).is_ok()
+    };
+
+    assert!(check(5, 3, 8));
+    assert!(check(-5, 3, 8));
+    assert!(check(-50, -70, 8));
+    assert!(check(i32::MAX as i64 / 2, i32::MIN as i64 / 2, 32));
+
+    // Overflows: of the sum, of the difference and of the absolute value.
+    assert!(!check(100, 50, 8));
+    assert!(!check(-100, 50, 8));
+    assert!(!check(-64, 64 + 1, 8));
+
+    // Inputs out of range.
+    assert!(!check(128, 0, 8));
+    assert!(!check(-129, 0, 8));
+
+    // The expected values must be right.
+    let circuit = IntCircuit {
+        n: 8,
+        expected: (2, -8, 8, false),
+    };
+    assert!(prove_and_verify(&[signed(-3), signed(5)], &circuit).is_err());
+
+    assert_eq!(Int::<Fr>::constant(-1, 8).unwrap().value(), Some(-1));
+    assert!(Int::<Fr>::constant(128, 8).is_err());
+}
+
+// Lookup gadget
+
+/// Proves and verifies that the committed `rows` are rows of `table`.
+fn lookup_helper(table: &LookupTable<Fr>, rows: &[Vec<u64>]) -> Result<(), R1CSError> {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(512, 1);
+
+    let (proof, commitments) = {
+        let mut prover_transcript = Transcript::new(b"LookupTest");
+        let mut rng = rand::thread_rng();
+        let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+
+        let (commitments, inputs): (Vec<Vec<_>>, Vec<Vec<_>>) = rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|v| {
+                        let (com, var) = prover.commit(Fr::from(*v), Fr::rand(&mut rng));
+                        (com, AllocatedScalar::new(var, Some(Fr::from(*v))))
+                    })
+                    .unzip()
+            })
+            .unzip();
+        table.enforce_rows(&mut prover, &inputs)?;
+
+        (prover.prove(&mut rng, &bp_gens)?, commitments)
+    };
+
+    let mut verifier_transcript = Transcript::new(b"LookupTest");
+    let mut verifier = Verifier::new(&mut verifier_transcript);
+    let inputs: Vec<Vec<_>> = commitments
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|V| AllocatedScalar::new(verifier.commit(*V), None))
+                .collect()
+        })
+        .collect();
+    table.enforce_rows(&mut verifier, &inputs)?;
+
+    verifier.verify(&proof, &pc_gens, &bp_gens)
+}
+
+#[test]
+fn lookup_gadget() {
+    // Range checks of bytes.
+    let bytes = LookupTable::from_values((0..256u64).map(Fr::from).collect()).unwrap();
+    let rows = |v: &[u64]| -> Vec<Vec<u64>> { v.iter().map(|x| vec![*x]).collect() };
+    assert!(lookup_helper(&bytes, &rows(&[0, 17, 255, 17, 42])).is_ok());
+    assert!(lookup_helper(&bytes, &rows(&[0, 17, 256])).is_err());
+    assert!(lookup_helper(&bytes, &[]).is_ok());
+
+    // XOR of nibbles, as a three-column table.
+    let xor = LookupTable::new(
+        (0..16u64)
+            .flat_map(|a| (0..16u64).map(move |b| vec![a, b, a ^ b]))
+            .map(|row| row.into_iter().map(Fr::from).collect())
+            .collect(),
+    )
+    .unwrap();
+    assert!(lookup_helper(&xor, &[vec![3, 5, 6], vec![15, 15, 0], vec![3, 5, 6]]).is_ok());
+    assert!(lookup_helper(&xor, &[vec![3, 5, 7]]).is_err());
+    assert!(lookup_helper(&xor, &[vec![3, 5]]).is_err());
+
+    assert!(LookupTable::<Fr>::new(vec![vec![Fr::from(1u64)], vec![]]).is_err());
+}
+
+// Dot product gadgets
+
+/// Proves and verifies that the product of the committed `matrix` and
+/// the committed vector `x` is `expected`, with every gadget.
+fn matrix_vector_helper(matrix: &[Vec<u64>], x: &[u64], expected: &[u64]) -> Result<(), R1CSError> {
+    fn gadgets<CS: RandomizableConstraintSystem<Fr>>(
+        cs: &mut CS,
+        matrix: &[Vec<AllocatedScalar<Fr>>],
+        x: &[AllocatedScalar<Fr>],
+        public_matrix: &[Vec<Fr>],
+        expected: &[u64],
+    ) -> Result<(), R1CSError> {
+        let y = matrix_vector_product(cs, matrix, x)?;
+        let z = constant_matrix_vector_product(public_matrix, x)?;
+        for (((y, z), row), e) in y.iter().zip(z).zip(matrix).zip(expected) {
+            let d = dot_product(cs, row, x)?;
+            cs.constrain(y.variable - Fr::from(*e));
+            cs.constrain(z - Fr::from(*e));
+            cs.constrain(d.variable - Fr::from(*e));
+        }
+        Ok(())
+    }
+
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(128, 1);
+    let public_matrix: Vec<Vec<Fr>> = matrix
+        .iter()
+        .map(|row| row.iter().map(|a| Fr::from(*a)).collect())
+        .collect();
+
+    let (proof, matrix_commitments, x_commitments) = {
+        let mut prover_transcript = Transcript::new(b"MatrixVectorTest");
+        let mut rng = rand::thread_rng();
+        let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+
+        let mut commit = |v: &[u64]| -> (Vec<_>, Vec<_>) {
+            v.iter()
+                .map(|v| {
+                    let (com, var) = prover.commit(Fr::from(*v), Fr::rand(&mut rng));
+                    (com, AllocatedScalar::new(var, Some(Fr::from(*v))))
+                })
+                .unzip()
+        };
+        let (matrix_commitments, matrix_vars): (Vec<_>, Vec<_>) =
+            matrix.iter().map(|row| commit(row)).unzip();
+        let (x_commitments, x_vars) = commit(x);
+        gadgets(&mut prover, &matrix_vars, &x_vars, &public_matrix, expected)?;
+
+        let proof = prover.prove(&mut rng, &bp_gens)?;
+        (proof, matrix_commitments, x_commitments)
+    };
+
+    let mut verifier_transcript = Transcript::new(b"MatrixVectorTest");
+    let mut verifier = Verifier::new(&mut verifier_transcript);
+    let mut commit = |v: &[Affine]| -> Vec<_> {
+        v.iter()
+            .map(|V| AllocatedScalar::new(verifier.commit(*V), None))
+            .collect()
+    };
+    let matrix_vars: Vec<_> = matrix_commitments.iter().map(|row| commit(row)).collect();
+    let x_vars = commit(&x_commitments);
+    gadgets(
+        &mut verifier,
+        &matrix_vars,
+        &x_vars,
+        &public_matrix,
+        expected,
+    )?;
+
+    verifier.verify(&proof, &pc_gens, &bp_gens)
+}
+
+#[test]
+fn dot_product_gadgets() {
+    let matrix = vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 10], vec![0, 1, 0]];
+    let x = [3, 2, 1];
+    assert!(matrix_vector_helper(&matrix, &x, &[10, 28, 47, 2]).is_ok());
+    assert!(matrix_vector_helper(&matrix, &x, &[10, 28, 48, 2]).is_err());
+    assert!(matrix_vector_helper(&matrix, &x[..2], &[10, 28, 47, 2]).is_err());
+}
+
+// Mock constraint system
+
+/// Synthesizes `circuit` over `values` into a [`MockCS`], and returns
+/// the index of the first unsatisfied constraint, if any.
+fn mock_check<C>(values: &[Fr], circuit: &C) -> Result<Option<usize>, R1CSError>
+where
+    C: Gadget<Fr, Input = Vec<AllocatedScalar<Fr>>, Output = ()>,
+{
+    let mut cs = MockCS::new();
+    let inputs = values
+        .iter()
+        .map(|v| AllocatedScalar::new(cs.commit(*v), Some(*v)))
+        .collect();
+    circuit.synthesize(&mut cs, inputs)?;
+    Ok(cs.first_unsatisfied())
+}
+
+#[test]
+fn mock_constraint_system() {
+    let div_rem = |a: u64, b: u64, expected| {
+        let circuit = DivRemCircuit { n: 8, expected };
+        mock_check(&[Fr::from(a), Fr::from(b)], &circuit).unwrap()
+    };
+    assert_eq!(div_rem(100, 7, (14, 2)), None);
+    assert!(div_rem(100, 7, (13, 9)).is_some());
+
+    // Randomized constraints are checked as soon as they are specified.
+    let permutation = |x: &[u64], y: &[u64]| {
+        let mut cs = MockCS::new();
+        let mut commit = |v: &[u64]| -> Vec<LinearCombination<Fr>> {
+            v.iter().map(|v| cs.commit(Fr::from(*v)).into()).collect()
+        };
+        let (x, y) = (commit(x), commit(y));
+        enforce_permutation(&mut cs, x, y).unwrap();
+        cs.is_satisfied()
+    };
+    assert!(permutation(&[1, 2, 3], &[3, 1, 2]));
+    assert!(!permutation(&[1, 2, 3], &[3, 1, 1]));
+
+    // The residuals of the unsatisfied constraints are reported.
+    let mut cs = MockCS::<Fr>::new();
+    let x = cs.commit(Fr::from(3u64));
+    let (_, _, o) = cs.multiply(x.into(), x.into());
+    cs.constrain(o - Fr::from(10u64));
+    assert_eq!(cs.constraints_len(), 3);
+    assert_eq!(cs.unsatisfied(), &[(2, -Fr::from(1u64))]);
+}
+
+/// A structured witness, allocated field by field.
+#[cfg(feature = "derive")]
+#[derive(AllocVar)]
+struct Transfer {
+    /// The amount transferred.
+    amount: u64,
+    asset: Fr,
+    flags: [bool; 2],
+}
+
+#[cfg(feature = "derive")]
+#[derive(AllocVar)]
+struct Transfers(u32, [Transfer; 2]);
+
+#[cfg(feature = "derive")]
+#[test]
+fn alloc_var_derive_test() {
+    let transfer = |amount: u64, asset: u64| Transfer {
+        amount,
+        asset: Fr::from(asset),
+        flags: [true, false],
+    };
+    let value = Transfers(7, [transfer(5, 1), transfer(6, 2)]);
+
+    let mut cs = MockCS::<Fr>::new();
+    let vars = Transfers::allocate(&mut cs, Some(&value)).unwrap();
+    assert_eq!(vars.0.assignment, Some(Fr::from(7u64)));
+    assert_eq!(vars.1[1].amount.assignment, Some(Fr::from(6u64)));
+    assert_eq!(vars.1[1].asset.assignment, Some(Fr::from(2u64)));
+    assert_eq!(vars.1[0].flags[0].value(), Some(true));
+
+    // The allocated variables are constrained by the application.
+    let total = vars.1[0].amount.variable + vars.1[1].amount.variable;
+    cs.constrain(total - Fr::from(11u64));
+    assert!(cs.is_satisfied());
+
+    // The verifier allocates the same variables without assignments.
+    let mut verifier = CircuitDescription::<Fr>::new();
+    let vars = Transfers::allocate(&mut verifier, None).unwrap();
+    assert_eq!(vars.1[0].amount.assignment, None);
+    assert_eq!(verifier.multipliers_len(), cs.multipliers_len());
+}
+
+/// Squares its input.
+#[cfg(feature = "derive")]
+struct Square;
+
+#[cfg(feature = "derive")]
+impl Gadget<Fr> for Square {
+    type Input = AllocatedScalar<Fr>;
+    type Output = AllocatedScalar<Fr>;
+
+    fn synthesize<CS: ConstraintSystem<Fr>>(
+        &self,
+        cs: &mut CS,
+        x: Self::Input,
+    ) -> Result<Self::Output, R1CSError> {
+        let (_, _, o) = cs.multiply(x.into(), x.into());
+        Ok(AllocatedScalar::new(o, x.assignment.map(|x| x * x)))
+    }
+}
+
+/// Raises its input to the fourth power.
+#[cfg(feature = "derive")]
+#[derive(Gadget)]
+struct Fourth {
+    square: Square,
+    again: Square,
+}
+
+/// Checks that the output of a gadget fits in the bits of its second
+/// member.
+#[cfg(feature = "derive")]
+#[derive(Gadget)]
+struct Bounded<G>(G, BitDecomposition);
+
+#[cfg(feature = "derive")]
+#[test]
+fn gadget_derive_test() {
+    let gadget = Bounded(
+        Fourth {
+            square: Square,
+            again: Square,
+        },
+        BitDecomposition::new(8),
+    );
+    let synthesize = |x: u64| {
+        let mut cs = MockCS::<Fr>::new();
+        let x = AllocatedScalar::new(cs.commit(Fr::from(x)), Some(Fr::from(x)));
+        let mut profiler = Profiler::new(&mut cs);
+        let bits = gadget.synthesize(&mut profiler, x).unwrap();
+        let paths: Vec<_> = profiler.report().iter().map(|p| p.path.clone()).collect();
+        (bits, paths, cs.is_satisfied())
+    };
+
+    // 3^4 = 81 fits in 8 bits, but 5^4 = 625 does not.
+    let (bits, paths, satisfied) = synthesize(3);
+    assert_eq!(bits.len(), 8);
+    assert!(satisfied);
+    assert_eq!(paths, vec!["0", "0/square", "0/again", "1"]);
+    assert!(!synthesize(5).2);
+}
@@ -1,13 +1,19 @@
 #![allow(non_snake_case)]
 
-use ark_bulletproofs::{r1cs::*, BulletproofGens, PedersenGens};
-use ark_ff::UniformRand;
-use ark_secq256k1::{Affine, Fr};
+use ark_bulletproofs::{
+    r1cs::{gadgets::*, *},
+    ArkworksMsm, BulletproofGens, DalekCompatTranscript, IdentityPolicy, KeccakTranscript,
+    MsmBackend, PedersenGens, PointValidation, RecordingTranscript, TranscriptEvent,
+};
+use ark_ff::{Field, UniformRand};
+use ark_secq256k1::{Affine, Fr, Projective};
 use ark_std::rand::seq::SliceRandom;
 use ark_std::rand::thread_rng;
 use ark_std::One;
 use merlin::Transcript;
 use rand_core::{CryptoRng, RngCore};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
 /// A proof-of-shuffle.
 struct ShuffleProof(R1CSProof<Affine>);
@@ -214,6 +220,130 @@ fn shuffle_gadget_test_42() {
     kshuffle_helper(42);
 }
 
+#[test]
+fn shuffle_gadget_keccak_transcript_test() {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(8, 1);
+    let mut rng = thread_rng();
+    let input: Vec<Fr> = (0..4u64).map(Fr::from).collect();
+    let output = vec![input[2], input[0], input[3], input[1]];
+
+    // The prover owns its transcript, and returns it with the proof.
+    let mut prover = Prover::new(&pc_gens, KeccakTranscript::new(b"ShuffleProofTest"));
+    let (commitments, vars): (Vec<_>, Vec<_>) = input
+        .iter()
+        .chain(&output)
+        .map(|v| prover.commit(*v, Fr::rand(&mut rng)))
+        .unzip();
+    let (x, y) = vars.split_at(4);
+    ShuffleProof::gadget(&mut prover, x.to_vec(), y.to_vec()).unwrap();
+    let (proof, mut prover_transcript) = prover
+        .prove_and_return_transcript(&mut rng, &bp_gens)
+        .unwrap();
+
+    // The verifier borrows its transcript.
+    let mut verifier_transcript = KeccakTranscript::new(b"ShuffleProofTest");
+    let mut verifier = Verifier::new(&mut verifier_transcript);
+    let vars: Vec<_> = commitments.iter().map(|V| verifier.commit(*V)).collect();
+    let (x, y) = vars.split_at(4);
+    ShuffleProof::gadget(&mut verifier, x.to_vec(), y.to_vec()).unwrap();
+    assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_ok());
+
+    let mut prover_state = [0u8; 32];
+    let mut verifier_state = [0u8; 32];
+    prover_transcript.challenge_bytes(b"state", &mut prover_state);
+    verifier_transcript.challenge_bytes(b"state", &mut verifier_state);
+    assert_eq!(prover_state, verifier_state);
+
+    // The challenges of a Merlin transcript do not match.
+    let mut verifier = Verifier::new(Transcript::new(b"ShuffleProofTest"));
+    let vars: Vec<_> = commitments.iter().map(|V| verifier.commit(*V)).collect();
+    let (x, y) = vars.split_at(4);
+    ShuffleProof::gadget(&mut verifier, x.to_vec(), y.to_vec()).unwrap();
+    assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_err());
+}
+
+#[test]
+fn shuffle_gadget_dalek_transcript_test() {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(8, 1);
+    let mut rng = thread_rng();
+    let input: Vec<Fr> = (0..4u64).map(Fr::from).collect();
+    let output = vec![input[3], input[2], input[1], input[0]];
+
+    let mut prover = Prover::new(&pc_gens, DalekCompatTranscript::new(b"ShuffleProofTest"));
+    let (commitments, vars): (Vec<_>, Vec<_>) = input
+        .iter()
+        .chain(&output)
+        .map(|v| prover.commit(*v, Fr::rand(&mut rng)))
+        .unzip();
+    let (x, y) = vars.split_at(4);
+    ShuffleProof::gadget(&mut prover, x.to_vec(), y.to_vec()).unwrap();
+    let proof = prover.prove(&mut rng, &bp_gens).unwrap();
+
+    let mut verifier = Verifier::new(DalekCompatTranscript::new(b"ShuffleProofTest"));
+    let vars: Vec<_> = commitments.iter().map(|V| verifier.commit(*V)).collect();
+    let (x, y) = vars.split_at(4);
+    ShuffleProof::gadget(&mut verifier, x.to_vec(), y.to_vec()).unwrap();
+    assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_ok());
+
+    // The encodings differ from those of a plain Merlin transcript.
+    let mut verifier = Verifier::new(Transcript::new(b"ShuffleProofTest"));
+    let vars: Vec<_> = commitments.iter().map(|V| verifier.commit(*V)).collect();
+    let (x, y) = vars.split_at(4);
+    ShuffleProof::gadget(&mut verifier, x.to_vec(), y.to_vec()).unwrap();
+    assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_err());
+}
+
+#[test]
+fn shuffle_gadget_recording_transcript_test() {
+    use std::sync::mpsc;
+
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(8, 1);
+    let mut rng = thread_rng();
+    let input: Vec<Fr> = (0..4u64).map(Fr::from).collect();
+    let output = vec![input[1], input[3], input[0], input[2]];
+
+    let (prover_sink, prover_log) = mpsc::channel();
+    let transcript =
+        RecordingTranscript::new(KeccakTranscript::new(b"ShuffleProofTest"), move |event| {
+            prover_sink.send(event).unwrap()
+        });
+    let mut prover = Prover::new(&pc_gens, transcript);
+    let (commitments, vars): (Vec<_>, Vec<_>) = input
+        .iter()
+        .chain(&output)
+        .map(|v| prover.commit(*v, Fr::rand(&mut rng)))
+        .unzip();
+    let (x, y) = vars.split_at(4);
+    ShuffleProof::gadget(&mut prover, x.to_vec(), y.to_vec()).unwrap();
+    let proof = prover.prove(&mut rng, &bp_gens).unwrap();
+
+    let (verifier_sink, verifier_log) = mpsc::channel();
+    let transcript =
+        RecordingTranscript::new(KeccakTranscript::new(b"ShuffleProofTest"), move |event| {
+            verifier_sink.send(event).unwrap()
+        });
+    let mut verifier = Verifier::new(transcript);
+    let vars: Vec<_> = commitments.iter().map(|V| verifier.commit(*V)).collect();
+    let (x, y) = vars.split_at(4);
+    ShuffleProof::gadget(&mut verifier, x.to_vec(), y.to_vec()).unwrap();
+    assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_ok());
+
+    // Both sides appended the same messages and drew the same challenges.
+    let prover_log: Vec<TranscriptEvent> = prover_log.try_iter().collect();
+    let verifier_log: Vec<TranscriptEvent> = verifier_log.try_iter().collect();
+    assert_eq!(prover_log, verifier_log);
+    assert!(prover_log.contains(&TranscriptEvent::Append {
+        label: b"dom-sep",
+        bytes: b"r1cs v1".to_vec(),
+    }));
+    assert!(prover_log
+        .iter()
+        .any(|event| matches!(event, TranscriptEvent::Challenge { label: b"x", .. })));
+}
+
 /// Constrains (a1 + a2) * (b1 + b2) = (c1 + c2)
 fn example_gadget<CS: ConstraintSystem<Fr>>(
     cs: &mut CS,
@@ -355,6 +485,106 @@ fn example_gadget_serialization_test() {
     assert!(example_gadget_roundtrip_serialization_helper(3, 4, 6, 1, 40, 10).is_err());
 }
 
+#[test]
+fn example_gadget_errors_test() {
+    use ark_bulletproofs::r1cs::mpc::MPCError;
+    use ark_ec::AffineRepr;
+    use ark_serialize::CanonicalSerialize;
+    use std::error::Error;
+
+    let pc_gens = PedersenGens::default();
+    let bp_gens = BulletproofGens::new(128, 1);
+    let (proof, commitments) = example_gadget_proof(&pc_gens, &bp_gens, 3, 4, 6, 1, 40, 9).unwrap();
+
+    let verify = |proof: &R1CSProof<Affine>, bp_gens: &BulletproofGens<Affine>, extra: bool| {
+        let mut verifier = Verifier::new(Transcript::new(b"R1CSExampleGadget"));
+        let vars: Vec<_> = commitments.iter().map(|V| verifier.commit(*V)).collect();
+        example_gadget(
+            &mut verifier,
+            vars[0].into(),
+            vars[1].into(),
+            vars[2].into(),
+            vars[3].into(),
+            vars[4].into(),
+            Fr::from(9u64).into(),
+        );
+        if extra {
+            verifier.multiply(vars[0].into(), vars[1].into());
+        }
+        verifier.verify(proof, &pc_gens, bp_gens)
+    };
+    assert!(verify(&proof, &bp_gens, false).is_ok());
+
+    // The proof has a single multiplier, so its inner-product proof has
+    // no rounds, while a circuit of 2 multipliers needs one.
+    assert_eq!(
+        verify(&proof, &bp_gens, true),
+        Err(R1CSError::InvalidInnerProductLength {
+            expected: 1,
+            actual: 0
+        })
+    );
+    assert_eq!(
+        verify(&proof, &BulletproofGens::new(1, 1), true),
+        Err(R1CSError::InsufficientGenerators {
+            capacity: 1,
+            required: 2
+        })
+    );
+
+    // The encoding ends with the points L and R of the inner-product
+    // proof, prefixed by their numbers, and with its scalars a and b.
+    let bytes = proof.to_bytes().unwrap();
+    let (head, ab) = (&bytes[..bytes.len() - 80], &bytes[bytes.len() - 64..]);
+    let mut point = Vec::new();
+    Affine::generator()
+        .serialize_compressed(&mut point)
+        .unwrap();
+    let with_rounds = |l: usize, r: usize| {
+        let mut bytes = head.to_vec();
+        for n in [l, r] {
+            bytes.extend_from_slice(&(n as u64).to_le_bytes());
+            for _ in 0..n {
+                bytes.extend_from_slice(&point);
+            }
+        }
+        bytes.extend_from_slice(ab);
+        R1CSProof::from_bytes(&bytes).unwrap()
+    };
+    assert!(verify(&with_rounds(0, 0), &bp_gens, false).is_ok());
+    assert!(matches!(
+        verify(&with_rounds(1, 0), &bp_gens, false),
+        Err(R1CSError::InvalidProofShape { .. })
+    ));
+    assert!(matches!(
+        verify(&with_rounds(1, 1), &BulletproofGens::new(1, 1), false),
+        Err(R1CSError::InvalidProofShape { .. })
+    ));
+    assert_eq!(
+        verify(&with_rounds(1, 1), &bp_gens, false),
+        Err(R1CSError::InvalidInnerProductLength {
+            expected: 0,
+            actual: 1
+        })
+    );
+
+    // Replace A_I1, the first point of the encoding, with the identity.
+    let mut bytes = proof.to_bytes().unwrap();
+    let mut identity = Vec::new();
+    Affine::zero().serialize_compressed(&mut identity).unwrap();
+    bytes[..identity.len()].copy_from_slice(&identity);
+    let proof = R1CSProof::from_bytes(&bytes).unwrap();
+    let error = verify(&proof, &bp_gens, false).unwrap_err();
+    assert_eq!(error, R1CSError::InvalidPoint { label: b"A_I1" });
+    assert!(error.to_string().contains("A_I1"));
+
+    let error = R1CSError::ProvingError(MPCError::MaliciousDealer);
+    assert_eq!(
+        error.source().unwrap().to_string(),
+        MPCError::MaliciousDealer.to_string()
+    );
+}
+
 // Range Proof gadget
 
 /// Enforces that the quantity of v is in the range [0, 2^n).
@@ -525,3 +755,2862 @@ fn batch_range_proof_helper(v_vals: &[(u64, usize)]) -> Result<(), R1CSError> {
     let a = verifiers.into_iter().zip(proofs.iter());
     batch_verify(&mut prng, a, &pc_gens, &bp_gens)
 }
+
+#[test]
+fn batch_verify_identifies_invalid_proofs() {
+    let values = [(0u64, 8usize), (3, 16), (255, 8), (1 << 20, 32), (7, 8)];
+    assert!(batch_identify_helper(&values, &[]).is_ok());
+    for tampered in [vec![0], vec![1, 4], vec![0, 1, 2, 3, 4]] {
+        match batch_identify_helper(&values, &tampered) {
+            Err(R1CSError::BatchVerificationError { invalid_proofs }) => {
+                assert_eq!(invalid_proofs, tampered)
+            }
+            _ => panic!("invalid proofs {:?} were not identified", tampered),
+        }
+    }
+}
+
+/// Batch verifies range proofs of `v_vals`, where the verifier is given a
+/// wrong commitment for the proofs at the `tampered` indices.
+fn batch_identify_helper(v_vals: &[(u64, usize)], tampered: &[usize]) -> Result<(), R1CSError> {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(128, 1);
+    let mut rng = thread_rng();
+
+    let mut proofs = vec![];
+    let mut commitments = vec![];
+    for (v, n) in v_vals {
+        let mut prover_transcript = Transcript::new(b"RangeProofTest");
+        let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+        let (com, var) = prover.commit(Fr::from(*v), Fr::rand(&mut rng));
+        assert!(range_proof(&mut prover, var.into(), Some(*v), *n).is_ok());
+        proofs.push(prover.prove(&mut rng, &bp_gens)?);
+        commitments.push(com);
+    }
+    for i in tampered {
+        commitments[*i] = pc_gens.commit(Fr::from(v_vals[*i].0), Fr::rand(&mut rng));
+    }
+
+    // The verifiers own their transcripts.
+    let mut verifiers = vec![];
+    for (commitment, (_, n)) in commitments.iter().zip(v_vals) {
+        let mut verifier = Verifier::new(Transcript::new(b"RangeProofTest"));
+        let var = verifier.commit(*commitment);
+        assert!(range_proof(&mut verifier, var.into(), None, *n).is_ok());
+        verifiers.push(verifier);
+    }
+
+    let a = verifiers.into_iter().zip(proofs.iter());
+    batch_verify_and_identify(&mut rng, a, &pc_gens, &bp_gens)
+}
+
+/// An `MsmBackend` counting the multiscalar multiplications it computes.
+struct CountingMsm(Arc<AtomicUsize>);
+
+impl MsmBackend<Affine> for CountingMsm {
+    fn msm(&self, bases: &[Affine], scalars: &[Fr]) -> Projective {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        ArkworksMsm.msm(bases, scalars)
+    }
+}
+
+#[test]
+fn msm_backend_is_used() {
+    let count = Arc::new(AtomicUsize::new(0));
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(128, 1).with_msm_backend(CountingMsm(count.clone()));
+
+    let (proof, commitments) = example_gadget_proof(&pc_gens, &bp_gens, 3, 4, 6, 1, 40, 9).unwrap();
+    // The prover computes its multiplications in constant time instead
+    // with the `ct` feature.
+    let proving = count.load(Ordering::SeqCst);
+    assert_eq!(proving > 0, cfg!(not(feature = "ct")));
+
+    assert!(example_gadget_verify(&pc_gens, &bp_gens, 9, proof, commitments).is_ok());
+    assert!(count.load(Ordering::SeqCst) > proving);
+}
+
+#[test]
+fn fixed_base_tables_test() {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let plain_gens = BulletproofGens::new(128, 1);
+    let mut table_gens = BulletproofGens::new(16, 1).with_fixed_base_tables(&pc_gens, 4);
+    table_gens.increase_capacity(128);
+
+    // Proofs created with the tables verify without them, and conversely.
+    for (prover_gens, verifier_gens) in [(&table_gens, &plain_gens), (&plain_gens, &table_gens)] {
+        let (proof, commitments) =
+            example_gadget_proof(&pc_gens, prover_gens, 3, 4, 6, 1, 40, 9).unwrap();
+        assert!(example_gadget_verify(&pc_gens, verifier_gens, 9, proof, commitments).is_ok());
+        assert!(fixed_base_tables_helper(&pc_gens, prover_gens, verifier_gens, 255).is_ok());
+        assert!(fixed_base_tables_helper(&pc_gens, prover_gens, verifier_gens, 256).is_err());
+    }
+
+    // The tables are ignored with other Pedersen generators.
+    let other_pc_gens = PedersenGens {
+        B: pc_gens.B_blinding,
+        B_blinding: pc_gens.B,
+    };
+    assert!(fixed_base_tables_helper(&other_pc_gens, &table_gens, &table_gens, 7).is_ok());
+}
+
+/// Proves that `v` is an 8-bit value with `prover_gens`, and verifies the
+/// proof alone and in a batch with `verifier_gens`.
+fn fixed_base_tables_helper(
+    pc_gens: &PedersenGens<Affine>,
+    prover_gens: &BulletproofGens<Affine>,
+    verifier_gens: &BulletproofGens<Affine>,
+    v: u64,
+) -> Result<(), R1CSError> {
+    let mut rng = thread_rng();
+    let mut prover = Prover::new(pc_gens, Transcript::new(b"RangeProofTest"));
+    let (commitment, var) = prover.commit(Fr::from(v), Fr::rand(&mut rng));
+    range_proof(&mut prover, var.into(), Some(v), 8)?;
+    let proof = prover.prove(&mut rng, prover_gens)?;
+
+    let verifier = || -> Result<Verifier<Affine, Transcript>, R1CSError> {
+        let mut verifier = Verifier::new(Transcript::new(b"RangeProofTest"));
+        let var = verifier.commit(commitment);
+        range_proof(&mut verifier, var.into(), None, 8)?;
+        Ok(verifier)
+    };
+    verifier()?.verify(&proof, pc_gens, verifier_gens)?;
+    let batch = vec![(verifier()?, &proof), (verifier()?, &proof)];
+    batch_verify(&mut rng, batch, pc_gens, verifier_gens)
+}
+
+#[test]
+fn verification_scalars_shape() {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(128, 1);
+
+    let (proof, commitments) = example_gadget_proof(&pc_gens, &bp_gens, 3, 4, 6, 1, 40, 9).unwrap();
+
+    let mut transcript = Transcript::new(b"R1CSExampleGadget");
+    let mut verifier = Verifier::new(&mut transcript);
+    let vars: Vec<_> = commitments.iter().map(|V| verifier.commit(*V)).collect();
+    example_gadget(
+        &mut verifier,
+        vars[0].into(),
+        vars[1].into(),
+        vars[2].into(),
+        vars[3].into(),
+        vars[4].into(),
+        Fr::from(9u64).into(),
+    );
+
+    let scalars = verifier.verification_scalars(&proof, &bp_gens).unwrap();
+    assert_eq!(scalars.padded_n(), 1);
+    assert_eq!(scalars.h.len(), 1);
+    assert_eq!(scalars.V.len(), commitments.len());
+    assert_eq!(scalars.L_vec.len(), 0);
+    assert_eq!(scalars.R_vec.len(), 0);
+    assert_eq!(
+        scalars.to_vec().len(),
+        2 + 2 * scalars.padded_n() + 6 + commitments.len() + 5
+    );
+}
+
+#[test]
+fn verification_msm_is_identity() {
+    use ark_ec::VariableBaseMSM;
+    use ark_std::Zero;
+
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(128, 1);
+
+    let msm_for = |c2: u64| {
+        let (proof, commitments) =
+            example_gadget_proof(&pc_gens, &bp_gens, 3, 4, 6, 1, 40, 9).unwrap();
+
+        let mut transcript = Transcript::new(b"R1CSExampleGadget");
+        let mut verifier = Verifier::new(&mut transcript);
+        let vars: Vec<_> = commitments.iter().map(|V| verifier.commit(*V)).collect();
+        example_gadget(
+            &mut verifier,
+            vars[0].into(),
+            vars[1].into(),
+            vars[2].into(),
+            vars[3].into(),
+            vars[4].into(),
+            Fr::from(c2).into(),
+        );
+
+        let (points, scalars) = verifier
+            .verification_msm(&proof, &pc_gens, &bp_gens)
+            .unwrap();
+        assert_eq!(points.len(), scalars.len());
+        <Affine as ark_ec::AffineRepr>::Group::msm(&points, &scalars).unwrap()
+    };
+
+    assert!(msm_for(9).is_zero());
+    assert!(!msm_for(10).is_zero());
+}
+
+#[test]
+fn verification_artifacts_test() {
+    use ark_ec::VariableBaseMSM;
+    use ark_std::Zero;
+
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(128, 1);
+    let (proof, commitments) = example_gadget_proof(&pc_gens, &bp_gens, 3, 4, 6, 1, 40, 9).unwrap();
+
+    let verifier = || {
+        let mut verifier = Verifier::new(Transcript::new(b"R1CSExampleGadget"));
+        let vars: Vec<_> = commitments.iter().map(|V| verifier.commit(*V)).collect();
+        example_gadget(
+            &mut verifier,
+            vars[0].into(),
+            vars[1].into(),
+            vars[2].into(),
+            vars[3].into(),
+            vars[4].into(),
+            Fr::from(9u64).into(),
+        );
+        verifier
+    };
+
+    let prepared = verifier().prepare().unwrap();
+    let artifacts = prepared.export_artifacts(&pc_gens, &bp_gens).unwrap();
+    assert_eq!(artifacts.circuit_digest, verifier().circuit_digest());
+    assert_eq!(artifacts.padded_multipliers, 1);
+    assert_eq!(artifacts.G.len(), 1);
+    assert_eq!(artifacts.W_V.len(), commitments.len());
+
+    // The points of the artifacts line up with the verification scalars.
+    let points = artifacts.msm_points(&proof, &commitments).unwrap();
+    let (expected, scalars) = verifier()
+        .verification_msm(&proof, &pc_gens, &bp_gens)
+        .unwrap();
+    assert_eq!(points, expected);
+    assert!(Projective::msm(&points, &scalars).unwrap().is_zero());
+    assert!(artifacts.msm_points(&proof, &commitments[1..]).is_err());
+
+    let json = artifacts.to_json();
+    assert!(json.starts_with("{\"circuit_digest\""));
+    assert!(json.contains("[\"V\", 5]"));
+
+    assert!(matches!(
+        prepared.export_artifacts(&pc_gens, &BulletproofGens::new(0, 1)),
+        Err(R1CSError::InsufficientGenerators { .. })
+    ));
+}
+
+/// Returns the sections `(type, contents)` of a circom binary file.
+fn circom_sections<'a>(bytes: &'a [u8], magic: &[u8]) -> Vec<(u32, &'a [u8])> {
+    let u32_at = |b: &[u8]| u32::from_le_bytes(b[..4].try_into().unwrap());
+    assert_eq!(&bytes[..4], magic);
+    let mut sections = vec![];
+    let mut rest = &bytes[12..];
+    for _ in 0..u32_at(&bytes[8..]) {
+        let len = u64::from_le_bytes(rest[4..12].try_into().unwrap()) as usize;
+        sections.push((u32_at(rest), &rest[12..12 + len]));
+        rest = &rest[12 + len..];
+    }
+    assert!(rest.is_empty());
+    sections
+}
+
+#[test]
+fn r1cs_export_test() {
+    use ark_ff::PrimeField;
+
+    let pc_gens = PedersenGens::<Affine>::default();
+    let mut rng = thread_rng();
+
+    let mut prover = Prover::new(&pc_gens, Transcript::new(b"R1CSExportTest"));
+    let vars: Vec<_> = [3u64, 4, 6, 1, 40]
+        .iter()
+        .map(|x| prover.commit(Fr::from(*x), Fr::rand(&mut rng)).1)
+        .collect();
+    let p = prover.public_input(Fr::from(9u64));
+    example_gadget(
+        &mut prover,
+        vars[0].into(),
+        vars[1].into(),
+        vars[2].into(),
+        vars[3].into(),
+        vars[4].into(),
+        p.into(),
+    );
+    let r1cs = prover.export_r1cs().unwrap();
+    let wtns = prover.export_witness().unwrap();
+
+    let mut verifier = Verifier::new(Transcript::new(b"R1CSExportTest"));
+    let vars: Vec<_> = (0..5).map(|_| verifier.commit(Affine::default())).collect();
+    let p = verifier.public_input(Fr::from(9u64));
+    example_gadget(
+        &mut verifier,
+        vars[0].into(),
+        vars[1].into(),
+        vars[2].into(),
+        vars[3].into(),
+        vars[4].into(),
+        p.into(),
+    );
+    assert_eq!(verifier.export_r1cs().unwrap(), r1cs);
+
+    // Wires: one, the public input, five commitments and the three
+    // wires of the multiplier, and four constraints.
+    let r1cs = circom_sections(&r1cs, b"r1cs");
+    let header = r1cs[0].1;
+    let n8 = u32::from_le_bytes(header[..4].try_into().unwrap()) as usize;
+    assert_eq!(n8, 32);
+    let counts: Vec<u32> = header[4 + n8..]
+        .chunks(4)
+        .map(|c| u32::from_le_bytes(c.try_into().unwrap()))
+        .collect();
+    // nWires, nPubOut, nPubIn, nPrvIn, nLabels (u64), nConstraints
+    assert_eq!(counts, vec![10, 0, 1, 5, 10, 0, 4]);
+
+    let wtns = circom_sections(&wtns, b"wtns");
+    let values: Vec<Fr> = wtns[1]
+        .1
+        .chunks(n8)
+        .map(Fr::from_le_bytes_mod_order)
+        .collect();
+    assert_eq!(values.len(), 10);
+    assert_eq!(values[0], Fr::from(1u64));
+
+    // Every exported constraint is satisfied by the witness.
+    let mut constraints = r1cs[1].1;
+    let mut read_lc = || {
+        let terms = u32::from_le_bytes(constraints[..4].try_into().unwrap()) as usize;
+        let mut sum = Fr::from(0u64);
+        for t in 0..terms {
+            let term = &constraints[4 + t * (4 + n8)..];
+            let wire = u32::from_le_bytes(term[..4].try_into().unwrap()) as usize;
+            sum += values[wire] * Fr::from_le_bytes_mod_order(&term[4..4 + n8]);
+        }
+        constraints = &constraints[4 + terms * (4 + n8)..];
+        sum
+    };
+    // The multiplier, then the two constraints of its inputs and the
+    // constraint of its output.
+    for _ in 0..4 {
+        let (a, b, c) = (read_lc(), read_lc(), read_lc());
+        assert_eq!(a * b, c);
+    }
+}
+
+#[test]
+fn prepared_range_proof_gadget() {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(128, 1);
+    let n = 32;
+
+    let proofs: Vec<_> = [0u64, 7, (1 << 32) - 1, 1 << 32]
+        .iter()
+        .map(|v| {
+            let mut prover_transcript = Transcript::new(b"RangeProofTest");
+            let mut rng = rand::thread_rng();
+            let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+            let (com, var) = prover.commit(Fr::from(*v), Fr::rand(&mut rng));
+            range_proof(&mut prover, var.into(), Some(*v), n).unwrap();
+            (prover.prove(&mut rng, &bp_gens).unwrap(), com)
+        })
+        .collect();
+
+    // The circuit is synthesized once, with any commitment.
+    let mut transcript = Transcript::new(b"RangeProofTest");
+    let mut verifier = Verifier::new(&mut transcript);
+    let var = verifier.commit(proofs[0].1);
+    range_proof(&mut verifier, var.into(), None, n).unwrap();
+    let circuit = verifier.prepare().unwrap();
+    assert_eq!(circuit.commitments_len(), 1);
+    assert_eq!(circuit.multipliers_len(), n);
+
+    for (i, (proof, com)) in proofs.iter().enumerate() {
+        let mut transcript = Transcript::new(b"RangeProofTest");
+        let result = circuit.verify(&mut transcript, &[*com], proof, &pc_gens, &bp_gens);
+        assert_eq!(result.is_ok(), i < 3);
+    }
+
+    // Randomized circuits cannot be prepared.
+    let mut transcript = Transcript::new(b"ShuffleProofTest");
+    let mut verifier = Verifier::<Affine, _>::new(&mut transcript);
+    let x: Vec<_> = proofs.iter().map(|(_, c)| verifier.commit(*c)).collect();
+    ShuffleProof::gadget(&mut verifier, x.clone(), x).unwrap();
+    assert!(verifier.prepare().is_err());
+}
+
+/// Records a circuit checking that `a * b = x` and `x + 1 = y`,
+/// where `x` and `y` are allocated around the multiplication.
+fn recorded_circuit() -> CircuitDescription<Fr> {
+    let mut circuit = CircuitDescription::new();
+    let a = circuit.commit();
+    let b = circuit.commit();
+
+    let x = circuit.allocate(None).unwrap();
+    let (_, _, ab) = circuit.multiply(a.into(), b.into());
+    let y = circuit.allocate(None).unwrap();
+    circuit.constrain(x - ab);
+    circuit.constrain(y - x - Fr::one());
+
+    circuit
+}
+
+fn circuit_description_helper(a: u64, b: u64, x: u64, y: u64) -> Result<(), R1CSError> {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(8, 1);
+    let circuit = recorded_circuit();
+    assert_eq!(circuit.commitments_len(), 2);
+    assert_eq!(circuit.witness_len(), 2);
+
+    let (proof, commitments) = {
+        let mut prover_transcript = Transcript::new(b"CircuitDescriptionTest");
+        let mut rng = rand::thread_rng();
+        let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+
+        let (commitments, vars): (Vec<_>, Vec<_>) = [a, b]
+            .iter()
+            .map(|v| prover.commit(Fr::from(*v), Fr::rand(&mut rng)))
+            .unzip();
+        circuit.replay(&mut prover, &vars, Some(&[Fr::from(x), Fr::from(y)]))?;
+
+        (prover.prove(&mut rng, &bp_gens)?, commitments)
+    };
+
+    let mut verifier_transcript = Transcript::new(b"CircuitDescriptionTest");
+    let mut verifier = Verifier::new(&mut verifier_transcript);
+    let vars: Vec<_> = commitments.iter().map(|V| verifier.commit(*V)).collect();
+    circuit.replay(&mut verifier, &vars, None)?;
+
+    verifier.verify(&proof, &pc_gens, &bp_gens)
+}
+
+#[test]
+fn circuit_description_test() {
+    assert!(circuit_description_helper(3, 4, 12, 13).is_ok());
+    assert!(circuit_description_helper(3, 4, 12, 14).is_err());
+    assert!(circuit_description_helper(3, 4, 11, 12).is_err());
+
+    // Replaying requires one input per declared variable.
+    let mut transcript = Transcript::new(b"CircuitDescriptionTest");
+    let mut verifier = Verifier::<Affine, _>::new(&mut transcript);
+    let a = verifier.commit(Affine::default());
+    assert!(recorded_circuit()
+        .replay(&mut verifier, &[a], None)
+        .is_err());
+}
+
+#[test]
+fn circuit_description_serde_test() {
+    let circuit = recorded_circuit();
+    let json = serde_json::to_string(&circuit).unwrap();
+    let decoded: CircuitDescription<Fr> = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded.commitments_len(), 2);
+    assert_eq!(decoded.witness_len(), 2);
+    assert_eq!(decoded.operations(), circuit.operations());
+
+    // The same circuit, as written by external tooling.
+    let minus_one = format!("\"{}\"", -Fr::one());
+    let json = format!(
+        r#"{{"commitments": 2, "operations": [
+            "Allocate",
+            {{"Multiply": [[[{{"Committed": 0}}, "1"]], [[{{"Committed": 1}}, "1"]]]}},
+            "Allocate",
+            {{"Constrain": [[{{"MultiplierLeft": 0}}, "1"], [{{"MultiplierOutput": 1}}, {m}]]}},
+            {{"Constrain": [
+                [{{"MultiplierRight": 0}}, "1"], [{{"MultiplierLeft": 0}}, {m}], ["One", {m}]
+            ]}}
+        ]}}"#,
+        m = minus_one
+    );
+    let decoded: CircuitDescription<Fr> = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded.operations(), circuit.operations());
+
+    let assignment: CircuitAssignment<Fr> =
+        serde_json::from_str(r#"{"inputs": ["3", "4"], "witness": ["12", "13"]}"#).unwrap();
+    let json = serde_json::to_string(&assignment).unwrap();
+    assert_eq!(json, r#"{"inputs":["3","4"],"witness":["12","13"]}"#);
+
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(8, 1);
+    let mut rng = rand::thread_rng();
+    let mut prover = Prover::new(&pc_gens, Transcript::new(b"CircuitDescriptionTest"));
+    let (commitments, vars): (Vec<_>, Vec<_>) = assignment
+        .inputs
+        .iter()
+        .map(|v| prover.commit(*v, Fr::rand(&mut rng)))
+        .unzip();
+    decoded
+        .replay(&mut prover, &vars, Some(&assignment.witness))
+        .unwrap();
+    let proof = prover.prove(&mut rng, &bp_gens).unwrap();
+
+    let mut verifier = Verifier::new(Transcript::new(b"CircuitDescriptionTest"));
+    let vars: Vec<_> = commitments.iter().map(|V| verifier.commit(*V)).collect();
+    decoded.replay(&mut verifier, &vars, None).unwrap();
+    assert!(verifier.verify(&proof, &pc_gens, &bp_gens).is_ok());
+
+    // Field elements must be canonical decimal integers.
+    let modulus = format!("\"{}\"", <Fr as ark_ff::PrimeField>::MODULUS);
+    for coeff in [modulus.as_str(), r#""-1""#, r#""0x1""#, r#""""#, "1"] {
+        let terms = format!("[[\"One\", {}]]", coeff);
+        assert!(serde_json::from_str::<LinearCombination<Fr>>(&terms).is_err());
+    }
+}
+
+fn circuit_digest_helper(prover_n: usize, verifier_n: usize, bind: bool) -> Result<(), R1CSError> {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(64, 1);
+
+    let (proof, commitment, prover_digest) = {
+        let mut prover_transcript = Transcript::new(b"CircuitDigestTest");
+        let mut rng = rand::thread_rng();
+        let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+
+        let (com, var) = prover.commit(Fr::from(5u64), Fr::rand(&mut rng));
+        range_proof(&mut prover, var.into(), Some(5), prover_n)?;
+        let digest = prover.circuit_digest();
+        prover.append_circuit_digest();
+
+        (prover.prove(&mut rng, &bp_gens)?, com, digest)
+    };
+
+    let mut verifier_transcript = Transcript::new(b"CircuitDigestTest");
+    let mut verifier = Verifier::new(&mut verifier_transcript);
+    let var = verifier.commit(commitment);
+    range_proof(&mut verifier, var.into(), None, verifier_n)?;
+    assert_eq!(
+        verifier.circuit_digest() == prover_digest,
+        prover_n == verifier_n
+    );
+    if bind {
+        verifier.append_circuit_digest();
+    }
+
+    verifier.verify(&proof, &pc_gens, &bp_gens)
+}
+
+#[test]
+fn circuit_digest_test() {
+    assert!(circuit_digest_helper(8, 8, true).is_ok());
+    assert!(circuit_digest_helper(8, 8, false).is_err());
+    assert!(circuit_digest_helper(8, 16, true).is_err());
+}
+
+/// Squares its input.
+struct Square;
+
+impl Gadget<Fr> for Square {
+    type Input = AllocatedScalar<Fr>;
+    type Output = AllocatedScalar<Fr>;
+
+    fn synthesize<CS: ConstraintSystem<Fr>>(
+        &self,
+        cs: &mut CS,
+        x: AllocatedScalar<Fr>,
+    ) -> Result<AllocatedScalar<Fr>, R1CSError> {
+        let (_, _, o) = cs.multiply(x.into(), x.into());
+        Ok(AllocatedScalar::new(o, x.assignment.map(|x| x * x)))
+    }
+}
+
+fn gadget_composition_helper(x: u64, y: u64) -> Result<(), R1CSError> {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(8, 1);
+    let fourth_power = Square.then(Square);
+
+    let (proof, commitments) = {
+        let mut prover_transcript = Transcript::new(b"GadgetCompositionTest");
+        let mut rng = rand::thread_rng();
+        let mut prover = Prover::new(&pc_gens, &mut prover_transcript);
+
+        let (x_com, x_var) = prover.commit(Fr::from(x), Fr::rand(&mut rng));
+        let (y_com, y_var) = prover.commit(Fr::from(y), Fr::rand(&mut rng));
+        let x = AllocatedScalar::new(x_var, Some(Fr::from(x)));
+        let out = fourth_power.synthesize(&mut prover, x)?;
+        assert_eq!(out.assignment, Some(x.assignment.unwrap().pow([4])));
+        prover.constrain(out.variable - y_var);
+
+        (prover.prove(&mut rng, &bp_gens)?, (x_com, y_com))
+    };
+
+    let mut verifier_transcript = Transcript::new(b"GadgetCompositionTest");
+    let mut verifier = Verifier::new(&mut verifier_transcript);
+    let x_var = verifier.commit(commitments.0);
+    let y_var = verifier.commit(commitments.1);
+    let out = fourth_power.synthesize(&mut verifier, AllocatedScalar::new(x_var, None))?;
+    assert_eq!(out.assignment, None);
+    verifier.constrain(out.variable - y_var);
+
+    verifier.verify(&proof, &pc_gens, &bp_gens)
+}
+
+#[test]
+fn gadget_composition_test() {
+    assert!(gadget_composition_helper(3, 81).is_ok());
+    assert!(gadget_composition_helper(3, 27).is_err());
+}
+
+fn strict_mode_helper(x: u64, y: u64) -> Result<(), R1CSError> {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(8, 1);
+    let mut transcript = Transcript::new(b"StrictModeTest");
+    let mut rng = rand::thread_rng();
+    let mut prover = Prover::new(&pc_gens, &mut transcript);
+    prover.set_strict_mode(true);
+
+    let (_, x_var) = prover.commit(Fr::from(x), Fr::rand(&mut rng));
+    let (_, y_var) = prover.commit(Fr::from(y), Fr::rand(&mut rng));
+    let x = AllocatedScalar::new(x_var, Some(Fr::from(x)));
+    let out = Square.then(Square).synthesize(&mut prover, x)?;
+    prover.constrain(out.variable - y_var);
+
+    prover.prove(&mut rng, &bp_gens).map(|_| ())
+}
+
+#[test]
+fn strict_mode_test() {
+    assert!(strict_mode_helper(3, 81).is_ok());
+    // Each multiplier adds two constraints, so the final one is the fifth.
+    assert_eq!(
+        strict_mode_helper(3, 80),
+        Err(R1CSError::UnsatisfiedConstraint { index: 4 })
+    );
+
+    // Constraints on variables that do not exist are unsatisfied.
+    let pc_gens = PedersenGens::<Affine>::default();
+    let mut transcript = Transcript::new(b"StrictModeTest");
+    let mut prover = Prover::new(&pc_gens, &mut transcript);
+    prover.set_strict_mode(true);
+    prover.constrain(Variable::Committed(0).into());
+    assert_eq!(prover.first_unsatisfied_constraint(), Some(0));
+}
+
+/// Allocates `values` in bulk, and proves that they are the squares of
+/// `0..values.len()` and that they sum to the committed `sum`.
+fn bulk_api_circuit<CS: ConstraintSystem<Fr>>(
+    cs: &mut CS,
+    n: usize,
+    values: Option<&[Fr]>,
+    sum: Variable<Fr>,
+) -> Result<(), R1CSError> {
+    let vars = cs.allocate_vec(n, values)?;
+    cs.constrain_all(
+        vars.iter()
+            .enumerate()
+            .map(|(i, v)| *v - Fr::from((i * i) as u64)),
+    );
+    cs.constrain(
+        vars.iter()
+            .fold(-LinearCombination::from(sum), |lc, v| lc + *v),
+    );
+    Ok(())
+}
+
+fn bulk_api_helper(values: &[u64], sum: u64) -> Result<(), R1CSError> {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(8, 1);
+    let values: Vec<_> = values.iter().map(|v| Fr::from(*v)).collect();
+
+    let (proof, commitment) = {
+        let mut transcript = Transcript::new(b"BulkApiTest");
+        let mut rng = rand::thread_rng();
+        let mut prover = Prover::new(&pc_gens, &mut transcript);
+        let (com, var) = prover.commit(Fr::from(sum), Fr::rand(&mut rng));
+        bulk_api_circuit(&mut prover, values.len(), Some(&values), var)?;
+        (prover.prove(&mut rng, &bp_gens)?, com)
+    };
+
+    let mut transcript = Transcript::new(b"BulkApiTest");
+    let mut verifier = Verifier::new(&mut transcript);
+    let var = verifier.commit(commitment);
+    bulk_api_circuit(&mut verifier, values.len(), None, var)?;
+    verifier.verify(&proof, &pc_gens, &bp_gens)
+}
+
+#[test]
+fn bulk_api_test() {
+    assert!(bulk_api_helper(&[0, 1, 4, 9, 16], 30).is_ok());
+    assert!(bulk_api_helper(&[0, 1, 4, 9, 16], 31).is_err());
+    assert!(bulk_api_helper(&[0, 1, 4, 9, 15], 29).is_err());
+
+    // The number of assignments must match.
+    let pc_gens = PedersenGens::<Affine>::default();
+    let mut transcript = Transcript::new(b"BulkApiTest");
+    let mut prover = Prover::new(&pc_gens, &mut transcript);
+    assert!(prover.allocate_vec(3, Some(&[Fr::one()])).is_err());
+}
+
+/// Proves that `b` is a bit and that `y = 3 * x + 2 * b + 5`.
+fn sugar_circuit<CS: ConstraintSystem<Fr>>(
+    cs: &mut CS,
+    x: Variable<Fr>,
+    b: Variable<Fr>,
+    y: Variable<Fr>,
+) {
+    cs.enforce_boolean(b);
+    let lc = cs.linear_combine(&[(x, Fr::from(3u64)), (b, Fr::from(2u64))]);
+    let lc = cs.add_constant(lc, Fr::from(5u64));
+    cs.enforce_equal(lc.clone(), y);
+    cs.enforce_zero(lc - y);
+}
+
+#[test]
+fn constraint_system_sugar_test() {
+    let check = |x: u64, b: u64, y: u64| {
+        let mut cs = test_utils::MockCS::new();
+        let x = cs.commit(Fr::from(x));
+        let b = cs.commit(Fr::from(b));
+        let y = cs.commit(Fr::from(y));
+        sugar_circuit(&mut cs, x, b, y);
+        assert_eq!(cs.multipliers_len(), 1);
+        cs.is_satisfied()
+    };
+    assert!(check(4, 1, 19));
+    assert!(check(4, 0, 17));
+    assert!(!check(4, 1, 18));
+    assert!(!check(4, 2, 21));
+}
+
+#[cfg(feature = "diagnostics")]
+#[test]
+fn unsatisfied_constraints_test() {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let mut transcript = Transcript::new(b"DiagnosticsTest");
+    let mut rng = rand::thread_rng();
+    let mut prover = Prover::new(&pc_gens, &mut transcript);
+
+    let (_, x_var) = prover.commit(Fr::from(3u64), Fr::rand(&mut rng));
+    let (_, y_var) = prover.commit(Fr::from(80u64), Fr::rand(&mut rng));
+    let x = AllocatedScalar::new(x_var, Some(Fr::from(3u64)));
+    prover.label("fourth power");
+    let out = Square.then(Square).synthesize(&mut prover, x).unwrap();
+    prover.label("output");
+    prover.constrain(out.variable - y_var);
+    prover.constrain(y_var - Fr::from(80u64));
+
+    assert_eq!(
+        prover.unsatisfied_constraints(),
+        vec![UnsatisfiedConstraint {
+            index: 4,
+            label: Some("output".to_string()),
+            residual: Fr::from(1u64),
+        }]
+    );
+}
+
+/// Proves `x^4 = y` and `x` in range, in namespaces.
+fn profiled_circuit<CS: ConstraintSystem<Fr>>(
+    cs: &mut CS,
+    x: AllocatedScalar<Fr>,
+    y: Variable<Fr>,
+) -> Result<(), R1CSError> {
+    cs.push_namespace("fourth power");
+    cs.push_namespace("square");
+    let x2 = Square.synthesize(cs, x)?;
+    cs.pop_namespace();
+    cs.push_namespace("square");
+    let x4 = Square.synthesize(cs, x2)?;
+    cs.pop_namespace();
+    cs.constrain(x4.variable - y);
+    cs.pop_namespace();
+
+    cs.push_namespace("range");
+    BitDecomposition::new(8).synthesize(cs, x)?;
+    cs.pop_namespace();
+    Ok(())
+}
+
+#[test]
+fn profiler_test() {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let mut transcript = Transcript::new(b"ProfilerTest");
+    let mut rng = rand::thread_rng();
+    let mut prover = Prover::new(&pc_gens, &mut transcript);
+    let (x_com, x_var) = prover.commit(Fr::from(3u64), Fr::rand(&mut rng));
+    let (y_com, y_var) = prover.commit(Fr::from(81u64), Fr::rand(&mut rng));
+
+    let mut profiler = Profiler::new(&mut prover);
+    let x = AllocatedScalar::new(x_var, Some(Fr::from(3u64)));
+    profiled_circuit(&mut profiler, x, y_var).unwrap();
+    let report = profiler.report().to_vec();
+    let total = profiler.total().clone();
+
+    let profile = |path: &str, multipliers, constraints| NamespaceProfile {
+        path: path.to_string(),
+        multipliers,
+        constraints,
+    };
+    assert_eq!(
+        report,
+        vec![
+            profile("fourth power", 2, 5),
+            profile("fourth power/square", 2, 4),
+            profile("range", 8, 17),
+        ]
+    );
+    assert_eq!(total, profile("", 10, 22));
+    assert_eq!(total.multipliers, prover.multipliers_len());
+
+    // The verifier builds the same circuit.
+    let mut transcript = Transcript::new(b"ProfilerTest");
+    let mut verifier = Verifier::new(&mut transcript);
+    let x_var = verifier.commit(x_com);
+    let y_var = verifier.commit(y_com);
+    let mut profiler = Profiler::new(&mut verifier);
+    profiled_circuit(&mut profiler, AllocatedScalar::new(x_var, None), y_var).unwrap();
+    assert_eq!(profiler.report(), &report[..]);
+}
+
+/// Enforces that `y` is a permutation of `x`, as the products of
+/// `x_i - z` and `y_i - z` agree for a random challenge `z`.
+fn scoped_shuffle<CS: RandomizedConstraintSystem<Fr>>(
+    cs: &mut CS,
+    x: &[Variable<Fr>],
+    y: &[Variable<Fr>],
+) -> Result<(), R1CSError> {
+    let z = cs.challenge_scalar(b"shuffle challenge");
+    let product = |cs: &mut CS, vars: &[Variable<Fr>]| {
+        vars.iter()
+            .fold(LinearCombination::from(Fr::one()), |acc, v| {
+                let (_, _, o) = cs.multiply(acc, *v - z);
+                o.into()
+            })
+    };
+    let (px, py) = (product(cs, x), product(cs, y));
+    cs.constrain(px - py);
+    Ok(())
+}
+
+fn scoped_randomized_helper(x: &[u64], y: &[u64]) -> Result<(), R1CSError> {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(16, 1);
+
+    let (proof, x_commitments, y_commitments) = {
+        let mut transcript = Transcript::new(b"ScopedRandomizedTest");
+        let mut rng = rand::thread_rng();
+        let mut prover = Prover::new(&pc_gens, &mut transcript);
+        let mut commit = |v: &[u64]| -> (Vec<_>, Vec<_>) {
+            v.iter()
+                .map(|v| prover.commit(Fr::from(*v), Fr::rand(&mut rng)))
+                .unzip()
+        };
+        let (x_commitments, x_vars) = commit(x);
+        let (y_commitments, y_vars) = commit(y);
+
+        // The callback borrows the variables instead of owning them.
+        let proof = prover.prove_with_randomized_constraints(&mut rng, &bp_gens, |cs| {
+            scoped_shuffle(cs, &x_vars, &y_vars)
+        })?;
+        (proof, x_commitments, y_commitments)
+    };
+
+    let mut transcript = Transcript::new(b"ScopedRandomizedTest");
+    let mut verifier = Verifier::new(&mut transcript);
+    let x_vars: Vec<_> = x_commitments.iter().map(|c| verifier.commit(*c)).collect();
+    let y_vars: Vec<_> = y_commitments.iter().map(|c| verifier.commit(*c)).collect();
+    verifier.verify_with_randomized_constraints(&proof, &pc_gens, &bp_gens, |cs| {
+        scoped_shuffle(cs, &x_vars, &y_vars)
+    })
+}
+
+#[test]
+fn scoped_randomized_constraints_test() {
+    assert!(scoped_randomized_helper(&[1, 2, 3, 4], &[4, 2, 1, 3]).is_ok());
+    assert!(scoped_randomized_helper(&[1, 2, 3, 4], &[4, 2, 1, 1]).is_err());
+}
+
+/// Like [`scoped_shuffle`], but specified as a deferred callback which
+/// takes ownership of the variables and consumes them.
+fn owned_shuffle<CS: RandomizableConstraintSystem<Fr>>(
+    cs: &mut CS,
+    x: Vec<Variable<Fr>>,
+    y: Vec<Variable<Fr>>,
+) -> Result<(), R1CSError> {
+    cs.specify_randomized_constraints(move |cs| {
+        let (x, y): (Vec<_>, Vec<_>) = (x.into_iter().collect(), y.into_iter().collect());
+        scoped_shuffle(cs, &x, &y)
+    })
+}
+
+fn owned_randomized_helper(x: &[u64], y: &[u64]) -> Result<(), R1CSError> {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(16, 1);
+
+    let (proof, x_commitments, y_commitments) = {
+        let mut transcript = Transcript::new(b"OwnedRandomizedTest");
+        let mut rng = rand::thread_rng();
+        let mut prover = Prover::new(&pc_gens, &mut transcript);
+        let mut commit = |v: &[u64]| -> (Vec<_>, Vec<_>) {
+            v.iter()
+                .map(|v| prover.commit(Fr::from(*v), Fr::rand(&mut rng)))
+                .unzip()
+        };
+        let (x_commitments, x_vars) = commit(x);
+        let (y_commitments, y_vars) = commit(y);
+        owned_shuffle(&mut prover, x_vars, y_vars)?;
+        (
+            prover.prove(&mut rng, &bp_gens)?,
+            x_commitments,
+            y_commitments,
+        )
+    };
+
+    let mut transcript = Transcript::new(b"OwnedRandomizedTest");
+    let mut verifier = Verifier::new(&mut transcript);
+    let x_vars = x_commitments.iter().map(|c| verifier.commit(*c)).collect();
+    let y_vars = y_commitments.iter().map(|c| verifier.commit(*c)).collect();
+    owned_shuffle(&mut verifier, x_vars, y_vars)?;
+    verifier.verify(&proof, &pc_gens, &bp_gens)
+}
+
+#[test]
+fn owned_randomized_constraints_test() {
+    assert!(owned_randomized_helper(&[5, 6, 7], &[7, 5, 6]).is_ok());
+    assert!(owned_randomized_helper(&[5, 6, 7], &[7, 5, 5]).is_err());
+}
+
+/// Proves that the committed `v` squares to `square`, over two nested
+/// randomization phases: the second phase multiplies `v + r` by `v - r`
+/// for a challenge `r`, and the third phase checks the product against
+/// `square` with a multiplier scaled by a challenge `s` drawn after the
+/// second phase is committed.
+///
+/// If `nested` is false, the third phase is omitted.
+fn nested_phases_gadget<CS>(
+    cs: &mut CS,
+    v: Variable<Fr>,
+    v_value: Option<Fr>,
+    square: Fr,
+    nested: bool,
+) -> Result<(), R1CSError>
+where
+    CS: RandomizableConstraintSystem<Fr>,
+    CS::RandomizedCS: RandomizableConstraintSystem<Fr>,
+{
+    cs.specify_randomized_constraints(move |cs| {
+        let r = cs.challenge_scalar(b"r");
+        let (a, b, ab) = cs.allocate_multiplier(v_value.map(|v| (v + r, v - r)))?;
+        cs.constrain(a - v - r);
+        cs.constrain(b - v + r);
+        if !nested {
+            return Ok(());
+        }
+
+        cs.specify_randomized_constraints(move |cs| {
+            let s = cs.challenge_scalar(b"s");
+            let (_, _, o) = cs.multiply(ab + r * r, s.into());
+            cs.constrain(o - square * s);
+            Ok(())
+        })
+    })
+}
+
+fn nested_phases_proof(v: u64, square: u64) -> Result<(R1CSProof<Affine>, Affine), R1CSError> {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(8, 1);
+
+    let mut transcript = Transcript::new(b"NestedPhasesTest");
+    let mut rng = rand::thread_rng();
+    let mut prover = Prover::new(&pc_gens, &mut transcript);
+    let v = Fr::from(v);
+    let (commitment, var) = prover.commit(v, Fr::rand(&mut rng));
+    nested_phases_gadget(&mut prover, var, Some(v), Fr::from(square), true)?;
+    Ok((prover.prove(&mut rng, &bp_gens)?, commitment))
+}
+
+fn nested_phases_verify(
+    proof: &R1CSProof<Affine>,
+    commitment: Affine,
+    square: u64,
+    nested: bool,
+) -> Result<(), R1CSError> {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(8, 1);
+
+    let mut transcript = Transcript::new(b"NestedPhasesTest");
+    let mut verifier = Verifier::new(&mut transcript);
+    let var = verifier.commit(commitment);
+    nested_phases_gadget(&mut verifier, var, None, Fr::from(square), nested)?;
+    verifier.verify(proof, &pc_gens, &bp_gens)
+}
+
+#[test]
+fn nested_phases_test() {
+    let (proof, commitment) = nested_phases_proof(3, 9).unwrap();
+    assert!(nested_phases_verify(&proof, commitment, 9, true).is_ok());
+    assert!(nested_phases_verify(&proof, commitment, 10, true).is_err());
+    // The verifier must run the same number of phases as the prover.
+    assert!(matches!(
+        nested_phases_verify(&proof, commitment, 9, false),
+        Err(R1CSError::InvalidProofShape { .. })
+    ));
+
+    // The commitments of the third phase survive serialization.
+    let proof = R1CSProof::from_bytes(&proof.to_bytes().unwrap()).unwrap();
+    assert!(nested_phases_verify(&proof, commitment, 9, true).is_ok());
+    let bytes = proof.to_bytes().unwrap();
+    let trusted =
+        R1CSProof::from_bytes_with_validation(&bytes, PointValidation::TrustedInput).unwrap();
+    assert!(nested_phases_verify(&trusted, commitment, 9, true).is_ok());
+
+    let (proof, commitment) = nested_phases_proof(3, 10).unwrap();
+    assert!(nested_phases_verify(&proof, commitment, 10, true).is_err());
+}
+
+#[test]
+fn identity_policy_test() {
+    use ark_ec::AffineRepr;
+    use ark_serialize::CanonicalSerialize;
+    use ark_std::Zero;
+
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(8, 1);
+
+    // A commitment to zero with a zero blinding factor is the identity.
+    let mut transcript = Transcript::new(b"IdentityPolicyTest");
+    let mut prover = Prover::new(&pc_gens, &mut transcript);
+    let (commitment, var) = prover.commit(Fr::zero(), Fr::zero());
+    let (_, _, o) = prover.multiply(var.into(), var.into());
+    prover.constrain(o.into());
+    let proof = prover.prove(&mut rand::thread_rng(), &bp_gens).unwrap();
+    assert!(commitment.is_zero());
+
+    let verifier = |policy| {
+        let mut verifier = Verifier::new(Transcript::new(b"IdentityPolicyTest"));
+        verifier.set_identity_policy(policy);
+        let var = verifier.commit(commitment);
+        let (_, _, o) = verifier.multiply(var.into(), var.into());
+        verifier.constrain(o.into());
+        verifier
+    };
+    assert!(verifier(IdentityPolicy::default())
+        .verify(&proof, &pc_gens, &bp_gens)
+        .is_ok());
+    assert!(matches!(
+        verifier(IdentityPolicy::strict()).verify(&proof, &pc_gens, &bp_gens),
+        Err(R1CSError::InvalidPoint { label: b"V" })
+    ));
+    // Prepared circuits keep the policy of their verifier.
+    let circuit = verifier(IdentityPolicy::strict()).prepare().unwrap();
+    assert!(matches!(
+        circuit.verify(
+            &mut Transcript::new(b"IdentityPolicyTest"),
+            &[commitment],
+            &proof,
+            &pc_gens,
+            &bp_gens
+        ),
+        Err(R1CSError::InvalidPoint { label: b"V" })
+    ));
+
+    // The phases with multipliers must have non-identity commitments.
+    let nested_verify = |proof: &R1CSProof<Affine>, commitment| {
+        let mut transcript = Transcript::new(b"NestedPhasesTest");
+        let mut verifier = Verifier::new(&mut transcript);
+        verifier.set_identity_policy(IdentityPolicy::strict());
+        let var = verifier.commit(commitment);
+        nested_phases_gadget(&mut verifier, var, None, Fr::from(9u64), true)?;
+        verifier.verify(proof, &pc_gens, &bp_gens)
+    };
+    let (proof, commitment) = nested_phases_proof(3, 9).unwrap();
+    assert!(nested_verify(&proof, commitment).is_ok());
+
+    // Replace A_I2, which follows A_I1, A_O1, S1 and the flag of the
+    // absent S_C, with the identity.
+    let mut bytes = proof.to_bytes().unwrap();
+    let mut identity = Vec::new();
+    Affine::zero().serialize_compressed(&mut identity).unwrap();
+    let offset = 3 * identity.len() + 1;
+    bytes[offset..offset + identity.len()].copy_from_slice(&identity);
+    let proof = R1CSProof::from_bytes(&bytes).unwrap();
+    assert!(matches!(
+        nested_verify(&proof, commitment),
+        Err(R1CSError::InvalidPoint { label: b"A_I2" })
+    ));
+    assert!(nested_phases_verify(&proof, commitment, 9, true).is_err());
+}
+
+#[test]
+fn verifier_limits_test() {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(8, 1);
+    let (proof, commitment) = nested_phases_proof(3, 9).unwrap();
+    let verify = |limits| {
+        let mut transcript = Transcript::new(b"NestedPhasesTest");
+        let mut verifier = Verifier::new(&mut transcript);
+        verifier.set_limits(limits);
+        let var = verifier.commit(commitment);
+        nested_phases_gadget(&mut verifier, var, None, Fr::from(9u64), true)?;
+        verifier.verify(&proof, &pc_gens, &bp_gens)
+    };
+
+    // The circuit has 1 commitment and 2 multipliers, in the second and
+    // third phases, so its inner-product proof has 1 round.
+    let limits = VerifierLimits {
+        max_multipliers: 2,
+        max_commitments: 1,
+        max_ipp_rounds: 1,
+    };
+    assert!(verify(VerifierLimits::default()).is_ok());
+    assert!(verify(limits).is_ok());
+    assert_eq!(
+        verify(VerifierLimits {
+            max_multipliers: 1,
+            ..limits
+        }),
+        Err(R1CSError::LimitExceeded {
+            resource: "multipliers",
+            limit: 1,
+            actual: 2
+        })
+    );
+    assert_eq!(
+        verify(VerifierLimits {
+            max_commitments: 0,
+            ..limits
+        }),
+        Err(R1CSError::LimitExceeded {
+            resource: "commitments",
+            limit: 0,
+            actual: 1
+        })
+    );
+    assert_eq!(
+        verify(VerifierLimits {
+            max_ipp_rounds: 0,
+            ..limits
+        }),
+        Err(R1CSError::LimitExceeded {
+            resource: "inner-product rounds",
+            limit: 0,
+            actual: 1
+        })
+    );
+}
+
+/// Enforces `x == y` for vectors of committed values, with a single
+/// constraint weighted by one challenge per element.
+fn equal_vectors_gadget<CS: RandomizableConstraintSystem<Fr>>(
+    cs: &mut CS,
+    x: Vec<Variable<Fr>>,
+    y: Vec<Variable<Fr>>,
+) -> Result<(), R1CSError> {
+    cs.specify_randomized_constraints(move |cs| {
+        let c = cs.challenge_scalars(b"equal vectors", x.len());
+        let lc: LinearCombination<Fr> = x
+            .iter()
+            .zip(&y)
+            .zip(&c)
+            .map(|((x_i, y_i), c_i)| (*x_i - *y_i) * *c_i)
+            .sum();
+        cs.constrain(lc);
+        Ok(())
+    })
+}
+
+fn equal_vectors_helper(x: &[u64], y: &[u64]) -> Result<(), R1CSError> {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(8, 1);
+
+    let (proof, x_commitments, y_commitments) = {
+        let mut transcript = Transcript::new(b"EqualVectorsTest");
+        let mut rng = rand::thread_rng();
+        let mut prover = Prover::new(&pc_gens, &mut transcript);
+        let mut commit = |v: &[u64]| -> (Vec<_>, Vec<_>) {
+            v.iter()
+                .map(|v| prover.commit(Fr::from(*v), Fr::rand(&mut rng)))
+                .unzip()
+        };
+        let (x_commitments, x_vars) = commit(x);
+        let (y_commitments, y_vars) = commit(y);
+        equal_vectors_gadget(&mut prover, x_vars, y_vars)?;
+        (
+            prover.prove(&mut rng, &bp_gens)?,
+            x_commitments,
+            y_commitments,
+        )
+    };
+
+    let mut transcript = Transcript::new(b"EqualVectorsTest");
+    let mut verifier = Verifier::new(&mut transcript);
+    let x_vars = x_commitments.iter().map(|c| verifier.commit(*c)).collect();
+    let y_vars = y_commitments.iter().map(|c| verifier.commit(*c)).collect();
+    equal_vectors_gadget(&mut verifier, x_vars, y_vars)?;
+    verifier.verify(&proof, &pc_gens, &bp_gens)
+}
+
+/// Proves and verifies that `y` is a shuffle of `x`, with the prover in
+/// streaming mode with `chunk_size` if given.
+fn streaming_helper(chunk_size: Option<usize>, x: &[u64], y: &[u64]) -> Result<(), R1CSError> {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(16, 1);
+    let mut rng = rand::thread_rng();
+
+    let mut prover = Prover::new(&pc_gens, Transcript::new(b"StreamingTest"));
+    prover.set_chunk_size(chunk_size);
+    let mut commit = |v: &[u64]| -> (Vec<_>, Vec<_>) {
+        v.iter()
+            .map(|v| prover.commit(Fr::from(*v), Fr::rand(&mut rng)))
+            .unzip()
+    };
+    let (x_commitments, x_vars) = commit(x);
+    let (y_commitments, y_vars) = commit(y);
+    ShuffleProof::gadget(&mut prover, x_vars, y_vars)?;
+    let proof = prover.prove(&mut rng, &bp_gens)?;
+
+    let mut verifier = Verifier::new(Transcript::new(b"StreamingTest"));
+    let x_vars = x_commitments.iter().map(|c| verifier.commit(*c)).collect();
+    let y_vars = y_commitments.iter().map(|c| verifier.commit(*c)).collect();
+    ShuffleProof::gadget(&mut verifier, x_vars, y_vars)?;
+    verifier.verify(&proof, &pc_gens, &bp_gens)
+}
+
+#[test]
+fn compact_verifier_test() {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(16, 1);
+    let mut rng = rand::thread_rng();
+
+    // A shuffle, whose 5 multipliers are padded to 8, in two phases.
+    let (x, y) = ([3u64, 1, 4], [4u64, 3, 1]);
+    let mut prover = Prover::new(&pc_gens, Transcript::new(b"CompactTest"));
+    let mut commit = |v: &[u64]| -> (Vec<_>, Vec<_>) {
+        v.iter()
+            .map(|v| prover.commit(Fr::from(*v), Fr::rand(&mut rng)))
+            .unzip()
+    };
+    let (x_commitments, x_vars) = commit(&x);
+    let (y_commitments, y_vars) = commit(&y);
+    ShuffleProof::gadget(&mut prover, x_vars, y_vars).unwrap();
+    let proof = prover.prove(&mut rng, &bp_gens).unwrap();
+
+    let shuffle_verifier = |y_commitments: &[Affine]| {
+        let mut verifier = Verifier::new(Transcript::new(b"CompactTest"));
+        let x_vars = x_commitments.iter().map(|c| verifier.commit(*c)).collect();
+        let y_vars = y_commitments.iter().map(|c| verifier.commit(*c)).collect();
+        ShuffleProof::gadget(&mut verifier, x_vars, y_vars).unwrap();
+        verifier
+    };
+    assert!(shuffle_verifier(&y_commitments)
+        .verify_compact::<16>(&proof, &pc_gens, &bp_gens)
+        .is_ok());
+    assert!(shuffle_verifier(&y_commitments)
+        .verify_compact::<1>(&proof, &pc_gens, &bp_gens)
+        .is_ok());
+    assert!(shuffle_verifier(&y_commitments)
+        .verify_compact::<7>(&proof, &pc_gens, &bp_gens)
+        .is_ok());
+    let swapped = [y_commitments[1], y_commitments[0], y_commitments[2]];
+    assert_eq!(
+        shuffle_verifier(&swapped).verify_compact::<16>(&proof, &pc_gens, &bp_gens),
+        Err(R1CSError::VerificationError)
+    );
+
+    // Three phases.
+    let (proof, commitment) = nested_phases_proof(3, 9).unwrap();
+    let nested_verifier = |square: u64| {
+        let mut verifier = Verifier::new(Transcript::new(b"NestedPhasesTest"));
+        let var = verifier.commit(commitment);
+        nested_phases_gadget(&mut verifier, var, None, Fr::from(square), true).unwrap();
+        verifier
+    };
+    let bp_gens = BulletproofGens::new(8, 1);
+    assert!(nested_verifier(9)
+        .verify_compact::<16>(&proof, &pc_gens, &bp_gens)
+        .is_ok());
+    assert!(nested_verifier(10)
+        .verify_compact::<16>(&proof, &pc_gens, &bp_gens)
+        .is_err());
+
+    // Vector commitments.
+    let values: Vec<Fr> = [2u64, 3, 5].iter().map(|v| Fr::from(*v)).collect();
+    let mut prover = Prover::new(&pc_gens, Transcript::new(b"CompactVectorTest"));
+    let (C, entries) = prover
+        .commit_vec(&bp_gens, &values, Fr::rand(&mut rng))
+        .unwrap();
+    let (V, product) = prover.commit(Fr::from(6u64), Fr::rand(&mut rng));
+    vector_commitment_circuit(&mut prover, &entries, 10, product);
+    let proof = prover.prove(&mut rng, &bp_gens).unwrap();
+
+    let vector_verifier = |sum: u64| {
+        let mut verifier = Verifier::new(Transcript::new(b"CompactVectorTest"));
+        let entries = verifier.commit_vec(C, 3).unwrap();
+        let product = verifier.commit(V);
+        vector_commitment_circuit(&mut verifier, &entries, sum, product);
+        verifier
+    };
+    assert!(vector_verifier(10)
+        .verify_compact::<16>(&proof, &pc_gens, &bp_gens)
+        .is_ok());
+    assert!(vector_verifier(11)
+        .verify_compact::<16>(&proof, &pc_gens, &bp_gens)
+        .is_err());
+}
+
+#[test]
+fn progress_callback_test() {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(16, 1);
+    let mut rng = rand::thread_rng();
+    let mut stages = Vec::new();
+
+    let mut prover = Prover::new(&pc_gens, Transcript::new(b"ProgressTest"));
+    prover.set_progress_callback(|stage| stages.push(stage));
+    let (_, vars): (Vec<_>, Vec<_>) = [1u64, 2, 3, 3, 1, 2]
+        .iter()
+        .map(|v| prover.commit(Fr::from(*v), Fr::rand(&mut rng)))
+        .unzip();
+    ShuffleProof::gadget(&mut prover, vars[..3].to_vec(), vars[3..].to_vec()).unwrap();
+    prover.prove(&mut rng, &bp_gens).unwrap();
+
+    // The shuffle of 3 values has 4 multipliers, i.e. 2 rounds of the
+    // inner-product proof.
+    assert_eq!(
+        stages,
+        vec![
+            ProvingStage::FirstRound,
+            ProvingStage::RandomizationPhase(1),
+            ProvingStage::PolynomialCommitments,
+            ProvingStage::InnerProductRound {
+                round: 1,
+                rounds: 2
+            },
+            ProvingStage::InnerProductRound {
+                round: 2,
+                rounds: 2
+            },
+            ProvingStage::Done,
+        ]
+    );
+}
+
+#[test]
+fn cancellation_test() {
+    use std::sync::atomic::AtomicBool;
+
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(16, 1);
+    let mut rng = rand::thread_rng();
+    let values = [1u64, 2, 3, 3, 1, 2];
+    let prover = |rng: &mut rand::rngs::ThreadRng| {
+        let mut prover = Prover::new(&pc_gens, Transcript::new(b"CancellationTest"));
+        let (commitments, vars): (Vec<_>, Vec<_>) = values
+            .iter()
+            .map(|v| prover.commit(Fr::from(*v), Fr::rand(rng)))
+            .unzip();
+        ShuffleProof::gadget(&mut prover, vars[..3].to_vec(), vars[3..].to_vec()).unwrap();
+        (prover, commitments)
+    };
+
+    // Cancelling during the proof aborts it at the next stage.
+    let cancel = Arc::new(AtomicBool::new(false));
+    let (mut cancelled, _) = prover(&mut rng);
+    cancelled.set_cancellation_flag(cancel.clone());
+    let flag = cancel.clone();
+    cancelled.set_progress_callback(move |stage| {
+        if stage == ProvingStage::FirstRound {
+            flag.store(true, Ordering::SeqCst);
+        }
+    });
+    assert_eq!(
+        cancelled.prove(&mut rng, &bp_gens).unwrap_err(),
+        R1CSError::Cancelled
+    );
+
+    let (mut prover, commitments) = prover(&mut rng);
+    prover.set_cancellation_flag(Arc::new(AtomicBool::new(false)));
+    let proof = prover.prove(&mut rng, &bp_gens).unwrap();
+
+    let verify = |cancel: bool| {
+        let mut verifier = Verifier::new(Transcript::new(b"CancellationTest"));
+        verifier.set_cancellation_flag(Arc::new(AtomicBool::new(cancel)));
+        let vars: Vec<_> = commitments.iter().map(|V| verifier.commit(*V)).collect();
+        ShuffleProof::gadget(&mut verifier, vars[..3].to_vec(), vars[3..].to_vec()).unwrap();
+        verifier.verify(&proof, &pc_gens, &bp_gens)
+    };
+    assert!(verify(false).is_ok());
+    assert_eq!(verify(true), Err(R1CSError::Cancelled));
+}
+
+#[test]
+fn streaming_prover_test() {
+    for chunk_size in [None, Some(1), Some(3), Some(4), Some(64)] {
+        assert!(streaming_helper(chunk_size, &[1, 2, 3, 4, 5], &[3, 5, 1, 4, 2]).is_ok());
+        assert!(streaming_helper(chunk_size, &[1, 2, 3, 4, 5], &[3, 5, 1, 4, 4]).is_err());
+    }
+}
+
+#[test]
+fn challenge_scalars_test() {
+    let mut cs = test_utils::MockCS::<Fr>::new();
+    let c = cs.challenge_scalars(b"test", 4);
+    assert_eq!(c.len(), 4);
+    for i in 0..4 {
+        for j in 0..i {
+            assert_ne!(c[i], c[j]);
+        }
+    }
+
+    assert!(equal_vectors_helper(&[1, 2, 3], &[1, 2, 3]).is_ok());
+    // Differences that cancel out with equal weights are caught.
+    assert!(equal_vectors_helper(&[1, 2, 3], &[2, 1, 3]).is_err());
+}
+
+#[test]
+fn statements_test() {
+    use ark_bulletproofs::r1cs::statements::*;
+
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(128, 1);
+    let mut rng = rand::thread_rng();
+    let blinding = || Fr::rand(&mut rand::thread_rng());
+
+    // Range
+    let (proof, commitment) = prove_in_range(
+        &pc_gens,
+        &bp_gens,
+        &mut Transcript::new(b"StatementsTest"),
+        Fr::from(200u64),
+        blinding(),
+        8,
+        &mut rng,
+    )
+    .unwrap();
+    let verify = |n| {
+        verify_in_range(
+            &pc_gens,
+            &bp_gens,
+            &mut Transcript::new(b"StatementsTest"),
+            &proof,
+            &commitment,
+            n,
+        )
+    };
+    assert!(verify(8).is_ok());
+    assert!(verify(16).is_err());
+    let (proof, commitment) = prove_in_range(
+        &pc_gens,
+        &bp_gens,
+        &mut Transcript::new(b"StatementsTest"),
+        Fr::from(300u64),
+        blinding(),
+        8,
+        &mut rng,
+    )
+    .unwrap();
+    assert!(verify_in_range(
+        &pc_gens,
+        &bp_gens,
+        &mut Transcript::new(b"StatementsTest"),
+        &proof,
+        &commitment,
+        8,
+    )
+    .is_err());
+
+    // Greater than
+    let greater_than = |a: u64, b: u64| {
+        let mut rng = rand::thread_rng();
+        let (proof, (c_a, c_b)) = prove_greater_than(
+            &pc_gens,
+            &bp_gens,
+            &mut Transcript::new(b"StatementsTest"),
+            (Fr::from(a), blinding()),
+            (Fr::from(b), blinding()),
+            16,
+            &mut rng,
+        )?;
+        verify_greater_than(
+            &pc_gens,
+            &bp_gens,
+            &mut Transcript::new(b"StatementsTest"),
+            &proof,
+            (&c_a, &c_b),
+            16,
+        )
+    };
+    assert!(greater_than(1000, 999).is_ok());
+    assert!(greater_than(1000, 1000).is_err());
+    assert!(greater_than(5, 1000).is_err());
+    assert!(greater_than(1 << 16, 1).is_err());
+
+    // Equality
+    let (proof, (c_a, c_b)) = prove_equal(
+        &pc_gens,
+        &bp_gens,
+        &mut Transcript::new(b"StatementsTest"),
+        Fr::from(42u64),
+        (blinding(), blinding()),
+        &mut rng,
+    )
+    .unwrap();
+    let verify = |c_a: &Affine, c_b: &Affine| {
+        verify_equal(
+            &pc_gens,
+            &bp_gens,
+            &mut Transcript::new(b"StatementsTest"),
+            &proof,
+            (c_a, c_b),
+        )
+    };
+    assert!(verify(&c_a, &c_b).is_ok());
+    let c_other = pc_gens.commit(Fr::from(43u64), blinding());
+    assert!(verify(&c_a, &c_other).is_err());
+}
+
+#[test]
+fn solvency_test() {
+    use ark_bulletproofs::r1cs::solvency::*;
+
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(256, 1);
+    let mut rng = rand::thread_rng();
+
+    let users: [&[u8]; 3] = [b"alice", b"bob", b"carol"];
+    let balances = [100u64, 2000, 30];
+    let liabilities: Vec<_> = users
+        .iter()
+        .zip(&balances)
+        .map(|(user, balance)| (*user, *balance, Fr::rand(&mut rng)))
+        .collect();
+
+    let solvency = |assets: u64| {
+        let mut rng = rand::thread_rng();
+        let (proof, tree) = prove_solvency(
+            &pc_gens,
+            &bp_gens,
+            &mut Transcript::new(b"SolvencyTest"),
+            &liabilities,
+            assets,
+            16,
+            &mut rng,
+        )
+        .unwrap();
+        verify_solvency(
+            &pc_gens,
+            &bp_gens,
+            &mut Transcript::new(b"SolvencyTest"),
+            &proof,
+            &tree,
+            assets,
+            16,
+        )
+        .map(|_| (proof, tree))
+    };
+    assert!(solvency(2130).is_ok());
+    assert!(solvency(5000).is_ok());
+    assert!(solvency(2129).is_err());
+
+    let (proof, tree) = solvency(3000).unwrap();
+    // The root commits to the total liabilities
+    let blinding = liabilities.iter().map(|(_, _, b)| b).sum();
+    assert_eq!(
+        tree.root().commitment,
+        pc_gens.commit(Fr::from(2130u64), blinding)
+    );
+
+    // Every user can check their inclusion
+    let root = tree.root();
+    for (i, (user, balance, blinding)) in liabilities.iter().enumerate() {
+        let inclusion = tree.inclusion_proof(i).unwrap();
+        assert!(inclusion
+            .verify(&pc_gens, user, *balance, *blinding, &root)
+            .is_ok());
+        assert!(inclusion
+            .verify(&pc_gens, user, *balance + 1, *blinding, &root)
+            .is_err());
+    }
+    assert!(tree.inclusion_proof(3).is_none());
+
+    // A tree with a tampered sum is rejected
+    let mut leaves = tree.leaves().to_vec();
+    leaves[1] = LiabilitiesNode::leaf(&pc_gens, b"bob", 20, liabilities[1].2);
+    let tampered = LiabilitiesTree::new(leaves);
+    assert!(verify_solvency(
+        &pc_gens,
+        &bp_gens,
+        &mut Transcript::new(b"SolvencyTest"),
+        &proof,
+        &tampered,
+        3000,
+        16,
+    )
+    .is_err());
+}
+
+#[test]
+fn balance_statement_test() {
+    use ark_bulletproofs::r1cs::statements::*;
+
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(128, 1);
+    let opening = |v: u64| (Fr::from(v), Fr::rand(&mut rand::thread_rng()));
+
+    let balance = |inputs: &[u64], outputs: &[u64], fee: u64| {
+        let mut rng = rand::thread_rng();
+        let inputs: Vec<_> = inputs.iter().map(|v| opening(*v)).collect();
+        let outputs: Vec<_> = outputs.iter().map(|v| opening(*v)).collect();
+        let (proof, (c_in, c_out)) = prove_balance(
+            &pc_gens,
+            &bp_gens,
+            &mut Transcript::new(b"BalanceTest"),
+            (&inputs, &outputs),
+            fee,
+            32,
+            &mut rng,
+        )
+        .unwrap();
+        verify_balance(
+            &pc_gens,
+            &bp_gens,
+            &mut Transcript::new(b"BalanceTest"),
+            &proof,
+            (&c_in, &c_out),
+            fee,
+            32,
+        )
+    };
+
+    assert!(balance(&[100, 50], &[120, 20], 10).is_ok());
+    assert!(balance(&[100], &[100], 0).is_ok());
+    assert!(balance(&[100, 50], &[120, 20], 11).is_err());
+    assert!(balance(&[100, 50], &[120, 30], 10).is_err());
+    // Outputs must be in range, even if they balance modulo the field.
+    let mut rng = rand::thread_rng();
+    let inputs = [opening(100)];
+    let outputs = [opening(200), (-Fr::from(100u64), Fr::rand(&mut rng))];
+    let (proof, (c_in, c_out)) = prove_balance(
+        &pc_gens,
+        &bp_gens,
+        &mut Transcript::new(b"BalanceTest"),
+        (&inputs, &outputs),
+        0,
+        32,
+        &mut rng,
+    )
+    .unwrap();
+    assert!(verify_balance(
+        &pc_gens,
+        &bp_gens,
+        &mut Transcript::new(b"BalanceTest"),
+        &proof,
+        (&c_in, &c_out),
+        0,
+        32,
+    )
+    .is_err());
+}
+
+#[test]
+fn lc_macro_test() {
+    use ark_bulletproofs::lc;
+
+    let mut cs = test_utils::MockCS::new();
+    let a = cs.commit(Fr::from(2u64));
+    let b = cs.commit(Fr::from(7u64));
+    let c = cs.commit(Fr::from(5u64));
+    let k = Fr::from(4u64);
+
+    let expected = a * Fr::from(3u64) + b - c * Fr::from(2u64) + Fr::from(5u64);
+    assert_eq!(lc!(3 * a + b - 2 * c + 5), expected);
+    assert_eq!(cs.eval(&lc!(3 * a + b - 2 * c + 5)), Fr::from(8u64));
+    assert_eq!(cs.eval(&lc!(-a + (k) * b - 1)), Fr::from(25u64));
+    assert_eq!(cs.eval(&lc!((lc!(a + b)) - 9)), Fr::from(0u64));
+    assert_eq!(lc!(a), LinearCombination::from(a));
+}
+
+#[test]
+fn linear_combination_arithmetic_test() {
+    let mut cs = test_utils::MockCS::new();
+    let a = cs.commit(Fr::from(2u64));
+    let b = cs.commit(Fr::from(7u64));
+
+    // Integer coefficients and references.
+    let lc = a * 3u64 + b * -2i64 + LinearCombination::from(Fr::from(1u128 << 70));
+    let doubled = &lc * 2u128;
+    assert_eq!(cs.eval(&(&lc + &doubled)), cs.eval(&lc) * Fr::from(3u64));
+    assert_eq!(cs.eval(&(&lc - &lc)), Fr::from(0u64));
+    assert_eq!(cs.eval(&-&lc), -cs.eval(&lc));
+
+    // Sums of variables and of linear combinations.
+    let vars = [a, b, a];
+    let sum: LinearCombination<Fr> = vars.iter().sum();
+    assert_eq!(cs.eval(&sum), Fr::from(11u64));
+    let sum: LinearCombination<Fr> = [lc.clone(), doubled].into_iter().sum();
+    assert_eq!(cs.eval(&sum), cs.eval(&lc) * Fr::from(3u64));
+
+    // Terms of the same variable are merged on construction.
+    let lc: LinearCombination<Fr> = vec![
+        (a, Fr::from(1u64)),
+        (b, Fr::from(2u64)),
+        (a, Fr::from(3u64)),
+    ]
+    .into_iter()
+    .collect();
+    assert_eq!(lc, a * 4u64 + b * 2u64);
+}
+
+/// Proves knowledge of a committed `x` such that `x * x + 1` equals the
+/// public input `y`.
+fn public_input_helper(x: u64, prover_y: u64, verifier_y: u64) -> Result<(), R1CSError> {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(8, 1);
+
+    let (proof, commitment) = {
+        let mut transcript = Transcript::new(b"PublicInputTest");
+        let mut rng = rand::thread_rng();
+        let mut prover = Prover::new(&pc_gens, &mut transcript);
+        let (com, x_var) = prover.commit(Fr::from(x), Fr::rand(&mut rng));
+        let y_var = prover.public_input(Fr::from(prover_y));
+        let (_, _, sq) = prover.multiply(x_var.into(), x_var.into());
+        prover.constrain(sq + Fr::one() - y_var);
+        (prover.prove(&mut rng, &bp_gens)?, com)
+    };
+
+    let mut transcript = Transcript::new(b"PublicInputTest");
+    let mut verifier = Verifier::new(&mut transcript);
+    let x_var = verifier.commit(commitment);
+    let y_var = verifier.public_input(Fr::from(verifier_y));
+    let (_, _, sq) = verifier.multiply(x_var.into(), x_var.into());
+    verifier.constrain(sq + Fr::one() - y_var);
+    verifier.verify(&proof, &pc_gens, &bp_gens)
+}
+
+#[test]
+fn public_input_test() {
+    assert!(public_input_helper(3, 10, 10).is_ok());
+    // The statement is false.
+    assert!(public_input_helper(3, 11, 11).is_err());
+    // The verifier expects a different public input.
+    assert!(public_input_helper(3, 10, 11).is_err());
+
+    let mut cs = test_utils::MockCS::<Fr>::new();
+    let x = cs.commit(Fr::from(3u64));
+    let y = cs.public_input(Fr::from(9u64));
+    let (_, _, sq) = cs.multiply(x.into(), x.into());
+    cs.constrain(sq - y);
+    assert!(cs.is_satisfied());
+
+    // Circuits with public inputs are bound to their values.
+    let mut transcript = Transcript::new(b"PublicInputTest");
+    let mut verifier = Verifier::<Affine, _>::new(&mut transcript);
+    verifier.public_input(Fr::one());
+    assert!(verifier.prepare().is_err());
+}
+
+/// Proves that the entries of a vector commitment sum to `sum`, and that
+/// the first two multiply to the committed `product`.
+fn vector_commitment_circuit<CS: ConstraintSystem<Fr>>(
+    cs: &mut CS,
+    entries: &[Variable<Fr>],
+    sum: u64,
+    product: Variable<Fr>,
+) {
+    let total: LinearCombination<Fr> = entries.iter().map(|e| LinearCombination::from(*e)).sum();
+    cs.constrain(total - Fr::from(sum));
+    let (_, _, out) = cs.multiply(entries[0].into(), entries[1].into());
+    cs.constrain(out - product);
+}
+
+fn vector_commitment_helper(
+    values: &[u64],
+    verifier_values: &[u64],
+    sum: u64,
+    product: u64,
+) -> Result<(), R1CSError> {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(8, 1);
+    let mut rng = rand::thread_rng();
+    let to_fr = |v: &[u64]| v.iter().map(|v| Fr::from(*v)).collect::<Vec<_>>();
+
+    let (proof, C, V) = {
+        let mut transcript = Transcript::new(b"VectorCommitmentTest");
+        let mut prover = Prover::new(&pc_gens, &mut transcript);
+        let (C, entries) = prover.commit_vec(&bp_gens, &to_fr(values), Fr::rand(&mut rng))?;
+        let (V, product) = prover.commit(Fr::from(product), Fr::rand(&mut rng));
+        vector_commitment_circuit(&mut prover, &entries, sum, product);
+        (prover.prove(&mut rng, &bp_gens)?, C, V)
+    };
+
+    // A commitment to other values, as a dishonest prover could send.
+    let C = if values == verifier_values {
+        C
+    } else {
+        let mut transcript = Transcript::new(b"Unused");
+        let mut prover = Prover::new(&pc_gens, &mut transcript);
+        let values = to_fr(verifier_values);
+        prover.commit_vec(&bp_gens, &values, Fr::rand(&mut rng))?.0
+    };
+
+    let mut transcript = Transcript::new(b"VectorCommitmentTest");
+    let mut verifier = Verifier::new(&mut transcript);
+    let entries = verifier.commit_vec(C, verifier_values.len())?;
+    let product = verifier.commit(V);
+    vector_commitment_circuit(&mut verifier, &entries, sum, product);
+    verifier.verify(&proof, &pc_gens, &bp_gens)
+}
+
+#[test]
+fn circuit_shape_test() {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(16, 1);
+    let mut rng = rand::thread_rng();
+    let values: Vec<Fr> = [3u64, 4, 5].iter().map(|v| Fr::from(*v)).collect();
+
+    // A vector commitment to 3 entries, and one multiplier.
+    let mut prover = Prover::new(&pc_gens, Transcript::new(b"CircuitShapeTest"));
+    let (C, entries) = prover
+        .commit_vec(&bp_gens, &values, Fr::rand(&mut rng))
+        .unwrap();
+    let (V, product) = prover.commit(Fr::from(12u64), Fr::rand(&mut rng));
+    vector_commitment_circuit(&mut prover, &entries, 12, product);
+    let proof = prover.prove(&mut rng, &bp_gens).unwrap();
+
+    let mut verifier = Verifier::new(Transcript::new(b"CircuitShapeTest"));
+    let entries = verifier.commit_vec(C, 3).unwrap();
+    let product = verifier.commit(V);
+    vector_commitment_circuit(&mut verifier, &entries, 12, product);
+    let (points, _) = verifier
+        .verification_msm(&proof, &pc_gens, &bp_gens)
+        .unwrap();
+
+    let shape = CircuitShape {
+        multipliers: 4,
+        commitments: 1,
+        vector_commitments: 1,
+        randomization_phases: 0,
+    };
+    assert_eq!(
+        proof.to_bytes().unwrap().len(),
+        shape.proof_size::<Affine>()
+    );
+    assert_eq!(points.len(), shape.verifier_msm_len());
+
+    // A shuffle of 5 values, with 8 multipliers in a randomization phase.
+    let x: Vec<Fr> = (1..=5u64).map(Fr::from).collect();
+    let mut prover = Prover::new(&pc_gens, Transcript::new(b"CircuitShapeTest"));
+    let (commitments, vars): (Vec<_>, Vec<_>) = x
+        .iter()
+        .chain(x.iter().rev())
+        .map(|v| prover.commit(*v, Fr::rand(&mut rng)))
+        .unzip();
+    ShuffleProof::gadget(&mut prover, vars[..5].to_vec(), vars[5..].to_vec()).unwrap();
+    let proof = prover.prove(&mut rng, &bp_gens).unwrap();
+
+    let mut verifier = Verifier::new(Transcript::new(b"CircuitShapeTest"));
+    let vars: Vec<_> = commitments.iter().map(|V| verifier.commit(*V)).collect();
+    ShuffleProof::gadget(&mut verifier, vars[..5].to_vec(), vars[5..].to_vec()).unwrap();
+    let (points, _) = verifier
+        .verification_msm(&proof, &pc_gens, &bp_gens)
+        .unwrap();
+
+    let shape = CircuitShape {
+        multipliers: 8,
+        commitments: 10,
+        vector_commitments: 0,
+        randomization_phases: 1,
+    };
+    assert_eq!(
+        proof.to_bytes().unwrap().len(),
+        shape.proof_size::<Affine>()
+    );
+    assert_eq!(points.len(), shape.verifier_msm_len());
+
+    // Streaming bounds the memory of the vector polynomials.
+    let large = CircuitShape {
+        multipliers: 1 << 20,
+        ..shape
+    };
+    assert!(large.prover_memory::<Affine>(Some(1 << 12)) < large.prover_memory::<Affine>(None));
+}
+
+#[test]
+fn vector_commitment_test() {
+    assert!(vector_commitment_helper(&[3, 4, 5], &[3, 4, 5], 12, 12).is_ok());
+    assert!(vector_commitment_helper(&[3, 4, 5], &[3, 4, 5], 13, 12).is_err());
+    assert!(vector_commitment_helper(&[3, 4, 5], &[3, 4, 5], 12, 15).is_err());
+    // The proof does not hold for a commitment to other values.
+    assert!(vector_commitment_helper(&[3, 4, 5], &[4, 3, 5], 12, 12).is_err());
+    assert!(vector_commitment_helper(&[3, 4, 5], &[3, 4, 5, 0], 12, 12).is_err());
+
+    // Vector commitments must precede the multipliers.
+    let mut cs = test_utils::MockCS::<Fr>::new();
+    let entries = cs.commit_vec(&[Fr::from(2u64), Fr::from(5u64)]).unwrap();
+    let product = cs.commit(Fr::from(10u64));
+    vector_commitment_circuit(&mut cs, &entries, 7, product);
+    assert!(cs.is_satisfied());
+    assert!(cs.commit_vec(&[Fr::one()]).is_err());
+}
+
+fn seeded_proof_helper(seed: u64) -> Vec<u8> {
+    use rand_chacha::{rand_core::SeedableRng, ChaChaRng};
+
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(128, 1);
+    let mut rng = ChaChaRng::seed_from_u64(seed);
+
+    let mut transcript = Transcript::new(b"R1CSExampleGadget");
+    let mut prover = Prover::new(&pc_gens, &mut transcript);
+    let vars: Vec<_> = [3u64, 4, 6, 1, 40]
+        .iter()
+        .map(|x| prover.commit(Fr::from(*x), Fr::rand(&mut rng)).1)
+        .collect();
+    example_gadget(
+        &mut prover,
+        vars[0].into(),
+        vars[1].into(),
+        vars[2].into(),
+        vars[3].into(),
+        vars[4].into(),
+        Fr::from(9u64).into(),
+    );
+    prover
+        .prove(&mut rng, &bp_gens)
+        .unwrap()
+        .to_bytes()
+        .unwrap()
+}
+
+#[test]
+fn seeded_rng_test() {
+    // All of the prover randomness comes from the caller's RNG.
+    assert_eq!(seeded_proof_helper(1), seeded_proof_helper(1));
+    assert_ne!(seeded_proof_helper(1), seeded_proof_helper(2));
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+fn perf_report_test() {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(128, 1);
+    let mut rng = rand::thread_rng();
+
+    let mut prover = Prover::new(&pc_gens, Transcript::new(b"R1CSExampleGadget"));
+    let (commitments, vars): (Vec<_>, Vec<_>) = [3u64, 4, 6, 1, 40]
+        .iter()
+        .map(|x| prover.commit(Fr::from(*x), Fr::rand(&mut rng)))
+        .unzip();
+    example_gadget(
+        &mut prover,
+        vars[0].into(),
+        vars[1].into(),
+        vars[2].into(),
+        vars[3].into(),
+        vars[4].into(),
+        Fr::from(9u64).into(),
+    );
+    let (proof, prover_report) = prover.prove_with_report(&mut rng, &bp_gens).unwrap();
+
+    let verifier = |output: u64| {
+        let mut verifier = Verifier::new(Transcript::new(b"R1CSExampleGadget"));
+        let vars: Vec<_> = commitments.iter().map(|V| verifier.commit(*V)).collect();
+        example_gadget(
+            &mut verifier,
+            vars[0].into(),
+            vars[1].into(),
+            vars[2].into(),
+            vars[3].into(),
+            vars[4].into(),
+            Fr::from(output).into(),
+        );
+        verifier
+    };
+    let verifier_report = verifier(9)
+        .verify_with_report(&proof, &pc_gens, &bp_gens)
+        .unwrap();
+
+    // 6 commitments to the phases and 5 to the polynomial t(x), while
+    // the inner-product proof of the single multiplier has no rounds.
+    assert_eq!(prover_report.point_count, 11);
+    assert_eq!(verifier_report.point_count, 11);
+    // The Pedersen generators, 2 generators per multiplier, the points
+    // of the proof and the commitments.
+    assert_eq!(verifier_report.scalar_count, 2 + 2 + 11 + 5);
+    assert!(prover_report.scalar_count > 0);
+    for report in [prover_report, verifier_report] {
+        assert!(report.flatten_ms >= 0.0 && report.msm_ms > 0.0 && report.ipp_ms >= 0.0);
+    }
+
+    assert_eq!(
+        verifier(10).verify_with_report(&proof, &pc_gens, &bp_gens),
+        Err(R1CSError::VerificationError)
+    );
+}
+
+#[cfg(feature = "deterministic")]
+#[test]
+fn deterministic_proof_test() {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(128, 1);
+
+    let proof = |seed: [u8; 32], a1: u64, a2: u64| {
+        let mut transcript = Transcript::new(b"R1CSExampleGadget");
+        let mut prover = Prover::new(&pc_gens, &mut transcript);
+        let vars: Vec<_> = [a1, a2, 6, 1, 40]
+            .iter()
+            .map(|x| prover.commit(Fr::from(*x), Fr::from(*x + 1)).1)
+            .collect();
+        example_gadget(
+            &mut prover,
+            vars[0].into(),
+            vars[1].into(),
+            vars[2].into(),
+            vars[3].into(),
+            vars[4].into(),
+            Fr::from(9u64).into(),
+        );
+        prover
+            .prove_deterministic(seed, &bp_gens)
+            .unwrap()
+            .to_bytes()
+            .unwrap()
+    };
+
+    assert_eq!(proof([1; 32], 3, 4), proof([1; 32], 3, 4));
+    assert_ne!(proof([1; 32], 3, 4), proof([2; 32], 3, 4));
+    // The witness is bound as well as the seed.
+    assert_ne!(proof([1; 32], 3, 4), proof([1; 32], 4, 3));
+}
+
+fn uncommitted_witness_proof(x: u64, y: u64) -> Vec<u8> {
+    use rand_chacha::{rand_core::SeedableRng, ChaChaRng};
+
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(16, 1);
+    let mut transcript = Transcript::new(b"R1CSWitnessRng");
+    let mut prover = Prover::new(&pc_gens, &mut transcript);
+    let (_, _, o) = prover.multiply(Fr::from(x).into(), Fr::from(y).into());
+    prover.constrain(o - Fr::from(x * y));
+    prover
+        .prove(&mut ChaChaRng::seed_from_u64(0), &bp_gens)
+        .unwrap()
+        .to_bytes()
+        .unwrap()
+}
+
+#[test]
+fn witness_bound_rng_test() {
+    use ark_serialize::CanonicalSerialize;
+
+    // With the same RNG and without any commitment, the commitment S1 to
+    // the blinding vectors must still depend on the witness.
+    let point_len = Affine::default().compressed_size();
+    let S1 = |proof: &[u8]| proof[1 + 2 * point_len..1 + 3 * point_len].to_vec();
+    assert_eq!(
+        S1(&uncommitted_witness_proof(2, 3)),
+        S1(&uncommitted_witness_proof(2, 3))
+    );
+    assert_ne!(
+        S1(&uncommitted_witness_proof(2, 3)),
+        S1(&uncommitted_witness_proof(3, 2))
+    );
+}
+
+fn verify_with_rng_helper(a1: u64) -> Result<(), R1CSError> {
+    let pc_gens = PedersenGens::default();
+    let bp_gens = BulletproofGens::new(128, 1);
+    let (proof, commitments) = example_gadget_proof(&pc_gens, &bp_gens, a1, 4, 6, 1, 40, 9)?;
+
+    let mut transcript = Transcript::new(b"R1CSExampleGadget");
+    let mut verifier = Verifier::new(&mut transcript);
+    let vars: Vec<_> = commitments.iter().map(|V| verifier.commit(*V)).collect();
+    example_gadget(
+        &mut verifier,
+        vars[0].into(),
+        vars[1].into(),
+        vars[2].into(),
+        vars[3].into(),
+        vars[4].into(),
+        Fr::from(9u64).into(),
+    );
+    verifier.verify_with_rng(&proof, &pc_gens, &bp_gens, &mut thread_rng())
+}
+
+#[test]
+fn verify_with_rng_test() {
+    // (3 + 4) * (6 + 1) = (40 + 9)
+    assert!(verify_with_rng_helper(3).is_ok());
+    // (5 + 4) * (6 + 1) != (40 + 9)
+    assert!(verify_with_rng_helper(5).is_err());
+}
+
+/// A blinding source standing for a secure element, which derives every
+/// blinding factor from a key it holds, the index of the commitment and
+/// the round of the protocol.
+struct KeyedBlindingSource {
+    key: u64,
+    counter: u64,
+}
+
+impl KeyedBlindingSource {
+    fn derive(&mut self, label: &[u8], index: u64) -> Fr {
+        use rand_chacha::{rand_core::SeedableRng, ChaChaRng};
+
+        let mut seed = [0u8; 32];
+        seed[..8].copy_from_slice(&self.key.to_le_bytes());
+        seed[8..16].copy_from_slice(&index.to_le_bytes());
+        seed[16..24].copy_from_slice(&self.counter.to_le_bytes());
+        for (s, l) in seed[24..].iter_mut().zip(label) {
+            *s = *l;
+        }
+        self.counter += 1;
+        Fr::rand(&mut ChaChaRng::from_seed(seed))
+    }
+}
+
+impl BlindingSource<Fr> for KeyedBlindingSource {
+    fn commitment_blinding(&mut self, index: usize) -> Fr {
+        self.derive(b"commit", index as u64)
+    }
+
+    fn proof_blinding(&mut self, label: &'static [u8]) -> Fr {
+        self.derive(label, 0)
+    }
+}
+
+fn blinding_source_helper(a1: u64) -> Result<(), R1CSError> {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(128, 1);
+    let mut source = KeyedBlindingSource { key: 7, counter: 0 };
+
+    let mut transcript = Transcript::new(b"R1CSExampleGadget");
+    let mut prover = Prover::new(&pc_gens, &mut transcript);
+    let (commitments, vars): (Vec<_>, Vec<_>) = [a1, 4, 6, 1, 40]
+        .iter()
+        .map(|x| prover.commit_with_blinding_source(Fr::from(*x), &mut source))
+        .unzip();
+    example_gadget(
+        &mut prover,
+        vars[0].into(),
+        vars[1].into(),
+        vars[2].into(),
+        vars[3].into(),
+        vars[4].into(),
+        Fr::from(9u64).into(),
+    );
+    let proof = prover.prove_with_blinding_source(&mut source, &bp_gens)?;
+
+    example_gadget_verify(&pc_gens, &bp_gens, 9, proof, commitments)
+}
+
+#[test]
+fn blinding_source_test() {
+    // (3 + 4) * (6 + 1) = (40 + 9)
+    assert!(blinding_source_helper(3).is_ok());
+    // (5 + 4) * (6 + 1) != (40 + 9)
+    assert!(blinding_source_helper(5).is_err());
+}
+
+#[test]
+fn prover_rounds_test() {
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(8, 1);
+    let mut rng = rand::thread_rng();
+
+    let mut transcript = Transcript::new(b"NestedPhasesTest");
+    let mut prover = Prover::new(&pc_gens, &mut transcript);
+    let (commitment, var) = prover.commit(Fr::from(3u64), Fr::rand(&mut rng));
+    nested_phases_gadget(&mut prover, var, Some(Fr::from(3u64)), Fr::from(9u64), true).unwrap();
+
+    // The commitments of every round can be sent away before the next one.
+    let mut rounds = prover.commit_first_round(&mut rng, &bp_gens).unwrap();
+    let mut sent = vec![];
+    loop {
+        let mut bytes = Vec::new();
+        rounds
+            .commitments()
+            .serialize_compressed(&mut bytes)
+            .unwrap();
+        sent.push(RoundCommitments::<Affine>::deserialize_compressed(&bytes[..]).unwrap());
+        if !rounds.has_next_round() {
+            break;
+        }
+        rounds = rounds.next_round(&mut rng, &bp_gens).unwrap();
+    }
+    assert_eq!(sent.len(), 3);
+    assert_eq!(sent[2], rounds.commitments());
+
+    let (proof, _) = rounds.finalize(&mut rng, &bp_gens).unwrap();
+    assert!(nested_phases_verify(&proof, commitment, 9, true).is_ok());
+    assert!(nested_phases_verify(&proof, commitment, 10, true).is_err());
+
+    // Finalizing commits to the rounds that are left.
+    let mut transcript = Transcript::new(b"NestedPhasesTest");
+    let mut prover = Prover::new(&pc_gens, &mut transcript);
+    let (commitment, var) = prover.commit(Fr::from(3u64), Fr::rand(&mut rng));
+    nested_phases_gadget(&mut prover, var, Some(Fr::from(3u64)), Fr::from(9u64), true).unwrap();
+    let rounds = prover.commit_first_round(&mut rng, &bp_gens).unwrap();
+    assert!(rounds.vector_commitments().is_none());
+    let (proof, _) = rounds.finalize(&mut rng, &bp_gens).unwrap();
+    assert!(nested_phases_verify(&proof, commitment, 9, true).is_ok());
+}
+
+/// Splits `value` into `k` random additive shares.
+fn additive_shares<R: RngCore + CryptoRng>(value: Fr, k: usize, rng: &mut R) -> Vec<Fr> {
+    let mut shares: Vec<Fr> = (1..k).map(|_| Fr::rand(rng)).collect();
+    let sum: Fr = shares.iter().sum();
+    shares.insert(0, value - sum);
+    shares
+}
+
+/// Proves the example gadget for `(3 + 4) * (6 + 1) = 40 + c2` with the
+/// witness shared among `k` parties, where the party `cheater`, if any,
+/// answers a different challenge than the dealer's.
+fn mpc_example_gadget_helper(
+    k: usize,
+    c2: u64,
+    cheater: Option<usize>,
+) -> Result<(R1CSProof<Affine>, Vec<Affine>), ark_bulletproofs::r1cs::mpc::MPCError> {
+    use ark_bulletproofs::r1cs::mpc::{Dealer, Party, PolyChallenge, WitnessShare};
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(8, 1);
+    let mut rng = thread_rng();
+
+    let values = [3u64, 4, 6, 1, 40];
+    let v_blinding: Vec<Fr> = values.iter().map(|_| Fr::rand(&mut rng)).collect();
+    let commitments: Vec<Affine> = values
+        .iter()
+        .zip(v_blinding.iter())
+        .map(|(v, b)| pc_gens.commit(Fr::from(*v), *b))
+        .collect();
+
+    // Everyone synthesizes the public circuit.
+    let mut transcript = Transcript::new(b"R1CSExampleGadget");
+    let mut verifier = Verifier::new(&mut transcript);
+    let vars: Vec<_> = commitments.iter().map(|V| verifier.commit(*V)).collect();
+    example_gadget(
+        &mut verifier,
+        vars[0].into(),
+        vars[1].into(),
+        vars[2].into(),
+        vars[3].into(),
+        vars[4].into(),
+        Fr::from(c2).into(),
+    );
+    let circuit = verifier.prepare().unwrap();
+
+    // The witness of the single multiplier, shared among the parties.
+    let a_L = additive_shares(Fr::from(7u64), k, &mut rng);
+    let a_R = additive_shares(Fr::from(7u64), k, &mut rng);
+    let a_O = additive_shares(Fr::from(49u64), k, &mut rng);
+    let v_blinding_shares: Vec<Vec<Fr>> = v_blinding
+        .iter()
+        .map(|b| additive_shares(*b, k, &mut rng))
+        .collect();
+
+    let mut transcript = Transcript::new(b"R1CSExampleGadget");
+    let dealer = Dealer::new(
+        &pc_gens,
+        &bp_gens,
+        &mut transcript,
+        &circuit,
+        &commitments,
+        k,
+    )?;
+
+    let (parties, witness_commitments): (Vec<_>, Vec<_>) = (0..k)
+        .map(|j| {
+            let share = WitnessShare {
+                a_L: vec![a_L[j]],
+                a_R: vec![a_R[j]],
+                a_O: vec![a_O[j]],
+                v_blinding: v_blinding_shares.iter().map(|b| b[j]).collect(),
+            };
+            Party::new(j, &pc_gens, &bp_gens, &circuit, share, &mut rng).unwrap()
+        })
+        .unzip();
+
+    let (dealer, witness_challenge) = dealer.receive_witness_commitments(witness_commitments)?;
+
+    let parties: Vec<_> = parties
+        .into_iter()
+        .map(|p| p.apply_challenge(&witness_challenge))
+        .collect::<Result<_, _>>()?;
+
+    // A trusted stand-in for the parties' MPC framework: reconstruct
+    // l(x) and r(x), and share the coefficients of their inner product.
+    let mut l_poly = vec![vec![Fr::from(0u64); 1]; 4];
+    let mut r_poly = vec![vec![Fr::from(0u64); 1]; 4];
+    for party in &parties {
+        for (d, (l, r)) in party.l_poly().iter().zip(party.r_poly().iter()).enumerate() {
+            l_poly[d][0] += l[0];
+            r_poly[d][0] += r[0];
+        }
+    }
+    let t_shares: Vec<Vec<Fr>> = (1..=6)
+        .map(|e| {
+            let t_e: Fr = (0..4)
+                .flat_map(|i| (0..4).map(move |j| (i, j)))
+                .filter(|(i, j)| i + j == e)
+                .map(|(i, j)| l_poly[i][0] * r_poly[j][0])
+                .sum();
+            additive_shares(t_e, k, &mut rng)
+        })
+        .collect();
+
+    let (parties, poly_commitments): (Vec<_>, Vec<_>) = parties
+        .into_iter()
+        .enumerate()
+        .map(|(j, p)| {
+            let t = [
+                t_shares[0][j],
+                t_shares[1][j],
+                t_shares[2][j],
+                t_shares[3][j],
+                t_shares[4][j],
+                t_shares[5][j],
+            ];
+            p.commit_poly(t, &mut rng)
+        })
+        .unzip();
+
+    let (dealer, poly_challenge) = dealer.receive_poly_commitments(poly_commitments)?;
+
+    let mut bytes = Vec::new();
+    Fr::rand(&mut rng).serialize_compressed(&mut bytes).unwrap();
+    let bad_challenge = PolyChallenge::deserialize_compressed(&bytes[..]).unwrap();
+
+    let proof_shares: Vec<_> = parties
+        .into_iter()
+        .enumerate()
+        .map(|(j, p)| {
+            if cheater == Some(j) {
+                p.apply_challenge(&bad_challenge)
+            } else {
+                p.apply_challenge(&poly_challenge)
+            }
+        })
+        .collect::<Result<_, _>>()?;
+
+    let proof = dealer.receive_shares(&proof_shares)?;
+    Ok((proof, commitments))
+}
+
+#[test]
+fn mpc_example_gadget_test() {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(8, 1);
+
+    for k in 1..=3 {
+        let (proof, commitments) = mpc_example_gadget_helper(k, 9, None).unwrap();
+        assert!(example_gadget_verify(&pc_gens, &bp_gens, 9, proof, commitments).is_ok());
+    }
+
+    // An unsatisfied circuit gives an invalid proof, with no party at fault.
+    assert_eq!(
+        mpc_example_gadget_helper(3, 10, None).unwrap_err(),
+        ark_bulletproofs::r1cs::mpc::MPCError::MalformedProofShares { bad_shares: vec![] }
+    );
+
+    // The dealer identifies the parties whose shares do not match their
+    // commitments.
+    assert_eq!(
+        mpc_example_gadget_helper(3, 9, Some(1)).unwrap_err(),
+        ark_bulletproofs::r1cs::mpc::MPCError::MalformedProofShares {
+            bad_shares: vec![1]
+        }
+    );
+}
+
+/// Constrains `c = a^3`.
+fn cube_gadget<CS: ConstraintSystem<Fr>>(cs: &mut CS, a: Variable<Fr>, c: Variable<Fr>) {
+    let (_, _, square) = cs.multiply(a.into(), a.into());
+    let (_, _, cube) = cs.multiply(square.into(), a.into());
+    cs.constrain(cube - c);
+}
+
+/// Aggregates the proofs of the example gadget and of `cube^(1/3) = 3`
+/// by two parties into a single proof.
+fn mpc_aggregation_helper(cube: u64) -> Result<(), ark_bulletproofs::r1cs::mpc::MPCError> {
+    use ark_bulletproofs::r1cs::mpc::{Dealer, Party, WitnessShare};
+
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(2, 2);
+    let mut rng = thread_rng();
+
+    let values = [vec![3u64, 4, 6, 1, 40], vec![3u64, cube]];
+    let v_blinding: Vec<Vec<Fr>> = values
+        .iter()
+        .map(|v| v.iter().map(|_| Fr::rand(&mut rng)).collect())
+        .collect();
+    let commitments: Vec<Vec<Affine>> = values
+        .iter()
+        .zip(v_blinding.iter())
+        .map(|(v, b)| {
+            v.iter()
+                .zip(b.iter())
+                .map(|(v, b)| pc_gens.commit(Fr::from(*v), *b))
+                .collect()
+        })
+        .collect();
+
+    let mut transcript = Transcript::new(b"R1CSAggregationTest");
+    let mut verifier = Verifier::new(&mut transcript);
+    let vars: Vec<_> = commitments[0].iter().map(|V| verifier.commit(*V)).collect();
+    example_gadget(
+        &mut verifier,
+        vars[0].into(),
+        vars[1].into(),
+        vars[2].into(),
+        vars[3].into(),
+        vars[4].into(),
+        Fr::from(9u64).into(),
+    );
+    let example = verifier.prepare().unwrap();
+
+    let mut transcript = Transcript::new(b"R1CSAggregationTest");
+    let mut verifier = Verifier::new(&mut transcript);
+    let vars: Vec<_> = commitments[1].iter().map(|V| verifier.commit(*V)).collect();
+    cube_gadget(&mut verifier, vars[0], vars[1]);
+    let cube_circuit = verifier.prepare().unwrap();
+
+    let circuit = PreparedCircuit::aggregate(&[example, cube_circuit]).unwrap();
+    assert_eq!(circuit.parties(), 2);
+    assert_eq!(circuit.multipliers_len(), 4);
+    let all_commitments: Vec<Affine> = commitments.concat();
+
+    let witnesses = [
+        (vec![7u64], vec![7u64], vec![49u64]),
+        (vec![3, 9], vec![3, 3], vec![9, 27]),
+    ];
+    let mut transcript = Transcript::new(b"R1CSAggregationTest");
+    let dealer = Dealer::new_aggregated(
+        &pc_gens,
+        &bp_gens,
+        &mut transcript,
+        &circuit,
+        &all_commitments,
+    )?;
+
+    let (parties, witness_commitments): (Vec<_>, Vec<_>) = witnesses
+        .iter()
+        .zip(v_blinding.iter())
+        .enumerate()
+        .map(|(j, ((a_L, a_R, a_O), v_blinding))| {
+            let to_fr = |v: &Vec<u64>| v.iter().map(|x| Fr::from(*x)).collect();
+            let witness = WitnessShare {
+                a_L: to_fr(a_L),
+                a_R: to_fr(a_R),
+                a_O: to_fr(a_O),
+                v_blinding: v_blinding.clone(),
+            };
+            Party::new_aggregated(j, &pc_gens, &bp_gens, &circuit, witness, &mut rng).unwrap()
+        })
+        .unzip();
+
+    let (dealer, witness_challenge) = dealer.receive_witness_commitments(witness_commitments)?;
+    let (parties, poly_commitments): (Vec<_>, Vec<_>) = parties
+        .into_iter()
+        .map(|p| {
+            p.apply_challenge(&witness_challenge)
+                .unwrap()
+                .commit_aggregated_poly(&mut rng)
+        })
+        .unzip();
+    let (dealer, poly_challenge) = dealer.receive_poly_commitments(poly_commitments)?;
+    let proof_shares: Vec<_> = parties
+        .into_iter()
+        .map(|p| p.apply_challenge(&poly_challenge))
+        .collect::<Result<_, _>>()?;
+    let proof = dealer.receive_shares(&proof_shares)?;
+
+    let mut transcript = Transcript::new(b"R1CSAggregationTest");
+    assert!(circuit
+        .verify(
+            &mut transcript,
+            &all_commitments,
+            &proof,
+            &pc_gens,
+            &bp_gens
+        )
+        .is_ok());
+    Ok(())
+}
+
+#[test]
+fn mpc_aggregation_test() {
+    assert!(mpc_aggregation_helper(27).is_ok());
+    assert_eq!(
+        mpc_aggregation_helper(28).unwrap_err(),
+        ark_bulletproofs::r1cs::mpc::MPCError::MalformedProofShares { bad_shares: vec![] }
+    );
+
+    // Only a power of two of circuits can be aggregated.
+    let mut transcript = Transcript::new(b"R1CSAggregationTest");
+    let verifier = Verifier::<Affine, _>::new(&mut transcript);
+    let circuit = verifier.prepare().unwrap();
+    assert!(PreparedCircuit::aggregate(&[circuit.clone(), circuit.clone(), circuit]).is_err());
+}
+
+fn aggregated_range_proof_helper(values: &[u64], n: usize) -> Result<(), R1CSError> {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(n, values.len());
+    let mut rng = rand::thread_rng();
+
+    let mut commitments = vec![];
+    let provers: Vec<_> = values
+        .iter()
+        .map(|v| {
+            let mut prover = Prover::new(&pc_gens, Transcript::new(b"RangeProofInstance"));
+            let (com, var) = prover.commit(Fr::from(*v), Fr::rand(&mut rng));
+            range_proof(&mut prover, var.into(), Some(*v), n).unwrap();
+            commitments.push(com);
+            prover
+        })
+        .collect();
+    let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+    let proof = Prover::prove_aggregated(provers, &mut transcript, &mut rng, &bp_gens)?;
+
+    // The proof only grows logarithmically with the number of proofs.
+    let mut prover = Prover::new(&pc_gens, Transcript::new(b"RangeProofInstance"));
+    let (_, var) = prover.commit(Fr::from(values[0]), Fr::rand(&mut rng));
+    range_proof(&mut prover, var.into(), Some(values[0]), n)?;
+    let single = prover.prove(&mut rng, &bp_gens)?;
+    assert!(proof.to_bytes().unwrap().len() < 2 * single.to_bytes().unwrap().len());
+
+    let mut transcript = Transcript::new(b"RangeProofInstance");
+    let mut verifier = Verifier::<Affine, _>::new(&mut transcript);
+    let var = verifier.commit(commitments[0]);
+    range_proof(&mut verifier, var.into(), None, n)?;
+    let circuit = verifier.prepare()?;
+    let circuit = PreparedCircuit::aggregate(&vec![circuit; values.len()])?;
+
+    let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+    circuit.verify(&mut transcript, &commitments, &proof, &pc_gens, &bp_gens)
+}
+
+#[test]
+fn aggregated_range_proof_test() {
+    assert!(aggregated_range_proof_helper(&[0, 7, 255, 100], 8).is_ok());
+    assert!(aggregated_range_proof_helper(&[0, 7, 256, 100], 8).is_err());
+    assert!(aggregated_range_proof_helper(&[3, 4], 16).is_ok());
+    // Only a power of two of proofs can be aggregated.
+    assert!(aggregated_range_proof_helper(&[1, 2, 3], 8).is_err());
+}
+
+#[test]
+fn aggregated_range_proof_keccak_test() {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::new(8, 2);
+    let mut rng = rand::thread_rng();
+
+    // The aggregation transcript need not be a Merlin transcript.
+    let mut commitments = vec![];
+    let provers: Vec<_> = [3u64, 200]
+        .iter()
+        .map(|v| {
+            let mut prover = Prover::new(&pc_gens, KeccakTranscript::new(b"RangeProofInstance"));
+            let (com, var) = prover.commit(Fr::from(*v), Fr::rand(&mut rng));
+            range_proof(&mut prover, var.into(), Some(*v), 8).unwrap();
+            commitments.push(com);
+            prover
+        })
+        .collect();
+    let mut transcript = KeccakTranscript::new(b"AggregatedRangeProofTest");
+    let proof = Prover::prove_aggregated(provers, &mut transcript, &mut rng, &bp_gens).unwrap();
+
+    let mut verifier = Verifier::<Affine, _>::new(KeccakTranscript::new(b"RangeProofInstance"));
+    let var = verifier.commit(commitments[0]);
+    range_proof(&mut verifier, var.into(), None, 8).unwrap();
+    let circuit = verifier.prepare().unwrap();
+    let circuit = PreparedCircuit::aggregate(&vec![circuit; 2]).unwrap();
+
+    let mut transcript = KeccakTranscript::new(b"AggregatedRangeProofTest");
+    assert!(circuit
+        .verify(&mut transcript, &commitments, &proof, &pc_gens, &bp_gens)
+        .is_ok());
+    let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+    assert!(circuit
+        .verify(&mut transcript, &commitments, &proof, &pc_gens, &bp_gens)
+        .is_err());
+}
+
+/// Returns the `.r1cs` and `.wtns` files of the example gadget, with its
+/// output split between a high-level variable and a public input.
+fn example_circom_files(
+    a1: u64,
+    a2: u64,
+    b1: u64,
+    b2: u64,
+    c1: u64,
+    c2: u64,
+) -> (Vec<u8>, Vec<u8>) {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let mut rng = thread_rng();
+
+    let mut prover = Prover::new(&pc_gens, Transcript::new(b"R1CSImportTest"));
+    let vars: Vec<_> = [a1, a2, b1, b2, c1]
+        .iter()
+        .map(|x| prover.commit(Fr::from(*x), Fr::rand(&mut rng)).1)
+        .collect();
+    let p = prover.public_input(Fr::from(c2));
+    example_gadget(
+        &mut prover,
+        vars[0].into(),
+        vars[1].into(),
+        vars[2].into(),
+        vars[3].into(),
+        vars[4].into(),
+        p.into(),
+    );
+    (
+        prover.export_r1cs().unwrap(),
+        prover.export_witness().unwrap(),
+    )
+}
+
+fn circom_roundtrip(r1cs: &[u8], wtns: &[u8], public: &[Fr]) -> Result<(), R1CSError> {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::<Affine>::new(128, 1);
+    let circuit = CircomCircuit::<Fr>::from_r1cs(r1cs)?;
+    let witness = circuit.read_witness(wtns)?;
+
+    let mut prover = Prover::new(&pc_gens, Transcript::new(b"R1CSImportTest"));
+    let vars: Vec<_> = circuit
+        .public_values(&witness)
+        .iter()
+        .map(|value| prover.public_input(*value))
+        .collect();
+    circuit.synthesize(&mut prover, &vars, Some(&witness))?;
+    let proof = prover.prove(&mut thread_rng(), &bp_gens)?;
+
+    let mut verifier = Verifier::new(Transcript::new(b"R1CSImportTest"));
+    let vars: Vec<_> = public
+        .iter()
+        .map(|value| verifier.public_input(*value))
+        .collect();
+    circuit.synthesize(&mut verifier, &vars, None)?;
+    verifier.verify(&proof, &pc_gens, &bp_gens)
+}
+
+#[test]
+fn r1cs_import_test() {
+    let (r1cs, wtns) = example_circom_files(3, 4, 6, 1, 40, 9);
+    let circuit = CircomCircuit::<Fr>::from_r1cs(&r1cs).unwrap();
+    assert_eq!(circuit.wires(), 10);
+    assert_eq!(circuit.public_len(), 1);
+    assert_eq!(circuit.private_inputs_len(), 5);
+    assert_eq!(circuit.constraints_len(), 4);
+    let witness = circuit.read_witness(&wtns).unwrap();
+    assert_eq!(circuit.public_values(&witness), &[Fr::from(9u64)]);
+
+    assert!(circom_roundtrip(&r1cs, &wtns, &[Fr::from(9u64)]).is_ok());
+    // The verifier's public input differs from the prover's.
+    assert!(circom_roundtrip(&r1cs, &wtns, &[Fr::from(10u64)]).is_err());
+
+    // A witness which does not satisfy the circuit.
+    let (_, wtns) = example_circom_files(3, 4, 6, 1, 41, 9);
+    assert!(circom_roundtrip(&r1cs, &wtns, &[Fr::from(9u64)]).is_err());
+
+    // Truncated files, and files of the other format.
+    assert!(CircomCircuit::<Fr>::from_r1cs(&r1cs[..r1cs.len() - 1]).is_err());
+    assert!(circuit.read_witness(&wtns[..wtns.len() - 1]).is_err());
+    assert!(CircomCircuit::<Fr>::from_r1cs(&wtns).is_err());
+    // A file over another field.
+    assert!(CircomCircuit::<ark_secq256k1::Fq>::from_r1cs(&r1cs).is_err());
+}
+
+/// Proves that `x * y = z` and `x + y = 7`, with the public input `z`.
+#[cfg(feature = "ark-relations")]
+struct ArkExample {
+    x: Option<Fr>,
+    y: Option<Fr>,
+    z: Option<Fr>,
+}
+
+#[cfg(feature = "ark-relations")]
+impl ark_relations::r1cs::ConstraintSynthesizer<Fr> for ArkExample {
+    fn generate_constraints(
+        self,
+        cs: ark_relations::r1cs::ConstraintSystemRef<Fr>,
+    ) -> ark_relations::r1cs::Result<()> {
+        use ark_relations::lc;
+        use ark_relations::r1cs::SynthesisError::AssignmentMissing;
+
+        let z = cs.new_input_variable(|| self.z.ok_or(AssignmentMissing))?;
+        let x = cs.new_witness_variable(|| self.x.ok_or(AssignmentMissing))?;
+        let y = cs.new_witness_variable(|| self.y.ok_or(AssignmentMissing))?;
+        let one = ark_relations::r1cs::Variable::One;
+        cs.enforce_constraint(lc!() + x, lc!() + y, lc!() + z)?;
+        cs.enforce_constraint(lc!() + x + y, lc!() + one, lc!() + (Fr::from(7u64), one))
+    }
+}
+
+#[cfg(feature = "ark-relations")]
+fn ark_example_roundtrip(x: u64, y: u64, z: u64, public: u64) -> Result<(), R1CSError> {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::<Affine>::new(128, 1);
+
+    let circuit = ArkCircuit::assign(ArkExample {
+        x: Some(Fr::from(x)),
+        y: Some(Fr::from(y)),
+        z: Some(Fr::from(z)),
+    })?;
+    let mut prover = Prover::new(&pc_gens, Transcript::new(b"ArkRelationsTest"));
+    let vars: Vec<_> = circuit
+        .public_values()
+        .unwrap()
+        .iter()
+        .map(|value| prover.public_input(*value))
+        .collect();
+    circuit.synthesize(&mut prover, &vars)?;
+    let proof = prover.prove(&mut thread_rng(), &bp_gens)?;
+
+    let circuit = ArkCircuit::setup(ArkExample {
+        x: None,
+        y: None,
+        z: None,
+    })?;
+    assert_eq!(circuit.public_len(), 1);
+    assert_eq!(circuit.constraints_len(), 2);
+    assert!(circuit.public_values().is_none());
+    let mut verifier = Verifier::new(Transcript::new(b"ArkRelationsTest"));
+    let vars = vec![verifier.public_input(Fr::from(public))];
+    circuit.synthesize(&mut verifier, &vars)?;
+    verifier.verify(&proof, &pc_gens, &bp_gens)
+}
+
+#[cfg(feature = "ark-relations")]
+#[test]
+fn ark_relations_test() {
+    assert!(ark_example_roundtrip(3, 4, 12, 12).is_ok());
+    assert!(ark_example_roundtrip(3, 4, 12, 13).is_err());
+    assert!(ark_example_roundtrip(2, 6, 12, 12).is_err());
+}
+
+/// Checks that `a * b = c` and that `c` fits in 8 bits.
+fn oneshot_circuit(
+    mut cs: &mut dyn ConstraintSystem<Fr>,
+    committed: &[AllocatedScalar<Fr>],
+) -> Result<(), R1CSError> {
+    let (a, b, c) = (committed[0], committed[1], committed[2]);
+    let (_, _, o) = cs.multiply(a.into(), b.into());
+    cs.constrain(o - c.variable);
+    BitDecomposition::new(8).synthesize(&mut cs, c)?;
+    Ok(())
+}
+
+#[test]
+fn oneshot_prove_verify_test() {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::<Affine>::new(16, 1);
+    let mut rng = thread_rng();
+    let values = [3u64, 5, 15].map(Fr::from);
+
+    let (proof, commitments) = ark_bulletproofs::r1cs::prove(
+        &pc_gens,
+        &bp_gens,
+        Transcript::new(b"OneShotTest"),
+        &values,
+        &mut rng,
+        oneshot_circuit,
+    )
+    .unwrap();
+    assert!(ark_bulletproofs::r1cs::verify(
+        &pc_gens,
+        &bp_gens,
+        Transcript::new(b"OneShotTest"),
+        &proof,
+        &commitments,
+        oneshot_circuit,
+    )
+    .is_ok());
+
+    // A verifier building other constraints, here with a wider range,
+    // does not accept the proof.
+    let wider = |mut cs: &mut dyn ConstraintSystem<Fr>, committed: &[AllocatedScalar<Fr>]| {
+        let (a, b, c) = (committed[0], committed[1], committed[2]);
+        let (_, _, o) = cs.multiply(a.into(), b.into());
+        cs.constrain(o - c.variable);
+        BitDecomposition::new(16).synthesize(&mut cs, c)?;
+        Ok(())
+    };
+    assert!(ark_bulletproofs::r1cs::verify(
+        &pc_gens,
+        &bp_gens,
+        Transcript::new(b"OneShotTest"),
+        &proof,
+        &commitments,
+        wider,
+    )
+    .is_err());
+
+    // Wrong values do not satisfy the circuit.
+    let values = [3u64, 5, 16].map(Fr::from);
+    let (proof, commitments) = ark_bulletproofs::r1cs::prove(
+        &pc_gens,
+        &bp_gens,
+        Transcript::new(b"OneShotTest"),
+        &values,
+        &mut rng,
+        oneshot_circuit,
+    )
+    .unwrap();
+    assert!(ark_bulletproofs::r1cs::verify(
+        &pc_gens,
+        &bp_gens,
+        Transcript::new(b"OneShotTest"),
+        &proof,
+        &commitments,
+        oneshot_circuit,
+    )
+    .is_err());
+}
+
+#[test]
+fn r1cs_proof_accessors_test() {
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::<Affine>::new(16, 1);
+    let values = [3u64, 5, 15].map(Fr::from);
+    let (proof, _) = ark_bulletproofs::r1cs::prove(
+        &pc_gens,
+        &bp_gens,
+        Transcript::new(b"ProofAccessorsTest"),
+        &values,
+        &mut thread_rng(),
+        oneshot_circuit,
+    )
+    .unwrap();
+
+    // One multiplier for the product and eight for the bits.
+    assert_eq!(proof.ipp_depth(), 4);
+    assert_eq!(proof.padded_multipliers(), 16);
+    assert_eq!(proof.phases(), 2);
+    assert!(!proof.has_vector_commitments());
+
+    let bytes = proof.to_bytes().unwrap();
+    assert_eq!(proof.serialized_size(), bytes.len());
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    assert_eq!(proof.to_string(), hex);
+    assert_eq!(format!("{:?}", proof), format!("R1CSProof({})", hex));
+}
+
+#[test]
+fn r1cs_proof_ct_eq_test() {
+    use subtle::ConstantTimeEq;
+
+    let pc_gens = PedersenGens::<Affine>::default();
+    let bp_gens = BulletproofGens::<Affine>::new(16, 1);
+    let values = [3u64, 5, 15].map(Fr::from);
+    let prove = |rng: &mut _| {
+        ark_bulletproofs::r1cs::prove(
+            &pc_gens,
+            &bp_gens,
+            Transcript::new(b"ProofEqualityTest"),
+            &values,
+            rng,
+            oneshot_circuit,
+        )
+        .unwrap()
+        .0
+    };
+    let mut rng = thread_rng();
+    let (proof, other) = (prove(&mut rng), prove(&mut rng));
+
+    let decoded = R1CSProof::<Affine>::from_bytes(&proof.to_bytes().unwrap()).unwrap();
+    assert_eq!(proof, decoded);
+    assert!(bool::from(proof.ct_eq(&decoded)));
+    // Proofs of the same statement are blinded differently.
+    assert_ne!(proof, other);
+    assert!(!bool::from(proof.ct_eq(&other)));
+}